@@ -0,0 +1,74 @@
+use std::io::{Read, Write};
+
+use acvm::acir::circuit::Circuit;
+use noirc_abi::Abi;
+use noirc_driver::CompiledProgram;
+use noirc_errors::debug_info::DebugInfo;
+use serde::{Deserialize, Serialize};
+
+use crate::errors::ArtifactError;
+
+/// The version of the circuit artifact format written by this version of Nargo.
+///
+/// This must be increased whenever the serialized form of a `CircuitArtifact` changes, and
+/// `CircuitArtifact::read` extended to convert artifacts of the previous version.
+///
+/// Version 0 refers to the artifacts written before the format was versioned, which hold the same
+/// fields without a version number.
+pub const CIRCUIT_ARTIFACT_VERSION: u64 = 1;
+
+/// `CircuitArtifact` holds the circuit of a Noir program as it is produced by the compiler, along
+/// with the information needed to execute it and report its errors.
+///
+/// Unlike a `PreprocessedProgram`, it does not depend on any particular backend, so it may be
+/// cached and read by other tools across releases of the compiler.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CircuitArtifact {
+    #[serde(
+        serialize_with = "super::serialize_circuit",
+        deserialize_with = "super::deserialize_circuit"
+    )]
+    pub circuit: Circuit,
+    pub debug: DebugInfo,
+    pub abi: Abi,
+}
+
+/// The serialized form of a `CircuitArtifact`, prefixed by the version of its format
+#[derive(Serialize)]
+struct VersionedCircuitArtifact<'a> {
+    version: u64,
+    #[serde(flatten)]
+    artifact: &'a CircuitArtifact,
+}
+
+impl CircuitArtifact {
+    /// Writes the artifact as JSON, along with the current version of the format.
+    pub fn write<W: Write>(&self, writer: W) -> Result<(), ArtifactError> {
+        let versioned =
+            VersionedCircuitArtifact { version: CIRCUIT_ARTIFACT_VERSION, artifact: self };
+        serde_json::to_writer(writer, &versioned)?;
+        Ok(())
+    }
+
+    /// Reads an artifact written by this or any earlier version of Nargo.
+    ///
+    /// Artifacts written by a later version of Nargo are rejected, as their format is unknown.
+    pub fn read<R: Read>(reader: R) -> Result<Self, ArtifactError> {
+        let value: serde_json::Value = serde_json::from_reader(reader)?;
+        let version = match value.get("version") {
+            None => 0,
+            Some(version) => version.as_u64().ok_or(ArtifactError::InvalidVersion)?,
+        };
+        match version {
+            // Versions 0 and 1 only differ by the version number, which is ignored here.
+            0 | 1 => Ok(serde_json::from_value(value)?),
+            found => Err(ArtifactError::UnsupportedVersion { found }),
+        }
+    }
+}
+
+impl From<CompiledProgram> for CircuitArtifact {
+    fn from(program: CompiledProgram) -> Self {
+        CircuitArtifact { circuit: program.circuit, debug: program.debug, abi: program.abi }
+    }
+}
@@ -1,6 +1,6 @@
 use acvm::acir::circuit::Circuit;
 use noirc_abi::Abi;
-use noirc_driver::ContractFunctionType;
+use noirc_driver::{ContractFunctionType, OracleSignature};
 use serde::{Deserialize, Serialize};
 
 /// `PreprocessedContract` represents a Noir contract which has been preprocessed by a particular backend proving system.
@@ -38,6 +38,9 @@ pub struct PreprocessedContractFunction {
     )]
     pub bytecode: Circuit,
 
+    #[serde(default)]
+    pub oracles: Vec<OracleSignature>,
+
     pub proving_key: Option<Vec<u8>>,
     pub verification_key: Option<Vec<u8>>,
 }
@@ -7,6 +7,7 @@ use acvm::acir::circuit::Circuit;
 use base64::Engine;
 use serde::{Deserializer, Serializer};
 
+pub mod circuit;
 pub mod contract;
 pub mod program;
 
@@ -26,7 +27,8 @@ where
     D: Deserializer<'de>,
 {
     let bytecode_b64: String = serde::Deserialize::deserialize(deserializer)?;
-    let circuit_bytes = base64::engine::general_purpose::STANDARD.decode(bytecode_b64).unwrap();
-    let circuit = Circuit::read(&*circuit_bytes).unwrap();
-    Ok(circuit)
+    let circuit_bytes = base64::engine::general_purpose::STANDARD
+        .decode(bytecode_b64)
+        .map_err(serde::de::Error::custom)?;
+    Circuit::read(&*circuit_bytes).map_err(serde::de::Error::custom)
 }
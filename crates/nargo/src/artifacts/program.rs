@@ -1,5 +1,6 @@
 use acvm::acir::circuit::Circuit;
 use noirc_abi::Abi;
+use noirc_driver::OracleSignature;
 use serde::{Deserialize, Serialize};
 
 /// `PreprocessedProgram` represents a Noir program which has been preprocessed by a particular backend proving system.
@@ -18,6 +19,10 @@ pub struct PreprocessedProgram {
     )]
     pub bytecode: Circuit,
 
+    /// The oracles called by the program, which executors must provide
+    #[serde(default)]
+    pub oracles: Vec<OracleSignature>,
+
     pub proving_key: Option<Vec<u8>>,
     pub verification_key: Option<Vec<u8>>,
 }
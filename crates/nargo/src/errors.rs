@@ -16,11 +16,29 @@ pub enum NargoError {
     ForeignCallError(#[from] ForeignCallError),
 }
 
+/// Errors reading or writing a circuit artifact
+#[derive(Debug, Error)]
+pub enum ArtifactError {
+    #[error("Circuit artifact has version {found}, but this version of Nargo only supports versions up to {}. Please update Nargo to read it.", crate::artifacts::circuit::CIRCUIT_ARTIFACT_VERSION)]
+    UnsupportedVersion { found: u64 },
+
+    #[error("Circuit artifact has a version which is not a non-negative integer")]
+    InvalidVersion,
+
+    #[error(transparent)]
+    JsonError(#[from] serde_json::Error),
+}
+
 #[derive(Debug, Error)]
 pub enum ForeignCallError {
     #[error("Foreign call inputs needed for execution are missing")]
     MissingForeignCallInputs,
 
+    #[error(
+        "The values returned by the `{name}` oracle do not have the shapes the program expects"
+    )]
+    InvalidResult { name: String },
+
     /// ABI encoding/decoding error
     #[error(transparent)]
     AbiError(#[from] AbiError),
@@ -12,4 +12,4 @@ mod errors;
 pub mod manifest;
 pub mod ops;
 
-pub use self::errors::NargoError;
+pub use self::errors::{ArtifactError, NargoError};
@@ -1,8 +1,10 @@
-use acvm::pwg::{ACVMStatus, ACVM};
+use acvm::acir::brillig::ForeignCallResult;
+use acvm::pwg::{ACVMStatus, ForeignCallWaitInfo, ACVM};
 use acvm::BlackBoxFunctionSolver;
 use acvm::{acir::circuit::Circuit, acir::native_types::WitnessMap};
+use noirc_driver::{oracle_signatures, OracleSignature};
 
-use crate::NargoError;
+use crate::{errors::ForeignCallError, NargoError};
 
 use super::foreign_calls::ForeignCall;
 
@@ -10,7 +12,9 @@ pub fn execute_circuit<B: BlackBoxFunctionSolver + Default>(
     _backend: &B,
     circuit: Circuit,
     initial_witness: WitnessMap,
+    show_output: bool,
 ) -> Result<WitnessMap, NargoError> {
+    let oracles = oracle_signatures(&circuit);
     let mut acvm = ACVM::new(B::default(), circuit.opcodes, initial_witness);
 
     loop {
@@ -23,7 +27,8 @@ pub fn execute_circuit<B: BlackBoxFunctionSolver + Default>(
             }
             ACVMStatus::Failure(error) => return Err(error.into()),
             ACVMStatus::RequiresForeignCall(foreign_call) => {
-                let foreign_call_result = ForeignCall::execute(&foreign_call)?;
+                let foreign_call_result = ForeignCall::execute(&foreign_call, show_output)?;
+                check_foreign_call_result(&oracles, &foreign_call, &foreign_call_result)?;
                 acvm.resolve_pending_foreign_call(foreign_call_result);
             }
         }
@@ -32,3 +37,22 @@ pub fn execute_circuit<B: BlackBoxFunctionSolver + Default>(
     let solved_witness = acvm.finalize();
     Ok(solved_witness)
 }
+
+/// Checks that the values returned for a foreign call have the shapes the calling Brillig code
+/// expects, as otherwise they would be written over the wrong registers and memory.
+fn check_foreign_call_result(
+    oracles: &[OracleSignature],
+    foreign_call: &ForeignCallWaitInfo,
+    result: &ForeignCallResult,
+) -> Result<(), ForeignCallError> {
+    let accepted = oracles
+        .iter()
+        .filter(|oracle| oracle.name == foreign_call.function)
+        .filter(|oracle| oracle.accepts_inputs(&foreign_call.inputs))
+        .any(|oracle| oracle.accepts_result(result));
+    if accepted {
+        Ok(())
+    } else {
+        Err(ForeignCallError::InvalidResult { name: foreign_call.function.clone() })
+    }
+}
@@ -30,6 +30,7 @@ pub fn preprocess_program<B: ProofSystemCompiler>(
             backend: String::from(BACKEND_IDENTIFIER),
             abi: compiled_program.abi,
             bytecode: optimized_bytecode,
+            oracles: compiled_program.oracles,
             proving_key,
             verification_key,
         },
@@ -61,6 +62,7 @@ pub fn preprocess_contract_function<B: ProofSystemCompiler>(
         abi: func.abi,
 
         bytecode: optimized_bytecode,
+        oracles: func.oracles,
         proving_key,
         verification_key,
     })
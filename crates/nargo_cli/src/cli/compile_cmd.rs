@@ -21,7 +21,7 @@ use super::fs::{
         read_cached_common_reference_string, update_common_reference_string,
         write_cached_common_reference_string,
     },
-    program::{save_contract_to_file, save_program_to_file},
+    program::{save_contract_to_file, save_program_to_file, save_provenance_report_to_file},
 };
 use super::NargoConfig;
 
@@ -101,8 +101,12 @@ pub(crate) fn run<B: Backend>(
             );
         }
     } else {
-        let (program, _) =
+        let (program, context) =
             compile_circuit(backend, None, &config.program_dir, &args.compile_options)?;
+        if args.compile_options.opcode_provenance {
+            let report = program.provenance_report(&context.file_manager);
+            save_provenance_report_to_file(&report, &args.circuit_name, &circuit_dir);
+        }
         common_reference_string =
             update_common_reference_string(backend, &common_reference_string, &program.circuit)
                 .map_err(CliError::CommonReferenceStringError)?;
@@ -62,7 +62,7 @@ fn execute_with_path<B: Backend>(
     compile_options: &CompileOptions,
 ) -> Result<(Option<InputValue>, WitnessMap), CliError<B>> {
     let (compiled_program, context) = compile_circuit(backend, None, program_dir, compile_options)?;
-    let CompiledProgram { abi, circuit, debug } = compiled_program;
+    let CompiledProgram { abi, circuit, debug, .. } = compiled_program;
 
     // Parse the initial witness values from Prover.toml
     let (inputs_map, _) =
@@ -101,11 +101,15 @@ fn report_unsatisfied_constraint_error(
 ) {
     if let Some(opcode_index) = opcode_idx {
         if let Some(loc) = debug.opcode_location(opcode_index) {
+            let secondary_message = match debug.opcode_assert_message(opcode_index) {
+                Some(message) => format!("Constraint failed: {message}"),
+                None => "Constraint failed".to_string(),
+            };
             noirc_errors::reporter::report(
                 &context.file_manager,
                 &CustomDiagnostic::simple_error(
                     "Unsatisfied constraint".to_string(),
-                    "Constraint failed".to_string(),
+                    secondary_message,
                     loc.span,
                 ),
                 Some(loc.file),
@@ -123,7 +127,7 @@ pub(crate) fn execute_program<B: Backend>(
     debug_data: Option<(DebugInfo, Context)>,
 ) -> Result<WitnessMap, CliError<B>> {
     let initial_witness = abi.encode(inputs_map, None)?;
-    let solved_witness_err = nargo::ops::execute_circuit(backend, circuit, initial_witness);
+    let solved_witness_err = nargo::ops::execute_circuit(backend, circuit, initial_witness, true);
     match solved_witness_err {
         Ok(solved_witness) => Ok(solved_witness),
         Err(err) => {
@@ -1,6 +1,7 @@
 use std::path::{Path, PathBuf};
 
 use nargo::artifacts::{contract::PreprocessedContract, program::PreprocessedProgram};
+use noirc_driver::ProvenanceReport;
 
 use crate::errors::FilesystemError;
 
@@ -20,6 +21,14 @@ pub(crate) fn save_contract_to_file<P: AsRef<Path>>(
 ) -> PathBuf {
     save_build_artifact_to_file(compiled_contract, circuit_name, circuit_dir)
 }
+/// Saves the provenance of each opcode of a program alongside its build artifact.
+pub(crate) fn save_provenance_report_to_file<P: AsRef<Path>>(
+    report: &ProvenanceReport,
+    circuit_name: &str,
+    circuit_dir: P,
+) -> PathBuf {
+    save_build_artifact_to_file(report, &format!("{circuit_name}-provenance"), circuit_dir)
+}
 fn save_build_artifact_to_file<P: AsRef<Path>, T: ?Sized + serde::Serialize>(
     build_artifact: &T,
     circuit_name: &str,
@@ -96,14 +96,14 @@ fn run_test<B: Backend>(
     show_output: bool,
     config: &CompileOptions,
 ) -> Result<(), CliError<B>> {
-    let mut program = compile_no_check(context, show_output, config, main)
+    let (mut program, _) = compile_no_check(context, config, main)
         .map_err(|_| CliError::Generic(format!("Test '{test_name}' failed to compile")))?;
     // Note: We could perform this test using the unoptimized ACIR as generated by `compile_no_check`.
     program.circuit = optimize_circuit(backend, program.circuit).unwrap().0;
 
     // Run the backend to ensure the PWG evaluates functions like std::hash::pedersen,
     // otherwise constraints involving these expressions will not error.
-    match execute_circuit(backend, program.circuit, WitnessMap::new()) {
+    match execute_circuit(backend, program.circuit, WitnessMap::new(), show_output) {
         Ok(_) => Ok(()),
         Err(error) => {
             let writer = StandardStream::stderr(ColorChoice::Always);
@@ -1,4 +1,5 @@
 use crate::program::{deserialize_circuit, serialize_circuit};
+use crate::OracleSignature;
 use acvm::acir::circuit::Circuit;
 use noirc_abi::Abi;
 use serde::{Deserialize, Serialize};
@@ -47,6 +48,9 @@ pub struct ContractFunction {
 
     #[serde(serialize_with = "serialize_circuit", deserialize_with = "deserialize_circuit")]
     pub bytecode: Circuit,
+
+    #[serde(default)]
+    pub oracles: Vec<OracleSignature>,
 }
 
 impl ContractFunctionType {
@@ -0,0 +1,150 @@
+//! A human-readable disassembly of a compiled circuit.
+//!
+//! Each opcode is printed on its own line, prefixed by its index and followed by the source
+//! location which generated it, if known. Arithmetic opcodes are printed as folded expressions,
+//! e.g. `EXPR 2 * x * _4 + y - 3 = 0`, while other opcodes are printed as ACIR formats them.
+//! Witnesses holding a parameter or return value of the program are named after it.
+use std::collections::HashMap;
+use std::fmt::Write;
+
+use acvm::{
+    acir::{
+        circuit::{Circuit, Opcode},
+        native_types::{Expression, Witness},
+    },
+    FieldElement,
+};
+use fm::FileManager;
+use noirc_abi::Abi;
+use noirc_errors::{debug_info::DebugInfo, Location};
+
+/// Returns a human-readable disassembly of the given circuit.
+pub fn disassemble(
+    circuit: &Circuit,
+    abi: &Abi,
+    debug: &DebugInfo,
+    file_manager: &FileManager,
+) -> String {
+    let names = witness_names(abi);
+    let name = |witness: &Witness| match names.get(witness) {
+        Some(name) => name.clone(),
+        None => format!("_{}", witness.witness_index()),
+    };
+
+    let mut output = String::new();
+    for (parameter, witnesses) in &abi.param_witnesses {
+        let witnesses = witnesses.iter().map(|witness| format!("_{}", witness.witness_index()));
+        let witnesses = witnesses.collect::<Vec<_>>().join(", ");
+        writeln!(output, "parameter {parameter}: {witnesses}").unwrap();
+    }
+    let return_witnesses = abi.return_witnesses.iter().map(&name).collect::<Vec<_>>();
+    writeln!(output, "return: {}", return_witnesses.join(", ")).unwrap();
+    writeln!(output, "witnesses: {}", circuit.current_witness_index + 1).unwrap();
+
+    let width = circuit.opcodes.len().to_string().len();
+    for (index, opcode) in circuit.opcodes.iter().enumerate() {
+        let opcode = match opcode {
+            Opcode::Arithmetic(expr) => format!("EXPR {} = 0", format_expression(expr, &name)),
+            opcode => opcode.to_string(),
+        };
+        write!(output, "{index:>width$}: {opcode}").unwrap();
+        if let Some(location) = debug.opcode_location(index) {
+            write!(output, "  // {}", format_location(*location, file_manager)).unwrap();
+        }
+        writeln!(output).unwrap();
+    }
+    output
+}
+
+/// Names each witness holding a parameter or return value, e.g. `x` or `x[1]` for the second
+/// witness of a parameter `x` spanning several witnesses.
+fn witness_names(abi: &Abi) -> HashMap<Witness, String> {
+    let mut names = HashMap::new();
+    let named_witnesses = abi
+        .param_witnesses
+        .iter()
+        .map(|(name, witnesses)| (name.as_str(), witnesses))
+        .chain(std::iter::once(("return", &abi.return_witnesses)));
+
+    for (name, witnesses) in named_witnesses {
+        for (index, witness) in witnesses.iter().enumerate() {
+            let name =
+                if witnesses.len() == 1 { name.to_owned() } else { format!("{name}[{index}]") };
+            // A witness returned from a parameter keeps the name of the parameter
+            names.entry(*witness).or_insert(name);
+        }
+    }
+    names
+}
+
+/// Formats an expression as a sum of terms, omitting coefficients of one and subtracting terms
+/// whose coefficients are shorter when negated.
+fn format_expression(expr: &Expression, name: &impl Fn(&Witness) -> String) -> String {
+    let mul_terms = expr.mul_terms.iter().map(|(coefficient, lhs, rhs)| {
+        (*coefficient, Some(format!("{} * {}", name(lhs), name(rhs))))
+    });
+    let linear_terms = expr
+        .linear_combinations
+        .iter()
+        .map(|(coefficient, witness)| (*coefficient, Some(name(witness))));
+    let constant = (!expr.q_c.is_zero()).then_some((expr.q_c, None));
+
+    let mut output = String::new();
+    for (coefficient, term) in mul_terms.chain(linear_terms).chain(constant) {
+        let (is_negative, coefficient) = if (-coefficient).num_bits() < coefficient.num_bits() {
+            (true, -coefficient)
+        } else {
+            (false, coefficient)
+        };
+
+        match (output.is_empty(), is_negative) {
+            (true, true) => output.push('-'),
+            (true, false) => (),
+            (false, true) => output.push_str(" - "),
+            (false, false) => output.push_str(" + "),
+        }
+        match term {
+            Some(term) if coefficient.is_one() => output.push_str(&term),
+            Some(term) => write!(output, "{} * {term}", format_field(coefficient)).unwrap(),
+            None => output.push_str(&format_field(coefficient)),
+        }
+    }
+
+    if output.is_empty() {
+        output.push('0');
+    }
+    output
+}
+
+/// Formats a field element in decimal if it fits within a u128, or in hexadecimal otherwise.
+fn format_field(field: FieldElement) -> String {
+    if field.num_bits() <= 128 {
+        field.to_u128().to_string()
+    } else {
+        format!("0x{}", field.to_hex())
+    }
+}
+
+/// Formats a location as `path:line:column`, or as a byte range if its source is unavailable.
+fn format_location(location: Location, file_manager: &FileManager) -> String {
+    let path = file_manager.path(location.file).display();
+    match line_and_column(location, file_manager) {
+        Some((line, column)) => format!("{path}:{line}:{column}"),
+        None => format!("{path}:{}..{}", location.span.start(), location.span.end()),
+    }
+}
+
+/// Returns the line and column, both starting from one, at which the given location starts.
+pub(crate) fn line_and_column(
+    location: Location,
+    file_manager: &FileManager,
+) -> Option<(usize, usize)> {
+    let start = location.span.start() as usize;
+    let files = file_manager.as_simple_files();
+    let before = files.get(location.file.as_usize()).ok()?.source().get(..start)?;
+
+    let line = before.matches('\n').count() + 1;
+    let line_start = before.rfind('\n').map_or(0, |newline| newline + 1);
+    let column = before[line_start..].chars().count() + 1;
+    Some((line, column))
+}
@@ -0,0 +1,63 @@
+use std::collections::BTreeMap;
+
+use fm::FileManager;
+use serde::Serialize;
+
+use crate::{disassembly::line_and_column, CompiledProgram};
+
+/// A report of where the opcodes of a compiled program come from, by function and by source line.
+/// Both lists are sorted from the most opcodes to the fewest.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct GateCountReport {
+    /// The total number of opcodes in the circuit
+    pub total: usize,
+
+    /// The number of opcodes generated by each function of the program before inlining
+    pub functions: Vec<FunctionGateCount>,
+
+    /// The number of opcodes generated by each line of source code
+    pub lines: Vec<LineGateCount>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct FunctionGateCount {
+    pub name: String,
+    pub opcodes: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct LineGateCount {
+    pub file: String,
+    pub line: usize,
+    pub opcodes: usize,
+}
+
+impl CompiledProgram {
+    /// Reports the number of opcodes generated by each function and each source line of the
+    /// program. Programs which were deserialized rather than compiled have no gate counts.
+    pub fn gate_count_report(&self, file_manager: &FileManager) -> GateCountReport {
+        let counts = &self.gate_counts;
+
+        let mut functions: Vec<_> = counts
+            .functions
+            .iter()
+            .map(|(name, opcodes)| FunctionGateCount { name: name.clone(), opcodes: *opcodes })
+            .collect();
+        functions.sort_by(|a, b| b.opcodes.cmp(&a.opcodes));
+
+        let mut line_counts: BTreeMap<(String, usize), usize> = BTreeMap::new();
+        for (location, opcodes) in &counts.locations {
+            if let Some((line, _)) = line_and_column(*location, file_manager) {
+                let file = file_manager.path(location.file).display().to_string();
+                *line_counts.entry((file, line)).or_default() += *opcodes;
+            }
+        }
+        let mut lines: Vec<_> = line_counts
+            .into_iter()
+            .map(|((file, line), opcodes)| LineGateCount { file, line, opcodes })
+            .collect();
+        lines.sort_by(|a, b| b.opcodes.cmp(&a.opcodes));
+
+        GateCountReport { total: counts.total, functions, lines }
+    }
+}
@@ -7,20 +7,32 @@ use clap::Args;
 use fm::FileId;
 use noirc_abi::FunctionSignature;
 use noirc_errors::{CustomDiagnostic, FileDiagnostic};
-use noirc_evaluator::create_circuit;
+use noirc_evaluator::{
+    create_circuit, BackendCapabilities, Pass, PassManager, PassManagerError, SsaOptions,
+    StatisticsFormat,
+};
 use noirc_frontend::graph::{CrateId, CrateName, CrateType};
 use noirc_frontend::hir::def_map::{Contract, CrateDefMap};
 use noirc_frontend::hir::Context;
 use noirc_frontend::monomorphization::monomorphize;
 use noirc_frontend::node_interner::FuncId;
 use serde::{Deserialize, Serialize};
-use std::path::Path;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
 
 mod contract;
+mod disassembly;
+mod gate_report;
+mod oracles;
 mod program;
+mod provenance;
 
 pub use contract::{CompiledContract, ContractFunction, ContractFunctionType};
+pub use disassembly::disassemble;
+pub use gate_report::{FunctionGateCount, GateCountReport, LineGateCount};
+pub use oracles::{oracle_signatures, OracleSignature, OracleValueType};
 pub use program::CompiledProgram;
+pub use provenance::{OpcodeProvenanceEntry, ProvenanceReport};
 
 #[derive(Args, Clone, Debug, Default, Serialize, Deserialize)]
 pub struct CompileOptions {
@@ -28,18 +40,108 @@ pub struct CompileOptions {
     #[arg(short, long)]
     pub show_ssa: bool,
 
+    /// Emit only the changes each optimization pass makes to the intermediate SSA IR
+    #[arg(long)]
+    pub show_ssa_diff: bool,
+
+    /// Emit a human-readable disassembly of the generated ACIR
+    #[arg(long)]
+    pub show_acir: bool,
+
     #[arg(long)]
     pub show_brillig: bool,
 
+    /// Check the intermediate SSA IR for errors after each optimization pass
+    #[arg(long)]
+    pub verify_ssa: bool,
+
+    /// Remove dead branches and blocks using aggressive dead code elimination
+    #[arg(long)]
+    pub aggressive_dce: bool,
+
+    /// Partially unroll loops in unconstrained functions whose bounds are unknown by this factor
+    #[arg(long, default_value_t = 1)]
+    pub unroll_factor: usize,
+
+    /// The maximum number of instructions the loops of each function may be unrolled into
+    #[arg(long)]
+    pub max_unrolled_instructions: Option<usize>,
+
+    /// The maximum number of instructions which specializing unconstrained functions to their
+    /// constant arguments may add to the program
+    #[arg(long, default_value_t = 1000)]
+    pub specialization_budget: usize,
+
+    /// Warn when the circuit has more than this many opcodes, naming the source locations which
+    /// generate the most opcodes. This is an error if warnings are denied.
+    #[arg(long)]
+    pub opcode_budget: Option<usize>,
+
+    /// Split arithmetic opcodes so that each refers to at most this many witnesses. If not set,
+    /// opcodes are left for the backend's own compiler to split.
+    #[arg(long)]
+    pub expression_width: Option<usize>,
+
+    /// A comma-separated list of the SSA passes to run on ACIR programs instead of the default
+    /// pipeline, e.g. `inline,unroll,simplify_cfg,flatten,mem2reg,gvn,die`
+    #[arg(long, value_delimiter = ',', value_parser = parse_pass_name)]
+    pub passes: Vec<String>,
+
+    /// A comma-separated list of the SSA passes to run before brillig is generated for
+    /// unconstrained functions instead of the default pipeline
+    #[arg(long, value_delimiter = ',', value_parser = parse_pass_name)]
+    pub brillig_passes: Vec<String>,
+
+    /// Write the CFG of each function and the call graph as DOT files into this directory
+    #[arg(long)]
+    pub dot_dir: Option<PathBuf>,
+
+    /// A comma-separated list of the passes after which DOT files are written, e.g. `inline,flatten`
+    #[arg(long, value_delimiter = ',', value_parser = parse_pass_name)]
+    pub dot_passes: Vec<String>,
+
+    /// Print the time, change in program size, and memory usage of each SSA pass,
+    /// formatted as either a `table` or `json`
+    #[arg(long, value_parser = parse_statistics_format)]
+    pub pass_statistics: Option<String>,
+
+    /// Cache the SSA generated for each function in this directory, so that it is only generated
+    /// again once the function changes
+    #[arg(long)]
+    pub ssa_cache_dir: Option<PathBuf>,
+
     /// Display the ACIR for compiled circuit
     #[arg(long)]
     pub print_acir: bool,
 
+    /// Record the SSA instruction and purpose each opcode is generated for, and write them to a
+    /// JSON file alongside the compiled circuit
+    #[arg(long)]
+    pub opcode_provenance: bool,
+
+    /// Record the source location of each Brillig opcode in the debug information of the
+    /// compiled circuit, so that unconstrained code can be stepped through
+    #[arg(long)]
+    pub brillig_locations: bool,
+
     /// Treat all warnings as errors
     #[arg(short, long)]
     pub deny_warnings: bool,
 }
 
+fn parse_pass_name(name: &str) -> Result<String, PassManagerError> {
+    Pass::from_str(name).map(|_| name.to_owned())
+}
+
+/// Builds a pipeline from pass names which were validated when parsing the command line
+fn pipeline_from_names(names: &[String]) -> PassManager {
+    PassManager::new(names.iter().filter_map(|name| Pass::from_str(name).ok()).collect())
+}
+
+fn parse_statistics_format(format: &str) -> Result<String, PassManagerError> {
+    StatisticsFormat::from_str(format).map(|_| format.to_owned())
+}
+
 /// Helper type used to signify where only warnings are expected in file diagnostics
 pub type Warnings = Vec<FileDiagnostic>;
 
@@ -179,7 +281,7 @@ pub fn compile_main(
     crate_id: CrateId,
     options: &CompileOptions,
 ) -> Result<(CompiledProgram, Warnings), ErrorsAndWarnings> {
-    let warnings = check_crate(context, crate_id, options.deny_warnings)?;
+    let mut warnings = check_crate(context, crate_id, options.deny_warnings)?;
 
     let main = match context.get_main_function(&crate_id) {
         Some(m) => m,
@@ -192,7 +294,14 @@ pub fn compile_main(
         }
     };
 
-    let compiled_program = compile_no_check(context, true, options, main)?;
+    let (compiled_program, mut budget_warnings) = compile_no_check(context, options, main)?;
+    warnings.append(&mut budget_warnings);
+
+    if options.show_acir {
+        println!("Disassembled ACIR for main:");
+        let CompiledProgram { circuit, abi, debug, .. } = &compiled_program;
+        println!("{}", disassemble(circuit, abi, debug, &context.file_manager));
+    }
 
     if options.print_acir {
         println!("Compiled ACIR for main (unoptimized):");
@@ -216,7 +325,10 @@ pub fn compile_contracts(
 
     for contract in contracts {
         match compile_contract(context, contract, options) {
-            Ok(contract) => compiled_contracts.push(contract),
+            Ok((contract, mut more_warnings)) => {
+                compiled_contracts.push(contract);
+                errors.append(&mut more_warnings);
+            }
             Err(mut more_errors) => errors.append(&mut more_errors),
         }
     }
@@ -254,18 +366,28 @@ fn compile_contract(
     context: &Context,
     contract: Contract,
     options: &CompileOptions,
-) -> Result<CompiledContract, Vec<FileDiagnostic>> {
+) -> Result<(CompiledContract, Warnings), Vec<FileDiagnostic>> {
     let mut functions = Vec::new();
+    let mut warnings = Vec::new();
     let mut errs = Vec::new();
     for function_id in &contract.functions {
         let name = context.function_name(function_id).to_owned();
-        let function = match compile_no_check(context, true, options, *function_id) {
-            Ok(function) => function,
+        let function = match compile_no_check(context, options, *function_id) {
+            Ok((function, mut function_warnings)) => {
+                warnings.append(&mut function_warnings);
+                function
+            }
             Err(err) => {
                 errs.push(err);
                 continue;
             }
         };
+        if options.show_acir {
+            println!("Disassembled ACIR for {}::{name}:", contract.name);
+            let CompiledProgram { circuit, abi, debug, .. } = &function;
+            println!("{}", disassemble(circuit, abi, debug, &context.file_manager));
+        }
+
         let func_meta = context.def_interner.function_meta(function_id);
         let func_type = func_meta
             .contract_function_type
@@ -279,11 +401,12 @@ fn compile_contract(
             is_internal: func_meta.is_internal.unwrap_or(false),
             abi: function.abi,
             bytecode: function.circuit,
+            oracles: function.oracles,
         });
     }
 
     if errs.is_empty() {
-        Ok(CompiledContract { name: contract.name, functions })
+        Ok((CompiledContract { name: contract.name, functions }, warnings))
     } else {
         Err(errs)
     }
@@ -292,18 +415,63 @@ fn compile_contract(
 /// Compile the current crate. Assumes self.check_crate is called beforehand!
 ///
 /// This function also assumes all errors in experimental_create_circuit and create_circuit
-/// are not warnings.
+/// are not warnings. The only warning returned is for a circuit exceeding its opcode budget.
 #[allow(deprecated)]
 pub fn compile_no_check(
     context: &Context,
-    show_output: bool,
     options: &CompileOptions,
     main_function: FuncId,
-) -> Result<CompiledProgram, FileDiagnostic> {
+) -> Result<(CompiledProgram, Warnings), FileDiagnostic> {
     let program = monomorphize(main_function, &context.def_interner);
 
-    let (circuit, debug, abi) =
-        create_circuit(program, options.show_ssa, options.show_brillig, show_output)?;
+    let mut ssa_options = SsaOptions {
+        print_ssa_passes: options.show_ssa,
+        print_ssa_diffs: options.show_ssa_diff,
+        verify_ssa: options.verify_ssa,
+        print_brillig_trace: options.show_brillig,
+        unroll_factor: options.unroll_factor,
+        max_unrolled_instructions: options.max_unrolled_instructions,
+        specialization_budget: options.specialization_budget,
+        opcode_budget: options.opcode_budget,
+        strict_opcode_budget: options.deny_warnings,
+        record_opcode_provenance: options.opcode_provenance,
+        record_brillig_locations: options.brillig_locations,
+        dot_directory: options.dot_dir.clone(),
+        ssa_cache_directory: options.ssa_cache_dir.clone(),
+        // Pass names are validated when parsing the command line
+        dot_passes: options
+            .dot_passes
+            .iter()
+            .filter_map(|name| Pass::from_str(name).ok())
+            .collect(),
+        pass_statistics: options
+            .pass_statistics
+            .as_deref()
+            .and_then(|format| StatisticsFormat::from_str(format).ok()),
+        ..SsaOptions::default()
+    };
+    if !options.passes.is_empty() {
+        ssa_options.acir_passes = pipeline_from_names(&options.passes);
+    }
+    if !options.brillig_passes.is_empty() {
+        ssa_options.brillig_passes = pipeline_from_names(&options.brillig_passes);
+    }
+    if options.aggressive_dce {
+        ssa_options.enable_aggressive_dce();
+    }
 
-    Ok(CompiledProgram { circuit, debug, abi })
+    let capabilities =
+        BackendCapabilities { expression_width: options.expression_width, ..Default::default() };
+    let (circuit, debug, abi, gate_counts, exceeded_budget) =
+        create_circuit(program, &ssa_options, &capabilities)?;
+
+    let warnings = exceeded_budget
+        .into_iter()
+        .map(|exceeded| {
+            let file_id = exceeded.location().map(|location| location.file).unwrap_or_default();
+            exceeded.diagnostic(false).in_file(file_id)
+        })
+        .collect();
+    let oracles = oracle_signatures(&circuit);
+    Ok((CompiledProgram { circuit, abi, debug, oracles, gate_counts }, warnings))
 }
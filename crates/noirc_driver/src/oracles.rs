@@ -0,0 +1,97 @@
+use std::collections::BTreeSet;
+
+use acvm::acir::{
+    brillig::{
+        ForeignCallOutput, ForeignCallResult, Opcode as BrilligOpcode, RegisterOrMemory, Value,
+    },
+    circuit::{Circuit, Opcode},
+};
+use serde::{Deserialize, Serialize};
+
+/// The shape of a value passed to or returned from an oracle, which is how the Brillig
+/// `ForeignCall` opcode lays it out: scalars are held in a register, while arrays and slices are
+/// flattened into memory.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "lowercase")]
+pub enum OracleValueType {
+    Scalar,
+    /// An array of the given number of field elements, once flattened
+    Array {
+        length: usize,
+    },
+    /// A slice, whose length is only known when the oracle is called
+    Slice,
+}
+
+/// The signature of an oracle, i.e. a function marked `#[oracle(name)]`, as called by a compiled
+/// program. Executors use it to check the values passed to the oracle and to lay out the values
+/// returned from it.
+///
+/// The same oracle may be called with several signatures, e.g. `println` with each type it prints.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct OracleSignature {
+    pub name: String,
+    pub inputs: Vec<OracleValueType>,
+    pub outputs: Vec<OracleValueType>,
+}
+
+impl OracleSignature {
+    /// Whether the inputs of a foreign call, one list of values per input, have the shapes of
+    /// the inputs of this signature.
+    pub fn accepts_inputs(&self, inputs: &[Vec<Value>]) -> bool {
+        inputs.len() == self.inputs.len()
+            && self.inputs.iter().zip(inputs).all(|(typ, values)| match typ {
+                OracleValueType::Scalar => values.len() == 1,
+                OracleValueType::Array { length } => values.len() == *length,
+                OracleValueType::Slice => true,
+            })
+    }
+
+    /// Whether the result of a foreign call has the shapes of the outputs of this signature.
+    pub fn accepts_result(&self, result: &ForeignCallResult) -> bool {
+        result.values.len() == self.outputs.len()
+            && self.outputs.iter().zip(&result.values).all(|(typ, output)| match (typ, output) {
+                (OracleValueType::Scalar, ForeignCallOutput::Single(_)) => true,
+                (OracleValueType::Array { length }, ForeignCallOutput::Array(values)) => {
+                    values.len() == *length
+                }
+                (OracleValueType::Slice, ForeignCallOutput::Array(_)) => true,
+                _ => false,
+            })
+    }
+}
+
+/// Returns the distinct signatures of the oracles called by the Brillig code of a circuit,
+/// sorted by name.
+pub fn oracle_signatures(circuit: &Circuit) -> Vec<OracleSignature> {
+    let signatures: BTreeSet<OracleSignature> = circuit
+        .opcodes
+        .iter()
+        .filter_map(|opcode| match opcode {
+            Opcode::Brillig(brillig) => Some(&brillig.bytecode),
+            _ => None,
+        })
+        .flatten()
+        .filter_map(|opcode| match opcode {
+            BrilligOpcode::ForeignCall { function, destinations, inputs } => {
+                Some(OracleSignature {
+                    name: function.clone(),
+                    inputs: inputs.iter().map(OracleValueType::from).collect(),
+                    outputs: destinations.iter().map(OracleValueType::from).collect(),
+                })
+            }
+            _ => None,
+        })
+        .collect();
+    signatures.into_iter().collect()
+}
+
+impl From<&RegisterOrMemory> for OracleValueType {
+    fn from(value: &RegisterOrMemory) -> Self {
+        match value {
+            RegisterOrMemory::RegisterIndex(_) => OracleValueType::Scalar,
+            RegisterOrMemory::HeapArray(array) => OracleValueType::Array { length: array.size },
+            RegisterOrMemory::HeapVector(_) => OracleValueType::Slice,
+        }
+    }
+}
@@ -2,14 +2,24 @@ use acvm::acir::circuit::Circuit;
 
 use base64::Engine;
 use noirc_errors::debug_info::DebugInfo;
+use noirc_evaluator::GateCounts;
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
 
+use crate::OracleSignature;
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct CompiledProgram {
     #[serde(serialize_with = "serialize_circuit", deserialize_with = "deserialize_circuit")]
     pub circuit: Circuit,
     pub abi: noirc_abi::Abi,
     pub debug: DebugInfo,
+    /// The oracles called by the program, which executors must provide
+    #[serde(default)]
+    pub oracles: Vec<OracleSignature>,
+    /// The number of opcodes generated by each source location and function, which is only
+    /// known when the program is compiled
+    #[serde(skip)]
+    pub gate_counts: GateCounts,
 }
 
 pub(crate) fn serialize_circuit<S>(circuit: &Circuit, s: S) -> Result<S::Ok, S::Error>
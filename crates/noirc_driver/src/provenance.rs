@@ -0,0 +1,55 @@
+use fm::FileManager;
+use serde::Serialize;
+
+use crate::{disassembly::line_and_column, CompiledProgram};
+
+/// What each opcode of a compiled program was generated for, in a form meant to be read by tools
+/// such as circuit visualizers. Only opcodes with a recorded provenance are listed, by index.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ProvenanceReport {
+    pub opcodes: Vec<OpcodeProvenanceEntry>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct OpcodeProvenanceEntry {
+    /// The index of the opcode within the circuit
+    pub index: usize,
+    /// The kind of SSA instruction the opcode was generated for, e.g. `mul`
+    pub instruction: String,
+    /// What the opcode does within the lowering of the instruction, e.g. `range check`
+    pub purpose: Option<String>,
+    /// The source file of the code which generated the opcode, if known
+    pub file: Option<String>,
+    /// The source line of the code which generated the opcode, if known
+    pub line: Option<usize>,
+}
+
+impl CompiledProgram {
+    /// Reports the provenance of each opcode of the program, which is only recorded when the
+    /// program is compiled with `--opcode-provenance`.
+    pub fn provenance_report(&self, file_manager: &FileManager) -> ProvenanceReport {
+        let mut opcodes: Vec<_> = self
+            .debug
+            .provenance
+            .iter()
+            .map(|(index, provenance)| {
+                let location = self.debug.opcode_location(*index).copied();
+                let file =
+                    location.map(|location| file_manager.path(location.file).display().to_string());
+                let line = location
+                    .and_then(|location| line_and_column(location, file_manager))
+                    .map(|(line, _)| line);
+                OpcodeProvenanceEntry {
+                    index: *index,
+                    instruction: provenance.instruction.clone(),
+                    purpose: provenance.purpose.clone(),
+                    file,
+                    line,
+                }
+            })
+            .collect();
+        opcodes.sort_by_key(|entry| entry.index);
+
+        ProvenanceReport { opcodes }
+    }
+}
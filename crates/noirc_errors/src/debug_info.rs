@@ -1,4 +1,4 @@
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 
 use crate::Location;
 use serde::{Deserialize, Serialize};
@@ -7,14 +7,41 @@ use serde::{Deserialize, Serialize};
 pub struct DebugInfo {
     /// Map opcode index of an ACIR circuit into the source code location
     pub locations: HashMap<usize, Location>,
+    /// Map opcode index of an ACIR circuit into the message of the assertion which generated it
+    #[serde(default)]
+    pub assert_messages: HashMap<usize, String>,
+    /// Map opcode index of an ACIR circuit into what generated it, if this was recorded
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub provenance: HashMap<usize, OpcodeProvenance>,
+    /// Map opcode index of a Brillig opcode of an ACIR circuit into the source code location of
+    /// each opcode of its bytecode which has one, by position in the bytecode, if this was
+    /// recorded. Debuggers use it to step through unconstrained code.
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub brillig_locations: HashMap<usize, BTreeMap<usize, Location>>,
+}
+
+/// What an opcode of an ACIR circuit was generated for, beyond its source location
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct OpcodeProvenance {
+    /// The kind of SSA instruction the opcode was generated for, e.g. `mul` or `call to_le_bits`
+    pub instruction: String,
+    /// What the opcode does within the lowering of that instruction, e.g. `range check`, if it
+    /// is generated for a specific purpose
+    pub purpose: Option<String>,
 }
 
 impl DebugInfo {
-    pub fn new(locations: HashMap<usize, Location>) -> Self {
-        DebugInfo { locations }
+    pub fn new(
+        locations: HashMap<usize, Location>,
+        assert_messages: HashMap<usize, String>,
+        provenance: HashMap<usize, OpcodeProvenance>,
+        brillig_locations: HashMap<usize, BTreeMap<usize, Location>>,
+    ) -> Self {
+        DebugInfo { locations, assert_messages, provenance, brillig_locations }
     }
 
-    /// Updates the locations map when the circuit is modified
+    /// Updates the locations, assertion messages, provenance and Brillig locations maps when the
+    /// circuit is modified
     ///
     /// When the circuit is generated, the indices are 0,1,..,n
     /// When the circuit is modified, the opcodes are eventually
@@ -25,15 +52,46 @@ impl DebugInfo {
     /// opcode_indices is this list of mixed indices
     pub fn update_acir(&mut self, opcode_indices: Vec<usize>) {
         let mut new_locations = HashMap::new();
+        let mut new_assert_messages = HashMap::new();
+        let mut new_provenance = HashMap::new();
+        let mut new_brillig_locations = HashMap::new();
         for (i, idx) in opcode_indices.iter().enumerate() {
             if self.locations.contains_key(idx) {
                 new_locations.insert(i, self.locations[idx]);
             }
+            if let Some(message) = self.assert_messages.get(idx) {
+                new_assert_messages.insert(i, message.clone());
+            }
+            if let Some(provenance) = self.provenance.get(idx) {
+                new_provenance.insert(i, provenance.clone());
+            }
+            if let Some(brillig_locations) = self.brillig_locations.get(idx) {
+                new_brillig_locations.insert(i, brillig_locations.clone());
+            }
         }
         self.locations = new_locations;
+        self.assert_messages = new_assert_messages;
+        self.provenance = new_provenance;
+        self.brillig_locations = new_brillig_locations;
     }
 
     pub fn opcode_location(&self, idx: usize) -> Option<&Location> {
         self.locations.get(&idx)
     }
+
+    /// Returns the message of the assertion which generated the opcode, if it was given one
+    pub fn opcode_assert_message(&self, idx: usize) -> Option<&str> {
+        self.assert_messages.get(&idx).map(String::as_str)
+    }
+
+    /// Returns what the opcode was generated for, if this was recorded
+    pub fn opcode_provenance(&self, idx: usize) -> Option<&OpcodeProvenance> {
+        self.provenance.get(&idx)
+    }
+
+    /// Returns the source code location of the opcode at position `brillig_idx` within the
+    /// bytecode of the Brillig opcode at `idx`, if it has one and Brillig locations were recorded
+    pub fn brillig_opcode_location(&self, idx: usize, brillig_idx: usize) -> Option<&Location> {
+        self.brillig_locations.get(&idx).and_then(|locations| locations.get(&brillig_idx))
+    }
 }
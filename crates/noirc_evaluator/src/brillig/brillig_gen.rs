@@ -4,7 +4,19 @@ pub(crate) mod brillig_directive;
 pub(crate) mod brillig_fn;
 pub(crate) mod brillig_slice_ops;
 
-use crate::ssa_refactor::ir::{function::Function, post_order::PostOrder};
+use acvm::acir::BlackBoxFunc;
+
+use crate::{
+    errors::{RuntimeError, RuntimeErrorKind},
+    ssa_refactor::ir::{
+        array_escape::ArrayEscapeAnalysis,
+        function::Function,
+        instruction::{Instruction, Intrinsic},
+        liveness::VariableLiveness,
+        post_order::PostOrder,
+        value::Value,
+    },
+};
 
 use std::collections::HashMap;
 
@@ -13,13 +25,22 @@ use self::{brillig_block::BrilligBlock, brillig_fn::FunctionContext};
 use super::brillig_ir::{artifact::BrilligArtifact, BrilligContext};
 
 /// Converting an SSA function into Brillig bytecode.
-pub(crate) fn convert_ssa_function(func: &Function, enable_debug_trace: bool) -> BrilligArtifact {
+pub(crate) fn convert_ssa_function(
+    func: &Function,
+    enable_debug_trace: bool,
+) -> Result<BrilligArtifact, RuntimeError> {
+    check_intrinsic_calls(func)?;
+
     let mut reverse_post_order = Vec::new();
     reverse_post_order.extend_from_slice(PostOrder::with_function(func).as_slice());
     reverse_post_order.reverse();
 
-    let mut function_context =
-        FunctionContext { function_id: func.id(), ssa_value_to_brillig_variable: HashMap::new() };
+    let mut function_context = FunctionContext {
+        function_id: func.id(),
+        ssa_value_to_brillig_variable: HashMap::new(),
+        array_escapes: ArrayEscapeAnalysis::with_function(func),
+        liveness: VariableLiveness::with_function(func),
+    };
 
     let mut brillig_context = BrilligContext::new(enable_debug_trace);
 
@@ -28,5 +49,305 @@ pub(crate) fn convert_ssa_function(func: &Function, enable_debug_trace: bool) ->
         BrilligBlock::compile(&mut function_context, &mut brillig_context, block, &func.dfg);
     }
 
-    brillig_context.artifact()
+    Ok(brillig_context.artifact())
+}
+
+/// Returns an error for the first call within the function to an intrinsic which brillig has no
+/// implementation of.
+fn check_intrinsic_calls(func: &Function) -> Result<(), RuntimeError> {
+    for block in func.reachable_blocks() {
+        for instruction_id in func.dfg[block].instructions() {
+            let callee = match &func.dfg[*instruction_id] {
+                Instruction::Call { func: callee, .. } => *callee,
+                _ => continue,
+            };
+            if let Value::Intrinsic(intrinsic) = &func.dfg[callee] {
+                if !is_supported_intrinsic(intrinsic) {
+                    let kind = RuntimeErrorKind::UnsupportedInUnconstrained {
+                        name: intrinsic.to_string(),
+                    };
+                    return Err(RuntimeError::new(kind, func.dfg.get_location(instruction_id)));
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Returns true if brillig implements the given intrinsic.
+///
+/// Checking that an array is a sorted permutation of another relies on a permutation network,
+/// and checking a big integer multiplication relies on limb carry constraints, which both only
+/// exist as constraints.
+fn is_supported_intrinsic(intrinsic: &Intrinsic) -> bool {
+    !matches!(
+        intrinsic,
+        Intrinsic::AssertSortedPermutation
+            | Intrinsic::BigIntMulAddMod
+            | Intrinsic::BlackBox(
+                BlackBoxFunc::AND
+                    | BlackBoxFunc::XOR
+                    | BlackBoxFunc::RANGE
+                    | BlackBoxFunc::RecursiveAggregation
+            )
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::HashSet, rc::Rc};
+
+    use acvm::acir::brillig::{Opcode as BrilligOpcode, RegisterIndex, Value};
+    use acvm::brillig_vm::{Registers, VMStatus, VM};
+
+    use crate::brillig::brillig_ir::tests::DummyBlackBoxSolver;
+    use crate::errors::RuntimeErrorKind;
+    use crate::ssa_refactor::{
+        ir::{
+            function::{FunctionId, RuntimeType},
+            instruction::{BinaryOp, Intrinsic},
+            map::Id,
+            types::Type,
+        },
+        ssa_builder::FunctionBuilder,
+        ssa_gen::Ssa,
+    };
+
+    use super::convert_ssa_function;
+
+    /// Compiles the brillig functions of `ssa`, then runs `main` on the given argument and
+    /// returns its result.
+    fn run_main(ssa: &Ssa, argument: usize) -> Value {
+        let brillig = ssa.to_brillig(false).unwrap();
+        let byte_code = brillig.link_entry_point(ssa.main()).finish().byte_code;
+
+        let registers = Registers { inner: vec![Value::from(argument)] };
+        let mut vm = VM::new(registers, vec![], byte_code, vec![], &DummyBlackBoxSolver);
+        assert_eq!(vm.process_opcodes(), VMStatus::Finished);
+        vm.get_registers().get(RegisterIndex(0))
+    }
+
+    /// Builds the current function so that it returns `base_case` if its argument is zero, and
+    /// the result of calling `callee` on the argument minus one otherwise, multiplied by the
+    /// argument if `multiply` is set.
+    fn build_countdown(
+        builder: &mut FunctionBuilder,
+        callee: FunctionId,
+        base_case: u128,
+        multiply: bool,
+    ) {
+        let b1 = builder.insert_block();
+        let b2 = builder.insert_block();
+
+        let v0 = builder.add_parameter(Type::field());
+        let zero = builder.field_constant(0u128);
+        let is_zero = builder.insert_binary(v0, BinaryOp::Eq, zero);
+        builder.terminate_with_jmpif(is_zero, b1, b2);
+
+        builder.switch_to_block(b1);
+        let base_case = builder.field_constant(base_case);
+        builder.terminate_with_return(vec![base_case]);
+
+        builder.switch_to_block(b2);
+        let callee = builder.import_function(callee);
+        let one = builder.field_constant(1u128);
+        let v1 = builder.insert_binary(v0, BinaryOp::Sub, one);
+        let mut result = builder.insert_call(callee, vec![v1], vec![Type::field()])[0];
+        if multiply {
+            result = builder.insert_binary(v0, BinaryOp::Mul, result);
+        }
+        builder.terminate_with_return(vec![result]);
+    }
+
+    #[test]
+    fn runs_recursive_functions() {
+        // brillig fn factorial f0 {
+        //   b0(v0: Field):
+        //     v1 = eq v0, Field 0
+        //     jmpif v1, then: b1, else: b2
+        //   b1():
+        //     return Field 1
+        //   b2():
+        //     v2 = sub v0, Field 1
+        //     v3 = call factorial(v2)
+        //     v4 = mul v0, v3
+        //     return v4
+        // }
+        let factorial_id = Id::test_new(0);
+        let mut builder =
+            FunctionBuilder::new("factorial".into(), factorial_id, RuntimeType::Brillig);
+        build_countdown(&mut builder, factorial_id, 1, true);
+        let ssa = builder.finish();
+
+        assert_eq!(run_main(&ssa, 5), Value::from(120_usize));
+    }
+
+    #[test]
+    fn runs_mutually_recursive_functions() {
+        // brillig fn is_even f0 {
+        //   b0(v0: Field):
+        //     v1 = eq v0, Field 0
+        //     jmpif v1, then: b1, else: b2
+        //   b1():
+        //     return Field 1
+        //   b2():
+        //     v2 = sub v0, Field 1
+        //     v3 = call is_odd(v2)
+        //     return v3
+        // }
+        // brillig fn is_odd f1 {
+        //   (the same, returning Field 0 and calling is_even)
+        // }
+        let is_even_id = Id::test_new(0);
+        let is_odd_id = Id::test_new(1);
+        let mut builder = FunctionBuilder::new("is_even".into(), is_even_id, RuntimeType::Brillig);
+        build_countdown(&mut builder, is_odd_id, 1, false);
+        builder.new_brillig_function("is_odd".into(), is_odd_id);
+        build_countdown(&mut builder, is_even_id, 0, false);
+        let ssa = builder.finish();
+
+        assert_eq!(run_main(&ssa, 7), Value::from(0_usize));
+        assert_eq!(run_main(&ssa, 10), Value::from(1_usize));
+    }
+
+    #[test]
+    fn runs_loops_with_runtime_exit_conditions() {
+        // brillig fn main f0 {
+        //   b0(v0: Field):
+        //     v1 = allocate
+        //     store v0 at v1
+        //     v2 = allocate
+        //     store Field 0 at v2
+        //     jmp b1()
+        //   b1():
+        //     v3 = load v1 -> Field
+        //     v4 = eq v3, Field 0
+        //     jmpif v4 then: b3, else: b2
+        //   b2():
+        //     v5 = load v2 -> Field
+        //     v6 = add v5, v3
+        //     store v6 at v2
+        //     v7 = sub v3, Field 1
+        //     store v7 at v1
+        //     jmp b1()
+        //   b3():
+        //     v8 = load v2 -> Field
+        //     return v8
+        // }
+        let main_id = Id::test_new(0);
+        let mut builder = FunctionBuilder::new("main".into(), main_id, RuntimeType::Brillig);
+        let b1 = builder.insert_block();
+        let b2 = builder.insert_block();
+        let b3 = builder.insert_block();
+
+        let v0 = builder.add_parameter(Type::field());
+        let v1 = builder.insert_allocate();
+        builder.insert_store(v1, v0);
+        let v2 = builder.insert_allocate();
+        let zero = builder.field_constant(0u128);
+        builder.insert_store(v2, zero);
+        builder.terminate_with_jmp(b1, vec![]);
+
+        builder.switch_to_block(b1);
+        let v3 = builder.insert_load(v1, Type::field());
+        let v4 = builder.insert_binary(v3, BinaryOp::Eq, zero);
+        builder.terminate_with_jmpif(v4, b3, b2);
+
+        builder.switch_to_block(b2);
+        let v5 = builder.insert_load(v2, Type::field());
+        let v6 = builder.insert_binary(v5, BinaryOp::Add, v3);
+        builder.insert_store(v2, v6);
+        let one = builder.field_constant(1u128);
+        let v7 = builder.insert_binary(v3, BinaryOp::Sub, one);
+        builder.insert_store(v1, v7);
+        builder.terminate_with_jmp(b1, vec![]);
+
+        builder.switch_to_block(b3);
+        let v8 = builder.insert_load(v2, Type::field());
+        builder.terminate_with_return(vec![v8]);
+
+        let ssa = builder.finish();
+
+        // The number of iterations is only known once the argument is
+        assert_eq!(run_main(&ssa, 4), Value::from(10_usize));
+        assert_eq!(run_main(&ssa, 0), Value::from(0_usize));
+    }
+
+    #[test]
+    fn reuses_the_registers_of_dead_variables() {
+        // brillig fn main f0 {
+        //   b0(v0: Field):
+        //     v1 = add v0, v0
+        //     v2 = add v1, v0
+        //     ...
+        //     v10 = add v9, v0
+        //     return v10
+        // }
+        let main_id = Id::test_new(0);
+        let mut builder = FunctionBuilder::new("main".into(), main_id, RuntimeType::Brillig);
+
+        let v0 = builder.add_parameter(Type::field());
+        let mut sum = v0;
+        for _ in 0..10 {
+            sum = builder.insert_binary(sum, BinaryOp::Add, v0);
+        }
+        builder.terminate_with_return(vec![sum]);
+
+        let ssa = builder.finish();
+        let artifact = convert_ssa_function(ssa.main(), false).unwrap();
+
+        // Each sum dies once the next one is computed, so two registers hold all of them
+        let destinations: HashSet<_> = artifact
+            .byte_code
+            .iter()
+            .filter_map(|opcode| match opcode {
+                BrilligOpcode::BinaryFieldOp { destination, .. } => Some(*destination),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(destinations.len(), 2);
+    }
+
+    #[test]
+    fn rejects_sorted_permutation_checks() {
+        // brillig fn main f0 {
+        //   b0(v0: [Field; 3], v1: [Field; 3]):
+        //     call assert_sorted_permutation(v0, v1)
+        //     return
+        // }
+        let main_id = Id::test_new(0);
+        let mut builder = FunctionBuilder::new("main".into(), main_id, RuntimeType::Brillig);
+
+        let array = Type::Array(Rc::new(vec![Type::field()]), 3);
+        let v0 = builder.add_parameter(array.clone());
+        let v1 = builder.add_parameter(array);
+        let check = builder.import_intrinsic_id(Intrinsic::AssertSortedPermutation);
+        builder.insert_call(check, vec![v0, v1], vec![]);
+        builder.terminate_with_return(vec![]);
+
+        let ssa = builder.finish();
+        let error = ssa.to_brillig(false).err().expect("The intrinsic has no brillig lowering");
+        assert!(matches!(error.kind, RuntimeErrorKind::UnsupportedInUnconstrained { .. }));
+    }
+
+    #[test]
+    fn rejects_big_integer_multiplication_checks() {
+        // brillig fn main f0 {
+        //   b0(v0: [Field; 4]):
+        //     call bigint_mul_add_mod(v0, v0, v0, v0, v0, v0)
+        //     return
+        // }
+        let main_id = Id::test_new(0);
+        let mut builder = FunctionBuilder::new("main".into(), main_id, RuntimeType::Brillig);
+
+        let limbs = Type::Array(Rc::new(vec![Type::field()]), 4);
+        let v0 = builder.add_parameter(limbs);
+        let check = builder.import_intrinsic_id(Intrinsic::BigIntMulAddMod);
+        builder.insert_call(check, vec![v0; 6], vec![]);
+        builder.terminate_with_return(vec![]);
+
+        let ssa = builder.finish();
+        let error = ssa.to_brillig(false).err().expect("The intrinsic has no brillig lowering");
+        assert!(matches!(error.kind, RuntimeErrorKind::UnsupportedInUnconstrained { .. }));
+    }
 }
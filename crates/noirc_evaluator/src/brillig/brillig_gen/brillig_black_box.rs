@@ -96,6 +96,26 @@ pub(crate) fn convert_black_box_call(
                 )
             }
         }
+        BlackBoxFunc::EcdsaSecp256r1 => {
+            if let (
+                [RegisterOrMemory::HeapArray(public_key_x), RegisterOrMemory::HeapArray(public_key_y), RegisterOrMemory::HeapArray(signature), RegisterOrMemory::HeapArray(message_hash)],
+                [RegisterOrMemory::RegisterIndex(result_register)],
+            ) = (function_arguments, function_results)
+            {
+                let message_hash_vector = brillig_context.array_to_vector(message_hash);
+                brillig_context.black_box_op_instruction(BlackBoxOp::EcdsaSecp256r1 {
+                    hashed_msg: message_hash_vector,
+                    public_key_x: *public_key_x,
+                    public_key_y: *public_key_y,
+                    signature: *signature,
+                    result: *result_register,
+                });
+            } else {
+                unreachable!(
+                    "ICE: EcdsaSecp256r1 expects four array arguments and one register result"
+                )
+            }
+        }
         BlackBoxFunc::Pedersen => {
             if let (
                 [RegisterOrMemory::HeapArray(message_array), RegisterOrMemory::RegisterIndex(domain_separator)],
@@ -147,6 +167,113 @@ pub(crate) fn convert_black_box_call(
                 )
             }
         }
-        _ => unimplemented!("ICE: Black box function {:?} is not implemented", bb_func),
+        BlackBoxFunc::AND
+        | BlackBoxFunc::XOR
+        | BlackBoxFunc::RANGE
+        | BlackBoxFunc::RecursiveAggregation => {
+            unreachable!("ICE: Calls to {bb_func:?} are rejected before brillig generation")
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::rc::Rc;
+
+    use acvm::acir::{
+        brillig::{RegisterIndex, Value},
+        BlackBoxFunc,
+    };
+    use acvm::brillig_vm::{Registers, VMStatus, VM};
+
+    use crate::brillig::brillig_ir::tests::DummyBlackBoxSolver;
+    use crate::errors::RuntimeErrorKind;
+    use crate::ssa_refactor::{
+        ir::{function::RuntimeType, instruction::Intrinsic, map::Id, types::Type},
+        ssa_builder::FunctionBuilder,
+    };
+
+    /// Runs an unconstrained function hashing `message` with the given black box function,
+    /// passing the message size as well if `message_size` is set, and returns the digest as hex.
+    fn run_hash(hash: BlackBoxFunc, message: &[u8], message_size: Option<usize>) -> String {
+        // brillig fn main f0 {
+        //   b0():
+        //     v0 = call hash([u8 ...], ...)
+        //     return v0
+        // }
+        let main_id = Id::test_new(0);
+        let mut builder = FunctionBuilder::new("main".into(), main_id, RuntimeType::Brillig);
+
+        let byte = Type::unsigned(8);
+        let elements = message
+            .iter()
+            .map(|value| builder.numeric_constant(*value as u128, byte.clone()))
+            .collect();
+        let message_type = Type::Array(Rc::new(vec![byte.clone()]), message.len());
+        let mut arguments = vec![builder.array_constant(elements, message_type)];
+        if let Some(message_size) = message_size {
+            arguments.push(builder.numeric_constant(message_size as u128, Type::unsigned(32)));
+        }
+
+        let hash = builder.import_intrinsic_id(Intrinsic::BlackBox(hash));
+        let digest_type = Type::Array(Rc::new(vec![byte]), 32);
+        let digest = builder.insert_call(hash, arguments, vec![digest_type]).to_vec();
+        builder.terminate_with_return(digest);
+
+        let ssa = builder.finish();
+        let brillig = ssa.to_brillig(false).unwrap();
+        let byte_code = brillig.link_entry_point(ssa.main()).finish().byte_code;
+
+        let registers = Registers { inner: vec![] };
+        let mut vm = VM::new(registers, vec![], byte_code, vec![], &DummyBlackBoxSolver);
+        assert_eq!(vm.process_opcodes(), VMStatus::Finished);
+
+        // The digest is returned as a pointer to its bytes
+        let pointer = vm.get_registers().get(RegisterIndex(0)).to_usize();
+        let digest: &[Value] = &vm.get_memory()[pointer..pointer + 32];
+        digest.iter().map(|byte| format!("{:02x}", byte.to_field().to_u128())).collect()
+    }
+
+    #[test]
+    fn hashes_with_black_box_opcodes() {
+        assert_eq!(
+            run_hash(BlackBoxFunc::SHA256, b"abc", None),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+        assert_eq!(
+            run_hash(BlackBoxFunc::Blake2s, b"abc", None),
+            "508c5e8c327c14e2e1a72ba34eeb452f37458b209ed63a294d999b4c86675982"
+        );
+    }
+
+    #[test]
+    fn hashes_the_first_bytes_of_the_message_with_keccak256() {
+        // Only the first `message_size` bytes are hashed
+        assert_eq!(
+            run_hash(BlackBoxFunc::Keccak256, b"abcd", Some(3)),
+            "4e03657aea45a94fc7d47ba826c8d667c0d1e6e33a64a036ec44f58fa12d6c45"
+        );
+    }
+
+    #[test]
+    fn rejects_proof_verification_in_unconstrained_code() {
+        // brillig fn main f0 {
+        //   b0(v0: [Field; 4]):
+        //     v1 = call recursive_aggregation(v0, v0, v0, v0, v0)
+        //     return v1
+        // }
+        let main_id = Id::test_new(0);
+        let mut builder = FunctionBuilder::new("main".into(), main_id, RuntimeType::Brillig);
+
+        let fields = Type::Array(Rc::new(vec![Type::field()]), 4);
+        let v0 = builder.add_parameter(fields.clone());
+        let aggregate =
+            builder.import_intrinsic_id(Intrinsic::BlackBox(BlackBoxFunc::RecursiveAggregation));
+        let v1 = builder.insert_call(aggregate, vec![v0; 5], vec![fields]).to_vec();
+        builder.terminate_with_return(v1);
+
+        let ssa = builder.finish();
+        let error = ssa.to_brillig(false).err().expect("Proofs cannot be verified in brillig");
+        assert!(matches!(error.kind, RuntimeErrorKind::UnsupportedInUnconstrained { .. }));
     }
 }
@@ -1,3 +1,5 @@
+use std::collections::HashSet;
+
 use crate::brillig::brillig_gen::brillig_slice_ops::{
     convert_array_or_vector_to_vector, slice_push_back_operation,
 };
@@ -57,14 +59,43 @@ impl<'block> BrilligBlock<'block> {
         self.convert_block_params(block, dfg);
 
         // Convert all of the instructions into the block
+        let mut defined = HashSet::new();
         for instruction_id in block.instructions() {
+            self.brillig_context.set_location(dfg.get_location(instruction_id));
             self.convert_ssa_instruction(*instruction_id, dfg);
+
+            defined.extend(dfg.instruction_results(*instruction_id).iter().copied());
+            let dying = self.function_context.liveness.last_uses(*instruction_id).cloned();
+            self.release_dead_variables(dying, &defined);
         }
 
-        // Process the block's terminator instruction
+        // Process the block's terminator instruction, which has no location of its own
+        self.brillig_context.set_location(None);
         let terminator_instruction =
             block.terminator().expect("block is expected to be constructed");
         self.convert_ssa_terminator(terminator_instruction, dfg);
+
+        let dying = self.function_context.liveness.terminator_last_uses(self.block_id).cloned();
+        self.release_dead_variables(dying, &defined);
+    }
+
+    /// Deallocates the registers of the variables which have just been used for the last time,
+    /// so that the variables defined afterward may reuse them.
+    ///
+    /// Only the results of the instructions of the current block are released. Any other
+    /// variable may still be live within a block compiled later, and the registers of a block
+    /// parameter are written to by the jumps to its block, which may also be compiled later.
+    fn release_dead_variables(
+        &mut self,
+        dying: Option<HashSet<ValueId>>,
+        defined: &HashSet<ValueId>,
+    ) {
+        for value in dying.into_iter().flatten().filter(|value| defined.contains(value)) {
+            let variable = self.function_context.get_variable(value);
+            for register in self.function_context.extract_registers(variable) {
+                self.brillig_context.deallocate_register(register);
+            }
+        }
     }
 
     fn get_bit_size_from_ssa_type(typ: Type) -> u32 {
@@ -97,8 +128,9 @@ impl<'block> BrilligBlock<'block> {
 
     /// Converts an SSA terminator instruction into the necessary opcodes.
     ///
-    /// TODO: document why the TerminatorInstruction::Return includes a stop instruction
-    /// TODO along with the `Self::compile`
+    /// A TerminatorInstruction::Return ends with a stop instruction, so that the artifact of a
+    /// function can be run on its own. Each stop is replaced by a return once the function is
+    /// linked after the entry point, or after any function calling it.
     fn convert_ssa_terminator(
         &mut self,
         terminator_instruction: &TerminatorInstruction,
@@ -218,7 +250,7 @@ impl<'block> BrilligBlock<'block> {
                 );
                 self.convert_ssa_binary(binary, dfg, result_register);
             }
-            Instruction::Constrain(value) => {
+            Instruction::Constrain(value, _) => {
                 let condition = self.convert_ssa_register_value(*value, dfg);
                 self.brillig_context.constrain_instruction(condition);
             }
@@ -408,7 +440,11 @@ impl<'block> BrilligBlock<'block> {
                 };
 
                 let index_register = self.convert_ssa_register_value(*index, dfg);
-                self.convert_ssa_array_get(array_pointer, index_register, destination_variable);
+                self.brillig_context.retrieve_variable_from_array(
+                    array_pointer,
+                    index_register,
+                    destination_variable,
+                );
             }
             Instruction::ArraySet { array, index, value } => {
                 let source_variable = self.convert_ssa_value(*array, dfg);
@@ -419,11 +455,14 @@ impl<'block> BrilligBlock<'block> {
                 let destination_variable =
                     self.function_context.create_variable(self.brillig_context, result_ids[0], dfg);
 
+                let in_place =
+                    self.function_context.array_escapes.is_in_place_update(instruction_id);
                 self.convert_ssa_array_set(
                     source_variable,
                     destination_variable,
                     index_register,
                     value_variable,
+                    in_place,
                 );
             }
             _ => todo!("ICE: Instruction not supported {instruction:?}"),
@@ -483,31 +522,18 @@ impl<'block> BrilligBlock<'block> {
             .post_call_prep_returns_load_registers(&returned_registers, &saved_registers);
     }
 
-    fn convert_ssa_array_get(
-        &mut self,
-        array_pointer: RegisterIndex,
-        index_register: RegisterIndex,
-        destination_variable: RegisterOrMemory,
-    ) {
-        match destination_variable {
-            RegisterOrMemory::RegisterIndex(destination_register) => {
-                self.brillig_context.array_get(array_pointer, index_register, destination_register);
-            }
-            RegisterOrMemory::HeapArray(HeapArray { pointer, .. }) => {
-                self.brillig_context.array_get(array_pointer, index_register, pointer);
-            }
-            RegisterOrMemory::HeapVector(_) => unimplemented!("ICE: Array get for vector"),
-        }
-    }
-
     /// Array set operation in SSA returns a new array or slice that is a copy of the parameter array or slice
     /// With a specific value changed.
+    ///
+    /// If `in_place` is set, the parameter array or slice is never used again, so it is updated
+    /// directly instead of being copied.
     fn convert_ssa_array_set(
         &mut self,
         source_variable: RegisterOrMemory,
         destination_variable: RegisterOrMemory,
         index_register: RegisterIndex,
         value_variable: RegisterOrMemory,
+        in_place: bool,
     ) {
         let destination_pointer = match destination_variable {
             RegisterOrMemory::HeapArray(HeapArray { pointer, .. }) => pointer,
@@ -515,6 +541,35 @@ impl<'block> BrilligBlock<'block> {
             _ => unreachable!("ICE: array set returns non-array"),
         };
 
+        if in_place {
+            match (source_variable, destination_variable) {
+                (
+                    RegisterOrMemory::HeapArray(HeapArray { pointer: source_pointer, .. }),
+                    RegisterOrMemory::HeapArray(_),
+                ) => {
+                    self.brillig_context.mov_instruction(destination_pointer, source_pointer);
+                }
+                (
+                    RegisterOrMemory::HeapVector(HeapVector {
+                        pointer: source_pointer,
+                        size: source_size,
+                    }),
+                    RegisterOrMemory::HeapVector(HeapVector { size: target_size, .. }),
+                ) => {
+                    self.brillig_context.mov_instruction(destination_pointer, source_pointer);
+                    self.brillig_context.mov_instruction(target_size, source_size);
+                }
+                _ => unreachable!("ICE: array set on non-array"),
+            }
+
+            self.brillig_context.store_variable_in_array(
+                destination_pointer,
+                index_register,
+                value_variable,
+            );
+            return;
+        }
+
         // First issue a array copy to the destination
         let (source_pointer, source_size_as_register) = match source_variable {
             RegisterOrMemory::HeapArray(HeapArray { size, pointer }) => {
@@ -546,31 +601,19 @@ impl<'block> BrilligBlock<'block> {
         }
 
         // Then set the value in the newly created array
-        self.store_variable_in_array(destination_pointer, index_register, value_variable);
+        self.brillig_context.store_variable_in_array(
+            destination_pointer,
+            index_register,
+            value_variable,
+        );
 
         self.brillig_context.deallocate_register(source_size_as_register);
     }
 
-    fn store_variable_in_array(
-        &mut self,
-        destination_pointer: RegisterIndex,
-        index_register: RegisterIndex,
-        value_variable: RegisterOrMemory,
-    ) {
-        match value_variable {
-            RegisterOrMemory::RegisterIndex(value_register) => {
-                self.brillig_context.array_set(destination_pointer, index_register, value_register);
-            }
-            RegisterOrMemory::HeapArray(HeapArray { pointer, .. }) => {
-                self.brillig_context.array_set(destination_pointer, index_register, pointer);
-            }
-            RegisterOrMemory::HeapVector(_) => {
-                unimplemented!("ICE: cannot store a vector in array")
-            }
-        }
-    }
-
     /// Convert the SSA slice operations to brillig slice operations
+    ///
+    /// The items of a slice of structs are passed to and returned from these operations as one
+    /// value per field, and each index is the index of an item rather than of a field.
     fn convert_ssa_slice_intrinsic_call(
         &mut self,
         dfg: &DataFlowGraph,
@@ -578,122 +621,135 @@ impl<'block> BrilligBlock<'block> {
         instruction_id: InstructionId,
         arguments: &[ValueId],
     ) {
-        let source_variable = self.convert_ssa_value(arguments[0], dfg);
+        let slice_id = arguments[0];
+        let element_size = dfg.type_of_value(slice_id).element_size();
+        let source_variable = self.convert_ssa_value(slice_id, dfg);
         let source_vector =
             convert_array_or_vector_to_vector(self.brillig_context, source_variable);
 
+        let results = dfg.instruction_results(instruction_id);
         match intrinsic {
             Value::Intrinsic(Intrinsic::SlicePushBack) => {
-                let target_variable = self.function_context.create_variable(
-                    self.brillig_context,
-                    dfg.instruction_results(instruction_id)[0],
-                    dfg,
-                );
+                let target_variable =
+                    self.function_context.create_variable(self.brillig_context, results[0], dfg);
                 let target_vector = self.function_context.extract_heap_vector(target_variable);
-                let item_value = self.convert_ssa_register_value(arguments[1], dfg);
+                let item_values = vecmap(&arguments[1..element_size + 1], |argument| {
+                    self.convert_ssa_value(*argument, dfg)
+                });
                 slice_push_back_operation(
                     self.brillig_context,
                     target_vector,
                     source_vector,
-                    item_value,
+                    &item_values,
                 );
             }
             Value::Intrinsic(Intrinsic::SlicePushFront) => {
-                let target_variable = self.function_context.create_variable(
-                    self.brillig_context,
-                    dfg.instruction_results(instruction_id)[0],
-                    dfg,
-                );
+                let target_variable =
+                    self.function_context.create_variable(self.brillig_context, results[0], dfg);
                 let target_vector = self.function_context.extract_heap_vector(target_variable);
-                let item_value = self.convert_ssa_register_value(arguments[1], dfg);
+                let item_values = vecmap(&arguments[1..element_size + 1], |argument| {
+                    self.convert_ssa_value(*argument, dfg)
+                });
                 slice_push_front_operation(
                     self.brillig_context,
                     target_vector,
                     source_vector,
-                    item_value,
+                    &item_values,
                 );
             }
             Value::Intrinsic(Intrinsic::SlicePopBack) => {
-                let results = dfg.instruction_results(instruction_id);
-
                 let target_variable =
                     self.function_context.create_variable(self.brillig_context, results[0], dfg);
                 let target_vector = self.function_context.extract_heap_vector(target_variable);
 
-                let pop_item = self.function_context.create_register_variable(
-                    self.brillig_context,
-                    results[1],
-                    dfg,
-                );
+                let pop_variables = vecmap(&results[1..element_size + 1], |result| {
+                    self.function_context.create_variable(self.brillig_context, *result, dfg)
+                });
 
                 slice_pop_back_operation(
                     self.brillig_context,
                     target_vector,
                     source_vector,
-                    pop_item,
+                    &pop_variables,
                 );
             }
             Value::Intrinsic(Intrinsic::SlicePopFront) => {
-                let results = dfg.instruction_results(instruction_id);
+                let pop_variables = vecmap(&results[0..element_size], |result| {
+                    self.function_context.create_variable(self.brillig_context, *result, dfg)
+                });
 
-                let pop_item = self.function_context.create_register_variable(
+                let target_variable = self.function_context.create_variable(
                     self.brillig_context,
-                    results[0],
+                    results[element_size],
                     dfg,
                 );
-                let target_variable =
-                    self.function_context.create_variable(self.brillig_context, results[1], dfg);
                 let target_vector = self.function_context.extract_heap_vector(target_variable);
 
                 slice_pop_front_operation(
                     self.brillig_context,
                     target_vector,
                     source_vector,
-                    pop_item,
+                    &pop_variables,
                 );
             }
             Value::Intrinsic(Intrinsic::SliceInsert) => {
-                let results = dfg.instruction_results(instruction_id);
-                let index = self.convert_ssa_register_value(arguments[1], dfg);
-                let item = self.convert_ssa_register_value(arguments[2], dfg);
                 let target_variable =
                     self.function_context.create_variable(self.brillig_context, results[0], dfg);
-
                 let target_vector = self.function_context.extract_heap_vector(target_variable);
+
+                let user_index = self.convert_ssa_register_value(arguments[1], dfg);
+                let index = self.flattened_slice_index(user_index, element_size);
+
+                let items = vecmap(&arguments[2..element_size + 2], |argument| {
+                    self.convert_ssa_value(*argument, dfg)
+                });
+
                 slice_insert_operation(
                     self.brillig_context,
                     target_vector,
                     source_vector,
                     index,
-                    item,
+                    &items,
                 );
+                self.brillig_context.deallocate_register(index);
             }
             Value::Intrinsic(Intrinsic::SliceRemove) => {
-                let results = dfg.instruction_results(instruction_id);
-                let index = self.convert_ssa_register_value(arguments[1], dfg);
-
                 let target_variable =
                     self.function_context.create_variable(self.brillig_context, results[0], dfg);
                 let target_vector = self.function_context.extract_heap_vector(target_variable);
 
-                let removed_item_register = self.function_context.create_register_variable(
-                    self.brillig_context,
-                    results[1],
-                    dfg,
-                );
+                let user_index = self.convert_ssa_register_value(arguments[1], dfg);
+                let index = self.flattened_slice_index(user_index, element_size);
+
+                let removed_items = vecmap(&results[1..element_size + 1], |result| {
+                    self.function_context.create_variable(self.brillig_context, *result, dfg)
+                });
 
                 slice_remove_operation(
                     self.brillig_context,
                     target_vector,
                     source_vector,
                     index,
-                    removed_item_register,
+                    &removed_items,
                 );
+                self.brillig_context.deallocate_register(index);
             }
             _ => unreachable!("ICE: Slice operation not supported"),
         }
     }
 
+    /// Returns a new register holding the index of the first field of the item at `item_index`,
+    /// in a slice whose items have `element_size` fields.
+    fn flattened_slice_index(
+        &mut self,
+        item_index: RegisterIndex,
+        element_size: usize,
+    ) -> RegisterIndex {
+        let index = self.brillig_context.allocate_register();
+        self.brillig_context.usize_op(item_index, index, BinaryIntOp::Mul, element_size);
+        index
+    }
+
     /// Converts an SSA cast to a sequence of Brillig opcodes.
     /// Casting is only necessary when shrinking the bit size of a numeric value.
     fn convert_cast(
@@ -773,6 +829,8 @@ impl<'block> BrilligBlock<'block> {
 
     /// Converts an SSA `ValueId` into a `RegisterOrMemory`. Initializes if necessary.
     fn convert_ssa_value(&mut self, value_id: ValueId, dfg: &DataFlowGraph) -> RegisterOrMemory {
+        // Optimizations may replace values without updating the instructions using them
+        let value_id = dfg.resolve(value_id);
         let value = &dfg[value_id];
 
         let variable = match value {
@@ -830,7 +888,11 @@ impl<'block> BrilligBlock<'block> {
                 for element_id in array.iter() {
                     let element_variable = self.convert_ssa_value(*element_id, dfg);
                     // Store the item in memory
-                    self.store_variable_in_array(pointer, iterator_register, element_variable);
+                    self.brillig_context.store_variable_in_array(
+                        pointer,
+                        iterator_register,
+                        element_variable,
+                    );
                     // Increment the iterator
                     self.brillig_context.usize_op_in_place(iterator_register, BinaryIntOp::Add, 1);
                 }
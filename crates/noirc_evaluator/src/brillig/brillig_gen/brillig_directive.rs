@@ -1,4 +1,9 @@
-use acvm::acir::brillig::{BinaryFieldOp, Opcode as BrilligOpcode, RegisterIndex, Value};
+use acvm::acir::brillig::{
+    BinaryFieldOp, BinaryIntOp, HeapArray, Opcode as BrilligOpcode, RegisterIndex,
+    RegisterOrMemory, Value,
+};
+
+use crate::brillig::brillig_ir::BRILLIG_MEMORY_ADDRESSING_BIT_SIZE;
 
 /// Generates brillig bytecode which computes the inverse of its input if not null, and zero else.
 pub(crate) fn directive_invert() -> Vec<BrilligOpcode> {
@@ -29,3 +34,91 @@ pub(crate) fn directive_invert() -> Vec<BrilligOpcode> {
         BrilligOpcode::Stop,
     ]
 }
+
+/// Generates brillig bytecode which passes its inputs to the `println` foreign call.
+///
+/// Each input is an array of the given size, which is placed in memory before the bytecode is
+/// run, with a pointer to it in the register of the same index as the input.
+pub(crate) fn directive_println(input_sizes: &[usize]) -> Vec<BrilligOpcode> {
+    let inputs = input_sizes.iter().enumerate().map(|(index, size)| {
+        RegisterOrMemory::HeapArray(HeapArray { pointer: RegisterIndex::from(index), size: *size })
+    });
+    vec![
+        BrilligOpcode::ForeignCall {
+            function: "println".to_owned(),
+            destinations: Vec::new(),
+            inputs: inputs.collect(),
+        },
+        BrilligOpcode::Stop,
+    ]
+}
+
+/// Generates brillig bytecode which sorts its input array in ascending order, in place.
+///
+/// The array holds `len` integers of `bit_size` bits. It is placed in memory before the bytecode
+/// is run, with a pointer to it in register (0), so the sorted array is read from the same place.
+pub(crate) fn directive_sort(len: usize, bit_size: u32) -> Vec<BrilligOpcode> {
+    //  We generate an insertion sort:
+    // for i in 1..len {
+    //    let mut j = i;
+    //    while j != 0 & array[j] < array[j - 1] {
+    //        swap(array[j], array[j - 1]);
+    //        j -= 1;
+    //    }
+    // }
+    let pointer = RegisterIndex::from(0);
+    let i = RegisterIndex::from(1);
+    let j = RegisterIndex::from(2);
+    let one = RegisterIndex::from(3);
+    let length = RegisterIndex::from(4);
+    let current_address = RegisterIndex::from(5);
+    let previous_address = RegisterIndex::from(6);
+    let previous = RegisterIndex::from(7);
+    let current = RegisterIndex::from(8);
+    let condition = RegisterIndex::from(9);
+    // Locations of the outer loop, the inner loop, the end of the inner loop and the stop opcode
+    let outer_loop = 3;
+    let inner_loop = 6;
+    let next_i = 17;
+    let stop_location = 19;
+
+    let index_op = |op: BinaryIntOp, destination, lhs, rhs| BrilligOpcode::BinaryIntOp {
+        destination,
+        op,
+        bit_size: BRILLIG_MEMORY_ADDRESSING_BIT_SIZE,
+        lhs,
+        rhs,
+    };
+
+    vec![
+        BrilligOpcode::Const { destination: one, value: Value::from(1_usize) },
+        BrilligOpcode::Const { destination: length, value: Value::from(len) },
+        BrilligOpcode::Const { destination: i, value: Value::from(1_usize) },
+        // Outer loop: stop once every element has been inserted
+        index_op(BinaryIntOp::LessThan, condition, i, length),
+        BrilligOpcode::JumpIfNot { condition, location: stop_location },
+        BrilligOpcode::Mov { destination: j, source: i },
+        // Inner loop: move the element down until it is not less than the previous one
+        BrilligOpcode::JumpIfNot { condition: j, location: next_i },
+        index_op(BinaryIntOp::Add, current_address, pointer, j),
+        index_op(BinaryIntOp::Sub, previous_address, current_address, one),
+        BrilligOpcode::Load { destination: previous, source_pointer: previous_address },
+        BrilligOpcode::Load { destination: current, source_pointer: current_address },
+        BrilligOpcode::BinaryIntOp {
+            destination: condition,
+            op: BinaryIntOp::LessThan,
+            bit_size,
+            lhs: current,
+            rhs: previous,
+        },
+        BrilligOpcode::JumpIfNot { condition, location: next_i },
+        BrilligOpcode::Store { destination_pointer: previous_address, source: current },
+        BrilligOpcode::Store { destination_pointer: current_address, source: previous },
+        index_op(BinaryIntOp::Sub, j, j, one),
+        BrilligOpcode::Jump { location: inner_loop },
+        // Insert the next element
+        index_op(BinaryIntOp::Add, i, i, one),
+        BrilligOpcode::Jump { location: outer_loop },
+        BrilligOpcode::Stop,
+    ]
+}
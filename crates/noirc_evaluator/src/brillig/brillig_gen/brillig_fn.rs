@@ -9,8 +9,10 @@ use crate::{
         BrilligContext,
     },
     ssa_refactor::ir::{
+        array_escape::ArrayEscapeAnalysis,
         dfg::DataFlowGraph,
         function::{Function, FunctionId},
+        liveness::VariableLiveness,
         types::{CompositeType, Type},
         value::ValueId,
     },
@@ -20,6 +22,10 @@ pub(crate) struct FunctionContext {
     pub(crate) function_id: FunctionId,
     /// Map from SSA values to register or memory.
     pub(crate) ssa_value_to_brillig_variable: HashMap<ValueId, RegisterOrMemory>,
+    /// The array writes which may update their source array in place rather than copying it.
+    pub(crate) array_escapes: ArrayEscapeAnalysis,
+    /// The last use of each variable, after which its registers may be reused.
+    pub(crate) liveness: VariableLiveness,
 }
 
 impl FunctionContext {
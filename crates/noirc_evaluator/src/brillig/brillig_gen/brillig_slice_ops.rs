@@ -2,14 +2,22 @@ use acvm::brillig_vm::brillig::{BinaryIntOp, HeapVector, RegisterIndex, Register
 
 use crate::brillig::brillig_ir::BrilligContext;
 
+// The items of a slice of structs are flattened, so each operation reads or writes the fields of
+// an item, in order, as consecutive values of the vector.
+
 pub(crate) fn slice_push_back_operation(
     brillig_context: &mut BrilligContext,
     target_vector: HeapVector,
     source_vector: HeapVector,
-    item_to_insert: RegisterIndex,
+    variables_to_insert: &[RegisterOrMemory],
 ) {
-    // First we need to allocate the target vector incrementing the size by 1
-    brillig_context.usize_op(source_vector.size, target_vector.size, BinaryIntOp::Add, 1);
+    // First we need to allocate the target vector incrementing the size by the item size
+    brillig_context.usize_op(
+        source_vector.size,
+        target_vector.size,
+        BinaryIntOp::Add,
+        variables_to_insert.len(),
+    );
     brillig_context.allocate_array_instruction(target_vector.pointer, target_vector.size);
 
     // Now we copy the source vector into the target vector
@@ -19,24 +27,40 @@ pub(crate) fn slice_push_back_operation(
         source_vector.size,
     );
 
-    brillig_context.array_set(target_vector.pointer, source_vector.size, item_to_insert);
+    // Then we write the fields of the item after the copied ones
+    store_items_from_index(
+        brillig_context,
+        target_vector.pointer,
+        source_vector.size,
+        variables_to_insert,
+    );
 }
 
 pub(crate) fn slice_push_front_operation(
     brillig_context: &mut BrilligContext,
     target_vector: HeapVector,
     source_vector: HeapVector,
-    item_to_insert: RegisterIndex,
+    variables_to_insert: &[RegisterOrMemory],
 ) {
-    // First we need to allocate the target vector incrementing the size by 1
-    brillig_context.usize_op(source_vector.size, target_vector.size, BinaryIntOp::Add, 1);
+    // First we need to allocate the target vector incrementing the size by the item size
+    brillig_context.usize_op(
+        source_vector.size,
+        target_vector.size,
+        BinaryIntOp::Add,
+        variables_to_insert.len(),
+    );
     brillig_context.allocate_array_instruction(target_vector.pointer, target_vector.size);
 
-    // Now we offset the target pointer by one
+    // Now we offset the target pointer by the item size
     let destination_copy_pointer = brillig_context.allocate_register();
-    brillig_context.usize_op(target_vector.pointer, destination_copy_pointer, BinaryIntOp::Add, 1);
+    brillig_context.usize_op(
+        target_vector.pointer,
+        destination_copy_pointer,
+        BinaryIntOp::Add,
+        variables_to_insert.len(),
+    );
 
-    // Now we copy the source vector into the target vector starting at index 1
+    // Now we copy the source vector into the target vector after the item
     brillig_context.copy_array_instruction(
         source_vector.pointer,
         destination_copy_pointer,
@@ -44,9 +68,9 @@ pub(crate) fn slice_push_front_operation(
     );
     brillig_context.deallocate_register(destination_copy_pointer);
 
-    // Then we write the item to insert at index 0
+    // Then we write the fields of the item at index 0
     let zero = brillig_context.make_constant(0_u128.into());
-    brillig_context.array_set(target_vector.pointer, zero, item_to_insert);
+    store_items_from_index(brillig_context, target_vector.pointer, zero, variables_to_insert);
     brillig_context.deallocate_register(zero);
 }
 
@@ -54,17 +78,27 @@ pub(crate) fn slice_pop_front_operation(
     brillig_context: &mut BrilligContext,
     target_vector: HeapVector,
     source_vector: HeapVector,
-    removed_item: RegisterIndex,
+    removed_items: &[RegisterOrMemory],
 ) {
-    // First we need to allocate the target vector decrementing the size by 1
-    brillig_context.usize_op(source_vector.size, target_vector.size, BinaryIntOp::Sub, 1);
+    // First we need to allocate the target vector decrementing the size by the item size
+    brillig_context.usize_op(
+        source_vector.size,
+        target_vector.size,
+        BinaryIntOp::Sub,
+        removed_items.len(),
+    );
     brillig_context.allocate_array_instruction(target_vector.pointer, target_vector.size);
 
-    // Now we offset the source pointer by one
+    // Now we offset the source pointer by the item size
     let source_copy_pointer = brillig_context.allocate_register();
-    brillig_context.usize_op(source_vector.pointer, source_copy_pointer, BinaryIntOp::Add, 1);
+    brillig_context.usize_op(
+        source_vector.pointer,
+        source_copy_pointer,
+        BinaryIntOp::Add,
+        removed_items.len(),
+    );
 
-    // Now we copy the source vector starting at index 1 into the target vector
+    // Now we copy the source vector after the first item into the target vector
     brillig_context.copy_array_instruction(
         source_copy_pointer,
         target_vector.pointer,
@@ -73,7 +107,7 @@ pub(crate) fn slice_pop_front_operation(
     brillig_context.deallocate_register(source_copy_pointer);
 
     let zero = brillig_context.make_constant(0_u128.into());
-    brillig_context.array_get(source_vector.pointer, zero, removed_item);
+    retrieve_items_from_index(brillig_context, source_vector.pointer, zero, removed_items);
     brillig_context.deallocate_register(zero);
 }
 
@@ -81,31 +115,43 @@ pub(crate) fn slice_pop_back_operation(
     brillig_context: &mut BrilligContext,
     target_vector: HeapVector,
     source_vector: HeapVector,
-    removed_item: RegisterIndex,
+    removed_items: &[RegisterOrMemory],
 ) {
-    // First we need to allocate the target vector decrementing the size by 1
-    brillig_context.usize_op(source_vector.size, target_vector.size, BinaryIntOp::Sub, 1);
+    // First we need to allocate the target vector decrementing the size by the item size
+    brillig_context.usize_op(
+        source_vector.size,
+        target_vector.size,
+        BinaryIntOp::Sub,
+        removed_items.len(),
+    );
     brillig_context.allocate_array_instruction(target_vector.pointer, target_vector.size);
 
-    // Now we copy all elements but the last into the target vector
+    // Now we copy all elements but the last item into the target vector
     brillig_context.copy_array_instruction(
         source_vector.pointer,
         target_vector.pointer,
         target_vector.size,
     );
 
-    brillig_context.array_get(source_vector.pointer, target_vector.size, removed_item);
+    retrieve_items_from_index(
+        brillig_context,
+        source_vector.pointer,
+        target_vector.size,
+        removed_items,
+    );
 }
 
+/// Inserts an item in a vector, where `index` is the index of the first field of the item once
+/// flattened, i.e. the index of the item multiplied by the number of fields in an item.
 pub(crate) fn slice_insert_operation(
     brillig_context: &mut BrilligContext,
     target_vector: HeapVector,
     source_vector: HeapVector,
     index: RegisterIndex,
-    item: RegisterIndex,
+    items: &[RegisterOrMemory],
 ) {
-    // First we need to allocate the target vector incrementing the size by 1
-    brillig_context.usize_op(source_vector.size, target_vector.size, BinaryIntOp::Add, 1);
+    // First we need to allocate the target vector incrementing the size by the item size
+    brillig_context.usize_op(source_vector.size, target_vector.size, BinaryIntOp::Add, items.len());
     brillig_context.allocate_array_instruction(target_vector.pointer, target_vector.size);
 
     // Copy the elements to the left of the index
@@ -120,7 +166,7 @@ pub(crate) fn slice_insert_operation(
         BinaryIntOp::Add,
     );
 
-    // Compute the target pointer after the index
+    // Compute the target pointer after the inserted item
     let target_pointer_after_index = brillig_context.allocate_register();
     brillig_context.memory_op(
         target_vector.pointer,
@@ -128,7 +174,7 @@ pub(crate) fn slice_insert_operation(
         target_pointer_after_index,
         BinaryIntOp::Add,
     );
-    brillig_context.usize_op_in_place(target_pointer_after_index, BinaryIntOp::Add, 1);
+    brillig_context.usize_op_in_place(target_pointer_after_index, BinaryIntOp::Add, items.len());
 
     // Compute the number of elements to the right of the index
     let item_count = brillig_context.allocate_register();
@@ -145,25 +191,32 @@ pub(crate) fn slice_insert_operation(
     brillig_context.deallocate_register(target_pointer_after_index);
     brillig_context.deallocate_register(item_count);
 
-    // Write the item to insert at the index
-    brillig_context.array_set(target_vector.pointer, index, item);
+    // Write the fields of the item to insert from the index
+    store_items_from_index(brillig_context, target_vector.pointer, index, items);
 }
 
+/// Removes an item from a vector, where `index` is the index of the first field of the item once
+/// flattened, as in `slice_insert_operation`.
 pub(crate) fn slice_remove_operation(
     brillig_context: &mut BrilligContext,
     target_vector: HeapVector,
     source_vector: HeapVector,
     index: RegisterIndex,
-    removed_item: RegisterIndex,
+    removed_items: &[RegisterOrMemory],
 ) {
-    // First we need to allocate the target vector decrementing the size by 1
-    brillig_context.usize_op(source_vector.size, target_vector.size, BinaryIntOp::Sub, 1);
+    // First we need to allocate the target vector decrementing the size by the item size
+    brillig_context.usize_op(
+        source_vector.size,
+        target_vector.size,
+        BinaryIntOp::Sub,
+        removed_items.len(),
+    );
     brillig_context.allocate_array_instruction(target_vector.pointer, target_vector.size);
 
     // Copy the elements to the left of the index
     brillig_context.copy_array_instruction(source_vector.pointer, target_vector.pointer, index);
 
-    // Compute the source pointer after the index
+    // Compute the source pointer after the removed item
     let source_pointer_after_index = brillig_context.allocate_register();
     brillig_context.memory_op(
         source_vector.pointer,
@@ -171,7 +224,11 @@ pub(crate) fn slice_remove_operation(
         source_pointer_after_index,
         BinaryIntOp::Add,
     );
-    brillig_context.usize_op_in_place(source_pointer_after_index, BinaryIntOp::Add, 1);
+    brillig_context.usize_op_in_place(
+        source_pointer_after_index,
+        BinaryIntOp::Add,
+        removed_items.len(),
+    );
 
     // Compute the target pointer at the index
     let target_pointer_at_index = brillig_context.allocate_register();
@@ -182,12 +239,12 @@ pub(crate) fn slice_remove_operation(
         BinaryIntOp::Add,
     );
 
-    // Compute the number of elements to the right of the index
+    // Compute the number of elements to the right of the removed item
     let item_count = brillig_context.allocate_register();
     brillig_context.memory_op(source_vector.size, index, item_count, BinaryIntOp::Sub);
-    brillig_context.usize_op_in_place(item_count, BinaryIntOp::Sub, 1);
+    brillig_context.usize_op_in_place(item_count, BinaryIntOp::Sub, removed_items.len());
 
-    // Copy the elements to the right of the index
+    // Copy the elements to the right of the removed item
     brillig_context.copy_array_instruction(
         source_pointer_after_index,
         target_pointer_at_index,
@@ -198,8 +255,44 @@ pub(crate) fn slice_remove_operation(
     brillig_context.deallocate_register(target_pointer_at_index);
     brillig_context.deallocate_register(item_count);
 
-    // Get the item at the index
-    brillig_context.array_get(source_vector.pointer, index, removed_item);
+    // Get the fields of the item at the index
+    retrieve_items_from_index(brillig_context, source_vector.pointer, index, removed_items);
+}
+
+/// Stores the given variables in consecutive values of an array, starting at `index`
+fn store_items_from_index(
+    brillig_context: &mut BrilligContext,
+    array_pointer: RegisterIndex,
+    index: RegisterIndex,
+    variables: &[RegisterOrMemory],
+) {
+    let item_index = brillig_context.allocate_register();
+    brillig_context.mov_instruction(item_index, index);
+    for (position, variable) in variables.iter().enumerate() {
+        if position > 0 {
+            brillig_context.usize_op_in_place(item_index, BinaryIntOp::Add, 1);
+        }
+        brillig_context.store_variable_in_array(array_pointer, item_index, *variable);
+    }
+    brillig_context.deallocate_register(item_index);
+}
+
+/// Loads consecutive values of an array, starting at `index`, into the given variables
+fn retrieve_items_from_index(
+    brillig_context: &mut BrilligContext,
+    array_pointer: RegisterIndex,
+    index: RegisterIndex,
+    variables: &[RegisterOrMemory],
+) {
+    let item_index = brillig_context.allocate_register();
+    brillig_context.mov_instruction(item_index, index);
+    for (position, variable) in variables.iter().enumerate() {
+        if position > 0 {
+            brillig_context.usize_op_in_place(item_index, BinaryIntOp::Add, 1);
+        }
+        brillig_context.retrieve_variable_from_array(array_pointer, item_index, *variable);
+    }
+    brillig_context.deallocate_register(item_index);
 }
 
 pub(crate) fn convert_array_or_vector_to_vector(
@@ -217,7 +310,7 @@ pub(crate) fn convert_array_or_vector_to_vector(
 mod tests {
     use std::vec;
 
-    use acvm::acir::brillig::{HeapVector, Value};
+    use acvm::acir::brillig::{HeapVector, RegisterOrMemory, Value};
     use acvm::brillig_vm::brillig::RegisterIndex;
 
     use crate::brillig::brillig_gen::brillig_slice_ops::{
@@ -262,14 +355,14 @@ mod tests {
                     &mut context,
                     HeapVector { pointer: copied_array_pointer, size: copied_array_size },
                     HeapVector { pointer: array_pointer, size: array_size },
-                    item_to_insert,
+                    &[RegisterOrMemory::RegisterIndex(item_to_insert)],
                 );
             } else {
                 slice_push_front_operation(
                     &mut context,
                     HeapVector { pointer: copied_array_pointer, size: copied_array_size },
                     HeapVector { pointer: array_pointer, size: array_size },
-                    item_to_insert,
+                    &[RegisterOrMemory::RegisterIndex(item_to_insert)],
                 );
             }
 
@@ -356,14 +449,14 @@ mod tests {
                     &mut context,
                     HeapVector { pointer: copied_array_pointer, size: copied_array_size },
                     HeapVector { pointer: array_pointer, size: array_size },
-                    removed_item,
+                    &[RegisterOrMemory::RegisterIndex(removed_item)],
                 );
             } else {
                 slice_pop_front_operation(
                     &mut context,
                     HeapVector { pointer: copied_array_pointer, size: copied_array_size },
                     HeapVector { pointer: array_pointer, size: array_size },
-                    removed_item,
+                    &[RegisterOrMemory::RegisterIndex(removed_item)],
                 );
             }
 
@@ -454,7 +547,7 @@ mod tests {
                 HeapVector { pointer: copied_array_pointer, size: copied_array_size },
                 HeapVector { pointer: array_pointer, size: array_size },
                 index_to_insert,
-                item_to_insert,
+                &[RegisterOrMemory::RegisterIndex(item_to_insert)],
             );
 
             context.return_instruction(&[copied_array_pointer, copied_array_size]);
@@ -576,7 +669,7 @@ mod tests {
                 HeapVector { pointer: copied_array_pointer, size: copied_array_size },
                 HeapVector { pointer: array_pointer, size: array_size },
                 index_to_insert,
-                removed_item,
+                &[RegisterOrMemory::RegisterIndex(removed_item)],
             );
 
             context.return_instruction(&[copied_array_pointer, copied_array_size, removed_item]);
@@ -641,4 +734,86 @@ mod tests {
             Value::from(1_usize),
         );
     }
+
+    #[test]
+    fn test_slice_operations_with_struct_items() {
+        // The slice [(1, 2), (3, 4)], which items have two fields
+        let array = vec![
+            Value::from(1_usize),
+            Value::from(2_usize),
+            Value::from(3_usize),
+            Value::from(4_usize),
+        ];
+        let arguments = vec![
+            BrilligParameter::Array(vec![BrilligParameter::Simple], array.len()),
+            BrilligParameter::Simple,
+            BrilligParameter::Simple,
+        ];
+        let returns = vec![
+            BrilligParameter::Array(vec![BrilligParameter::Simple], array.len() + 2),
+            BrilligParameter::Simple,
+            BrilligParameter::Array(vec![BrilligParameter::Simple], array.len()),
+            BrilligParameter::Simple,
+            BrilligParameter::Simple,
+            BrilligParameter::Simple,
+        ];
+
+        let mut context = create_context();
+
+        // Allocate the parameters
+        let array_pointer = context.allocate_register();
+        let first_field = context.allocate_register();
+        let second_field = context.allocate_register();
+        let array_size = context.make_constant(array.len().into());
+
+        // Push the item (5, 6) to the back of the slice
+        let pushed_vector =
+            HeapVector { pointer: context.allocate_register(), size: context.allocate_register() };
+        slice_push_back_operation(
+            &mut context,
+            pushed_vector,
+            HeapVector { pointer: array_pointer, size: array_size },
+            &[
+                RegisterOrMemory::RegisterIndex(first_field),
+                RegisterOrMemory::RegisterIndex(second_field),
+            ],
+        );
+
+        // Then pop the first item from the front of the new slice
+        let popped_vector =
+            HeapVector { pointer: context.allocate_register(), size: context.allocate_register() };
+        let popped_fields = [context.allocate_register(), context.allocate_register()];
+        slice_pop_front_operation(
+            &mut context,
+            popped_vector,
+            pushed_vector,
+            &popped_fields.map(RegisterOrMemory::RegisterIndex),
+        );
+
+        context.return_instruction(&[
+            pushed_vector.pointer,
+            pushed_vector.size,
+            popped_vector.pointer,
+            popped_vector.size,
+            popped_fields[0],
+            popped_fields[1],
+        ]);
+
+        let vm = create_and_run_vm(
+            array.clone(),
+            vec![Value::from(0_usize), Value::from(5_usize), Value::from(6_usize)],
+            context,
+            arguments,
+            returns,
+        );
+
+        let expected_memory: Vec<Value> =
+            [1_usize, 2, 3, 4, 1, 2, 3, 4, 5, 6, 3, 4, 5, 6].map(Value::from).to_vec();
+        assert_eq!(vm.get_memory(), &expected_memory);
+
+        let expected_registers: Vec<Value> = [4_usize, 6, 10, 4, 1, 2].map(Value::from).to_vec();
+        for (index, expected) in expected_registers.into_iter().enumerate() {
+            assert_eq!(vm.get_registers().get(RegisterIndex::from(index)), expected);
+        }
+    }
 }
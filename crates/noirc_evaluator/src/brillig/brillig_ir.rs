@@ -6,6 +6,7 @@
 //! A similar paradigm can be seen with the `acir_ir` module.
 pub(crate) mod artifact;
 pub(crate) mod debug_show;
+pub(crate) mod peephole;
 pub(crate) mod registers;
 
 mod entry_point;
@@ -22,6 +23,7 @@ use acvm::{
     FieldElement,
 };
 use debug_show::DebugShow;
+use noirc_errors::Location;
 
 /// Integer arithmetic in Brillig is limited to 127 bit
 /// integers.
@@ -104,7 +106,12 @@ impl BrilligContext {
 
     /// Adds a brillig instruction to the brillig byte code
     pub(crate) fn push_opcode(&mut self, opcode: BrilligOpcode) {
-        self.obj.byte_code.push(opcode);
+        self.obj.push_opcode(opcode);
+    }
+
+    /// Sets the source code location of the opcodes added from now on
+    pub(crate) fn set_location(&mut self, location: Option<Location>) {
+        self.obj.set_location(location);
     }
 
     /// Returns the artifact
@@ -215,6 +222,58 @@ impl BrilligContext {
         self.deallocate_register(index_of_element_in_memory);
     }
 
+    /// Gets the variable in the array at index `index` and stores it in `destination`
+    ///
+    /// Each item of an array takes a single value in memory: nested arrays are stored as a
+    /// pointer to their items, and nested vectors as a pointer to a header holding the pointer to
+    /// their items followed by their size, laid out as by `store_variable_instruction`.
+    pub(crate) fn retrieve_variable_from_array(
+        &mut self,
+        array_ptr: RegisterIndex,
+        index: RegisterIndex,
+        destination: RegisterOrMemory,
+    ) {
+        match destination {
+            RegisterOrMemory::RegisterIndex(register_index) => {
+                self.array_get(array_ptr, index, register_index);
+            }
+            RegisterOrMemory::HeapArray(HeapArray { pointer, .. }) => {
+                self.array_get(array_ptr, index, pointer);
+            }
+            RegisterOrMemory::HeapVector(_) => {
+                let header_pointer = self.allocate_register();
+                self.array_get(array_ptr, index, header_pointer);
+                self.load_variable_instruction(destination, header_pointer);
+                self.deallocate_register(header_pointer);
+            }
+        }
+    }
+
+    /// Sets the item in the array at index `index` to the variable `value`, using the layout
+    /// described in `retrieve_variable_from_array`
+    pub(crate) fn store_variable_in_array(
+        &mut self,
+        array_ptr: RegisterIndex,
+        index: RegisterIndex,
+        value: RegisterOrMemory,
+    ) {
+        match value {
+            RegisterOrMemory::RegisterIndex(register_index) => {
+                self.array_set(array_ptr, index, register_index);
+            }
+            RegisterOrMemory::HeapArray(HeapArray { pointer, .. }) => {
+                self.array_set(array_ptr, index, pointer);
+            }
+            RegisterOrMemory::HeapVector(_) => {
+                let header_pointer = self.allocate_register();
+                self.allocate_variable_instruction(header_pointer);
+                self.store_variable_instruction(header_pointer, value);
+                self.array_set(array_ptr, index, header_pointer);
+                self.deallocate_register(header_pointer);
+            }
+        }
+    }
+
     /// Copies the values of an array pointed by source with length stored in `num_elements_register`
     /// Into the array pointed by destination
     pub(crate) fn copy_array_instruction(
@@ -349,8 +408,6 @@ impl BrilligContext {
     }
 
     /// Push a register to the deallocation list, ready for reuse.
-    /// TODO(AD): currently, register deallocation is only done with immediate values.
-    /// TODO(AD): See https://github.com/noir-lang/noir/issues/1720
     pub(crate) fn deallocate_register(&mut self, register_index: RegisterIndex) {
         self.registers.deallocate_register(register_index);
     }
@@ -1021,7 +1078,7 @@ pub(crate) mod tests {
         let mut entry_point_artifact =
             BrilligContext::new_entry_point_artifact(arguments, returns, "test".to_string());
         entry_point_artifact.link_with(&artifact);
-        entry_point_artifact.finish()
+        entry_point_artifact.finish().byte_code
     }
 
     pub(crate) fn create_and_run_vm(
@@ -1097,4 +1154,46 @@ pub(crate) mod tests {
         let status = vm.process_opcodes();
         assert_eq!(status, VMStatus::Finished);
     }
+
+    /// Test storing a vector as the item of an array, and reading it back
+    #[test]
+    fn test_brillig_ir_vector_in_array() {
+        let arguments = vec![BrilligParameter::Array(vec![BrilligParameter::Simple], 2)];
+        let returns = vec![BrilligParameter::Simple, BrilligParameter::Simple];
+
+        let mut context = create_context();
+        let vector = HeapVector {
+            pointer: context.allocate_register(),
+            size: context.make_constant(2_usize.into()),
+        };
+
+        // Store the vector as the only item of an array
+        let array_pointer = context.allocate_register();
+        context.allocate_fixed_length_array(array_pointer, 1);
+        let index = context.make_constant(0_usize.into());
+        context.store_variable_in_array(array_pointer, index, RegisterOrMemory::HeapVector(vector));
+
+        let retrieved_vector =
+            HeapVector { pointer: context.allocate_register(), size: context.allocate_register() };
+        context.retrieve_variable_from_array(
+            array_pointer,
+            index,
+            RegisterOrMemory::HeapVector(retrieved_vector),
+        );
+        context.return_instruction(&[retrieved_vector.pointer, retrieved_vector.size]);
+
+        let vm = create_and_run_vm(
+            vec![Value::from(10_usize), Value::from(20_usize)],
+            vec![Value::from(0_usize)],
+            context,
+            arguments,
+            returns,
+        );
+
+        // The array holds a pointer to the header of the vector, which holds its pointer and size
+        let expected_memory: Vec<Value> = [10_usize, 20, 3, 0, 2].map(Value::from).to_vec();
+        assert_eq!(vm.get_memory(), &expected_memory);
+        assert_eq!(vm.get_registers().get(RegisterIndex(0)), Value::from(0_usize));
+        assert_eq!(vm.get_registers().get(RegisterIndex(1)), Value::from(2_usize));
+    }
 }
@@ -1,5 +1,6 @@
 use acvm::acir::brillig::Opcode as BrilligOpcode;
-use std::collections::HashMap;
+use noirc_errors::Location;
+use std::collections::{BTreeMap, HashMap};
 
 /// Represents a parameter or a return value of a function.
 #[derive(Debug, Clone)]
@@ -26,6 +27,19 @@ pub(crate) struct BrilligArtifact {
     /// TODO: perhaps we should combine this with the `unresolved_jumps` field
     /// TODO: and have an enum which indicates whether the jump is internal or external
     unresolved_external_call_labels: Vec<(JumpInstructionPosition, UnresolvedJumpLocation)>,
+    /// The source code location of each opcode generated for an SSA instruction with a location.
+    locations: BTreeMap<OpcodeLocation, Location>,
+    /// The source code location of the SSA instruction currently being converted, if known
+    current_location: Option<Location>,
+}
+
+/// Bytecode which has been linked and had its jumps resolved, along with the position of each
+/// label in it and the source code location of each of its opcodes which has one.
+#[derive(Debug, Clone)]
+pub(crate) struct LinkedBytecode {
+    pub(crate) byte_code: Vec<BrilligOpcode>,
+    pub(crate) labels: HashMap<Label, OpcodeLocation>,
+    pub(crate) locations: BTreeMap<OpcodeLocation, Location>,
 }
 
 /// A pointer to a location in the opcode.
@@ -51,10 +65,11 @@ pub(crate) type JumpInstructionPosition = OpcodeLocation;
 pub(crate) type UnresolvedJumpLocation = Label;
 
 impl BrilligArtifact {
-    /// Resolves all jumps and generates the final bytecode
-    pub(crate) fn finish(mut self) -> Vec<BrilligOpcode> {
+    /// Resolves all jumps and generates the final bytecode, along with the position of each
+    /// label and the location of each opcode in it
+    pub(crate) fn finish(mut self) -> LinkedBytecode {
         self.resolve_jumps();
-        self.byte_code
+        LinkedBytecode { byte_code: self.byte_code, labels: self.labels, locations: self.locations }
     }
 
     /// Gets the first unresolved function call of this artifact.
@@ -74,12 +89,14 @@ impl BrilligArtifact {
         let mut byte_code = obj.byte_code.clone();
 
         // Replace STOP with RETURN because this is not the end of the program now.
-        let stop_position = byte_code
-            .iter()
-            .position(|opcode| matches!(opcode, BrilligOpcode::Stop))
-            .expect("Trying to link with a function that does not have a stop opcode");
-
-        byte_code[stop_position] = BrilligOpcode::Return;
+        // A function stops at each of its return blocks, of which there may be several.
+        let mut stop_opcodes =
+            byte_code.iter_mut().filter(|opcode| matches!(opcode, BrilligOpcode::Stop)).peekable();
+        assert!(
+            stop_opcodes.peek().is_some(),
+            "Trying to link with a function that does not have a stop opcode"
+        );
+        stop_opcodes.for_each(|opcode| *opcode = BrilligOpcode::Return);
 
         self.byte_code.append(&mut byte_code);
 
@@ -116,13 +133,25 @@ impl BrilligArtifact {
             self.unresolved_external_call_labels
                 .push((position_in_bytecode + offset, label_id.clone()));
         }
+
+        for (position_in_bytecode, location) in &obj.locations {
+            self.locations.insert(position_in_bytecode + offset, *location);
+        }
     }
 
     /// Adds a brillig instruction to the brillig byte code
     pub(crate) fn push_opcode(&mut self, opcode: BrilligOpcode) {
+        if let Some(location) = self.current_location {
+            self.locations.insert(self.index_of_next_opcode(), location);
+        }
         self.byte_code.push(opcode);
     }
 
+    /// Sets the source code location of the opcodes added from now on
+    pub(crate) fn set_location(&mut self, location: Option<Location>) {
+        self.current_location = location;
+    }
+
     /// Adds a unresolved jump to be fixed at the end of bytecode processing.
     pub(crate) fn add_unresolved_jump(
         &mut self,
@@ -1,9 +1,14 @@
 ///! This module contains functions for producing a higher level view disassembler of Brillig.
-use super::BrilligBinaryOp;
+use std::collections::{BTreeMap, HashMap};
+
+use super::{
+    artifact::{Label, OpcodeLocation},
+    BrilligBinaryOp,
+};
 use crate::brillig::brillig_ir::{ReservedRegisters, BRILLIG_MEMORY_ADDRESSING_BIT_SIZE};
 use acvm::acir::brillig::{
-    BinaryFieldOp, BinaryIntOp, BlackBoxOp, HeapArray, HeapVector, RegisterIndex, RegisterOrMemory,
-    Value,
+    BinaryFieldOp, BinaryIntOp, BlackBoxOp, HeapArray, HeapVector, Opcode as BrilligOpcode,
+    RegisterIndex, RegisterOrMemory, Value,
 };
 
 /// Trait for converting values into debug-friendly strings.
@@ -126,6 +131,92 @@ impl<T: DebugToString> DebugToString for [T] {
     }
 }
 
+impl DebugToString for BlackBoxOp {
+    fn debug_to_string(&self) -> String {
+        match self {
+            BlackBoxOp::Sha256 { message, output } => {
+                format!("SHA256 {} -> {}", message.debug_to_string(), output.debug_to_string())
+            }
+            BlackBoxOp::Keccak256 { message, output } => {
+                format!("KECCAK256 {} -> {}", message.debug_to_string(), output.debug_to_string())
+            }
+            BlackBoxOp::Blake2s { message, output } => {
+                format!("BLAKE2S {} -> {}", message.debug_to_string(), output.debug_to_string())
+            }
+            BlackBoxOp::HashToField128Security { message, output } => {
+                format!(
+                    "HASH_TO_FIELD_128_SECURITY {} -> {}",
+                    message.debug_to_string(),
+                    output.debug_to_string()
+                )
+            }
+            BlackBoxOp::EcdsaSecp256k1 {
+                hashed_msg,
+                public_key_x,
+                public_key_y,
+                signature,
+                result,
+            } => {
+                format!(
+                    "ECDSA_SECP256K1 {} {} {} {} -> {}",
+                    hashed_msg.debug_to_string(),
+                    public_key_x.debug_to_string(),
+                    public_key_y.debug_to_string(),
+                    signature.debug_to_string(),
+                    result.debug_to_string()
+                )
+            }
+            BlackBoxOp::EcdsaSecp256r1 {
+                hashed_msg,
+                public_key_x,
+                public_key_y,
+                signature,
+                result,
+            } => {
+                format!(
+                    "ECDSA_SECP256R1 {} {} {} {} -> {}",
+                    hashed_msg.debug_to_string(),
+                    public_key_x.debug_to_string(),
+                    public_key_y.debug_to_string(),
+                    signature.debug_to_string(),
+                    result.debug_to_string()
+                )
+            }
+            BlackBoxOp::FixedBaseScalarMul { input, result } => {
+                format!(
+                    "FIXED_BASE_SCALAR_MUL {} -> {}",
+                    input.debug_to_string(),
+                    result.debug_to_string()
+                )
+            }
+            BlackBoxOp::Pedersen { inputs, domain_separator, output } => {
+                format!(
+                    "PEDERSEN {} {} -> {}",
+                    inputs.debug_to_string(),
+                    domain_separator.debug_to_string(),
+                    output.debug_to_string()
+                )
+            }
+            BlackBoxOp::SchnorrVerify {
+                public_key_x,
+                public_key_y,
+                message,
+                signature,
+                result,
+            } => {
+                format!(
+                    "SCHNORR_VERIFY {} {} {} {} -> {}",
+                    public_key_x.debug_to_string(),
+                    public_key_y.debug_to_string(),
+                    message.debug_to_string(),
+                    signature.debug_to_string(),
+                    result.debug_to_string()
+                )
+            }
+        }
+    }
+}
+
 macro_rules! debug_println {
     ( $enable_debug:expr, $literal:expr ) => {
         if $enable_debug {
@@ -341,93 +432,7 @@ impl DebugShow {
 
     /// Debug function for black_box_op
     pub(crate) fn black_box_op_instruction(&self, op: BlackBoxOp) {
-        match op {
-            BlackBoxOp::Sha256 { message, output } => {
-                debug_println!(self.enable_debug_trace, "  SHA256 {} -> {}", message, output);
-            }
-            BlackBoxOp::Keccak256 { message, output } => {
-                debug_println!(self.enable_debug_trace, "  KECCAK256 {} -> {}", message, output);
-            }
-            BlackBoxOp::Blake2s { message, output } => {
-                debug_println!(self.enable_debug_trace, "  BLAKE2S {} -> {}", message, output);
-            }
-            BlackBoxOp::HashToField128Security { message, output } => {
-                debug_println!(
-                    self.enable_debug_trace,
-                    "  HASH_TO_FIELD_128_SECURITY {} -> {}",
-                    message,
-                    output
-                );
-            }
-            BlackBoxOp::EcdsaSecp256k1 {
-                hashed_msg,
-                public_key_x,
-                public_key_y,
-                signature,
-                result,
-            } => {
-                debug_println!(
-                    self.enable_debug_trace,
-                    "  ECDSA_SECP256K1 {} {} {} {} -> {}",
-                    hashed_msg,
-                    public_key_x,
-                    public_key_y,
-                    signature,
-                    result
-                );
-            }
-            BlackBoxOp::EcdsaSecp256r1 {
-                hashed_msg,
-                public_key_x,
-                public_key_y,
-                signature,
-                result,
-            } => {
-                debug_println!(
-                    self.enable_debug_trace,
-                    "  ECDSA_SECP256R1 {} {} {} {} -> {}",
-                    hashed_msg,
-                    public_key_x,
-                    public_key_y,
-                    signature,
-                    result
-                );
-            }
-            BlackBoxOp::FixedBaseScalarMul { input, result } => {
-                debug_println!(
-                    self.enable_debug_trace,
-                    "  FIXED_BASE_SCALAR_MUL {} -> {}",
-                    input,
-                    result
-                );
-            }
-            BlackBoxOp::Pedersen { inputs, domain_separator, output } => {
-                debug_println!(
-                    self.enable_debug_trace,
-                    "  PEDERSEN {} {} -> {}",
-                    inputs,
-                    domain_separator,
-                    output
-                );
-            }
-            BlackBoxOp::SchnorrVerify {
-                public_key_x,
-                public_key_y,
-                message,
-                signature,
-                result,
-            } => {
-                debug_println!(
-                    self.enable_debug_trace,
-                    "  SCHNORR_VERIFY {} {} {} {} -> {}",
-                    public_key_x,
-                    public_key_y,
-                    message,
-                    signature,
-                    result
-                );
-            }
-        }
+        debug_println!(self.enable_debug_trace, "  {}", op);
     }
 
     /// Debug function for cast_instruction
@@ -435,3 +440,165 @@ impl DebugShow {
         debug_println!(self.enable_debug_trace, "  CALL {}", func_label);
     }
 }
+
+/// Returns a listing of linked bytecode, one opcode per line, in which every jump and call
+/// target is labeled.
+///
+/// A target is named after the labels at its position, with function labels replaced by the
+/// name of the function given in `function_names`. Targets without a label, e.g. those of jumps
+/// generated within an instruction, are named after their position.
+pub(crate) fn disassemble(
+    byte_code: &[BrilligOpcode],
+    labels: &HashMap<Label, OpcodeLocation>,
+    function_names: &HashMap<Label, String>,
+) -> String {
+    let mut names: BTreeMap<OpcodeLocation, Vec<String>> = BTreeMap::new();
+    for (label, position) in labels {
+        let name = function_names.get(label).cloned().unwrap_or_else(|| label.clone());
+        names.entry(*position).or_default().push(name);
+    }
+    for opcode in byte_code {
+        if let Some(location) = jump_target(opcode) {
+            names.entry(location).or_insert_with(|| vec![format!("L{location}")]);
+        }
+    }
+    for position_names in names.values_mut() {
+        // Blocks are labeled after their function, so function names sort first
+        position_names.sort_by_key(|name| (name.contains('-'), name.clone()));
+        // The entry block of a function starts with the function, and is named after it
+        if position_names.len() > 1 {
+            position_names.retain(|name| !name.ends_with("-b0"));
+        }
+    }
+
+    let target_name = |location: OpcodeLocation| names[&location][0].clone();
+    let mut listing = String::new();
+    for (index, opcode) in byte_code.iter().enumerate() {
+        for name in names.get(&index).into_iter().flatten() {
+            listing.push_str(&format!("{name}:\n"));
+        }
+        listing.push_str(&format!("{index:>6}  {}\n", opcode_to_string(opcode, target_name)));
+    }
+    listing
+}
+
+fn jump_target(opcode: &BrilligOpcode) -> Option<OpcodeLocation> {
+    match opcode {
+        BrilligOpcode::Jump { location }
+        | BrilligOpcode::JumpIf { location, .. }
+        | BrilligOpcode::JumpIfNot { location, .. }
+        | BrilligOpcode::Call { location } => Some(*location),
+        _ => None,
+    }
+}
+
+fn opcode_to_string(
+    opcode: &BrilligOpcode,
+    target_name: impl Fn(OpcodeLocation) -> String,
+) -> String {
+    match opcode {
+        BrilligOpcode::BinaryFieldOp { destination, op, lhs, rhs } => format!(
+            "{} = {} {} {}",
+            destination.debug_to_string(),
+            lhs.debug_to_string(),
+            op.debug_to_string(),
+            rhs.debug_to_string()
+        ),
+        BrilligOpcode::BinaryIntOp { destination, op, bit_size, lhs, rhs } => format!(
+            "{} = {} {} {}",
+            destination.debug_to_string(),
+            lhs.debug_to_string(),
+            BrilligBinaryOp::Integer { op: *op, bit_size: *bit_size }.debug_to_string(),
+            rhs.debug_to_string()
+        ),
+        BrilligOpcode::JumpIfNot { condition, location } => {
+            format!("JUMP_IF_NOT {} TO {}", condition.debug_to_string(), target_name(*location))
+        }
+        BrilligOpcode::JumpIf { condition, location } => {
+            format!("JUMP_IF {} TO {}", condition.debug_to_string(), target_name(*location))
+        }
+        BrilligOpcode::Jump { location } => format!("JUMP_TO {}", target_name(*location)),
+        BrilligOpcode::Call { location } => format!("CALL {}", target_name(*location)),
+        // Constants may not fit in a usize, so the whole field element is shown
+        BrilligOpcode::Const { destination, value } => {
+            format!("CONST {} = {}", destination.debug_to_string(), value.to_field())
+        }
+        BrilligOpcode::Return => "RETURN".into(),
+        BrilligOpcode::ForeignCall { function, destinations, inputs } => format!(
+            "FOREIGN_CALL {} ({}) => {}",
+            function,
+            inputs.debug_to_string(),
+            destinations.debug_to_string()
+        ),
+        BrilligOpcode::Mov { destination, source } => {
+            format!("MOV {}, {}", destination.debug_to_string(), source.debug_to_string())
+        }
+        BrilligOpcode::Load { destination, source_pointer } => {
+            format!(
+                "LOAD {} = *{}",
+                destination.debug_to_string(),
+                source_pointer.debug_to_string()
+            )
+        }
+        BrilligOpcode::Store { destination_pointer, source } => {
+            format!(
+                "STORE *{} = {}",
+                destination_pointer.debug_to_string(),
+                source.debug_to_string()
+            )
+        }
+        BrilligOpcode::BlackBox(op) => op.debug_to_string(),
+        BrilligOpcode::Trap => "TRAP".into(),
+        BrilligOpcode::Stop => "STOP".into(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use acvm::acir::brillig::{BinaryFieldOp, Opcode as BrilligOpcode, RegisterIndex, Value};
+
+    use super::disassemble;
+
+    #[test]
+    fn labels_jump_targets_with_function_names() {
+        let r2 = RegisterIndex::from(2);
+        let r3 = RegisterIndex::from(3);
+        let byte_code = vec![
+            BrilligOpcode::Call { location: 2 },
+            BrilligOpcode::Stop,
+            // 2: the called function
+            BrilligOpcode::Const { destination: r2, value: Value::from(1_usize) },
+            BrilligOpcode::BinaryFieldOp {
+                destination: r3,
+                op: BinaryFieldOp::Add,
+                lhs: r2,
+                rhs: r2,
+            },
+            BrilligOpcode::JumpIf { condition: r3, location: 6 },
+            BrilligOpcode::Trap,
+            // 6: an unlabeled target
+            BrilligOpcode::Return,
+        ];
+        let labels = HashMap::from([
+            ("f1".to_string(), 2),
+            ("f1-b0".to_string(), 2),
+            ("f1-b1".to_string(), 4),
+        ]);
+        let function_names = HashMap::from([("f1".to_string(), "foo".to_string())]);
+
+        let expected = "     0  CALL foo
+     1  STOP
+foo:
+     2  CONST R2 = 1
+     3  R3 = R2 f+ R2
+f1-b1:
+     4  JUMP_IF R3 TO L6
+     5  TRAP
+L6:
+     6  RETURN
+";
+        assert_eq!(disassemble(&byte_code, &labels, &function_names), expected);
+    }
+}
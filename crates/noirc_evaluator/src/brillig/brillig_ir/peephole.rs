@@ -0,0 +1,275 @@
+//! Peephole optimizations over linked Brillig bytecode.
+//!
+//! Brillig generation favors simple, local translations of each instruction, which leaves behind
+//! opcodes that a later look at the bytecode can show to be redundant:
+//! - jumps to another jump, which are retargeted to the final destination,
+//! - jumps to the next opcode and moves of a register into itself, which are removed,
+//! - moves which undo the move right before them, which are removed,
+//! - field arithmetic over registers holding known constants, which is replaced by a constant.
+//!
+//! Brillig has no push or pop opcodes: registers are saved to memory around calls, and the
+//! stores saving them are never directly followed by the loads restoring them.
+//!
+//! Registers are only known to hold a constant between two jump targets, since any of the jumps
+//! to a target may come from a point where the register holds another value.
+use std::collections::{HashMap, HashSet};
+
+use acvm::{
+    acir::brillig::{BinaryFieldOp, Opcode as BrilligOpcode, RegisterIndex, Value},
+    FieldElement,
+};
+
+use super::artifact::LinkedBytecode;
+
+/// The number of opcodes before and after optimizing some bytecode
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub(crate) struct PeepholeStatistics {
+    pub(crate) opcodes_before: usize,
+    pub(crate) opcodes_after: usize,
+}
+
+/// Optimizes linked bytecode whose jumps have been resolved, returning the optimized bytecode
+/// along with the number of opcodes before and after.
+///
+/// The positions of the labels are moved along with the opcodes they point to, and the locations
+/// of the opcodes removed are dropped.
+pub(crate) fn optimize(linked: LinkedBytecode) -> (LinkedBytecode, PeepholeStatistics) {
+    let LinkedBytecode { mut byte_code, mut labels, locations } = linked;
+    let opcodes_before = byte_code.len();
+
+    retarget_jump_chains(&mut byte_code);
+    let jump_targets: HashSet<usize> = byte_code.iter().filter_map(jump_location).collect();
+
+    let mut removed = vec![false; byte_code.len()];
+    let mut constants: HashMap<RegisterIndex, Value> = HashMap::new();
+    for index in 0..byte_code.len() {
+        if jump_targets.contains(&index) {
+            constants.clear();
+        }
+
+        let undoes_previous_move = index > 0
+            && !removed[index - 1]
+            && !jump_targets.contains(&index)
+            && matches!(
+                (&byte_code[index - 1], &byte_code[index]),
+                (
+                    BrilligOpcode::Mov { destination, source },
+                    BrilligOpcode::Mov { destination: undone_destination, source: undone_source },
+                ) if destination == undone_source && source == undone_destination
+            );
+
+        match &byte_code[index] {
+            BrilligOpcode::Mov { destination, source } if destination == source => {
+                removed[index] = true;
+            }
+            BrilligOpcode::Mov { .. } if undoes_previous_move => removed[index] = true,
+            BrilligOpcode::Jump { location } if *location == index + 1 => removed[index] = true,
+            BrilligOpcode::Mov { destination, source } => {
+                match constants.get(source).copied() {
+                    Some(value) => constants.insert(*destination, value),
+                    None => constants.remove(destination),
+                };
+            }
+            BrilligOpcode::Const { destination, value } => {
+                constants.insert(*destination, *value);
+            }
+            BrilligOpcode::BinaryFieldOp { destination, op, lhs, rhs } => {
+                let destination = *destination;
+                let folded = match (constants.get(lhs), constants.get(rhs)) {
+                    (Some(lhs), Some(rhs)) => fold_field_op(op, lhs.to_field(), rhs.to_field()),
+                    _ => None,
+                };
+                match folded {
+                    Some(value) => {
+                        byte_code[index] = BrilligOpcode::Const { destination, value };
+                        constants.insert(destination, value);
+                    }
+                    None => {
+                        constants.remove(&destination);
+                    }
+                }
+            }
+            BrilligOpcode::BinaryIntOp { destination, .. }
+            | BrilligOpcode::Load { destination, .. } => {
+                constants.remove(destination);
+            }
+            BrilligOpcode::Store { .. }
+            | BrilligOpcode::Jump { .. }
+            | BrilligOpcode::JumpIf { .. }
+            | BrilligOpcode::JumpIfNot { .. }
+            | BrilligOpcode::Return
+            | BrilligOpcode::Trap
+            | BrilligOpcode::Stop => (),
+            // Calls may write to any register, and the outputs of foreign calls and black box
+            // functions may be held in several registers
+            BrilligOpcode::Call { .. }
+            | BrilligOpcode::ForeignCall { .. }
+            | BrilligOpcode::BlackBox(_) => constants.clear(),
+        }
+    }
+
+    // The new index of each opcode, or of the next opcode kept if it was removed
+    let mut new_indices = Vec::with_capacity(byte_code.len() + 1);
+    let mut kept = 0;
+    for is_removed in &removed {
+        new_indices.push(kept);
+        kept += usize::from(!is_removed);
+    }
+    new_indices.push(kept);
+
+    let locations = locations
+        .into_iter()
+        .filter(|(position, _)| !removed[*position])
+        .map(|(position, location)| (new_indices[position], location))
+        .collect();
+    let mut optimized: Vec<BrilligOpcode> = byte_code
+        .into_iter()
+        .zip(removed)
+        .filter_map(|(opcode, is_removed)| (!is_removed).then_some(opcode))
+        .collect();
+    for opcode in &mut optimized {
+        if let Some(location) = jump_location_mut(opcode) {
+            *location = new_indices[*location];
+        }
+    }
+    for position in labels.values_mut() {
+        *position = new_indices[*position];
+    }
+
+    let statistics = PeepholeStatistics { opcodes_before, opcodes_after: optimized.len() };
+    (LinkedBytecode { byte_code: optimized, labels, locations }, statistics)
+}
+
+/// Retargets each jump to an unconditional jump so that it jumps to the final destination
+/// instead. Cycles of unconditional jumps are left as they are.
+fn retarget_jump_chains(byte_code: &mut [BrilligOpcode]) {
+    for index in 0..byte_code.len() {
+        let mut target = match jump_location(&byte_code[index]) {
+            Some(target) if !matches!(byte_code[index], BrilligOpcode::Call { .. }) => target,
+            _ => continue,
+        };
+
+        let mut visited = HashSet::from([index]);
+        while let Some(BrilligOpcode::Jump { location }) = byte_code.get(target) {
+            if !visited.insert(target) {
+                break;
+            }
+            target = *location;
+        }
+
+        if let Some(location) = jump_location_mut(&mut byte_code[index]) {
+            *location = target;
+        }
+    }
+}
+
+/// Returns the location an opcode may jump to, if it is a jump or a call
+fn jump_location(opcode: &BrilligOpcode) -> Option<usize> {
+    match opcode {
+        BrilligOpcode::Jump { location }
+        | BrilligOpcode::JumpIf { location, .. }
+        | BrilligOpcode::JumpIfNot { location, .. }
+        | BrilligOpcode::Call { location } => Some(*location),
+        _ => None,
+    }
+}
+
+fn jump_location_mut(opcode: &mut BrilligOpcode) -> Option<&mut usize> {
+    match opcode {
+        BrilligOpcode::Jump { location }
+        | BrilligOpcode::JumpIf { location, .. }
+        | BrilligOpcode::JumpIfNot { location, .. }
+        | BrilligOpcode::Call { location } => Some(location),
+        _ => None,
+    }
+}
+
+/// Returns the result of a field operation over two constants, unless it is a division, which
+/// is left to fail at runtime when dividing by zero.
+fn fold_field_op(op: &BinaryFieldOp, lhs: FieldElement, rhs: FieldElement) -> Option<Value> {
+    match op {
+        BinaryFieldOp::Add => Some((lhs + rhs).into()),
+        BinaryFieldOp::Sub => Some((lhs - rhs).into()),
+        BinaryFieldOp::Mul => Some((lhs * rhs).into()),
+        BinaryFieldOp::Equals => Some(FieldElement::from(u128::from(lhs == rhs)).into()),
+        BinaryFieldOp::Div => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::{BTreeMap, HashMap};
+
+    use acvm::acir::brillig::{BinaryFieldOp, Opcode as BrilligOpcode, RegisterIndex, Value};
+    use noirc_errors::{Location, Span};
+
+    use super::{optimize, PeepholeStatistics};
+    use crate::brillig::brillig_ir::artifact::LinkedBytecode;
+
+    #[test]
+    fn removes_redundant_moves_and_jumps() {
+        let r0 = RegisterIndex::from(0);
+        let r1 = RegisterIndex::from(1);
+        let byte_code = vec![
+            // 0: jumps to a jump to 4
+            BrilligOpcode::Jump { location: 3 },
+            BrilligOpcode::Mov { destination: r0, source: r0 },
+            BrilligOpcode::Trap,
+            BrilligOpcode::Jump { location: 4 },
+            // 4
+            BrilligOpcode::Mov { destination: r0, source: r1 },
+            BrilligOpcode::Mov { destination: r1, source: r0 },
+            BrilligOpcode::Stop,
+        ];
+
+        let labels = HashMap::from([("f0-b1".to_string(), 4)]);
+        let location = Location::new(Span::single_char(0), Default::default());
+        let locations = BTreeMap::from([(1, location), (4, location)]);
+        let (optimized, statistics) = optimize(LinkedBytecode { byte_code, labels, locations });
+        let expected = vec![
+            BrilligOpcode::Jump { location: 2 },
+            BrilligOpcode::Trap,
+            BrilligOpcode::Mov { destination: r0, source: r1 },
+            BrilligOpcode::Stop,
+        ];
+        assert_eq!(optimized.byte_code, expected);
+        assert_eq!(statistics, PeepholeStatistics { opcodes_before: 7, opcodes_after: 4 });
+        assert_eq!(optimized.labels["f0-b1"], 2);
+        assert_eq!(optimized.locations, BTreeMap::from([(2, location)]));
+    }
+
+    #[test]
+    fn folds_constant_field_arithmetic() {
+        let r0 = RegisterIndex::from(0);
+        let r1 = RegisterIndex::from(1);
+        let r2 = RegisterIndex::from(2);
+        let byte_code = vec![
+            BrilligOpcode::Const { destination: r0, value: Value::from(2_usize) },
+            BrilligOpcode::Const { destination: r1, value: Value::from(3_usize) },
+            BrilligOpcode::BinaryFieldOp {
+                destination: r2,
+                op: BinaryFieldOp::Mul,
+                lhs: r0,
+                rhs: r1,
+            },
+            // 3: a jump target, where r0 may hold any value
+            BrilligOpcode::BinaryFieldOp {
+                destination: r2,
+                op: BinaryFieldOp::Add,
+                lhs: r0,
+                rhs: r1,
+            },
+            BrilligOpcode::JumpIf { condition: r2, location: 3 },
+            BrilligOpcode::Stop,
+        ];
+
+        let linked =
+            LinkedBytecode { byte_code, labels: HashMap::new(), locations: BTreeMap::new() };
+        let (LinkedBytecode { byte_code: optimized, .. }, _) = optimize(linked);
+        assert_eq!(
+            optimized[2],
+            BrilligOpcode::Const { destination: r2, value: Value::from(6_usize) }
+        );
+        assert!(matches!(optimized[3], BrilligOpcode::BinaryFieldOp { .. }));
+    }
+}
@@ -7,9 +7,8 @@ use super::ReservedRegisters;
 ///
 /// Each has a stack base pointer from which all stack allocations can be offset.
 pub(crate) struct BrilligRegistersContext {
-    /// A free-list of registers that have been deallocated and can be used again.
-    /// TODO(AD): currently, register deallocation is only done with immediate values.
-    /// TODO(AD): See https://github.com/noir-lang/noir/issues/1720
+    /// A free-list of registers that have been deallocated and can be used again, either
+    /// temporary registers or those of SSA variables which are no longer live.
     deallocated_registers: Vec<RegisterIndex>,
     /// A usize indicating the next un-used register.
     next_free_register_index: usize,
@@ -58,8 +57,6 @@ impl BrilligRegistersContext {
     }
 
     /// Push a register to the deallocation list, ready for reuse.
-    /// TODO(AD): currently, register deallocation is only done with immediate values.
-    /// TODO(AD): See https://github.com/noir-lang/noir/issues/1720
     pub(crate) fn deallocate_register(&mut self, register_index: RegisterIndex) {
         assert!(!self.deallocated_registers.contains(&register_index));
         self.deallocated_registers.push(register_index);
@@ -3,8 +3,12 @@ pub(crate) mod brillig_ir;
 
 use self::{
     brillig_gen::{brillig_fn::FunctionContext, convert_ssa_function},
-    brillig_ir::artifact::{BrilligArtifact, Label},
+    brillig_ir::{
+        artifact::{BrilligArtifact, Label},
+        BrilligContext,
+    },
 };
+use crate::errors::RuntimeError;
 use crate::ssa_refactor::{
     ir::{
         function::{Function, FunctionId, RuntimeType},
@@ -20,13 +24,52 @@ use std::collections::{HashMap, HashSet};
 pub struct Brillig {
     /// Maps SSA function labels to their brillig artifact
     ssa_function_to_brillig: HashMap<FunctionId, BrilligArtifact>,
+    /// Maps the labels of the compiled functions to their names, for disassembly
+    function_names: HashMap<Label, String>,
+    /// Whether the linked bytecode of each Brillig call should be printed
+    pub(crate) enable_debug_trace: bool,
 }
 
 impl Brillig {
     /// Compiles a function into brillig and store the compilation artifacts
-    pub(crate) fn compile(&mut self, func: &Function, enable_debug_trace: bool) {
-        let obj = convert_ssa_function(func, enable_debug_trace);
+    pub(crate) fn compile(
+        &mut self,
+        func: &Function,
+        enable_debug_trace: bool,
+    ) -> Result<(), RuntimeError> {
+        let obj = convert_ssa_function(func, enable_debug_trace)?;
         self.ssa_function_to_brillig.insert(func.id(), obj);
+        self.function_names.insert(
+            FunctionContext::function_id_to_function_label(func.id()),
+            func.name().to_owned(),
+        );
+        Ok(())
+    }
+
+    /// Returns the names of the compiled functions by their label
+    pub(crate) fn function_names(&self) -> &HashMap<Label, String> {
+        &self.function_names
+    }
+
+    /// Creates the entry point of a call into the given function, linked with the artifacts of
+    /// all the functions it may call.
+    ///
+    /// A function is linked once however many times it is called, so recursive functions simply
+    /// call back into their own bytecode, with each call saving the registers of the caller.
+    pub(crate) fn link_entry_point(&self, func: &Function) -> BrilligArtifact {
+        let mut entry_point = BrilligContext::new_entry_point_artifact(
+            FunctionContext::parameters(func),
+            FunctionContext::return_values(func),
+            FunctionContext::function_id_to_function_label(func.id()),
+        );
+        // Link the entry point with all dependencies
+        while let Some(unresolved_fn_label) = entry_point.first_unresolved_function_call() {
+            let artifact = self
+                .find_by_function_label(unresolved_fn_label.clone())
+                .unwrap_or_else(|| panic!("Cannot find linked fn {unresolved_fn_label}"));
+            entry_point.link_with(artifact);
+        }
+        entry_point
     }
 
     /// Finds a brillig function artifact by its function label
@@ -50,7 +93,7 @@ impl std::ops::Index<FunctionId> for Brillig {
 
 impl Ssa {
     /// Compile to brillig brillig functions and ACIR functions reachable from them
-    pub(crate) fn to_brillig(&self, enable_debug_trace: bool) -> Brillig {
+    pub(crate) fn to_brillig(&self, enable_debug_trace: bool) -> Result<Brillig, RuntimeError> {
         // Collect all the function ids that are reachable from brillig
         // That means all the functions marked as brillig and ACIR functions called by them
         let mut brillig_reachable_function_ids: HashSet<FunctionId> = HashSet::new();
@@ -93,12 +136,12 @@ impl Ssa {
             }
         }
 
-        let mut brillig = Brillig::default();
+        let mut brillig = Brillig { enable_debug_trace, ..Brillig::default() };
         for brillig_function_id in brillig_reachable_function_ids {
             let func = &self.functions[&brillig_function_id];
-            brillig.compile(func, enable_debug_trace);
+            brillig.compile(func, enable_debug_trace)?;
         }
 
-        brillig
+        Ok(brillig)
     }
 }
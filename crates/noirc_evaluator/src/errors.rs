@@ -1,6 +1,10 @@
 use noirc_errors::{CustomDiagnostic as Diagnostic, FileDiagnostic, Location};
 use thiserror::Error;
 
+use crate::ssa_refactor::{
+    ir::verifier::SsaVerificationError, OpcodeBudgetExceeded, SsaSerializationError,
+};
+
 #[derive(Debug)]
 pub struct RuntimeError {
     pub location: Option<Location>,
@@ -38,9 +42,21 @@ impl From<RuntimeErrorKind> for RuntimeError {
     }
 }
 
+impl From<SsaVerificationError> for RuntimeError {
+    fn from(error: SsaVerificationError) -> RuntimeError {
+        RuntimeErrorKind::InvalidSsa(error.to_string()).into()
+    }
+}
+
+impl From<SsaSerializationError> for RuntimeError {
+    fn from(error: SsaSerializationError) -> RuntimeError {
+        RuntimeErrorKind::SsaCacheFailed(error.to_string()).into()
+    }
+}
+
 impl From<RuntimeError> for FileDiagnostic {
     fn from(err: RuntimeError) -> Self {
-        let file_id = err.location.map(|loc| loc.file).unwrap();
+        let file_id = err.location.map(|loc| loc.file).unwrap_or_default();
         FileDiagnostic { file_id, diagnostic: err.into() }
     }
 }
@@ -96,8 +112,40 @@ pub enum RuntimeErrorKind {
     #[error("unimplemented")]
     Unimplemented(String),
 
+    #[error("Invalid input to the {name} black box function: {reason}")]
+    InvalidBlackBoxInput { name: String, reason: String },
+
     #[error("Unsupported operation error")]
     UnsupportedOp { op: String, first_type: String, second_type: String },
+
+    #[error("ICE: {0}")]
+    InvalidSsa(String),
+
+    #[error("Could not determine the {bound} of the loop range at compile time")]
+    UnknownLoopBound { bound: &'static str },
+
+    #[error(
+        "Unrolling loops would create more than the maximum of {max_instructions} instructions"
+    )]
+    UnrollLimitExceeded { max_instructions: usize },
+
+    #[error("While loops are only supported in unconstrained functions")]
+    UnboundedLoop,
+
+    #[error("recursive call cycle: {cycle}")]
+    RecursiveCallCycle { cycle: String },
+
+    #[error("Attempted to recur more than {limit} times during function inlining")]
+    RecursionLimitExceeded { limit: u32 },
+
+    #[error("{0}")]
+    OpcodeBudgetExceeded(OpcodeBudgetExceeded),
+
+    #[error("{name} cannot be called from unconstrained functions")]
+    UnsupportedInUnconstrained { name: String },
+
+    #[error("Failed to cache SSA: {0}")]
+    SsaCacheFailed(String),
 }
 
 impl From<RuntimeError> for Diagnostic {
@@ -124,6 +172,7 @@ impl From<RuntimeError> for Diagnostic {
             | RuntimeErrorKind::DefaultWitnesses(_)
             | RuntimeErrorKind::CannotConvertSignedIntoField(_)
             | RuntimeErrorKind::IndexOutOfBounds { .. }
+            | RuntimeErrorKind::InvalidBlackBoxInput { .. }
             | RuntimeErrorKind::PrivateAbiInput => {
                 Diagnostic::simple_error("".to_owned(), error.kind.to_string(), span)
             }
@@ -141,6 +190,38 @@ impl From<RuntimeError> for Diagnostic {
                 Diagnostic::from_message(&error.kind.to_string())
             }
             RuntimeErrorKind::Unimplemented(message) => Diagnostic::from_message(message),
+            RuntimeErrorKind::InvalidSsa(_) | RuntimeErrorKind::SsaCacheFailed(_) => {
+                Diagnostic::from_message(&error.kind.to_string())
+            }
+            RuntimeErrorKind::UnknownLoopBound { .. } => Diagnostic::simple_error(
+                error.kind.to_string(),
+                "loops in constrained functions must have constant bounds. Consider moving this loop into an unconstrained function".to_owned(),
+                span,
+            ),
+            RuntimeErrorKind::UnrollLimitExceeded { .. } => Diagnostic::simple_error(
+                error.kind.to_string(),
+                "consider raising the limit or moving this loop into an unconstrained function".to_owned(),
+                span,
+            ),
+            RuntimeErrorKind::UnboundedLoop => Diagnostic::simple_error(
+                error.kind.to_string(),
+                "constrained functions cannot loop a number of times only known at runtime. Consider moving this loop into an unconstrained function".to_owned(),
+                span,
+            ),
+            RuntimeErrorKind::RecursiveCallCycle { .. } => Diagnostic::simple_error(
+                error.kind.to_string(),
+                "recursion in constrained functions must terminate at compile time. Consider moving this call into an unconstrained function".to_owned(),
+                span,
+            ),
+            RuntimeErrorKind::RecursionLimitExceeded { .. } => {
+                Diagnostic::simple_error("".to_owned(), error.kind.to_string(), span)
+            }
+            RuntimeErrorKind::OpcodeBudgetExceeded(exceeded) => exceeded.diagnostic(true),
+            RuntimeErrorKind::UnsupportedInUnconstrained { .. } => Diagnostic::simple_error(
+                error.kind.to_string(),
+                "brillig has no implementation of this function. Consider moving this call into a constrained function".to_owned(),
+                span,
+            ),
             RuntimeErrorKind::FunctionNonMainContext { func_name } => Diagnostic::simple_error(
                 "cannot call function outside of main".to_owned(),
                 format!("function {func_name} can only be called in main"),
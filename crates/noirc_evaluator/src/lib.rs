@@ -11,4 +11,9 @@ pub mod ssa_refactor;
 
 pub mod brillig;
 
-pub use ssa_refactor::create_circuit;
+pub use ssa_refactor::{
+    create_circuit,
+    pass_manager::{Pass, PassManager, PassManagerError, SsaOptions, StatisticsFormat},
+    ssa_gen::Ssa,
+    BackendCapabilities, GateCounts, OpcodeBudgetExceeded, SsaSerializationError,
+};
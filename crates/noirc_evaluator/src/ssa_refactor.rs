@@ -9,7 +9,7 @@
 
 use std::collections::BTreeSet;
 
-use crate::errors::RuntimeError;
+use crate::errors::{RuntimeError, RuntimeErrorKind};
 use acvm::acir::{
     circuit::{Circuit, PublicInputs},
     native_types::Witness,
@@ -21,66 +21,124 @@ use noirc_abi::Abi;
 
 use noirc_frontend::monomorphization::ast::Program;
 
-use self::{abi_gen::gen_abi, acir_gen::GeneratedAcir, ir::function::RuntimeType, ssa_gen::Ssa};
+use self::{
+    abi_gen::gen_abi,
+    acir_gen::GeneratedAcir,
+    gate_counts::{GateCounts, OpcodeBudgetExceeded},
+    ir::function::RuntimeType,
+    pass_manager::{SsaOptions, StatisticsFormat},
+    pass_statistics::PassStatistics,
+    ssa_gen::{FunctionCache, Ssa},
+};
 
 mod abi_gen;
 mod acir_gen;
+mod backend_capabilities;
+mod gate_counts;
+mod interpreter;
 pub mod ir;
 mod opt;
+pub mod pass_manager;
+mod pass_statistics;
 mod ssa_builder;
 pub mod ssa_gen;
 
+pub use backend_capabilities::BackendCapabilities;
+pub use gate_counts::{GateCounts, OpcodeBudgetExceeded};
+pub use ir::serialization::SsaSerializationError;
+
 /// Optimize the given program by converting it into SSA
 /// form and performing optimizations there. When finished,
-/// convert the final SSA into ACIR and return it.
+/// convert the final SSA into ACIR and return it, along with the number of opcodes generated by
+/// each source location and function.
 pub(crate) fn optimize_into_acir(
     program: Program,
-    allow_log_ops: bool,
-    print_ssa_passes: bool,
-    print_brillig_trace: bool,
-) -> Result<GeneratedAcir, RuntimeError> {
+    options: &SsaOptions,
+    capabilities: &BackendCapabilities,
+) -> Result<(GeneratedAcir, GateCounts), RuntimeError> {
     let abi_distinctness = program.return_distinctness;
-    let mut ssa = ssa_gen::generate_ssa(program)
-        .print(print_ssa_passes, "Initial SSA:")
-        .defunctionalize()
-        .print(print_ssa_passes, "After Defunctionalization:");
+    let print_initial_ssa = options.print_ssa_passes || options.print_ssa_diffs;
+    let cache = options.ssa_cache_directory.as_deref().map(FunctionCache::new);
+    let ssa = ssa_gen::generate_ssa(program, cache.as_ref())?;
+    let ssa = ssa.print(print_initial_ssa, "Initial SSA:");
+    if options.verify_ssa {
+        ssa.verify()?;
+    }
+    let function_locations = ssa.function_locations();
+    let mut statistics = Vec::new();
+    let mut ssa = options.brillig_passes.run(ssa, options, "brillig", &mut statistics)?;
 
-    let brillig = ssa.to_brillig(print_brillig_trace);
+    let brillig = ssa.to_brillig(options.print_brillig_trace)?;
     if let RuntimeType::Acir = ssa.main().runtime() {
-        ssa = ssa
-            .inline_functions()
-            .print(print_ssa_passes, "After Inlining:")
-            .unroll_loops()
-            .print(print_ssa_passes, "After Unrolling:")
-            .simplify_cfg()
-            .print(print_ssa_passes, "After Simplifying:")
-            .flatten_cfg()
-            .print(print_ssa_passes, "After Flattening:")
-            .mem2reg()
-            .print(print_ssa_passes, "After Mem2Reg:")
-            .fold_constants()
-            .print(print_ssa_passes, "After Constant Folding:")
-            .dead_instruction_elimination()
-            .print(print_ssa_passes, "After Dead Instruction Elimination:");
+        ssa = options.acir_passes.run(ssa, options, "acir", &mut statistics)?;
+    }
+    if let Some(format) = options.pass_statistics {
+        print_statistics(&statistics, format);
+    }
+    let acir = ssa.into_acir(
+        brillig,
+        abi_distinctness,
+        capabilities,
+        options.record_opcode_provenance,
+        options.record_brillig_locations,
+    )?;
+    let gate_counts = GateCounts::new(&acir, &function_locations);
+    Ok((acir, gate_counts))
+}
+
+fn print_statistics(statistics: &[PassStatistics], format: StatisticsFormat) {
+    match format {
+        StatisticsFormat::Table => {
+            println!("SSA pass statistics:\n{}\n", pass_statistics::display_table(statistics));
+        }
+        StatisticsFormat::Json => println!("{}", pass_statistics::to_json(statistics)),
     }
-    ssa.into_acir(brillig, abi_distinctness, allow_log_ops)
 }
 
 /// Compiles the Program into ACIR and applies optimizations to the arithmetic gates
 /// This is analogous to `ssa:create_circuit` and this method is called when one wants
 /// to use the new ssa module to process Noir code.
-// TODO: This no longer needs to return a result, but it is kept to match the signature of `create_circuit`
+///
+/// If the circuit exceeds the opcode budget of the options, this is returned alongside the
+/// circuit, or as an error if the budget is strict.
 pub fn create_circuit(
     program: Program,
-    enable_ssa_logging: bool,
-    enable_brillig_logging: bool,
-    show_output: bool,
-) -> Result<(Circuit, DebugInfo, Abi), RuntimeError> {
+    options: &SsaOptions,
+    capabilities: &BackendCapabilities,
+) -> Result<(Circuit, DebugInfo, Abi, GateCounts, Option<OpcodeBudgetExceeded>), RuntimeError> {
     let func_sig = program.main_function_signature.clone();
-    let GeneratedAcir { current_witness_index, opcodes, return_witnesses, locations, .. } =
-        optimize_into_acir(program, show_output, enable_ssa_logging, enable_brillig_logging)?;
+    let (mut acir, gate_counts) = optimize_into_acir(program, options, capabilities)?;
+
+    let exceeded_budget =
+        options.opcode_budget.and_then(|budget| gate_counts.exceeded_budget(budget));
+    match exceeded_budget {
+        Some(exceeded) if options.strict_opcode_budget => {
+            let location = exceeded.location();
+            return Err(RuntimeError::new(
+                RuntimeErrorKind::OpcodeBudgetExceeded(exceeded),
+                location,
+            ));
+        }
+        _ => (),
+    }
 
-    let abi = gen_abi(func_sig, return_witnesses.clone());
+    // Parameter witnesses are kept even if unused, so that every input of the ABI is accepted
+    let mut abi = gen_abi(func_sig, acir.return_witnesses.clone());
+    let new_indices = acir.compact_witnesses(abi.param_witnesses.values().flatten().copied());
+    for witness in abi.param_witnesses.values_mut().flatten() {
+        *witness = new_indices[&*witness];
+    }
+    let GeneratedAcir {
+        current_witness_index,
+        opcodes,
+        return_witnesses,
+        locations,
+        assert_messages,
+        provenance,
+        brillig_locations,
+        ..
+    } = acir;
+    abi.return_witnesses = return_witnesses.clone();
     let public_abi = abi.clone().public_abi();
 
     let public_parameters =
@@ -99,15 +157,21 @@ pub fn create_circuit(
         public_parameters,
         return_values,
     };
-    let debug_info = DebugInfo::new(locations);
-
-    Ok((circuit, debug_info, abi))
+    let debug_info = DebugInfo::new(
+        locations,
+        assert_messages,
+        provenance.unwrap_or_default(),
+        brillig_locations.unwrap_or_default(),
+    );
+
+    Ok((circuit, debug_info, abi, gate_counts, exceeded_budget))
 }
 
 impl Ssa {
+    /// Prints the program with normalized ids, so that the ids printed are stable across passes
     fn print(self, print_ssa_passes: bool, msg: &str) -> Ssa {
         if print_ssa_passes {
-            println!("{msg}\n{self}");
+            println!("{msg}\n{}", self.normalize_ids());
         }
         self
     }
@@ -1,4 +1,9 @@
 pub(crate) mod acir_variable;
+pub(crate) mod big_int;
+pub(crate) mod constant_propagation;
+pub(crate) mod dead_code;
+pub(crate) mod deduplication;
 pub(crate) mod errors;
 pub(crate) mod generated_acir;
 pub(crate) mod sort;
+pub(crate) mod witness_compaction;
@@ -1,10 +1,10 @@
 use super::{errors::AcirGenError, generated_acir::GeneratedAcir};
 use crate::brillig::brillig_gen::brillig_directive;
 use crate::ssa_refactor::acir_gen::{AcirDynamicArray, AcirValue};
+use crate::ssa_refactor::backend_capabilities::BackendCapabilities;
 use crate::ssa_refactor::ir::types::Type as SsaType;
 use crate::ssa_refactor::ir::{instruction::Endian, types::NumericType};
-use acvm::acir::circuit::opcodes::{BlockId, MemOp};
-use acvm::acir::circuit::Opcode;
+use acvm::acir::circuit::opcodes::BlockId;
 use acvm::acir::{
     brillig::Opcode as BrilligOpcode,
     circuit::brillig::{BrilligInputs, BrilligOutputs},
@@ -20,7 +20,7 @@ use acvm::{
 };
 use iter_extended::vecmap;
 use noirc_errors::Location;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::{borrow::Cow, hash::Hash};
 
 #[derive(Clone, Debug, PartialEq, Eq, Hash)]
@@ -60,6 +60,14 @@ impl AcirType {
         AcirType::NumericType(NumericType::Unsigned { bit_size: 1 })
     }
 
+    /// True if type is a signed or unsigned integer
+    pub(crate) fn is_integer(&self) -> bool {
+        matches!(
+            self,
+            AcirType::NumericType(NumericType::Signed { .. } | NumericType::Unsigned { .. })
+        )
+    }
+
     /// True if type is signed
     pub(crate) fn is_signed(&self) -> bool {
         let numeric_type = match self {
@@ -126,6 +134,15 @@ impl AcirContext {
         self.add_data(var_data)
     }
 
+    /// Adds a Variable to the context for a parameter of the program, whose value is provided
+    /// as an input.
+    pub(crate) fn add_input_variable(&mut self) -> AcirVar {
+        let var_index = self.acir_ir.next_witness_index();
+        self.acir_ir.input_witnesses.push(var_index);
+
+        self.add_data(AcirVarData::Witness(var_index))
+    }
+
     pub(crate) fn get_location(&mut self) -> Option<Location> {
         self.acir_ir.current_location
     }
@@ -134,6 +151,47 @@ impl AcirContext {
         self.acir_ir.current_location = location;
     }
 
+    /// Sets the message reported when any of the opcodes added from now on fails.
+    pub(crate) fn set_assert_message(&mut self, message: Option<String>) {
+        self.acir_ir.current_assert_message = message;
+    }
+
+    /// Records the SSA instruction and purpose each opcode added from now on is generated for.
+    pub(crate) fn record_provenance(&mut self) {
+        self.acir_ir.provenance = Some(HashMap::new());
+    }
+
+    /// Records the source code location of each opcode of the Brillig code called from now on.
+    pub(crate) fn record_brillig_locations(&mut self) {
+        self.acir_ir.brillig_locations = Some(HashMap::new());
+    }
+
+    /// True if the SSA instruction and purpose each opcode is generated for are recorded
+    pub(crate) fn records_provenance(&self) -> bool {
+        self.acir_ir.provenance.is_some()
+    }
+
+    /// Sets the kind of SSA instruction the opcodes added from now on are generated for.
+    pub(crate) fn set_instruction_kind(&mut self, kind: Option<String>) {
+        self.acir_ir.current_instruction = kind;
+    }
+
+    /// Sets what the opcodes added from now on are for, unless an enclosing purpose is already
+    /// set, returning the previous purpose to be restored with `restore_purpose`.
+    pub(crate) fn set_purpose(&mut self, purpose: &'static str) -> Option<&'static str> {
+        self.acir_ir.set_purpose(purpose)
+    }
+
+    /// Restores the purpose returned by `set_purpose`.
+    pub(crate) fn restore_purpose(&mut self, previous: Option<&'static str>) {
+        self.acir_ir.restore_purpose(previous);
+    }
+
+    /// Sets the features of the backend the ACIR is generated for.
+    pub(crate) fn set_capabilities(&mut self, capabilities: BackendCapabilities) {
+        self.acir_ir.capabilities = capabilities;
+    }
+
     /// True if the given AcirVar refers to a constant one value
     pub(crate) fn is_constant_one(&self, var: &AcirVar) -> bool {
         match self.vars[var] {
@@ -179,7 +237,8 @@ impl AcirContext {
             inverse_code,
             vec![AcirValue::Var(var, field_type.clone())],
             vec![field_type],
-        );
+            BTreeMap::new(),
+        )?;
         let inverted_var = Self::expect_one_var(results);
 
         let should_be_one = self.mul_var(inverted_var, var)?;
@@ -207,6 +266,27 @@ impl AcirContext {
         self.assert_eq_var(pred_mul_var, predicate)
     }
 
+    /// Constrains each of `values` to be one if `predicate` is one.
+    ///
+    /// Every value must be either zero or one, so that their sum only equals their number when
+    /// they are all one. The values are then asserted together by `predicate * (n - sum) == 0`.
+    pub(crate) fn assert_all_one_if(
+        &mut self,
+        values: &[AcirVar],
+        predicate: AcirVar,
+    ) -> Result<(), AcirGenError> {
+        let mut missing = self.add_constant(FieldElement::from(values.len() as u128));
+        for value in values {
+            missing = self.sub_var(missing, *value)?;
+        }
+
+        let missing_expr = self.vars[&missing].to_expression();
+        let predicate_expr = self.vars[&predicate].to_expression();
+        let constraint = self.acir_ir.mul_with_witness(&missing_expr, &predicate_expr);
+        self.acir_ir.assert_is_zero(constraint);
+        Ok(())
+    }
+
     // Returns the variable from the results, assuming it is the only result
     fn expect_one_var(results: Vec<AcirValue>) -> AcirVar {
         assert_eq!(results.len(), 1);
@@ -239,6 +319,14 @@ impl AcirContext {
         rhs: AcirVar,
         typ: AcirType,
     ) -> Result<AcirVar, AcirGenError> {
+        if typ.bit_size() == 1 {
+            // Operands are booleans
+            // a + b - 2ab
+            let sum = self.add_var(lhs, rhs)?;
+            let mul = self.mul_var(lhs, rhs)?;
+            let both = self.add_var(mul, mul)?;
+            return self.sub_var(sum, both);
+        }
         let inputs = vec![AcirValue::Var(lhs, typ.clone()), AcirValue::Var(rhs, typ)];
         let outputs = self.black_box_function(BlackBoxFunc::XOR, inputs)?;
         Ok(outputs[0])
@@ -251,6 +339,11 @@ impl AcirContext {
         rhs: AcirVar,
         typ: AcirType,
     ) -> Result<AcirVar, AcirGenError> {
+        if typ.bit_size() == 1 {
+            // Operands are booleans
+            // ab
+            return self.mul_var(lhs, rhs);
+        }
         let inputs = vec![AcirValue::Var(lhs, typ.clone()), AcirValue::Var(rhs, typ)];
         let outputs = self.black_box_function(BlackBoxFunc::AND, inputs)?;
         Ok(outputs[0])
@@ -337,7 +430,7 @@ impl AcirContext {
             }
             NumericType::Signed { bit_size } => {
                 let (quotient_var, _remainder_var) =
-                    self.signed_division_var(lhs, rhs, bit_size)?;
+                    self.signed_division_var(lhs, rhs, bit_size, predicate)?;
                 Ok(quotient_var)
             }
         }
@@ -351,10 +444,8 @@ impl AcirContext {
         let result = match (lhs_data, rhs_data) {
             (AcirVarData::Witness(witness), AcirVarData::Expr(expr))
             | (AcirVarData::Expr(expr), AcirVarData::Witness(witness)) => {
-                let expr_as_witness = self.acir_ir.get_or_create_witness(expr);
-                let mut expr = Expression::default();
-                expr.push_multiplication_term(FieldElement::one(), *witness, expr_as_witness);
-
+                // The product only needs the expression to be given a witness when it is not linear
+                let expr = self.acir_ir.mul_with_witness(&Expression::from(*witness), expr);
                 self.add_data(AcirVarData::Expr(expr))
             }
             (AcirVarData::Witness(witness), AcirVarData::Const(constant))
@@ -376,14 +467,7 @@ impl AcirContext {
                 self.add_data(AcirVarData::Const(*lhs_constant * *rhs_constant))
             }
             (AcirVarData::Expr(lhs_expr), AcirVarData::Expr(rhs_expr)) => {
-                let lhs_expr_as_witness = self.acir_ir.get_or_create_witness(lhs_expr);
-                let rhs_expr_as_witness = self.acir_ir.get_or_create_witness(rhs_expr);
-                let mut expr = Expression::default();
-                expr.push_multiplication_term(
-                    FieldElement::one(),
-                    lhs_expr_as_witness,
-                    rhs_expr_as_witness,
-                );
+                let expr = self.acir_ir.mul_with_witness(lhs_expr, rhs_expr);
                 self.add_data(AcirVarData::Expr(expr))
             }
         };
@@ -424,26 +508,138 @@ impl AcirContext {
     /// Returns an `AcirVar` that is constrained to be `lhs << rhs`.
     ///
     /// We convert left shifts to multiplications, so this is equivalent to
-    /// `lhs * 2^rhs`.
+    /// `lhs * 2^rhs`. If `rhs` is not a constant, `2^rhs` is computed from the bits of `rhs`,
+    /// see [AcirContext::shift_power_var].
     ///
-    /// We currently require `rhs` to be a constant
-    /// however this can be extended, see #1478.
+    /// If the shifted value may not fit within a field element, the bits of `lhs` which would be
+    /// shifted out of the integer are removed first, so that the result is already truncated.
     pub(crate) fn shift_left_var(
         &mut self,
         lhs: AcirVar,
         rhs: AcirVar,
-        _typ: AcirType,
+        typ: AcirType,
+        predicate: AcirVar,
     ) -> Result<AcirVar, AcirGenError> {
         let rhs_data = &self.vars[&rhs];
+        let bit_size = typ.bit_size();
 
         // Compute 2^{rhs}
-        let two_pow_rhs = match rhs_data.as_constant() {
-            Some(exponent) => FieldElement::from(2_i128).pow(&exponent),
-            None => unimplemented!("rhs must be a constant when doing a right shift"),
+        let exponent = match rhs_data.as_constant() {
+            Some(exponent) => exponent,
+            None => {
+                let (power, in_range) = self.shift_power_var(rhs, bit_size, predicate)?;
+                // Out of range shifts leave no bits of `lhs`
+                let power = self.mul_var(power, in_range)?;
+                // The shifted value is always reduced to the bit size of the integer, as the
+                // truncation which follows a shift by an unknown amount is skipped.
+                if 2 * bit_size < FieldElement::max_num_bits() {
+                    let shifted = self.mul_var(lhs, power)?;
+                    return self.truncate_var(shifted, bit_size, 2 * bit_size);
+                }
+                return self.wrapping_mul_var(lhs, power, bit_size);
+            }
         };
+        let two_pow_rhs = FieldElement::from(2_i128).pow(&exponent);
         let two_pow_rhs_var = self.add_constant(two_pow_rhs);
 
-        self.mul_var(lhs, two_pow_rhs_var)
+        let shift = exponent.to_u128();
+        if !typ.is_integer() || u128::from(bit_size) + shift < FieldElement::max_num_bits() as u128
+        {
+            return self.mul_var(lhs, two_pow_rhs_var);
+        }
+
+        if shift >= u128::from(bit_size) {
+            return Ok(self.add_constant(FieldElement::zero()));
+        }
+        let remaining_bits = self.truncate_var(lhs, bit_size - shift as u32, bit_size)?;
+        self.mul_var(remaining_bits, two_pow_rhs_var)
+    }
+
+    /// Returns `AcirVar`s for `2^{rhs mod 2^k}`, where `k = ceil(log2(bit_size))`, and for
+    /// whether `rhs < bit_size`, given a non-constant shift amount `rhs` of `bit_size` bits.
+    ///
+    /// The power is the product of `2^{2^i}` over the set bits `i` among the lowest `k` bits of
+    /// `rhs`, so a shift takes one multiplication per bit which may lead to a shift within the
+    /// integer. The power is less than `2^bit_size` whenever `rhs < bit_size`.
+    fn shift_power_var(
+        &mut self,
+        rhs: AcirVar,
+        bit_size: u32,
+        predicate: AcirVar,
+    ) -> Result<(AcirVar, AcirVar), AcirGenError> {
+        let low_bit_count = u32::BITS - (bit_size - 1).leading_zeros();
+        let low_bits = self.truncate_var(rhs, low_bit_count, bit_size)?;
+        let low_bits_expr = self.vars[&low_bits].to_expression();
+        let bits = self.acir_ir.radix_le_decompose(&low_bits_expr, 2, low_bit_count, 1)?;
+
+        let one = self.add_constant(FieldElement::one());
+        let mut power = one;
+        for (index, bit) in bits.into_iter().enumerate() {
+            // power *= bit ? 2^{2^index} : 1
+            let bit = self.add_data(AcirVarData::Witness(bit));
+            let factor = FieldElement::from(2_u128).pow(&FieldElement::from(1_u128 << index));
+            let factor = self.add_constant(factor - FieldElement::one());
+            let factor = self.mul_var(bit, factor)?;
+            let factor = self.add_var(factor, one)?;
+            power = self.mul_var(power, factor)?;
+        }
+
+        // Every value of the low bits is a shift within the integer if its bit size is a power
+        // of two, in which case only the higher bits need to be zero
+        let in_range = if bit_size.is_power_of_two() {
+            self.eq_var(rhs, low_bits)?
+        } else {
+            let bit_size_var = self.add_constant(FieldElement::from(bit_size as u128));
+            self.less_than_var(rhs, bit_size_var, bit_size, predicate)?
+        };
+        Ok((power, in_range))
+    }
+
+    /// Returns an `AcirVar` constrained to be `(lhs * rhs) mod 2^bit_size`, for integers whose
+    /// product may not fit within a field element.
+    ///
+    /// Splitting each operand into a low limb of `h = ceil(bit_size / 2)` bits and a high limb,
+    /// `a = a_1 * 2^h + a_0`, the product modulo `2^bit_size` is
+    /// `a_0 * b_0 + 2^h * (a_0 * b_1 + a_1 * b_0)`, since `a_1 * b_1 * 2^{2h}` is a multiple of
+    /// `2^bit_size`. Every term of this sum fits within a field element.
+    pub(crate) fn wrapping_mul_var(
+        &mut self,
+        lhs: AcirVar,
+        rhs: AcirVar,
+        bit_size: u32,
+    ) -> Result<AcirVar, AcirGenError> {
+        // A product with a small enough constant fits within a field element
+        let constant_bits = [lhs, rhs]
+            .iter()
+            .filter_map(|var| self.vars[var].as_constant())
+            .map(|constant| constant.num_bits())
+            .min();
+        if let Some(constant_bits) = constant_bits {
+            let max_bit_size = bit_size + constant_bits;
+            if max_bit_size < FieldElement::max_num_bits() {
+                let product = self.mul_var(lhs, rhs)?;
+                return self.truncate_var(product, bit_size, max_bit_size);
+            }
+        }
+
+        let low_bits = (bit_size + 1) / 2;
+        let low_radix = FieldElement::from(2_u128).pow(&FieldElement::from(low_bits as u128));
+        let low_radix = self.add_constant(low_radix);
+        let one = self.add_constant(FieldElement::one());
+        let (lhs_high, lhs_low) = self.euclidean_division_var(lhs, low_radix, bit_size, one)?;
+        let (rhs_high, rhs_low) = self.euclidean_division_var(rhs, low_radix, bit_size, one)?;
+
+        // The high limbs have `bit_size - low_bits` bits, so only the bits of the cross terms
+        // below that are kept once shifted by `low_bits`.
+        let lhs_cross = self.mul_var(lhs_low, rhs_high)?;
+        let rhs_cross = self.mul_var(lhs_high, rhs_low)?;
+        let cross = self.add_var(lhs_cross, rhs_cross)?;
+        let cross = self.truncate_var(cross, bit_size - low_bits, bit_size + 1)?;
+
+        let low_product = self.mul_var(lhs_low, rhs_low)?;
+        let shifted_cross = self.mul_var(cross, low_radix)?;
+        let product = self.add_var(low_product, shifted_cross)?;
+        self.truncate_var(product, bit_size, bit_size + 2)
     }
 
     /// Returns the quotient and remainder such that lhs = rhs * quotient + remainder
@@ -476,26 +672,30 @@ impl AcirContext {
     /// and remainder has the same sign than lhs
     /// Note that this is not the euclidian division, where we have instead remainder < |rhs|
     ///
-    ///
-    ///
-    ///
-
+    /// Operands and results are integers of `bit_size` bits in two's complement.
     fn signed_division_var(
         &mut self,
         lhs: AcirVar,
         rhs: AcirVar,
         bit_size: u32,
+        predicate: AcirVar,
     ) -> Result<(AcirVar, AcirVar), AcirGenError> {
         let lhs_data = &self.vars[&lhs].clone();
         let rhs_data = &self.vars[&rhs].clone();
+        let predicate_data = &self.vars[&predicate].clone();
 
         let lhs_expr = lhs_data.to_expression();
         let rhs_expr = rhs_data.to_expression();
+        let predicate_expr = predicate_data.to_expression();
         let l_witness = self.acir_ir.get_or_create_witness(&lhs_expr);
         let r_witness = self.acir_ir.get_or_create_witness(&rhs_expr);
         assert_ne!(bit_size, 0, "signed integer should have at least one bit");
-        let (q, r) =
-            self.acir_ir.signed_division(&l_witness.into(), &r_witness.into(), bit_size)?;
+        let (q, r) = self.acir_ir.signed_division(
+            &l_witness.into(),
+            &r_witness.into(),
+            bit_size,
+            &predicate_expr,
+        )?;
 
         let q_vd = AcirVarData::Expr(q);
         let r_vd = AcirVarData::Expr(r);
@@ -503,24 +703,28 @@ impl AcirContext {
     }
 
     /// Returns a variable which is constrained to be `lhs mod rhs`
+    ///
+    /// The remainder of a signed division has the same sign as `lhs`.
     pub(crate) fn modulo_var(
         &mut self,
         lhs: AcirVar,
         rhs: AcirVar,
-        bit_size: u32,
+        typ: AcirType,
         predicate: AcirVar,
     ) -> Result<AcirVar, AcirGenError> {
-        let (_, remainder) = self.euclidean_division_var(lhs, rhs, bit_size, predicate)?;
+        let (_, remainder) = if typ.is_signed() {
+            self.signed_division_var(lhs, rhs, typ.bit_size(), predicate)?
+        } else {
+            self.euclidean_division_var(lhs, rhs, typ.bit_size(), predicate)?
+        };
         Ok(remainder)
     }
 
     /// Returns an `AcirVar` that is constrained to be `lhs >> rhs`.
     ///
     /// We convert right shifts to divisions, so this is equivalent to
-    /// `lhs / 2^rhs`.
-    ///
-    /// We currently require `rhs` to be a constant
-    /// however this can be extended, see #1478.
+    /// `lhs / 2^rhs`. If `rhs` is not a constant, `2^rhs` is computed from the bits of `rhs`,
+    /// see [AcirContext::shift_power_var].
     ///
     /// This code is doing a field division instead of an integer division,
     /// see #1479 about how this is expected to change.
@@ -536,7 +740,16 @@ impl AcirContext {
         // Compute 2^{rhs}
         let two_pow_rhs = match rhs_data.as_constant() {
             Some(exponent) => FieldElement::from(2_i128).pow(&exponent),
-            None => unimplemented!("rhs must be a constant when doing a right shift"),
+            None => {
+                let (power, in_range) = self.shift_power_var(rhs, typ.bit_size(), predicate)?;
+                // Out of range shifts divide by one, and the quotient is then discarded
+                let one = self.add_constant(FieldElement::one());
+                let power_minus_one = self.sub_var(power, one)?;
+                let divisor = self.mul_var(power_minus_one, in_range)?;
+                let divisor = self.add_var(divisor, one)?;
+                let quotient = self.div_var(lhs, divisor, typ, predicate)?;
+                return self.mul_var(quotient, in_range);
+            }
         };
         let two_pow_rhs_var = self.add_constant(two_pow_rhs);
 
@@ -654,6 +867,23 @@ impl AcirContext {
         name: BlackBoxFunc,
         mut inputs: Vec<AcirValue>,
     ) -> Result<Vec<AcirVar>, AcirGenError> {
+        if !self.acir_ir.capabilities.supports_black_box(name) {
+            return match (name, inputs.as_slice()) {
+                (
+                    BlackBoxFunc::AND | BlackBoxFunc::XOR,
+                    [AcirValue::Var(lhs, typ), AcirValue::Var(rhs, _)],
+                ) => {
+                    let result = self.bitwise_from_bits(name, *lhs, *rhs, typ.bit_size())?;
+                    Ok(vec![result])
+                }
+                _ => Err(AcirGenError::UnsupportedBlackBox { name, location: self.get_location() }),
+            };
+        }
+
+        if matches!(name, BlackBoxFunc::EcdsaSecp256k1 | BlackBoxFunc::EcdsaSecp256r1) {
+            self.check_ecdsa_inputs(name, &inputs)?;
+        }
+
         // Separate out any arguments that should be constants
         let constants = match name {
             BlackBoxFunc::Pedersen => {
@@ -663,10 +893,23 @@ impl AcirContext {
 
                 let domain_constant = self.vars[&domain_var]
                     .as_constant()
-                    .expect("ICE: Domain separator must be a constant");
+                    .filter(|constant| constant.num_bits() <= 32)
+                    .ok_or_else(|| AcirGenError::InvalidBlackBoxInput {
+                        name,
+                        reason: "the domain separator must be a constant u32".to_string(),
+                        location: self.get_location(),
+                    })?;
 
                 vec![domain_constant]
             }
+            BlackBoxFunc::Keccak256 => self.keccak_message_size(&mut inputs)?,
+            BlackBoxFunc::RecursiveAggregation => {
+                // The verification key, proof, and public inputs each have a variable length, so
+                // their lengths are passed along to separate them once the inputs are flattened
+                vecmap(&inputs[..3], |input| {
+                    FieldElement::from(input.clone().flatten().len() as u128)
+                })
+            }
             _ => vec![],
         };
 
@@ -684,6 +927,124 @@ impl AcirContext {
         Ok(vecmap(&outputs, |witness_index| self.add_data(AcirVarData::Witness(*witness_index))))
     }
 
+    /// Checks the size of the message hashed by a Keccak256 call, which is its last input, against
+    /// the length of the array holding the message.
+    ///
+    /// If the size is known to be the length of the whole array, it is removed from the inputs
+    /// and returned as a constant so that the fixed-length opcode is used. Otherwise the size is
+    /// constrained to be at most the length of the array, and the variable-length opcode is used.
+    fn keccak_message_size(
+        &mut self,
+        inputs: &mut Vec<AcirValue>,
+    ) -> Result<Vec<FieldElement>, AcirGenError> {
+        let (message, message_size) = match inputs.as_slice() {
+            [message, AcirValue::Var(message_size, _)] => (message, *message_size),
+            _ => unreachable!("ICE: Keccak256 expects a message and a message size"),
+        };
+        let length = message.clone().flatten().len() as u128;
+
+        match self.vars[&message_size].as_constant() {
+            Some(size) if size == FieldElement::from(length) => {
+                inputs.pop();
+                Ok(vec![size])
+            }
+            Some(size) if size.to_u128() > length => Err(AcirGenError::InvalidBlackBoxInput {
+                name: BlackBoxFunc::Keccak256,
+                reason: format!("the message size {size} exceeds the message length {length}"),
+                location: self.get_location(),
+            }),
+            Some(_) => Ok(Vec::new()),
+            None => {
+                let max_size = self.add_constant(FieldElement::from(length + 1));
+                let one = self.add_constant(FieldElement::one());
+                self.less_than_constrain(message_size, max_size, 32, one)?;
+                Ok(Vec::new())
+            }
+        }
+    }
+
+    /// Checks that the inputs of an ECDSA signature verification are the byte arrays of the
+    /// public key coordinates, the signature and the hashed message, as the opcode expects.
+    fn check_ecdsa_inputs(
+        &self,
+        name: BlackBoxFunc,
+        inputs: &[AcirValue],
+    ) -> Result<(), AcirGenError> {
+        let invalid_input = |reason| AcirGenError::InvalidBlackBoxInput {
+            name,
+            reason,
+            location: self.acir_ir.current_location,
+        };
+
+        let arguments = [
+            ("public key x coordinate", Some(32)),
+            ("public key y coordinate", Some(32)),
+            ("signature", Some(64)),
+            ("hashed message", None),
+        ];
+        if inputs.len() != arguments.len() {
+            let reason = format!("expected 4 arguments but found {}", inputs.len());
+            return Err(invalid_input(reason));
+        }
+
+        let byte = AcirType::NumericType(NumericType::Unsigned { bit_size: 8 });
+        let is_byte =
+            |element: &AcirValue| matches!(element, AcirValue::Var(_, typ) if *typ == byte);
+        for (input, (argument, expected_length)) in inputs.iter().zip(arguments) {
+            let bytes = match input {
+                AcirValue::Array(elements) if elements.iter().all(is_byte) => elements,
+                _ => {
+                    let reason = format!("the {argument} must be an array of bytes");
+                    return Err(invalid_input(reason));
+                }
+            };
+            match expected_length {
+                Some(length) if bytes.len() != length => {
+                    let found = bytes.len();
+                    let reason = format!("the {argument} must be {length} bytes long, not {found}");
+                    return Err(invalid_input(reason));
+                }
+                _ => (),
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns the bitwise AND or XOR of `lhs` and `rhs`, computed from the bits of each, for
+    /// backends which do not support the corresponding black box function.
+    fn bitwise_from_bits(
+        &mut self,
+        operation: BlackBoxFunc,
+        lhs: AcirVar,
+        rhs: AcirVar,
+        bit_size: u32,
+    ) -> Result<AcirVar, AcirGenError> {
+        let lhs_expr = self.vars[&lhs].to_expression();
+        let rhs_expr = self.vars[&rhs].to_expression();
+        let lhs_bits = self.acir_ir.radix_le_decompose(&lhs_expr, 2, bit_size, 1)?;
+        let rhs_bits = self.acir_ir.radix_le_decompose(&rhs_expr, 2, bit_size, 1)?;
+
+        let mut result = self.add_constant(FieldElement::zero());
+        for (index, (lhs_bit, rhs_bit)) in lhs_bits.into_iter().zip(rhs_bits).enumerate() {
+            let lhs_bit = self.add_data(AcirVarData::Witness(lhs_bit));
+            let rhs_bit = self.add_data(AcirVarData::Witness(rhs_bit));
+
+            // a AND b = ab, while a XOR b = a + b - 2ab
+            let mut bit = self.mul_var(lhs_bit, rhs_bit)?;
+            if operation == BlackBoxFunc::XOR {
+                let sum = self.add_var(lhs_bit, rhs_bit)?;
+                let both = self.add_var(bit, bit)?;
+                bit = self.sub_var(sum, both)?;
+            }
+
+            let power_of_two = FieldElement::from(2_u128).pow(&FieldElement::from(index as u128));
+            let power_of_two = self.add_constant(power_of_two);
+            let term = self.mul_var(bit, power_of_two)?;
+            result = self.add_var(result, term)?;
+        }
+        Ok(result)
+    }
+
     /// Black box function calls expect their inputs to be in a specific data structure (FunctionInput).
     ///
     /// This function will convert `AcirVar` into `FunctionInput` for a blackbox function call.
@@ -770,16 +1131,25 @@ impl AcirContext {
         self.radix_decompose(endian, input_var, two_var, limb_count_var, result_element_type)
     }
 
-    /// Prints the given `AcirVar`s as witnesses.
-    pub(crate) fn print(&mut self, input: Vec<AcirValue>) -> Result<(), AcirGenError> {
-        let input = Self::flatten_values(input);
-
-        let witnesses = vecmap(input, |acir_var| {
-            let var_data = &self.vars[&acir_var];
-            let expr = var_data.to_expression();
-            self.acir_ir.get_or_create_witness(&expr)
+    /// Prints the given values through the `println` foreign call, if `predicate` is one.
+    ///
+    /// The values are passed along with `format`, a string describing their type, so that the
+    /// executor displays them as it does the values passed to the `println` oracle.
+    pub(crate) fn print(
+        &mut self,
+        input: Vec<AcirValue>,
+        format: &str,
+        predicate: AcirVar,
+    ) -> Result<(), AcirGenError> {
+        let value_count = Self::flatten_values(input.clone()).len();
+        let format = vecmap(format.bytes(), |byte| {
+            let byte = self.add_constant(FieldElement::from(byte as u128));
+            AcirValue::Var(byte, AcirType::NumericType(NumericType::NativeField))
         });
-        self.acir_ir.call_print(witnesses);
+
+        let println_code = brillig_directive::directive_println(&[value_count, format.len()]);
+        let inputs = vec![AcirValue::Array(input.into()), AcirValue::Array(format.into())];
+        self.brillig(predicate, println_code, inputs, Vec::new(), BTreeMap::new())?;
         Ok(())
     }
 
@@ -823,13 +1193,22 @@ impl AcirContext {
         id
     }
 
+    /// Calls unconstrained Brillig code, returning its outputs.
+    ///
+    /// Nothing constrains the values computed by Brillig code, so each integer output is range
+    /// constrained to the bit size of its type. The length of each array output is already fixed
+    /// by the number of witnesses it is given.
+    ///
+    /// `locations` maps the position of opcodes of the code to the source code location they
+    /// were generated for, which is recorded if Brillig locations are.
     pub(crate) fn brillig(
         &mut self,
         predicate: AcirVar,
         code: Vec<BrilligOpcode>,
         inputs: Vec<AcirValue>,
         outputs: Vec<AcirType>,
-    ) -> Vec<AcirValue> {
+        locations: BTreeMap<usize, Location>,
+    ) -> Result<Vec<AcirValue>, AcirGenError> {
         let b_inputs = vecmap(inputs, |i| match i {
             AcirValue::Var(var, _) => {
                 BrilligInputs::Single(self.vars[&var].to_expression().into_owned())
@@ -863,9 +1242,14 @@ impl AcirContext {
             }
         });
         let predicate = self.vars[&predicate].to_expression().into_owned();
-        self.acir_ir.brillig(Some(predicate), code, b_inputs, b_outputs);
+        self.acir_ir.brillig(Some(predicate), code, b_inputs, b_outputs, locations);
 
-        outputs_var
+        for (var, typ) in outputs_var.iter().flat_map(|output| output.clone().flatten()) {
+            if let AcirType::NumericType(numeric_type) = typ {
+                self.range_constrain_var(var, &numeric_type)?;
+            }
+        }
+        Ok(outputs_var)
     }
 
     fn brillig_array_input(&mut self, var_expressions: &mut Vec<Expression>, input: AcirValue) {
@@ -930,9 +1314,9 @@ impl AcirContext {
     }
 
     /// Generate output variables that are constrained to be the sorted inputs
-    /// The outputs are the sorted inputs iff
-    /// outputs are sorted and
-    /// outputs are a permutation of the inputs
+    ///
+    /// The outputs are computed by unconstrained Brillig code, and are then constrained
+    /// to be a sorted permutation of the inputs.
     pub(crate) fn sort(
         &mut self,
         inputs: Vec<AcirVar>,
@@ -940,26 +1324,86 @@ impl AcirContext {
         predicate: AcirVar,
     ) -> Result<Vec<AcirVar>, AcirGenError> {
         let len = inputs.len();
-        // Convert the inputs into expressions
-        let inputs_expr = vecmap(inputs, |input| self.vars[&input].to_expression().into_owned());
-        // Generate output witnesses
-        let outputs_witness = vecmap(0..len, |_| self.acir_ir.next_witness_index());
-        let output_expr =
-            vecmap(&outputs_witness, |witness_index| Expression::from(*witness_index));
-        let outputs_var = vecmap(&outputs_witness, |witness_index| {
-            self.add_data(AcirVarData::Witness(*witness_index))
-        });
+        if len == 0 {
+            return Ok(Vec::new());
+        }
+
+        let sort_code = brillig_directive::directive_sort(len, bit_size);
+        let field_type = AcirType::NumericType(NumericType::NativeField);
+        let array = AcirValue::Array(
+            inputs.iter().map(|input| AcirValue::Var(*input, field_type.clone())).collect(),
+        );
+        let results = self.brillig(
+            predicate,
+            sort_code,
+            vec![array],
+            vec![AcirType::Array(vec![field_type], len)],
+            BTreeMap::new(),
+        )?;
+        let outputs_var = Self::flatten_values(results);
+
+        self.assert_sorted_permutation(inputs, outputs_var.clone(), bit_size, predicate)?;
+        Ok(outputs_var)
+    }
+
+    /// Constrains `sorted` to hold the elements of `inputs` in ascending order, when the
+    /// predicate is true.
+    ///
+    /// `sorted` is a permutation of `inputs` if it is the output of a permutation network whose
+    /// inputs are `inputs`, and it is sorted if no element is greater than the next one.
+    pub(crate) fn assert_sorted_permutation(
+        &mut self,
+        inputs: Vec<AcirVar>,
+        sorted: Vec<AcirVar>,
+        bit_size: u32,
+        predicate: AcirVar,
+    ) -> Result<(), AcirGenError> {
+        assert_eq!(
+            inputs.len(),
+            sorted.len(),
+            "ICE: a permutation must have as many elements as its inputs"
+        );
+        if inputs.is_empty() {
+            return Ok(());
+        }
+
+        // Both arrays are multiplied by the predicate, so that they are equal when it is false
+        let mut predicated_expr = |vars: Vec<AcirVar>| -> Result<Vec<Expression>, AcirGenError> {
+            let mut exprs = Vec::with_capacity(vars.len());
+            for var in vars {
+                let predicated_var = self.mul_var(var, predicate)?;
+                exprs.push(self.vars[&predicated_var].to_expression().into_owned());
+            }
+            Ok(exprs)
+        };
+        let inputs_expr = predicated_expr(inputs)?;
+        let sorted_expr = predicated_expr(sorted.clone())?;
 
         // Enforce the outputs to be a permutation of the inputs
-        self.acir_ir.permutation(&inputs_expr, &output_expr);
+        self.acir_ir.permutation(&inputs_expr, &sorted_expr);
 
         // Enforce the outputs to be sorted
-        for i in 0..(outputs_var.len() - 1) {
-            self.less_than_constrain(outputs_var[i], outputs_var[i + 1], bit_size, predicate)?;
+        for pair in sorted.windows(2) {
+            self.less_than_constrain(pair[0], pair[1], bit_size, predicate)?;
         }
+        Ok(())
+    }
 
-        Ok(outputs_var)
+    /// Constrains `a * b + c` to equal `remainder` modulo `modulus`, given the limbs of the big
+    /// integers `[a, b, c, modulus, quotient, remainder]` such that
+    /// `a * b + c == quotient * modulus + remainder`.
+    pub(crate) fn big_int_mul_add_mod(
+        &mut self,
+        operands: Vec<Vec<AcirVar>>,
+        predicate: AcirVar,
+    ) -> Result<(), AcirGenError> {
+        let operands = vecmap(operands, |limbs| {
+            vecmap(limbs, |limb| self.vars[&limb].to_expression().into_owned())
+        });
+        let predicate = self.vars[&predicate].to_expression().into_owned();
+        self.acir_ir.big_int_mul_add_mod(&operands, &predicate)
     }
+
     /// Converts an AcirVar to a Witness
     fn var_to_witness(&mut self, var: AcirVar) -> Witness {
         let var_data = self.vars.get(&var).expect("ICE: undeclared AcirVar");
@@ -989,8 +1433,7 @@ impl AcirContext {
         let value_read_witness = self.var_to_witness(value_read_var);
 
         // Add the memory read operation to the list of opcodes
-        let op = MemOp::read_at_mem_index(index_witness.into(), value_read_witness);
-        self.acir_ir.opcodes.push(Opcode::MemoryOp { block_id, op });
+        self.acir_ir.read_from_memory(block_id, index_witness, value_read_witness);
 
         value_read_var
     }
@@ -1005,8 +1448,7 @@ impl AcirContext {
         let value_write_witness = self.var_to_witness(*value);
 
         // Add the memory write operation to the list of opcodes
-        let op = MemOp::write_to_mem_index(index_witness.into(), value_write_witness.into());
-        self.acir_ir.opcodes.push(Opcode::MemoryOp { block_id, op });
+        self.acir_ir.write_to_memory(block_id, index_witness, value_write_witness);
     }
 
     /// Initializes an array in memory with the given values `optional_values`.
@@ -1031,7 +1473,7 @@ impl AcirContext {
             }),
         };
 
-        self.acir_ir.opcodes.push(Opcode::MemoryInit { block_id, init: initialized_values });
+        self.acir_ir.initialize_memory(block_id, initialized_values);
     }
 }
 
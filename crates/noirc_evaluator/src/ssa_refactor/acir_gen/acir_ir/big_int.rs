@@ -0,0 +1,149 @@
+//! Modular arithmetic on unsigned integers wider than a field element, such as the 256-bit
+//! integers of elliptic curves defined over a field other than the native one.
+//!
+//! Each integer is given by its limbs of [BIG_INT_LIMB_BITS] bits, least significant first.
+//! Rather than computing `a * b + c mod modulus` within the circuit, the quotient `q` and the
+//! remainder `r` are provided by the program and the circuit checks that
+//! `a * b + c == q * modulus + r` over the integers, and that `r < modulus`.
+//!
+//! The product is checked one column of limbs at a time, as in schoolbook multiplication. The
+//! difference between both sides of the equation within each column, plus the carry from the
+//! previous column, must be a multiple of `2^BIG_INT_LIMB_BITS`, and the last carry must be zero.
+//! Since each limb is range constrained, every column stays far below the field modulus so the
+//! equation holds over the integers rather than only modulo the field.
+use super::{errors::AcirGenError, generated_acir::GeneratedAcir};
+use acvm::{acir::native_types::Expression, FieldElement};
+
+/// The number of bits of each limb of a big integer
+pub(crate) const BIG_INT_LIMB_BITS: u32 = 64;
+
+impl GeneratedAcir {
+    /// Constrains `a * b + c == quotient * modulus + remainder` and `remainder < modulus`, where
+    /// each operand is a big integer given by the same number of limbs.
+    ///
+    /// Every limb which is not constant is range constrained to [BIG_INT_LIMB_BITS] bits.
+    /// The constraints are only enforced if `predicate` is one.
+    pub(crate) fn big_int_mul_add_mod(
+        &mut self,
+        operands: &[Vec<Expression>],
+        predicate: &Expression,
+    ) -> Result<(), AcirGenError> {
+        let (a, b, c, modulus, quotient, remainder) = match operands {
+            [a, b, c, modulus, quotient, remainder] => (a, b, c, modulus, quotient, remainder),
+            _ => unreachable!("ICE: big integer multiplication expects 6 operands"),
+        };
+        let limb_count = a.len();
+        assert!(
+            operands.iter().all(|operand| operand.len() == limb_count),
+            "ICE: big integer operands must have the same number of limbs"
+        );
+
+        for limb in operands.iter().flatten() {
+            if limb.to_const().is_none() {
+                let witness = self.get_or_create_witness(limb);
+                self.range_constraint(witness, BIG_INT_LIMB_BITS)?;
+            }
+        }
+
+        self.big_int_carry_constraints(a, b, c, modulus, quotient, remainder, predicate)?;
+        self.big_int_less_than(remainder, modulus, predicate)
+    }
+
+    /// Constrains `a * b + c == quotient * modulus + remainder` over the integers, column by
+    /// column.
+    ///
+    /// Each carry may be negative, so it is shifted by a constant offset to be computed by a
+    /// quotient directive, and then range constrained.
+    #[allow(clippy::too_many_arguments)]
+    fn big_int_carry_constraints(
+        &mut self,
+        a: &[Expression],
+        b: &[Expression],
+        c: &[Expression],
+        modulus: &[Expression],
+        quotient: &[Expression],
+        remainder: &[Expression],
+        predicate: &Expression,
+    ) -> Result<(), AcirGenError> {
+        let limb_count = a.len();
+        let limb_radix = two_pow(BIG_INT_LIMB_BITS);
+
+        // Each column holds at most `limb_count` products of two limbs on each side, so every carry
+        // is less than `(limb_count + 1) * 2^BIG_INT_LIMB_BITS` in absolute value.
+        let carry_bits = BIG_INT_LIMB_BITS + usize::BITS - limb_count.leading_zeros() + 1;
+        let carry_offset = two_pow(carry_bits);
+
+        let mut carry = Expression::default();
+        for column in 0..(2 * limb_count).saturating_sub(1) {
+            let mut difference = carry;
+            for i in column.saturating_sub(limb_count - 1)..=column.min(limb_count - 1) {
+                let j = column - i;
+                difference = &difference + &self.mul_with_witness(&a[i], &b[j]);
+                difference = &difference - &self.mul_with_witness(&quotient[i], &modulus[j]);
+            }
+            if column < limb_count {
+                difference = &(&difference + &c[column]) - &remainder[column];
+            }
+
+            // difference + offset * 2^L == shifted_carry * 2^L
+            let shifted_difference = difference + carry_offset * limb_radix;
+            let (shifted_carry, _) = self.quotient_directive(
+                shifted_difference.clone(),
+                limb_radix.into(),
+                Some(predicate.clone()),
+                carry_bits + 1,
+                BIG_INT_LIMB_BITS,
+            )?;
+            let column_constraint =
+                shifted_difference.add_mul(-limb_radix, &Expression::from(shifted_carry));
+            let column_constraint = self.mul_with_witness(&column_constraint, predicate);
+            self.assert_is_zero(column_constraint);
+
+            carry = Expression::from(shifted_carry) - carry_offset;
+        }
+
+        // No carry may remain past the most significant column
+        let last_carry = self.mul_with_witness(&carry, predicate);
+        self.assert_is_zero(last_carry);
+        Ok(())
+    }
+
+    /// Constrains `lhs < rhs` by computing `rhs - lhs - 1` limb by limb and checking that the
+    /// subtraction does not borrow from past the most significant limb.
+    fn big_int_less_than(
+        &mut self,
+        lhs: &[Expression],
+        rhs: &[Expression],
+        predicate: &Expression,
+    ) -> Result<(), AcirGenError> {
+        let limb_radix = two_pow(BIG_INT_LIMB_BITS);
+
+        let mut borrow = Expression::one();
+        for (lhs_limb, rhs_limb) in lhs.iter().zip(rhs) {
+            // rhs - lhs - borrow + 2^L == no_borrow * 2^L + difference
+            let shifted_difference = &(rhs_limb - lhs_limb) - &borrow + limb_radix;
+            let (no_borrow, difference) = self.quotient_directive(
+                shifted_difference.clone(),
+                limb_radix.into(),
+                Some(predicate.clone()),
+                1,
+                BIG_INT_LIMB_BITS,
+            )?;
+            let limb_constraint = shifted_difference
+                .add_mul(-limb_radix, &Expression::from(no_borrow))
+                .add_mul(-FieldElement::one(), &Expression::from(difference));
+            let limb_constraint = self.mul_with_witness(&limb_constraint, predicate);
+            self.assert_is_zero(limb_constraint);
+
+            borrow = &Expression::one() - &Expression::from(no_borrow);
+        }
+
+        let last_borrow = self.mul_with_witness(&borrow, predicate);
+        self.assert_is_zero(last_borrow);
+        Ok(())
+    }
+}
+
+fn two_pow(bits: u32) -> FieldElement {
+    FieldElement::from(2_u128).pow(&FieldElement::from(bits as u128))
+}
@@ -0,0 +1,146 @@
+//! Substitutes the witnesses which are fixed to a constant into the expressions referring to them.
+//!
+//! ACIR generation often asserts that a witness is equal to a constant, e.g. when a constant is
+//! given a witness to be returned or range constrained. The expressions generated later, such as
+//! predicates and bit recompositions, keep referring to that witness even though its value is
+//! known. Once the circuit is complete, each such witness is replaced by its value in every other
+//! expression, which lowers the degree of the expressions and may in turn fix other witnesses to
+//! a constant. The assertions which become trivial are then removed by deduplication, and the
+//! definitions of the constants which are no longer referred to by dead opcode removal.
+use std::collections::HashMap;
+
+use acvm::{
+    acir::{
+        circuit::{
+            brillig::BrilligInputs,
+            directives::{Directive, QuotientDirective},
+            Opcode as AcirOpcode,
+        },
+        native_types::{Expression, Witness},
+    },
+    FieldElement,
+};
+
+use super::{deduplication::NormalizedExpression, generated_acir::GeneratedAcir};
+
+impl GeneratedAcir {
+    /// Replaces the witnesses asserted to be equal to a constant by their value, in the
+    /// expressions of every opcode other than the assertions defining them.
+    pub(crate) fn propagate_constants(&mut self) {
+        loop {
+            let constants: HashMap<Witness, FieldElement> =
+                self.opcodes.iter().filter_map(constant_definition).collect();
+            if constants.is_empty() {
+                return;
+            }
+
+            let mut changed = false;
+            for opcode in &mut self.opcodes {
+                if constant_definition(opcode).is_none() {
+                    for_each_expression(opcode, &mut |expr| {
+                        if let Some(substituted) = substitute(expr, &constants) {
+                            *expr = substituted;
+                            changed = true;
+                        }
+                    });
+                }
+            }
+
+            if !changed {
+                return;
+            }
+        }
+    }
+}
+
+/// Returns the witness defined by the opcode along with its value, if the opcode asserts that
+/// a single witness is equal to a constant.
+pub(super) fn constant_definition(opcode: &AcirOpcode) -> Option<(Witness, FieldElement)> {
+    match opcode {
+        AcirOpcode::Arithmetic(expr) if expr.mul_terms.is_empty() => {
+            match expr.linear_combinations.as_slice() {
+                [(coefficient, witness)] if !coefficient.is_zero() => {
+                    Some((*witness, -expr.q_c / *coefficient))
+                }
+                _ => None,
+            }
+        }
+        _ => None,
+    }
+}
+
+/// Calls `f` on every expression read by the given opcode.
+///
+/// The expressions holding the witnesses an opcode writes to, such as the value of a memory
+/// read, are left out, as those witnesses must not be replaced.
+fn for_each_expression(opcode: &mut AcirOpcode, f: &mut impl FnMut(&mut Expression)) {
+    match opcode {
+        AcirOpcode::Arithmetic(expr) => f(expr),
+        AcirOpcode::Directive(Directive::Quotient(QuotientDirective {
+            a, b, predicate, ..
+        })) => {
+            f(a);
+            f(b);
+            if let Some(predicate) = predicate {
+                f(predicate);
+            }
+        }
+        AcirOpcode::Directive(Directive::ToLeRadix { a, .. }) => f(a),
+        AcirOpcode::Directive(Directive::PermutationSort { inputs, .. }) => {
+            inputs.iter_mut().flatten().for_each(f);
+        }
+        AcirOpcode::Brillig(brillig) => {
+            for input in &mut brillig.inputs {
+                match input {
+                    BrilligInputs::Single(expr) => f(expr),
+                    BrilligInputs::Array(exprs) => exprs.iter_mut().for_each(&mut *f),
+                }
+            }
+            if let Some(predicate) = &mut brillig.predicate {
+                f(predicate);
+            }
+        }
+        AcirOpcode::MemoryOp { op, .. } => f(&mut op.index),
+        AcirOpcode::Directive(Directive::Log(_))
+        | AcirOpcode::BlackBoxFuncCall(_)
+        | AcirOpcode::MemoryInit { .. } => (),
+    }
+}
+
+/// Returns the expression with each witness fixed to a constant replaced by its value, or None if
+/// it refers to no such witness.
+fn substitute(expr: &Expression, constants: &HashMap<Witness, FieldElement>) -> Option<Expression> {
+    let refers_to_constant = expr
+        .mul_terms
+        .iter()
+        .any(|(_, lhs, rhs)| constants.contains_key(lhs) || constants.contains_key(rhs))
+        || expr.linear_combinations.iter().any(|(_, witness)| constants.contains_key(witness));
+    if !refers_to_constant {
+        return None;
+    }
+
+    let mut substituted = Expression::from_field(expr.q_c);
+    for (coefficient, lhs, rhs) in &expr.mul_terms {
+        match (constants.get(lhs), constants.get(rhs)) {
+            (Some(lhs), Some(rhs)) => {
+                substituted.q_c = substituted.q_c + *coefficient * *lhs * *rhs
+            }
+            (Some(constant), None) => {
+                substituted.push_addition_term(*coefficient * *constant, *rhs)
+            }
+            (None, Some(constant)) => {
+                substituted.push_addition_term(*coefficient * *constant, *lhs)
+            }
+            (None, None) => substituted.push_multiplication_term(*coefficient, *lhs, *rhs),
+        }
+    }
+    for (coefficient, witness) in &expr.linear_combinations {
+        match constants.get(witness) {
+            Some(constant) => substituted.q_c = substituted.q_c + *coefficient * *constant,
+            None => substituted.push_addition_term(*coefficient, *witness),
+        }
+    }
+
+    // Terms over the same witnesses may now appear several times
+    Some(NormalizedExpression::new(&substituted).into_expression())
+}
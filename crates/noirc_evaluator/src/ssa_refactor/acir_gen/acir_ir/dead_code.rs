@@ -0,0 +1,96 @@
+//! Removes the opcodes which have no effect on the set of solutions of a circuit.
+//!
+//! This is the last cleanup applied to the generated ACIR, once assertions have been
+//! deduplicated. An opcode is removed when it:
+//! - asserts an expression which is always zero,
+//! - range constrains a witness which is defined to be a constant fitting within the range,
+//! - or only defines witnesses which no other opcode, return value or parameter refers to.
+//!
+//! A witness referred to by a single arithmetic opcode, and only linearly, can always be given
+//! the value which satisfies that opcode, so the opcode does not constrain the other witnesses.
+//! Removing an opcode may leave the witnesses it referred to unused in turn, so opcodes are
+//! removed until none is left to remove. The witnesses no longer referred to are dropped when
+//! witnesses are compacted.
+use std::collections::{HashMap, HashSet};
+
+use acvm::{
+    acir::{
+        circuit::{
+            brillig::BrilligOutputs,
+            directives::{Directive, QuotientDirective},
+            opcodes::BlackBoxFuncCall,
+            Opcode as AcirOpcode,
+        },
+        native_types::{Expression, Witness},
+    },
+    FieldElement,
+};
+
+use super::{
+    constant_propagation::constant_definition, deduplication::NormalizedExpression,
+    generated_acir::GeneratedAcir, witness_compaction::for_each_witness,
+};
+
+impl GeneratedAcir {
+    /// Removes the opcodes which do not constrain the parameters or return values of the
+    /// circuit, along with the assertions which always hold.
+    pub(crate) fn remove_dead_opcodes(&mut self) {
+        let mut live_witnesses: HashSet<Witness> = self.input_witnesses.iter().copied().collect();
+        live_witnesses.extend(self.return_witnesses.iter().copied());
+
+        loop {
+            let mut use_counts: HashMap<Witness, usize> = HashMap::new();
+            for opcode in &mut self.opcodes {
+                for_each_witness(opcode, &mut |witness| {
+                    *use_counts.entry(*witness).or_default() += 1;
+                });
+            }
+            let is_dead = |witness: &Witness| {
+                !live_witnesses.contains(witness) && use_counts.get(witness) == Some(&1)
+            };
+            let constants: HashMap<Witness, FieldElement> =
+                self.opcodes.iter().filter_map(constant_definition).collect();
+
+            let is_removable = |opcode: &AcirOpcode| match opcode {
+                AcirOpcode::Arithmetic(expr) => {
+                    NormalizedExpression::new(expr).is_zero() || defines_dead_witness(expr, is_dead)
+                }
+                AcirOpcode::BlackBoxFuncCall(BlackBoxFuncCall::RANGE { input }) => constants
+                    .get(&input.witness)
+                    .map_or(false, |constant| constant.num_bits() <= input.num_bits),
+                AcirOpcode::Directive(Directive::Quotient(QuotientDirective { q, r, .. })) => {
+                    is_dead(q) && is_dead(r)
+                }
+                AcirOpcode::Directive(Directive::ToLeRadix { b, .. }) => b.iter().all(is_dead),
+                AcirOpcode::Directive(Directive::PermutationSort { bits, .. }) => {
+                    bits.iter().all(is_dead)
+                }
+                // Brillig calls without outputs are only made for their side effects, e.g. printing
+                AcirOpcode::Brillig(brillig) => {
+                    !brillig.outputs.is_empty()
+                        && brillig.outputs.iter().all(|output| match output {
+                            BrilligOutputs::Simple(witness) => is_dead(witness),
+                            BrilligOutputs::Array(witnesses) => witnesses.iter().all(is_dead),
+                        })
+                }
+                _ => false,
+            };
+            let kept: Vec<bool> = self.opcodes.iter().map(|opcode| !is_removable(opcode)).collect();
+
+            if !kept.contains(&false) {
+                return;
+            }
+            self.retain_opcodes(&kept);
+        }
+    }
+}
+
+/// True if the expression refers to a dead witness linearly, in which case the witness can be
+/// given the value which makes the expression zero whatever the values of the other witnesses.
+///
+/// A dead witness is referred to only once, so it cannot also appear in a multiplication term.
+fn defines_dead_witness(expr: &Expression, is_dead: impl Fn(&Witness) -> bool) -> bool {
+    expr.linear_combinations
+        .iter()
+        .any(|(coefficient, witness)| !coefficient.is_zero() && is_dead(witness))
+}
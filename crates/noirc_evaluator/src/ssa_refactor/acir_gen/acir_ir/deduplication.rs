@@ -0,0 +1,138 @@
+//! Removes the redundant arithmetic opcodes left behind by ACIR generation.
+//!
+//! Different instructions often lead to the same expression being asserted to be zero, or to the
+//! same expression being turned into a witness, e.g. when several operations share a predicate.
+//! Intermediate witnesses are hash-consed as they are created, so an expression is only given one
+//! witness. Assertions are deduplicated once the whole circuit has been generated, since an
+//! assertion may only be known to be redundant once the expressions it refers to are complete.
+use std::collections::{hash_map::Entry, BTreeMap, HashMap};
+
+use acvm::{
+    acir::{
+        circuit::Opcode as AcirOpcode,
+        native_types::{Expression, Witness},
+    },
+    FieldElement,
+};
+
+use super::generated_acir::GeneratedAcir;
+
+/// An expression in a canonical form which can be compared and hashed: its terms are sorted,
+/// terms over the same witnesses are merged, and terms whose coefficient is zero are removed.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub(crate) struct NormalizedExpression {
+    mul_terms: Vec<(Witness, Witness, FieldElement)>,
+    linear_combinations: Vec<(Witness, FieldElement)>,
+    q_c: FieldElement,
+}
+
+impl NormalizedExpression {
+    pub(crate) fn new(expr: &Expression) -> Self {
+        let mut mul_terms: BTreeMap<(Witness, Witness), FieldElement> = BTreeMap::new();
+        for (coefficient, lhs, rhs) in &expr.mul_terms {
+            let witnesses = if lhs <= rhs { (*lhs, *rhs) } else { (*rhs, *lhs) };
+            let sum = mul_terms.entry(witnesses).or_insert_with(FieldElement::zero);
+            *sum = *sum + *coefficient;
+        }
+        let mut linear_combinations: BTreeMap<Witness, FieldElement> = BTreeMap::new();
+        for (coefficient, witness) in &expr.linear_combinations {
+            let sum = linear_combinations.entry(*witness).or_insert_with(FieldElement::zero);
+            *sum = *sum + *coefficient;
+        }
+
+        let mul_terms = mul_terms
+            .into_iter()
+            .filter(|(_, coefficient)| !coefficient.is_zero())
+            .map(|((lhs, rhs), coefficient)| (lhs, rhs, coefficient))
+            .collect();
+        let linear_combinations =
+            linear_combinations.into_iter().filter(|(_, coefficient)| !coefficient.is_zero());
+        Self { mul_terms, linear_combinations: linear_combinations.collect(), q_c: expr.q_c }
+    }
+
+    /// Scales the expression so that its first coefficient is one. The assertions that two
+    /// expressions are zero are equivalent if one expression is a multiple of the other.
+    fn into_assertion(mut self) -> Self {
+        let first_coefficient = self
+            .mul_terms
+            .first()
+            .map(|(_, _, coefficient)| *coefficient)
+            .or_else(|| self.linear_combinations.first().map(|(_, coefficient)| *coefficient))
+            .unwrap_or(self.q_c);
+        if first_coefficient.is_zero() || first_coefficient.is_one() {
+            return self;
+        }
+
+        let inverse = first_coefficient.inverse();
+        for (_, _, coefficient) in &mut self.mul_terms {
+            *coefficient = *coefficient * inverse;
+        }
+        for (_, coefficient) in &mut self.linear_combinations {
+            *coefficient = *coefficient * inverse;
+        }
+        self.q_c = self.q_c * inverse;
+        self
+    }
+
+    /// Converts the expression back into an [`Expression`], with its terms in canonical order.
+    pub(super) fn into_expression(self) -> Expression {
+        Expression {
+            mul_terms: self
+                .mul_terms
+                .into_iter()
+                .map(|(lhs, rhs, coefficient)| (coefficient, lhs, rhs))
+                .collect(),
+            linear_combinations: self
+                .linear_combinations
+                .into_iter()
+                .map(|(witness, coefficient)| (coefficient, witness))
+                .collect(),
+            q_c: self.q_c,
+        }
+    }
+
+    /// True if the expression is zero whatever the values of the witnesses
+    pub(super) fn is_zero(&self) -> bool {
+        self.mul_terms.is_empty() && self.linear_combinations.is_empty() && self.q_c.is_zero()
+    }
+}
+
+impl GeneratedAcir {
+    /// Removes each arithmetic opcode which asserts an expression that is always zero, or a
+    /// multiple of an expression already asserted to be zero by an earlier opcode.
+    ///
+    /// The message of a removed assertion is kept by the earlier opcode if it has none, so that it
+    /// is still reported when the constraint fails.
+    pub(crate) fn deduplicate_assertions(&mut self) {
+        // The index of the earliest opcode asserting each expression
+        let mut assertions = HashMap::new();
+        let mut kept = Vec::with_capacity(self.opcodes.len());
+        for (index, opcode) in self.opcodes.iter().enumerate() {
+            let assertion = match opcode {
+                AcirOpcode::Arithmetic(expr) => NormalizedExpression::new(expr).into_assertion(),
+                _ => {
+                    kept.push(true);
+                    continue;
+                }
+            };
+            if assertion.is_zero() {
+                kept.push(false);
+                continue;
+            }
+            match assertions.entry(assertion) {
+                Entry::Occupied(earlier) => {
+                    if let Some(message) = self.assert_messages.remove(&index) {
+                        self.assert_messages.entry(*earlier.get()).or_insert(message);
+                    }
+                    kept.push(false);
+                }
+                Entry::Vacant(entry) => {
+                    entry.insert(index);
+                    kept.push(true);
+                }
+            }
+        }
+
+        self.retain_opcodes(&kept);
+    }
+}
@@ -1,4 +1,4 @@
-use acvm::FieldElement;
+use acvm::{acir::BlackBoxFunc, FieldElement};
 use noirc_errors::Location;
 
 use crate::errors::{RuntimeError, RuntimeErrorKind};
@@ -9,6 +9,9 @@ pub(crate) enum AcirGenError {
     IndexOutOfBounds { index: usize, array_size: usize, location: Option<Location> },
     UnsupportedIntegerSize { num_bits: u32, max_num_bits: u32, location: Option<Location> },
     BadConstantEquality { lhs: FieldElement, rhs: FieldElement, location: Option<Location> },
+    UnsupportedAcirCall { name: String, location: Option<Location> },
+    UnsupportedBlackBox { name: BlackBoxFunc, location: Option<Location> },
+    InvalidBlackBoxInput { name: BlackBoxFunc, reason: String, location: Option<Location> },
 }
 
 impl AcirGenError {
@@ -28,6 +31,11 @@ impl AcirGenError {
             AcirGenError::BadConstantEquality { lhs, rhs, .. } => {
                 format!("{lhs} and {rhs} constrained to be equal though they never can be")
             }
+            AcirGenError::UnsupportedAcirCall { name, .. } => unsupported_acir_call_message(name),
+            AcirGenError::UnsupportedBlackBox { name, .. } => unsupported_black_box_message(*name),
+            AcirGenError::InvalidBlackBoxInput { name, reason, .. } => {
+                format!("Invalid input to the {name} black box function: {reason}")
+            }
         }
     }
 }
@@ -57,6 +65,27 @@ impl From<AcirGenError> for RuntimeError {
                 let kind = RuntimeErrorKind::FailedConstraint;
                 RuntimeError::new(kind, location)
             }
+            AcirGenError::UnsupportedAcirCall { name, location } => {
+                let kind = RuntimeErrorKind::Unimplemented(unsupported_acir_call_message(&name));
+                RuntimeError::new(kind, location)
+            }
+            AcirGenError::UnsupportedBlackBox { name, location } => {
+                let kind = RuntimeErrorKind::Unimplemented(unsupported_black_box_message(name));
+                RuntimeError::new(kind, location)
+            }
+            AcirGenError::InvalidBlackBoxInput { name, reason, location } => {
+                let kind =
+                    RuntimeErrorKind::InvalidBlackBoxInput { name: name.to_string(), reason };
+                RuntimeError::new(kind, location)
+            }
         }
     }
 }
+
+fn unsupported_acir_call_message(name: &str) -> String {
+    format!("Calls to ACIR functions are not yet supported but `{name}` was not inlined.")
+}
+
+fn unsupported_black_box_message(name: BlackBoxFunc) -> String {
+    format!("The backend does not support the {name} black box function")
+}
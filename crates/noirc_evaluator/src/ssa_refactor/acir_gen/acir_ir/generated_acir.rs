@@ -1,16 +1,19 @@
 //! `GeneratedAcir` is constructed as part of the `acir_gen` pass to accumulate all of the ACIR
 //! program as it is being converted from SSA form.
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap, HashSet};
 
 use crate::brillig::brillig_gen::brillig_directive;
+use crate::ssa_refactor::backend_capabilities::BackendCapabilities;
 
-use super::errors::AcirGenError;
+use super::{
+    deduplication::NormalizedExpression, errors::AcirGenError, witness_compaction::for_each_witness,
+};
 use acvm::acir::{
     brillig::Opcode as BrilligOpcode,
     circuit::{
         brillig::{Brillig as AcvmBrillig, BrilligInputs, BrilligOutputs},
-        directives::{LogInfo, QuotientDirective},
-        opcodes::{BlackBoxFuncCall, FunctionInput, Opcode as AcirOpcode},
+        directives::QuotientDirective,
+        opcodes::{BlackBoxFuncCall, BlockId, FunctionInput, MemOp, Opcode as AcirOpcode},
     },
     native_types::Witness,
     BlackBoxFunc,
@@ -20,7 +23,7 @@ use acvm::{
     FieldElement,
 };
 use iter_extended::vecmap;
-use noirc_errors::Location;
+use noirc_errors::{debug_info::OpcodeProvenance, Location};
 use num_bigint::BigUint;
 
 #[derive(Debug, Default)]
@@ -40,12 +43,71 @@ pub(crate) struct GeneratedAcir {
     /// abi's return type.
     pub(crate) return_witnesses: Vec<Witness>,
 
+    /// The witnesses of the program's parameters, in the order they were declared
+    pub(crate) input_witnesses: Vec<Witness>,
+
     /// Correspondance between an opcode index (in opcodes) and the source code location which generated it
     pub(crate) locations: HashMap<usize, Location>,
 
     /// Source code location of the current instruction being processed
     /// None if we do not know the location
     pub(crate) current_location: Option<Location>,
+
+    /// Correspondance between an opcode index and the message of the assertion which generated it
+    pub(crate) assert_messages: HashMap<usize, String>,
+
+    /// The message of the assertion currently being converted, if it was given one
+    pub(crate) current_assert_message: Option<String>,
+
+    /// Correspondance between an opcode index and what generated it, if provenance is recorded
+    pub(crate) provenance: Option<HashMap<usize, OpcodeProvenance>>,
+
+    /// The kind of the SSA instruction currently being converted, if provenance is recorded
+    pub(crate) current_instruction: Option<String>,
+
+    /// What the opcodes currently being generated are for, within the lowering of the current
+    /// instruction
+    current_purpose: Option<&'static str>,
+
+    /// Correspondance between the index of a Brillig opcode and the source code location of each
+    /// opcode of its bytecode, if Brillig locations are recorded
+    pub(crate) brillig_locations: Option<HashMap<usize, BTreeMap<usize, Location>>>,
+
+    /// The features of the backend the ACIR is generated for, which determine the width of
+    /// arithmetic opcodes and which black box functions may be called.
+    pub(crate) capabilities: BackendCapabilities,
+
+    /// The tightest range constraint enforced on each witness so far, as the index of the
+    /// `RANGE` opcode enforcing it along with its number of bits.
+    range_constraints: HashMap<Witness, (usize, u32)>,
+
+    /// The witnesses which the opcodes defining them already constrain to be zero or one, so
+    /// that they need no further range constraint.
+    boolean_witnesses: HashSet<Witness>,
+
+    /// Whether each memory block initialized so far is read-only. Each block holds the elements
+    /// of one array.
+    pub(crate) memory_blocks: HashMap<BlockId, MemoryKind>,
+
+    /// The little endian limbs of each witness decomposed so far, by witness and radix.
+    radix_decompositions: HashMap<(Witness, u32), Vec<Witness>>,
+
+    /// The witness created for each expression so far, so that the same expression is only
+    /// given one intermediate witness.
+    intermediate_witnesses: HashMap<NormalizedExpression, Witness>,
+
+    /// The witnesses referred to by the opcodes so far. Opcodes are solved in order, so each of
+    /// these is known by the time the next opcode is solved.
+    solved_witnesses: HashSet<Witness>,
+}
+
+/// Whether a memory block is written to after it is initialized.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub(crate) enum MemoryKind {
+    /// Read-only memory, which backends may implement with a cheaper ROM argument.
+    Rom,
+    /// Read-write memory, which requires a RAM argument.
+    Ram,
 }
 
 impl GeneratedAcir {
@@ -56,10 +118,135 @@ impl GeneratedAcir {
 
     /// Adds a new opcode into ACIR.
     fn push_opcode(&mut self, opcode: AcirOpcode) {
+        let opcode = match opcode {
+            AcirOpcode::Arithmetic(expr) => AcirOpcode::Arithmetic(self.fit_to_width(expr)),
+            opcode => opcode,
+        };
         self.opcodes.push(opcode);
+        let solved_witnesses = &mut self.solved_witnesses;
+        let opcode = self.opcodes.last_mut().expect("An opcode was just pushed");
+        for_each_witness(opcode, &mut |witness| {
+            solved_witnesses.insert(*witness);
+        });
         if let Some(location) = self.current_location {
             self.locations.insert(self.opcodes.len() - 1, location);
         }
+        if let Some(message) = &self.current_assert_message {
+            self.assert_messages.insert(self.opcodes.len() - 1, message.clone());
+        }
+        self.record_provenance(self.opcodes.len() - 1);
+    }
+
+    /// Records the current instruction and purpose as the provenance of the opcode at `index`,
+    /// if provenance is recorded.
+    fn record_provenance(&mut self, index: usize) {
+        if let (Some(provenance), Some(instruction)) =
+            (&mut self.provenance, &self.current_instruction)
+        {
+            let purpose = self.current_purpose.map(str::to_owned);
+            provenance
+                .insert(index, OpcodeProvenance { instruction: instruction.clone(), purpose });
+        }
+    }
+
+    /// Sets what the opcodes generated from now on are for, unless they are already generated
+    /// for an enclosing purpose. Returns the previous purpose, to be restored with
+    /// `restore_purpose` once these opcodes are generated.
+    pub(crate) fn set_purpose(&mut self, purpose: &'static str) -> Option<&'static str> {
+        let previous = self.current_purpose;
+        self.current_purpose = previous.or(Some(purpose));
+        previous
+    }
+
+    /// Restores the purpose which was current before the last call to `set_purpose`.
+    pub(crate) fn restore_purpose(&mut self, previous: Option<&'static str>) {
+        self.current_purpose = previous;
+    }
+
+    /// Initializes the memory block `block_id` with the given witnesses. The block is read-only
+    /// until it is first written to.
+    pub(crate) fn initialize_memory(&mut self, block_id: BlockId, init: Vec<Witness>) {
+        self.memory_blocks.insert(block_id, MemoryKind::Rom);
+        self.push_opcode(AcirOpcode::MemoryInit { block_id, init });
+    }
+
+    /// Constrains `value` to be the element at `index` in the memory block `block_id`.
+    pub(crate) fn read_from_memory(&mut self, block_id: BlockId, index: Witness, value: Witness) {
+        let op = MemOp::read_at_mem_index(index.into(), value);
+        self.push_opcode(AcirOpcode::MemoryOp { block_id, op });
+    }
+
+    /// Writes `value` to `index` in the memory block `block_id`, which makes the block read-write.
+    pub(crate) fn write_to_memory(&mut self, block_id: BlockId, index: Witness, value: Witness) {
+        self.memory_blocks.insert(block_id, MemoryKind::Ram);
+        let op = MemOp::write_to_mem_index(index.into(), value.into());
+        self.push_opcode(AcirOpcode::MemoryOp { block_id, op });
+    }
+
+    /// Moves terms of `expr` into intermediate witnesses until it fits within the expression
+    /// width, returning the remaining expression.
+    ///
+    /// Each intermediate witness is constrained by its own opcode, which is packed with as many
+    /// terms as fit alongside the intermediate witness itself: at most one multiplication term,
+    /// followed by linear terms. Intermediate witnesses are added to the remaining expression
+    /// as linear terms, so they may be packed into later intermediate witnesses in turn.
+    ///
+    /// Only terms whose witnesses are known before `expr` is solved are moved, which are the
+    /// program's inputs and the witnesses of earlier opcodes. The intermediate opcodes are pushed
+    /// before `expr`, so each can be solved for its intermediate witness alone, and `expr` keeps
+    /// any witness it is solved for.
+    fn fit_to_width(&mut self, mut expr: Expression) -> Expression {
+        // Each intermediate opcode must refer to at least two terms besides its own witness
+        // for splitting an expression to make progress.
+        let width = match self.capabilities.expression_width {
+            Some(width) if width >= 3 => width,
+            _ => return expr,
+        };
+
+        while !fits_in_width(&expr, width) {
+            let can_move = |witness: Witness| {
+                self.solved_witnesses.contains(&witness) || self.input_witnesses.contains(&witness)
+            };
+
+            let mut intermediate = Expression::default();
+
+            if expr.mul_terms.len() > 1 {
+                let position = expr
+                    .mul_terms
+                    .iter()
+                    .position(|(_, lhs, rhs)| can_move(*lhs) && can_move(*rhs));
+                match position {
+                    Some(position) => intermediate.mul_terms.push(expr.mul_terms.remove(position)),
+                    None => break,
+                }
+            }
+
+            // Leave room for the intermediate witness itself
+            let mut index = 0;
+            while index < expr.linear_combinations.len() {
+                let term = expr.linear_combinations[index];
+                if !can_move(term.1) {
+                    index += 1;
+                    continue;
+                }
+                intermediate.linear_combinations.push(term);
+                if !fits_in_width(&intermediate, width - 1) {
+                    intermediate.linear_combinations.pop();
+                    break;
+                }
+                expr.linear_combinations.remove(index);
+            }
+
+            if intermediate.mul_terms.is_empty() && intermediate.linear_combinations.len() < 2 {
+                // Moving a single linear term makes no progress
+                expr.linear_combinations.extend(intermediate.linear_combinations);
+                break;
+            }
+
+            let witness = self.create_witness_for_expression(&intermediate);
+            expr.push_addition_term(FieldElement::one(), witness);
+        }
+        expr
     }
 
     /// Updates the witness index counter and returns
@@ -71,11 +258,15 @@ impl GeneratedAcir {
 
     /// Converts [`Expression`] `expr` into a [`Witness`].
     ///
-    /// If `expr` can be represented as a `Witness` then this function will return it,
-    /// else a new opcode will be added to create a `Witness` that is equal to `expr`.
+    /// If `expr` can be represented as a `Witness` then this function will return it, or the
+    /// witness already created for an identical expression if there is one, else a new opcode
+    /// will be added to create a `Witness` that is equal to `expr`.
     pub(crate) fn get_or_create_witness(&mut self, expr: &Expression) -> Witness {
-        match expr.to_witness() {
-            Some(witness) => witness,
+        if let Some(witness) = expr.to_witness() {
+            return witness;
+        }
+        match self.intermediate_witnesses.get(&NormalizedExpression::new(expr)) {
+            Some(witness) => *witness,
             None => self.create_witness_for_expression(expr),
         }
     }
@@ -102,6 +293,7 @@ impl GeneratedAcir {
         //  => expression == fresh_witness
         self.assert_is_zero(constraint);
 
+        self.intermediate_witnesses.insert(NormalizedExpression::new(expression), fresh_witness);
         fresh_witness
     }
 
@@ -109,6 +301,21 @@ impl GeneratedAcir {
     pub(crate) fn push_return_witness(&mut self, witness: Witness) {
         self.return_witnesses.push(witness);
     }
+
+    /// Ensures that no return witness is also a parameter witness or another return witness.
+    ///
+    /// Only the return witnesses which alias a parameter or an earlier return witness are
+    /// replaced, by fresh witnesses constrained to be equal to them.
+    pub(crate) fn make_return_witnesses_distinct(&mut self) {
+        let mut seen_witnesses: HashSet<Witness> = self.input_witnesses.iter().copied().collect();
+        for index in 0..self.return_witnesses.len() {
+            let return_witness = self.return_witnesses[index];
+            if !seen_witnesses.insert(return_witness) {
+                self.return_witnesses[index] =
+                    self.create_witness_for_expression(&Expression::from(return_witness));
+            }
+        }
+    }
 }
 
 impl GeneratedAcir {
@@ -178,13 +385,34 @@ impl GeneratedAcir {
                 input: inputs[0],
                 outputs: (outputs[0], outputs[1]),
             },
-            BlackBoxFunc::Keccak256 => {
+            // The message size is only passed as an input if it is not known to be the length
+            // of the whole message at compile time
+            BlackBoxFunc::Keccak256 if constants.is_empty() => {
                 let var_message_size = inputs.pop().expect("ICE: Missing message_size arg");
                 BlackBoxFuncCall::Keccak256VariableLength { inputs, var_message_size, outputs }
             }
-            // TODO(#1570): Generate ACIR for recursive aggregation
+            BlackBoxFunc::Keccak256 => BlackBoxFuncCall::Keccak256 { inputs, outputs },
             BlackBoxFunc::RecursiveAggregation => {
-                panic!("ICE: Cannot generate ACIR for recursive aggregation")
+                let mut inputs = inputs.into_iter();
+                let mut take = |length: FieldElement| {
+                    inputs.by_ref().take(length.to_u128() as usize).collect::<Vec<_>>()
+                };
+                let verification_key = take(constants[0]);
+                let proof = take(constants[1]);
+                let public_inputs = take(constants[2]);
+                let key_hash = inputs.next().expect("ICE: Missing key hash arg");
+                // The first verification in a circuit has no aggregation object to accumulate into
+                let input_aggregation_object: Vec<_> = inputs.collect();
+                let input_aggregation_object =
+                    (!input_aggregation_object.is_empty()).then_some(input_aggregation_object);
+                BlackBoxFuncCall::RecursiveAggregation {
+                    verification_key,
+                    proof,
+                    public_inputs,
+                    key_hash,
+                    input_aggregation_object,
+                    output_aggregation_object: outputs,
+                }
             }
         };
 
@@ -197,6 +425,9 @@ impl GeneratedAcir {
     /// decomposed from the input for the given radix and limb count.
     ///
     /// Only radix that are a power of two are supported
+    ///
+    /// A witness which was already decomposed over the same radix into at least as many limbs
+    /// reuses those limbs, and any limbs beyond `limb_count` are constrained to be zero.
     pub(crate) fn radix_le_decompose(
         &mut self,
         input_expr: &Expression,
@@ -211,6 +442,19 @@ impl GeneratedAcir {
             "ICE: Radix must be a power of 2"
         );
 
+        let input_witness = input_expr.to_witness();
+        if let Some(limbs) = input_witness.and_then(|w| self.radix_decompositions.get(&(w, radix)))
+        {
+            if limbs.len() >= limb_count as usize {
+                let (limbs, extra_limbs) = limbs.split_at(limb_count as usize);
+                let (limbs, extra_limbs) = (limbs.to_vec(), extra_limbs.to_vec());
+                for extra_limb in extra_limbs {
+                    self.assert_is_zero(extra_limb.into());
+                }
+                return Ok(limbs);
+            }
+        }
+
         let limb_witnesses = vecmap(0..limb_count, |_| self.next_witness_index());
         self.push_opcode(AcirOpcode::Directive(Directive::ToLeRadix {
             a: input_expr.clone(),
@@ -234,6 +478,9 @@ impl GeneratedAcir {
 
         self.assert_is_zero(input_expr - &composed_limbs);
 
+        if let Some(input_witness) = input_witness {
+            self.radix_decompositions.insert((input_witness, radix), limb_witnesses.clone());
+        }
         Ok(limb_witnesses)
     }
 
@@ -312,31 +559,27 @@ impl GeneratedAcir {
     // sign(a)a = q1*sign(b)b + r1
     // => a = sign(a)sign(b)q1*b + sign(a)r1
     // => a = qb+r, with |r|<|b| and a and r have the same sign.
+    //
+    // Both the operands and the results are in two's complement, so the quotient of the minimum
+    // value by -1 wraps around to the minimum value.
     pub(crate) fn signed_division(
         &mut self,
         lhs: &Expression,
         rhs: &Expression,
         max_bit_size: u32,
+        predicate: &Expression,
     ) -> Result<(Expression, Expression), AcirGenError> {
         // 2^{max_bit size-1}
         let max_power_of_two =
             FieldElement::from(2_i128).pow(&FieldElement::from(max_bit_size as i128 - 1));
 
         // Get the sign bit of rhs by computing rhs / max_power_of_two
-        let (rhs_leading, _) = self.euclidean_division(
-            rhs,
-            &max_power_of_two.into(),
-            max_bit_size,
-            &Expression::one(),
-        )?;
+        let (rhs_leading, _) =
+            self.euclidean_division(rhs, &max_power_of_two.into(), max_bit_size, predicate)?;
 
         // Get the sign bit of lhs by computing lhs / max_power_of_two
-        let (lhs_leading, _) = self.euclidean_division(
-            lhs,
-            &max_power_of_two.into(),
-            max_bit_size,
-            &Expression::one(),
-        )?;
+        let (lhs_leading, _) =
+            self.euclidean_division(lhs, &max_power_of_two.into(), max_bit_size, predicate)?;
 
         // Signed to unsigned:
         let unsigned_lhs = self.two_complement(lhs, lhs_leading, max_bit_size);
@@ -344,12 +587,13 @@ impl GeneratedAcir {
         let unsigned_l_witness = self.get_or_create_witness(&unsigned_lhs);
         let unsigned_r_witness = self.get_or_create_witness(&unsigned_rhs);
 
-        // Performs the division using the unsigned values of lhs and rhs
+        // Performs the division using the unsigned values of lhs and rhs.
+        // The magnitude of the minimum value is 2^{max_bit_size-1}, which needs all the bits.
         let (q1, r1) = self.euclidean_division(
             &unsigned_l_witness.into(),
             &unsigned_r_witness.into(),
-            max_bit_size - 1,
-            &Expression::one(),
+            max_bit_size,
+            predicate,
         )?;
 
         // Unsigned to signed: derive q and r from q1,r1 and the signs of lhs and rhs
@@ -359,11 +603,28 @@ impl GeneratedAcir {
             &(&Expression::from(lhs_leading) * &Expression::from(rhs_leading)).unwrap(),
         );
         let q_sign_witness = self.get_or_create_witness(&q_sign);
-        let quotient = self.two_complement(&q1.into(), q_sign_witness, max_bit_size);
-        let remainder = self.two_complement(&r1.into(), lhs_leading, max_bit_size);
+        let quotient = self.signed_from_magnitude(q1, q_sign_witness, max_bit_size);
+        let remainder = self.signed_from_magnitude(r1, lhs_leading, max_bit_size);
         Ok((quotient, remainder))
     }
 
+    // Returns the two's complement representation of the integer whose magnitude is `magnitude`
+    // and whose sign bit is `sign`.
+    // Unlike with `two_complement`, a magnitude of zero is zero whatever its sign, rather than
+    // 2^bit_size, which does not fit within bit_size bits.
+    fn signed_from_magnitude(
+        &mut self,
+        magnitude: Witness,
+        sign: Witness,
+        max_bit_size: u32,
+    ) -> Expression {
+        let is_zero = self.is_equal(&magnitude.into(), &Expression::default());
+        let is_negative =
+            self.mul_with_witness(&sign.into(), &(&Expression::one() - &Expression::from(is_zero)));
+        let is_negative = self.get_or_create_witness(&is_negative);
+        self.two_complement(&magnitude.into(), is_negative, max_bit_size)
+    }
+
     /// Computes lhs/rhs by using euclidean division.
     ///
     /// Returns `q` for quotient and `r` for remainder such
@@ -406,7 +667,12 @@ impl GeneratedAcir {
         // When the predicate is 0, the equation always passes.
         // When the predicate is 1, the euclidean division needs to be
         // true.
-        let rhs_constraint = &self.mul_with_witness(rhs, &q_witness.into()) + r_witness;
+        let rhs_times_q = if max_q_bits + max_rhs_bits < FieldElement::max_num_bits() {
+            self.mul_with_witness(rhs, &q_witness.into())
+        } else {
+            self.wide_product(rhs, &q_witness.into(), max_bit_size, predicate)?
+        };
+        let rhs_constraint = &rhs_times_q + r_witness;
         let div_euclidean = &self.mul_with_witness(lhs, predicate)
             - &self.mul_with_witness(&rhs_constraint, predicate);
 
@@ -415,6 +681,43 @@ impl GeneratedAcir {
         Ok((q_witness, r_witness))
     }
 
+    /// Returns an expression equal to `lhs * rhs`, for two integers of `max_bit_size` bits whose
+    /// product must also fit within `max_bit_size` bits, when the product of any two such
+    /// integers may not fit within a field element.
+    ///
+    /// Splitting each operand into a low limb of `h = ceil(max_bit_size / 2)` bits and a high
+    /// limb, `a = a_1 * 2^h + a_0`, the product is `a_0 * b_0 + 2^h * (a_0 * b_1 + a_1 * b_0)`
+    /// once `a_1 * b_1` is constrained to be zero, as a non-zero `a_1 * b_1 * 2^{2h}` would not
+    /// fit within `max_bit_size` bits. Every term of this sum fits within a field element, so
+    /// the field does not wrap around as it could with `lhs * rhs`.
+    fn wide_product(
+        &mut self,
+        lhs: &Expression,
+        rhs: &Expression,
+        max_bit_size: u32,
+        predicate: &Expression,
+    ) -> Result<Expression, AcirGenError> {
+        let low_bits = (max_bit_size + 1) / 2;
+        let low_radix = FieldElement::from(2_u128).pow(&FieldElement::from(low_bits as u128));
+
+        // The decompositions always hold, so they are not guarded by the predicate
+        let radix = Expression::from_field(low_radix);
+        let one = Expression::one();
+        let (lhs_high, lhs_low) = self.euclidean_division(lhs, &radix, max_bit_size, &one)?;
+        let (rhs_high, rhs_low) = self.euclidean_division(rhs, &radix, max_bit_size, &one)?;
+
+        // predicate * a_1 * b_1 == 0
+        let high_product = self.mul_with_witness(&lhs_high.into(), &rhs_high.into());
+        let no_overflow = self.mul_with_witness(&high_product, predicate);
+        self.push_opcode(AcirOpcode::Arithmetic(no_overflow));
+
+        let lhs_cross = self.mul_with_witness(&lhs_low.into(), &rhs_high.into());
+        let rhs_cross = self.mul_with_witness(&lhs_high.into(), &rhs_low.into());
+        let cross = self.get_or_create_witness(&(&lhs_cross + &rhs_cross));
+        let low_product = self.mul_with_witness(&lhs_low.into(), &rhs_low.into());
+        Ok(low_product.add_mul(low_radix, &cross.into()))
+    }
+
     /// Generate constraints that are satisfied iff
     /// lhs < rhs , when offset is 1, or
     /// lhs <= rhs, when offset is 0
@@ -499,13 +802,6 @@ impl GeneratedAcir {
         &expr_squared - expr
     }
 
-    /// Adds a log directive to print the provided witnesses.
-    ///
-    /// Logging of strings is currently unsupported.
-    pub(crate) fn call_print(&mut self, witnesses: Vec<Witness>) {
-        self.push_opcode(AcirOpcode::Directive(Directive::Log(LogInfo::WitnessOutput(witnesses))));
-    }
-
     /// Adds an inversion brillig opcode.
     ///
     /// This code will invert `expr` without applying constraints
@@ -522,7 +818,7 @@ impl GeneratedAcir {
         let inverse_code = brillig_directive::directive_invert();
         let inputs = vec![BrilligInputs::Single(expr)];
         let outputs = vec![BrilligOutputs::Simple(inverted_witness)];
-        self.brillig(Some(Expression::one()), inverse_code, inputs, outputs);
+        self.brillig(Some(Expression::one()), inverse_code, inputs, outputs, BTreeMap::new());
 
         inverted_witness
     }
@@ -623,15 +919,32 @@ impl GeneratedAcir {
         };
         self.assert_is_zero(ty_zero_constraint);
 
+        // Together, both constraints imply that `y` is boolean
+        self.boolean_witnesses.insert(y);
         y
     }
 
     /// Adds a constraint which ensure thats `witness` is an
     /// integer within the range `[0, 2^{num_bits} - 1]`
+    ///
+    /// If `witness` is already constrained to `num_bits` or fewer, or is known to be boolean,
+    /// no constraint is added. If it is constrained to more bits, the existing constraint is
+    /// tightened instead.
     pub(crate) fn range_constraint(
         &mut self,
         witness: Witness,
         num_bits: u32,
+    ) -> Result<(), AcirGenError> {
+        let previous_purpose = self.set_purpose("range check");
+        let result = self.push_range_constraint(witness, num_bits);
+        self.restore_purpose(previous_purpose);
+        result
+    }
+
+    fn push_range_constraint(
+        &mut self,
+        witness: Witness,
+        num_bits: u32,
     ) -> Result<(), AcirGenError> {
         // We class this as an error because users should instead
         // do `as Field`.
@@ -642,14 +955,115 @@ impl GeneratedAcir {
             });
         };
 
+        if num_bits >= 1 && self.boolean_witnesses.contains(&witness) {
+            return Ok(());
+        }
+
+        if !self.capabilities.supports_black_box(BlackBoxFunc::RANGE) {
+            return self.range_constraint_by_bits(witness, num_bits);
+        }
+
         let constraint = AcirOpcode::BlackBoxFuncCall(BlackBoxFuncCall::RANGE {
             input: FunctionInput { witness, num_bits },
         });
-        self.push_opcode(constraint);
+        match self.range_constraints.get(&witness) {
+            Some((_, enforced_bits)) if *enforced_bits <= num_bits => (),
+            Some((index, _)) => {
+                let index = *index;
+                self.opcodes[index] = constraint;
+                if let Some(location) = self.current_location {
+                    self.locations.insert(index, location);
+                }
+                self.record_provenance(index);
+                self.range_constraints.insert(witness, (index, num_bits));
+            }
+            None => {
+                self.push_opcode(constraint);
+                self.range_constraints.insert(witness, (self.opcodes.len() - 1, num_bits));
+            }
+        }
 
         Ok(())
     }
 
+    /// Keeps only the opcodes marked as kept, moving the location, assertion message, provenance
+    /// and Brillig locations of each of them to its new index.
+    ///
+    /// The range constraints whose `RANGE` opcode was removed are forgotten.
+    pub(super) fn retain_opcodes(&mut self, kept: &[bool]) {
+        let opcodes = std::mem::take(&mut self.opcodes);
+        let mut locations = std::mem::take(&mut self.locations);
+        let mut assert_messages = std::mem::take(&mut self.assert_messages);
+        let mut provenance = self.provenance.as_mut().map(std::mem::take);
+        let mut brillig_locations = self.brillig_locations.as_mut().map(std::mem::take);
+
+        // The index of each opcode which is kept, within the new list of opcodes
+        let mut new_indices = Vec::with_capacity(opcodes.len());
+        for (index, opcode) in opcodes.into_iter().enumerate() {
+            if !kept[index] {
+                new_indices.push(None);
+                continue;
+            }
+
+            let new_index = self.opcodes.len();
+            new_indices.push(Some(new_index));
+            if let Some(location) = locations.remove(&index) {
+                self.locations.insert(new_index, location);
+            }
+            if let Some(message) = assert_messages.remove(&index) {
+                self.assert_messages.insert(new_index, message);
+            }
+            if let (Some(provenance), Some(new_provenance)) =
+                (&mut provenance, &mut self.provenance)
+            {
+                if let Some(entry) = provenance.remove(&index) {
+                    new_provenance.insert(new_index, entry);
+                }
+            }
+            if let (Some(brillig_locations), Some(new_brillig_locations)) =
+                (&mut brillig_locations, &mut self.brillig_locations)
+            {
+                if let Some(entry) = brillig_locations.remove(&index) {
+                    new_brillig_locations.insert(new_index, entry);
+                }
+            }
+            self.opcodes.push(opcode);
+        }
+
+        self.range_constraints.retain(|_, (index, _)| match new_indices[*index] {
+            Some(new_index) => {
+                *index = new_index;
+                true
+            }
+            None => false,
+        });
+    }
+
+    /// Constrains `witness` to fit within `num_bits` bits by decomposing it into bits, for
+    /// backends which do not support the `RANGE` black box function. Each bit is constrained to
+    /// be boolean by an arithmetic opcode.
+    fn range_constraint_by_bits(
+        &mut self,
+        witness: Witness,
+        num_bits: u32,
+    ) -> Result<(), AcirGenError> {
+        match self.range_constraints.get(&witness) {
+            Some((_, enforced_bits)) if *enforced_bits <= num_bits => return Ok(()),
+            _ => (),
+        }
+
+        if num_bits == 1 {
+            let mut boolean = Expression::default();
+            boolean.push_multiplication_term(FieldElement::one(), witness, witness);
+            boolean.push_addition_term(-FieldElement::one(), witness);
+            self.assert_is_zero(boolean);
+        } else {
+            self.radix_le_decompose(&witness.into(), 2, num_bits, 1)?;
+        }
+        self.range_constraints.insert(witness, (self.opcodes.len() - 1, num_bits));
+        Ok(())
+    }
+
     /// Adds a directive which injects witnesses with values `q = a / b` and `r = a % b`.
     ///
     /// Suitable range constraints are also applied to `q` and `r`.
@@ -739,6 +1153,7 @@ impl GeneratedAcir {
         code: Vec<BrilligOpcode>,
         inputs: Vec<BrilligInputs>,
         outputs: Vec<BrilligOutputs>,
+        locations: BTreeMap<usize, Location>,
     ) {
         let opcode = AcirOpcode::Brillig(AcvmBrillig {
             inputs,
@@ -748,6 +1163,11 @@ impl GeneratedAcir {
             predicate,
         });
         self.push_opcode(opcode);
+        if let Some(brillig_locations) = &mut self.brillig_locations {
+            if !locations.is_empty() {
+                brillig_locations.insert(self.opcodes.len() - 1, locations);
+            }
+        }
     }
 
     /// Generate gates and control bits witnesses which ensure that out_expr is a permutation of in_expr
@@ -806,15 +1226,33 @@ fn black_box_func_expected_input_size(name: BlackBoxFunc) -> Option<usize> {
         // Inputs for fixed based scalar multiplication
         // is just a scalar
         BlackBoxFunc::FixedBaseScalarMul => Some(1),
-        // TODO(#1570): Generate ACIR for recursive aggregation
-        // RecursiveAggregation has variable inputs and we could return `None` here,
-        // but as it is not fully implemented we panic for now
-        BlackBoxFunc::RecursiveAggregation => {
-            panic!("ICE: Cannot generate ACIR for recursive aggregation")
-        }
+        // The verification key, proof, public inputs, and aggregation object
+        // of a recursive proof verification each have a variable length.
+        BlackBoxFunc::RecursiveAggregation => None,
     }
 }
 
+/// Returns true if `expr` fits within an arithmetic opcode of the given expression width.
+fn fits_in_width(expr: &Expression, width: usize) -> bool {
+    if expr.mul_terms.len() > 1 {
+        return false;
+    }
+
+    let is_linear_term = |witness: &Witness| {
+        expr.linear_combinations.iter().any(|(_, linear_witness)| linear_witness == witness)
+    };
+    let mut witnesses = expr.linear_combinations.len();
+    for (_, lhs, rhs) in &expr.mul_terms {
+        witnesses += usize::from(!is_linear_term(lhs));
+        witnesses += usize::from(rhs != lhs && !is_linear_term(rhs));
+    }
+    witnesses <= width
+}
+
+/// The number of field elements in the aggregation object of a recursive proof verification.
+/// It holds two points, each coordinate of which is split into four limbs.
+const AGGREGATION_OBJECT_SIZE: u32 = 16;
+
 /// This function will return the number of outputs that a blackbox function
 /// expects. Returning `None` if there is no expectation.
 fn black_box_expected_output_size(name: BlackBoxFunc) -> u32 {
@@ -838,10 +1276,9 @@ fn black_box_expected_output_size(name: BlackBoxFunc) -> u32 {
         // Output of fixed based scalar mul over the embedded curve
         // will be 2 field elements representing the point.
         BlackBoxFunc::FixedBaseScalarMul => 2,
-        // TODO(#1570): Generate ACIR for recursive aggregation
-        BlackBoxFunc::RecursiveAggregation => {
-            panic!("ICE: Cannot generate ACIR for recursive aggregation")
-        }
+        // Recursive proof verification returns the aggregation object accumulating
+        // the pairing points of each verified proof.
+        BlackBoxFunc::RecursiveAggregation => AGGREGATION_OBJECT_SIZE,
     }
 }
 
@@ -0,0 +1,214 @@
+//! Renumbers the witnesses of a circuit once it has been generated.
+//!
+//! Witness indices are handed out as ACIR generation goes, and some of the witnesses created are
+//! never referred to by the final circuit, e.g. once redundant assertions have been removed.
+//! Backends allocate memory for every index up to `current_witness_index`, so the witnesses which
+//! are used are given consecutive indices, in the same order as before. Parameter witnesses keep
+//! the lowest indices, so the witness ranges of the ABI parameters are preserved.
+use std::collections::{BTreeMap, BTreeSet};
+
+use acvm::acir::{
+    circuit::{
+        brillig::{BrilligInputs, BrilligOutputs},
+        directives::{Directive, LogInfo, QuotientDirective},
+        opcodes::{BlackBoxFuncCall, FunctionInput},
+        Opcode as AcirOpcode,
+    },
+    native_types::{Expression, Witness},
+};
+
+use super::generated_acir::GeneratedAcir;
+
+impl GeneratedAcir {
+    /// Drops the witnesses which are not referred to by any opcode or return value, other than
+    /// those in `kept_witnesses`, and renumbers the remaining witnesses consecutively from one.
+    ///
+    /// Returns the new index of each remaining witness. This must only be called once the
+    /// circuit is complete, as no further opcode may refer to the previous indices.
+    pub(crate) fn compact_witnesses(
+        &mut self,
+        kept_witnesses: impl IntoIterator<Item = Witness>,
+    ) -> BTreeMap<Witness, Witness> {
+        let mut used_witnesses: BTreeSet<Witness> = kept_witnesses.into_iter().collect();
+        used_witnesses.extend(self.return_witnesses.iter().copied());
+        for opcode in &mut self.opcodes {
+            for_each_witness(opcode, &mut |witness| {
+                used_witnesses.insert(*witness);
+            });
+        }
+
+        let new_indices: BTreeMap<Witness, Witness> = used_witnesses
+            .into_iter()
+            .zip(1..)
+            .map(|(witness, new_index)| (witness, Witness(new_index)))
+            .collect();
+
+        let mut rename = |witness: &mut Witness| *witness = new_indices[&*witness];
+        for opcode in &mut self.opcodes {
+            for_each_witness(opcode, &mut rename);
+        }
+        self.return_witnesses.iter_mut().for_each(rename);
+        self.current_witness_index = new_indices.len() as u32;
+        new_indices
+    }
+}
+
+/// Calls `f` on every witness the given opcode refers to.
+pub(crate) fn for_each_witness(opcode: &mut AcirOpcode, f: &mut impl FnMut(&mut Witness)) {
+    match opcode {
+        AcirOpcode::Arithmetic(expr) => expression_witnesses(expr, f),
+        AcirOpcode::BlackBoxFuncCall(call) => black_box_witnesses(call, f),
+        AcirOpcode::Directive(directive) => directive_witnesses(directive, f),
+        AcirOpcode::Brillig(brillig) => {
+            for input in &mut brillig.inputs {
+                match input {
+                    BrilligInputs::Single(expr) => expression_witnesses(expr, f),
+                    BrilligInputs::Array(exprs) => {
+                        exprs.iter_mut().for_each(|expr| expression_witnesses(expr, f));
+                    }
+                }
+            }
+            for output in &mut brillig.outputs {
+                match output {
+                    BrilligOutputs::Simple(witness) => f(witness),
+                    BrilligOutputs::Array(witnesses) => witnesses.iter_mut().for_each(&mut *f),
+                }
+            }
+            if let Some(predicate) = &mut brillig.predicate {
+                expression_witnesses(predicate, f);
+            }
+        }
+        AcirOpcode::MemoryOp { op, .. } => {
+            expression_witnesses(&mut op.operation, f);
+            expression_witnesses(&mut op.index, f);
+            expression_witnesses(&mut op.value, f);
+        }
+        AcirOpcode::MemoryInit { init, .. } => init.iter_mut().for_each(f),
+    }
+}
+
+fn expression_witnesses(expr: &mut Expression, f: &mut impl FnMut(&mut Witness)) {
+    for (_, lhs, rhs) in &mut expr.mul_terms {
+        f(lhs);
+        f(rhs);
+    }
+    for (_, witness) in &mut expr.linear_combinations {
+        f(witness);
+    }
+}
+
+fn directive_witnesses(directive: &mut Directive, f: &mut impl FnMut(&mut Witness)) {
+    match directive {
+        Directive::Quotient(QuotientDirective { a, b, q, r, predicate }) => {
+            expression_witnesses(a, f);
+            expression_witnesses(b, f);
+            f(q);
+            f(r);
+            if let Some(predicate) = predicate {
+                expression_witnesses(predicate, f);
+            }
+        }
+        Directive::ToLeRadix { a, b, .. } => {
+            expression_witnesses(a, f);
+            b.iter_mut().for_each(f);
+        }
+        Directive::PermutationSort { inputs, bits, .. } => {
+            for expr in inputs.iter_mut().flatten() {
+                expression_witnesses(expr, f);
+            }
+            bits.iter_mut().for_each(f);
+        }
+        Directive::Log(LogInfo::WitnessOutput(witnesses)) => witnesses.iter_mut().for_each(f),
+        Directive::Log(LogInfo::FinalizedOutput(_)) => (),
+    }
+}
+
+fn black_box_witnesses(call: &mut BlackBoxFuncCall, f: &mut impl FnMut(&mut Witness)) {
+    match call {
+        BlackBoxFuncCall::AND { lhs, rhs, output } | BlackBoxFuncCall::XOR { lhs, rhs, output } => {
+            f(&mut lhs.witness);
+            f(&mut rhs.witness);
+            f(output);
+        }
+        BlackBoxFuncCall::RANGE { input } => f(&mut input.witness),
+        BlackBoxFuncCall::SHA256 { inputs, outputs }
+        | BlackBoxFuncCall::Blake2s { inputs, outputs }
+        | BlackBoxFuncCall::Keccak256 { inputs, outputs } => {
+            input_witnesses(inputs, f);
+            outputs.iter_mut().for_each(f);
+        }
+        BlackBoxFuncCall::Keccak256VariableLength { inputs, var_message_size, outputs } => {
+            input_witnesses(inputs, f);
+            f(&mut var_message_size.witness);
+            outputs.iter_mut().for_each(f);
+        }
+        BlackBoxFuncCall::HashToField128Security { inputs, output } => {
+            input_witnesses(inputs, f);
+            f(output);
+        }
+        BlackBoxFuncCall::SchnorrVerify {
+            public_key_x,
+            public_key_y,
+            signature,
+            message,
+            output,
+        } => {
+            f(&mut public_key_x.witness);
+            f(&mut public_key_y.witness);
+            input_witnesses(signature, f);
+            input_witnesses(message, f);
+            f(output);
+        }
+        BlackBoxFuncCall::Pedersen { inputs, outputs, .. } => {
+            input_witnesses(inputs, f);
+            f(&mut outputs.0);
+            f(&mut outputs.1);
+        }
+        BlackBoxFuncCall::EcdsaSecp256k1 {
+            public_key_x,
+            public_key_y,
+            signature,
+            hashed_message,
+            output,
+        }
+        | BlackBoxFuncCall::EcdsaSecp256r1 {
+            public_key_x,
+            public_key_y,
+            signature,
+            hashed_message,
+            output,
+        } => {
+            input_witnesses(public_key_x, f);
+            input_witnesses(public_key_y, f);
+            input_witnesses(signature, f);
+            input_witnesses(hashed_message, f);
+            f(output);
+        }
+        BlackBoxFuncCall::FixedBaseScalarMul { input, outputs } => {
+            f(&mut input.witness);
+            f(&mut outputs.0);
+            f(&mut outputs.1);
+        }
+        BlackBoxFuncCall::RecursiveAggregation {
+            verification_key,
+            proof,
+            public_inputs,
+            key_hash,
+            input_aggregation_object,
+            output_aggregation_object,
+        } => {
+            input_witnesses(verification_key, f);
+            input_witnesses(proof, f);
+            input_witnesses(public_inputs, f);
+            f(&mut key_hash.witness);
+            if let Some(input_aggregation_object) = input_aggregation_object {
+                input_witnesses(input_aggregation_object, f);
+            }
+            output_aggregation_object.iter_mut().for_each(f);
+        }
+    }
+}
+
+fn input_witnesses(inputs: &mut [FunctionInput], f: &mut impl FnMut(&mut Witness)) {
+    inputs.iter_mut().for_each(|input| f(&mut input.witness));
+}
@@ -1,42 +1,48 @@
 //! This file holds the pass to convert from Noir's SSA IR to ACIR.
 
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::fmt::Debug;
 
-use crate::brillig::brillig_ir::BrilligContext;
-use crate::{
-    brillig::{brillig_gen::brillig_fn::FunctionContext as BrilligFunctionContext, Brillig},
-    errors::RuntimeError,
-};
+use crate::brillig::brillig_ir::{debug_show::disassemble, peephole};
+use crate::{brillig::Brillig, errors::RuntimeError};
 
 use self::acir_ir::{
     acir_variable::{AcirContext, AcirType, AcirVar},
     errors::AcirGenError,
 };
 use super::{
+    backend_capabilities::BackendCapabilities,
     ir::{
+        array_escape::ArrayEscapeAnalysis,
         dfg::DataFlowGraph,
         function::{Function, RuntimeType},
         instruction::{
             Binary, BinaryOp, Instruction, InstructionId, Intrinsic, TerminatorInstruction,
         },
         map::Id,
+        predicated_constraints::{PredicatedConstraint, PredicatedConstraints},
+        range_analysis::RangeAnalysis,
         types::{NumericType, Type},
         value::{Value, ValueId},
     },
     ssa_gen::Ssa,
 };
 use acvm::{
-    acir::{brillig::Opcode, circuit::opcodes::BlockId, native_types::Expression},
+    acir::{brillig::Opcode, circuit::opcodes::BlockId},
     FieldElement,
 };
 use iter_extended::{try_vecmap, vecmap};
+use noirc_errors::Location;
 
 pub(crate) use acir_ir::generated_acir::GeneratedAcir;
-use noirc_abi::AbiDistinctness;
+use noirc_abi::{AbiDistinctness, AbiType, Sign};
 
 mod acir_ir;
 
+/// The largest bit size of the integers which ACIR generation supports. Products of integers wider
+/// than half of a field element are computed from limbs of each operand.
+const MAX_INTEGER_BIT_SIZE: u32 = 128;
+
 /// Context struct for the acir generation pass.
 /// May be similar to the Evaluator struct in the current SSA IR.
 struct Context {
@@ -62,6 +68,37 @@ struct Context {
     /// This set is used to ensure that a MemoryOp opcode is only pushed to the circuit
     /// if there is already a MemoryInit opcode.
     initialized_arrays: HashSet<BlockId>,
+
+    /// The ranges of the integer values within the main function, if it is an ACIR function.
+    ///
+    /// Casts of values which are already known to fit within the target bit size are skipped,
+    /// avoiding the range constraints they would otherwise add. Truncations which have no effect
+    /// are instead removed beforehand when integer arithmetic is lowered.
+    value_ranges: Option<RangeAnalysis>,
+
+    /// The array writes within the main function which may update their source array in place,
+    /// if it is an ACIR function.
+    ///
+    /// Other array writes copy their source array into a new memory block before writing to it.
+    array_escapes: Option<ArrayEscapeAnalysis>,
+
+    /// The constraints within the main function which are guarded by a branch condition, if it
+    /// is an ACIR function.
+    predicated_constraints: Option<PredicatedConstraints>,
+
+    /// The guarded constraints on boolean values converted so far, by branch condition and
+    /// location. They are asserted once the whole main function has been converted, so that
+    /// constraints sharing a condition and a location may be asserted together.
+    pending_constraints: Vec<PendingConstraints>,
+}
+
+/// The constraints on boolean values guarded by a single branch condition, and generated from a
+/// single location, which are yet to be asserted by a single constraint
+struct PendingConstraints {
+    condition: ValueId,
+    predicate: AcirVar,
+    location: Option<Location>,
+    values: Vec<AcirVar>,
 }
 
 #[derive(Clone)]
@@ -71,6 +108,8 @@ pub(crate) struct AcirDynamicArray {
     block_id: BlockId,
     /// Length of the array
     len: usize,
+    /// The type of each element of the array
+    element_type: AcirType,
 }
 impl Debug for AcirDynamicArray {
     fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
@@ -102,33 +141,82 @@ impl AcirValue {
             AcirValue::DynamicArray(_) => unimplemented!("Cannot flatten a dynamic array"),
         }
     }
+
+    /// Returns the type describing how the value should be displayed. Arrays whose elements
+    /// have different types, such as arrays of tuples, are described as structs whose fields
+    /// are the flattened elements.
+    fn abi_type(&self) -> AbiType {
+        match self {
+            AcirValue::Var(_, AcirType::NumericType(numeric_type)) => match numeric_type {
+                NumericType::NativeField => AbiType::Field,
+                NumericType::Unsigned { bit_size: 1 } => AbiType::Boolean,
+                NumericType::Unsigned { bit_size } => {
+                    AbiType::Integer { sign: Sign::Unsigned, width: *bit_size }
+                }
+                NumericType::Signed { bit_size } => {
+                    AbiType::Integer { sign: Sign::Signed, width: *bit_size }
+                }
+            },
+            AcirValue::Var(_, AcirType::Array(..)) => unreachable!("ICE: Expected a numeric type"),
+            AcirValue::Array(elements) => {
+                let element_types = vecmap(elements, AcirValue::abi_type);
+                match element_types.first() {
+                    Some(first) if element_types.iter().any(|typ| typ != first) => {
+                        positional_struct(element_types)
+                    }
+                    first => AbiType::Array {
+                        length: element_types.len() as u64,
+                        typ: Box::new(first.cloned().unwrap_or(AbiType::Field)),
+                    },
+                }
+            }
+            AcirValue::DynamicArray(_) => unreachable!("ICE: Dynamic arrays must be loaded first"),
+        }
+    }
+}
+
+/// Returns the encoding of the string describing how the given values should be displayed by a
+/// call to `println`, in the same format as the type passed to the `println` oracle.
+fn println_format(values: &[AcirValue]) -> String {
+    let abi_type = match values {
+        [value] => value.abi_type(),
+        values => positional_struct(vecmap(values, AcirValue::abi_type)),
+    };
+    serde_json::to_string(&abi_type).expect("ICE: expected Abi type to serialize")
+}
+
+fn positional_struct(field_types: Vec<AbiType>) -> AbiType {
+    let fields = field_types.into_iter().enumerate();
+    AbiType::Struct { fields: fields.map(|(index, typ)| (index.to_string(), typ)).collect() }
 }
 
 impl Ssa {
+    /// Converts the program into ACIR. If `record_provenance` is set, the SSA instruction each
+    /// opcode is generated for is recorded along with the opcode's purpose. If
+    /// `record_brillig_locations` is set, the source code location of each opcode of the Brillig
+    /// code called is recorded.
     pub(crate) fn into_acir(
         self,
         brillig: Brillig,
         abi_distinctness: AbiDistinctness,
-        allow_log_ops: bool,
+        capabilities: &BackendCapabilities,
+        record_provenance: bool,
+        record_brillig_locations: bool,
     ) -> Result<GeneratedAcir, RuntimeError> {
-        let context = Context::new();
-        let mut generated_acir = context.convert_ssa(self, brillig, allow_log_ops)?;
+        let mut context = Context::new(capabilities.clone());
+        if record_provenance {
+            context.acir_context.record_provenance();
+        }
+        if record_brillig_locations {
+            context.acir_context.record_brillig_locations();
+        }
+        let mut generated_acir = context.convert_ssa(self, brillig)?;
 
         match abi_distinctness {
             AbiDistinctness::Distinct => {
-                // Create a witness for each return witness we have
-                // to guarantee that the return witnesses are distinct
-                let distinct_return_witness: Vec<_> = generated_acir
-                    .return_witnesses
-                    .clone()
-                    .into_iter()
-                    .map(|return_witness| {
-                        generated_acir
-                            .create_witness_for_expression(&Expression::from(return_witness))
-                    })
-                    .collect();
-
-                generated_acir.return_witnesses = distinct_return_witness;
+                // Return witnesses which are parameters or are returned more than once are
+                // replaced by fresh witnesses, the others are already distinct
+                generated_acir.make_return_witnesses_distinct();
                 Ok(generated_acir)
             }
             AbiDistinctness::DuplicationAllowed => Ok(generated_acir),
@@ -137,8 +225,9 @@ impl Ssa {
 }
 
 impl Context {
-    fn new() -> Context {
+    fn new(capabilities: BackendCapabilities) -> Context {
         let mut acir_context = AcirContext::default();
+        acir_context.set_capabilities(capabilities);
         let current_side_effects_enabled_var = acir_context.add_constant(FieldElement::one());
 
         Context {
@@ -146,19 +235,18 @@ impl Context {
             current_side_effects_enabled_var,
             acir_context,
             initialized_arrays: HashSet::new(),
+            value_ranges: None,
+            array_escapes: None,
+            predicated_constraints: None,
+            pending_constraints: Vec::new(),
         }
     }
 
     /// Converts SSA into ACIR
-    fn convert_ssa(
-        self,
-        ssa: Ssa,
-        brillig: Brillig,
-        allow_log_ops: bool,
-    ) -> Result<GeneratedAcir, AcirGenError> {
+    fn convert_ssa(self, ssa: Ssa, brillig: Brillig) -> Result<GeneratedAcir, AcirGenError> {
         let main_func = ssa.main();
         match main_func.runtime() {
-            RuntimeType::Acir => self.convert_acir_main(main_func, &ssa, brillig, allow_log_ops),
+            RuntimeType::Acir => self.convert_acir_main(main_func, &ssa, brillig),
             RuntimeType::Brillig => self.convert_brillig_main(main_func, brillig),
         }
     }
@@ -168,20 +256,105 @@ impl Context {
         main_func: &Function,
         ssa: &Ssa,
         brillig: Brillig,
-        allow_log_ops: bool,
     ) -> Result<GeneratedAcir, AcirGenError> {
         let dfg = &main_func.dfg;
         let entry_block = &dfg[main_func.entry_block()];
+        self.value_ranges = Some(RangeAnalysis::with_function(main_func));
+        self.array_escapes = Some(ArrayEscapeAnalysis::with_function(main_func));
+        self.predicated_constraints = Some(PredicatedConstraints::with_function(main_func));
 
+        self.set_instruction_kind(|| "parameter".to_owned());
         self.convert_ssa_block_params(entry_block.parameters(), dfg)?;
 
         for instruction_id in entry_block.instructions() {
-            self.convert_ssa_instruction(*instruction_id, dfg, ssa, &brillig, allow_log_ops)?;
+            // The guards of predicated constraints are replaced by a multiplication by their
+            // condition when the constraints are asserted
+            if self.is_predicated_constraint_guard(*instruction_id) {
+                continue;
+            }
+            self.convert_ssa_instruction(*instruction_id, dfg, ssa, &brillig)?;
         }
+        self.assert_pending_constraints()?;
 
+        self.set_instruction_kind(|| "return".to_owned());
         self.convert_ssa_return(entry_block.unwrap_terminator(), dfg);
 
-        Ok(self.acir_context.finish())
+        let mut acir = self.acir_context.finish();
+        acir.propagate_constants();
+        acir.deduplicate_assertions();
+        acir.remove_dead_opcodes();
+        Ok(acir)
+    }
+
+    /// Sets the kind of SSA instruction the opcodes generated from now on are for, if the
+    /// provenance of opcodes is recorded.
+    fn set_instruction_kind(&mut self, kind: impl FnOnce() -> String) {
+        if self.acir_context.records_provenance() {
+            self.acir_context.set_instruction_kind(Some(kind()));
+        }
+    }
+
+    /// True if the instruction only computes the guarded value of a predicated constraint
+    fn is_predicated_constraint_guard(&self, instruction_id: InstructionId) -> bool {
+        self.predicated_constraints
+            .as_ref()
+            .map_or(false, |constraints| constraints.is_guard(instruction_id))
+    }
+
+    /// Converts a constraint guarded by a branch condition. Constraints on boolean values are
+    /// recorded to be asserted along with the other constraints guarded by the same condition at
+    /// the same location.
+    ///
+    /// Any other constraint is asserted in place, as it cannot be combined with others: values
+    /// which may not be boolean cannot be summed, and values asserted with a message must be
+    /// asserted alone so that the message can be reported when the constraint fails.
+    fn convert_predicated_constraint(
+        &mut self,
+        constraint: PredicatedConstraint,
+        message: Option<String>,
+        dfg: &DataFlowGraph,
+    ) -> Result<(), AcirGenError> {
+        let value = self.convert_numeric_value(constraint.value, dfg);
+        if !constraint.is_boolean || message.is_some() {
+            let predicate = self.convert_numeric_value(constraint.condition, dfg);
+            let previous_purpose = self.acir_context.set_purpose("flattening predicate");
+            self.acir_context.set_assert_message(message);
+            self.acir_context.assert_all_one_if(&[value], predicate)?;
+            self.acir_context.set_assert_message(None);
+            self.acir_context.restore_purpose(previous_purpose);
+            return Ok(());
+        }
+
+        let location = self.acir_context.get_location();
+        let pending = self.pending_constraints.iter_mut().find(|pending| {
+            pending.condition == constraint.condition && pending.location == location
+        });
+        match pending {
+            Some(pending) => pending.values.push(value),
+            None => {
+                let predicate = self.convert_numeric_value(constraint.condition, dfg);
+                self.pending_constraints.push(PendingConstraints {
+                    condition: constraint.condition,
+                    predicate,
+                    location,
+                    values: vec![value],
+                });
+            }
+        }
+        Ok(())
+    }
+
+    /// Asserts the constraints guarded by each branch condition at each location, with a single
+    /// multiplication by the condition for all of them.
+    fn assert_pending_constraints(&mut self) -> Result<(), AcirGenError> {
+        self.set_instruction_kind(|| "constrain".to_owned());
+        let previous_purpose = self.acir_context.set_purpose("flattening predicate");
+        for pending in std::mem::take(&mut self.pending_constraints) {
+            self.acir_context.set_location(pending.location);
+            self.acir_context.assert_all_one_if(&pending.values, pending.predicate)?;
+        }
+        self.acir_context.restore_purpose(previous_purpose);
+        Ok(())
     }
 
     fn convert_brillig_main(
@@ -199,10 +372,15 @@ impl Context {
         let outputs: Vec<AcirType> =
             vecmap(main_func.returns(), |result_id| dfg.type_of_value(*result_id).into());
 
-        let code = self.gen_brillig_for(main_func, &brillig);
+        let (code, locations) = self.gen_brillig_for(main_func, &brillig);
 
-        let output_values =
-            self.acir_context.brillig(self.current_side_effects_enabled_var, code, inputs, outputs);
+        let output_values = self.acir_context.brillig(
+            self.current_side_effects_enabled_var,
+            code,
+            inputs,
+            outputs,
+            locations,
+        )?;
         let output_vars: Vec<_> = output_values
             .iter()
             .flat_map(|value| value.clone().flatten())
@@ -216,6 +394,19 @@ impl Context {
         Ok(self.acir_context.finish())
     }
 
+    /// Converts a call to an ACIR function which was not inlined, which happens when the
+    /// inlining pass is removed from the pipeline.
+    ///
+    /// Such a call should be lowered into an ACIR call opcode referring to a separately generated
+    /// circuit for the callee. The ACVM version targeted here has no call opcode yet, so these
+    /// calls are reported as an error rather than silently dropped.
+    fn convert_acir_call(&mut self, func: &Function) -> Result<(), AcirGenError> {
+        Err(AcirGenError::UnsupportedAcirCall {
+            name: func.name().to_owned(),
+            location: self.acir_context.get_location(),
+        })
+    }
+
     /// Adds and binds `AcirVar`s for each numeric block parameter or block parameter array element.
     fn convert_ssa_block_params(
         &mut self,
@@ -279,7 +470,7 @@ impl Context {
         &mut self,
         numeric_type: &NumericType,
     ) -> Result<AcirVar, AcirGenError> {
-        let acir_var = self.acir_context.add_variable();
+        let acir_var = self.acir_context.add_input_variable();
         if matches!(numeric_type, NumericType::Signed { .. } | NumericType::Unsigned { .. }) {
             self.acir_context.range_constrain_var(acir_var, numeric_type)?;
         }
@@ -293,18 +484,31 @@ impl Context {
         dfg: &DataFlowGraph,
         ssa: &Ssa,
         brillig: &Brillig,
-        allow_log_ops: bool,
     ) -> Result<(), AcirGenError> {
         let instruction = &dfg[instruction_id];
         self.acir_context.set_location(dfg.get_location(&instruction_id));
+        self.set_instruction_kind(|| instruction_kind(instruction, dfg, ssa));
         match instruction {
             Instruction::Binary(binary) => {
                 let result_acir_var = self.convert_ssa_binary(binary, dfg)?;
                 self.define_result_var(dfg, instruction_id, result_acir_var);
             }
-            Instruction::Constrain(value_id) => {
-                let constrain_condition = self.convert_numeric_value(*value_id, dfg);
-                self.acir_context.assert_eq_one(constrain_condition)?;
+            Instruction::Constrain(value_id, message) => {
+                let predicated_constraint = self
+                    .predicated_constraints
+                    .as_ref()
+                    .and_then(|constraints| constraints.constraint(instruction_id));
+                match predicated_constraint {
+                    Some(constraint) => {
+                        self.convert_predicated_constraint(constraint, message.clone(), dfg)?;
+                    }
+                    None => {
+                        let constrain_condition = self.convert_numeric_value(*value_id, dfg);
+                        self.acir_context.set_assert_message(message.clone());
+                        self.acir_context.assert_eq_one(constrain_condition)?;
+                        self.acir_context.set_assert_message(None);
+                    }
+                }
             }
             Instruction::Cast(value_id, typ) => {
                 let result_acir_var = self.convert_ssa_cast(value_id, typ, dfg)?;
@@ -316,17 +520,23 @@ impl Context {
                     Value::Function(id) => {
                         let func = &ssa.functions[id];
                         match func.runtime() {
-                            RuntimeType::Acir => unimplemented!(
-                                "expected an intrinsic/brillig call, but found {func:?}. All ACIR methods should be inlined"
-                            ),
+                            RuntimeType::Acir => self.convert_acir_call(func)?,
                             RuntimeType::Brillig => {
-                                let inputs = vecmap(arguments, |arg| self.convert_value(*arg, dfg));
+                                let inputs = vecmap(arguments, |arg| self.load_value(*arg, dfg));
 
-                                let code = self.gen_brillig_for(func, brillig);
+                                let (code, locations) = self.gen_brillig_for(func, brillig);
 
-                                let outputs: Vec<AcirType> = vecmap(result_ids, |result_id| dfg.type_of_value(*result_id).into());
+                                let outputs: Vec<AcirType> = vecmap(result_ids, |result_id| {
+                                    dfg.type_of_value(*result_id).into()
+                                });
 
-                                let output_values = self.acir_context.brillig(self.current_side_effects_enabled_var, code, inputs, outputs);
+                                let output_values = self.acir_context.brillig(
+                                    self.current_side_effects_enabled_var,
+                                    code,
+                                    inputs,
+                                    outputs,
+                                    locations,
+                                )?;
 
                                 // Compiler sanity check
                                 assert_eq!(result_ids.len(), output_values.len(), "ICE: The number of Brillig output values should match the result ids in SSA");
@@ -338,13 +548,8 @@ impl Context {
                         }
                     }
                     Value::Intrinsic(intrinsic) => {
-                        let outputs = self.convert_ssa_intrinsic_call(
-                            *intrinsic,
-                            arguments,
-                            dfg,
-                            allow_log_ops,
-                            result_ids,
-                        )?;
+                        let outputs = self
+                            .convert_ssa_intrinsic_call(*intrinsic, arguments, dfg, result_ids)?;
 
                         // Issue #1438 causes this check to fail with intrinsics that return 0
                         // results but the ssa form instead creates 1 unit result value.
@@ -397,22 +602,19 @@ impl Context {
         Ok(())
     }
 
-    fn gen_brillig_for(&self, func: &Function, brillig: &Brillig) -> Vec<Opcode> {
-        // Create the entry point artifact
-        let mut entry_point = BrilligContext::new_entry_point_artifact(
-            BrilligFunctionContext::parameters(func),
-            BrilligFunctionContext::return_values(func),
-            BrilligFunctionContext::function_id_to_function_label(func.id()),
-        );
-        // Link the entry point with all dependencies
-        while let Some(unresolved_fn_label) = entry_point.first_unresolved_function_call() {
-            let artifact = &brillig
-                .find_by_function_label(unresolved_fn_label.clone())
-                .unwrap_or_else(|| panic!("Cannot find linked fn {unresolved_fn_label}"));
-            entry_point.link_with(artifact);
+    /// Generates the final bytecode of a Brillig function, along with the source code location
+    /// of its opcodes which have one.
+    fn gen_brillig_for(
+        &self,
+        func: &Function,
+        brillig: &Brillig,
+    ) -> (Vec<Opcode>, BTreeMap<usize, Location>) {
+        let (linked, _) = peephole::optimize(brillig.link_entry_point(func).finish());
+        if brillig.enable_debug_trace {
+            println!("Brillig bytecode of `{}`:", func.name());
+            print!("{}", disassemble(&linked.byte_code, &linked.labels, brillig.function_names()));
         }
-        // Generate the final bytecode
-        entry_point.finish()
+        (linked.byte_code, linked.locations)
     }
 
     /// Handles an ArrayGet or ArraySet instruction.
@@ -487,22 +689,12 @@ impl Context {
         dfg: &DataFlowGraph,
     ) {
         let array = dfg.resolve(array);
-        let block_id = BlockId(array.to_usize() as u32);
-        if !self.initialized_arrays.contains(&block_id) {
-            match &dfg[array] {
-                Value::Array { array, .. } => {
-                    let values: Vec<AcirValue> =
-                        array.iter().map(|i| self.convert_value(*i, dfg)).collect();
-                    self.initialize_array(block_id, array.len(), Some(&values));
-                }
-                _ => panic!("reading uninitialized array"),
-            }
-        }
+        let block_id = self.array_block_id(array, dfg);
 
         let index_var = self.convert_value(index, dfg).into_var();
         let read = self.acir_context.read_from_memory(block_id, &index_var);
         let typ = match dfg.type_of_value(array) {
-            Type::Array(typ, _) => {
+            Type::Array(typ, _) | Type::Slice(typ) => {
                 if typ.len() != 1 {
                     unimplemented!(
                         "Non-const array indices is not implemented for non-homogenous array"
@@ -516,9 +708,7 @@ impl Context {
         self.define_result(dfg, instruction, AcirValue::Var(read, typ));
     }
 
-    /// Copy the array and generates a write opcode on the new array
-    ///
-    /// Note: Copying the array is inefficient and is not the way we want to do it in the end.
+    /// Generates a write opcode for the array, copying the array first unless it has no later uses
     fn array_set(
         &mut self,
         instruction: InstructionId,
@@ -527,66 +717,97 @@ impl Context {
         store_value: ValueId,
         dfg: &DataFlowGraph,
     ) {
-        // Fetch the internal SSA ID for the array
         let array = dfg.resolve(array);
-        let array_ssa_id = array.to_usize() as u32;
-
-        // Use the SSA ID to create a block ID
-        // There is currently a 1-1 mapping from array SSA ID to block ID
-        let block_id = BlockId(array_ssa_id);
+        let block_id = self.array_block_id(array, dfg);
 
         // Every array has a length in its type, so we fetch that from
-        // the SSA IR.
+        // the SSA IR. The length of a slice is instead known from its current value.
         let len = match dfg.type_of_value(array) {
             Type::Array(_, len) => len,
+            Type::Slice(_) => self.slice_length(array, dfg),
             _ => unreachable!("ICE - expected an array"),
         };
 
+        let in_place = self
+            .array_escapes
+            .as_ref()
+            .map_or(false, |escapes| escapes.is_in_place_update(instruction));
+
+        let result_block_id = if in_place {
+            // The source array is never used again, so we can write to it directly
+            block_id
+        } else {
+            // Since array_set creates a new array, we create a new block ID for this
+            // array.
+            let result_id = dfg
+                .instruction_results(instruction)
+                .first()
+                .expect("Array set does not have one result");
+            let result_array_id = result_id.to_usize() as u32;
+            let result_block_id = BlockId(result_array_id);
+
+            // Initialize the new array with zero values
+            self.initialize_array(result_block_id, len, None);
+
+            // Copy the values from the old array into the newly created zeroed array
+            for i in 0..len {
+                let index = AcirValue::Var(
+                    self.acir_context.add_constant(FieldElement::from(i as u128)),
+                    AcirType::NumericType(NumericType::NativeField),
+                );
+                let var = index.into_var();
+                let read = self.acir_context.read_from_memory(block_id, &var);
+                self.acir_context.write_to_memory(result_block_id, &var, &read);
+            }
+            result_block_id
+        };
+
+        // Write the new value into the new array at the specified index
+        let index_var = self.convert_value(index, dfg).into_var();
+        let value_var = self.convert_value(store_value, dfg).into_var();
+        self.acir_context.write_to_memory(result_block_id, &index_var, &value_var);
+
+        let element_type = Self::array_element_type(dfg, array);
+        let result_value = AcirValue::DynamicArray(AcirDynamicArray {
+            block_id: result_block_id,
+            len,
+            element_type,
+        });
+        self.define_result(dfg, instruction, result_value);
+    }
+
+    /// Returns the memory block holding the given array.
+    ///
+    /// Arrays written to dynamically are held in the memory block they were written to, which
+    /// may have been the memory block of an earlier array updated in place. Any other array is
+    /// held in a memory block identified by its SSA ID, which is initialized the first time it
+    /// is used.
+    fn array_block_id(&mut self, array: ValueId, dfg: &DataFlowGraph) -> BlockId {
+        if let Some(AcirValue::DynamicArray(dynamic_array)) = self.ssa_values.get(&array) {
+            return dynamic_array.block_id;
+        }
+
+        // Use the SSA ID to create a block ID
+        let block_id = BlockId(array.to_usize() as u32);
+
         // Check if the array has already been initialized in ACIR gen
         // if not, we initialize it using the values from SSA
-        let already_initialized = self.initialized_arrays.contains(&block_id);
-        if !already_initialized {
-            match &dfg[array] {
-                Value::Array { array, .. } => {
+        if !self.initialized_arrays.contains(&block_id) {
+            match (&dfg[array], self.ssa_values.get(&array)) {
+                (Value::Array { array, .. }, _) => {
                     let values: Vec<AcirValue> =
                         array.iter().map(|i| self.convert_value(*i, dfg)).collect();
                     self.initialize_array(block_id, array.len(), Some(&values));
                 }
+                // e.g. a slice returned by a slice intrinsic
+                (_, Some(AcirValue::Array(values))) => {
+                    let values: Vec<AcirValue> = values.iter().cloned().collect();
+                    self.initialize_array(block_id, values.len(), Some(&values));
+                }
                 _ => panic!("Array {} should be initialized", array),
             }
         }
-
-        // Since array_set creates a new array, we create a new block ID for this
-        // array.
-        let result_id = dfg
-            .instruction_results(instruction)
-            .first()
-            .expect("Array set does not have one result");
-        let result_array_id = result_id.to_usize() as u32;
-        let result_block_id = BlockId(result_array_id);
-
-        // Initialize the new array with zero values
-        self.initialize_array(result_block_id, len, None);
-
-        // Copy the values from the old array into the newly created zeroed array
-        for i in 0..len {
-            let index = AcirValue::Var(
-                self.acir_context.add_constant(FieldElement::from(i as u128)),
-                AcirType::NumericType(NumericType::NativeField),
-            );
-            let var = index.into_var();
-            let read = self.acir_context.read_from_memory(block_id, &var);
-            self.acir_context.write_to_memory(result_block_id, &var, &read);
-        }
-
-        // Write the new value into the new array at the specified index
-        let index_var = self.convert_value(index, dfg).into_var();
-        let value_var = self.convert_value(store_value, dfg).into_var();
-        self.acir_context.write_to_memory(result_block_id, &index_var, &value_var);
-
-        let result_value =
-            AcirValue::DynamicArray(AcirDynamicArray { block_id: result_block_id, len });
-        self.define_result(dfg, instruction, result_value);
+        block_id
     }
 
     /// Initializes an array with the given values and caches the fact that we
@@ -696,14 +917,10 @@ impl Context {
         match &binary_type {
             Type::Numeric(NumericType::Unsigned { bit_size })
             | Type::Numeric(NumericType::Signed { bit_size }) => {
-                // Conservative max bit size that is small enough such that two operands can be
-                // multiplied and still fit within the field modulus. This is necessary for the
-                // truncation technique: result % 2^bit_size to be valid.
-                let max_integer_bit_size = FieldElement::max_num_bits() / 2;
-                if *bit_size > max_integer_bit_size {
+                if *bit_size > MAX_INTEGER_BIT_SIZE {
                     return Err(AcirGenError::UnsupportedIntegerSize {
                         num_bits: *bit_size,
-                        max_num_bits: max_integer_bit_size,
+                        max_num_bits: MAX_INTEGER_BIT_SIZE,
                         location: self.acir_context.get_location(),
                     });
                 }
@@ -717,6 +934,13 @@ impl Context {
         match binary.operator {
             BinaryOp::Add => self.acir_context.add_var(lhs, rhs),
             BinaryOp::Sub => self.acir_context.sub_var(lhs, rhs),
+            // The product of integers this wide may not fit within a field element, so it must
+            // be reduced as it is computed rather than by the truncation which follows it
+            BinaryOp::Mul
+                if binary_type.is_integer() && 2 * bit_count >= FieldElement::max_num_bits() =>
+            {
+                self.acir_context.wrapping_mul_var(lhs, rhs, bit_count)
+            }
             BinaryOp::Mul => self.acir_context.mul_var(lhs, rhs),
             BinaryOp::Div => self.acir_context.div_var(
                 lhs,
@@ -733,7 +957,12 @@ impl Context {
                 bit_count,
                 self.current_side_effects_enabled_var,
             ),
-            BinaryOp::Shl => self.acir_context.shift_left_var(lhs, rhs, binary_type),
+            BinaryOp::Shl => self.acir_context.shift_left_var(
+                lhs,
+                rhs,
+                binary_type,
+                self.current_side_effects_enabled_var,
+            ),
             BinaryOp::Shr => self.acir_context.shift_right_var(
                 lhs,
                 rhs,
@@ -746,7 +975,7 @@ impl Context {
             BinaryOp::Mod => self.acir_context.modulo_var(
                 lhs,
                 rhs,
-                bit_count,
+                binary_type,
                 self.current_side_effects_enabled_var,
             ),
         }
@@ -830,7 +1059,7 @@ impl Context {
                     todo!("Cast from unsigned to signed")
                 }
                 let max_bit_size = incoming_type.bit_size();
-                if max_bit_size <= *bit_size {
+                if max_bit_size <= *bit_size || self.fits_in_bit_size(*value_id, *bit_size, dfg) {
                     // Incoming variable already fits into target bit size -  this is a no-op
                     return Ok(variable);
                 }
@@ -849,22 +1078,46 @@ impl Context {
         dfg: &DataFlowGraph,
     ) -> Result<AcirVar, AcirGenError> {
         let mut var = self.convert_numeric_value(value_id, dfg);
-        let truncation_target = match &dfg[value_id] {
-            Value::Instruction { instruction, .. } => &dfg[*instruction],
-            _ => unreachable!("ICE: Truncates are only ever applied to the result of a binary op"),
-        };
-        if matches!(truncation_target, Instruction::Binary(Binary { operator: BinaryOp::Sub, .. }))
-        {
-            // Subtractions must first have the integer modulus added before truncation can be
-            // applied. This is done in order to prevent underflow.
-            let integer_modulus =
-                self.acir_context.add_constant(FieldElement::from(2_u128.pow(bit_size)));
-            var = self.acir_context.add_var(var, integer_modulus)?;
+
+        // Products of integers which may not fit within a field element are already reduced to
+        // the bit size of the integer when they are computed
+        if max_bit_size >= FieldElement::max_num_bits() {
+            if let Value::Instruction { instruction, .. } = &dfg[value_id] {
+                if matches!(
+                    &dfg[*instruction],
+                    Instruction::Binary(Binary { operator: BinaryOp::Mul | BinaryOp::Shl, .. })
+                ) {
+                    return Ok(var);
+                }
+            }
+        }
+
+        // Subtractions may underflow, so the integer modulus must be added to them before they
+        // can be truncated. This is usually done when integer arithmetic is lowered, but that
+        // pass may have been removed from the pipeline.
+        if let Value::Instruction { instruction, .. } = &dfg[value_id] {
+            if matches!(
+                &dfg[*instruction],
+                Instruction::Binary(Binary { operator: BinaryOp::Sub, .. })
+            ) {
+                let integer_modulus =
+                    FieldElement::from(2_u128).pow(&FieldElement::from(bit_size as u128));
+                let integer_modulus = self.acir_context.add_constant(integer_modulus);
+                var = self.acir_context.add_var(var, integer_modulus)?;
+            }
         }
 
         self.acir_context.truncate_var(var, bit_size, max_bit_size)
     }
 
+    /// Returns true if the given value is known to fit within the given number of bits.
+    fn fits_in_bit_size(&self, value_id: ValueId, bit_size: u32, dfg: &DataFlowGraph) -> bool {
+        self.value_ranges
+            .as_ref()
+            .and_then(|ranges| ranges.range(dfg, value_id))
+            .map_or(false, |range| range.fits_in(bit_size))
+    }
+
     /// Returns a vector of `AcirVar`s constrained to be result of the function call.
     ///
     /// The function being called is required to be intrinsic.
@@ -873,16 +1126,24 @@ impl Context {
         intrinsic: Intrinsic,
         arguments: &[ValueId],
         dfg: &DataFlowGraph,
-        allow_log_ops: bool,
         result_ids: &[ValueId],
     ) -> Result<Vec<AcirValue>, AcirGenError> {
         match intrinsic {
             Intrinsic::BlackBox(black_box) => {
-                let inputs = vecmap(arguments, |arg| self.convert_value(*arg, dfg));
+                let inputs = vecmap(arguments, |arg| self.load_value(*arg, dfg));
 
                 let vars = self.acir_context.black_box_function(black_box, inputs)?;
 
-                Ok(Self::convert_vars_to_values(vars, dfg, result_ids))
+                // A black box function returning a slice, such as the aggregation object of a
+                // recursive proof verification, returns each of its outputs as an element of it
+                match result_ids {
+                    [result] if matches!(dfg.type_of_value(*result), Type::Slice(_)) => {
+                        let typ = Self::array_element_type(dfg, *result);
+                        let elements = vars.into_iter().map(|var| AcirValue::Var(var, typ.clone()));
+                        Ok(vec![AcirValue::Array(elements.collect())])
+                    }
+                    _ => Ok(Self::convert_vars_to_values(vars, dfg, result_ids)),
+                }
             }
             Intrinsic::ToRadix(endian) => {
                 let field = self.convert_value(arguments[0], dfg).into_var();
@@ -900,31 +1161,90 @@ impl Context {
                 self.acir_context.bit_decompose(endian, field, bit_size, result_type)
             }
             Intrinsic::Println => {
-                let inputs = vecmap(arguments, |arg| self.convert_value(*arg, dfg));
-                if allow_log_ops {
-                    self.acir_context.print(inputs)?;
-                }
+                let inputs = vecmap(arguments, |arg| self.load_value(*arg, dfg));
+                let format = println_format(&inputs);
+                self.acir_context.print(inputs, &format, self.current_side_effects_enabled_var)?;
                 Ok(Vec::new())
             }
-            Intrinsic::Sort => {
-                let inputs = vecmap(arguments, |arg| self.convert_value(*arg, dfg));
-                // We flatten the inputs and retrieve the bit_size of the elements
-                let mut input_vars = Vec::new();
-                let mut bit_size = 0;
-                for input in inputs {
-                    for (var, typ) in input.flatten() {
-                        input_vars.push(var);
-                        if bit_size == 0 {
-                            bit_size = typ.bit_size();
+            Intrinsic::ArrayLen => {
+                let length = self.slice_length(arguments[0], dfg);
+                let length = self.acir_context.add_constant(FieldElement::from(length as u128));
+                Ok(vec![AcirValue::Var(length, AcirType::NumericType(NumericType::NativeField))])
+            }
+            Intrinsic::SlicePushBack => {
+                let mut slice = self.slice_elements(arguments[0], dfg);
+                slice.push_back(self.convert_value(arguments[1], dfg));
+                Ok(vec![AcirValue::Array(slice)])
+            }
+            Intrinsic::SlicePushFront => {
+                let mut slice = self.slice_elements(arguments[0], dfg);
+                slice.push_front(self.convert_value(arguments[1], dfg));
+                Ok(vec![AcirValue::Array(slice)])
+            }
+            Intrinsic::SlicePopBack => {
+                let mut slice = self.slice_elements(arguments[0], dfg);
+                let element = match slice.pop_back() {
+                    Some(element) => element,
+                    None => self.slice_index_out_of_bounds(0, 0, result_ids[1], dfg)?,
+                };
+                Ok(vec![AcirValue::Array(slice), element])
+            }
+            Intrinsic::SlicePopFront => {
+                let mut slice = self.slice_elements(arguments[0], dfg);
+                let element = match slice.pop_front() {
+                    Some(element) => element,
+                    None => self.slice_index_out_of_bounds(0, 0, result_ids[0], dfg)?,
+                };
+                Ok(vec![element, AcirValue::Array(slice)])
+            }
+            Intrinsic::SliceInsert => {
+                let mut slice = self.slice_elements(arguments[0], dfg);
+                let element = self.convert_value(arguments[2], dfg);
+
+                match dfg.get_numeric_constant(arguments[1]) {
+                    Some(index) => {
+                        let index = index.to_u128() as usize;
+                        if index <= slice.len() {
+                            slice.insert(index, element);
                         } else {
-                            assert_eq!(
-                                bit_size,
-                                typ.bit_size(),
-                                "cannot sort element of different bit size"
-                            );
+                            // The slice is left unchanged if the insertion is never executed
+                            let array_size = slice.len() + 1;
+                            self.check_slice_index_in_bounds(index, array_size)?;
                         }
                     }
+                    None => {
+                        let index = self.convert_value(arguments[1], dfg).into_var();
+                        slice = self.insert_at_dynamic_index(slice, index, element)?;
+                    }
                 }
+                Ok(vec![AcirValue::Array(slice)])
+            }
+            Intrinsic::SliceRemove => {
+                let mut slice = self.slice_elements(arguments[0], dfg);
+
+                let element = match dfg.get_numeric_constant(arguments[1]) {
+                    Some(index) if (index.to_u128() as usize) < slice.len() => {
+                        slice.remove(index.to_u128() as usize)
+                    }
+                    Some(index) => {
+                        let index = index.to_u128() as usize;
+                        self.slice_index_out_of_bounds(index, slice.len(), result_ids[1], dfg)?
+                    }
+                    None if slice.is_empty() => {
+                        self.slice_index_out_of_bounds(0, 0, result_ids[1], dfg)?
+                    }
+                    None => {
+                        let index = self.convert_value(arguments[1], dfg).into_var();
+                        let (remaining, element) = self.remove_at_dynamic_index(slice, index)?;
+                        slice = remaining;
+                        element
+                    }
+                };
+                Ok(vec![AcirValue::Array(slice), element])
+            }
+            Intrinsic::Sort => {
+                let inputs = vecmap(arguments, |arg| self.load_value(*arg, dfg));
+                let (input_vars, bit_size) = Self::flatten_sort_elements(inputs);
                 // Generate the sorted output variables
                 let out_vars = self
                     .acir_context
@@ -933,10 +1253,223 @@ impl Context {
 
                 Ok(Self::convert_vars_to_values(out_vars, dfg, result_ids))
             }
+            Intrinsic::AssertSortedPermutation => {
+                let (input_vars, bit_size) =
+                    Self::flatten_sort_elements(vec![self.load_value(arguments[0], dfg)]);
+                let (sorted_vars, _) =
+                    Self::flatten_sort_elements(vec![self.load_value(arguments[1], dfg)]);
+                self.acir_context.assert_sorted_permutation(
+                    input_vars,
+                    sorted_vars,
+                    bit_size,
+                    self.current_side_effects_enabled_var,
+                )?;
+                Ok(Vec::new())
+            }
+            Intrinsic::BigIntMulAddMod => {
+                let operands = vecmap(arguments, |arg| {
+                    let mut limbs = Vec::new();
+                    AcirContext::flatten_value(&mut limbs, self.load_value(*arg, dfg));
+                    limbs
+                });
+                self.acir_context
+                    .big_int_mul_add_mod(operands, self.current_side_effects_enabled_var)?;
+                Ok(Vec::new())
+            }
             _ => todo!("expected a black box function"),
         }
     }
 
+    /// Converts the given value, reading each element of an array held in memory so that it can
+    /// be flattened. An array is only held in memory once it is written to at an index which is
+    /// not known at compile time.
+    fn load_value(&mut self, value_id: ValueId, dfg: &DataFlowGraph) -> AcirValue {
+        match self.convert_value(value_id, dfg) {
+            AcirValue::DynamicArray(_) => AcirValue::Array(self.slice_elements(value_id, dfg)),
+            value => value,
+        }
+    }
+
+    /// Returns the number of elements within the given slice or array.
+    fn slice_length(&mut self, slice: ValueId, dfg: &DataFlowGraph) -> usize {
+        match self.convert_value(slice, dfg) {
+            AcirValue::Array(elements) => elements.len(),
+            AcirValue::DynamicArray(AcirDynamicArray { len, .. }) => len,
+            AcirValue::Var(..) => unreachable!("Expected a slice value"),
+        }
+    }
+
+    /// Returns each element of the given slice. Every slice within an ACIR program has a length
+    /// known at compile time, so the elements of a slice held in memory are read from it.
+    fn slice_elements(&mut self, slice: ValueId, dfg: &DataFlowGraph) -> im::Vector<AcirValue> {
+        let slice = self.convert_value(slice, dfg);
+        self.array_elements(slice)
+    }
+
+    /// Returns each element of the given array, reading them from memory if the array is held
+    /// in memory.
+    fn array_elements(&mut self, array: AcirValue) -> im::Vector<AcirValue> {
+        match array {
+            AcirValue::Array(elements) => elements,
+            AcirValue::DynamicArray(AcirDynamicArray { block_id, len, element_type }) => (0..len)
+                .map(|index| {
+                    let index = self.acir_context.add_constant(FieldElement::from(index as u128));
+                    let element = self.acir_context.read_from_memory(block_id, &index);
+                    AcirValue::Var(element, element_type.clone())
+                })
+                .collect(),
+            AcirValue::Var(..) => unreachable!("Expected an array value"),
+        }
+    }
+
+    /// Reports a slice operation on an index which is known to be out of bounds. This is only an
+    /// error if side effects are enabled, otherwise the operation is never executed.
+    fn check_slice_index_in_bounds(
+        &mut self,
+        index: usize,
+        array_size: usize,
+    ) -> Result<(), AcirGenError> {
+        if self.acir_context.is_constant_one(&self.current_side_effects_enabled_var) {
+            let location = self.acir_context.get_location();
+            return Err(AcirGenError::IndexOutOfBounds { index, array_size, location });
+        }
+        Ok(())
+    }
+
+    /// Reports a slice operation on an index which is known to be out of bounds, returning a
+    /// default value for the given result if the operation is never executed.
+    fn slice_index_out_of_bounds(
+        &mut self,
+        index: usize,
+        array_size: usize,
+        result: ValueId,
+        dfg: &DataFlowGraph,
+    ) -> Result<AcirValue, AcirGenError> {
+        self.check_slice_index_in_bounds(index, array_size)?;
+        self.create_default_value(&dfg.type_of_value(result))
+    }
+
+    /// Returns a flag for each index of a slice with the given length, which is one only if it is
+    /// equal to the given index. The index is constrained to be less than the length, unless side
+    /// effects are disabled.
+    fn slice_index_flags(
+        &mut self,
+        index: AcirVar,
+        length: usize,
+    ) -> Result<Vec<AcirVar>, AcirGenError> {
+        let mut flags = Vec::with_capacity(length);
+        let mut in_bounds = self.acir_context.add_constant(FieldElement::zero());
+        for position in 0..length {
+            let position = self.acir_context.add_constant(FieldElement::from(position as u128));
+            let flag = self.acir_context.eq_var(index, position)?;
+            in_bounds = self.acir_context.add_var(in_bounds, flag)?;
+            flags.push(flag);
+        }
+
+        let predicate = self.current_side_effects_enabled_var;
+        let in_bounds = self.acir_context.mul_var(in_bounds, predicate)?;
+        self.acir_context.assert_eq_var(in_bounds, predicate)?;
+        Ok(flags)
+    }
+
+    /// Returns the value of the choice whose flag is one, where at most one flag is one.
+    /// Arrays are selected between element-wise.
+    fn select_value(
+        &mut self,
+        choices: Vec<(AcirVar, AcirValue)>,
+    ) -> Result<AcirValue, AcirGenError> {
+        match &choices[0].1 {
+            AcirValue::Var(_, typ) => {
+                let typ = typ.clone();
+                let mut selected = self.acir_context.add_constant(FieldElement::zero());
+                for (flag, value) in choices {
+                    let term = self.acir_context.mul_var(flag, value.into_var())?;
+                    selected = self.acir_context.add_var(selected, term)?;
+                }
+                Ok(AcirValue::Var(selected, typ))
+            }
+            AcirValue::Array(_) | AcirValue::DynamicArray(_) => {
+                // Arrays held in memory are read so that their elements can be selected between
+                let choices = vecmap(choices, |(flag, value)| (flag, self.array_elements(value)));
+                let mut selected = im::Vector::new();
+                for index in 0..choices[0].1.len() {
+                    let element_choices =
+                        vecmap(&choices, |(flag, elements)| (*flag, elements[index].clone()));
+                    selected.push_back(self.select_value(element_choices)?);
+                }
+                Ok(AcirValue::Array(selected))
+            }
+        }
+    }
+
+    /// Inserts an element into a slice at an index which is not known at compile time. Each
+    /// element of the result is selected from the element at the same index of the slice, the
+    /// inserted element, or the element before it, depending on where the index lies.
+    fn insert_at_dynamic_index(
+        &mut self,
+        slice: im::Vector<AcirValue>,
+        index: AcirVar,
+        element: AcirValue,
+    ) -> Result<im::Vector<AcirValue>, AcirGenError> {
+        let length = slice.len();
+        let flags = self.slice_index_flags(index, length + 1)?;
+        let one = self.acir_context.add_constant(FieldElement::one());
+
+        // Whether the index is before the current position
+        let mut index_is_before = self.acir_context.add_constant(FieldElement::zero());
+        let mut result = im::Vector::new();
+
+        for (position, flag) in flags.into_iter().enumerate() {
+            let mut choices = vec![(flag, element.clone())];
+            if position > 0 {
+                choices.push((index_is_before, slice[position - 1].clone()));
+            }
+
+            let index_is_not_after = self.acir_context.add_var(index_is_before, flag)?;
+            if position < length {
+                let index_is_after = self.acir_context.sub_var(one, index_is_not_after)?;
+                choices.push((index_is_after, slice[position].clone()));
+            }
+
+            result.push_back(self.select_value(choices)?);
+            index_is_before = index_is_not_after;
+        }
+        Ok(result)
+    }
+
+    /// Removes an element from a non-empty slice at an index which is not known at compile time,
+    /// returning the remaining slice along with the removed element. Each element of the result
+    /// is selected from the element at the same index of the slice or the element after it,
+    /// depending on where the index lies.
+    fn remove_at_dynamic_index(
+        &mut self,
+        slice: im::Vector<AcirValue>,
+        index: AcirVar,
+    ) -> Result<(im::Vector<AcirValue>, AcirValue), AcirGenError> {
+        let length = slice.len();
+        let flags = self.slice_index_flags(index, length)?;
+        let one = self.acir_context.add_constant(FieldElement::one());
+
+        let choices = flags.iter().copied().zip(slice.iter().cloned()).collect();
+        let removed = self.select_value(choices)?;
+
+        // Whether the index is at or before the current position
+        let mut index_is_not_after = self.acir_context.add_constant(FieldElement::zero());
+        let mut result = im::Vector::new();
+
+        for (position, flag) in flags.into_iter().enumerate().take(length - 1) {
+            index_is_not_after = self.acir_context.add_var(index_is_not_after, flag)?;
+            let index_is_after = self.acir_context.sub_var(one, index_is_not_after)?;
+
+            let choices = vec![
+                (index_is_after, slice[position].clone()),
+                (index_is_not_after, slice[position + 1].clone()),
+            ];
+            result.push_back(self.select_value(choices)?);
+        }
+        Ok((result, removed))
+    }
+
     /// Given an array value, return the numerical type of its element.
     /// Panics if the given value is not an array or has a non-numeric element type.
     fn array_element_type(dfg: &DataFlowGraph, value: ValueId) -> AcirType {
@@ -959,7 +1492,7 @@ impl Context {
     fn flatten_value_list(&mut self, arguments: &[ValueId], dfg: &DataFlowGraph) -> Vec<AcirVar> {
         let mut acir_vars = Vec::with_capacity(arguments.len());
         for value_id in arguments {
-            let value = self.convert_value(*value_id, dfg);
+            let value = self.load_value(*value_id, dfg);
             AcirContext::flatten_value(&mut acir_vars, value);
         }
         acir_vars
@@ -974,6 +1507,27 @@ impl Context {
         }
     }
 
+    /// Flattens the arrays to sort, returning their elements along with their bit size.
+    fn flatten_sort_elements(values: Vec<AcirValue>) -> (Vec<AcirVar>, u32) {
+        let mut vars = Vec::new();
+        let mut bit_size = 0;
+        for value in values {
+            for (var, typ) in value.flatten() {
+                vars.push(var);
+                if bit_size == 0 {
+                    bit_size = typ.bit_size();
+                } else {
+                    assert_eq!(
+                        bit_size,
+                        typ.bit_size(),
+                        "cannot sort element of different bit size"
+                    );
+                }
+            }
+        }
+        (vars, bit_size)
+    }
+
     /// Convert a Vec<AcirVar> into a Vec<AcirValue> using the given result ids.
     /// If the type of a result id is an array, several acir vars are collected into
     /// a single AcirValue::Array of the same length.
@@ -1023,27 +1577,75 @@ impl Context {
     }
 }
 
+/// Describes the kind of an SSA instruction for the provenance of the opcodes generated for it,
+/// e.g. `mul` or `call to_le_bits`.
+fn instruction_kind(instruction: &Instruction, dfg: &DataFlowGraph, ssa: &Ssa) -> String {
+    match instruction {
+        Instruction::Binary(binary) => binary.operator.to_string(),
+        Instruction::Call { func, .. } => match &dfg[*func] {
+            Value::Function(id) => format!("call {}", ssa.functions[id].name()),
+            Value::Intrinsic(intrinsic) => format!("call {intrinsic}"),
+            Value::ForeignFunction(name) => format!("call {name}"),
+            _ => "call".to_owned(),
+        },
+        Instruction::Cast(..) => "cast".to_owned(),
+        Instruction::Not(_) => "not".to_owned(),
+        Instruction::Truncate { .. } => "truncate".to_owned(),
+        Instruction::Constrain(..) => "constrain".to_owned(),
+        Instruction::Allocate => "allocate".to_owned(),
+        Instruction::Load { .. } => "load".to_owned(),
+        Instruction::Store { .. } => "store".to_owned(),
+        Instruction::EnableSideEffects { .. } => "enable_side_effects".to_owned(),
+        Instruction::ArrayGet { .. } => "array_get".to_owned(),
+        Instruction::ArraySet { .. } => "array_set".to_owned(),
+    }
+}
+
 #[cfg(test)]
 mod tests {
-    use std::rc::Rc;
+    use std::{
+        collections::{HashMap, HashSet},
+        rc::Rc,
+    };
+
+    use iter_extended::vecmap;
 
     use acvm::{
         acir::{
-            circuit::Opcode,
+            brillig::Opcode as BrilligOpcode,
+            circuit::{
+                brillig::{BrilligInputs, BrilligOutputs},
+                directives::{Directive, QuotientDirective},
+                opcodes::{BlackBoxFuncCall, BlockId, FunctionInput},
+                Opcode,
+            },
             native_types::{Expression, Witness},
+            BlackBoxFunc,
         },
         FieldElement,
     };
+    use noirc_abi::{decode_string_value, AbiType, Sign};
+    use noirc_errors::{Location, Span};
 
     use crate::{
         brillig::Brillig,
         ssa_refactor::{
-            ir::{function::RuntimeType, map::Id, types::Type},
+            backend_capabilities::BackendCapabilities,
+            ir::{
+                function::RuntimeType,
+                instruction::{BinaryOp, Intrinsic},
+                map::Id,
+                types::Type,
+                value::ValueId,
+            },
             ssa_builder::FunctionBuilder,
         },
     };
 
-    use super::Context;
+    use super::{
+        acir_ir::{generated_acir::MemoryKind, witness_compaction::for_each_witness},
+        Context, GeneratedAcir,
+    };
 
     #[test]
     fn returns_body_scoped_arrays() {
@@ -1064,12 +1666,1299 @@ mod tests {
 
         let ssa = builder.finish();
 
-        let context = Context::new();
-        let acir = context.convert_ssa(ssa, Brillig::default(), false).unwrap();
+        let context = Context::new(BackendCapabilities::default());
+        let acir = context.convert_ssa(ssa, Brillig::default()).unwrap();
 
         let expected_opcodes =
             vec![Opcode::Arithmetic(&Expression::one() - &Expression::from(Witness(1)))];
         assert_eq!(acir.opcodes, expected_opcodes);
         assert_eq!(acir.return_witnesses, vec![Witness(1)]);
     }
+
+    #[test]
+    fn inserts_into_slices_at_dynamic_indices() {
+        // fn main f0 {
+        //   b0(v0: Field, v1: Field):
+        //     v2 = call slice_insert([v0, Field 2], v1, Field 3) -> [Field]
+        //     return v2
+        // }
+        let func_id = Id::test_new(0);
+        let mut builder = FunctionBuilder::new("func".into(), func_id, RuntimeType::Acir);
+
+        let v0 = builder.add_parameter(Type::field());
+        let v1 = builder.add_parameter(Type::field());
+        let two = builder.field_constant(2u128);
+        let three = builder.field_constant(3u128);
+
+        let slice_type = Type::Slice(Rc::new(vec![Type::field()]));
+        let slice = builder.array_constant(im::vector![v0, two], slice_type.clone());
+        let slice_insert = builder.import_intrinsic_id(Intrinsic::SliceInsert);
+        let v2 = builder.insert_call(slice_insert, vec![slice, v1, three], vec![slice_type])[0];
+        builder.terminate_with_return(vec![v2]);
+
+        let ssa = builder.finish();
+
+        let context = Context::new(BackendCapabilities::default());
+        let acir = context.convert_ssa(ssa, Brillig::default()).unwrap();
+
+        // The index is constrained to be at most the length of the slice
+        assert!(!acir.opcodes.is_empty());
+        assert_eq!(acir.return_witnesses.len(), 3);
+    }
+
+    #[test]
+    fn inserts_arrays_held_in_memory_into_slices_at_dynamic_indices() {
+        // fn main f0 {
+        //   b0(v0: Field, v1: Field):
+        //     v2 = array_set [Field 1, Field 2], index v0, value v1
+        //     v3 = call slice_insert([v2, [Field 1, Field 2]], v0, v2) -> [[Field; 2]]
+        //     return v3
+        // }
+        let func_id = Id::test_new(0);
+        let mut builder = FunctionBuilder::new("func".into(), func_id, RuntimeType::Acir);
+
+        let v0 = builder.add_parameter(Type::field());
+        let v1 = builder.add_parameter(Type::field());
+        let one = builder.field_constant(1u128);
+        let two = builder.field_constant(2u128);
+
+        let array_type = Type::Array(Rc::new(vec![Type::field()]), 2);
+        let array = builder.array_constant(im::vector![one, two], array_type.clone());
+        let v2 = builder.insert_array_set(array, v0, v1);
+
+        let slice_type = Type::Slice(Rc::new(vec![array_type]));
+        let slice = builder.array_constant(im::vector![v2, array], slice_type.clone());
+        let slice_insert = builder.import_intrinsic_id(Intrinsic::SliceInsert);
+        let v3 = builder.insert_call(slice_insert, vec![slice, v0, v2], vec![slice_type])[0];
+        builder.terminate_with_return(vec![v3]);
+
+        let ssa = builder.finish();
+
+        let context = Context::new(BackendCapabilities::default());
+        let acir = context.convert_ssa(ssa, Brillig::default()).unwrap();
+
+        // The elements of the array held in memory are read before being selected between
+        assert!(acir.opcodes.iter().any(|opcode| matches!(opcode, Opcode::MemoryOp { .. })));
+        assert_eq!(acir.return_witnesses.len(), 6);
+    }
+
+    #[test]
+    fn returns_arrays_written_at_dynamic_indices() {
+        // fn main f0 {
+        //   b0(v0: Field, v1: Field):
+        //     v2 = array_set [Field 1, Field 2], index v0, value v1
+        //     v3 = array_get v2, index v1
+        //     return v2, v3
+        // }
+        let func_id = Id::test_new(0);
+        let mut builder = FunctionBuilder::new("func".into(), func_id, RuntimeType::Acir);
+
+        let v0 = builder.add_parameter(Type::field());
+        let v1 = builder.add_parameter(Type::field());
+        let one = builder.field_constant(1u128);
+        let two = builder.field_constant(2u128);
+
+        let array_type = Type::Array(Rc::new(vec![Type::field()]), 2);
+        let array = builder.array_constant(im::vector![one, two], array_type);
+        let v2 = builder.insert_array_set(array, v0, v1);
+        let v3 = builder.insert_array_get(v2, v1, Type::field());
+        builder.terminate_with_return(vec![v2, v3]);
+
+        let ssa = builder.finish();
+
+        let context = Context::new(BackendCapabilities::default());
+        let acir = context.convert_ssa(ssa, Brillig::default()).unwrap();
+
+        // Each dynamic access is a single memory operation. Copying the array takes a read and a
+        // write per element, and each returned element is read back from memory.
+        let memory_ops =
+            acir.opcodes.iter().filter(|opcode| matches!(opcode, Opcode::MemoryOp { .. })).count();
+        assert!(memory_ops > 0 && memory_ops <= 8);
+        assert_eq!(acir.return_witnesses.len(), 3);
+    }
+
+    #[test]
+    fn distinguishes_read_only_memory() {
+        // fn main f0 {
+        //   b0(v0: Field, v1: Field):
+        //     v2 = array_get [Field 1, Field 2], index v0
+        //     v3 = array_set [Field 3, Field 4], index v0, value v1
+        //     return v2, v3
+        // }
+        let func_id = Id::test_new(0);
+        let mut builder = FunctionBuilder::new("func".into(), func_id, RuntimeType::Acir);
+
+        let v0 = builder.add_parameter(Type::field());
+        let v1 = builder.add_parameter(Type::field());
+        let one = builder.field_constant(1u128);
+        let two = builder.field_constant(2u128);
+        let three = builder.field_constant(3u128);
+        let four = builder.field_constant(4u128);
+
+        let array_type = Type::Array(Rc::new(vec![Type::field()]), 2);
+        let read_only = builder.array_constant(im::vector![one, two], array_type.clone());
+        let written = builder.array_constant(im::vector![three, four], array_type);
+        let v2 = builder.insert_array_get(read_only, v0, Type::field());
+        let v3 = builder.insert_array_set(written, v0, v1);
+        builder.terminate_with_return(vec![v2, v3]);
+
+        let ssa = builder.finish();
+
+        let context = Context::new(BackendCapabilities::default());
+        let acir = context.convert_ssa(ssa, Brillig::default()).unwrap();
+
+        let read_only = BlockId(read_only.to_usize() as u32);
+        assert_eq!(acir.memory_blocks[&read_only], MemoryKind::Rom);
+        assert!(acir.memory_blocks.values().any(|kind| *kind == MemoryKind::Ram));
+    }
+
+    #[test]
+    fn lowers_unsupported_black_boxes_into_bits() {
+        // fn main f0 {
+        //   b0(v0: u8, v1: u8):
+        //     v2 = and v0, v1
+        //     v3 = xor v0, v1
+        //     return v2, v3
+        // }
+        let func_id = Id::test_new(0);
+        let mut builder = FunctionBuilder::new("func".into(), func_id, RuntimeType::Acir);
+
+        let v0 = builder.add_parameter(Type::unsigned(8));
+        let v1 = builder.add_parameter(Type::unsigned(8));
+        let v2 = builder.insert_binary(v0, BinaryOp::And, v1);
+        let v3 = builder.insert_binary(v0, BinaryOp::Xor, v1);
+        builder.terminate_with_return(vec![v2, v3]);
+
+        let ssa = builder.finish();
+
+        let unsupported = [BlackBoxFunc::AND, BlackBoxFunc::XOR, BlackBoxFunc::RANGE];
+        let capabilities = BackendCapabilities::new(None, |func| !unsupported.contains(func));
+        let context = Context::new(capabilities);
+        let acir = context.convert_ssa(ssa, Brillig::default()).unwrap();
+
+        assert!(!acir.opcodes.iter().any(|opcode| matches!(opcode, Opcode::BlackBoxFuncCall(_))));
+        assert_eq!(acir.return_witnesses.len(), 2);
+    }
+
+    #[test]
+    fn keeps_boolean_operations_as_expressions() {
+        // fn main f0 {
+        //   b0(v0: u1, v1: u1, v2: u1):
+        //     v3 = and v0, v1
+        //     v4 = xor v3, v2
+        //     return v4
+        // }
+        let func_id = Id::test_new(0);
+        let mut builder = FunctionBuilder::new("func".into(), func_id, RuntimeType::Acir);
+
+        let v0 = builder.add_parameter(Type::bool());
+        let v1 = builder.add_parameter(Type::bool());
+        let v2 = builder.add_parameter(Type::bool());
+        let v3 = builder.insert_binary(v0, BinaryOp::And, v1);
+        let v4 = builder.insert_binary(v3, BinaryOp::Xor, v2);
+        builder.terminate_with_return(vec![v4]);
+
+        let ssa = builder.finish();
+
+        let context = Context::new(BackendCapabilities::default());
+        let acir = context.convert_ssa(ssa, Brillig::default()).unwrap();
+
+        let is_bitwise = |opcode: &Opcode| {
+            matches!(
+                opcode,
+                Opcode::BlackBoxFuncCall(
+                    BlackBoxFuncCall::AND { .. } | BlackBoxFuncCall::XOR { .. }
+                )
+            )
+        };
+        assert!(!acir.opcodes.iter().any(is_bitwise));
+
+        // `v3` is only given a witness to be multiplied by `v2`, and `v4` to be returned
+        let arithmetic_opcodes =
+            acir.opcodes.iter().filter(|opcode| matches!(opcode, Opcode::Arithmetic(_))).count();
+        assert_eq!(arithmetic_opcodes, 2);
+    }
+
+    #[test]
+    fn verifies_proofs_recursively() {
+        // fn main f0 {
+        //   b0(v0: Field, v1: Field, v2: Field, v3: Field, v4: Field):
+        //     v5 = call recursive_aggregation([v0, v1], [v2], [v3], v4, []) -> [Field]
+        //     return v5
+        // }
+        let func_id = Id::test_new(0);
+        let mut builder = FunctionBuilder::new("func".into(), func_id, RuntimeType::Acir);
+
+        let params = vecmap(0..5, |_| builder.add_parameter(Type::field()));
+        let slice_type = Type::Slice(Rc::new(vec![Type::field()]));
+        let verification_key =
+            builder.array_constant(im::vector![params[0], params[1]], slice_type.clone());
+        let proof = builder.array_constant(im::vector![params[2]], slice_type.clone());
+        let public_inputs = builder.array_constant(im::vector![params[3]], slice_type.clone());
+        let aggregation_object = builder.array_constant(im::Vector::new(), slice_type.clone());
+
+        let verify_proof =
+            builder.import_intrinsic_id(Intrinsic::BlackBox(BlackBoxFunc::RecursiveAggregation));
+        let arguments = vec![verification_key, proof, public_inputs, params[4], aggregation_object];
+        let v5 = builder.insert_call(verify_proof, arguments, vec![slice_type])[0];
+        builder.terminate_with_return(vec![v5]);
+
+        let ssa = builder.finish();
+
+        let context = Context::new(BackendCapabilities::default());
+        let acir = context.convert_ssa(ssa, Brillig::default()).unwrap();
+
+        let call = acir.opcodes.iter().find_map(|opcode| match opcode {
+            Opcode::BlackBoxFuncCall(call @ BlackBoxFuncCall::RecursiveAggregation { .. }) => {
+                Some(call)
+            }
+            _ => None,
+        });
+        match call {
+            Some(BlackBoxFuncCall::RecursiveAggregation {
+                verification_key,
+                proof,
+                public_inputs,
+                key_hash,
+                input_aggregation_object,
+                output_aggregation_object,
+            }) => {
+                assert_eq!(verification_key.len(), 2);
+                assert_eq!(proof.len(), 1);
+                assert_eq!(public_inputs.len(), 1);
+                assert_eq!(key_hash.witness, Witness(5));
+                assert_eq!(*input_aggregation_object, None);
+                assert_eq!(acir.return_witnesses, *output_aggregation_object);
+            }
+            _ => panic!("Expected a recursive aggregation opcode"),
+        }
+        assert_eq!(acir.return_witnesses.len(), 16);
+    }
+
+    #[test]
+    fn multiplies_128_bit_integers_from_limbs() {
+        // fn main f0 {
+        //   b0(v0: u128, v1: u128):
+        //     v2 = mul v0, v1
+        //     v3 = truncate v2 to 128 bits, max_bit_size: 256
+        //     return v3
+        // }
+        let func_id = Id::test_new(0);
+        let mut builder = FunctionBuilder::new("func".into(), func_id, RuntimeType::Acir);
+
+        let v0 = builder.add_parameter(Type::unsigned(128));
+        let v1 = builder.add_parameter(Type::unsigned(128));
+        let v2 = builder.insert_binary(v0, BinaryOp::Mul, v1);
+        let v3 = builder.insert_truncate(v2, 128, 256);
+        builder.terminate_with_return(vec![v3]);
+
+        let ssa = builder.finish();
+
+        let context = Context::new(BackendCapabilities::default());
+        let acir = context.convert_ssa(ssa, Brillig::default()).unwrap();
+
+        // No value is ever assumed to have more bits than a 128-bit integer, as a product which
+        // does not fit within a field element would be
+        for opcode in &acir.opcodes {
+            if let Opcode::BlackBoxFuncCall(BlackBoxFuncCall::RANGE { input }) = opcode {
+                assert!(input.num_bits <= 128);
+            }
+        }
+        assert_eq!(acir.return_witnesses.len(), 1);
+    }
+
+    #[test]
+    fn divides_128_bit_integers_without_wrapping_around_the_field() {
+        // fn main f0 {
+        //   b0(v0: u128, v1: u128):
+        //     v2 = div v0, v1
+        //     v3 = mod v0, u128 170141183460469231731687303715884105729
+        //     return v2, v3
+        // }
+        let func_id = Id::test_new(0);
+        let mut builder = FunctionBuilder::new("func".into(), func_id, RuntimeType::Acir);
+
+        let v0 = builder.add_parameter(Type::unsigned(128));
+        let v1 = builder.add_parameter(Type::unsigned(128));
+        let v2 = builder.insert_binary(v0, BinaryOp::Div, v1);
+        // 2^127 + 1
+        let divisor =
+            FieldElement::from(2_u128).pow(&FieldElement::from(127_u128)) + FieldElement::one();
+        let divisor = builder.numeric_constant(divisor, Type::unsigned(128));
+        let v3 = builder.insert_binary(v0, BinaryOp::Mod, divisor);
+        builder.terminate_with_return(vec![v2, v3]);
+
+        let ssa = builder.finish();
+
+        let context = Context::new(BackendCapabilities::default());
+        let acir = context.convert_ssa(ssa, Brillig::default()).unwrap();
+
+        // A divisor and a quotient of up to 128 bits each may have a product which wraps around
+        // the field, so no two witnesses whose range constraints allow it are multiplied together
+        let mut num_bits = HashMap::new();
+        for opcode in &acir.opcodes {
+            if let Opcode::BlackBoxFuncCall(BlackBoxFuncCall::RANGE { input }) = opcode {
+                num_bits.insert(input.witness, input.num_bits);
+            }
+        }
+        for opcode in &acir.opcodes {
+            if let Opcode::Arithmetic(expr) = opcode {
+                for (_, lhs, rhs) in &expr.mul_terms {
+                    if let (Some(lhs_bits), Some(rhs_bits)) = (num_bits.get(lhs), num_bits.get(rhs))
+                    {
+                        assert!(lhs_bits + rhs_bits < FieldElement::max_num_bits());
+                    }
+                }
+            }
+        }
+        assert_eq!(acir.return_witnesses.len(), 2);
+    }
+
+    #[test]
+    fn truncates_subtractions_which_are_not_lowered() {
+        // fn main f0 {
+        //   b0(v0: u8, v1: u8):
+        //     v2 = sub v0, v1
+        //     v3 = truncate v2 to 8 bits, max_bit_size: 9
+        //     return v3
+        // }
+        let func_id = Id::test_new(0);
+        let mut builder = FunctionBuilder::new("func".into(), func_id, RuntimeType::Acir);
+
+        let v0 = builder.add_parameter(Type::unsigned(8));
+        let v1 = builder.add_parameter(Type::unsigned(8));
+        let v2 = builder.insert_binary(v0, BinaryOp::Sub, v1);
+        let v3 = builder.insert_truncate(v2, 8, 9);
+        builder.terminate_with_return(vec![v3]);
+
+        let ssa = builder.finish();
+
+        // Integer arithmetic is not lowered here, as when the pass is removed from the pipeline,
+        // so the integer modulus is added to the subtraction when it is truncated
+        let context = Context::new(BackendCapabilities::default());
+        let acir = context.convert_ssa(ssa, Brillig::default()).unwrap();
+
+        let underflow_offset = FieldElement::from(256_u128);
+        assert!(acir.opcodes.iter().any(|opcode| matches!(
+            opcode,
+            Opcode::Directive(Directive::Quotient(QuotientDirective { a, .. }))
+                if a.q_c == underflow_offset
+        )));
+        assert_eq!(acir.return_witnesses.len(), 1);
+    }
+
+    #[test]
+    fn shifts_by_non_constant_amounts_through_their_bits() {
+        // fn main f0 {
+        //   b0(v0: u32, v1: u32):
+        //     v2 = shl v0, v1
+        //     v3 = shr v0, v1
+        //     return v2, v3
+        // }
+        let func_id = Id::test_new(0);
+        let mut builder = FunctionBuilder::new("func".into(), func_id, RuntimeType::Acir);
+
+        let v0 = builder.add_parameter(Type::unsigned(32));
+        let v1 = builder.add_parameter(Type::unsigned(32));
+        let v2 = builder.insert_binary(v0, BinaryOp::Shl, v1);
+        let v3 = builder.insert_binary(v0, BinaryOp::Shr, v1);
+        builder.terminate_with_return(vec![v2, v3]);
+
+        let ssa = builder.finish();
+
+        let context = Context::new(BackendCapabilities::default());
+        let acir = context.convert_ssa(ssa, Brillig::default()).unwrap();
+
+        // Only the five bits of the shift amount which may shift within a u32 are decomposed for
+        // each shift, rather than selecting between every possible shift
+        let decompositions: Vec<_> = acir
+            .opcodes
+            .iter()
+            .filter_map(|opcode| match opcode {
+                Opcode::Directive(Directive::ToLeRadix { b, .. }) => Some(b.len()),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(decompositions, vec![5, 5]);
+        assert_eq!(acir.return_witnesses.len(), 2);
+    }
+
+    #[test]
+    fn reduces_shifts_by_non_constant_amounts() {
+        // fn main f0 {
+        //   b0(v0: u8, v1: u8):
+        //     v2 = shl v0, v1
+        //     v3 = truncate v2 to 8 bits, max_bit_size: 254
+        //     return v3
+        // }
+        let func_id = Id::test_new(0);
+        let mut builder = FunctionBuilder::new("func".into(), func_id, RuntimeType::Acir);
+
+        let v0 = builder.add_parameter(Type::unsigned(8));
+        let v1 = builder.add_parameter(Type::unsigned(8));
+        let v2 = builder.insert_binary(v0, BinaryOp::Shl, v1);
+        let v3 = builder.insert_truncate(v2, 8, FieldElement::max_num_bits());
+        builder.terminate_with_return(vec![v3]);
+
+        let ssa = builder.finish();
+
+        let context = Context::new(BackendCapabilities::default());
+        let acir = context.convert_ssa(ssa, Brillig::default()).unwrap();
+
+        // The truncation is skipped as the shift may exceed the field, so the shift itself
+        // returns the remainder of the shifted value by 2^8, e.g. 144 rather than 400 for 200 << 1
+        let divisor = Expression::from_field(FieldElement::from(256_u128));
+        assert_eq!(acir.return_witnesses.len(), 1);
+        assert!(acir.opcodes.iter().any(|opcode| matches!(
+            opcode,
+            Opcode::Directive(Directive::Quotient(QuotientDirective { b, r, .. }))
+                if *b == divisor && *r == acir.return_witnesses[0]
+        )));
+    }
+
+    #[test]
+    fn prints_through_foreign_calls() {
+        // fn main f0 {
+        //   b0(v0: u8):
+        //     call println(v0)
+        //     return
+        // }
+        let func_id = Id::test_new(0);
+        let mut builder = FunctionBuilder::new("func".into(), func_id, RuntimeType::Acir);
+
+        let v0 = builder.add_parameter(Type::unsigned(8));
+        let println = builder.import_intrinsic_id(Intrinsic::Println);
+        builder.insert_call(println, vec![v0], vec![]);
+        builder.terminate_with_return(vec![]);
+
+        let ssa = builder.finish();
+
+        let context = Context::new(BackendCapabilities::default());
+        let acir = context.convert_ssa(ssa, Brillig::default()).unwrap();
+
+        let brillig = acir
+            .opcodes
+            .iter()
+            .find_map(|opcode| match opcode {
+                Opcode::Brillig(brillig) => Some(brillig),
+                _ => None,
+            })
+            .expect("Expected a brillig opcode calling println");
+        assert!(matches!(
+            &brillig.bytecode[0],
+            BrilligOpcode::ForeignCall { function, .. } if function == "println"
+        ));
+
+        // The printed value is followed by the type describing how to display it
+        let format = match &brillig.inputs[..] {
+            [BrilligInputs::Array(values), BrilligInputs::Array(format)] => {
+                assert_eq!(values, &vec![Expression::from(Witness(1))]);
+                format
+            }
+            inputs => panic!("Expected the printed values and their format, found {inputs:?}"),
+        };
+        let format = vecmap(format, |byte| byte.to_const().unwrap());
+        let abi_type = AbiType::Integer { sign: Sign::Unsigned, width: 8 };
+        assert_eq!(decode_string_value(&format), serde_json::to_string(&abi_type).unwrap());
+    }
+
+    #[test]
+    fn hashes_messages_of_variable_length() {
+        // fn main f0 {
+        //   b0(v0: u8, v1: u32):
+        //     v2 = call keccak256([v0, v0], 2) -> [u8; 32]
+        //     v3 = call keccak256([v0, v0], v1) -> [u8; 32]
+        //     return v2, v3
+        // }
+        let func_id = Id::test_new(0);
+        let mut builder = FunctionBuilder::new("func".into(), func_id, RuntimeType::Acir);
+
+        let v0 = builder.add_parameter(Type::unsigned(8));
+        let v1 = builder.add_parameter(Type::unsigned(32));
+        let message_type = Type::Array(Rc::new(vec![Type::unsigned(8)]), 2);
+        let message = builder.array_constant(im::vector![v0, v0], message_type);
+        let two = builder.numeric_constant(2u128, Type::unsigned(32));
+
+        let keccak = builder.import_intrinsic_id(Intrinsic::BlackBox(BlackBoxFunc::Keccak256));
+        let digest_type = Type::Array(Rc::new(vec![Type::unsigned(8)]), 32);
+        let v2 = builder.insert_call(keccak, vec![message, two], vec![digest_type.clone()])[0];
+        let v3 = builder.insert_call(keccak, vec![message, v1], vec![digest_type])[0];
+        builder.terminate_with_return(vec![v2, v3]);
+
+        let ssa = builder.finish();
+
+        let context = Context::new(BackendCapabilities::default());
+        let acir = context.convert_ssa(ssa, Brillig::default()).unwrap();
+
+        let calls = acir.opcodes.iter().filter_map(|opcode| match opcode {
+            Opcode::BlackBoxFuncCall(BlackBoxFuncCall::Keccak256 { inputs, .. }) => {
+                Some((inputs.len(), None))
+            }
+            Opcode::BlackBoxFuncCall(BlackBoxFuncCall::Keccak256VariableLength {
+                inputs,
+                var_message_size,
+                ..
+            }) => Some((inputs.len(), Some(var_message_size.witness))),
+            _ => None,
+        });
+        assert_eq!(calls.collect::<Vec<_>>(), vec![(2, None), (2, Some(Witness(2)))]);
+    }
+
+    #[test]
+    fn rejects_ecdsa_signatures_of_the_wrong_length() {
+        // fn main f0 {
+        //   b0(v0: u8):
+        //     v1 = call ecdsa_secp256r1([v0; 32], [v0; 32], [v0; 63], [v0; 32]) -> u1
+        //     return v1
+        // }
+        let func_id = Id::test_new(0);
+        let mut builder = FunctionBuilder::new("func".into(), func_id, RuntimeType::Acir);
+
+        let v0 = builder.add_parameter(Type::unsigned(8));
+        let mut bytes = |length| {
+            let array_type = Type::Array(Rc::new(vec![Type::unsigned(8)]), length);
+            builder.array_constant(im::Vector::from(vec![v0; length]), array_type)
+        };
+        let arguments = vec![bytes(32), bytes(32), bytes(63), bytes(32)];
+
+        let verify_signature =
+            builder.import_intrinsic_id(Intrinsic::BlackBox(BlackBoxFunc::EcdsaSecp256r1));
+        let v1 = builder.insert_call(verify_signature, arguments, vec![Type::bool()])[0];
+        builder.terminate_with_return(vec![v1]);
+
+        let ssa = builder.finish();
+
+        let context = Context::new(BackendCapabilities::default());
+        let error = context.convert_ssa(ssa, Brillig::default()).unwrap_err();
+        assert_eq!(
+            error.message(),
+            "Invalid input to the ecdsa_secp256r1 black box function: the signature must be 64 bytes long, not 63"
+        );
+    }
+
+    #[test]
+    fn passes_the_domain_separator_to_pedersen() {
+        // fn main f0 {
+        //   b0(v0: Field):
+        //     v1 = call pedersen([v0], u32 7) -> [Field; 2]
+        //     return v1
+        // }
+        let func_id = Id::test_new(0);
+        let mut builder = FunctionBuilder::new("func".into(), func_id, RuntimeType::Acir);
+
+        let v0 = builder.add_parameter(Type::field());
+        let input_type = Type::Array(Rc::new(vec![Type::field()]), 1);
+        let input = builder.array_constant(im::Vector::from(vec![v0]), input_type);
+        let separator = builder.numeric_constant(7_u128, Type::unsigned(32));
+        let output_type = Type::Array(Rc::new(vec![Type::field()]), 2);
+
+        let pedersen = builder.import_intrinsic_id(Intrinsic::BlackBox(BlackBoxFunc::Pedersen));
+        let v1 = builder.insert_call(pedersen, vec![input, separator], vec![output_type])[0];
+        builder.terminate_with_return(vec![v1]);
+
+        let ssa = builder.finish();
+
+        let context = Context::new(BackendCapabilities::default());
+        let acir = context.convert_ssa(ssa, Brillig::default()).unwrap();
+
+        let domain_separators: Vec<u32> = acir
+            .opcodes
+            .iter()
+            .filter_map(|opcode| match opcode {
+                Opcode::BlackBoxFuncCall(BlackBoxFuncCall::Pedersen {
+                    domain_separator, ..
+                }) => Some(*domain_separator),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(domain_separators, vec![7]);
+    }
+
+    #[test]
+    fn rejects_pedersen_domain_separators_unknown_at_compile_time() {
+        // fn main f0 {
+        //   b0(v0: Field, v1: u32):
+        //     v2 = call pedersen([v0], v1) -> [Field; 2]
+        //     return v2
+        // }
+        let func_id = Id::test_new(0);
+        let mut builder = FunctionBuilder::new("func".into(), func_id, RuntimeType::Acir);
+
+        let v0 = builder.add_parameter(Type::field());
+        let v1 = builder.add_parameter(Type::unsigned(32));
+        let input_type = Type::Array(Rc::new(vec![Type::field()]), 1);
+        let input = builder.array_constant(im::Vector::from(vec![v0]), input_type);
+        let output_type = Type::Array(Rc::new(vec![Type::field()]), 2);
+
+        let pedersen = builder.import_intrinsic_id(Intrinsic::BlackBox(BlackBoxFunc::Pedersen));
+        let v2 = builder.insert_call(pedersen, vec![input, v1], vec![output_type])[0];
+        builder.terminate_with_return(vec![v2]);
+
+        let ssa = builder.finish();
+
+        let context = Context::new(BackendCapabilities::default());
+        let error = context.convert_ssa(ssa, Brillig::default()).unwrap_err();
+        assert_eq!(
+            error.message(),
+            "Invalid input to the pedersen black box function: the domain separator must be a constant u32"
+        );
+    }
+
+    #[test]
+    fn checks_big_integer_multiplication() {
+        // fn main f0 {
+        //   b0(v0: Field, v1: Field, v2: Field, v3: Field,
+        //      v4: Field, v5: Field, v6: Field, v7: Field):
+        //     call bigint_mul_add_mod([v0, v1], [v2, v3], [0, 0], [5, 7], [v4, v5], [v6, v7])
+        //     return
+        // }
+        let func_id = Id::test_new(0);
+        let mut builder = FunctionBuilder::new("func".into(), func_id, RuntimeType::Acir);
+
+        let params = vecmap(0..8, |_| builder.add_parameter(Type::field()));
+        let array_type = Type::Array(Rc::new(vec![Type::field()]), 2);
+        let zero = builder.field_constant(0u128);
+        let (five, seven) = (builder.field_constant(5u128), builder.field_constant(7u128));
+        let mut limbs = |limbs: [ValueId; 2]| {
+            builder.array_constant(im::Vector::from(limbs.to_vec()), array_type.clone())
+        };
+        let arguments = vec![
+            limbs([params[0], params[1]]),
+            limbs([params[2], params[3]]),
+            limbs([zero, zero]),
+            limbs([five, seven]),
+            limbs([params[4], params[5]]),
+            limbs([params[6], params[7]]),
+        ];
+
+        let mul_add_mod = builder.import_intrinsic_id(Intrinsic::BigIntMulAddMod);
+        builder.insert_call(mul_add_mod, arguments, Vec::new());
+        builder.terminate_with_return(Vec::new());
+
+        let ssa = builder.finish();
+
+        let context = Context::new(BackendCapabilities::default());
+        let acir = context.convert_ssa(ssa, Brillig::default()).unwrap();
+
+        // Each limb of the parameters is range constrained
+        for index in 1..=8 {
+            let range = Opcode::BlackBoxFuncCall(BlackBoxFuncCall::RANGE {
+                input: FunctionInput { witness: Witness(index), num_bits: 64 },
+            });
+            assert!(acir.opcodes.contains(&range));
+        }
+
+        // One carry is computed for each of the three columns of the product, and one borrow for
+        // each limb when comparing the remainder to the modulus
+        let quotients = acir
+            .opcodes
+            .iter()
+            .filter(|opcode| matches!(opcode, Opcode::Directive(Directive::Quotient(_))))
+            .count();
+        assert_eq!(quotients, 5);
+    }
+
+    #[test]
+    fn asserts_constraints_guarded_by_the_same_condition_together() {
+        // fn main f0 {
+        //   b0(v0: Field, v1: Field, v2: Field, v3: u1):
+        //     v4 = eq v0, v1
+        //     v5 = mul v4, v3
+        //     v6 = eq v5, v3
+        //     constrain v6
+        //     v7 = eq v0, v2
+        //     v8 = mul v7, v3
+        //     v9 = eq v8, v3
+        //     constrain v9
+        //     return
+        // }
+        let func_id = Id::test_new(0);
+        let mut builder = FunctionBuilder::new("func".into(), func_id, RuntimeType::Acir);
+
+        let v0 = builder.add_parameter(Type::field());
+        let v1 = builder.add_parameter(Type::field());
+        let v2 = builder.add_parameter(Type::field());
+        let v3 = builder.add_parameter(Type::bool());
+        for rhs in [v1, v2] {
+            let value = builder.insert_binary(v0, BinaryOp::Eq, rhs);
+            let guarded = builder.insert_binary(value, BinaryOp::Mul, v3);
+            let guarded = builder.insert_binary(guarded, BinaryOp::Eq, v3);
+            builder.insert_constrain(guarded, None);
+        }
+        builder.terminate_with_return(Vec::new());
+
+        let ssa = builder.finish();
+
+        let context = Context::new(BackendCapabilities::default());
+        let acir = context.convert_ssa(ssa, Brillig::default()).unwrap();
+
+        // Only the two comparisons of the parameters need an inverse, rather than also the two
+        // comparisons of the guarded values with the condition
+        let inversions =
+            acir.opcodes.iter().filter(|opcode| matches!(opcode, Opcode::Brillig(_))).count();
+        assert_eq!(inversions, 2);
+
+        // Both constraints are asserted by a single multiplication by the condition
+        let condition = Witness(4);
+        let guarded_assertions = acir
+            .opcodes
+            .iter()
+            .filter(|opcode| match opcode {
+                Opcode::Arithmetic(expr) => expr
+                    .mul_terms
+                    .iter()
+                    .any(|(_, lhs, rhs)| *lhs == condition || *rhs == condition),
+                _ => false,
+            })
+            .count();
+        assert_eq!(guarded_assertions, 1);
+    }
+
+    #[test]
+    fn keeps_the_location_of_guarded_constraints() {
+        // fn main f0 {
+        //   b0(v0: Field, v1: Field, v2: Field, v3: u1):
+        //     v4 = eq v0, v1
+        //     v5 = mul v4, v3
+        //     v6 = eq v5, v3
+        //     constrain v6
+        //     v7 = eq v0, v2
+        //     v8 = mul v7, v3
+        //     v9 = eq v8, v3
+        //     constrain v9
+        //     return
+        // }
+        // with each constraint at its own location
+        let func_id = Id::test_new(0);
+        let mut builder = FunctionBuilder::new("func".into(), func_id, RuntimeType::Acir);
+
+        let v0 = builder.add_parameter(Type::field());
+        let v1 = builder.add_parameter(Type::field());
+        let v2 = builder.add_parameter(Type::field());
+        let v3 = builder.add_parameter(Type::bool());
+        let locations = vec![
+            Location::new(Span::single_char(0), Default::default()),
+            Location::new(Span::single_char(1), Default::default()),
+        ];
+        for (rhs, location) in [v1, v2].into_iter().zip(&locations) {
+            builder.set_location(*location);
+            let value = builder.insert_binary(v0, BinaryOp::Eq, rhs);
+            let guarded = builder.insert_binary(value, BinaryOp::Mul, v3);
+            let guarded = builder.insert_binary(guarded, BinaryOp::Eq, v3);
+            builder.insert_constrain(guarded, None);
+        }
+        builder.terminate_with_return(Vec::new());
+
+        let ssa = builder.finish();
+
+        let context = Context::new(BackendCapabilities::default());
+        let acir = context.convert_ssa(ssa, Brillig::default()).unwrap();
+
+        // Constraints from different locations are asserted separately, each at its location
+        let condition = Witness(4);
+        let assertion_locations: Vec<_> = acir
+            .opcodes
+            .iter()
+            .enumerate()
+            .filter(|(_, opcode)| match opcode {
+                Opcode::Arithmetic(expr) => expr
+                    .mul_terms
+                    .iter()
+                    .any(|(_, lhs, rhs)| *lhs == condition || *rhs == condition),
+                _ => false,
+            })
+            .map(|(index, _)| acir.locations[&index])
+            .collect();
+        assert_eq!(assertion_locations, locations);
+    }
+
+    #[test]
+    fn range_constrains_brillig_outputs() {
+        // fn main f0 {
+        //   b0(v0: Field):
+        //     v1 = call f1(v0) -> u8
+        //     return v1
+        // }
+        // brillig fn hint f1 {
+        //   b0(v0: Field):
+        //     v1 = cast v0 as u8
+        //     return v1
+        // }
+        let main_id = Id::test_new(0);
+        let hint_id = Id::test_new(1);
+        let mut builder = FunctionBuilder::new("main".into(), main_id, RuntimeType::Acir);
+        let v0 = builder.add_parameter(Type::field());
+        let hint = builder.import_function(hint_id);
+        let v1 = builder.insert_call(hint, vec![v0], vec![Type::unsigned(8)])[0];
+        builder.terminate_with_return(vec![v1]);
+
+        builder.new_brillig_function("hint".into(), hint_id);
+        let v0 = builder.add_parameter(Type::field());
+        let v1 = builder.insert_cast(v0, Type::unsigned(8));
+        builder.terminate_with_return(vec![v1]);
+
+        let ssa = builder.finish();
+        let brillig = ssa.to_brillig(false).unwrap();
+
+        let context = Context::new(BackendCapabilities::default());
+        let acir = context.convert_ssa(ssa, brillig).unwrap();
+
+        // The value returned by the unconstrained hint is constrained to fit within a u8
+        let output = acir.return_witnesses[0];
+        let range = Opcode::BlackBoxFuncCall(BlackBoxFuncCall::RANGE {
+            input: FunctionInput { witness: output, num_bits: 8 },
+        });
+        assert!(acir.opcodes.contains(&range));
+    }
+
+    #[test]
+    fn compiles_the_calls_kept_by_inlining() {
+        // fn main f0 {
+        //   b0(v0: Field):
+        //     v1 = call f1(v0) -> Field
+        //     v2 = call f2(v1) -> Field
+        //     return v2
+        // }
+        // fn double f1 {
+        //   b0(v0: Field):
+        //     v1 = mul v0, Field 2
+        //     return v1
+        // }
+        // brillig fn hint f2 {
+        //   b0(v0: Field):
+        //     v1 = add v0, Field 1
+        //     return v1
+        // }
+        let main_id = Id::test_new(0);
+        let double_id = Id::test_new(1);
+        let hint_id = Id::test_new(2);
+        let mut builder = FunctionBuilder::new("main".into(), main_id, RuntimeType::Acir);
+        let v0 = builder.add_parameter(Type::field());
+        let double = builder.import_function(double_id);
+        let v1 = builder.insert_call(double, vec![v0], vec![Type::field()])[0];
+        let hint = builder.import_function(hint_id);
+        let v2 = builder.insert_call(hint, vec![v1], vec![Type::field()])[0];
+        builder.terminate_with_return(vec![v2]);
+
+        builder.new_function("double".into(), double_id);
+        let v0 = builder.add_parameter(Type::field());
+        let two = builder.field_constant(2_u128);
+        let v1 = builder.insert_binary(v0, BinaryOp::Mul, two);
+        builder.terminate_with_return(vec![v1]);
+
+        builder.new_brillig_function("hint".into(), hint_id);
+        let v0 = builder.add_parameter(Type::field());
+        let one = builder.field_constant(1_u128);
+        let v1 = builder.insert_binary(v0, BinaryOp::Add, one);
+        builder.terminate_with_return(vec![v1]);
+
+        // Every call to an ACIR function is inlined, whereas calls to brillig functions are kept
+        // and compiled into brillig opcodes
+        let ssa = builder.finish().inline_functions().unwrap();
+        let brillig = ssa.to_brillig(false).unwrap();
+
+        let context = Context::new(BackendCapabilities::default());
+        let acir = context.convert_ssa(ssa, brillig).unwrap();
+
+        let brillig_calls =
+            acir.opcodes.iter().filter(|opcode| matches!(opcode, Opcode::Brillig(_))).count();
+        assert_eq!(brillig_calls, 1);
+    }
+
+    #[test]
+    fn records_brillig_locations() {
+        // fn main f0 {
+        //   b0(v0: Field):
+        //     v1 = call f1(v0) -> Field
+        //     return v1
+        // }
+        // brillig fn hint f1 {
+        //   b0(v0: Field):
+        //     v1 = add v0, v0
+        //     return v1
+        // }
+        let main_id = Id::test_new(0);
+        let hint_id = Id::test_new(1);
+        let mut builder = FunctionBuilder::new("main".into(), main_id, RuntimeType::Acir);
+        let v0 = builder.add_parameter(Type::field());
+        let hint = builder.import_function(hint_id);
+        let v1 = builder.insert_call(hint, vec![v0], vec![Type::field()])[0];
+        builder.terminate_with_return(vec![v1]);
+
+        builder.new_brillig_function("hint".into(), hint_id);
+        let v0 = builder.add_parameter(Type::field());
+        let location = Location::new(Span::single_char(0), Default::default());
+        let v1 = builder.set_location(location).insert_binary(v0, BinaryOp::Add, v0);
+        builder.terminate_with_return(vec![v1]);
+
+        let ssa = builder.finish();
+        let brillig = ssa.to_brillig(false).unwrap();
+
+        let mut context = Context::new(BackendCapabilities::default());
+        context.acir_context.record_brillig_locations();
+        let acir = context.convert_ssa(ssa, brillig).unwrap();
+
+        let brillig_index =
+            acir.opcodes.iter().position(|opcode| matches!(opcode, Opcode::Brillig(_))).unwrap();
+        let bytecode = match &acir.opcodes[brillig_index] {
+            Opcode::Brillig(brillig) => &brillig.bytecode,
+            _ => unreachable!(),
+        };
+        let locations = &acir.brillig_locations.unwrap()[&brillig_index];
+        assert!(!locations.is_empty());
+        assert!(locations.values().all(|recorded| *recorded == location));
+        assert!(locations
+            .keys()
+            .any(|index| matches!(bytecode[*index], BrilligOpcode::BinaryFieldOp { .. })));
+    }
+
+    #[test]
+    fn checks_sorted_permutations_with_a_permutation_network() {
+        // fn main f0 {
+        //   b0(v0: [u8; 3], v1: [u8; 3]):
+        //     call assert_sorted_permutation(v0, v1)
+        //     return
+        // }
+        let func_id = Id::test_new(0);
+        let mut builder = FunctionBuilder::new("func".into(), func_id, RuntimeType::Acir);
+        let array_type = Type::Array(Rc::new(vec![Type::unsigned(8)]), 3);
+        let v0 = builder.add_parameter(array_type.clone());
+        let v1 = builder.add_parameter(array_type);
+        let assert_sorted = builder.import_intrinsic_id(Intrinsic::AssertSortedPermutation);
+        builder.insert_call(assert_sorted, vec![v0, v1], Vec::new());
+        builder.terminate_with_return(Vec::new());
+
+        let ssa = builder.finish();
+
+        let context = Context::new(BackendCapabilities::default());
+        let acir = context.convert_ssa(ssa, Brillig::default()).unwrap();
+
+        // The sorted array is given, so no unconstrained code is needed to compute it
+        let permutations = acir
+            .opcodes
+            .iter()
+            .filter(|opcode| matches!(opcode, Opcode::Directive(Directive::PermutationSort { .. })))
+            .count();
+        assert_eq!(permutations, 1);
+        assert!(!acir.opcodes.iter().any(|opcode| matches!(opcode, Opcode::Brillig(_))));
+    }
+
+    #[test]
+    fn sorts_arrays_with_unconstrained_code() {
+        // fn main f0 {
+        //   b0(v0: [u8; 3]):
+        //     v1 = call arraysort(v0)
+        //     return v1
+        // }
+        let func_id = Id::test_new(0);
+        let mut builder = FunctionBuilder::new("func".into(), func_id, RuntimeType::Acir);
+        let array_type = Type::Array(Rc::new(vec![Type::unsigned(8)]), 3);
+        let v0 = builder.add_parameter(array_type.clone());
+        let sort = builder.import_intrinsic_id(Intrinsic::Sort);
+        let v1 = builder.insert_call(sort, vec![v0], vec![array_type])[0];
+        builder.terminate_with_return(vec![v1]);
+
+        let ssa = builder.finish();
+
+        let context = Context::new(BackendCapabilities::default());
+        let acir = context.convert_ssa(ssa, Brillig::default()).unwrap();
+
+        // The sorted array is computed by Brillig code, and then checked by the permutation network
+        let sorted = acir.opcodes.iter().find_map(|opcode| match opcode {
+            Opcode::Brillig(brillig) => match brillig.outputs.as_slice() {
+                [BrilligOutputs::Array(witnesses)] => Some(witnesses.clone()),
+                _ => None,
+            },
+            _ => None,
+        });
+        assert_eq!(sorted, Some(acir.return_witnesses.clone()));
+        assert!(acir
+            .opcodes
+            .iter()
+            .any(|opcode| matches!(opcode, Opcode::Directive(Directive::PermutationSort { .. }))));
+    }
+
+    #[test]
+    fn records_assertion_messages() {
+        // fn main f0 {
+        //   b0(v0: Field, v1: Field):
+        //     v2 = eq v0, v1
+        //     constrain v2, "v0 and v1 differ"
+        //     return
+        // }
+        let func_id = Id::test_new(0);
+        let mut builder = FunctionBuilder::new("func".into(), func_id, RuntimeType::Acir);
+
+        let v0 = builder.add_parameter(Type::field());
+        let v1 = builder.add_parameter(Type::field());
+        let v2 = builder.insert_binary(v0, BinaryOp::Eq, v1);
+        builder.insert_constrain(v2, Some("v0 and v1 differ".to_string()));
+        builder.terminate_with_return(Vec::new());
+
+        let ssa = builder.finish();
+
+        let context = Context::new(BackendCapabilities::default());
+        let acir = context.convert_ssa(ssa, Brillig::default()).unwrap();
+
+        // Only the assertion itself is given the message, not the opcodes computing the comparison
+        assert_eq!(acir.assert_messages.len(), 1);
+        let (index, message) = acir.assert_messages.iter().next().unwrap();
+        assert_eq!(message, "v0 and v1 differ");
+        assert!(matches!(acir.opcodes[*index], Opcode::Arithmetic(_)));
+        assert_eq!(*index, acir.opcodes.len() - 1);
+    }
+
+    #[test]
+    fn records_opcode_provenance() {
+        // fn main f0 {
+        //   b0(v0: Field):
+        //     v1 = cast v0 as u8
+        //     return v1
+        // }
+        let func_id = Id::test_new(0);
+        let mut builder = FunctionBuilder::new("func".into(), func_id, RuntimeType::Acir);
+        let v0 = builder.add_parameter(Type::field());
+        let v1 = builder.insert_cast(v0, Type::unsigned(8));
+        builder.terminate_with_return(vec![v1]);
+
+        let ssa = builder.finish();
+
+        let mut context = Context::new(BackendCapabilities::default());
+        context.acir_context.record_provenance();
+        let acir = context.convert_ssa(ssa, Brillig::default()).unwrap();
+
+        let provenance = acir.provenance.unwrap();
+        let range_index = acir
+            .opcodes
+            .iter()
+            .position(|opcode| {
+                matches!(opcode, Opcode::BlackBoxFuncCall(BlackBoxFuncCall::RANGE { .. }))
+            })
+            .unwrap();
+        let range_provenance = &provenance[&range_index];
+        assert_eq!(range_provenance.instruction, "cast");
+        assert_eq!(range_provenance.purpose.as_deref(), Some("range check"));
+    }
+
+    #[test]
+    fn merges_range_constraints() {
+        let mut acir = GeneratedAcir::default();
+        acir.range_constraint(Witness(1), 32).unwrap();
+        acir.range_constraint(Witness(2), 16).unwrap();
+        acir.range_constraint(Witness(1), 8).unwrap();
+        acir.range_constraint(Witness(1), 16).unwrap();
+
+        let range = |witness, num_bits| {
+            Opcode::BlackBoxFuncCall(BlackBoxFuncCall::RANGE {
+                input: FunctionInput { witness, num_bits },
+            })
+        };
+        assert_eq!(acir.opcodes, vec![range(Witness(1), 8), range(Witness(2), 16)]);
+    }
+
+    #[test]
+    fn skips_range_constraints_on_boolean_witnesses() {
+        let mut acir = GeneratedAcir::default();
+        acir.current_witness_index = 2;
+
+        let is_equal = acir.is_equal(&Witness(1).into(), &Witness(2).into());
+        let opcode_count = acir.opcodes.len();
+        acir.range_constraint(is_equal, 1).unwrap();
+        assert_eq!(acir.opcodes.len(), opcode_count);
+    }
+
+    #[test]
+    fn reuses_radix_decompositions() {
+        let mut acir = GeneratedAcir::default();
+        acir.current_witness_index = 1;
+
+        let bits = acir.radix_le_decompose(&Witness(1).into(), 2, 16, 1).unwrap();
+        let opcode_count = acir.opcodes.len();
+        let low_bits = acir.radix_le_decompose(&Witness(1).into(), 2, 8, 1).unwrap();
+        assert_eq!(low_bits, bits[..8]);
+
+        // The upper eight bits are constrained to be zero rather than decomposed again
+        assert_eq!(acir.opcodes.len(), opcode_count + 8);
+        let decompositions = acir
+            .opcodes
+            .iter()
+            .filter(|opcode| matches!(opcode, Opcode::Directive(Directive::ToLeRadix { .. })))
+            .count();
+        assert_eq!(decompositions, 1);
+    }
+
+    #[test]
+    fn deduplicates_assertions_and_intermediate_witnesses() {
+        let mut acir = GeneratedAcir::default();
+        acir.current_witness_index = 2;
+
+        let product = (&Expression::from(Witness(1)) * &Expression::from(Witness(2))).unwrap();
+        let witness = acir.get_or_create_witness(&product);
+        assert_eq!(acir.get_or_create_witness(&product), witness);
+
+        let difference = &Expression::from(Witness(1)) - &Expression::from(Witness(2));
+        acir.assert_is_zero(difference.clone());
+        acir.assert_is_zero(&difference * -FieldElement::from(2_u128));
+        acir.assert_is_zero(Expression::default());
+        acir.deduplicate_assertions();
+
+        let definition = &product - witness;
+        let expected_opcodes = vec![Opcode::Arithmetic(definition), Opcode::Arithmetic(difference)];
+        assert_eq!(acir.opcodes, expected_opcodes);
+    }
+
+    #[test]
+    fn propagates_constant_witnesses() {
+        let mut acir = GeneratedAcir::default();
+        acir.current_witness_index = 3;
+
+        // w2 = 5, w3 = w1 * w2
+        let five = FieldElement::from(5_u128);
+        let definition = &Expression::from(Witness(2)) - &Expression::from_field(five);
+        acir.assert_is_zero(definition.clone());
+        let product = (&Expression::from(Witness(1)) * &Expression::from(Witness(2))).unwrap();
+        acir.assert_is_zero(&product - Witness(3));
+        acir.propagate_constants();
+
+        let substituted = Expression {
+            mul_terms: Vec::new(),
+            linear_combinations: vec![(five, Witness(1)), (-FieldElement::one(), Witness(3))],
+            q_c: FieldElement::zero(),
+        };
+        let expected_opcodes =
+            vec![Opcode::Arithmetic(definition), Opcode::Arithmetic(substituted)];
+        assert_eq!(acir.opcodes, expected_opcodes);
+    }
+
+    #[test]
+    fn removes_dead_opcodes() {
+        let mut acir = GeneratedAcir::default();
+        acir.current_witness_index = 1;
+        acir.input_witnesses = vec![Witness(1)];
+
+        // A witness defined to be a constant which fits within its range constraint
+        let constant = acir.next_witness_index();
+        let five = Expression::from_field(FieldElement::from(5_u128));
+        acir.assert_is_zero(&Expression::from(constant) - &five);
+        acir.range_constraint(constant, 8).unwrap();
+
+        // An intermediate witness which is never used, and an assertion which always holds
+        let square = (&Expression::from(Witness(1)) * &Expression::from(Witness(1))).unwrap();
+        acir.get_or_create_witness(&square);
+        acir.assert_is_zero(Expression::default());
+
+        let parameter_constraint = &Expression::from(Witness(1)) - &five;
+        acir.assert_is_zero(parameter_constraint.clone());
+        acir.remove_dead_opcodes();
+
+        // Once its range constraint is removed, the constant witness is unused as well
+        assert_eq!(acir.opcodes, vec![Opcode::Arithmetic(parameter_constraint)]);
+    }
+
+    #[test]
+    fn only_replaces_aliased_return_witnesses() {
+        let mut acir = GeneratedAcir::default();
+        acir.current_witness_index = 4;
+        acir.input_witnesses = vec![Witness(1), Witness(2)];
+        acir.return_witnesses = vec![Witness(1), Witness(3), Witness(3), Witness(4)];
+
+        acir.make_return_witnesses_distinct();
+
+        // The parameter and the second occurrence of witness 3 are given fresh witnesses
+        assert_eq!(acir.return_witnesses, vec![Witness(5), Witness(3), Witness(6), Witness(4)]);
+        let expected_opcodes = vec![
+            Opcode::Arithmetic(&Expression::from(Witness(1)) - Witness(5)),
+            Opcode::Arithmetic(&Expression::from(Witness(3)) - Witness(6)),
+        ];
+        assert_eq!(acir.opcodes, expected_opcodes);
+    }
+
+    #[test]
+    fn compacts_unused_witnesses() {
+        let mut acir = GeneratedAcir::default();
+        acir.current_witness_index = 3;
+
+        // Witness 2 is an unused parameter, and witness 3 is never referred to
+        let product = (&Expression::from(Witness(1)) * &Expression::from(Witness(1))).unwrap();
+        let square = acir.get_or_create_witness(&product);
+        assert_eq!(square, Witness(4));
+        acir.return_witnesses.push(square);
+
+        let new_indices = acir.compact_witnesses([Witness(1), Witness(2)]);
+        assert_eq!(new_indices[&Witness(2)], Witness(2));
+        assert_eq!(new_indices[&square], Witness(3));
+        assert!(!new_indices.contains_key(&Witness(3)));
+
+        assert_eq!(acir.current_witness_index, 3);
+        assert_eq!(acir.return_witnesses, vec![Witness(3)]);
+        assert_eq!(acir.opcodes, vec![Opcode::Arithmetic(&product - Witness(3))]);
+    }
+
+    #[test]
+    fn splits_expressions_wider_than_the_expression_width() {
+        let mut acir = GeneratedAcir::default();
+        acir.capabilities.expression_width = Some(3);
+        acir.current_witness_index = 6;
+        acir.input_witnesses = (1..=5).map(Witness).collect();
+
+        // The expression is solved for witness 6
+        let mut expr = Expression::default();
+        for index in 1..=6 {
+            expr.push_addition_term(FieldElement::one(), Witness(index));
+        }
+        acir.assert_is_zero(expr);
+
+        // Three intermediate witnesses, each packing two terms, are the fewest which fit
+        assert_eq!(acir.opcodes.len(), 4);
+        for opcode in &acir.opcodes {
+            match opcode {
+                Opcode::Arithmetic(expr) => assert!(expr.linear_combinations.len() <= 3),
+                other => panic!("Expected an arithmetic opcode, found {other:?}"),
+            }
+        }
+        assert_solvable_in_order(&acir);
+    }
+
+    #[test]
+    fn splits_expressions_solved_for_any_witness() {
+        let mut acir = GeneratedAcir::default();
+        acir.capabilities.expression_width = Some(3);
+        let input = acir.next_witness_index();
+        acir.input_witnesses.push(input);
+
+        // The wide expression is solved for a witness older than the bits it also refers to
+        let unknown = acir.next_witness_index();
+        let bits = acir.radix_le_decompose(&input.into(), 2, 4, 1).unwrap();
+        let mut expr = Expression::from(unknown);
+        expr.push_addition_term(FieldElement::one(), input);
+        for bit in bits {
+            expr.push_addition_term(FieldElement::one(), bit);
+        }
+        acir.assert_is_zero(expr);
+
+        assert_solvable_in_order(&acir);
+        let solved_by = acir.opcodes.iter().filter(|opcode| {
+            let mut witnesses = Vec::new();
+            for_each_witness(&mut (*opcode).clone(), &mut |witness| witnesses.push(*witness));
+            witnesses.contains(&unknown)
+        });
+        assert_eq!(solved_by.count(), 1);
+    }
+
+    /// Asserts that each arithmetic opcode refers to at most one witness which is neither an input
+    /// nor referred to by an earlier opcode, so that it can be solved for that witness.
+    fn assert_solvable_in_order(acir: &GeneratedAcir) {
+        let mut known: HashSet<Witness> = acir.input_witnesses.iter().copied().collect();
+        for opcode in &acir.opcodes {
+            let mut witnesses = HashSet::new();
+            for_each_witness(&mut opcode.clone(), &mut |witness| {
+                witnesses.insert(*witness);
+            });
+            if let Opcode::Arithmetic(_) = opcode {
+                let unknown = witnesses.difference(&known).count();
+                assert!(unknown <= 1, "{opcode:?} has {unknown} unknown witnesses");
+            }
+            known.extend(witnesses);
+        }
+    }
 }
@@ -0,0 +1,56 @@
+use std::collections::HashSet;
+
+use acvm::acir::BlackBoxFunc;
+
+/// The features of the proving backend a circuit is generated for.
+///
+/// By default a backend is assumed to support arithmetic opcodes of any width and every black
+/// box function. Opcodes the backend does not support are lowered into ones it does where
+/// possible: `AND` and `XOR` are computed from the bits of their operands, and `RANGE` by
+/// decomposing the constrained witness into bits. Calls to any other unsupported black box
+/// function are reported as errors.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct BackendCapabilities {
+    /// The maximum number of witnesses each arithmetic opcode may refer to. Arithmetic opcodes
+    /// referring to more witnesses are split into several opcodes. None if the backend supports
+    /// arithmetic opcodes of any width.
+    pub expression_width: Option<usize>,
+
+    /// The black box functions the backend does not support
+    pub unsupported_black_boxes: HashSet<BlackBoxFunc>,
+}
+
+impl BackendCapabilities {
+    /// Returns the capabilities of a backend supporting the black box functions for which
+    /// `is_supported` holds, out of all those the compiler may generate.
+    pub fn new(
+        expression_width: Option<usize>,
+        is_supported: impl Fn(&BlackBoxFunc) -> bool,
+    ) -> Self {
+        let unsupported_black_boxes =
+            ALL_BLACK_BOXES.iter().filter(|func| !is_supported(func)).copied().collect();
+        Self { expression_width, unsupported_black_boxes }
+    }
+
+    /// True if the backend supports the given black box function
+    pub fn supports_black_box(&self, func: BlackBoxFunc) -> bool {
+        !self.unsupported_black_boxes.contains(&func)
+    }
+}
+
+/// Every black box function which ACIR generation may emit
+const ALL_BLACK_BOXES: [BlackBoxFunc; 13] = [
+    BlackBoxFunc::AND,
+    BlackBoxFunc::XOR,
+    BlackBoxFunc::RANGE,
+    BlackBoxFunc::SHA256,
+    BlackBoxFunc::Blake2s,
+    BlackBoxFunc::SchnorrVerify,
+    BlackBoxFunc::Pedersen,
+    BlackBoxFunc::HashToField128Security,
+    BlackBoxFunc::EcdsaSecp256k1,
+    BlackBoxFunc::EcdsaSecp256r1,
+    BlackBoxFunc::FixedBaseScalarMul,
+    BlackBoxFunc::Keccak256,
+    BlackBoxFunc::RecursiveAggregation,
+];
@@ -0,0 +1,129 @@
+//! Gate counts attribute each opcode of a circuit to the source location and the function of the
+//! original program which generated it, to show where the size of a circuit comes from.
+//!
+//! The location of each opcode is recorded by [GeneratedAcir] as it is generated. Since functions
+//! are inlined before ACIR generation, the function generating each location is instead recorded
+//! from the initial SSA, before any function is inlined into another.
+//!
+//! Gate counts are also checked against the opcode budget of a program, if it has one, so that an
+//! increase in the size of a circuit is reported along with the locations generating most opcodes.
+use std::collections::{BTreeMap, HashMap};
+
+use noirc_errors::{CustomDiagnostic as Diagnostic, DiagnosticKind, Location};
+
+use super::{acir_gen::GeneratedAcir, ssa_gen::Ssa};
+
+/// The number of opcodes generated by each source location and each function of a program.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct GateCounts {
+    /// The total number of opcodes in the circuit
+    pub total: usize,
+
+    /// The number of opcodes generated by each function of the program before inlining, by name.
+    /// Opcodes without a source location are not attributed to any function.
+    pub functions: BTreeMap<String, usize>,
+
+    /// The number of opcodes generated by each source location
+    pub locations: HashMap<Location, usize>,
+}
+
+impl GateCounts {
+    /// Counts the opcodes generated by each location and function, given the function which
+    /// generated each location.
+    pub(crate) fn new(acir: &GeneratedAcir, functions: &HashMap<Location, String>) -> Self {
+        let mut counts = GateCounts { total: acir.opcodes.len(), ..GateCounts::default() };
+        for location in acir.locations.values() {
+            *counts.locations.entry(*location).or_default() += 1;
+            if let Some(function) = functions.get(location) {
+                *counts.functions.entry(function.clone()).or_default() += 1;
+            }
+        }
+        counts
+    }
+
+    /// Returns the source locations generating the most opcodes if the circuit has more opcodes
+    /// than the given budget.
+    pub fn exceeded_budget(&self, budget: usize) -> Option<OpcodeBudgetExceeded> {
+        if self.total <= budget {
+            return None;
+        }
+
+        let mut top_locations: Vec<_> =
+            self.locations.iter().map(|(location, opcodes)| (*location, *opcodes)).collect();
+        // Ties are broken by position, so that the locations reported are deterministic
+        top_locations.sort_by_key(|(location, opcodes)| {
+            (std::cmp::Reverse(*opcodes), location.file.as_usize(), location.span.start())
+        });
+        top_locations.truncate(OpcodeBudgetExceeded::MAX_REPORTED_LOCATIONS);
+
+        Some(OpcodeBudgetExceeded { budget, total: self.total, top_locations })
+    }
+}
+
+/// A circuit with more opcodes than its budget allows.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OpcodeBudgetExceeded {
+    /// The maximum number of opcodes the circuit was expected to have
+    pub budget: usize,
+
+    /// The number of opcodes in the circuit
+    pub total: usize,
+
+    /// The source locations generating the most opcodes, along with the number of opcodes each
+    /// generates, from the most opcodes to the fewest
+    pub top_locations: Vec<(Location, usize)>,
+}
+
+impl OpcodeBudgetExceeded {
+    /// The number of source locations reported when the budget is exceeded
+    pub const MAX_REPORTED_LOCATIONS: usize = 5;
+
+    /// The location generating the most opcodes, if any opcode has a location
+    pub fn location(&self) -> Option<Location> {
+        self.top_locations.first().map(|(location, _)| *location)
+    }
+
+    /// Creates a diagnostic pointing at the locations generating the most opcodes. Only the
+    /// locations within the same file as the first one can be labelled, the others are noted.
+    pub fn diagnostic(&self, is_error: bool) -> Diagnostic {
+        let mut diagnostic = Diagnostic::from_message(&self.to_string());
+        if !is_error {
+            diagnostic.kind = DiagnosticKind::Warning;
+        }
+
+        let file = self.location().map(|location| location.file);
+        for (location, opcodes) in &self.top_locations {
+            if Some(location.file) == file {
+                diagnostic
+                    .add_secondary(format!("{opcodes} opcodes generated here"), location.span);
+            } else {
+                diagnostic.add_note(format!("{opcodes} opcodes are generated in another file"));
+            }
+        }
+        diagnostic
+    }
+}
+
+impl std::fmt::Display for OpcodeBudgetExceeded {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Circuit has {} opcodes, exceeding its budget of {}", self.total, self.budget)
+    }
+}
+
+impl Ssa {
+    /// Returns the name of the function which contains each source location within the program.
+    /// This must be called before functions are inlined.
+    pub(crate) fn function_locations(&self) -> HashMap<Location, String> {
+        let mut locations = HashMap::new();
+        for function in self.functions.values() {
+            for block in function.reachable_blocks() {
+                for instruction in function.dfg[block].instructions() {
+                    if let Some(location) = function.dfg.get_location(instruction) {
+                        locations.entry(location).or_insert_with(|| function.name().to_owned());
+                    }
+                }
+            }
+        }
+        locations
+    }
+}
@@ -0,0 +1,696 @@
+//! The interpreter executes the SSA IR directly with concrete inputs.
+//!
+//! This is intended for testing optimization passes, by checking that a program returns the
+//! same results before and after a pass, and for evaluating programs whose inputs are all known
+//! at compile time.
+//!
+//! Values are interpreted with the semantics of the generated programs: integer arithmetic wraps
+//! around at the bit size of its type, constraints which do not hold are errors, and calls to
+//! functions made while side effects are disabled are skipped, returning zeroed values.
+//! Arrays are flattened, so an array of tuples holds each element of each tuple in turn.
+use std::collections::HashMap;
+
+use acvm::FieldElement;
+use num_bigint::BigUint;
+use thiserror::Error;
+
+use super::{
+    ir::{
+        basic_block::BasicBlockId,
+        function::{Function, FunctionId},
+        instruction::{
+            radix_limbs, Binary, BinaryOp, Endian, Instruction, InstructionId, Intrinsic,
+            TerminatorInstruction,
+        },
+        types::{NumericType, Type},
+        value::{Value, ValueId},
+    },
+    ssa_gen::Ssa,
+};
+
+/// The maximum number of instructions executed before the interpreter gives up, in case the
+/// program never terminates.
+const STEP_LIMIT: usize = 1_000_000;
+
+/// A value produced while interpreting a program
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum InterpreterValue {
+    Numeric(FieldElement, NumericType),
+    Array(Vec<InterpreterValue>),
+    /// An index into the interpreter's memory
+    Reference(usize),
+    Function(FunctionId),
+    Intrinsic(Intrinsic),
+    ForeignFunction(String),
+}
+
+impl InterpreterValue {
+    /// Creates a field element value
+    pub(crate) fn field(value: impl Into<FieldElement>) -> Self {
+        InterpreterValue::Numeric(value.into(), NumericType::NativeField)
+    }
+
+    /// Creates an unsigned integer value with the given bit size
+    pub(crate) fn unsigned(value: u128, bit_size: u32) -> Self {
+        InterpreterValue::Numeric(value.into(), NumericType::Unsigned { bit_size })
+    }
+
+    /// Creates a boolean value
+    pub(crate) fn bool(value: bool) -> Self {
+        InterpreterValue::unsigned(value as u128, 1)
+    }
+
+    /// The zeroed value of the given type, as returned by calls made while side effects
+    /// are disabled.
+    fn zeroed(typ: &Type) -> Self {
+        match typ {
+            Type::Numeric(numeric_type) => {
+                InterpreterValue::Numeric(FieldElement::zero(), *numeric_type)
+            }
+            Type::Array(element_types, length) => {
+                let elements = element_types.iter().map(InterpreterValue::zeroed);
+                let elements = elements.collect::<Vec<_>>();
+                InterpreterValue::Array(elements.repeat(*length))
+            }
+            Type::Slice(_) => InterpreterValue::Array(Vec::new()),
+            Type::Reference => InterpreterValue::Reference(usize::MAX),
+            Type::Function => {
+                InterpreterValue::Numeric(FieldElement::zero(), NumericType::NativeField)
+            }
+        }
+    }
+}
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub(crate) enum InterpreterError {
+    #[error("Failed constraint in function {function}")]
+    FailedConstraint { function: FunctionId },
+    #[error("Division by zero in function {function}")]
+    DivisionByZero { function: FunctionId },
+    #[error("Index {index} is out of bounds for an array of length {length}")]
+    IndexOutOfBounds { index: usize, length: usize },
+    #[error("Loaded from a reference which has not been stored to")]
+    UninitializedLoad,
+    #[error("Value {0} is used before it is defined")]
+    UndefinedValue(ValueId),
+    #[error("Expected {expected} in function {function}, found {found:?}")]
+    UnexpectedValue { expected: &'static str, found: InterpreterValue, function: FunctionId },
+    #[error("Calls to {0} cannot be interpreted")]
+    UnsupportedCall(String),
+    #[error("Exceeded the maximum number of interpreted instructions")]
+    StepLimitExceeded,
+}
+
+impl Ssa {
+    /// Executes the main function of this program with the given arguments, returning the
+    /// values it returns.
+    pub(crate) fn interpret(
+        &self,
+        arguments: Vec<InterpreterValue>,
+    ) -> Result<Vec<InterpreterValue>, InterpreterError> {
+        Interpreter::new(self).call_function(self.main_id, arguments)
+    }
+}
+
+/// Executes the functions of a program, sharing a single memory between them.
+struct Interpreter<'ssa> {
+    ssa: &'ssa Ssa,
+    memory: Vec<Option<InterpreterValue>>,
+    steps: usize,
+}
+
+/// The values defined so far within a single call to a function
+struct CallFrame<'f> {
+    function: &'f Function,
+    values: HashMap<ValueId, InterpreterValue>,
+    side_effects_enabled: bool,
+}
+
+impl<'ssa> Interpreter<'ssa> {
+    fn new(ssa: &'ssa Ssa) -> Self {
+        Self { ssa, memory: Vec::new(), steps: 0 }
+    }
+
+    fn call_function(
+        &mut self,
+        id: FunctionId,
+        arguments: Vec<InterpreterValue>,
+    ) -> Result<Vec<InterpreterValue>, InterpreterError> {
+        let ssa = self.ssa;
+        let function = &ssa.functions[&id];
+        let mut frame = CallFrame { function, values: HashMap::new(), side_effects_enabled: true };
+
+        let mut block = function.entry_block();
+        frame.bind_parameters(block, arguments);
+
+        loop {
+            for instruction in function.dfg[block].instructions() {
+                self.steps += 1;
+                if self.steps > STEP_LIMIT {
+                    return Err(InterpreterError::StepLimitExceeded);
+                }
+                self.interpret_instruction(&mut frame, *instruction)?;
+            }
+
+            match function.dfg[block].unwrap_terminator() {
+                TerminatorInstruction::Jmp { destination, arguments } => {
+                    let arguments = frame.lookup_all(arguments)?;
+                    frame.bind_parameters(*destination, arguments);
+                    block = *destination;
+                }
+                TerminatorInstruction::JmpIf { condition, then_destination, else_destination } => {
+                    let condition = frame.numeric(*condition)?.0;
+                    block = if condition.is_zero() { *else_destination } else { *then_destination };
+                }
+                TerminatorInstruction::Return { return_values } => {
+                    return frame.lookup_all(return_values);
+                }
+            }
+        }
+    }
+
+    fn interpret_instruction(
+        &mut self,
+        frame: &mut CallFrame<'ssa>,
+        instruction_id: InstructionId,
+    ) -> Result<(), InterpreterError> {
+        let current_function: &'ssa Function = frame.function;
+        let dfg = &current_function.dfg;
+        let function = current_function.id();
+
+        let results = match &dfg[instruction_id] {
+            Instruction::Binary(binary) => vec![frame.binary(binary)?],
+            Instruction::Cast(value, typ) => {
+                let (value, _) = frame.numeric(*value)?;
+                match typ {
+                    Type::Numeric(NumericType::NativeField) => vec![InterpreterValue::field(value)],
+                    Type::Numeric(
+                        numeric_type @ (NumericType::Unsigned { bit_size }
+                        | NumericType::Signed { bit_size }),
+                    ) => vec![InterpreterValue::Numeric(truncate(value, *bit_size), *numeric_type)],
+                    _ => unreachable!("Values may only be cast to numeric types"),
+                }
+            }
+            Instruction::Not(value) => {
+                let (value, typ) = frame.numeric(*value)?;
+                let bit_size = integer_bit_size(typ, function)?;
+                let not = wrap(!value.to_u128(), bit_size);
+                vec![InterpreterValue::Numeric(not.into(), typ)]
+            }
+            Instruction::Truncate { value, bit_size, .. } => {
+                let (value, typ) = frame.numeric(*value)?;
+                vec![InterpreterValue::Numeric(truncate(value, *bit_size), typ)]
+            }
+            Instruction::Constrain(value, _) => {
+                if frame.numeric(*value)?.0.is_zero() {
+                    return Err(InterpreterError::FailedConstraint { function });
+                }
+                Vec::new()
+            }
+            Instruction::Call { func, arguments } => {
+                let arguments = frame.lookup_all(arguments)?;
+                let result_types = dfg.instruction_results(instruction_id);
+                let result_types = result_types.iter().map(|result| dfg.type_of_value(*result));
+
+                match frame.lookup(*func)? {
+                    InterpreterValue::Function(_) if !frame.side_effects_enabled => {
+                        result_types.map(|typ| InterpreterValue::zeroed(&typ)).collect()
+                    }
+                    InterpreterValue::Function(id) => self.call_function(id, arguments)?,
+                    InterpreterValue::Intrinsic(Intrinsic::AssertSortedPermutation)
+                        if !frame.side_effects_enabled =>
+                    {
+                        Vec::new()
+                    }
+                    InterpreterValue::Intrinsic(intrinsic) => {
+                        call_intrinsic(intrinsic, arguments, function)?
+                    }
+                    InterpreterValue::ForeignFunction(name) => {
+                        return Err(InterpreterError::UnsupportedCall(name))
+                    }
+                    found => {
+                        let expected = "a function";
+                        return Err(InterpreterError::UnexpectedValue {
+                            expected,
+                            found,
+                            function,
+                        });
+                    }
+                }
+            }
+            Instruction::Allocate => {
+                self.memory.push(None);
+                vec![InterpreterValue::Reference(self.memory.len() - 1)]
+            }
+            Instruction::Load { address } => {
+                let address = frame.reference(*address)?;
+                let value = self.memory.get(address).cloned().flatten();
+                vec![value.ok_or(InterpreterError::UninitializedLoad)?]
+            }
+            Instruction::Store { address, value } => {
+                let address = frame.reference(*address)?;
+                let value = frame.lookup(*value)?;
+                if let Some(slot) = self.memory.get_mut(address) {
+                    *slot = Some(value);
+                }
+                Vec::new()
+            }
+            Instruction::EnableSideEffects { condition } => {
+                frame.side_effects_enabled = !frame.numeric(*condition)?.0.is_zero();
+                Vec::new()
+            }
+            Instruction::ArrayGet { array, index } => {
+                let array = frame.array(*array)?;
+                let index = frame.index(*index, array.len())?;
+                vec![array[index].clone()]
+            }
+            Instruction::ArraySet { array, index, value } => {
+                let mut array = frame.array(*array)?;
+                let index = frame.index(*index, array.len())?;
+                array[index] = frame.lookup(*value)?;
+                vec![InterpreterValue::Array(array)]
+            }
+        };
+
+        // Intrinsics returning nothing may still have a single unit result (see #1438)
+        let result_ids = dfg.instruction_results(instruction_id);
+        for (result, value) in result_ids.iter().zip(results) {
+            frame.values.insert(*result, value);
+        }
+        Ok(())
+    }
+}
+
+impl<'f> CallFrame<'f> {
+    fn bind_parameters(&mut self, block: BasicBlockId, arguments: Vec<InterpreterValue>) {
+        let parameters = self.function.dfg.block_parameters(block);
+        assert_eq!(parameters.len(), arguments.len(), "ICE: Expected an argument per parameter");
+
+        for (parameter, argument) in parameters.iter().zip(arguments) {
+            self.values.insert(*parameter, argument);
+        }
+    }
+
+    fn lookup(&self, value: ValueId) -> Result<InterpreterValue, InterpreterError> {
+        let dfg = &self.function.dfg;
+        let value = dfg.resolve(value);
+
+        Ok(match &dfg[value] {
+            Value::NumericConstant { constant, typ } => {
+                InterpreterValue::Numeric(*constant, numeric_type(typ))
+            }
+            Value::Array { array, .. } => {
+                let elements = array.iter().map(|element| self.lookup(*element));
+                InterpreterValue::Array(elements.collect::<Result<_, _>>()?)
+            }
+            Value::Function(id) => InterpreterValue::Function(*id),
+            Value::Intrinsic(intrinsic) => InterpreterValue::Intrinsic(*intrinsic),
+            Value::ForeignFunction(name) => InterpreterValue::ForeignFunction(name.clone()),
+            Value::Instruction { .. } | Value::Param { .. } => {
+                self.values.get(&value).cloned().ok_or(InterpreterError::UndefinedValue(value))?
+            }
+        })
+    }
+
+    fn lookup_all(&self, values: &[ValueId]) -> Result<Vec<InterpreterValue>, InterpreterError> {
+        values.iter().map(|value| self.lookup(*value)).collect()
+    }
+
+    fn numeric(&self, value: ValueId) -> Result<(FieldElement, NumericType), InterpreterError> {
+        match self.lookup(value)? {
+            InterpreterValue::Numeric(value, typ) => Ok((value, typ)),
+            found => Err(self.unexpected("a numeric value", found)),
+        }
+    }
+
+    fn array(&self, value: ValueId) -> Result<Vec<InterpreterValue>, InterpreterError> {
+        match self.lookup(value)? {
+            InterpreterValue::Array(elements) => Ok(elements),
+            found => Err(self.unexpected("an array", found)),
+        }
+    }
+
+    fn reference(&self, value: ValueId) -> Result<usize, InterpreterError> {
+        match self.lookup(value)? {
+            InterpreterValue::Reference(address) => Ok(address),
+            found => Err(self.unexpected("a reference", found)),
+        }
+    }
+
+    fn index(&self, value: ValueId, length: usize) -> Result<usize, InterpreterError> {
+        let index = self.numeric(value)?.0.to_u128() as usize;
+        if index < length {
+            Ok(index)
+        } else {
+            Err(InterpreterError::IndexOutOfBounds { index, length })
+        }
+    }
+
+    fn unexpected(&self, expected: &'static str, found: InterpreterValue) -> InterpreterError {
+        InterpreterError::UnexpectedValue { expected, found, function: self.function.id() }
+    }
+
+    fn binary(&self, binary: &Binary) -> Result<InterpreterValue, InterpreterError> {
+        let (lhs, typ) = self.numeric(binary.lhs)?;
+        let (rhs, _) = self.numeric(binary.rhs)?;
+        let function = self.function.id();
+
+        let result = match typ {
+            NumericType::NativeField => match binary.operator {
+                BinaryOp::Add => lhs + rhs,
+                BinaryOp::Sub => lhs - rhs,
+                BinaryOp::Mul => lhs * rhs,
+                BinaryOp::Div if rhs.is_zero() => {
+                    return Err(InterpreterError::DivisionByZero { function })
+                }
+                BinaryOp::Div => lhs / rhs,
+                BinaryOp::Eq => return Ok(InterpreterValue::bool(lhs == rhs)),
+                BinaryOp::Lt => return Ok(InterpreterValue::bool(lhs < rhs)),
+                _ => {
+                    let expected = "an integer";
+                    let found = InterpreterValue::Numeric(lhs, typ);
+                    return Err(InterpreterError::UnexpectedValue { expected, found, function });
+                }
+            },
+            NumericType::Unsigned { bit_size } | NumericType::Signed { bit_size } => {
+                let signed = matches!(typ, NumericType::Signed { .. });
+                match integer_binary(
+                    binary.operator,
+                    lhs.to_u128(),
+                    rhs.to_u128(),
+                    bit_size,
+                    signed,
+                ) {
+                    Some(IntegerResult::Integer(result)) => result.into(),
+                    Some(IntegerResult::Bool(result)) => return Ok(InterpreterValue::bool(result)),
+                    None => return Err(InterpreterError::DivisionByZero { function }),
+                }
+            }
+        };
+        Ok(InterpreterValue::Numeric(result, typ))
+    }
+}
+
+/// The result of a binary operation on integers
+enum IntegerResult {
+    Integer(u128),
+    Bool(bool),
+}
+
+/// Evaluates a binary operation on two integers of the given bit size, returning None on a
+/// division by zero.
+fn integer_binary(
+    operator: BinaryOp,
+    lhs: u128,
+    rhs: u128,
+    bit_size: u32,
+    signed: bool,
+) -> Option<IntegerResult> {
+    let to_signed = |value: u128| {
+        if bit_size < 128 && value >= 1 << (bit_size - 1) {
+            value as i128 - (1 << bit_size)
+        } else {
+            value as i128
+        }
+    };
+
+    let result = match operator {
+        BinaryOp::Add => lhs.wrapping_add(rhs),
+        BinaryOp::Sub => lhs.wrapping_sub(rhs),
+        BinaryOp::Mul => lhs.wrapping_mul(rhs),
+        BinaryOp::Div | BinaryOp::Mod if rhs == 0 => return None,
+        BinaryOp::Div if signed => to_signed(lhs).wrapping_div(to_signed(rhs)) as u128,
+        BinaryOp::Mod if signed => to_signed(lhs).wrapping_rem(to_signed(rhs)) as u128,
+        BinaryOp::Div => lhs / rhs,
+        BinaryOp::Mod => lhs % rhs,
+        BinaryOp::And => lhs & rhs,
+        BinaryOp::Or => lhs | rhs,
+        BinaryOp::Xor => lhs ^ rhs,
+        BinaryOp::Shl if rhs >= bit_size as u128 => 0,
+        BinaryOp::Shl => lhs << rhs,
+        BinaryOp::Shr if rhs >= bit_size as u128 => 0,
+        BinaryOp::Shr => lhs >> rhs,
+        BinaryOp::Eq => return Some(IntegerResult::Bool(lhs == rhs)),
+        BinaryOp::Lt if signed => {
+            return Some(IntegerResult::Bool(to_signed(lhs) < to_signed(rhs)))
+        }
+        BinaryOp::Lt => return Some(IntegerResult::Bool(lhs < rhs)),
+    };
+    Some(IntegerResult::Integer(wrap(result, bit_size)))
+}
+
+fn call_intrinsic(
+    intrinsic: Intrinsic,
+    arguments: Vec<InterpreterValue>,
+    function: FunctionId,
+) -> Result<Vec<InterpreterValue>, InterpreterError> {
+    let unexpected = |expected, found: &InterpreterValue| InterpreterError::UnexpectedValue {
+        expected,
+        found: found.clone(),
+        function,
+    };
+    let array = |index: usize| match &arguments[index] {
+        InterpreterValue::Array(elements) => Ok(elements.clone()),
+        found => Err(unexpected("an array", found)),
+    };
+    let integer = |index: usize| match &arguments[index] {
+        InterpreterValue::Numeric(value, _) => Ok(value.to_u128()),
+        found => Err(unexpected("a numeric value", found)),
+    };
+    let pop = |element: Option<InterpreterValue>| {
+        element.ok_or(InterpreterError::IndexOutOfBounds { index: 0, length: 0 })
+    };
+
+    Ok(match intrinsic {
+        Intrinsic::ArrayLen => vec![InterpreterValue::field(array(0)?.len() as u128)],
+        Intrinsic::SlicePushBack => {
+            let mut slice = array(0)?;
+            slice.push(arguments[1].clone());
+            vec![InterpreterValue::Array(slice)]
+        }
+        Intrinsic::SlicePushFront => {
+            let mut slice = array(0)?;
+            slice.insert(0, arguments[1].clone());
+            vec![InterpreterValue::Array(slice)]
+        }
+        Intrinsic::SlicePopBack => {
+            let mut slice = array(0)?;
+            let element = pop(slice.pop())?;
+            vec![InterpreterValue::Array(slice), element]
+        }
+        Intrinsic::SlicePopFront => {
+            let mut slice = array(0)?;
+            let element = pop((!slice.is_empty()).then(|| slice.remove(0)))?;
+            vec![element, InterpreterValue::Array(slice)]
+        }
+        Intrinsic::SliceInsert => {
+            let mut slice = array(0)?;
+            let index = integer(1)? as usize;
+            if index > slice.len() {
+                let length = slice.len();
+                return Err(InterpreterError::IndexOutOfBounds { index, length });
+            }
+            slice.insert(index, arguments[2].clone());
+            vec![InterpreterValue::Array(slice)]
+        }
+        Intrinsic::SliceRemove => {
+            let mut slice = array(0)?;
+            let index = integer(1)? as usize;
+            if index >= slice.len() {
+                let length = slice.len();
+                return Err(InterpreterError::IndexOutOfBounds { index, length });
+            }
+            let element = slice.remove(index);
+            vec![InterpreterValue::Array(slice), element]
+        }
+        Intrinsic::Sort => {
+            let mut elements = array(0)?;
+            sort(&mut elements);
+            vec![InterpreterValue::Array(elements)]
+        }
+        Intrinsic::AssertSortedPermutation => {
+            let mut elements = array(0)?;
+            sort(&mut elements);
+            if elements != array(1)? {
+                return Err(InterpreterError::FailedConstraint { function });
+            }
+            Vec::new()
+        }
+        Intrinsic::ToBits(endian) => {
+            vec![to_radix(&arguments[0], endian, 2, integer(1)? as u32, function)?]
+        }
+        Intrinsic::ToRadix(endian) => {
+            let radix = integer(1)? as u32;
+            vec![to_radix(&arguments[0], endian, radix, integer(2)? as u32, function)?]
+        }
+        Intrinsic::Println => Vec::new(),
+        Intrinsic::BlackBox(_) | Intrinsic::BigIntMulAddMod => {
+            return Err(InterpreterError::UnsupportedCall(intrinsic.to_string()))
+        }
+    })
+}
+
+/// Sorts numeric values in ascending order.
+fn sort(elements: &mut [InterpreterValue]) {
+    elements.sort_by(|a, b| match (a, b) {
+        (InterpreterValue::Numeric(a, _), InterpreterValue::Numeric(b, _)) => {
+            a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal)
+        }
+        _ => std::cmp::Ordering::Equal,
+    });
+}
+
+fn to_radix(
+    value: &InterpreterValue,
+    endian: Endian,
+    radix: u32,
+    limb_count: u32,
+    function: FunctionId,
+) -> Result<InterpreterValue, InterpreterError> {
+    let field = match value {
+        InterpreterValue::Numeric(field, _) => *field,
+        found => {
+            let found = found.clone();
+            let expected = "a numeric value";
+            return Err(InterpreterError::UnexpectedValue { expected, found, function });
+        }
+    };
+
+    let bit_size = u32::BITS - (radix - 1).leading_zeros();
+    let limbs = radix_limbs(endian, field, radix, limb_count);
+    let limbs = limbs.into_iter().map(|limb| InterpreterValue::unsigned(limb.to_u128(), bit_size));
+    Ok(InterpreterValue::Array(limbs.collect()))
+}
+
+fn numeric_type(typ: &Type) -> NumericType {
+    match typ {
+        Type::Numeric(numeric_type) => *numeric_type,
+        _ => unreachable!("Numeric constants should have a numeric type"),
+    }
+}
+
+fn integer_bit_size(typ: NumericType, function: FunctionId) -> Result<u32, InterpreterError> {
+    match typ {
+        NumericType::Unsigned { bit_size } | NumericType::Signed { bit_size } => Ok(bit_size),
+        NumericType::NativeField => {
+            let expected = "an integer";
+            let found = InterpreterValue::Numeric(FieldElement::zero(), typ);
+            Err(InterpreterError::UnexpectedValue { expected, found, function })
+        }
+    }
+}
+
+/// Wraps the given integer around to the given bit size
+fn wrap(value: u128, bit_size: u32) -> u128 {
+    if bit_size >= 128 {
+        value
+    } else {
+        value % (1 << bit_size)
+    }
+}
+
+/// Truncates the given field element to its lowest `bit_size` bits
+fn truncate(value: FieldElement, bit_size: u32) -> FieldElement {
+    let integer_modulus = BigUint::from(2u128).pow(bit_size);
+    let value = BigUint::from_bytes_be(&value.to_be_bytes()) % integer_modulus;
+    FieldElement::from_be_bytes_reduce(&value.to_bytes_be())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use crate::ssa_refactor::ssa_gen::Ssa;
+
+    use super::{InterpreterError, InterpreterValue};
+
+    #[test]
+    fn interprets_loops_and_memory() {
+        let src = "
+            acir fn main f0 {
+              b0(v0: u8):
+                v1 = allocate
+                store u8 0 at v1
+                jmp b1(u32 0)
+              b1(v2: u32):
+                v3 = lt v2, u32 4
+                jmpif v3 then: b2, else: b3
+              b2():
+                v4 = load v1 -> u8
+                v5 = add v4, v0
+                store v5 at v1
+                v6 = add v2, u32 1
+                jmp b1(v6)
+              b3():
+                v7 = load v1 -> u8
+                v8 = call f1(v7) -> Field
+                return v7, v8
+            }
+            brillig fn double f1 {
+              b0(v0: u8):
+                v1 = cast v0 as Field
+                v2 = mul v1, Field 2
+                return v2
+            }
+        ";
+        let ssa = Ssa::from_str(src).unwrap();
+
+        // The sum wraps around at the bit size of u8
+        let results = ssa.interpret(vec![InterpreterValue::unsigned(100, 8)]);
+        let expected = vec![InterpreterValue::unsigned(144, 8), InterpreterValue::field(288u128)];
+        assert_eq!(results, Ok(expected));
+    }
+
+    #[test]
+    fn reports_failed_constraints() {
+        let src = "
+            acir fn main f0 {
+              b0(v0: Field, v1: [Field; 2]):
+                v2 = array_get v1, index u32 1 -> Field
+                v3 = eq v0, v2
+                constrain v3
+                return
+            }
+        ";
+        let ssa = Ssa::from_str(src).unwrap();
+        let array = InterpreterValue::Array(vec![
+            InterpreterValue::field(1u128),
+            InterpreterValue::field(2u128),
+        ]);
+
+        let results = ssa.interpret(vec![InterpreterValue::field(2u128), array.clone()]);
+        assert_eq!(results, Ok(Vec::new()));
+
+        let results = ssa.interpret(vec![InterpreterValue::field(1u128), array]);
+        let function = ssa.main_id;
+        assert_eq!(results, Err(InterpreterError::FailedConstraint { function }));
+    }
+
+    #[test]
+    fn optimizations_preserve_results() {
+        let src = "
+            acir fn main f0 {
+              b0(v0: u1, v1: Field):
+                v2 = allocate
+                store v1 at v2
+                jmpif v0 then: b1, else: b2
+              b1():
+                v3 = load v2 -> Field
+                v4 = mul v3, Field 3
+                store v4 at v2
+                jmp b2()
+              b2():
+                v5 = load v2 -> Field
+                return v5
+            }
+        ";
+        let ssa = Ssa::from_str(src).unwrap();
+        let optimized = Ssa::from_str(src).unwrap().flatten_cfg().mem2reg().fold_constants();
+
+        for condition in [false, true] {
+            let arguments = vec![InterpreterValue::bool(condition), InterpreterValue::field(5u128)];
+            let results = ssa.interpret(arguments.clone()).unwrap();
+            assert_eq!(optimized.interpret(arguments), Ok(results));
+        }
+    }
+}
@@ -1,12 +1,22 @@
+pub(crate) mod array_escape;
 pub(crate) mod basic_block;
+pub(crate) mod call_graph;
 pub(crate) mod cfg;
 pub(crate) mod dfg;
 pub(crate) mod dom;
+pub(crate) mod dot;
 pub(crate) mod function;
 pub(crate) mod function_inserter;
 pub(crate) mod instruction;
+pub(crate) mod liveness;
+pub(crate) mod loops;
 pub(crate) mod map;
+pub(crate) mod parser;
 pub(crate) mod post_order;
+pub(crate) mod predicated_constraints;
 pub(crate) mod printer;
+pub(crate) mod range_analysis;
+pub(crate) mod serialization;
 pub(crate) mod types;
 pub(crate) mod value;
+pub(crate) mod verifier;
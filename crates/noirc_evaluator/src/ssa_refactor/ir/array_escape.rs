@@ -0,0 +1,217 @@
+//! Array escape analysis for a single function.
+//!
+//! An `array_set` semantically creates a new array, leaving its source array unchanged. Both
+//! ACIR and brillig generation implement this by copying the source array before writing to the
+//! copy. The copy is unnecessary if the source array is never used again, in which case the write
+//! can update the source array in place.
+//!
+//! This analysis finds each `array_set` whose source array is the result of an earlier
+//! `array_set` within the same block, and is otherwise only read by `array_get`s preceding the
+//! write. Restricting the source to the result of an `array_set` guarantees that it is not shared
+//! with any other value: arrays passed as parameters, loaded from references, or returned from
+//! calls may be aliased elsewhere, and constant arrays may be reused each time a loop repeats.
+//! Any other use of the source array, such as passing it to a call, storing it, including it in
+//! another array, or passing it to another block, is considered to let the array escape.
+use std::collections::{HashMap, HashSet};
+
+use super::{
+    basic_block::BasicBlockId,
+    dfg::DataFlowGraph,
+    function::Function,
+    instruction::{Instruction, InstructionId},
+    value::{Value, ValueId},
+};
+
+/// A single use of an array produced by an `array_set`
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum ArrayUse {
+    /// The array is read from by an `array_get`
+    Read,
+    /// The array is the source array of the given `array_set`
+    Write(InstructionId),
+    /// The array is used in any other way, after which it may be aliased
+    Escape,
+}
+
+/// The `array_set` instructions of a function which may update their source array in place.
+pub(crate) struct ArrayEscapeAnalysis {
+    in_place_updates: HashSet<InstructionId>,
+}
+
+impl ArrayEscapeAnalysis {
+    pub(crate) fn with_function(function: &Function) -> Self {
+        let dfg = &function.dfg;
+        let blocks = function.reachable_blocks();
+
+        // The block defining each array produced by an `array_set`
+        let mut defining_blocks = HashMap::new();
+        for block in &blocks {
+            for instruction in dfg[*block].instructions() {
+                if let Instruction::ArraySet { .. } = &dfg[*instruction] {
+                    defining_blocks.insert(dfg.instruction_results(*instruction)[0], *block);
+                }
+            }
+        }
+
+        let mut context = AnalysisContext { dfg, defining_blocks, uses: HashMap::new() };
+        for block in &blocks {
+            context.collect_uses(*block);
+        }
+
+        let in_place_updates = context
+            .defining_blocks
+            .keys()
+            .filter_map(|array| context.in_place_update(*array))
+            .collect();
+
+        Self { in_place_updates }
+    }
+
+    /// Returns true if the given `array_set` may update its source array in place rather than
+    /// writing to a copy of it.
+    pub(crate) fn is_in_place_update(&self, instruction: InstructionId) -> bool {
+        self.in_place_updates.contains(&instruction)
+    }
+}
+
+struct AnalysisContext<'f> {
+    dfg: &'f DataFlowGraph,
+    defining_blocks: HashMap<ValueId, BasicBlockId>,
+    /// Every use of each array within `defining_blocks`, in order, along with the block using it
+    uses: HashMap<ValueId, Vec<(BasicBlockId, ArrayUse)>>,
+}
+
+impl<'f> AnalysisContext<'f> {
+    fn collect_uses(&mut self, block: BasicBlockId) {
+        let dfg = self.dfg;
+
+        for instruction_id in dfg[block].instructions() {
+            match &dfg[*instruction_id] {
+                Instruction::ArrayGet { array, index } => {
+                    self.record_use(block, *array, ArrayUse::Read);
+                    self.record_use(block, *index, ArrayUse::Escape);
+                }
+                Instruction::ArraySet { array, index, value } => {
+                    self.record_use(block, *array, ArrayUse::Write(*instruction_id));
+                    self.record_use(block, *index, ArrayUse::Escape);
+                    self.record_use(block, *value, ArrayUse::Escape);
+                }
+                instruction => {
+                    instruction.for_each_value(|value| {
+                        self.record_use(block, value, ArrayUse::Escape);
+                    });
+                }
+            }
+        }
+
+        if let Some(terminator) = dfg[block].terminator() {
+            terminator.for_each_value(|value| self.record_use(block, value, ArrayUse::Escape));
+        }
+    }
+
+    fn record_use(&mut self, block: BasicBlockId, value: ValueId, array_use: ArrayUse) {
+        let dfg = self.dfg;
+        let value = dfg.resolve(value);
+        match &dfg[value] {
+            // Each element of a constant array is used by it. The elements may be aliased by the
+            // constant array, regardless of how the constant array itself is used.
+            Value::Array { array, .. } => {
+                for element in array.iter() {
+                    self.record_use(block, *element, ArrayUse::Escape);
+                }
+            }
+            _ => {
+                if self.defining_blocks.contains_key(&value) {
+                    self.uses.entry(value).or_default().push((block, array_use));
+                }
+            }
+        }
+    }
+
+    /// Returns the `array_set` which may update the given array in place, if any.
+    ///
+    /// This is the case if the `array_set` is the array's last use, each earlier use is an
+    /// `array_get`, and every use is within the block defining the array.
+    fn in_place_update(&self, array: ValueId) -> Option<InstructionId> {
+        let defining_block = self.defining_blocks[&array];
+        let uses = self.uses.get(&array)?;
+        let (last_use, earlier_uses) = uses.split_last()?;
+
+        let is_read = |(block, array_use): &(BasicBlockId, ArrayUse)| {
+            *block == defining_block && *array_use == ArrayUse::Read
+        };
+
+        match last_use {
+            (block, ArrayUse::Write(instruction))
+                if *block == defining_block && earlier_uses.iter().all(is_read) =>
+            {
+                Some(*instruction)
+            }
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use crate::ssa_refactor::{
+        ir::instruction::{Instruction, InstructionId},
+        ssa_gen::Ssa,
+    };
+
+    use super::ArrayEscapeAnalysis;
+
+    /// Returns whether each `array_set` within the entry block of the given program's main
+    /// function may update its source array in place.
+    fn in_place_updates(src: &str) -> Vec<bool> {
+        let ssa = Ssa::from_str(src).unwrap();
+        let main = ssa.main();
+        let analysis = ArrayEscapeAnalysis::with_function(main);
+
+        let instructions = main.dfg[main.entry_block()].instructions();
+        let is_array_set = |instruction: &&InstructionId| {
+            matches!(main.dfg[**instruction], Instruction::ArraySet { .. })
+        };
+        let array_sets = instructions.iter().filter(is_array_set);
+        array_sets.map(|instruction| analysis.is_in_place_update(*instruction)).collect()
+    }
+
+    #[test]
+    fn updates_arrays_without_later_uses_in_place() {
+        let src = "
+            acir fn main f0 {
+              b0(v0: [Field; 4], v1: u32):
+                v2 = array_set v0, index v1, value Field 1
+                v3 = array_get v2, index u32 0 -> Field
+                v4 = array_set v2, index u32 1, value v3
+                v5 = array_set v4, index u32 2, value Field 2
+                v6 = array_get v4, index u32 3 -> Field
+                return v5, v6
+            }
+        ";
+        // The parameter v0 may be aliased, and v4 is read after it is written to
+        assert_eq!(in_place_updates(src), vec![false, true, false]);
+    }
+
+    #[test]
+    fn keeps_copies_of_escaping_arrays() {
+        let src = "
+            brillig fn main f0 {
+              b0(v0: [Field; 4]):
+                v1 = array_set v0, index u32 0, value Field 1
+                v2 = array_set v1, index u32 1, value Field 2
+                v3 = array_set v0, index u32 2, value Field 3
+                v4 = array_set v3, index u32 3, value Field 4
+                v5 = array_set v4, index u32 0, value Field 5
+                jmp b1()
+              b1():
+                v6 = array_get v1, index u32 0 -> Field
+                return v4, v6
+            }
+        ";
+        // v1 and v4 are used within another block
+        assert_eq!(in_place_updates(src), vec![false, false, false, true, false]);
+    }
+}
@@ -1,3 +1,5 @@
+use serde::{Deserialize, Serialize};
+
 use super::{
     instruction::{InstructionId, TerminatorInstruction},
     map::Id,
@@ -10,7 +12,7 @@ use super::{
 ///
 /// This means that if one instruction is executed in a basic
 /// block, then all instructions are executed. ie single-entry single-exit.
-#[derive(Debug, PartialEq, Eq, Hash, Clone)]
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Serialize, Deserialize)]
 pub(crate) struct BasicBlock {
     /// Parameters to the basic block.
     parameters: Vec<ValueId>,
@@ -0,0 +1,273 @@
+//! The call graph of a program records which functions each function calls directly, and from
+//! which call sites.
+//!
+//! Only calls to known functions are recorded. Calls through a function value which is not yet
+//! known, such as a parameter, are left out, as are calls to intrinsics and foreign functions.
+//!
+//! The call graph is partitioned into strongly connected components, where each component is a
+//! maximal set of functions which can all reach each other through calls. A function is recursive
+//! if it is in a component with other functions, or if it calls itself directly.
+use std::collections::{BTreeMap, BTreeSet, HashMap, VecDeque};
+
+use crate::ssa_refactor::ssa_gen::Ssa;
+
+use super::{
+    function::{Function, FunctionId},
+    instruction::{Instruction, InstructionId},
+    value::Value,
+};
+
+/// The direct calls between the functions of a program.
+pub(crate) struct CallGraph {
+    /// The call sites of each function called by each caller, keyed by caller then callee.
+    call_sites: BTreeMap<FunctionId, BTreeMap<FunctionId, Vec<InstructionId>>>,
+
+    /// The callers of each function.
+    callers: BTreeMap<FunctionId, BTreeSet<FunctionId>>,
+
+    /// The strongly connected components of the call graph. Each component is listed after
+    /// every component it calls into, so callees are always visited before their callers.
+    components: Vec<Vec<FunctionId>>,
+
+    /// The index of the component containing each function.
+    component_of: HashMap<FunctionId, usize>,
+}
+
+impl CallGraph {
+    /// Builds the call graph of the given program.
+    pub(crate) fn with_ssa(ssa: &Ssa) -> Self {
+        let mut call_sites = BTreeMap::new();
+        let mut callers: BTreeMap<FunctionId, BTreeSet<FunctionId>> =
+            ssa.functions.keys().map(|id| (*id, BTreeSet::new())).collect();
+
+        for (id, function) in &ssa.functions {
+            let function_call_sites = direct_calls(function);
+            for callee in function_call_sites.keys() {
+                callers.entry(*callee).or_default().insert(*id);
+            }
+            call_sites.insert(*id, function_call_sites);
+        }
+
+        let mut call_graph =
+            Self { call_sites, callers, components: Vec::new(), component_of: HashMap::new() };
+        call_graph.components = StronglyConnectedComponents::find(&call_graph);
+        for (index, component) in call_graph.components.iter().enumerate() {
+            for function in component {
+                call_graph.component_of.insert(*function, index);
+            }
+        }
+        call_graph
+    }
+
+    /// Returns each function directly called by the given function, in ascending order.
+    pub(crate) fn callees(&self, function: FunctionId) -> impl Iterator<Item = FunctionId> + '_ {
+        self.call_sites.get(&function).into_iter().flat_map(|callees| callees.keys().copied())
+    }
+
+    /// Returns each function which directly calls the given function, in ascending order.
+    pub(crate) fn callers(&self, function: FunctionId) -> impl Iterator<Item = FunctionId> + '_ {
+        self.callers.get(&function).into_iter().flat_map(|callers| callers.iter().copied())
+    }
+
+    /// Returns each call instruction within `caller` which calls `callee`.
+    pub(crate) fn call_sites(&self, caller: FunctionId, callee: FunctionId) -> &[InstructionId] {
+        self.call_sites
+            .get(&caller)
+            .and_then(|callees| callees.get(&callee))
+            .map_or(&[], |call_sites| call_sites.as_slice())
+    }
+
+    /// Returns the strongly connected components of the call graph, where each component is
+    /// listed after every component it calls into.
+    pub(crate) fn strongly_connected_components(&self) -> &[Vec<FunctionId>] {
+        &self.components
+    }
+
+    /// Returns true if the given function may call itself, either directly or through other
+    /// functions.
+    pub(crate) fn is_recursive(&self, function: FunctionId) -> bool {
+        match self.component_of.get(&function) {
+            Some(component) => {
+                self.components[*component].len() > 1
+                    || self.callees(function).any(|callee| callee == function)
+            }
+            None => false,
+        }
+    }
+
+    /// Returns a shortest cycle of calls from the given function back to itself, beginning and
+    /// ending with the given function, or None if the function is not recursive.
+    pub(crate) fn find_cycle(&self, function: FunctionId) -> Option<Vec<FunctionId>> {
+        if !self.is_recursive(function) {
+            return None;
+        }
+
+        // Every function on a cycle through `function` is within its component, so the search
+        // never leaves it.
+        let component = self.component_of[&function];
+        let mut previous = HashMap::new();
+        let mut queue = VecDeque::from([function]);
+
+        while let Some(caller) = queue.pop_front() {
+            for callee in self.callees(caller) {
+                if callee == function {
+                    let mut cycle = vec![function, caller];
+                    let mut current = caller;
+                    while current != function {
+                        current = previous[&current];
+                        cycle.push(current);
+                    }
+                    cycle.reverse();
+                    return Some(cycle);
+                }
+                if self.component_of[&callee] == component && !previous.contains_key(&callee) {
+                    previous.insert(callee, caller);
+                    queue.push_back(callee);
+                }
+            }
+        }
+        unreachable!("ICE: {function} is recursive but has no call cycle")
+    }
+}
+
+/// Returns the call sites of each function called directly from the reachable blocks of the
+/// given function.
+fn direct_calls(function: &Function) -> BTreeMap<FunctionId, Vec<InstructionId>> {
+    let dfg = &function.dfg;
+    let mut calls: BTreeMap<_, Vec<_>> = BTreeMap::new();
+
+    let mut blocks: Vec<_> = function.reachable_blocks().into_iter().collect();
+    blocks.sort();
+
+    for block in blocks {
+        for instruction in dfg[block].instructions() {
+            if let Instruction::Call { func, .. } = &dfg[*instruction] {
+                if let Value::Function(callee) = &dfg[dfg.resolve(*func)] {
+                    calls.entry(*callee).or_default().push(*instruction);
+                }
+            }
+        }
+    }
+    calls
+}
+
+/// Tarjan's algorithm for finding the strongly connected components of the call graph.
+///
+/// Tarjan's algorithm completes each component only after every component reachable from it, so
+/// components are found with callees before their callers.
+#[derive(Default)]
+struct StronglyConnectedComponents {
+    next_index: usize,
+    indices: HashMap<FunctionId, usize>,
+    lowest_links: HashMap<FunctionId, usize>,
+    stack: Vec<FunctionId>,
+    on_stack: BTreeSet<FunctionId>,
+    components: Vec<Vec<FunctionId>>,
+}
+
+impl StronglyConnectedComponents {
+    fn find(call_graph: &CallGraph) -> Vec<Vec<FunctionId>> {
+        let mut context = Self::default();
+        for function in call_graph.callers.keys() {
+            if !context.indices.contains_key(function) {
+                context.visit(call_graph, *function);
+            }
+        }
+        context.components
+    }
+
+    fn visit(&mut self, call_graph: &CallGraph, function: FunctionId) {
+        let index = self.next_index;
+        self.next_index += 1;
+        self.indices.insert(function, index);
+        self.lowest_links.insert(function, index);
+        self.stack.push(function);
+        self.on_stack.insert(function);
+
+        for callee in call_graph.callees(function) {
+            if !self.indices.contains_key(&callee) {
+                self.visit(call_graph, callee);
+                let lowest_link = self.lowest_links[&function].min(self.lowest_links[&callee]);
+                self.lowest_links.insert(function, lowest_link);
+            } else if self.on_stack.contains(&callee) {
+                let lowest_link = self.lowest_links[&function].min(self.indices[&callee]);
+                self.lowest_links.insert(function, lowest_link);
+            }
+        }
+
+        if self.lowest_links[&function] == index {
+            let mut component = Vec::new();
+            loop {
+                let member = self.stack.pop().expect("ICE: Function should be on the stack");
+                self.on_stack.remove(&member);
+                component.push(member);
+                if member == function {
+                    break;
+                }
+            }
+            component.sort();
+            self.components.push(component);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use crate::ssa_refactor::{ir::map::Id, ssa_gen::Ssa};
+
+    use super::CallGraph;
+
+    #[test]
+    fn finds_recursive_call_cycles() {
+        let src = "
+            acir fn main f0 {
+              b0(v0: Field):
+                v1 = call f1(v0) -> Field
+                v2 = call f3(v1) -> Field
+                return v2
+            }
+            acir fn even f1 {
+              b0(v0: Field):
+                v1 = call f2(v0) -> Field
+                return v1
+            }
+            acir fn odd f2 {
+              b0(v0: Field):
+                v1 = call f1(v0) -> Field
+                return v1
+            }
+            acir fn countdown f3 {
+              b0(v0: Field):
+                v1 = call f3(v0) -> Field
+                v2 = call f3(v1) -> Field
+                return v2
+            }
+        ";
+        let ssa = Ssa::from_str(src).unwrap();
+        let call_graph = CallGraph::with_ssa(&ssa);
+        let [main, even, odd, countdown] = [0, 1, 2, 3].map(Id::test_new);
+
+        assert_eq!(call_graph.callees(main).collect::<Vec<_>>(), vec![even, countdown]);
+        assert_eq!(call_graph.callers(even).collect::<Vec<_>>(), vec![main, odd]);
+        assert_eq!(call_graph.call_sites(countdown, countdown).len(), 2);
+        assert!(call_graph.call_sites(even, main).is_empty());
+
+        // Each component is listed after the components it calls into
+        let components = call_graph.strongly_connected_components();
+        let position = |function| components.iter().position(|c| c.contains(&function)).unwrap();
+        assert_eq!(components.len(), 3);
+        assert_eq!(components[position(even)], vec![even, odd]);
+        assert!(position(even) < position(main));
+        assert!(position(countdown) < position(main));
+
+        assert!(!call_graph.is_recursive(main));
+        assert!(call_graph.is_recursive(odd));
+        assert!(call_graph.is_recursive(countdown));
+
+        assert_eq!(call_graph.find_cycle(main), None);
+        assert_eq!(call_graph.find_cycle(odd), Some(vec![odd, even, odd]));
+        assert_eq!(call_graph.find_cycle(countdown), Some(vec![countdown, countdown]));
+    }
+}
@@ -16,12 +16,13 @@ use super::{
 use acvm::FieldElement;
 use iter_extended::vecmap;
 use noirc_errors::Location;
+use serde::{Deserialize, Serialize};
 
 /// The DataFlowGraph contains most of the actual data in a function including
 /// its blocks, instructions, and values. This struct is largely responsible for
 /// owning most data in a function and handing out Ids to this data that can be
 /// shared without worrying about ownership.
-#[derive(Debug, Default)]
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
 pub(crate) struct DataFlowGraph {
     /// All of the instructions in a function
     instructions: DenseMap<Instruction>,
@@ -42,7 +43,8 @@ pub(crate) struct DataFlowGraph {
     values: DenseMap<Value>,
 
     /// Each constant is unique, attempting to insert the same constant
-    /// twice will return the same ValueId.
+    /// twice will return the same ValueId. Since constants are interned,
+    /// two resolved constants are equal only if their ValueIds are equal.
     constants: HashMap<(FieldElement, Type), ValueId>,
 
     /// Contains each function that has been imported into the current function.
@@ -189,20 +191,50 @@ impl DataFlowGraph {
     }
 
     /// Set the type of value_id to the target_type.
+    ///
+    /// Retyping a numeric constant keeps the constant pool unique: if the retyped constant
+    /// already exists, value_id is replaced with the existing value.
     pub(crate) fn set_type_of_value(&mut self, value_id: ValueId, target_type: Type) {
         let value = &mut self.values[value_id];
         match value {
-            Value::Instruction { typ, .. }
-            | Value::Param { typ, .. }
-            | Value::NumericConstant { typ, .. } => {
+            Value::Instruction { typ, .. } | Value::Param { typ, .. } => {
                 *typ = target_type;
             }
+            Value::NumericConstant { constant, typ } => {
+                let constant = *constant;
+                let old_type = std::mem::replace(typ, target_type.clone());
+                self.reintern_constant(value_id, constant, old_type, target_type);
+            }
             _ => {
                 unreachable!("ICE: Cannot set type of {:?}", value);
             }
         }
     }
 
+    /// Moves the pool entry of a constant whose type changed from old_type to new_type.
+    fn reintern_constant(
+        &mut self,
+        value_id: ValueId,
+        constant: FieldElement,
+        old_type: Type,
+        new_type: Type,
+    ) {
+        let old_key = (constant, old_type);
+        if self.constants.get(&old_key) == Some(&value_id) {
+            self.constants.remove(&old_key);
+        }
+
+        match self.constants.get(&(constant, new_type.clone())) {
+            Some(existing) if *existing != value_id => {
+                self.replaced_value_ids.insert(value_id, *existing);
+            }
+            Some(_) => (),
+            None => {
+                self.constants.insert((constant, new_type), value_id);
+            }
+        }
+    }
+
     /// If `original_value_id`'s underlying `Value` has been substituted for that of another
     /// `ValueId`, this function will return the `ValueId` from which the substitution was taken.
     /// If `original_value_id`'s underlying `Value` has not been substituted, the same `ValueId`
@@ -406,6 +438,11 @@ impl DataFlowGraph {
         destination.set_terminator(terminator);
     }
 
+    /// Sets the source location of the given instruction
+    pub(crate) fn set_location(&mut self, id: InstructionId, location: Location) {
+        self.locations.insert(id, location);
+    }
+
     pub(crate) fn get_location(&self, id: &InstructionId) -> Option<Location> {
         self.locations.get(id).cloned()
     }
@@ -501,8 +538,10 @@ impl<'dfg> InsertInstructionResult<'dfg> {
 
 #[cfg(test)]
 mod tests {
+    use acvm::FieldElement;
+
     use super::DataFlowGraph;
-    use crate::ssa_refactor::ir::instruction::Instruction;
+    use crate::ssa_refactor::ir::{instruction::Instruction, types::Type};
 
     #[test]
     fn make_instruction() {
@@ -513,4 +552,30 @@ mod tests {
         let results = dfg.instruction_results(ins_id);
         assert_eq!(results.len(), 1);
     }
+
+    #[test]
+    fn constants_are_interned() {
+        let mut dfg = DataFlowGraph::default();
+        let one = dfg.make_constant(FieldElement::one(), Type::field());
+        assert_eq!(dfg.make_constant(FieldElement::one(), Type::field()), one);
+        assert_ne!(dfg.make_constant(FieldElement::one(), Type::bool()), one);
+    }
+
+    #[test]
+    fn retyped_constants_stay_interned() {
+        let mut dfg = DataFlowGraph::default();
+        let field_one = dfg.make_constant(FieldElement::one(), Type::field());
+        let bool_one = dfg.make_constant(FieldElement::one(), Type::bool());
+        let field_two = dfg.make_constant(FieldElement::from(2u128), Type::field());
+
+        // Retyping onto an existing constant replaces the retyped value with it
+        dfg.set_type_of_value(field_one, Type::bool());
+        assert_eq!(dfg.resolve(field_one), bool_one);
+        assert_ne!(dfg.make_constant(FieldElement::one(), Type::field()), field_one);
+
+        // Otherwise the retyped value becomes the pooled constant for its new type
+        dfg.set_type_of_value(field_two, Type::unsigned(8));
+        let u8_two = dfg.make_constant(FieldElement::from(2u128), Type::unsigned(8));
+        assert_eq!(u8_two, field_two);
+    }
 }
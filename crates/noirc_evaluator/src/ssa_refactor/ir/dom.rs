@@ -3,15 +3,23 @@
 //!
 //! Dominator trees are useful for tasks such as identifying back-edges in loop analysis or
 //! calculating dominance frontiers.
+//!
+//! The post-dominator tree of a function is the dominator tree of its reversed control flow graph,
+//! rooted at the function's return block. It is useful for tasks such as sinking instructions or
+//! finding the block where the branches of a conditional rejoin.
 
-use std::{cmp::Ordering, collections::HashMap};
+use std::{
+    cmp::Ordering,
+    collections::{HashMap, HashSet},
+};
 
 use super::{
-    basic_block::BasicBlockId, cfg::ControlFlowGraph, function::Function, post_order::PostOrder,
+    basic_block::BasicBlockId, cfg::ControlFlowGraph, function::Function,
+    instruction::TerminatorInstruction, post_order::PostOrder,
 };
 
 /// Dominator tree node. We keep one of these per reachable block.
-#[derive(Clone, Default)]
+#[derive(Clone, Default, Debug)]
 struct DominatorTreeNode {
     /// The block's idx in the control flow graph's reverse post-order
     reverse_post_order_idx: u32,
@@ -38,6 +46,7 @@ impl DominatorTreeNode {
 }
 
 /// The dominator tree for a single function.
+#[derive(Clone, Debug)]
 pub(crate) struct DominatorTree {
     /// The nodes of the dominator tree
     ///
@@ -118,11 +127,63 @@ impl DominatorTree {
         }
     }
 
+    /// Computes the dominance frontier of each reachable block.
+    ///
+    /// The dominance frontier of a block is the set of blocks where its dominance ends: each block
+    /// which is not strictly dominated by it, but which has a predecessor that is dominated by it.
+    /// These are the blocks where a value defined within the block may need a block parameter to
+    /// be merged with values from other paths.
+    ///
+    /// The given control flow graph is expected to be the one this tree was computed from.
+    /// Blocks with an empty dominance frontier are omitted from the result.
+    pub(crate) fn dominance_frontiers(
+        &self,
+        cfg: &ControlFlowGraph,
+    ) -> HashMap<BasicBlockId, HashSet<BasicBlockId>> {
+        let mut frontiers: HashMap<BasicBlockId, HashSet<BasicBlockId>> = HashMap::new();
+
+        // This uses the algorithm from Cooper, Harvey, and Kennedy's "A Simple, Fast Dominance
+        // Algorithm": only join points can be within a dominance frontier, and a join point is in
+        // the frontier of each block from its predecessors up to (but excluding) its immediate
+        // dominator.
+        for block in self.nodes.keys() {
+            let predecessors = cfg.predecessors(*block);
+            if predecessors.len() < 2 {
+                continue;
+            }
+
+            let immediate_dominator = self.immediate_dominator(*block);
+            for predecessor in predecessors.filter(|predecessor| self.is_reachable(*predecessor)) {
+                let mut runner = predecessor;
+                while Some(runner) != immediate_dominator {
+                    frontiers.entry(runner).or_default().insert(*block);
+                    match self.immediate_dominator(runner) {
+                        Some(next) => runner = next,
+                        None => break,
+                    }
+                }
+            }
+        }
+
+        frontiers
+    }
+
     /// Allocate and compute a dominator tree from a pre-computed control flow graph and
     /// post-order counterpart.
     pub(crate) fn with_cfg_and_post_order(cfg: &ControlFlowGraph, post_order: &PostOrder) -> Self {
+        Self::with_predecessors_and_post_order(post_order.as_slice(), |block| {
+            cfg.predecessors(block)
+        })
+    }
+
+    /// Allocate and compute a dominator tree over an arbitrary graph, given the predecessors of
+    /// each block and the post-order of the graph from its root.
+    fn with_predecessors_and_post_order<I: Iterator<Item = BasicBlockId>>(
+        post_order: &[BasicBlockId],
+        predecessors: impl Fn(BasicBlockId) -> I,
+    ) -> Self {
         let mut dom_tree = DominatorTree { nodes: HashMap::new(), cache: HashMap::new() };
-        dom_tree.compute_dominator_tree(cfg, post_order);
+        dom_tree.compute_dominator_tree(post_order, &predecessors);
         dom_tree
     }
 
@@ -139,12 +200,14 @@ impl DominatorTree {
 
     /// Build a dominator tree from a control flow graph using Keith D. Cooper's
     /// "Simple, Fast Dominator Algorithm."
-    fn compute_dominator_tree(&mut self, cfg: &ControlFlowGraph, post_order: &PostOrder) {
+    fn compute_dominator_tree<I: Iterator<Item = BasicBlockId>>(
+        &mut self,
+        post_order: &[BasicBlockId],
+        predecessors: &impl Fn(BasicBlockId) -> I,
+    ) {
         // We'll be iterating over a reverse post-order of the CFG, skipping the entry block.
-        let (entry_block_id, entry_free_post_order) = post_order
-            .as_slice()
-            .split_last()
-            .expect("ICE: functions always have at least one block");
+        let (entry_block_id, entry_free_post_order) =
+            post_order.split_last().expect("ICE: functions always have at least one block");
 
         // Do a first pass where we assign reverse post-order indices to all reachable nodes. The
         // entry block will be the only node with no immediate dominator.
@@ -158,7 +221,7 @@ impl DominatorTree {
 
             // Due to the nature of the post-order traversal, every node we visit will have at
             // least one predecessor that has previously been assigned during this loop.
-            let immediate_dominator = self.compute_immediate_dominator(block_id, cfg);
+            let immediate_dominator = self.compute_immediate_dominator(block_id, predecessors);
             self.nodes.insert(
                 block_id,
                 DominatorTreeNode {
@@ -176,7 +239,7 @@ impl DominatorTree {
         while changed {
             changed = false;
             for &block_id in entry_free_post_order.iter().rev() {
-                let immediate_dominator = self.compute_immediate_dominator(block_id, cfg);
+                let immediate_dominator = self.compute_immediate_dominator(block_id, predecessors);
                 changed = self
                     .nodes
                     .get_mut(&block_id)
@@ -188,15 +251,15 @@ impl DominatorTree {
 
     // Compute the immediate dominator for `block_id` using the pre-calculate immediate dominators
     // of reachable nodes.
-    fn compute_immediate_dominator(
+    fn compute_immediate_dominator<I: Iterator<Item = BasicBlockId>>(
         &self,
         block_id: BasicBlockId,
-        cfg: &ControlFlowGraph,
+        predecessors: &impl Fn(BasicBlockId) -> I,
     ) -> BasicBlockId {
         // Get an iterator with just the reachable, already visited predecessors to `block_id`.
         // Note that during the first pass `node` was pre-populated with all reachable blocks.
         let mut reachable_predecessors =
-            cfg.predecessors(block_id).filter(|pred_id| self.nodes.contains_key(pred_id));
+            predecessors(block_id).filter(|pred_id| self.nodes.contains_key(pred_id));
 
         // This function isn't called on unreachable blocks or the entry block, so the reverse
         // post-order will contain at least one predecessor to this block.
@@ -241,14 +304,87 @@ impl DominatorTree {
     }
 }
 
+/// The post-dominator tree for a single function.
+///
+/// A block is said to *post-dominate* another if all control flow paths from the other block to
+/// the function's return must go through it. Blocks from which the return block is unreachable,
+/// such as those within an infinite loop, are not part of the tree.
+#[derive(Clone, Debug)]
+pub(crate) struct PostDominatorTree {
+    /// The dominator tree of the reversed control flow graph
+    tree: DominatorTree,
+}
+
+/// Methods for querying the post-dominator tree.
+impl PostDominatorTree {
+    /// Can the return block be reached from `block_id`?
+    pub(crate) fn is_reachable(&self, block_id: BasicBlockId) -> bool {
+        self.tree.is_reachable(block_id)
+    }
+
+    /// Returns the immediate post-dominator of `block_id`, which is the post-dominator that is
+    /// closest to `block_id`.
+    ///
+    /// This returns `None` if the return block is not reachable from `block_id`, or if it is the
+    /// return block which has no post-dominators.
+    pub(crate) fn immediate_post_dominator(&self, block_id: BasicBlockId) -> Option<BasicBlockId> {
+        self.tree.immediate_dominator(block_id)
+    }
+
+    /// Compare two blocks relative to the reverse post-order of the reversed control flow graph.
+    pub(crate) fn reverse_post_order_cmp(&self, a: BasicBlockId, b: BasicBlockId) -> Ordering {
+        self.tree.reverse_post_order_cmp(a, b)
+    }
+
+    /// Returns `true` if `block_a_id` post-dominates `block_b_id`.
+    ///
+    /// This function panics if the return block is unreachable from either of the blocks.
+    ///
+    /// A block is considered to post-dominate itself.
+    pub(crate) fn post_dominates(
+        &mut self,
+        block_a_id: BasicBlockId,
+        block_b_id: BasicBlockId,
+    ) -> bool {
+        self.tree.dominates(block_a_id, block_b_id)
+    }
+
+    /// Allocate and compute the post-dominator tree from a pre-computed control flow graph.
+    ///
+    /// The function is expected to have a single reachable return block, which is the root of
+    /// the tree.
+    pub(crate) fn with_cfg(func: &Function, cfg: &ControlFlowGraph) -> Self {
+        let exit_block = func
+            .reachable_blocks()
+            .into_iter()
+            .find(|block| {
+                matches!(func.dfg[*block].terminator(), Some(TerminatorInstruction::Return { .. }))
+            })
+            .expect("ICE: Expected a function to have a return block");
+
+        let post_order = PostOrder::with_reversed_cfg(cfg, exit_block);
+        let tree =
+            DominatorTree::with_predecessors_and_post_order(post_order.as_slice(), |block| {
+                cfg.successors(block)
+            });
+        Self { tree }
+    }
+
+    /// Allocate and compute the post-dominator tree for the given function.
+    pub(crate) fn with_function(func: &Function) -> Self {
+        let cfg = ControlFlowGraph::with_function(func);
+        Self::with_cfg(func, &cfg)
+    }
+}
+
 #[cfg(test)]
 mod tests {
-    use std::cmp::Ordering;
+    use std::{cmp::Ordering, collections::HashSet};
 
     use crate::ssa_refactor::{
         ir::{
             basic_block::BasicBlockId,
-            dom::DominatorTree,
+            dom::{DominatorTree, PostDominatorTree},
             function::{Function, RuntimeType},
             instruction::TerminatorInstruction,
             map::Id,
@@ -451,4 +587,171 @@ mod tests {
         assert!(dt.dominates(block2_id, block1_id));
         assert!(dt.dominates(block2_id, block2_id));
     }
+
+    #[test]
+    fn dominance_frontiers() {
+        // func {
+        //   block0(cond: u1):
+        //     jmp block1()
+        //   block1():
+        //     jmpif cond block2() block3()
+        //   block2():
+        //     jmp block4()
+        //   block3():
+        //     jmp block4()
+        //   block4():
+        //     jmpif cond block1() block5()
+        //   block5():
+        //     return ()
+        // }
+        let func_id = Id::test_new(0);
+        let mut builder = FunctionBuilder::new("func".into(), func_id, RuntimeType::Acir);
+        let cond = builder.add_parameter(Type::bool());
+        let block1_id = builder.insert_block();
+        let block2_id = builder.insert_block();
+        let block3_id = builder.insert_block();
+        let block4_id = builder.insert_block();
+        let block5_id = builder.insert_block();
+
+        builder.terminate_with_jmp(block1_id, vec![]);
+        builder.switch_to_block(block1_id);
+        builder.terminate_with_jmpif(cond, block2_id, block3_id);
+        builder.switch_to_block(block2_id);
+        builder.terminate_with_jmp(block4_id, vec![]);
+        builder.switch_to_block(block3_id);
+        builder.terminate_with_jmp(block4_id, vec![]);
+        builder.switch_to_block(block4_id);
+        builder.terminate_with_jmpif(cond, block1_id, block5_id);
+        builder.switch_to_block(block5_id);
+        builder.terminate_with_return(vec![]);
+
+        let ssa = builder.finish();
+        let frontiers = ssa.main().dominance_frontiers();
+
+        let frontier = |block| frontiers.get(&block).cloned().unwrap_or_default();
+        assert!(frontier(ssa.main().entry_block()).is_empty());
+        assert_eq!(frontier(block1_id), HashSet::from([block1_id]));
+        assert_eq!(frontier(block2_id), HashSet::from([block4_id]));
+        assert_eq!(frontier(block3_id), HashSet::from([block4_id]));
+        assert_eq!(frontier(block4_id), HashSet::from([block1_id]));
+        assert!(frontier(block5_id).is_empty());
+    }
+
+    #[test]
+    fn cached_dominator_tree_is_recomputed_after_cfg_changes() {
+        // func {
+        //   block0():
+        //     jmp block1()
+        //   block1():
+        //     jmp block2()
+        //   block2():
+        //     return ()
+        // }
+        let func_id = Id::test_new(0);
+        let mut builder = FunctionBuilder::new("func".into(), func_id, RuntimeType::Acir);
+        let block1_id = builder.insert_block();
+        let block2_id = builder.insert_block();
+
+        builder.terminate_with_jmp(block1_id, vec![]);
+        builder.switch_to_block(block1_id);
+        builder.terminate_with_jmp(block2_id, vec![]);
+        builder.switch_to_block(block2_id);
+        builder.terminate_with_return(vec![]);
+
+        let mut ssa = builder.finish();
+        let func = ssa.main_mut();
+        let block0_id = func.entry_block();
+        assert_eq!(func.dominator_tree().immediate_dominator(block2_id), Some(block1_id));
+
+        // Jump from block0 directly to block2 so that block1 becomes unreachable
+        let jmp = TerminatorInstruction::Jmp { destination: block2_id, arguments: vec![] };
+        func.dfg.set_block_terminator(block0_id, jmp);
+
+        let dom_tree = func.dominator_tree();
+        assert_eq!(dom_tree.immediate_dominator(block2_id), Some(block0_id));
+        assert!(!dom_tree.is_reachable(block1_id));
+    }
+
+    #[test]
+    fn post_dominators() {
+        // func {
+        //   block0(cond: u1):
+        //     jmpif cond block1() block2()
+        //   block1():
+        //     jmp block3()
+        //   block2():
+        //     jmp block3()
+        //   block3():
+        //     return ()
+        // }
+        let func_id = Id::test_new(0);
+        let mut builder = FunctionBuilder::new("func".into(), func_id, RuntimeType::Acir);
+        let cond = builder.add_parameter(Type::bool());
+        let block1_id = builder.insert_block();
+        let block2_id = builder.insert_block();
+        let block3_id = builder.insert_block();
+
+        builder.terminate_with_jmpif(cond, block1_id, block2_id);
+        builder.switch_to_block(block1_id);
+        builder.terminate_with_jmp(block3_id, vec![]);
+        builder.switch_to_block(block2_id);
+        builder.terminate_with_jmp(block3_id, vec![]);
+        builder.switch_to_block(block3_id);
+        builder.terminate_with_return(vec![]);
+
+        let ssa = builder.finish();
+        let func = ssa.main();
+        let block0_id = func.entry_block();
+
+        let mut post_dom_tree = PostDominatorTree::with_function(func);
+
+        // Expected post-dominator tree:
+        // block3 {
+        //   block0
+        //   block1
+        //   block2
+        // }
+        assert_eq!(post_dom_tree.immediate_post_dominator(block0_id), Some(block3_id));
+        assert_eq!(post_dom_tree.immediate_post_dominator(block1_id), Some(block3_id));
+        assert_eq!(post_dom_tree.immediate_post_dominator(block2_id), Some(block3_id));
+        assert_eq!(post_dom_tree.immediate_post_dominator(block3_id), None);
+
+        assert!(post_dom_tree.post_dominates(block3_id, block0_id));
+        assert!(post_dom_tree.post_dominates(block3_id, block1_id));
+        assert!(post_dom_tree.post_dominates(block0_id, block0_id));
+        assert!(!post_dom_tree.post_dominates(block1_id, block0_id));
+        assert!(!post_dom_tree.post_dominates(block0_id, block3_id));
+    }
+
+    #[test]
+    fn post_dominators_exclude_infinite_loops() {
+        // func {
+        //   block0(cond: u1):
+        //     jmpif cond block1() block2()
+        //   block1():
+        //     jmp block1()
+        //   block2():
+        //     return ()
+        // }
+        let func_id = Id::test_new(0);
+        let mut builder = FunctionBuilder::new("func".into(), func_id, RuntimeType::Acir);
+        let cond = builder.add_parameter(Type::bool());
+        let block1_id = builder.insert_block();
+        let block2_id = builder.insert_block();
+
+        builder.terminate_with_jmpif(cond, block1_id, block2_id);
+        builder.switch_to_block(block1_id);
+        builder.terminate_with_jmp(block1_id, vec![]);
+        builder.switch_to_block(block2_id);
+        builder.terminate_with_return(vec![]);
+
+        let ssa = builder.finish();
+        let func = ssa.main();
+        let block0_id = func.entry_block();
+
+        let post_dom_tree = func.post_dominator_tree();
+        assert_eq!(post_dom_tree.immediate_post_dominator(block0_id), Some(block2_id));
+        assert!(post_dom_tree.is_reachable(block0_id));
+        assert!(!post_dom_tree.is_reachable(block1_id));
+    }
 }
@@ -0,0 +1,173 @@
+//! Renders the control flow graph of each function, and the call graph of the program, in the
+//! DOT language used by Graphviz.
+//!
+//! These graphs are intended for debugging, for example to see which branches flattening merged
+//! or which loops unrolling expanded. Each block of a CFG is labelled with its printed
+//! instructions, and each function of the call graph with the number of instructions within it.
+use std::{
+    collections::BTreeMap,
+    fmt::{Display, Formatter},
+    path::Path,
+};
+
+use crate::ssa_refactor::ssa_gen::Ssa;
+
+use super::{
+    basic_block::BasicBlockId,
+    function::{Function, FunctionId, RuntimeType},
+    instruction::{Instruction, TerminatorInstruction},
+    printer::display_block,
+    value::Value,
+};
+
+/// Writes a DOT file for the CFG of each function in the program, along with a DOT file for the
+/// program's call graph, into the given directory. Each file name begins with the given prefix.
+pub(crate) fn write_dot_files(ssa: &Ssa, directory: &Path, prefix: &str) -> std::io::Result<()> {
+    std::fs::create_dir_all(directory)?;
+
+    for (id, function) in &ssa.functions {
+        let path = directory.join(format!("{prefix}_{id}.dot"));
+        std::fs::write(path, function_cfg_to_dot(function))?;
+    }
+
+    let path = directory.join(format!("{prefix}_call_graph.dot"));
+    std::fs::write(path, call_graph_to_dot(ssa))
+}
+
+/// Renders the CFG of the given function, with a node for each block reachable from the
+/// entry block.
+pub(crate) fn function_cfg_to_dot(function: &Function) -> String {
+    let mut blocks: Vec<_> = function.reachable_blocks().into_iter().collect();
+    blocks.sort();
+
+    let mut lines = vec![format!("digraph \"{} {}\" {{", function.name(), function.id())];
+    lines.push("  node [shape=box, fontname=monospace];".to_owned());
+
+    for block in &blocks {
+        let label = escape(&BlockDisplay(function, *block).to_string());
+        lines.push(format!("  {block} [label=\"{label}\"];"));
+    }
+
+    for block in &blocks {
+        match function.dfg[*block].terminator() {
+            Some(TerminatorInstruction::Jmp { destination, .. }) => {
+                lines.push(format!("  {block} -> {destination};"));
+            }
+            Some(TerminatorInstruction::JmpIf { then_destination, else_destination, .. }) => {
+                lines.push(format!("  {block} -> {then_destination} [label=\"then\"];"));
+                lines.push(format!("  {block} -> {else_destination} [label=\"else\"];"));
+            }
+            Some(TerminatorInstruction::Return { .. }) | None => (),
+        }
+    }
+
+    lines.push("}".to_owned());
+    lines.join("\n")
+}
+
+/// Renders the call graph of the given program, with an edge from each function to each
+/// function it calls directly, labelled with the number of calls. Unconstrained functions are
+/// drawn with dashed outlines.
+pub(crate) fn call_graph_to_dot(ssa: &Ssa) -> String {
+    let mut lines = vec!["digraph call_graph {".to_owned()];
+    lines.push("  node [shape=box, fontname=monospace];".to_owned());
+
+    for (id, function) in &ssa.functions {
+        let style = match function.runtime() {
+            RuntimeType::Acir => "solid",
+            RuntimeType::Brillig => "dashed",
+        };
+        let instructions = instruction_count(function);
+        let label = escape(&format!("{} {id}\n{instructions} instructions\n", function.name()));
+        lines.push(format!("  {id} [label=\"{label}\", style={style}];"));
+    }
+
+    for (id, function) in &ssa.functions {
+        for (callee, calls) in callees(function) {
+            lines.push(format!("  {id} -> {callee} [label=\"{calls}\"];"));
+        }
+    }
+
+    lines.push("}".to_owned());
+    lines.join("\n")
+}
+
+/// The number of direct calls the given function makes to each other function
+fn callees(function: &Function) -> BTreeMap<FunctionId, usize> {
+    let dfg = &function.dfg;
+    let mut callees = BTreeMap::new();
+
+    for block in function.reachable_blocks() {
+        for instruction in dfg[block].instructions() {
+            if let Instruction::Call { func, .. } = &dfg[*instruction] {
+                if let Value::Function(callee) = &dfg[dfg.resolve(*func)] {
+                    *callees.entry(*callee).or_default() += 1;
+                }
+            }
+        }
+    }
+    callees
+}
+
+fn instruction_count(function: &Function) -> usize {
+    let blocks = function.reachable_blocks();
+    blocks.iter().map(|block| function.dfg[*block].instructions().len()).sum()
+}
+
+/// Escapes the given text for use within a quoted DOT label, left-justifying each line.
+fn escape(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\l")
+}
+
+/// Displays a single block as it is printed within its function
+struct BlockDisplay<'f>(&'f Function, BasicBlockId);
+
+impl Display for BlockDisplay<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        display_block(self.0, self.1, f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use crate::ssa_refactor::ssa_gen::Ssa;
+
+    use super::{call_graph_to_dot, function_cfg_to_dot};
+
+    #[test]
+    fn renders_cfgs_and_call_graphs() {
+        let src = "
+            acir fn main f0 {
+              b0(v0: u1):
+                jmpif v0 then: b1, else: b2
+              b1():
+                v1 = call f1(Field 1) -> Field
+                jmp b2()
+              b2():
+                return
+            }
+            brillig fn foo f1 {
+              b0(v0: Field):
+                return v0
+            }
+        ";
+        let ssa = Ssa::from_str(src).unwrap();
+
+        let cfg = function_cfg_to_dot(ssa.main());
+        assert!(cfg.starts_with("digraph \"main f0\" {"));
+        assert!(cfg.contains(r#"b0 [label="  b0(v0: u1):\l    jmpif v0 then: b1, else: b2\l"];"#));
+        assert!(cfg.contains(r#"b0 -> b1 [label="then"];"#));
+        assert!(cfg.contains(r#"b0 -> b2 [label="else"];"#));
+        assert!(cfg.contains("b1 -> b2;"));
+
+        let expected_call_graph = r#"digraph call_graph {
+  node [shape=box, fontname=monospace];
+  f0 [label="main f0\l1 instructions\l", style=solid];
+  f1 [label="foo f1\l0 instructions\l", style=dashed];
+  f0 -> f1 [label="1"];
+}"#;
+        assert_eq!(call_graph_to_dot(&ssa), expected_call_graph);
+    }
+}
@@ -1,15 +1,19 @@
-use std::collections::HashSet;
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
 
 use iter_extended::vecmap;
+use serde::{Deserialize, Serialize};
 
 use super::basic_block::BasicBlockId;
+use super::cfg::ControlFlowGraph;
 use super::dfg::DataFlowGraph;
+use super::dom::{DominatorTree, PostDominatorTree};
 use super::instruction::TerminatorInstruction;
 use super::map::Id;
 use super::types::Type;
 use super::value::ValueId;
 
-#[derive(Clone, Copy, PartialEq, Eq, Debug, Hash)]
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Hash, Serialize, Deserialize)]
 pub(crate) enum RuntimeType {
     // A noir function, to be compiled in ACIR and executed by ACVM
     Acir,
@@ -23,7 +27,7 @@ pub(crate) enum RuntimeType {
 /// All functions outside of the current function are seen as external.
 /// To reference external functions its FunctionId can be used but this
 /// cannot be checked for correctness until inlining is performed.
-#[derive(Debug)]
+#[derive(Debug, Serialize, Deserialize)]
 pub(crate) struct Function {
     /// The first basic block in the function
     entry_block: BasicBlockId,
@@ -38,6 +42,32 @@ pub(crate) struct Function {
     /// The DataFlowGraph holds the majority of data pertaining to the function
     /// including its blocks, instructions, and values.
     pub(crate) dfg: DataFlowGraph,
+
+    /// The most recently computed dominator tree of this function, along with the
+    /// control flow it was computed from.
+    #[serde(skip)]
+    dominator_tree: RefCell<Option<(ControlFlowSnapshot, DominatorTree)>>,
+
+    /// The most recently computed post-dominator tree of this function, along with the
+    /// control flow it was computed from.
+    #[serde(skip)]
+    post_dominator_tree: RefCell<Option<(ControlFlowSnapshot, PostDominatorTree)>>,
+}
+
+/// The entry block and successors of every block within a function.
+/// If this is unchanged then the function's dominator tree is also unchanged.
+#[derive(Debug, PartialEq, Eq)]
+struct ControlFlowSnapshot {
+    entry_block: BasicBlockId,
+    successors: Vec<Vec<BasicBlockId>>,
+}
+
+impl ControlFlowSnapshot {
+    fn new(function: &Function) -> Self {
+        let blocks = function.dfg.basic_blocks_iter();
+        let successors = blocks.map(|(_, block)| block.successors().collect()).collect();
+        Self { entry_block: function.entry_block, successors }
+    }
 }
 
 impl Function {
@@ -47,7 +77,28 @@ impl Function {
     pub(crate) fn new(name: String, id: FunctionId) -> Self {
         let mut dfg = DataFlowGraph::default();
         let entry_block = dfg.make_block();
-        Self { name, id, entry_block, dfg, runtime: RuntimeType::Acir }
+        Self {
+            name,
+            id,
+            entry_block,
+            dfg,
+            runtime: RuntimeType::Acir,
+            dominator_tree: RefCell::default(),
+            post_dominator_tree: RefCell::default(),
+        }
+    }
+
+    /// Creates a copy of this function with the given id.
+    pub(crate) fn clone_with_id(&self, id: FunctionId) -> Self {
+        Self {
+            name: self.name.clone(),
+            id,
+            entry_block: self.entry_block,
+            dfg: self.dfg.clone(),
+            runtime: self.runtime,
+            dominator_tree: RefCell::default(),
+            post_dominator_tree: RefCell::default(),
+        }
     }
 
     /// The name of the function.
@@ -119,6 +170,68 @@ impl Function {
         blocks
     }
 
+    /// Counts how many times each value is used by an instruction or terminator in the
+    /// reachable blocks of this function.
+    pub(crate) fn use_counts(&self) -> HashMap<ValueId, usize> {
+        let dfg = &self.dfg;
+        let mut use_counts = HashMap::new();
+        let mut count_use =
+            |value: ValueId| *use_counts.entry(dfg.resolve(value)).or_default() += 1;
+
+        for block in self.reachable_blocks() {
+            for instruction in dfg[block].instructions() {
+                dfg[*instruction].for_each_value(&mut count_use);
+            }
+            if let Some(terminator) = dfg[block].terminator() {
+                terminator.for_each_value(&mut count_use);
+            }
+        }
+        use_counts
+    }
+
+    /// Returns the dominator tree of this function.
+    ///
+    /// The tree is cached on the function and is only recomputed if the control flow
+    /// of the function has changed since the tree was last requested.
+    pub(crate) fn dominator_tree(&self) -> DominatorTree {
+        let snapshot = ControlFlowSnapshot::new(self);
+        let mut cache = self.dominator_tree.borrow_mut();
+
+        match cache.as_ref() {
+            Some((cached_snapshot, dom_tree)) if *cached_snapshot == snapshot => dom_tree.clone(),
+            _ => {
+                let dom_tree = DominatorTree::with_function(self);
+                *cache = Some((snapshot, dom_tree.clone()));
+                dom_tree
+            }
+        }
+    }
+
+    /// Returns the post-dominator tree of this function.
+    ///
+    /// Like the dominator tree, the tree is cached on the function and is only recomputed if
+    /// the control flow of the function has changed since the tree was last requested.
+    pub(crate) fn post_dominator_tree(&self) -> PostDominatorTree {
+        let snapshot = ControlFlowSnapshot::new(self);
+        let mut cache = self.post_dominator_tree.borrow_mut();
+
+        match cache.as_ref() {
+            Some((cached_snapshot, tree)) if *cached_snapshot == snapshot => tree.clone(),
+            _ => {
+                let tree = PostDominatorTree::with_function(self);
+                *cache = Some((snapshot, tree.clone()));
+                tree
+            }
+        }
+    }
+
+    /// Returns the dominance frontier of each reachable block within this function.
+    /// Blocks with an empty dominance frontier are omitted.
+    pub(crate) fn dominance_frontiers(&self) -> HashMap<BasicBlockId, HashSet<BasicBlockId>> {
+        let cfg = ControlFlowGraph::with_function(self);
+        self.dominator_tree().dominance_frontiers(&cfg)
+    }
+
     pub(crate) fn signature(&self) -> Signature {
         let params = vecmap(self.parameters(), |param| self.dfg.type_of_value(*param));
         let returns = vecmap(self.returns(), |ret| self.dfg.type_of_value(*ret));
@@ -141,7 +254,7 @@ impl std::fmt::Display for RuntimeType {
 /// within Call instructions.
 pub(crate) type FunctionId = Id<Function>;
 
-#[derive(Debug, Default, Clone, PartialEq, Eq, Hash, Ord, PartialOrd)]
+#[derive(Debug, Default, Clone, PartialEq, Eq, Hash, Ord, PartialOrd, Serialize, Deserialize)]
 pub(crate) struct Signature {
     pub(crate) params: Vec<Type>,
     pub(crate) returns: Vec<Type>,
@@ -3,6 +3,7 @@ use std::rc::Rc;
 use acvm::{acir::BlackBoxFunc, FieldElement};
 use iter_extended::vecmap;
 use num_bigint::BigUint;
+use serde::{Deserialize, Serialize};
 
 use crate::ssa_refactor::ir::types::NumericType;
 
@@ -29,9 +30,10 @@ pub(crate) type InstructionId = Id<Instruction>;
 /// - Opcodes which have no function definition in the
 /// source code and must be processed by the IR. An example
 /// of this is println.
-#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub(crate) enum Intrinsic {
     Sort,
+    AssertSortedPermutation,
     ArrayLen,
     SlicePushBack,
     SlicePushFront,
@@ -43,6 +45,7 @@ pub(crate) enum Intrinsic {
     ToBits(Endian),
     ToRadix(Endian),
     BlackBox(BlackBoxFunc),
+    BigIntMulAddMod,
 }
 
 impl std::fmt::Display for Intrinsic {
@@ -50,6 +53,7 @@ impl std::fmt::Display for Intrinsic {
         match self {
             Intrinsic::Println => write!(f, "println"),
             Intrinsic::Sort => write!(f, "arraysort"),
+            Intrinsic::AssertSortedPermutation => write!(f, "assert_sorted_permutation"),
             Intrinsic::ArrayLen => write!(f, "array_len"),
             Intrinsic::SlicePushBack => write!(f, "slice_push_back"),
             Intrinsic::SlicePushFront => write!(f, "slice_push_front"),
@@ -62,6 +66,7 @@ impl std::fmt::Display for Intrinsic {
             Intrinsic::ToRadix(Endian::Big) => write!(f, "to_be_radix"),
             Intrinsic::ToRadix(Endian::Little) => write!(f, "to_le_radix"),
             Intrinsic::BlackBox(function) => write!(f, "{function}"),
+            Intrinsic::BigIntMulAddMod => write!(f, "bigint_mul_add_mod"),
         }
     }
 }
@@ -73,6 +78,7 @@ impl Intrinsic {
         match name {
             "println" => Some(Intrinsic::Println),
             "arraysort" => Some(Intrinsic::Sort),
+            "assert_sorted_permutation" => Some(Intrinsic::AssertSortedPermutation),
             "array_len" => Some(Intrinsic::ArrayLen),
             "slice_push_back" => Some(Intrinsic::SlicePushBack),
             "slice_push_front" => Some(Intrinsic::SlicePushFront),
@@ -84,19 +90,20 @@ impl Intrinsic {
             "to_be_radix" => Some(Intrinsic::ToRadix(Endian::Big)),
             "to_le_bits" => Some(Intrinsic::ToBits(Endian::Little)),
             "to_be_bits" => Some(Intrinsic::ToBits(Endian::Big)),
+            "bigint_mul_add_mod" => Some(Intrinsic::BigIntMulAddMod),
             other => BlackBoxFunc::lookup(other).map(Intrinsic::BlackBox),
         }
     }
 }
 
 /// The endian-ness of bits when encoding values as bits in e.g. ToBits or ToRadix
-#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq)]
+#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq, Serialize, Deserialize)]
 pub(crate) enum Endian {
     Big,
     Little,
 }
 
-#[derive(Debug, PartialEq, Eq, Hash, Clone)]
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Serialize, Deserialize)]
 /// Instructions are used to perform tasks.
 /// The instructions that the IR is able to specify are listed below.
 pub(crate) enum Instruction {
@@ -112,8 +119,8 @@ pub(crate) enum Instruction {
     /// Truncates `value` to `bit_size`
     Truncate { value: ValueId, bit_size: u32, max_bit_size: u32 },
 
-    /// Constrains a value to be equal to true
-    Constrain(ValueId),
+    /// Constrains a value to be equal to true, with the message to report if it is not
+    Constrain(ValueId, Option<String>),
 
     /// Performs a function call with a list of its arguments.
     Call { func: ValueId, arguments: Vec<ValueId> },
@@ -162,7 +169,7 @@ impl Instruction {
                 InstructionResultType::Operand(*value)
             }
             Instruction::ArraySet { array, .. } => InstructionResultType::Operand(*array),
-            Instruction::Constrain(_)
+            Instruction::Constrain(..)
             | Instruction::Store { .. }
             | Instruction::EnableSideEffects { .. } => InstructionResultType::None,
             Instruction::Load { .. } | Instruction::ArrayGet { .. } | Instruction::Call { .. } => {
@@ -194,7 +201,9 @@ impl Instruction {
                 bit_size: *bit_size,
                 max_bit_size: *max_bit_size,
             },
-            Instruction::Constrain(value) => Instruction::Constrain(f(*value)),
+            Instruction::Constrain(value, message) => {
+                Instruction::Constrain(f(*value), message.clone())
+            }
             Instruction::Call { func, arguments } => Instruction::Call {
                 func: f(*func),
                 arguments: vecmap(arguments.iter().copied(), f),
@@ -232,7 +241,7 @@ impl Instruction {
             Instruction::Cast(value, _)
             | Instruction::Not(value)
             | Instruction::Truncate { value, .. }
-            | Instruction::Constrain(value)
+            | Instruction::Constrain(value, _)
             | Instruction::Load { address: value } => {
                 f(*value);
             }
@@ -256,6 +265,33 @@ impl Instruction {
         }
     }
 
+    /// Returns true if the ACIR generated for this instruction depends on the currently active
+    /// `EnableSideEffects` condition, i.e. if it may fail or have an effect which must be
+    /// disabled when the condition is false.
+    pub(crate) fn requires_side_effects_condition(&self, dfg: &DataFlowGraph) -> bool {
+        match self {
+            Instruction::Binary(binary) => {
+                matches!(
+                    binary.operator,
+                    BinaryOp::Div | BinaryOp::Mod | BinaryOp::Lt | BinaryOp::Shr
+                )
+            }
+            Instruction::ArrayGet { .. } | Instruction::ArraySet { .. } => true,
+            Instruction::Call { func, .. } => match &dfg[*func] {
+                Value::Intrinsic(intrinsic) => matches!(
+                    intrinsic,
+                    Intrinsic::Sort
+                        | Intrinsic::AssertSortedPermutation
+                        | Intrinsic::SliceInsert
+                        | Intrinsic::SliceRemove
+                        | Intrinsic::BigIntMulAddMod
+                ),
+                _ => true,
+            },
+            _ => false,
+        }
+    }
+
     /// Try to simplify this instruction. If the instruction can be simplified to a known value,
     /// that value is returned. Otherwise None is returned.
     ///
@@ -286,7 +322,7 @@ impl Instruction {
                     _ => None,
                 }
             }
-            Instruction::Constrain(value) => {
+            Instruction::Constrain(value, _) => {
                 if let Some(constant) = dfg.get_numeric_constant(*value) {
                     if constant.is_one() {
                         return Remove;
@@ -493,7 +529,11 @@ fn simplify_call(func: ValueId, arguments: &[ValueId], dfg: &mut DataFlowGraph)
                 None
             }
         }
-        Intrinsic::BlackBox(_) | Intrinsic::Println | Intrinsic::Sort => None,
+        Intrinsic::BlackBox(_)
+        | Intrinsic::Println
+        | Intrinsic::Sort
+        | Intrinsic::AssertSortedPermutation
+        | Intrinsic::BigIntMulAddMod => None,
     }
 }
 
@@ -505,6 +545,24 @@ fn constant_to_radix(
     limb_count: u32,
     dfg: &mut DataFlowGraph,
 ) -> ValueId {
+    let bit_size = u32::BITS - (radix - 1).leading_zeros();
+    let limbs = radix_limbs(endian, field, radix, limb_count);
+
+    let result_constants: im::Vector<ValueId> =
+        limbs.into_iter().map(|limb| dfg.make_constant(limb, Type::unsigned(bit_size))).collect();
+
+    let typ = Type::Array(Rc::new(vec![Type::unsigned(bit_size)]), result_constants.len());
+    dfg.make_array(result_constants, typ)
+}
+
+/// Returns the limbs of the radix decomposition of the given field element, as returned by the
+/// `to_radix` and `to_bits` intrinsics.
+pub(crate) fn radix_limbs(
+    endian: Endian,
+    field: FieldElement,
+    radix: u32,
+    limb_count: u32,
+) -> Vec<FieldElement> {
     let bit_size = u32::BITS - (radix - 1).leading_zeros();
     let radix_big = BigUint::from(radix);
     assert_eq!(BigUint::from(2u128).pow(bit_size), radix_big, "ICE: Radix must be a power of 2");
@@ -528,11 +586,7 @@ fn constant_to_radix(
     while limbs.len() < limb_count_with_padding as usize {
         limbs.push(FieldElement::zero());
     }
-    let result_constants: im::Vector<ValueId> =
-        limbs.into_iter().map(|limb| dfg.make_constant(limb, Type::unsigned(bit_size))).collect();
-
-    let typ = Type::Array(Rc::new(vec![Type::unsigned(bit_size)]), result_constants.len());
-    dfg.make_array(result_constants, typ)
+    limbs
 }
 
 /// The possible return values for Instruction::return_types
@@ -557,7 +611,7 @@ pub(crate) enum InstructionResultType {
 /// Since our IR needs to be in SSA form, it makes sense
 /// to split up instructions like this, as we are sure that these instructions
 /// will not be in the list of instructions for a basic block.
-#[derive(Debug, PartialEq, Eq, Hash, Clone)]
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Serialize, Deserialize)]
 pub(crate) enum TerminatorInstruction {
     /// Control flow
     ///
@@ -641,7 +695,7 @@ impl TerminatorInstruction {
 }
 
 /// A binary instruction in the IR.
-#[derive(Debug, PartialEq, Eq, Hash, Clone)]
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Serialize, Deserialize)]
 pub(crate) struct Binary {
     /// Left hand side of the binary operation
     pub(crate) lhs: ValueId,
@@ -744,7 +798,7 @@ impl Binary {
             }
             BinaryOp::Xor => {
                 if dfg.resolve(self.lhs) == dfg.resolve(self.rhs) {
-                    let zero = dfg.make_constant(FieldElement::zero(), Type::bool());
+                    let zero = dfg.make_constant(FieldElement::zero(), operand_type);
                     return SimplifyResult::SimplifiedTo(zero);
                 }
             }
@@ -800,6 +854,19 @@ fn truncate(int: u128, bit_size: u32) -> u128 {
 }
 
 impl BinaryOp {
+    /// Returns true if swapping the operands of this operator does not change its result.
+    pub(crate) fn is_commutative(self) -> bool {
+        matches!(
+            self,
+            BinaryOp::Add
+                | BinaryOp::Mul
+                | BinaryOp::Eq
+                | BinaryOp::And
+                | BinaryOp::Or
+                | BinaryOp::Xor
+        )
+    }
+
     fn get_field_function(self) -> Option<fn(FieldElement, FieldElement) -> FieldElement> {
         match self {
             BinaryOp::Add => Some(std::ops::Add::add),
@@ -843,7 +910,7 @@ impl BinaryOp {
 /// All binary operators are also only for numeric types. To implement
 /// e.g. equality for a compound type like a struct, one must add a
 /// separate Eq operation for each field and combine them later with And.
-#[derive(Debug, PartialEq, Eq, Hash, Copy, Clone)]
+#[derive(Debug, PartialEq, Eq, Hash, Copy, Clone, Serialize, Deserialize)]
 pub(crate) enum BinaryOp {
     /// Addition of lhs + rhs.
     Add,
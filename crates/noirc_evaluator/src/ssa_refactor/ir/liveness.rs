@@ -0,0 +1,257 @@
+//! Liveness analysis for the variables of a single function.
+//!
+//! A variable is a block parameter or an instruction result. It is live at a point within the
+//! function if it is used along some path from that point before the function returns. Constants,
+//! functions, and intrinsics are never live, but a constant array is treated as a use of each
+//! variable it contains.
+//!
+//! Liveness is computed with a backward dataflow analysis over the reachable blocks:
+//! - The variables live into a block are those it uses without defining, along with those live
+//!   out of it which it does not define.
+//! - The variables live out of a block are those live into any of its successors.
+//!
+//! Since block parameters are defined by the block itself, they are never live into their block.
+//! The arguments of a jump are instead used by the terminator of the block jumping.
+//!
+//! From the variables live out of each block, a walk back through the block finds the last use
+//! of each variable which dies within it, e.g. so that the register holding the variable can be
+//! reused afterward.
+use std::collections::{HashMap, HashSet};
+
+use super::{
+    basic_block::BasicBlockId,
+    cfg::ControlFlowGraph,
+    dfg::DataFlowGraph,
+    function::Function,
+    instruction::InstructionId,
+    post_order::PostOrder,
+    value::{Value, ValueId},
+};
+
+/// The variables live into and out of each reachable block of a function, along with the
+/// variables whose last use is each instruction or terminator.
+pub(crate) struct VariableLiveness {
+    live_in: HashMap<BasicBlockId, HashSet<ValueId>>,
+    live_out: HashMap<BasicBlockId, HashSet<ValueId>>,
+    last_uses: HashMap<InstructionId, HashSet<ValueId>>,
+    terminator_last_uses: HashMap<BasicBlockId, HashSet<ValueId>>,
+}
+
+impl VariableLiveness {
+    /// Computes the liveness of each variable within the given function.
+    pub(crate) fn with_function(function: &Function) -> Self {
+        let cfg = ControlFlowGraph::with_function(function);
+        let post_order = PostOrder::with_function(function);
+        Self::with_cfg_and_post_order(function, &cfg, &post_order)
+    }
+
+    /// Computes the liveness of each variable within the given function from a pre-computed
+    /// control flow graph and post-order.
+    pub(crate) fn with_cfg_and_post_order(
+        function: &Function,
+        cfg: &ControlFlowGraph,
+        post_order: &PostOrder,
+    ) -> Self {
+        let dfg = &function.dfg;
+        let blocks = post_order.as_slice();
+
+        let mut defined = HashMap::new();
+        let mut used = HashMap::new();
+        for block in blocks {
+            let (block_defined, block_used) = block_definitions_and_uses(dfg, *block);
+            defined.insert(*block, block_defined);
+            used.insert(*block, block_used);
+        }
+
+        let mut liveness = Self {
+            live_in: blocks.iter().map(|block| (*block, used[block].clone())).collect(),
+            live_out: blocks.iter().map(|block| (*block, HashSet::new())).collect(),
+            last_uses: HashMap::new(),
+            terminator_last_uses: HashMap::new(),
+        };
+
+        // Visiting blocks in post-order visits most successors before their predecessors.
+        // Loops require further iterations until no block's liveness changes.
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for block in blocks {
+                let mut live_out = HashSet::new();
+                for successor in cfg.successors(*block) {
+                    live_out.extend(liveness.live_in[&successor].iter().copied());
+                }
+
+                let live_in = live_out.iter().filter(|value| !defined[block].contains(*value));
+                let live_in: Vec<_> = live_in.copied().collect();
+                let block_live_in = liveness.live_in.get_mut(block).expect("Block is reachable");
+                for value in live_in {
+                    changed |= block_live_in.insert(value);
+                }
+                liveness.live_out.insert(*block, live_out);
+            }
+        }
+
+        for block in blocks {
+            liveness.compute_last_uses(dfg, *block);
+        }
+        liveness
+    }
+
+    /// Returns the variables live on entry to the given reachable block.
+    pub(crate) fn live_in(&self, block: BasicBlockId) -> &HashSet<ValueId> {
+        self.live_in.get(&block).expect("ICE: Liveness is only computed for reachable blocks")
+    }
+
+    /// Returns the variables live on exit from the given reachable block.
+    pub(crate) fn live_out(&self, block: BasicBlockId) -> &HashSet<ValueId> {
+        self.live_out.get(&block).expect("ICE: Liveness is only computed for reachable blocks")
+    }
+
+    /// Returns the variables which are used for the last time by the given instruction.
+    pub(crate) fn last_uses(&self, instruction: InstructionId) -> Option<&HashSet<ValueId>> {
+        self.last_uses.get(&instruction)
+    }
+
+    /// Returns the variables which are used for the last time by the terminator of the given
+    /// block.
+    pub(crate) fn terminator_last_uses(&self, block: BasicBlockId) -> Option<&HashSet<ValueId>> {
+        self.terminator_last_uses.get(&block)
+    }
+
+    /// Walks backward through the given block from the variables live out of it, recording the
+    /// instruction or terminator where each variable which dies within the block is last used.
+    fn compute_last_uses(&mut self, dfg: &DataFlowGraph, block: BasicBlockId) {
+        let mut live = self.live_out[&block].clone();
+
+        if let Some(terminator) = dfg[block].terminator() {
+            let mut dying = HashSet::new();
+            terminator.for_each_value(|value| {
+                for_each_variable(dfg, value, &mut |variable| {
+                    if live.insert(variable) {
+                        dying.insert(variable);
+                    }
+                });
+            });
+            if !dying.is_empty() {
+                self.terminator_last_uses.insert(block, dying);
+            }
+        }
+
+        for instruction in dfg[block].instructions().iter().rev() {
+            let mut dying = HashSet::new();
+            dfg[*instruction].for_each_value(|value| {
+                for_each_variable(dfg, value, &mut |variable| {
+                    if live.insert(variable) {
+                        dying.insert(variable);
+                    }
+                });
+            });
+            if !dying.is_empty() {
+                self.last_uses.insert(*instruction, dying);
+            }
+        }
+    }
+}
+
+/// Returns the variables defined within the given block, along with the variables it uses
+/// which are defined elsewhere.
+fn block_definitions_and_uses(
+    dfg: &DataFlowGraph,
+    block: BasicBlockId,
+) -> (HashSet<ValueId>, HashSet<ValueId>) {
+    let mut defined: HashSet<ValueId> = dfg.block_parameters(block).iter().copied().collect();
+    let mut used = HashSet::new();
+
+    let mut use_value = |defined: &HashSet<ValueId>, value| {
+        for_each_variable(dfg, value, &mut |variable| {
+            if !defined.contains(&variable) {
+                used.insert(variable);
+            }
+        });
+    };
+
+    for instruction in dfg[block].instructions() {
+        dfg[*instruction].for_each_value(|value| use_value(&defined, value));
+        defined.extend(dfg.instruction_results(*instruction).iter().copied());
+    }
+    if let Some(terminator) = dfg[block].terminator() {
+        terminator.for_each_value(|value| use_value(&defined, value));
+    }
+    (defined, used)
+}
+
+/// Applies the given function to the given value if it is a variable, or to each variable
+/// within it if it is a constant array.
+fn for_each_variable(dfg: &DataFlowGraph, value: ValueId, f: &mut impl FnMut(ValueId)) {
+    let value = dfg.resolve(value);
+    match &dfg[value] {
+        Value::Instruction { .. } | Value::Param { .. } => f(value),
+        Value::Array { array, .. } => {
+            for element in array {
+                for_each_variable(dfg, *element, f);
+            }
+        }
+        Value::NumericConstant { .. }
+        | Value::Function(_)
+        | Value::Intrinsic(_)
+        | Value::ForeignFunction(_) => (),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::HashSet, str::FromStr};
+
+    use crate::ssa_refactor::{
+        ir::{map::Id, value::ValueId},
+        ssa_gen::Ssa,
+    };
+
+    use super::VariableLiveness;
+
+    #[test]
+    fn computes_liveness_across_loops() {
+        let src = "
+            acir fn main f0 {
+              b0(v0: u32):
+                v1 = add v0, u32 1
+                jmp b1(u32 0)
+              b1(v2: u32):
+                v3 = lt v2, u32 10
+                jmpif v3 then: b2, else: b3
+              b2():
+                v4 = add v2, v1
+                jmp b1(v4)
+              b3():
+                return v1
+            }
+        ";
+        let ssa = Ssa::from_str(src).unwrap();
+        let function = ssa.main();
+        let dfg = &function.dfg;
+        let liveness = VariableLiveness::with_function(function);
+
+        let [b0, b1, b2, b3] = [0, 1, 2, 3].map(Id::test_new);
+        let v0 = function.parameters()[0];
+        let v2 = dfg.block_parameters(b1)[0];
+        let result = |block, index| dfg.instruction_results(dfg[block].instructions()[index])[0];
+        let (v1, v3, v4) = (result(b0, 0), result(b1, 0), result(b2, 0));
+        let set = |values: &[ValueId]| values.iter().copied().collect::<HashSet<_>>();
+
+        assert_eq!(liveness.live_in(b0), &set(&[]));
+        assert_eq!(liveness.live_out(b0), &set(&[v1]));
+        assert_eq!(liveness.live_in(b1), &set(&[v1]));
+        assert_eq!(liveness.live_in(b2), &set(&[v1, v2]));
+        assert_eq!(liveness.live_out(b2), &set(&[v1]));
+        assert_eq!(liveness.live_in(b3), &set(&[v1]));
+        assert_eq!(liveness.live_out(b3), &set(&[]));
+
+        // v1 remains live throughout the loop, so it is only last used by the return
+        assert_eq!(liveness.last_uses(dfg[b0].instructions()[0]), Some(&set(&[v0])));
+        assert_eq!(liveness.last_uses(dfg[b1].instructions()[0]), None);
+        assert_eq!(liveness.terminator_last_uses(b1), Some(&set(&[v3])));
+        assert_eq!(liveness.last_uses(dfg[b2].instructions()[0]), Some(&set(&[v2])));
+        assert_eq!(liveness.terminator_last_uses(b2), Some(&set(&[v4])));
+        assert_eq!(liveness.terminator_last_uses(b3), Some(&set(&[v1])));
+    }
+}
@@ -0,0 +1,262 @@
+//! Natural loop analysis for a single function.
+//!
+//! A natural loop is identified by its back-edges: an edge `n -> h` where `h` dominates `n`.
+//! The block `h` is the loop's header and `n` is one of its latches. The body of the loop is every
+//! block which can reach a latch without going through the header, along with the header itself.
+//! Back-edges sharing a header form a single loop.
+//!
+//! The loops of a function form a forest: two natural loops with distinct headers are either
+//! disjoint or one is nested entirely within the other.
+use std::collections::{HashMap, HashSet};
+
+use super::{
+    basic_block::BasicBlockId, cfg::ControlFlowGraph, dom::DominatorTree, function::Function,
+    post_order::PostOrder,
+};
+
+/// Identifies a loop by its index within a LoopForest
+pub(crate) type LoopId = usize;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Loop {
+    /// The header block of a loop is the block which dominates all the
+    /// other blocks in the loop.
+    pub(crate) header: BasicBlockId,
+
+    /// The blocks at the end of the loop which jump back to the header and restart the loop.
+    /// Each `latch -> header` edge is a back-edge. These are sorted by block id.
+    pub(crate) latches: Vec<BasicBlockId>,
+
+    /// All the blocks contained within the loop, including `header` and each latch.
+    /// This also includes the blocks of any loops nested within this one.
+    pub(crate) blocks: HashSet<BasicBlockId>,
+
+    /// The innermost loop containing this loop, if any.
+    pub(crate) parent: Option<LoopId>,
+
+    /// The number of loops this loop is nested within, plus one.
+    /// Outermost loops have a depth of 1.
+    pub(crate) depth: usize,
+}
+
+impl Loop {
+    /// Returns the block which jumps back to the header, if the loop has exactly one back-edge.
+    pub(crate) fn single_latch(&self) -> Option<BasicBlockId> {
+        match self.latches.as_slice() {
+            [latch] => Some(*latch),
+            _ => None,
+        }
+    }
+}
+
+/// Every natural loop within a function.
+pub(crate) struct LoopForest {
+    /// Each loop, ordered such that each loop comes before any loop containing it.
+    loops: Vec<Loop>,
+
+    /// Maps each block within a loop to the innermost loop containing it
+    innermost_loops: HashMap<BasicBlockId, LoopId>,
+}
+
+impl LoopForest {
+    /// Computes the loops of the given function.
+    pub(crate) fn with_function(function: &Function) -> Self {
+        let cfg = ControlFlowGraph::with_function(function);
+        let post_order = PostOrder::with_function(function);
+        let mut dom_tree = DominatorTree::with_cfg_and_post_order(&cfg, &post_order);
+        Self::with_cfg_and_dom_tree(&cfg, &post_order, &mut dom_tree)
+    }
+
+    /// Computes the loops of a function from its pre-computed control flow graph,
+    /// post-order, and dominator tree.
+    pub(crate) fn with_cfg_and_dom_tree(
+        cfg: &ControlFlowGraph,
+        post_order: &PostOrder,
+        dom_tree: &mut DominatorTree,
+    ) -> Self {
+        // Map each loop header to its latches. Only reachable blocks are within the post-order.
+        let mut latches: HashMap<BasicBlockId, Vec<BasicBlockId>> = HashMap::new();
+        for block in post_order.as_slice() {
+            for predecessor in cfg.predecessors(*block) {
+                if dom_tree.is_reachable(predecessor) && dom_tree.dominates(*block, predecessor) {
+                    latches.entry(*block).or_default().push(predecessor);
+                }
+            }
+        }
+
+        let mut loops = latches
+            .into_iter()
+            .map(|(header, mut latches)| {
+                latches.sort();
+                let blocks = find_blocks_in_loop(header, &latches, cfg);
+                Loop { header, latches, blocks, parent: None, depth: 1 }
+            })
+            .collect::<Vec<_>>();
+
+        // A nested loop always has fewer blocks than the loop containing it, so sorting by size
+        // places inner loops first. The header is used to keep the order deterministic.
+        loops.sort_by_key(|loop_| (loop_.blocks.len(), loop_.header));
+
+        // The parent of each loop is the smallest larger loop containing its header
+        for index in 0..loops.len() {
+            let header = loops[index].header;
+            loops[index].parent =
+                (index + 1..loops.len()).find(|outer| loops[*outer].blocks.contains(&header));
+        }
+
+        // Parents come after their children, so visit loops outermost first to compute depths
+        for index in (0..loops.len()).rev() {
+            if let Some(parent) = loops[index].parent {
+                loops[index].depth = loops[parent].depth + 1;
+            }
+        }
+
+        // Visiting outer loops first lets inner loops overwrite the entries of their blocks
+        let mut innermost_loops = HashMap::new();
+        for (index, loop_) in loops.iter().enumerate().rev() {
+            for block in &loop_.blocks {
+                innermost_loops.insert(*block, index);
+            }
+        }
+
+        Self { loops, innermost_loops }
+    }
+
+    /// Returns each loop, ordered such that each loop comes before any loop containing it.
+    pub(crate) fn loops(&self) -> &[Loop] {
+        &self.loops
+    }
+
+    /// Consumes the forest, returning each loop ordered from innermost to outermost.
+    pub(crate) fn into_loops(self) -> Vec<Loop> {
+        self.loops
+    }
+
+    pub(crate) fn get(&self, id: LoopId) -> &Loop {
+        &self.loops[id]
+    }
+
+    /// Returns the innermost loop containing the given block, if any.
+    pub(crate) fn innermost_loop(&self, block: BasicBlockId) -> Option<LoopId> {
+        self.innermost_loops.get(&block).copied()
+    }
+
+    /// Returns the number of loops containing the given block.
+    pub(crate) fn loop_depth(&self, block: BasicBlockId) -> usize {
+        self.innermost_loop(block).map_or(0, |loop_| self.loops[loop_].depth)
+    }
+
+    /// Returns true if the given block is the header of a loop.
+    pub(crate) fn is_loop_header(&self, block: BasicBlockId) -> bool {
+        self.innermost_loop(block).map_or(false, |loop_| self.loops[loop_].header == block)
+    }
+}
+
+/// Return each block that is in a loop starting in the given header block.
+/// Expects each `latch -> header` edge to be a back-edge of the loop.
+fn find_blocks_in_loop(
+    header: BasicBlockId,
+    latches: &[BasicBlockId],
+    cfg: &ControlFlowGraph,
+) -> HashSet<BasicBlockId> {
+    let mut blocks = HashSet::new();
+    blocks.insert(header);
+
+    // Starting from the back edges of the loop, each predecessor of these blocks until
+    // the header is within the loop.
+    let mut stack = vec![];
+    for latch in latches {
+        if blocks.insert(*latch) {
+            stack.push(*latch);
+        }
+    }
+
+    while let Some(block) = stack.pop() {
+        for predecessor in cfg.predecessors(block) {
+            if blocks.insert(predecessor) {
+                stack.push(predecessor);
+            }
+        }
+    }
+
+    blocks
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use crate::ssa_refactor::{
+        ir::{function::RuntimeType, map::Id, types::Type},
+        ssa_builder::FunctionBuilder,
+    };
+
+    use super::LoopForest;
+
+    #[test]
+    fn finds_nested_loops() {
+        // fn main f0 {
+        //   b0(v0: u1):
+        //     jmp b1()
+        //   b1():                        // header of outer loop
+        //     jmpif v0 then: b2, else: b5
+        //   b2():                        // header of inner loop
+        //     jmpif v0 then: b3, else: b4
+        //   b3():
+        //     jmp b2()
+        //   b4():
+        //     jmp b1()
+        //   b5():
+        //     return
+        // }
+        let main_id = Id::test_new(0);
+        let mut builder = FunctionBuilder::new("main".into(), main_id, RuntimeType::Acir);
+        let v0 = builder.add_parameter(Type::bool());
+
+        let b1 = builder.insert_block();
+        let b2 = builder.insert_block();
+        let b3 = builder.insert_block();
+        let b4 = builder.insert_block();
+        let b5 = builder.insert_block();
+
+        builder.terminate_with_jmp(b1, vec![]);
+        builder.switch_to_block(b1);
+        builder.terminate_with_jmpif(v0, b2, b5);
+        builder.switch_to_block(b2);
+        builder.terminate_with_jmpif(v0, b3, b4);
+        builder.switch_to_block(b3);
+        builder.terminate_with_jmp(b2, vec![]);
+        builder.switch_to_block(b4);
+        builder.terminate_with_jmp(b1, vec![]);
+        builder.switch_to_block(b5);
+        builder.terminate_with_return(vec![]);
+
+        let ssa = builder.finish();
+        let main = ssa.main();
+        let forest = LoopForest::with_function(main);
+
+        let loops = forest.loops();
+        assert_eq!(loops.len(), 2);
+
+        let (inner, outer) = (&loops[0], &loops[1]);
+        assert_eq!(inner.header, b2);
+        assert_eq!(inner.single_latch(), Some(b3));
+        assert_eq!(inner.blocks, HashSet::from([b2, b3]));
+        assert_eq!(inner.parent, Some(1));
+        assert_eq!(inner.depth, 2);
+
+        assert_eq!(outer.header, b1);
+        assert_eq!(outer.single_latch(), Some(b4));
+        assert_eq!(outer.blocks, HashSet::from([b1, b2, b3, b4]));
+        assert_eq!(outer.parent, None);
+        assert_eq!(outer.depth, 1);
+
+        assert_eq!(forest.loop_depth(main.entry_block()), 0);
+        assert_eq!(forest.loop_depth(b4), 1);
+        assert_eq!(forest.loop_depth(b3), 2);
+        assert_eq!(forest.innermost_loop(b3), Some(0));
+        assert_eq!(forest.innermost_loop(b5), None);
+        assert!(forest.is_loop_header(b2));
+        assert!(!forest.is_loop_header(b3));
+    }
+}
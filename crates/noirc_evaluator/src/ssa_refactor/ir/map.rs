@@ -4,6 +4,8 @@ use std::{
     sync::atomic::{AtomicUsize, Ordering},
 };
 
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
 /// A unique ID corresponding to a value of type T.
 /// This type can be used to retrieve a value of type T from
 /// either a DenseMap<T> or SparseMap<T>.
@@ -86,6 +88,19 @@ impl<T> std::fmt::Debug for Id<T> {
     }
 }
 
+// Ids are serialized as their index alone, for the same reason as above.
+impl<T> Serialize for Id<T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.index.serialize(serializer)
+    }
+}
+
+impl<'de, T> Deserialize<'de> for Id<T> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        usize::deserialize(deserializer).map(Self::new)
+    }
+}
+
 impl std::fmt::Display for Id<super::basic_block::BasicBlock> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(f, "b{}", self.index)
@@ -115,7 +130,7 @@ impl std::fmt::Display for Id<super::instruction::Instruction> {
 /// access to indices is provided. Since IDs must be stable and correspond
 /// to indices in the internal Vec, operations that would change element
 /// ordering like pop, remove, swap_remove, etc, are not possible.
-#[derive(Debug)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub(crate) struct DenseMap<T> {
     storage: Vec<T>,
 }
@@ -324,3 +339,16 @@ impl<T> Default for AtomicCounter<T> {
         Self { next: Default::default(), _marker: Default::default() }
     }
 }
+
+impl<T> Serialize for AtomicCounter<T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.next.load(Ordering::Relaxed).serialize(serializer)
+    }
+}
+
+impl<'de, T> Deserialize<'de> for AtomicCounter<T> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let next = usize::deserialize(deserializer)?;
+        Ok(Self { next: AtomicUsize::new(next), _marker: Default::default() })
+    }
+}
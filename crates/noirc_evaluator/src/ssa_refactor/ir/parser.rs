@@ -0,0 +1,967 @@
+//! This file is for parsing the textual SSA format produced by the SSA printer back into SSA.
+//!
+//! This lets tests be written in terms of the printed SSA rather than building each function by
+//! hand with the FunctionBuilder. For example:
+//!
+//! ```text
+//! acir fn main f0 {
+//!   b0(v0: Field):
+//!     v1 = add v0, Field 1
+//!     return v1
+//! }
+//! ```
+//!
+//! Values and blocks are given fresh ids in the order they are defined in the source, so the
+//! names used within the source only need to be consistent with each other. Since the printer
+//! visits blocks in a fixed order, parsing and re-printing two programs which only differ in their
+//! value and block names results in identical output. Function ids are kept when possible.
+//!
+//! Instructions are inserted exactly as they are written and are never simplified.
+use std::{collections::HashMap, rc::Rc, str::FromStr};
+
+use acvm::FieldElement;
+use num_bigint::BigUint;
+use thiserror::Error;
+
+use crate::ssa_refactor::ssa_gen::Ssa;
+
+use super::{
+    basic_block::BasicBlockId,
+    function::{Function, FunctionId, RuntimeType},
+    instruction::{Binary, BinaryOp, Instruction, Intrinsic, TerminatorInstruction},
+    map::AtomicCounter,
+    types::Type,
+    value::ValueId,
+};
+
+#[derive(Debug, Error, PartialEq, Eq)]
+#[error("Error parsing SSA on line {line}: {message}")]
+pub(crate) struct SsaParseError {
+    pub(crate) line: usize,
+    pub(crate) message: String,
+}
+
+impl FromStr for Ssa {
+    type Err = SsaParseError;
+
+    fn from_str(src: &str) -> Result<Ssa, SsaParseError> {
+        parse_ssa(src)
+    }
+}
+
+/// Parses the given textual SSA into an Ssa program.
+/// The first function within the source is expected to be the main function.
+pub(crate) fn parse_ssa(src: &str) -> Result<Ssa, SsaParseError> {
+    let mut lines = Vec::new();
+    for (index, line) in src.lines().enumerate() {
+        let line = Line::tokenize(line, index + 1)?;
+        if !line.tokens.is_empty() {
+            lines.push(line);
+        }
+    }
+
+    // Split the source into each function's header and body
+    let mut function_sources = Vec::new();
+    let mut remaining = lines.as_slice();
+    while let Some((header, rest)) = remaining.split_first() {
+        let end = rest
+            .iter()
+            .position(|line| line.tokens == [Token::RightBrace])
+            .ok_or_else(|| header.error("Expected a closing '}' for this function"))?;
+
+        function_sources.push((header, &rest[..end]));
+        remaining = &rest[end + 1..];
+    }
+
+    if function_sources.is_empty() {
+        return Err(SsaParseError { line: 1, message: "Expected at least 1 function".into() });
+    }
+
+    let headers = function_sources
+        .iter()
+        .map(|(header, _)| FunctionHeader::parse(header))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let function_ids = assign_function_ids(&headers, &function_sources)?;
+
+    let mut functions = Vec::with_capacity(headers.len());
+    for (header, (_, body)) in headers.into_iter().zip(&function_sources) {
+        let id = function_ids[&header.id];
+        let context = FunctionContext::new(header, id, body, &function_ids)?;
+        functions.push(context.parse_body(body)?);
+    }
+
+    Ok(Ssa::new(functions))
+}
+
+/// Asserts that the given SSA is equal to the expected SSA source,
+/// ignoring any differences in the names of values and blocks.
+#[cfg(test)]
+pub(crate) fn assert_ssa_equals(ssa: &Ssa, expected: &str) {
+    let normalize = |src: &str| match parse_ssa(src) {
+        Ok(ssa) => ssa.to_string(),
+        Err(error) => panic!("{error}\n{src}"),
+    };
+    assert_eq!(normalize(&ssa.to_string()), normalize(expected));
+}
+
+/// Maps each function name (e.g. `f1`) to a fresh FunctionId.
+/// Ids are kept as written as long as the functions are in ascending order of id.
+fn assign_function_ids(
+    headers: &[FunctionHeader],
+    function_sources: &[(&Line, &[Line])],
+) -> Result<HashMap<String, FunctionId>, SsaParseError> {
+    let counter = AtomicCounter::default();
+    let mut function_ids = HashMap::new();
+
+    for (header, (header_line, _)) in headers.iter().zip(function_sources) {
+        let index = parse_id(&header.id, 'f')
+            .ok_or_else(|| header_line.error(format!("Invalid function id '{}'", header.id)))?;
+
+        let mut id = counter.next();
+        while id.to_usize() < index {
+            id = counter.next();
+        }
+
+        if function_ids.insert(header.id.clone(), id).is_some() {
+            return Err(header_line.error(format!("Function '{}' is defined twice", header.id)));
+        }
+    }
+
+    Ok(function_ids)
+}
+
+/// The first line of a function, e.g. `acir fn main f0 {`
+struct FunctionHeader {
+    runtime: RuntimeType,
+    name: String,
+    id: String,
+}
+
+impl FunctionHeader {
+    fn parse(line: &Line) -> Result<Self, SsaParseError> {
+        let mut parser = line.parser();
+
+        let runtime = match parser.expect_ident()?.as_str() {
+            "acir" => RuntimeType::Acir,
+            "brillig" => RuntimeType::Brillig,
+            other => return Err(line.error(format!("Unknown runtime '{other}'"))),
+        };
+        parser.expect_keyword("fn")?;
+        let name = parser.expect_ident()?;
+        let id = parser.expect_ident()?;
+        parser.expect(Token::LeftBrace)?;
+        parser.expect_end()?;
+
+        Ok(FunctionHeader { runtime, name, id })
+    }
+}
+
+/// The context for parsing the body of a single function
+struct FunctionContext<'a> {
+    function: Function,
+    function_ids: &'a HashMap<String, FunctionId>,
+    blocks: HashMap<String, BasicBlockId>,
+    values: HashMap<String, ValueId>,
+}
+
+impl<'a> FunctionContext<'a> {
+    /// Creates the function along with each of its blocks. Blocks are created up front since
+    /// they may be jumped to before they are defined.
+    fn new(
+        header: FunctionHeader,
+        id: FunctionId,
+        body: &[Line],
+        function_ids: &'a HashMap<String, FunctionId>,
+    ) -> Result<Self, SsaParseError> {
+        let mut function = Function::new(header.name, id);
+        function.set_runtime(header.runtime);
+
+        let mut blocks = HashMap::new();
+        for line in body.iter().filter(|line| line.is_block_label()) {
+            let name = line.parser().expect_ident()?;
+
+            let block =
+                if blocks.is_empty() { function.entry_block() } else { function.dfg.make_block() };
+
+            if blocks.insert(name.clone(), block).is_some() {
+                return Err(line.error(format!("Block '{name}' is defined twice")));
+            }
+        }
+
+        Ok(FunctionContext { function, function_ids, blocks, values: HashMap::new() })
+    }
+
+    fn parse_body(mut self, body: &[Line]) -> Result<Function, SsaParseError> {
+        let mut current_block = None;
+
+        for line in body {
+            let mut parser = line.parser();
+
+            if line.is_block_label() {
+                if let Some(block) = current_block {
+                    self.expect_terminated(block, line)?;
+                }
+                current_block = Some(self.parse_block_label(&mut parser)?);
+                continue;
+            }
+
+            let block = current_block
+                .ok_or_else(|| line.error("Expected a block label before the first instruction"))?;
+
+            if self.function.dfg[block].terminator().is_some() {
+                return Err(line.error("Unexpected instruction after the block's terminator"));
+            }
+
+            match parser.peek() {
+                Some(Token::Ident(name)) if matches!(name.as_str(), "jmp" | "jmpif" | "return") => {
+                    let terminator = self.parse_terminator(&mut parser)?;
+                    self.function.dfg[block].set_terminator(terminator);
+                }
+                _ => self.parse_instruction(&mut parser, block)?,
+            }
+            parser.expect_end()?;
+        }
+
+        match (current_block, body.last()) {
+            (Some(block), Some(line)) => self.expect_terminated(block, line)?,
+            _ => return Err(SsaParseError { line: 1, message: "Function has no blocks".into() }),
+        }
+
+        Ok(self.function)
+    }
+
+    fn expect_terminated(&self, block: BasicBlockId, line: &Line) -> Result<(), SsaParseError> {
+        if self.function.dfg[block].terminator().is_none() {
+            return Err(line.error("Expected the previous block to end in a terminator"));
+        }
+        Ok(())
+    }
+
+    /// Parses a block label along with its parameters, e.g. `b1(v0: Field, v1: u32):`
+    fn parse_block_label(
+        &mut self,
+        parser: &mut LineParser,
+    ) -> Result<BasicBlockId, SsaParseError> {
+        let block = self.parse_block(parser)?;
+        parser.expect(Token::LeftParen)?;
+
+        if !parser.eat(&Token::RightParen) {
+            loop {
+                let name = parser.expect_ident()?;
+                parser.expect(Token::Colon)?;
+                let typ = parser.parse_type()?;
+
+                let parameter = self.function.dfg.add_block_parameter(block, typ);
+                self.define_value(parser, name, parameter)?;
+
+                if !parser.eat(&Token::Comma) {
+                    break;
+                }
+            }
+            parser.expect(Token::RightParen)?;
+        }
+
+        parser.expect(Token::Colon)?;
+        Ok(block)
+    }
+
+    fn parse_terminator(
+        &mut self,
+        parser: &mut LineParser,
+    ) -> Result<TerminatorInstruction, SsaParseError> {
+        match parser.expect_ident()?.as_str() {
+            "jmp" => {
+                let destination = self.parse_block(parser)?;
+                parser.expect(Token::LeftParen)?;
+                let arguments = self.parse_value_list(parser, &Token::RightParen)?;
+                parser.expect(Token::RightParen)?;
+                Ok(TerminatorInstruction::Jmp { destination, arguments })
+            }
+            "jmpif" => {
+                let condition = self.parse_value(parser)?;
+                parser.expect_keyword("then")?;
+                parser.expect(Token::Colon)?;
+                let then_destination = self.parse_block(parser)?;
+                parser.expect(Token::Comma)?;
+                parser.expect_keyword("else")?;
+                parser.expect(Token::Colon)?;
+                let else_destination = self.parse_block(parser)?;
+                Ok(TerminatorInstruction::JmpIf { condition, then_destination, else_destination })
+            }
+            _ => {
+                let return_values = if parser.is_at_end() {
+                    Vec::new()
+                } else {
+                    self.parse_value_list(parser, &Token::RightParen)?
+                };
+                Ok(TerminatorInstruction::Return { return_values })
+            }
+        }
+    }
+
+    /// Parses an instruction along with its results, e.g. `v2 = add v0, v1`,
+    /// and inserts it at the end of the given block.
+    fn parse_instruction(
+        &mut self,
+        parser: &mut LineParser,
+        block: BasicBlockId,
+    ) -> Result<(), SsaParseError> {
+        let mut result_names = Vec::new();
+        if parser.tokens.contains(&Token::Equals) {
+            loop {
+                result_names.push(parser.expect_ident()?);
+                if !parser.eat(&Token::Comma) {
+                    break;
+                }
+            }
+            parser.expect(Token::Equals)?;
+        }
+
+        let instruction = match parser.expect_ident()?.as_str() {
+            "cast" => {
+                let value = self.parse_value(parser)?;
+                parser.expect_keyword("as")?;
+                Instruction::Cast(value, parser.parse_type()?)
+            }
+            "not" => Instruction::Not(self.parse_value(parser)?),
+            "truncate" => {
+                let value = self.parse_value(parser)?;
+                parser.expect_keyword("to")?;
+                let bit_size = parser.expect_int()?;
+                parser.expect_keyword("bits")?;
+                parser.expect(Token::Comma)?;
+                parser.expect_keyword("max_bit_size")?;
+                parser.expect(Token::Colon)?;
+                let max_bit_size = parser.expect_int()?;
+                Instruction::Truncate { value, bit_size, max_bit_size }
+            }
+            "constrain" => {
+                let value = self.parse_value(parser)?;
+                let message =
+                    if parser.eat(&Token::Comma) { Some(parser.expect_string()?) } else { None };
+                Instruction::Constrain(value, message)
+            }
+            "call" => {
+                let func = self.parse_value(parser)?;
+                parser.expect(Token::LeftParen)?;
+                let arguments = self.parse_value_list(parser, &Token::RightParen)?;
+                parser.expect(Token::RightParen)?;
+                Instruction::Call { func, arguments }
+            }
+            "allocate" => Instruction::Allocate,
+            "load" => Instruction::Load { address: self.parse_value(parser)? },
+            "store" => {
+                let value = self.parse_value(parser)?;
+                parser.expect_keyword("at")?;
+                let address = self.parse_value(parser)?;
+                Instruction::Store { address, value }
+            }
+            "enable_side_effects" => {
+                Instruction::EnableSideEffects { condition: self.parse_value(parser)? }
+            }
+            "array_get" => {
+                let array = self.parse_value(parser)?;
+                parser.expect(Token::Comma)?;
+                parser.expect_keyword("index")?;
+                let index = self.parse_value(parser)?;
+                Instruction::ArrayGet { array, index }
+            }
+            "array_set" => {
+                let array = self.parse_value(parser)?;
+                parser.expect(Token::Comma)?;
+                parser.expect_keyword("index")?;
+                let index = self.parse_value(parser)?;
+                parser.expect(Token::Comma)?;
+                parser.expect_keyword("value")?;
+                let value = self.parse_value(parser)?;
+                Instruction::ArraySet { array, index, value }
+            }
+            other => match parse_binary_op(other) {
+                Some(operator) => {
+                    let lhs = self.parse_value(parser)?;
+                    parser.expect(Token::Comma)?;
+                    let rhs = self.parse_value(parser)?;
+                    Instruction::Binary(Binary { lhs, rhs, operator })
+                }
+                None => return Err(parser.error(format!("Unknown instruction '{other}'"))),
+            },
+        };
+
+        // Instructions whose result types can't be inferred list them explicitly: `-> Field`
+        let ctrl_typevars = if parser.eat(&Token::Arrow) {
+            let mut types = vec![parser.parse_type()?];
+            while parser.eat(&Token::Comma) {
+                types.push(parser.parse_type()?);
+            }
+            Some(types)
+        } else {
+            instruction.requires_ctrl_typevars().then(Vec::new)
+        };
+
+        let instruction = self.function.dfg.make_instruction(instruction, ctrl_typevars);
+        self.function.dfg[block].insert_instruction(instruction);
+
+        let results = self.function.dfg.instruction_results(instruction).to_vec();
+        if results.len() != result_names.len() {
+            let message =
+                format!("Expected {} result(s) but found {}", results.len(), result_names.len());
+            return Err(parser.error(message));
+        }
+
+        for (name, result) in result_names.into_iter().zip(results) {
+            self.define_value(parser, name, result)?;
+        }
+        Ok(())
+    }
+
+    /// Parses a comma-separated list of values, stopping at the end of the line
+    /// or before the given closing token.
+    fn parse_value_list(
+        &mut self,
+        parser: &mut LineParser,
+        closing_token: &Token,
+    ) -> Result<Vec<ValueId>, SsaParseError> {
+        let mut values = Vec::new();
+        if parser.is_at_end() || parser.peek() == Some(closing_token) {
+            return Ok(values);
+        }
+
+        loop {
+            values.push(self.parse_value(parser)?);
+            if !parser.eat(&Token::Comma) {
+                return Ok(values);
+            }
+        }
+    }
+
+    /// Parses a single value. This is one of:
+    /// - A previously defined value, e.g. `v3`
+    /// - A numeric constant, e.g. `Field 2` or `u32 7`
+    /// - An array constant, e.g. `[Field; 2] [v1, Field 2]`
+    /// - A function, e.g. `f1`
+    /// - An intrinsic function, e.g. `to_le_bits`
+    /// - A foreign function, which is any other name
+    fn parse_value(&mut self, parser: &mut LineParser) -> Result<ValueId, SsaParseError> {
+        if parser.peek() == Some(&Token::LeftBracket) {
+            let typ = parser.parse_type()?;
+            parser.expect(Token::LeftBracket)?;
+            let elements = self.parse_value_list(parser, &Token::RightBracket)?;
+            parser.expect(Token::RightBracket)?;
+            return Ok(self.function.dfg.make_array(elements.into(), typ));
+        }
+
+        let name = parser.expect_ident()?;
+
+        if let Some(typ @ Type::Numeric(_)) = parse_type_name(&name) {
+            let constant = parser.parse_field_element()?;
+            return Ok(self.function.dfg.make_constant(constant, typ));
+        }
+
+        if let Some(value) = self.values.get(&name) {
+            return Ok(*value);
+        }
+
+        if let Some(function) = self.function_ids.get(&name) {
+            return Ok(self.function.dfg.import_function(*function));
+        }
+
+        if parse_id(&name, 'v').is_some() {
+            return Err(parser.error(format!("Undefined value '{name}'")));
+        }
+
+        if parse_id(&name, 'f').is_some() {
+            return Err(parser.error(format!("Undefined function '{name}'")));
+        }
+
+        Ok(match Intrinsic::lookup(&name) {
+            Some(intrinsic) => self.function.dfg.import_intrinsic(intrinsic),
+            None => self.function.dfg.import_foreign_function(&name),
+        })
+    }
+
+    fn parse_block(&mut self, parser: &mut LineParser) -> Result<BasicBlockId, SsaParseError> {
+        let name = parser.expect_ident()?;
+        self.blocks
+            .get(&name)
+            .copied()
+            .ok_or_else(|| parser.error(format!("Undefined block '{name}'")))
+    }
+
+    fn define_value(
+        &mut self,
+        parser: &LineParser,
+        name: String,
+        value: ValueId,
+    ) -> Result<(), SsaParseError> {
+        if self.values.contains_key(&name) {
+            return Err(parser.error(format!("Value '{name}' is defined twice")));
+        }
+        self.values.insert(name, value);
+        Ok(())
+    }
+}
+
+/// Parses an id of the form `{prefix}{index}`, e.g. `v3`, returning the index
+fn parse_id(name: &str, prefix: char) -> Option<usize> {
+    let index = name.strip_prefix(prefix)?;
+    if index.is_empty() || !index.chars().all(|char| char.is_ascii_digit()) {
+        return None;
+    }
+    index.parse().ok()
+}
+
+/// Parses the name of a non-composite type, e.g. `Field` or `u32`
+fn parse_type_name(name: &str) -> Option<Type> {
+    match name {
+        "Field" => Some(Type::field()),
+        "reference" => Some(Type::Reference),
+        "function" => Some(Type::Function),
+        _ => {
+            if let Some(bit_size) = name.strip_prefix('u') {
+                bit_size.parse().ok().map(Type::unsigned)
+            } else if let Some(bit_size) = name.strip_prefix('i') {
+                bit_size.parse().ok().map(Type::signed)
+            } else {
+                None
+            }
+        }
+    }
+}
+
+fn parse_binary_op(name: &str) -> Option<BinaryOp> {
+    match name {
+        "add" => Some(BinaryOp::Add),
+        "sub" => Some(BinaryOp::Sub),
+        "mul" => Some(BinaryOp::Mul),
+        "div" => Some(BinaryOp::Div),
+        "mod" => Some(BinaryOp::Mod),
+        "eq" => Some(BinaryOp::Eq),
+        "lt" => Some(BinaryOp::Lt),
+        "and" => Some(BinaryOp::And),
+        "or" => Some(BinaryOp::Or),
+        "xor" => Some(BinaryOp::Xor),
+        "shl" => Some(BinaryOp::Shl),
+        "shr" => Some(BinaryOp::Shr),
+        _ => None,
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Token {
+    Ident(String),
+    /// A decimal integer
+    Int(String),
+    /// An integer written in superscript digits, used when printing powers of two, e.g. `2⁶⁴`
+    Superscript(String),
+    /// A string literal, with the escapes of its printed form resolved
+    Str(String),
+    Minus,
+    Times,
+    Arrow,
+    Equals,
+    Comma,
+    Colon,
+    Semicolon,
+    LeftParen,
+    RightParen,
+    LeftBrace,
+    RightBrace,
+    LeftBracket,
+    RightBracket,
+}
+
+impl std::fmt::Display for Token {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Token::Ident(name) => write!(f, "'{name}'"),
+            Token::Int(int) => write!(f, "'{int}'"),
+            Token::Superscript(int) => write!(f, "superscript '{int}'"),
+            Token::Str(string) => write!(f, "{string:?}"),
+            Token::Minus => write!(f, "'-'"),
+            Token::Times => write!(f, "'×'"),
+            Token::Arrow => write!(f, "'->'"),
+            Token::Equals => write!(f, "'='"),
+            Token::Comma => write!(f, "','"),
+            Token::Colon => write!(f, "':'"),
+            Token::Semicolon => write!(f, "';'"),
+            Token::LeftParen => write!(f, "'('"),
+            Token::RightParen => write!(f, "')'"),
+            Token::LeftBrace => write!(f, "'{{'"),
+            Token::RightBrace => write!(f, "'}}'"),
+            Token::LeftBracket => write!(f, "'['"),
+            Token::RightBracket => write!(f, "']'"),
+        }
+    }
+}
+
+/// Each line of the SSA format is parsed separately since the end of a line is
+/// also the end of the instruction, terminator, or block label on that line.
+struct Line {
+    number: usize,
+    tokens: Vec<Token>,
+}
+
+impl Line {
+    fn tokenize(line: &str, number: usize) -> Result<Self, SsaParseError> {
+        let mut tokens = Vec::new();
+        let mut chars = line.chars().peekable();
+
+        while let Some(char) = chars.next() {
+            let token = match char {
+                _ if char.is_whitespace() => continue,
+                '/' if chars.peek() == Some(&'/') => break, // The rest of the line is a comment
+                '-' if chars.peek() == Some(&'>') => {
+                    chars.next();
+                    Token::Arrow
+                }
+                '-' => Token::Minus,
+                '×' => Token::Times,
+                '=' => Token::Equals,
+                ',' => Token::Comma,
+                ':' => Token::Colon,
+                ';' => Token::Semicolon,
+                '(' => Token::LeftParen,
+                ')' => Token::RightParen,
+                '{' => Token::LeftBrace,
+                '}' => Token::RightBrace,
+                '[' => Token::LeftBracket,
+                ']' => Token::RightBracket,
+                _ if char.is_ascii_digit() => {
+                    let mut int = char.to_string();
+                    while let Some(digit) = chars.next_if(char::is_ascii_digit) {
+                        int.push(digit);
+                    }
+                    Token::Int(int)
+                }
+                _ if superscript_digit(char).is_some() => {
+                    let mut int = String::new();
+                    int.extend(superscript_digit(char));
+                    while let Some(digit) = chars.next_if(|char| superscript_digit(*char).is_some())
+                    {
+                        int.extend(superscript_digit(digit));
+                    }
+                    Token::Superscript(int)
+                }
+                '"' => Token::Str(Self::tokenize_string(&mut chars, number)?),
+                _ if char.is_ascii_alphabetic() || char == '_' => {
+                    let mut ident = char.to_string();
+                    while let Some(char) =
+                        chars.next_if(|char| char.is_ascii_alphanumeric() || *char == '_')
+                    {
+                        ident.push(char);
+                    }
+                    Token::Ident(ident)
+                }
+                other => {
+                    let message = format!("Unexpected character '{other}'");
+                    return Err(SsaParseError { line: number, message });
+                }
+            };
+            tokens.push(token);
+        }
+
+        Ok(Line { number, tokens })
+    }
+
+    /// Reads the rest of a string literal whose opening quote has been read. Strings are printed
+    /// with Rust's debug formatting, so the same escapes are accepted.
+    fn tokenize_string(
+        chars: &mut impl Iterator<Item = char>,
+        number: usize,
+    ) -> Result<String, SsaParseError> {
+        let error = |message: &str| SsaParseError { line: number, message: message.to_string() };
+        let mut string = String::new();
+        loop {
+            match chars.next().ok_or_else(|| error("Unterminated string"))? {
+                '"' => return Ok(string),
+                '\\' => match chars.next().ok_or_else(|| error("Unterminated string"))? {
+                    'n' => string.push('\n'),
+                    'r' => string.push('\r'),
+                    't' => string.push('\t'),
+                    '0' => string.push('\0'),
+                    escaped @ ('\\' | '"' | '\'') => string.push(escaped),
+                    other => return Err(error(&format!("Unknown escape '\\{other}'"))),
+                },
+                char => string.push(char),
+            }
+        }
+    }
+
+    /// Returns true if this line begins with a block label, e.g. `b1(`
+    fn is_block_label(&self) -> bool {
+        match self.tokens.as_slice() {
+            [Token::Ident(name), Token::LeftParen, ..] => parse_id(name, 'b').is_some(),
+            _ => false,
+        }
+    }
+
+    fn parser(&self) -> LineParser {
+        LineParser { tokens: &self.tokens, position: 0, line: self.number }
+    }
+
+    fn error(&self, message: impl Into<String>) -> SsaParseError {
+        SsaParseError { line: self.number, message: message.into() }
+    }
+}
+
+fn superscript_digit(char: char) -> Option<char> {
+    match char {
+        '⁰' => Some('0'),
+        '¹' => Some('1'),
+        '²' => Some('2'),
+        '³' => Some('3'),
+        '⁴' => Some('4'),
+        '⁵' => Some('5'),
+        '⁶' => Some('6'),
+        '⁷' => Some('7'),
+        '⁸' => Some('8'),
+        '⁹' => Some('9'),
+        _ => None,
+    }
+}
+
+/// A cursor over the tokens of a single line
+struct LineParser<'a> {
+    tokens: &'a [Token],
+    position: usize,
+    line: usize,
+}
+
+impl<'a> LineParser<'a> {
+    fn peek(&self) -> Option<&'a Token> {
+        self.tokens.get(self.position)
+    }
+
+    fn is_at_end(&self) -> bool {
+        self.position >= self.tokens.len()
+    }
+
+    fn next_token(&mut self) -> Result<&'a Token, SsaParseError> {
+        let token = self.peek().ok_or_else(|| self.error("Unexpected end of line"))?;
+        self.position += 1;
+        Ok(token)
+    }
+
+    /// Advances past the next token if it is equal to the given token
+    fn eat(&mut self, token: &Token) -> bool {
+        if self.peek() == Some(token) {
+            self.position += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, expected: Token) -> Result<(), SsaParseError> {
+        match self.next_token()? {
+            token if *token == expected => Ok(()),
+            token => Err(self.error(format!("Expected {expected}, found {token}"))),
+        }
+    }
+
+    fn expect_ident(&mut self) -> Result<String, SsaParseError> {
+        match self.next_token()? {
+            Token::Ident(name) => Ok(name.clone()),
+            token => Err(self.error(format!("Expected a name, found {token}"))),
+        }
+    }
+
+    fn expect_keyword(&mut self, keyword: &str) -> Result<(), SsaParseError> {
+        match self.next_token()? {
+            Token::Ident(name) if name == keyword => Ok(()),
+            token => Err(self.error(format!("Expected '{keyword}', found {token}"))),
+        }
+    }
+
+    fn expect_int<T: FromStr>(&mut self) -> Result<T, SsaParseError> {
+        match self.next_token()? {
+            Token::Int(int) => {
+                int.parse().map_err(|_| self.error(format!("Integer '{int}' is out of range")))
+            }
+            token => Err(self.error(format!("Expected an integer, found {token}"))),
+        }
+    }
+
+    fn expect_string(&mut self) -> Result<String, SsaParseError> {
+        match self.next_token()? {
+            Token::Str(string) => Ok(string.clone()),
+            token => Err(self.error(format!("Expected a string, found {token}"))),
+        }
+    }
+
+    fn expect_end(&self) -> Result<(), SsaParseError> {
+        match self.peek() {
+            None => Ok(()),
+            Some(token) => Err(self.error(format!("Expected the end of the line, found {token}"))),
+        }
+    }
+
+    /// Parses a type, e.g. `Field`, `[u8; 4]`, or `[Field, u32]`
+    fn parse_type(&mut self) -> Result<Type, SsaParseError> {
+        if self.eat(&Token::LeftBracket) {
+            let mut element_types = vec![self.parse_type()?];
+            while self.eat(&Token::Comma) {
+                element_types.push(self.parse_type()?);
+            }
+
+            let typ = if self.eat(&Token::Semicolon) {
+                Type::Array(Rc::new(element_types), self.expect_int()?)
+            } else {
+                Type::Slice(Rc::new(element_types))
+            };
+            self.expect(Token::RightBracket)?;
+            Ok(typ)
+        } else {
+            let name = self.expect_ident()?;
+            parse_type_name(&name).ok_or_else(|| self.error(format!("Unknown type '{name}'")))
+        }
+    }
+
+    /// Parses a FieldElement in the format it is displayed in, e.g. `5`, `-1`, or `2⁶⁴×3`
+    fn parse_field_element(&mut self) -> Result<FieldElement, SsaParseError> {
+        let is_negative = self.eat(&Token::Minus);
+        let value = self.parse_unsigned_field_element()?;
+        Ok(if is_negative { -value } else { value })
+    }
+
+    fn parse_unsigned_field_element(&mut self) -> Result<FieldElement, SsaParseError> {
+        let mut value = match self.next_token()? {
+            Token::Int(int) => decimal_to_field_element(int),
+            token => return Err(self.error(format!("Expected a number, found {token}"))),
+        };
+
+        if let Some(Token::Superscript(exponent)) = self.peek() {
+            self.position += 1;
+            value = value.pow(&decimal_to_field_element(exponent));
+        }
+
+        if self.eat(&Token::Times) {
+            value = value * self.parse_unsigned_field_element()?;
+        }
+        Ok(value)
+    }
+
+    fn error(&self, message: impl Into<String>) -> SsaParseError {
+        SsaParseError { line: self.line, message: message.into() }
+    }
+}
+
+/// Converts a string of decimal digits into a FieldElement, reducing it by the field modulus
+fn decimal_to_field_element(digits: &str) -> FieldElement {
+    let int = BigUint::parse_bytes(digits.as_bytes(), 10).expect("Expected only decimal digits");
+    FieldElement::from_be_bytes_reduce(&int.to_bytes_be())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use crate::ssa_refactor::{
+        ir::{function::RuntimeType, instruction::TerminatorInstruction},
+        ssa_gen::Ssa,
+    };
+
+    use super::assert_ssa_equals;
+
+    #[test]
+    fn parses_and_prints_consistently() {
+        let src = "
+            acir fn main f0 {
+              b0(v0: Field, v1: u1):
+                v2 = add v0, Field 1
+                jmpif v1 then: b1, else: b2
+              b1():
+                v3 = call f1(v2) -> Field
+                v4 = call to_le_bits(v3, u32 8) -> [u1; 8]
+                v5 = array_get v4, index Field 0 -> u1
+                v6 = cast v5 as Field
+                jmp b3(v6)
+              b2():
+                v7 = allocate
+                store [Field; 2] [v2, Field -1] at v7
+                v8 = load v7 -> [Field; 2]
+                v9 = array_get v8, index Field 1 -> Field
+                jmp b3(v9)
+              b3(v10: Field):
+                return v10
+            }
+            brillig fn foo f1 {
+              b0(v0: Field):
+                v1 = truncate v0 to 8 bits, max_bit_size: 254
+                v2 = eq v1, Field 1
+                constrain v2, \"v1 is not \\\"one\\\"\"
+                return v1
+            }
+        ";
+
+        let ssa = Ssa::from_str(src).unwrap();
+        assert_eq!(ssa.functions.len(), 2);
+
+        let main = ssa.main();
+        assert_eq!(main.name(), "main");
+        assert_eq!(main.parameters().len(), 2);
+        assert_eq!(main.reachable_blocks().len(), 4);
+        assert!(matches!(
+            main.dfg[main.entry_block()].terminator(),
+            Some(TerminatorInstruction::JmpIf { .. })
+        ));
+
+        let foo = ssa.functions.values().find(|function| function.name() == "foo").unwrap();
+        assert_eq!(foo.runtime(), RuntimeType::Brillig);
+
+        // Re-parsing the printed SSA should result in the same program
+        let printed = ssa.to_string();
+        assert_eq!(Ssa::from_str(&printed).unwrap().to_string(), printed);
+    }
+
+    #[test]
+    fn instructions_are_not_simplified() {
+        let src = "
+            acir fn main f0 {
+              b0():
+                v0 = add Field 1, Field 2
+                return v0
+            }
+        ";
+        let ssa = Ssa::from_str(src).unwrap();
+        let main = ssa.main();
+        assert_eq!(main.dfg[main.entry_block()].instructions().len(), 1);
+    }
+
+    #[test]
+    fn compares_ssa_ignoring_names() {
+        let src = "
+            acir fn main f0 {
+              b0(v0: Field):
+                v1 = mul v0, v0
+                v2 = mul v0, v0
+                v3 = add v1, v2
+                return v3
+            }
+        ";
+        let expected = "
+            acir fn main f0 {
+              b0(v10: Field):
+                v11 = mul v10, v10
+                v12 = add v11, v11
+                return v12
+            }
+        ";
+        let ssa = Ssa::from_str(src).unwrap().global_value_numbering();
+        assert_ssa_equals(&ssa, expected);
+    }
+
+    #[test]
+    fn reports_undefined_values() {
+        let src = "
+            acir fn main f0 {
+              b0():
+                return v1
+            }
+        ";
+        let error = Ssa::from_str(src).err().unwrap();
+        assert_eq!(error.line, 4);
+        assert_eq!(error.message, "Undefined value 'v1'");
+    }
+}
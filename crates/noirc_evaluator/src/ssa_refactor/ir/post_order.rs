@@ -5,7 +5,9 @@
 
 use std::collections::HashSet;
 
-use crate::ssa_refactor::ir::{basic_block::BasicBlockId, function::Function};
+use crate::ssa_refactor::ir::{
+    basic_block::BasicBlockId, cfg::ControlFlowGraph, function::Function,
+};
 
 /// Depth-first traversal stack state marker for computing the cfg post-order.
 enum Visit {
@@ -24,14 +26,30 @@ impl PostOrder {
 impl PostOrder {
     /// Allocate and compute a function's block post-order. Pos
     pub(crate) fn with_function(func: &Function) -> Self {
-        PostOrder(Self::compute_post_order(func))
+        PostOrder(Self::compute_post_order(func.entry_block(), |block| {
+            func.dfg[block].successors().collect()
+        }))
     }
 
-    // Computes the post-order of the function by doing a depth-first traversal of the
-    // function's entry block's previously unvisited children. Each block is sequenced according
-    // to when the traversal exits it.
-    fn compute_post_order(func: &Function) -> Vec<BasicBlockId> {
-        let mut stack = vec![(Visit::First, func.entry_block())];
+    /// Allocate and compute the post-order of a function's reversed control flow graph, starting
+    /// from its exit block. Predecessors are visited in order of their ids, since the control flow
+    /// graph does not order them.
+    pub(crate) fn with_reversed_cfg(cfg: &ControlFlowGraph, exit_block: BasicBlockId) -> Self {
+        PostOrder(Self::compute_post_order(exit_block, |block| {
+            let mut predecessors: Vec<_> = cfg.predecessors(block).collect();
+            predecessors.sort();
+            predecessors
+        }))
+    }
+
+    // Computes the post-order of a graph by doing a depth-first traversal of the root's
+    // previously unvisited children. Each block is sequenced according to when the traversal
+    // exits it.
+    fn compute_post_order(
+        root: BasicBlockId,
+        successors: impl Fn(BasicBlockId) -> Vec<BasicBlockId>,
+    ) -> Vec<BasicBlockId> {
+        let mut stack = vec![(Visit::First, root)];
         let mut visited: HashSet<BasicBlockId> = HashSet::new();
         let mut post_order: Vec<BasicBlockId> = Vec::new();
 
@@ -45,7 +63,7 @@ impl PostOrder {
                         stack.push((Visit::Last, block_id));
                         // Stack successors for visiting. Because items are taken from the top of the
                         // stack, we push the item that's due for a visit first to the top.
-                        for successor_id in func.dfg[block_id].successors().rev() {
+                        for successor_id in successors(block_id).into_iter().rev() {
                             if !visited.contains(&successor_id) {
                                 // This not visited check would also be cover by the the next
                                 // iteration, but checking here two saves an iteration per successor.
@@ -0,0 +1,113 @@
+//! Finds the constraints of a function which are guarded by a branch condition.
+//!
+//! Flattening guards each `constrain v` within a branch by the condition `c` of that branch:
+//! ```text
+//! v1 = mul v, c
+//! v2 = eq v1, c
+//! constrain v2
+//! ```
+//! which holds if and only if `c * (v - 1) == 0`. Comparing `v1` with `c` takes an inversion in
+//! ACIR, so when `v1` and `v2` are used by nothing else, ACIR generation skips both instructions
+//! and asserts that product directly.
+//!
+//! Several constraints guarded by the same condition can further be asserted at once when their
+//! values are known to be boolean: the sum of `n` booleans is `n` only if every one of them is one,
+//! so `c * (n - sum) == 0` asserts all of them with a single multiplication by `c`. Values are
+//! known to be boolean when they are the result of a comparison, which ACIR generation always
+//! constrains to be zero or one.
+use std::collections::{HashMap, HashSet};
+
+use super::{
+    dfg::DataFlowGraph,
+    function::Function,
+    instruction::{Binary, BinaryOp, Instruction, InstructionId},
+    value::{Value, ValueId},
+};
+
+/// A constraint guarded by a branch condition
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub(crate) struct PredicatedConstraint {
+    /// The value constrained to be one when the condition holds
+    pub(crate) value: ValueId,
+    /// The branch condition guarding the constraint
+    pub(crate) condition: ValueId,
+    /// True if the value is known to be either zero or one
+    pub(crate) is_boolean: bool,
+}
+
+/// The constraints of a function which are guarded by a branch condition.
+pub(crate) struct PredicatedConstraints {
+    constraints: HashMap<InstructionId, PredicatedConstraint>,
+    /// The `mul` and `eq` instructions which only guard the constraints
+    guards: HashSet<InstructionId>,
+}
+
+impl PredicatedConstraints {
+    pub(crate) fn with_function(function: &Function) -> Self {
+        let dfg = &function.dfg;
+        let use_counts = function.use_counts();
+        let is_only_use = |value: ValueId| use_counts.get(&dfg.resolve(value)) == Some(&1);
+
+        let mut constraints = HashMap::new();
+        let mut guards = HashSet::new();
+        for block in function.reachable_blocks() {
+            for instruction in dfg[block].instructions() {
+                let guarded = match &dfg[*instruction] {
+                    Instruction::Constrain(guarded, _) => *guarded,
+                    _ => continue,
+                };
+
+                // constrain (eq (mul value condition) condition)
+                let (eq, lhs, condition) = match binary(dfg, guarded, BinaryOp::Eq) {
+                    Some(eq) => eq,
+                    None => continue,
+                };
+                let (mul, value) = match binary(dfg, lhs, BinaryOp::Mul) {
+                    Some((mul, value, rhs)) if rhs == condition => (mul, value),
+                    Some((mul, lhs, value)) if lhs == condition => (mul, value),
+                    _ => continue,
+                };
+                if !is_only_use(guarded) || !is_only_use(lhs) {
+                    continue;
+                }
+
+                let is_boolean = binary(dfg, value, BinaryOp::Eq).is_some()
+                    || binary(dfg, value, BinaryOp::Lt).is_some();
+                let constraint = PredicatedConstraint { value, condition, is_boolean };
+                constraints.insert(*instruction, constraint);
+                guards.extend([eq, mul]);
+            }
+        }
+        Self { constraints, guards }
+    }
+
+    /// Returns the guarded value and condition of the given `constrain` instruction, if it is
+    /// guarded by a branch condition.
+    pub(crate) fn constraint(&self, instruction: InstructionId) -> Option<PredicatedConstraint> {
+        self.constraints.get(&instruction).copied()
+    }
+
+    /// True if the given instruction only computes the guarded value of a constraint, and need
+    /// not be converted.
+    pub(crate) fn is_guard(&self, instruction: InstructionId) -> bool {
+        self.guards.contains(&instruction)
+    }
+}
+
+/// Returns the instruction defining `value` along with its operands, if it is a binary
+/// instruction with the given operator.
+fn binary(
+    dfg: &DataFlowGraph,
+    value: ValueId,
+    operator: BinaryOp,
+) -> Option<(InstructionId, ValueId, ValueId)> {
+    match &dfg[dfg.resolve(value)] {
+        Value::Instruction { instruction, .. } => match &dfg[*instruction] {
+            Instruction::Binary(Binary { lhs, rhs, operator: op }) if *op == operator => {
+                Some((*instruction, dfg.resolve(*lhs), dfg.resolve(*rhs)))
+            }
+            _ => None,
+        },
+        _ => None,
+    }
+}
@@ -68,13 +68,12 @@ fn value(function: &Function, id: ValueId) -> String {
         }
         Value::Function(id) => id.to_string(),
         Value::Intrinsic(intrinsic) => intrinsic.to_string(),
-        Value::Array { array, .. } => {
+        Value::Array { array, typ } => {
             let elements = vecmap(array, |element| value(function, *element));
-            format!("[{}]", elements.join(", "))
-        }
-        Value::Param { .. } | Value::Instruction { .. } | Value::ForeignFunction(_) => {
-            id.to_string()
+            format!("{typ} [{}]", elements.join(", "))
         }
+        Value::ForeignFunction(name) => name.clone(),
+        Value::Param { .. } | Value::Instruction { .. } => id.to_string(),
     }
 }
 
@@ -133,43 +132,195 @@ pub(crate) fn display_instruction(
         write!(f, "{} = ", value_list(function, results))?;
     }
 
+    display_instruction_inner(function, &function.dfg[instruction], f)?;
+
+    // The result types of these instructions can't be inferred from their
+    // operands, so they are displayed explicitly, e.g. `v1 = load v0 -> Field`
+    if function.dfg[instruction].requires_ctrl_typevars() && !results.is_empty() {
+        let types = vecmap(results, |result| function.dfg.type_of_value(*result).to_string());
+        write!(f, " -> {}", types.join(", "))?;
+    }
+    writeln!(f)
+}
+
+/// Display an instruction without its results or a trailing newline
+fn display_instruction_inner(
+    function: &Function,
+    instruction: &Instruction,
+    f: &mut Formatter,
+) -> Result {
     let show = |id| value(function, id);
 
-    match &function.dfg[instruction] {
+    match instruction {
         Instruction::Binary(binary) => {
-            writeln!(f, "{} {}, {}", binary.operator, show(binary.lhs), show(binary.rhs))
+            write!(f, "{} {}, {}", binary.operator, show(binary.lhs), show(binary.rhs))
         }
-        Instruction::Cast(lhs, typ) => writeln!(f, "cast {} as {typ}", show(*lhs)),
-        Instruction::Not(rhs) => writeln!(f, "not {}", show(*rhs)),
+        Instruction::Cast(lhs, typ) => write!(f, "cast {} as {typ}", show(*lhs)),
+        Instruction::Not(rhs) => write!(f, "not {}", show(*rhs)),
         Instruction::Truncate { value, bit_size, max_bit_size } => {
             let value = show(*value);
-            writeln!(f, "truncate {value} to {bit_size} bits, max_bit_size: {max_bit_size}",)
+            write!(f, "truncate {value} to {bit_size} bits, max_bit_size: {max_bit_size}",)
         }
-        Instruction::Constrain(value) => {
-            writeln!(f, "constrain {}", show(*value))
+        Instruction::Constrain(value, message) => {
+            write!(f, "constrain {}", show(*value))?;
+            match message {
+                Some(message) => write!(f, ", {message:?}"),
+                None => Ok(()),
+            }
         }
         Instruction::Call { func, arguments } => {
-            writeln!(f, "call {}({})", show(*func), value_list(function, arguments))
+            write!(f, "call {}({})", show(*func), value_list(function, arguments))
         }
-        Instruction::Allocate => writeln!(f, "allocate"),
-        Instruction::Load { address } => writeln!(f, "load {}", show(*address)),
+        Instruction::Allocate => write!(f, "allocate"),
+        Instruction::Load { address } => write!(f, "load {}", show(*address)),
         Instruction::Store { address, value } => {
-            writeln!(f, "store {} at {}", show(*value), show(*address))
+            write!(f, "store {} at {}", show(*value), show(*address))
         }
         Instruction::EnableSideEffects { condition } => {
-            writeln!(f, "enable_side_effects {}", show(*condition))
+            write!(f, "enable_side_effects {}", show(*condition))
         }
         Instruction::ArrayGet { array, index } => {
-            writeln!(f, "array_get {}, index {}", show(*array), show(*index))
+            write!(f, "array_get {}, index {}", show(*array), show(*index))
         }
         Instruction::ArraySet { array, index, value } => {
-            writeln!(
-                f,
-                "array_set {}, index {}, value {}",
-                show(*array),
-                show(*index),
-                show(*value)
-            )
+            write!(f, "array_set {}, index {}, value {}", show(*array), show(*index), show(*value))
+        }
+    }
+}
+
+/// A line of a function's printed form, compared between two printings of the function
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum LineChange<'a> {
+    Unchanged(&'a str),
+    Removed(&'a str),
+    Added(&'a str),
+}
+
+/// Displays the changes between two printings of the same function, such as before and after an
+/// optimization pass. Either printing may be empty if the function was added or removed.
+///
+/// Removed lines are marked with `-` and added lines with `+`. Unchanged lines are omitted other
+/// than the function's first line and the header of each block containing a change, with `...`
+/// in place of each run of omitted lines.
+pub(crate) fn display_function_diff(before: &str, after: &str) -> String {
+    let before: Vec<_> = before.lines().collect();
+    let after: Vec<_> = after.lines().collect();
+    let is_block_header = |line: &str| line.starts_with("  b") && line.ends_with(':');
+
+    let mut output = Vec::new();
+    // Whether any unchanged lines have been omitted since the last line in the output
+    let mut omitted = false;
+    // The header of the current block if it is unchanged but not yet in the output, along with
+    // whether any lines before it were omitted
+    let mut pending_header = None;
+
+    for (index, change) in diff_lines(&before, &after).into_iter().enumerate() {
+        let (marker, line) = match change {
+            LineChange::Unchanged(line) if index == 0 => ("  ", line),
+            LineChange::Unchanged(line) if is_block_header(line) => {
+                let omitted_before = omitted || pending_header.is_some();
+                pending_header = Some((line, omitted_before));
+                omitted = false;
+                continue;
+            }
+            LineChange::Unchanged(_) => {
+                omitted = true;
+                continue;
+            }
+            LineChange::Removed(line) => ("- ", line),
+            LineChange::Added(line) => ("+ ", line),
+        };
+
+        if let Some((header, omitted_before)) = pending_header.take() {
+            if is_block_header(line) {
+                // The block containing this change is not the pending block
+                omitted = true;
+            } else {
+                if omitted_before {
+                    output.push("  ...".to_owned());
+                }
+                output.push(format!("  {header}"));
+            }
         }
+        if omitted {
+            output.push("  ...".to_owned());
+            omitted = false;
+        }
+        output.push(format!("{marker}{line}"));
+    }
+
+    if omitted || pending_header.is_some() {
+        output.push("  ...".to_owned());
+    }
+    output.join("\n")
+}
+
+/// Compares two lists of lines, keeping the longest common subsequence of lines unchanged.
+fn diff_lines<'a>(before: &[&'a str], after: &[&'a str]) -> Vec<LineChange<'a>> {
+    // common[i][j] is the length of the longest common subsequence of before[i..] and after[j..]
+    let mut common = vec![vec![0; after.len() + 1]; before.len() + 1];
+    for i in (0..before.len()).rev() {
+        for j in (0..after.len()).rev() {
+            common[i][j] = if before[i] == after[j] {
+                common[i + 1][j + 1] + 1
+            } else {
+                common[i + 1][j].max(common[i][j + 1])
+            };
+        }
+    }
+
+    let (mut i, mut j) = (0, 0);
+    let mut changes = Vec::with_capacity(before.len().max(after.len()));
+    while i < before.len() || j < after.len() {
+        if i < before.len() && j < after.len() && before[i] == after[j] {
+            changes.push(LineChange::Unchanged(before[i]));
+            i += 1;
+            j += 1;
+        } else if j == after.len() || (i < before.len() && common[i + 1][j] >= common[i][j + 1]) {
+            changes.push(LineChange::Removed(before[i]));
+            i += 1;
+        } else {
+            changes.push(LineChange::Added(after[j]));
+            j += 1;
+        }
+    }
+    changes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::display_function_diff;
+
+    #[test]
+    fn displays_only_changed_blocks() {
+        let before = "\
+acir fn main f0 {
+  b0(v0: Field):
+    v1 = add v0, Field 1
+    jmp b1()
+  b1():
+    v2 = mul v1, v1
+    v3 = mul v2, Field 2
+    return v3
+}";
+        let after = "\
+acir fn main f0 {
+  b0(v0: Field):
+    v1 = add v0, Field 1
+    jmp b1()
+  b1():
+    v2 = mul v1, v1
+    v3 = add v2, v2
+    return v3
+}";
+        let expected = "\
+  acir fn main f0 {
+  ...
+    b1():
+  ...
+-     v3 = mul v2, Field 2
++     v3 = add v2, v2
+  ...";
+        assert_eq!(display_function_diff(before, after), expected);
     }
 }
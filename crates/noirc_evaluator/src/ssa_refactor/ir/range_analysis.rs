@@ -0,0 +1,650 @@
+//! Integer range analysis for a single function.
+//!
+//! Each integer value is given an inclusive interval `[min, max]` which contains every value it
+//! may take at runtime. Intervals are seeded from constants, the types of the function's
+//! parameters, casts, and truncations, and are then propagated through arithmetic.
+//!
+//! Comparisons which are known to hold narrow the intervals of the values they compare. A
+//! constrained comparison holds within the block containing the constraint, since execution fails
+//! otherwise, and a branch condition holds within the destination it leads to. Either way, the
+//! comparison also holds within every block dominated by that block.
+//!
+//! Block parameters receive the union of the intervals of the arguments passed to them, which is
+//! iterated to a fixed point for loops. A parameter whose interval keeps growing, such as a loop
+//! induction variable, is widened to an unknown range. The loop condition still bounds such a
+//! variable within the loop body, which is usually enough to bound the variable after it is
+//! incremented and passed back to the loop header.
+use std::collections::HashMap;
+
+use super::{
+    basic_block::BasicBlockId,
+    cfg::ControlFlowGraph,
+    dfg::DataFlowGraph,
+    dom::DominatorTree,
+    function::Function,
+    instruction::{Binary, BinaryOp, Instruction, TerminatorInstruction},
+    post_order::PostOrder,
+    types::{NumericType, Type},
+    value::{Value, ValueId},
+};
+
+/// The number of times the range of a block parameter may change before it is widened to an
+/// unknown range. A parameter which keeps changing after twice as many updates is left unknown.
+const MAX_PARAMETER_UPDATES: usize = 4;
+
+/// An inclusive range of unsigned integers
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub(crate) struct ValueRange {
+    pub(crate) min: u128,
+    pub(crate) max: u128,
+}
+
+impl ValueRange {
+    pub(crate) fn new(min: u128, max: u128) -> Self {
+        assert!(min <= max, "ICE: Range minimum {min} is greater than its maximum {max}");
+        Self { min, max }
+    }
+
+    pub(crate) fn constant(value: u128) -> Self {
+        Self::new(value, value)
+    }
+
+    /// Returns the range of every integer which fits within the given number of bits.
+    pub(crate) fn of_bit_size(bit_size: u32) -> Self {
+        Self::new(0, max_of_bit_size(bit_size))
+    }
+
+    /// Returns true if every value within this range fits within the given number of bits.
+    pub(crate) fn fits_in(&self, bit_size: u32) -> bool {
+        self.max <= max_of_bit_size(bit_size)
+    }
+
+    fn union(self, other: ValueRange) -> ValueRange {
+        Self::new(self.min.min(other.min), self.max.max(other.max))
+    }
+
+    /// Returns the values within both ranges, or None if there are no such values.
+    fn intersection(self, other: ValueRange) -> Option<ValueRange> {
+        let min = self.min.max(other.min);
+        let max = self.max.min(other.max);
+        (min <= max).then(|| Self::new(min, max))
+    }
+}
+
+fn max_of_bit_size(bit_size: u32) -> u128 {
+    if bit_size >= 128 {
+        u128::MAX
+    } else {
+        (1 << bit_size) - 1
+    }
+}
+
+/// What is known about the range of a value while the analysis is running
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum Bound {
+    /// The value's definition has not been visited yet
+    Unvisited,
+    /// The value may be any field element
+    Unknown,
+    Known(ValueRange),
+}
+
+impl Bound {
+    fn union(self, other: Bound) -> Bound {
+        match (self, other) {
+            (Bound::Unvisited, other) | (other, Bound::Unvisited) => other,
+            (Bound::Known(a), Bound::Known(b)) => Bound::Known(a.union(b)),
+            _ => Bound::Unknown,
+        }
+    }
+
+    /// Narrows this bound with a range the value is known to be within.
+    fn narrow(self, range: ValueRange) -> Bound {
+        match self {
+            Bound::Unvisited => Bound::Unvisited,
+            Bound::Unknown => Bound::Known(range),
+            // An empty intersection means the block is unreachable, so either range will do
+            Bound::Known(current) => Bound::Known(current.intersection(range).unwrap_or(current)),
+        }
+    }
+
+    /// Returns the bound of a value after it is truncated to the given number of bits.
+    fn truncate(self, bit_size: u32) -> Bound {
+        match self {
+            Bound::Unvisited => Bound::Unvisited,
+            Bound::Known(range) if range.fits_in(bit_size) => Bound::Known(range),
+            _ => Bound::Known(ValueRange::of_bit_size(bit_size)),
+        }
+    }
+}
+
+/// The ranges of the integer values within a function.
+pub(crate) struct RangeAnalysis {
+    /// The range of each value defined by the function whose range could be bounded,
+    /// keyed by resolved value id. These do not account for the comparisons known to hold
+    /// within the value's own block.
+    ranges: HashMap<ValueId, ValueRange>,
+
+    /// The ranges implied by the comparisons known to hold within each block,
+    /// not including those implied within the block's dominators.
+    facts: HashMap<BasicBlockId, Vec<(ValueId, ValueRange)>>,
+
+    dom_tree: DominatorTree,
+}
+
+impl RangeAnalysis {
+    /// Computes the ranges of the integer values within the given function.
+    pub(crate) fn with_function(function: &Function) -> Self {
+        let cfg = ControlFlowGraph::with_function(function);
+        let post_order = PostOrder::with_function(function);
+        let dom_tree = DominatorTree::with_cfg_and_post_order(&cfg, &post_order);
+
+        // Visiting blocks in reverse post-order visits the definition of each value before its
+        // uses, other than those reached along back-edges.
+        let blocks: Vec<_> = post_order.as_slice().iter().rev().copied().collect();
+        let facts = blocks.iter().map(|block| (*block, collect_facts(function, &cfg, *block)));
+        let facts = facts.collect();
+
+        let mut context = AnalysisContext {
+            function,
+            cfg: &cfg,
+            dom_tree: &dom_tree,
+            facts: &facts,
+            bounds: HashMap::new(),
+            parameter_updates: HashMap::new(),
+        };
+        while context.visit_blocks(&blocks) {}
+
+        let ranges = context
+            .bounds
+            .into_iter()
+            .filter_map(|(value, bound)| match bound {
+                Bound::Known(range) => Some((value, range)),
+                Bound::Unvisited | Bound::Unknown => None,
+            })
+            .collect();
+
+        Self { ranges, facts, dom_tree }
+    }
+
+    /// Returns the range of the given value which holds anywhere it is used, if it is known.
+    pub(crate) fn range(&self, dfg: &DataFlowGraph, value: ValueId) -> Option<ValueRange> {
+        let value = dfg.resolve(value);
+        constant_value(dfg, value)
+            .map(ValueRange::constant)
+            .or_else(|| self.ranges.get(&value).copied())
+    }
+
+    /// Returns the range of the given value within the given block, narrowed by any comparisons
+    /// known to hold within the block.
+    pub(crate) fn range_in_block(
+        &self,
+        dfg: &DataFlowGraph,
+        block: BasicBlockId,
+        value: ValueId,
+    ) -> Option<ValueRange> {
+        let value = dfg.resolve(value);
+        let bound = self.range(dfg, value).map_or(Bound::Unknown, Bound::Known);
+        match narrow_with_facts(&self.facts, &self.dom_tree, block, value, bound) {
+            Bound::Known(range) => Some(range),
+            Bound::Unvisited | Bound::Unknown => None,
+        }
+    }
+}
+
+struct AnalysisContext<'f> {
+    function: &'f Function,
+    cfg: &'f ControlFlowGraph,
+    dom_tree: &'f DominatorTree,
+    facts: &'f HashMap<BasicBlockId, Vec<(ValueId, ValueRange)>>,
+
+    bounds: HashMap<ValueId, Bound>,
+
+    /// The number of times the bound of each block parameter has changed
+    parameter_updates: HashMap<ValueId, usize>,
+}
+
+impl<'f> AnalysisContext<'f> {
+    /// Recomputes the bound of each value within the given blocks.
+    /// Returns true if any bound changed.
+    fn visit_blocks(&mut self, blocks: &[BasicBlockId]) -> bool {
+        let mut changed = false;
+        for block in blocks {
+            changed |= self.visit_block(*block);
+        }
+        changed
+    }
+
+    fn visit_block(&mut self, block: BasicBlockId) -> bool {
+        let function = self.function;
+        let dfg = &function.dfg;
+        let mut changed = false;
+
+        for (index, parameter) in dfg.block_parameters(block).iter().enumerate() {
+            let mut bound = if block == function.entry_block() {
+                type_bound(&dfg.type_of_value(*parameter))
+            } else {
+                self.parameter_bound(block, index)
+            };
+
+            let updates = self.parameter_updates.entry(*parameter).or_default();
+            if *updates == MAX_PARAMETER_UPDATES || *updates > 2 * MAX_PARAMETER_UPDATES {
+                bound = Bound::Unknown;
+            }
+            if self.bounds.insert(*parameter, bound) != Some(bound) {
+                *updates += 1;
+                changed = true;
+            }
+        }
+
+        for instruction in dfg[block].instructions() {
+            let results = dfg.instruction_results(*instruction);
+            let bound = match results {
+                [result] => self.instruction_bound(block, &dfg[*instruction], *result),
+                _ => Bound::Unknown,
+            };
+            for result in results {
+                changed |= self.bounds.insert(*result, bound) != Some(bound);
+            }
+        }
+        changed
+    }
+
+    /// Returns the union of the bounds of each argument passed to the given parameter of a block.
+    fn parameter_bound(&self, block: BasicBlockId, index: usize) -> Bound {
+        let dfg = &self.function.dfg;
+        let mut bound = Bound::Unvisited;
+
+        for predecessor in self.cfg.predecessors(block) {
+            match dfg[predecessor].terminator() {
+                Some(TerminatorInstruction::Jmp { destination, arguments }) => {
+                    assert_eq!(*destination, block);
+                    bound = bound.union(self.bound(predecessor, arguments[index]));
+                }
+                _ => return Bound::Unknown,
+            }
+        }
+        bound
+    }
+
+    /// Returns the bound of the given value within the given block.
+    fn bound(&self, block: BasicBlockId, value: ValueId) -> Bound {
+        let dfg = &self.function.dfg;
+        let value = dfg.resolve(value);
+
+        let bound = match &dfg[value] {
+            Value::NumericConstant { .. } => match constant_value(dfg, value) {
+                Some(constant) => Bound::Known(ValueRange::constant(constant)),
+                None => Bound::Unknown,
+            },
+            Value::Instruction { .. } | Value::Param { .. } => {
+                self.bounds.get(&value).copied().unwrap_or(Bound::Unvisited)
+            }
+            _ => Bound::Unknown,
+        };
+        narrow_with_facts(self.facts, self.dom_tree, block, value, bound)
+    }
+
+    fn instruction_bound(
+        &self,
+        block: BasicBlockId,
+        instruction: &Instruction,
+        result: ValueId,
+    ) -> Bound {
+        let typ = self.function.dfg.type_of_value(result);
+        match instruction {
+            Instruction::Binary(binary) => self.binary_bound(block, binary, &typ),
+            Instruction::Cast(value, Type::Numeric(NumericType::Unsigned { bit_size })) => {
+                self.bound(block, *value).truncate(*bit_size)
+            }
+            Instruction::Cast(value, Type::Numeric(NumericType::NativeField)) => {
+                self.bound(block, *value)
+            }
+            Instruction::Truncate { value, bit_size, .. } => {
+                self.bound(block, *value).truncate(*bit_size)
+            }
+            Instruction::Not(value) => match (self.bound(block, *value), typ) {
+                (Bound::Unvisited, _) => Bound::Unvisited,
+                (Bound::Known(range), Type::Numeric(NumericType::Unsigned { bit_size }))
+                    if range.fits_in(bit_size) =>
+                {
+                    let max = max_of_bit_size(bit_size);
+                    Bound::Known(ValueRange::new(max - range.max, max - range.min))
+                }
+                _ => Bound::Unknown,
+            },
+            _ => Bound::Unknown,
+        }
+    }
+
+    fn binary_bound(&self, block: BasicBlockId, binary: &Binary, typ: &Type) -> Bound {
+        let lhs = self.bound(block, binary.lhs);
+        let rhs = self.bound(block, binary.rhs);
+        if lhs == Bound::Unvisited || rhs == Bound::Unvisited {
+            return Bound::Unvisited;
+        }
+
+        let unsigned = matches!(typ, Type::Numeric(NumericType::Unsigned { .. }));
+        match (binary.operator, lhs, rhs) {
+            (BinaryOp::Eq | BinaryOp::Lt, _, _) => Bound::Known(ValueRange::new(0, 1)),
+            (BinaryOp::And, Bound::Known(range), _) | (BinaryOp::And, _, Bound::Known(range))
+                if unsigned =>
+            {
+                let max = match (lhs, rhs) {
+                    (Bound::Known(lhs), Bound::Known(rhs)) => lhs.max.min(rhs.max),
+                    _ => range.max,
+                };
+                Bound::Known(ValueRange::new(0, max))
+            }
+            (BinaryOp::Mod, _, Bound::Known(rhs)) if unsigned && rhs.max > 0 => {
+                let max = match lhs {
+                    Bound::Known(lhs) => lhs.max.min(rhs.max - 1),
+                    _ => rhs.max - 1,
+                };
+                Bound::Known(ValueRange::new(0, max))
+            }
+            (operator, Bound::Known(lhs), Bound::Known(rhs)) => {
+                known_binary_range(operator, lhs, rhs, unsigned)
+                    .map_or(Bound::Unknown, Bound::Known)
+            }
+            _ => Bound::Unknown,
+        }
+    }
+}
+
+/// Returns the range of the result of a binary operation on two values with known ranges.
+///
+/// Additions, subtractions, and multiplications are field operations regardless of the type of
+/// their operands, so their results are only bounded when they cannot wrap around. Every other
+/// operation is only bounded for unsigned integers.
+fn known_binary_range(
+    operator: BinaryOp,
+    lhs: ValueRange,
+    rhs: ValueRange,
+    unsigned: bool,
+) -> Option<ValueRange> {
+    let range = match operator {
+        BinaryOp::Add => {
+            ValueRange::new(lhs.min.checked_add(rhs.min)?, lhs.max.checked_add(rhs.max)?)
+        }
+        BinaryOp::Sub if lhs.min >= rhs.max => {
+            ValueRange::new(lhs.min - rhs.max, lhs.max - rhs.min)
+        }
+        BinaryOp::Mul => {
+            ValueRange::new(lhs.min.checked_mul(rhs.min)?, lhs.max.checked_mul(rhs.max)?)
+        }
+        BinaryOp::Div if unsigned && rhs.max > 0 => {
+            ValueRange::new(lhs.min / rhs.max, lhs.max / rhs.min.max(1))
+        }
+        BinaryOp::Or | BinaryOp::Xor if unsigned => {
+            ValueRange::new(0, all_ones_up_to(lhs.max.max(rhs.max)))
+        }
+        BinaryOp::Shr if unsigned => {
+            let min_shift = u32::try_from(rhs.min).ok().filter(|shift| *shift < 128);
+            let max_shift = u32::try_from(rhs.max).ok().filter(|shift| *shift < 128);
+            let min = max_shift.map_or(0, |shift| lhs.min >> shift);
+            let max = min_shift.map_or(0, |shift| lhs.max >> shift);
+            ValueRange::new(min, max)
+        }
+        BinaryOp::Shl if unsigned => {
+            let min_factor = 1_u128.checked_shl(u32::try_from(rhs.min).ok()?)?;
+            let max_factor = 1_u128.checked_shl(u32::try_from(rhs.max).ok()?)?;
+            ValueRange::new(lhs.min.checked_mul(min_factor)?, lhs.max.checked_mul(max_factor)?)
+        }
+        _ => return None,
+    };
+    Some(range)
+}
+
+/// Returns the smallest integer of the form `2^k - 1` which is at least the given value.
+fn all_ones_up_to(value: u128) -> u128 {
+    if value == 0 {
+        0
+    } else {
+        u128::MAX >> value.leading_zeros()
+    }
+}
+
+/// Returns the range of every value of the given type, which is assumed to hold
+/// for the parameters of a function.
+fn type_bound(typ: &Type) -> Bound {
+    match typ {
+        Type::Numeric(NumericType::Unsigned { bit_size } | NumericType::Signed { bit_size }) => {
+            Bound::Known(ValueRange::of_bit_size(*bit_size))
+        }
+        _ => Bound::Unknown,
+    }
+}
+
+/// Narrows the bound of a value within a block by every fact about it which holds within the
+/// block or one of its dominators.
+fn narrow_with_facts(
+    facts: &HashMap<BasicBlockId, Vec<(ValueId, ValueRange)>>,
+    dom_tree: &DominatorTree,
+    mut block: BasicBlockId,
+    value: ValueId,
+    mut bound: Bound,
+) -> Bound {
+    loop {
+        for (fact_value, range) in facts.get(&block).into_iter().flatten() {
+            if *fact_value == value {
+                bound = bound.narrow(*range);
+            }
+        }
+        match dom_tree.immediate_dominator(block) {
+            Some(dominator) => block = dominator,
+            None => return bound,
+        }
+    }
+}
+
+/// Collects the ranges implied by the constraints within a block, along with those implied by
+/// the condition of the branch leading to the block if it is the block's only predecessor.
+fn collect_facts(
+    function: &Function,
+    cfg: &ControlFlowGraph,
+    block: BasicBlockId,
+) -> Vec<(ValueId, ValueRange)> {
+    let dfg = &function.dfg;
+    let mut facts = Vec::new();
+
+    for instruction in dfg[block].instructions() {
+        if let Instruction::Constrain(condition, _) = &dfg[*instruction] {
+            condition_facts(dfg, *condition, true, &mut facts);
+        }
+    }
+
+    let predecessors: Vec<_> = cfg.predecessors(block).collect();
+    if let [predecessor] = predecessors.as_slice() {
+        if let Some(TerminatorInstruction::JmpIf {
+            condition,
+            then_destination,
+            else_destination,
+        }) = dfg[*predecessor].terminator()
+        {
+            if then_destination != else_destination {
+                condition_facts(dfg, *condition, block == *then_destination, &mut facts);
+            }
+        }
+    }
+    facts
+}
+
+/// Pushes the ranges implied by the given boolean condition being equal to `holds`.
+///
+/// Only comparisons against constants are used.
+fn condition_facts(
+    dfg: &DataFlowGraph,
+    condition: ValueId,
+    holds: bool,
+    facts: &mut Vec<(ValueId, ValueRange)>,
+) {
+    let instruction = match &dfg[dfg.resolve(condition)] {
+        Value::Instruction { instruction, .. } => &dfg[*instruction],
+        _ => return,
+    };
+
+    let Binary { lhs, rhs, operator } = match instruction {
+        Instruction::Not(value) => return condition_facts(dfg, *value, !holds, facts),
+        Instruction::Binary(binary) => binary.clone(),
+        _ => return,
+    };
+    let (lhs, rhs) = (dfg.resolve(lhs), dfg.resolve(rhs));
+
+    match (operator, holds) {
+        // Both operands of a boolean `and` hold if it holds, and neither holds for an `or` which
+        // does not hold.
+        (BinaryOp::And, true) | (BinaryOp::Or, false) if dfg.type_of_value(lhs) == Type::bool() => {
+            condition_facts(dfg, lhs, holds, facts);
+            condition_facts(dfg, rhs, holds, facts);
+        }
+        (BinaryOp::Eq, true) => match (constant_value(dfg, lhs), constant_value(dfg, rhs)) {
+            (None, Some(constant)) => facts.push((lhs, ValueRange::constant(constant))),
+            (Some(constant), None) => facts.push((rhs, ValueRange::constant(constant))),
+            _ => (),
+        },
+        // Comparisons are only between integers, but a field element cast to an integer type
+        // may be any value so only unsigned comparisons are trusted.
+        (BinaryOp::Lt, _) if is_unsigned(dfg, lhs) => {
+            match (constant_value(dfg, lhs), constant_value(dfg, rhs), holds) {
+                // lhs < c
+                (None, Some(constant), true) => {
+                    if let Some(max) = constant.checked_sub(1) {
+                        facts.push((lhs, ValueRange::new(0, max)));
+                    }
+                }
+                // lhs >= c
+                (None, Some(constant), false) => {
+                    facts.push((lhs, ValueRange::new(constant, u128::MAX)));
+                }
+                // c < rhs
+                (Some(constant), None, true) => {
+                    if let Some(min) = constant.checked_add(1) {
+                        facts.push((rhs, ValueRange::new(min, u128::MAX)));
+                    }
+                }
+                // c >= rhs
+                (Some(constant), None, false) => {
+                    facts.push((rhs, ValueRange::new(0, constant)));
+                }
+                _ => (),
+            }
+        }
+        _ => (),
+    }
+}
+
+fn is_unsigned(dfg: &DataFlowGraph, value: ValueId) -> bool {
+    matches!(dfg.type_of_value(value), Type::Numeric(NumericType::Unsigned { .. }))
+}
+
+/// Returns the value of a numeric constant if it fits within a u128.
+fn constant_value(dfg: &DataFlowGraph, value: ValueId) -> Option<u128> {
+    dfg.get_numeric_constant(value)
+        .filter(|constant| constant.num_bits() <= 128)
+        .map(|constant| constant.to_u128())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::ssa_refactor::{
+        ir::{function::RuntimeType, instruction::BinaryOp, map::Id, types::Type},
+        ssa_builder::FunctionBuilder,
+    };
+
+    use super::{RangeAnalysis, ValueRange};
+
+    #[test]
+    fn bounds_loop_induction_variables() {
+        // brillig fn main f0 {
+        //   b0():
+        //     jmp b1(u32 0)
+        //   b1(v0: u32):
+        //     v1 = lt v0, u32 10
+        //     jmpif v1 then: b2, else: b3
+        //   b2():
+        //     v2 = add v0, u32 1
+        //     jmp b1(v2)
+        //   b3():
+        //     return v0
+        // }
+        let main_id = Id::test_new(0);
+        let mut builder = FunctionBuilder::new("main".into(), main_id, RuntimeType::Brillig);
+
+        let b1 = builder.insert_block();
+        let b2 = builder.insert_block();
+        let b3 = builder.insert_block();
+        let v0 = builder.add_block_parameter(b1, Type::unsigned(32));
+
+        let zero = builder.numeric_constant(0u128, Type::unsigned(32));
+        builder.terminate_with_jmp(b1, vec![zero]);
+
+        builder.switch_to_block(b1);
+        let ten = builder.numeric_constant(10u128, Type::unsigned(32));
+        let v1 = builder.insert_binary(v0, BinaryOp::Lt, ten);
+        builder.terminate_with_jmpif(v1, b2, b3);
+
+        builder.switch_to_block(b2);
+        let one = builder.numeric_constant(1u128, Type::unsigned(32));
+        let v2 = builder.insert_binary(v0, BinaryOp::Add, one);
+        builder.terminate_with_jmp(b1, vec![v2]);
+
+        builder.switch_to_block(b3);
+        builder.terminate_with_return(vec![v0]);
+
+        let ssa = builder.finish();
+        let main = ssa.main();
+        let ranges = RangeAnalysis::with_function(main);
+
+        assert_eq!(ranges.range(&main.dfg, v0), Some(ValueRange::new(0, 10)));
+        assert_eq!(ranges.range(&main.dfg, v1), Some(ValueRange::new(0, 1)));
+        assert_eq!(ranges.range(&main.dfg, v2), Some(ValueRange::new(1, 10)));
+        assert_eq!(ranges.range_in_block(&main.dfg, b2, v0), Some(ValueRange::new(0, 9)));
+        assert_eq!(ranges.range_in_block(&main.dfg, b3, v0), Some(ValueRange::constant(10)));
+    }
+
+    #[test]
+    fn bounds_casts_and_constrained_values() {
+        // acir fn main f0 {
+        //   b0(v0: Field, v1: u8):
+        //     v2 = cast v0 as u8
+        //     v3 = add v1, v2
+        //     v4 = cast v0 as u32
+        //     v5 = lt v4, u32 100
+        //     constrain v5
+        //     v6 = mul v4, u32 3
+        //     v7 = sub v6, v1
+        //     return v3, v6, v7
+        // }
+        let main_id = Id::test_new(0);
+        let mut builder = FunctionBuilder::new("main".into(), main_id, RuntimeType::Acir);
+        let v0 = builder.add_parameter(Type::field());
+        let v1 = builder.add_parameter(Type::unsigned(8));
+
+        let v2 = builder.insert_cast(v0, Type::unsigned(8));
+        let v3 = builder.insert_binary(v1, BinaryOp::Add, v2);
+        let v4 = builder.insert_cast(v0, Type::unsigned(32));
+        let hundred = builder.numeric_constant(100u128, Type::unsigned(32));
+        let v5 = builder.insert_binary(v4, BinaryOp::Lt, hundred);
+        builder.insert_constrain(v5, None);
+        let three = builder.numeric_constant(3u128, Type::unsigned(32));
+        let v6 = builder.insert_binary(v4, BinaryOp::Mul, three);
+        let v7 = builder.insert_binary(v6, BinaryOp::Sub, v1);
+        builder.terminate_with_return(vec![v3, v6, v7]);
+
+        let ssa = builder.finish();
+        let main = ssa.main();
+        let ranges = RangeAnalysis::with_function(main);
+        let entry = main.entry_block();
+
+        assert_eq!(ranges.range(&main.dfg, v0), None);
+        assert_eq!(ranges.range(&main.dfg, v1), Some(ValueRange::of_bit_size(8)));
+        assert_eq!(ranges.range(&main.dfg, v3), Some(ValueRange::new(0, 510)));
+        assert_eq!(ranges.range(&main.dfg, v4), Some(ValueRange::of_bit_size(32)));
+        assert_eq!(ranges.range_in_block(&main.dfg, entry, v4), Some(ValueRange::new(0, 99)));
+        assert_eq!(ranges.range(&main.dfg, v6), Some(ValueRange::new(0, 297)));
+
+        // v6 may be less than v1, so the subtraction may wrap around
+        assert_eq!(ranges.range(&main.dfg, v7), None);
+    }
+}
@@ -0,0 +1,146 @@
+//! This file is for serializing the SSA IR into a compact binary form so that it may be cached
+//! between compilations, e.g. to skip regenerating a function's SSA when its monomorphized AST is
+//! unchanged.
+//!
+//! Each serialized program or function begins with a header containing the bytes "NSSA" followed
+//! by the format version as a little-endian u32. Data written with a different format version is
+//! rejected rather than misinterpreted, so callers can treat it as a cache miss.
+use serde::{de::DeserializeOwned, Serialize};
+use thiserror::Error;
+
+use crate::ssa_refactor::ssa_gen::Ssa;
+
+use super::function::Function;
+
+/// The bytes every serialized program or function starts with.
+const MAGIC: [u8; 4] = *b"NSSA";
+
+/// The version of the binary format of the SSA IR.
+///
+/// This must be incremented whenever the serialized representation of any IR type changes,
+/// including adding, removing, or reordering fields or enum variants.
+pub(crate) const SSA_FORMAT_VERSION: u32 = 2;
+
+const HEADER_LENGTH: usize = MAGIC.len() + std::mem::size_of::<u32>();
+
+#[derive(Debug, Error)]
+pub enum SsaSerializationError {
+    #[error("Serialized SSA is missing its header")]
+    MissingHeader,
+    #[error("Serialized SSA has format version {found}, but version {expected} is required")]
+    UnsupportedVersion { found: u32, expected: u32 },
+    #[error("Failed to (de)serialize SSA: {0}")]
+    Bincode(#[from] bincode::Error),
+    #[error("Failed to read or write serialized SSA: {0}")]
+    Io(#[from] std::io::Error),
+}
+
+impl Ssa {
+    /// Serializes the entire program, including the id of its main function.
+    pub fn to_bytes(&self) -> Result<Vec<u8>, SsaSerializationError> {
+        serialize_with_header(self)
+    }
+
+    /// Deserializes a program previously serialized with `Ssa::to_bytes`.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Ssa, SsaSerializationError> {
+        deserialize_with_header(bytes)
+    }
+}
+
+impl Function {
+    /// Serializes a single function so that it may be cached separately from the rest
+    /// of the program. Any FunctionIds referenced by the function are kept as-is, so the
+    /// function must be restored into a program which uses the same ids.
+    pub(crate) fn to_bytes(&self) -> Result<Vec<u8>, SsaSerializationError> {
+        serialize_with_header(self)
+    }
+
+    /// Deserializes a function previously serialized with `Function::to_bytes`.
+    pub(crate) fn from_bytes(bytes: &[u8]) -> Result<Function, SsaSerializationError> {
+        deserialize_with_header(bytes)
+    }
+}
+
+fn serialize_with_header<T: Serialize>(value: &T) -> Result<Vec<u8>, SsaSerializationError> {
+    let mut bytes = Vec::with_capacity(HEADER_LENGTH);
+    bytes.extend_from_slice(&MAGIC);
+    bytes.extend_from_slice(&SSA_FORMAT_VERSION.to_le_bytes());
+    bincode::serialize_into(&mut bytes, value)?;
+    Ok(bytes)
+}
+
+fn deserialize_with_header<T: DeserializeOwned>(bytes: &[u8]) -> Result<T, SsaSerializationError> {
+    if bytes.len() < HEADER_LENGTH || bytes[..MAGIC.len()] != MAGIC {
+        return Err(SsaSerializationError::MissingHeader);
+    }
+
+    let (header, body) = bytes.split_at(HEADER_LENGTH);
+    let version = u32::from_le_bytes(header[MAGIC.len()..].try_into().unwrap());
+    if version != SSA_FORMAT_VERSION {
+        return Err(SsaSerializationError::UnsupportedVersion {
+            found: version,
+            expected: SSA_FORMAT_VERSION,
+        });
+    }
+
+    Ok(bincode::deserialize(body)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use crate::ssa_refactor::{ir::function::Function, ssa_gen::Ssa};
+
+    use super::{SsaSerializationError, SSA_FORMAT_VERSION};
+
+    const SRC: &str = "
+        acir fn main f0 {
+          b0(v0: Field, v1: u1):
+            v2 = add v0, Field 1
+            jmpif v1 then: b1, else: b2
+          b1():
+            v3 = call f1([Field; 2] [v2, Field 2]) -> Field
+            jmp b2()
+          b2():
+            return v2
+        }
+        brillig fn foo f1 {
+          b0(v0: [Field; 2]):
+            v1 = array_get v0, index Field 1 -> Field
+            return v1
+        }
+    ";
+
+    #[test]
+    fn round_trips_programs() {
+        let ssa = Ssa::from_str(SRC).unwrap();
+        let bytes = ssa.to_bytes().unwrap();
+
+        let deserialized = Ssa::from_bytes(&bytes).unwrap();
+        assert_eq!(deserialized.to_string(), ssa.to_string());
+        assert_eq!(deserialized.main_id, ssa.main_id);
+    }
+
+    #[test]
+    fn round_trips_functions() {
+        let ssa = Ssa::from_str(SRC).unwrap();
+        let bytes = ssa.main().to_bytes().unwrap();
+
+        let deserialized = Function::from_bytes(&bytes).unwrap();
+        assert_eq!(deserialized.to_string(), ssa.main().to_string());
+    }
+
+    #[test]
+    fn rejects_other_versions() {
+        let ssa = Ssa::from_str(SRC).unwrap();
+        let mut bytes = ssa.to_bytes().unwrap();
+        bytes[4..8].copy_from_slice(&(SSA_FORMAT_VERSION + 1).to_le_bytes());
+
+        assert!(matches!(
+            Ssa::from_bytes(&bytes),
+            Err(SsaSerializationError::UnsupportedVersion { .. })
+        ));
+        assert!(matches!(Ssa::from_bytes(b"SSA"), Err(SsaSerializationError::MissingHeader)));
+    }
+}
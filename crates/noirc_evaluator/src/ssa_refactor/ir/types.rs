@@ -1,6 +1,7 @@
 use std::rc::Rc;
 
 use iter_extended::vecmap;
+use serde::{Deserialize, Serialize};
 
 /// A numeric type in the Intermediate representation
 /// Note: we class NativeField as a numeric type
@@ -10,7 +11,7 @@ use iter_extended::vecmap;
 ///
 /// Fields do not have a notion of ordering, so this distinction
 /// is reasonable.
-#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, Ord, PartialOrd)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, Ord, PartialOrd, Serialize, Deserialize)]
 pub(crate) enum NumericType {
     Signed { bit_size: u32 },
     Unsigned { bit_size: u32 },
@@ -18,7 +19,7 @@ pub(crate) enum NumericType {
 }
 
 /// All types representable in the IR.
-#[derive(Clone, Debug, PartialEq, Eq, Hash, Ord, PartialOrd)]
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Ord, PartialOrd, Serialize, Deserialize)]
 pub(crate) enum Type {
     /// Represents numeric types in the IR, including field elements
     Numeric(NumericType),
@@ -61,6 +62,14 @@ impl Type {
     pub(crate) fn field() -> Type {
         Type::Numeric(NumericType::NativeField)
     }
+
+    /// Returns the number of flattened fields in each element of an array or slice type.
+    pub(crate) fn element_size(&self) -> usize {
+        match self {
+            Type::Array(elements, _) | Type::Slice(elements) => elements.len(),
+            other => panic!("ICE: tried to take the element size of non-array type {other}"),
+        }
+    }
 }
 
 /// Composite Types are essentially flattened struct or tuple types.
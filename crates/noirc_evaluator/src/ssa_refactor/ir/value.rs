@@ -1,4 +1,5 @@
 use acvm::FieldElement;
+use serde::{Deserialize, Serialize};
 
 use crate::ssa_refactor::ir::basic_block::BasicBlockId;
 
@@ -13,7 +14,7 @@ pub(crate) type ValueId = Id<Value>;
 
 /// Value is the most basic type allowed in the IR.
 /// Transition Note: A Id<Value> is similar to `NodeId` in our previous IR.
-#[derive(Debug, PartialEq, Eq, Hash, Clone)]
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Serialize, Deserialize)]
 pub(crate) enum Value {
     /// This value was created due to an instruction
     ///
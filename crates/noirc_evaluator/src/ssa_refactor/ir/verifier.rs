@@ -0,0 +1,493 @@
+//! The SSA verifier checks that the invariants the optimization passes rely on hold for every
+//! reachable block of every function:
+//! - Each block ends in a well-formed terminator. Jumps pass one argument of the correct type for
+//!   each parameter of their destination, and conditional jumps branch on a `u1` to blocks
+//!   without parameters.
+//! - Each value used is defined in a block which dominates its use, and if it is defined in the
+//!   same block then it is defined before its use.
+//! - The operands of each instruction have the types that instruction expects.
+//! - Each call to a known function passes arguments of the types the function's parameters expect
+//!   and expects results of the types the function returns.
+//!
+//! Verification is meant to be run between passes to catch a broken invariant in the pass which
+//! introduced it, rather than in a later pass or in ACIR generation.
+use std::collections::HashMap;
+
+use thiserror::Error;
+
+use crate::ssa_refactor::ssa_gen::Ssa;
+
+use super::{
+    basic_block::BasicBlockId,
+    dom::DominatorTree,
+    function::{Function, FunctionId},
+    instruction::{BinaryOp, Instruction, InstructionId, TerminatorInstruction},
+    types::Type,
+    value::{Value, ValueId},
+};
+
+#[derive(Debug, Error, PartialEq, Eq)]
+#[error("SSA verification failed in {function_name} ({function}){}: {kind}", block_name(.block))]
+pub(crate) struct SsaVerificationError {
+    pub(crate) function: FunctionId,
+    pub(crate) function_name: String,
+    /// The block containing the error, if the error is within a single block
+    pub(crate) block: Option<BasicBlockId>,
+    pub(crate) kind: VerificationErrorKind,
+}
+
+fn block_name(block: &Option<BasicBlockId>) -> String {
+    block.map(|block| format!(", block {block}")).unwrap_or_default()
+}
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub(crate) enum VerificationErrorKind {
+    #[error("Block has no terminator")]
+    MissingTerminator,
+    #[error("Jump to {0} which does not exist")]
+    UndefinedBlock(BasicBlockId),
+    #[error("Jump to {destination} passes {found} argument(s) but the block expects {expected}")]
+    JumpArgumentCount { destination: BasicBlockId, expected: usize, found: usize },
+    #[error("Conditional jump to {0} which has block parameters")]
+    ConditionalJumpToBlockWithParameters(BasicBlockId),
+    #[error("{value} is used before it is defined")]
+    UseNotDominated { value: ValueId },
+    #[error("{value} is defined by an instruction which is not within any block")]
+    DefinedOutsideOfBlocks { value: ValueId },
+    #[error("Expected {value} to have type {expected} but found {found}")]
+    TypeMismatch { value: ValueId, expected: String, found: Type },
+    #[error("Call to {callee} passes {found} argument(s) but it expects {expected}")]
+    CallArgumentCount { callee: FunctionId, expected: usize, found: usize },
+    #[error("Call to {callee} expects {found} result(s) but it returns {expected}")]
+    CallResultCount { callee: FunctionId, expected: usize, found: usize },
+    #[error("Call to {0} which does not exist")]
+    UndefinedFunction(FunctionId),
+}
+
+impl Ssa {
+    /// Verifies each function within the program, returning the first error found.
+    pub(crate) fn verify(&self) -> Result<(), SsaVerificationError> {
+        // Signatures are computed up front since a function may be called many times
+        let signatures: HashMap<_, _> = self
+            .functions
+            .iter()
+            .map(|(id, function)| (*id, FunctionSignature::new(function)))
+            .collect();
+
+        for function in self.functions.values() {
+            FunctionVerifier::new(function, &signatures).verify()?;
+        }
+        Ok(())
+    }
+}
+
+/// The parameter and return types of a function. The return types are None
+/// if the function has no reachable return instruction.
+struct FunctionSignature {
+    params: Vec<Type>,
+    returns: Option<Vec<Type>>,
+}
+
+impl FunctionSignature {
+    fn new(function: &Function) -> Self {
+        let params = function.parameters().iter();
+        let params = params.map(|param| function.dfg.type_of_value(*param)).collect();
+
+        let returns = function.reachable_blocks().into_iter().find_map(|block| {
+            match function.dfg[block].terminator() {
+                Some(TerminatorInstruction::Return { return_values }) => Some(
+                    return_values.iter().map(|value| function.dfg.type_of_value(*value)).collect(),
+                ),
+                _ => None,
+            }
+        });
+
+        FunctionSignature { params, returns }
+    }
+}
+
+struct FunctionVerifier<'f> {
+    function: &'f Function,
+    signatures: &'f HashMap<FunctionId, FunctionSignature>,
+    dom_tree: DominatorTree,
+
+    /// Maps each instruction within a reachable block to that block
+    /// and the instruction's index within the block.
+    instruction_locations: HashMap<InstructionId, (BasicBlockId, usize)>,
+}
+
+impl<'f> FunctionVerifier<'f> {
+    fn new(function: &'f Function, signatures: &'f HashMap<FunctionId, FunctionSignature>) -> Self {
+        let mut instruction_locations = HashMap::new();
+        for block in function.reachable_blocks() {
+            for (index, instruction) in function.dfg[block].instructions().iter().enumerate() {
+                instruction_locations.insert(*instruction, (block, index));
+            }
+        }
+
+        let dom_tree = DominatorTree::with_function(function);
+        Self { function, signatures, dom_tree, instruction_locations }
+    }
+
+    fn verify(mut self) -> Result<(), SsaVerificationError> {
+        // Visit blocks in a deterministic order so the same error is always reported first
+        let mut blocks: Vec<_> = self.function.reachable_blocks().into_iter().collect();
+        blocks.sort();
+
+        for block in blocks {
+            self.verify_block(block).map_err(|kind| self.error(Some(block), kind))?;
+        }
+        Ok(())
+    }
+
+    fn error(
+        &self,
+        block: Option<BasicBlockId>,
+        kind: VerificationErrorKind,
+    ) -> SsaVerificationError {
+        SsaVerificationError {
+            function: self.function.id(),
+            function_name: self.function.name().to_owned(),
+            block,
+            kind,
+        }
+    }
+
+    fn verify_block(&mut self, block: BasicBlockId) -> Result<(), VerificationErrorKind> {
+        let function = self.function;
+        let dfg = &function.dfg;
+
+        for (index, instruction_id) in dfg[block].instructions().iter().enumerate() {
+            let instruction = &dfg[*instruction_id];
+
+            let mut operands = Vec::new();
+            instruction.for_each_value(|value| operands.push(value));
+            for operand in operands {
+                self.verify_use(operand, block, index)?;
+            }
+
+            self.verify_instruction(instruction, dfg.instruction_results(*instruction_id))?;
+        }
+
+        let terminator = dfg[block].terminator().ok_or(VerificationErrorKind::MissingTerminator)?;
+
+        // Terminators are treated as the last instruction of their block
+        let terminator_index = dfg[block].instructions().len();
+        let mut operands = Vec::new();
+        terminator.for_each_value(|value| operands.push(value));
+        for operand in operands {
+            self.verify_use(operand, block, terminator_index)?;
+        }
+
+        self.verify_terminator(terminator)
+    }
+
+    /// Verifies that the given value is defined before the instruction at
+    /// `index` within `block` which uses it.
+    fn verify_use(
+        &mut self,
+        value: ValueId,
+        block: BasicBlockId,
+        index: usize,
+    ) -> Result<(), VerificationErrorKind> {
+        let function = self.function;
+        let value = function.dfg.resolve(value);
+
+        let is_dominated = match &function.dfg[value] {
+            Value::Instruction { instruction, .. } => {
+                let (definition_block, definition_index) = *self
+                    .instruction_locations
+                    .get(instruction)
+                    .ok_or(VerificationErrorKind::DefinedOutsideOfBlocks { value })?;
+
+                if definition_block == block {
+                    definition_index < index
+                } else {
+                    self.dom_tree.dominates(definition_block, block)
+                }
+            }
+            Value::Param { block: definition_block, .. } => {
+                self.dom_tree.is_reachable(*definition_block)
+                    && self.dom_tree.dominates(*definition_block, block)
+            }
+            Value::Array { array, .. } => {
+                for element in array.clone() {
+                    self.verify_use(element, block, index)?;
+                }
+                true
+            }
+            Value::NumericConstant { .. }
+            | Value::Function(_)
+            | Value::Intrinsic(_)
+            | Value::ForeignFunction(_) => true,
+        };
+
+        if is_dominated {
+            Ok(())
+        } else {
+            Err(VerificationErrorKind::UseNotDominated { value })
+        }
+    }
+
+    fn verify_instruction(
+        &self,
+        instruction: &Instruction,
+        results: &[ValueId],
+    ) -> Result<(), VerificationErrorKind> {
+        match instruction {
+            Instruction::Binary(binary) => {
+                self.expect_numeric(binary.lhs)?;
+                // The bit size of a shift is not required to match the shifted value
+                if !matches!(binary.operator, BinaryOp::Shl | BinaryOp::Shr) {
+                    let lhs_type = self.function.dfg.type_of_value(binary.lhs);
+                    self.expect_type(binary.rhs, &lhs_type)?;
+                }
+                self.expect_numeric(binary.rhs)
+            }
+            Instruction::Cast(value, _)
+            | Instruction::Not(value)
+            | Instruction::Truncate { value, .. } => self.expect_numeric(*value),
+            Instruction::Constrain(value, _)
+            | Instruction::EnableSideEffects { condition: value } => {
+                self.expect_type(*value, &Type::bool())
+            }
+            Instruction::Load { address } | Instruction::Store { address, .. } => {
+                self.expect_type(*address, &Type::Reference)
+            }
+            Instruction::ArrayGet { array, index } | Instruction::ArraySet { array, index, .. } => {
+                self.expect_array(*array)?;
+                self.expect_numeric(*index)
+            }
+            Instruction::Call { func, arguments } => self.verify_call(*func, arguments, results),
+            Instruction::Allocate => Ok(()),
+        }
+    }
+
+    /// Verifies a call against the signature of the function called, if it is known.
+    fn verify_call(
+        &self,
+        func: ValueId,
+        arguments: &[ValueId],
+        results: &[ValueId],
+    ) -> Result<(), VerificationErrorKind> {
+        let dfg = &self.function.dfg;
+        let callee = match &dfg[dfg.resolve(func)] {
+            Value::Function(callee) => *callee,
+            _ => return Ok(()),
+        };
+
+        let signature =
+            self.signatures.get(&callee).ok_or(VerificationErrorKind::UndefinedFunction(callee))?;
+
+        if arguments.len() != signature.params.len() {
+            let (expected, found) = (signature.params.len(), arguments.len());
+            return Err(VerificationErrorKind::CallArgumentCount { callee, expected, found });
+        }
+        for (argument, param_type) in arguments.iter().zip(&signature.params) {
+            self.expect_type(*argument, param_type)?;
+        }
+
+        if let Some(returns) = &signature.returns {
+            if results.len() != returns.len() {
+                let (expected, found) = (returns.len(), results.len());
+                return Err(VerificationErrorKind::CallResultCount { callee, expected, found });
+            }
+            for (result, return_type) in results.iter().zip(returns) {
+                self.expect_type(*result, return_type)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn verify_terminator(
+        &self,
+        terminator: &TerminatorInstruction,
+    ) -> Result<(), VerificationErrorKind> {
+        let dfg = &self.function.dfg;
+        let block_exists = |block: BasicBlockId| block.to_usize() < dfg.basic_blocks_iter().len();
+
+        match terminator {
+            TerminatorInstruction::Jmp { destination, arguments } => {
+                if !block_exists(*destination) {
+                    return Err(VerificationErrorKind::UndefinedBlock(*destination));
+                }
+
+                let parameters = dfg.block_parameters(*destination);
+                if arguments.len() != parameters.len() {
+                    return Err(VerificationErrorKind::JumpArgumentCount {
+                        destination: *destination,
+                        expected: parameters.len(),
+                        found: arguments.len(),
+                    });
+                }
+
+                for (argument, parameter) in arguments.iter().zip(parameters) {
+                    self.expect_type(*argument, &dfg.type_of_value(*parameter))?;
+                }
+                Ok(())
+            }
+            TerminatorInstruction::JmpIf { condition, then_destination, else_destination } => {
+                self.expect_type(*condition, &Type::bool())?;
+
+                for destination in [*then_destination, *else_destination] {
+                    if !block_exists(destination) {
+                        return Err(VerificationErrorKind::UndefinedBlock(destination));
+                    }
+                    if !dfg.block_parameters(destination).is_empty() {
+                        return Err(VerificationErrorKind::ConditionalJumpToBlockWithParameters(
+                            destination,
+                        ));
+                    }
+                }
+                Ok(())
+            }
+            TerminatorInstruction::Return { .. } => Ok(()),
+        }
+    }
+
+    fn expect_type(&self, value: ValueId, expected: &Type) -> Result<(), VerificationErrorKind> {
+        let found = self.function.dfg.type_of_value(value);
+        if found == *expected {
+            Ok(())
+        } else {
+            Err(VerificationErrorKind::TypeMismatch {
+                value,
+                expected: expected.to_string(),
+                found,
+            })
+        }
+    }
+
+    fn expect_numeric(&self, value: ValueId) -> Result<(), VerificationErrorKind> {
+        match self.function.dfg.type_of_value(value) {
+            Type::Numeric(_) => Ok(()),
+            found => {
+                let expected = "a numeric type".to_owned();
+                Err(VerificationErrorKind::TypeMismatch { value, expected, found })
+            }
+        }
+    }
+
+    fn expect_array(&self, value: ValueId) -> Result<(), VerificationErrorKind> {
+        match self.function.dfg.type_of_value(value) {
+            Type::Array(..) | Type::Slice(_) => Ok(()),
+            found => {
+                let expected = "an array or slice".to_owned();
+                Err(VerificationErrorKind::TypeMismatch { value, expected, found })
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use crate::ssa_refactor::{
+        ir::{map::Id, types::Type},
+        ssa_gen::Ssa,
+    };
+
+    use super::VerificationErrorKind;
+
+    fn verify(src: &str) -> Result<(), VerificationErrorKind> {
+        Ssa::from_str(src).unwrap().verify().map_err(|error| error.kind)
+    }
+
+    #[test]
+    fn accepts_valid_ssa() {
+        let src = "
+            acir fn main f0 {
+              b0(v0: Field, v1: u1):
+                v2 = add v0, Field 1
+                jmpif v1 then: b1, else: b2
+              b1():
+                v3 = call f1(v2) -> Field
+                jmp b3(v3)
+              b2():
+                jmp b3(v2)
+              b3(v4: Field):
+                return v4
+            }
+            brillig fn foo f1 {
+              b0(v0: Field):
+                return v0
+            }
+        ";
+        assert_eq!(verify(src), Ok(()));
+    }
+
+    #[test]
+    fn rejects_uses_in_non_dominated_blocks() {
+        // v2 is defined in b1, which does not dominate b2
+        let src = "
+            acir fn main f0 {
+              b0(v0: Field, v1: u1):
+                jmpif v1 then: b1, else: b2
+              b1():
+                v2 = add v0, Field 1
+                jmp b2()
+              b2():
+                return v2
+            }
+        ";
+        let value = Id::test_new(3);
+        assert_eq!(verify(src), Err(VerificationErrorKind::UseNotDominated { value }));
+    }
+
+    #[test]
+    fn rejects_mismatched_types() {
+        let src = "
+            acir fn main f0 {
+              b0(v0: Field, v1: u32):
+                v2 = add v0, v1
+                return v2
+            }
+        ";
+        let error = VerificationErrorKind::TypeMismatch {
+            value: Id::test_new(1),
+            expected: "Field".to_owned(),
+            found: Type::unsigned(32),
+        };
+        assert_eq!(verify(src), Err(error));
+    }
+
+    #[test]
+    fn rejects_mismatched_jump_arguments() {
+        let src = "
+            acir fn main f0 {
+              b0(v0: Field):
+                jmp b1()
+              b1(v1: Field):
+                return v1
+            }
+        ";
+        let error = VerificationErrorKind::JumpArgumentCount {
+            destination: Id::test_new(1),
+            expected: 1,
+            found: 0,
+        };
+        assert_eq!(verify(src), Err(error));
+    }
+
+    #[test]
+    fn rejects_calls_with_the_wrong_signature() {
+        let src = "
+            acir fn main f0 {
+              b0(v0: Field):
+                v1 = call f1(v0, v0) -> Field
+                return v1
+            }
+            acir fn foo f1 {
+              b0(v0: Field):
+                return v0
+            }
+        ";
+        let error = VerificationErrorKind::CallArgumentCount {
+            callee: Id::test_new(1),
+            expected: 1,
+            found: 2,
+        };
+        assert_eq!(verify(src), Err(error));
+    }
+}
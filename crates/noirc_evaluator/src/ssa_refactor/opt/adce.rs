@@ -0,0 +1,493 @@
+//! Aggressive Dead Code Elimination (ADCE) pass: Removes any instruction, block parameter, or
+//! branch which does not contribute to the function's side-effects or return values.
+//!
+//! Unlike `dead_instruction_elimination`, which assumes every instruction referenced by a
+//! terminator is needed, this pass starts by assuming everything is dead and only marks code as
+//! live when it is required:
+//! - Instructions with side-effects and the values returned by the function are live.
+//! - The operands of a live instruction are live.
+//! - A block parameter is live if it is used by live code, in which case each argument passed
+//!   to it is live as well.
+//! - Each branch that a block containing live code is control dependent on is live, along with
+//!   its condition.
+//!
+//! Any `jmpif` which is not live is replaced with a `jmp` to its immediate post-dominator,
+//! which removes the entire region of blocks between the two.
+use std::collections::{HashMap, HashSet};
+
+use crate::ssa_refactor::{
+    ir::{
+        basic_block::BasicBlockId,
+        cfg::ControlFlowGraph,
+        dfg::DataFlowGraph,
+        function::Function,
+        instruction::{Instruction, InstructionId, TerminatorInstruction},
+        post_order::PostOrder,
+        value::{Value, ValueId},
+    },
+    ssa_gen::Ssa,
+};
+
+impl Ssa {
+    /// Performs Aggressive Dead Code Elimination (ADCE) to remove any instructions and
+    /// branches which are not needed to compute a function's side-effects or return values.
+    pub(crate) fn aggressive_dead_code_elimination(mut self) -> Ssa {
+        for function in self.functions.values_mut() {
+            aggressive_dead_code_elimination(function);
+        }
+        self
+    }
+}
+
+fn aggressive_dead_code_elimination(function: &mut Function) {
+    let mut context = Context::new(function);
+    context.mark_live_code(function);
+    context.remove_dead_code(function);
+}
+
+struct Context {
+    cfg: ControlFlowGraph,
+
+    /// Each reachable block, in post order
+    blocks: Vec<BasicBlockId>,
+
+    /// Maps each reachable block to the set of blocks which post-dominate it
+    post_dominators: HashMap<BasicBlockId, HashSet<BasicBlockId>>,
+
+    /// Maps each reachable block to the blocks ending in a `jmpif` which it is control dependent on
+    control_dependencies: HashMap<BasicBlockId, Vec<BasicBlockId>>,
+
+    /// Maps each instruction in a reachable block to the block containing it
+    instruction_blocks: HashMap<InstructionId, BasicBlockId>,
+
+    live_instructions: HashSet<InstructionId>,
+    live_parameters: HashSet<ValueId>,
+    live_blocks: HashSet<BasicBlockId>,
+
+    /// The blocks whose `jmpif` terminator is live
+    live_branches: HashSet<BasicBlockId>,
+
+    values_to_visit: Vec<ValueId>,
+    blocks_to_visit: Vec<BasicBlockId>,
+}
+
+impl Context {
+    fn new(function: &Function) -> Self {
+        let cfg = ControlFlowGraph::with_function(function);
+        let blocks = PostOrder::with_function(function).as_slice().to_vec();
+
+        let post_dominators = compute_post_dominators(function, &blocks);
+        let control_dependencies =
+            compute_control_dependencies(function, &blocks, &post_dominators);
+
+        let mut instruction_blocks = HashMap::new();
+        for block in &blocks {
+            for instruction in function.dfg[*block].instructions() {
+                instruction_blocks.insert(*instruction, *block);
+            }
+        }
+
+        Context {
+            cfg,
+            blocks,
+            post_dominators,
+            control_dependencies,
+            instruction_blocks,
+            live_instructions: HashSet::new(),
+            live_parameters: HashSet::new(),
+            live_blocks: HashSet::new(),
+            live_branches: HashSet::new(),
+            values_to_visit: Vec::new(),
+            blocks_to_visit: Vec::new(),
+        }
+    }
+
+    fn mark_live_code(&mut self, function: &Function) {
+        self.mark_block_live(function.entry_block());
+
+        for block in self.blocks.clone() {
+            for instruction in function.dfg[block].instructions() {
+                if has_side_effects(&function.dfg[*instruction]) {
+                    self.mark_instruction_live(&function.dfg, *instruction);
+                }
+            }
+
+            if let Some(TerminatorInstruction::Return { return_values }) =
+                function.dfg[block].terminator()
+            {
+                self.mark_block_live(block);
+                self.values_to_visit.extend(return_values);
+            }
+        }
+
+        loop {
+            self.visit_worklists(function);
+
+            // A dead branch is replaced with a jump to its immediate post-dominator, which is only
+            // possible if that block has no live parameters which would need arguments.
+            let mut changed = false;
+            for block in self.blocks.clone() {
+                if !self.is_dead_branch(function, block) {
+                    continue;
+                }
+
+                let can_be_removed = self
+                    .immediate_post_dominator(block)
+                    .map_or(false, |destination| !self.has_live_parameters(function, destination));
+
+                if !can_be_removed {
+                    self.mark_branch_live(function, block);
+                    changed = true;
+                }
+            }
+
+            if !changed {
+                break;
+            }
+        }
+    }
+
+    fn visit_worklists(&mut self, function: &Function) {
+        loop {
+            if let Some(value) = self.values_to_visit.pop() {
+                self.visit_value(&function.dfg, value);
+            } else if let Some(block) = self.blocks_to_visit.pop() {
+                let branches = self.control_dependencies.get(&block).cloned().unwrap_or_default();
+                for branch in branches {
+                    self.mark_branch_live(function, branch);
+                }
+            } else {
+                break;
+            }
+        }
+    }
+
+    fn visit_value(&mut self, dfg: &DataFlowGraph, value: ValueId) {
+        let value = dfg.resolve(value);
+        match &dfg[value] {
+            Value::Instruction { instruction, .. } => {
+                self.mark_instruction_live(dfg, *instruction);
+            }
+            Value::Param { block, position, .. } => {
+                if self.live_parameters.insert(value) {
+                    for predecessor in self.cfg.predecessors(*block).collect::<Vec<_>>() {
+                        if let Some(TerminatorInstruction::Jmp { arguments, .. }) =
+                            dfg[predecessor].terminator()
+                        {
+                            self.mark_block_live(predecessor);
+                            self.values_to_visit.push(arguments[*position]);
+                        }
+                    }
+                }
+            }
+            Value::Array { array, .. } => {
+                self.values_to_visit.extend(array);
+            }
+            _ => {
+                // Does not comprise of any instruction results or block parameters
+            }
+        }
+    }
+
+    fn mark_instruction_live(&mut self, dfg: &DataFlowGraph, instruction: InstructionId) {
+        // Instructions outside of the reachable blocks are never live
+        let block = match self.instruction_blocks.get(&instruction) {
+            Some(block) => *block,
+            None => return,
+        };
+
+        if self.live_instructions.insert(instruction) {
+            self.mark_block_live(block);
+            dfg[instruction].for_each_value(|value| self.values_to_visit.push(value));
+        }
+    }
+
+    fn mark_block_live(&mut self, block: BasicBlockId) {
+        if self.live_blocks.insert(block) {
+            self.blocks_to_visit.push(block);
+        }
+    }
+
+    fn mark_branch_live(&mut self, function: &Function, block: BasicBlockId) {
+        if self.live_branches.insert(block) {
+            self.mark_block_live(block);
+            if let Some(TerminatorInstruction::JmpIf { condition, .. }) =
+                function.dfg[block].terminator()
+            {
+                self.values_to_visit.push(*condition);
+            }
+        }
+    }
+
+    fn is_dead_branch(&self, function: &Function, block: BasicBlockId) -> bool {
+        matches!(function.dfg[block].terminator(), Some(TerminatorInstruction::JmpIf { .. }))
+            && !self.live_branches.contains(&block)
+    }
+
+    fn has_live_parameters(&self, function: &Function, block: BasicBlockId) -> bool {
+        function
+            .dfg
+            .block_parameters(block)
+            .iter()
+            .any(|param| self.live_parameters.contains(param))
+    }
+
+    /// Returns the closest block, other than the given block, which post-dominates the given block.
+    ///
+    /// The post-dominators of a block form a chain, so the closest post-dominator
+    /// is the one with the most post-dominators of its own.
+    fn immediate_post_dominator(&self, block: BasicBlockId) -> Option<BasicBlockId> {
+        self.post_dominators[&block]
+            .iter()
+            .filter(|post_dominator| **post_dominator != block)
+            .max_by_key(|post_dominator| self.post_dominators[*post_dominator].len())
+            .copied()
+    }
+
+    fn remove_dead_code(&self, function: &mut Function) {
+        for block in &self.blocks {
+            function.dfg[*block]
+                .instructions_mut()
+                .retain(|instruction| self.live_instructions.contains(instruction));
+
+            if self.is_dead_branch(function, *block) {
+                let destination = self
+                    .immediate_post_dominator(*block)
+                    .expect("ICE: dead branch should have an immediate post-dominator");
+
+                let jmp = TerminatorInstruction::Jmp { destination, arguments: Vec::new() };
+                function.dfg[*block].set_terminator(jmp);
+            }
+        }
+
+        // Remove each dead block parameter along with the arguments passed to it.
+        // The entry block's parameters are the function's parameters so they must be kept.
+        let mut live_parameter_positions = HashMap::new();
+        for block in &self.blocks {
+            if *block == function.entry_block() {
+                continue;
+            }
+
+            let parameters = function.dfg.block_parameters(*block);
+            let is_live = parameters
+                .iter()
+                .map(|parameter| self.live_parameters.contains(parameter))
+                .collect::<Vec<_>>();
+
+            let live_parameters =
+                parameters.iter().filter(|parameter| self.live_parameters.contains(parameter));
+            let live_parameters = live_parameters.copied().collect();
+
+            function.dfg[*block].set_parameters(live_parameters);
+            live_parameter_positions.insert(*block, is_live);
+        }
+
+        for block in &self.blocks {
+            if let TerminatorInstruction::Jmp { destination, arguments } =
+                function.dfg[*block].unwrap_terminator_mut()
+            {
+                if let Some(is_live) = live_parameter_positions.get(destination) {
+                    let mut is_live = is_live.iter();
+                    arguments.retain(|_| *is_live.next().unwrap());
+                }
+            }
+        }
+    }
+}
+
+/// Returns true if the given instruction cannot be removed even if its results are unused.
+fn has_side_effects(instruction: &Instruction) -> bool {
+    use Instruction::*;
+    matches!(instruction, Constrain(..) | Call { .. } | Store { .. } | EnableSideEffects { .. })
+}
+
+/// Computes the post-dominators of each of the given blocks, which are expected to be in post order.
+///
+/// A block `p` post-dominates a block `b` if every path from `b` to a return passes through `p`.
+/// Each block post-dominates itself.
+fn compute_post_dominators(
+    function: &Function,
+    blocks: &[BasicBlockId],
+) -> HashMap<BasicBlockId, HashSet<BasicBlockId>> {
+    let all_blocks: HashSet<_> = blocks.iter().copied().collect();
+    let mut post_dominators: HashMap<_, _> =
+        blocks.iter().map(|block| (*block, all_blocks.clone())).collect();
+
+    let mut changed = true;
+    while changed {
+        changed = false;
+
+        for block in blocks {
+            let mut new_post_dominators: Option<HashSet<BasicBlockId>> = None;
+            for successor in function.dfg[*block].successors() {
+                let successor_post_dominators = &post_dominators[&successor];
+                new_post_dominators = Some(match new_post_dominators {
+                    Some(set) => set.intersection(successor_post_dominators).copied().collect(),
+                    None => successor_post_dominators.clone(),
+                });
+            }
+
+            let mut new_post_dominators = new_post_dominators.unwrap_or_default();
+            new_post_dominators.insert(*block);
+
+            if new_post_dominators != post_dominators[block] {
+                post_dominators.insert(*block, new_post_dominators);
+                changed = true;
+            }
+        }
+    }
+
+    post_dominators
+}
+
+/// Computes the blocks ending in a `jmpif` which each block is control dependent on.
+///
+/// A block `b` is control dependent on a block `a` if `b` post-dominates a successor of `a`,
+/// but does not strictly post-dominate `a` itself. In other words, `a` decides whether
+/// `b` is executed.
+fn compute_control_dependencies(
+    function: &Function,
+    blocks: &[BasicBlockId],
+    post_dominators: &HashMap<BasicBlockId, HashSet<BasicBlockId>>,
+) -> HashMap<BasicBlockId, Vec<BasicBlockId>> {
+    let mut control_dependencies: HashMap<BasicBlockId, Vec<BasicBlockId>> = HashMap::new();
+
+    for block in blocks {
+        if !matches!(function.dfg[*block].terminator(), Some(TerminatorInstruction::JmpIf { .. })) {
+            continue;
+        }
+
+        let block_post_dominators = &post_dominators[block];
+        let mut dependents = HashSet::new();
+
+        for successor in function.dfg[*block].successors() {
+            for post_dominator in &post_dominators[&successor] {
+                if *post_dominator == *block || !block_post_dominators.contains(post_dominator) {
+                    dependents.insert(*post_dominator);
+                }
+            }
+        }
+
+        for dependent in dependents {
+            control_dependencies.entry(dependent).or_default().push(*block);
+        }
+    }
+
+    control_dependencies
+}
+
+#[cfg(test)]
+mod test {
+    use crate::ssa_refactor::{
+        ir::{
+            function::RuntimeType,
+            instruction::{BinaryOp, TerminatorInstruction},
+            map::Id,
+            types::Type,
+        },
+        ssa_builder::FunctionBuilder,
+    };
+
+    #[test]
+    fn removes_dead_branch() {
+        // fn main f0 {
+        //   b0(v0: u1, v1: Field):
+        //     jmpif v0 then: b1, else: b2
+        //   b1():
+        //     v2 = mul v1, v1
+        //     jmp b3(v2)
+        //   b2():
+        //     jmp b3(v1)
+        //   b3(v3: Field):
+        //     return v1
+        // }
+        let main_id = Id::test_new(0);
+        let mut builder = FunctionBuilder::new("main".into(), main_id, RuntimeType::Acir);
+        let v0 = builder.add_parameter(Type::bool());
+        let v1 = builder.add_parameter(Type::field());
+
+        let b1 = builder.insert_block();
+        let b2 = builder.insert_block();
+        let b3 = builder.insert_block();
+
+        builder.terminate_with_jmpif(v0, b1, b2);
+
+        builder.switch_to_block(b1);
+        let v2 = builder.insert_binary(v1, BinaryOp::Mul, v1);
+        builder.terminate_with_jmp(b3, vec![v2]);
+
+        builder.switch_to_block(b2);
+        builder.terminate_with_jmp(b3, vec![v1]);
+
+        builder.switch_to_block(b3);
+        builder.add_block_parameter(b3, Type::field());
+        builder.terminate_with_return(vec![v1]);
+
+        // Expected output:
+        //
+        // fn main f0 {
+        //   b0(v0: u1, v1: Field):
+        //     jmp b3()
+        //   b3():
+        //     return v1
+        // }
+        let ssa = builder.finish().aggressive_dead_code_elimination();
+        let main = ssa.main();
+
+        match main.dfg[main.entry_block()].terminator() {
+            Some(TerminatorInstruction::Jmp { destination, arguments }) => {
+                assert_eq!(*destination, b3);
+                assert!(arguments.is_empty());
+            }
+            _ => unreachable!("b0 should end in a jmp to b3"),
+        }
+
+        assert!(main.dfg.block_parameters(b3).is_empty());
+        assert_eq!(main.reachable_blocks().len(), 2);
+    }
+
+    #[test]
+    fn keeps_branch_with_side_effects() {
+        // fn main f0 {
+        //   b0(v0: u1, v1: Field):
+        //     v2 = mul v1, v1
+        //     jmpif v0 then: b1, else: b2
+        //   b1():
+        //     v3 = eq v2, Field 4
+        //     constrain v3
+        //     jmp b2()
+        //   b2():
+        //     return v1
+        // }
+        let main_id = Id::test_new(0);
+        let mut builder = FunctionBuilder::new("main".into(), main_id, RuntimeType::Acir);
+        let v0 = builder.add_parameter(Type::bool());
+        let v1 = builder.add_parameter(Type::field());
+
+        let b1 = builder.insert_block();
+        let b2 = builder.insert_block();
+
+        let four = builder.field_constant(4u128);
+        let v2 = builder.insert_binary(v1, BinaryOp::Mul, v1);
+        builder.terminate_with_jmpif(v0, b1, b2);
+
+        builder.switch_to_block(b1);
+        let v3 = builder.insert_binary(v2, BinaryOp::Eq, four);
+        builder.insert_constrain(v3, None);
+        builder.terminate_with_jmp(b2, vec![]);
+
+        builder.switch_to_block(b2);
+        builder.terminate_with_return(vec![v1]);
+
+        let ssa = builder.finish().aggressive_dead_code_elimination();
+        let main = ssa.main();
+
+        assert_eq!(main.dfg[main.entry_block()].instructions().len(), 1);
+        assert!(matches!(
+            main.dfg[main.entry_block()].terminator(),
+            Some(TerminatorInstruction::JmpIf { .. })
+        ));
+        assert_eq!(main.dfg[b1].instructions().len(), 2);
+        assert_eq!(main.reachable_blocks().len(), 3);
+    }
+}
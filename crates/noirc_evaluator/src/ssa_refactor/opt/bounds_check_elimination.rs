@@ -0,0 +1,205 @@
+//! Bounds check elimination removes the guards added by flattening to array accesses whose
+//! indices are known to be in bounds.
+//!
+//! Flattening multiplies the index of each array access within a branch by the branch's
+//! condition, so that an access within a branch which is not taken uses index 0 rather than an
+//! index which may be out of bounds. The guard is unnecessary if the index is always in bounds:
+//! the result of an access within a branch which is not taken is never used, so it makes no
+//! difference which in-bounds element is accessed.
+//!
+//! Indices are proven to be in bounds by [RangeAnalysis], for example an index masked with `i & 7`
+//! for an array of length 8, or an index previously constrained to be less than the array's
+//! length. Flattening already leaves the indices known to be in bounds beforehand unguarded, so
+//! this pass removes the guards of those only proven to be in bounds once the function has been
+//! flattened and simplified. The instructions computing a removed guard are left for dead
+//! instruction elimination.
+use crate::ssa_refactor::{
+    ir::{
+        basic_block::BasicBlockId,
+        dfg::DataFlowGraph,
+        instruction::{Binary, BinaryOp, Instruction},
+        range_analysis::RangeAnalysis,
+        types::Type,
+        value::{Value, ValueId},
+    },
+    ssa_gen::Ssa,
+};
+
+impl Ssa {
+    /// Removes the guards of array accesses within each function whose indices are known to be
+    /// in bounds.
+    pub(crate) fn eliminate_bounds_checks(mut self) -> Ssa {
+        for function in self.functions.values_mut() {
+            let ranges = RangeAnalysis::with_function(function);
+            for block in function.reachable_blocks() {
+                eliminate_bounds_checks_in_block(&mut function.dfg, block, &ranges);
+            }
+        }
+        self
+    }
+}
+
+fn eliminate_bounds_checks_in_block(
+    dfg: &mut DataFlowGraph,
+    block: BasicBlockId,
+    ranges: &RangeAnalysis,
+) {
+    let mut side_effects_condition = None;
+
+    for instruction in dfg[block].instructions().to_vec() {
+        let unguarded = match &dfg[instruction] {
+            Instruction::EnableSideEffects { condition } => {
+                side_effects_condition = Some(dfg.resolve(*condition));
+                None
+            }
+            access => side_effects_condition
+                .and_then(|condition| remove_guard(dfg, ranges, block, access, condition)),
+        };
+        if let Some(unguarded) = unguarded {
+            dfg[instruction] = unguarded;
+        }
+    }
+}
+
+/// Returns the given array access without its guard, if its index is guarded by the given side
+/// effects condition and the unguarded index is known to be in bounds.
+fn remove_guard(
+    dfg: &DataFlowGraph,
+    ranges: &RangeAnalysis,
+    block: BasicBlockId,
+    instruction: &Instruction,
+    condition: ValueId,
+) -> Option<Instruction> {
+    let (array, index) = match instruction {
+        Instruction::ArrayGet { array, index } | Instruction::ArraySet { array, index, .. } => {
+            (*array, *index)
+        }
+        _ => return None,
+    };
+
+    let index = guarded_index(dfg, index, condition)?;
+    if !index_in_bounds(dfg, ranges, block, array, index) {
+        return None;
+    }
+
+    Some(match instruction {
+        Instruction::ArrayGet { array, .. } => Instruction::ArrayGet { array: *array, index },
+        Instruction::ArraySet { array, value, .. } => {
+            Instruction::ArraySet { array: *array, index, value: *value }
+        }
+        _ => unreachable!("Expected an array access"),
+    })
+}
+
+/// Returns true if `index` is known to be within the bounds of `array` within the given block.
+pub(super) fn index_in_bounds(
+    dfg: &DataFlowGraph,
+    ranges: &RangeAnalysis,
+    block: BasicBlockId,
+    array: ValueId,
+    index: ValueId,
+) -> bool {
+    let length = match dfg.type_of_value(array) {
+        Type::Array(element_types, length) => element_types.len() * length,
+        _ => return false,
+    };
+    ranges.range_in_block(dfg, block, index).map_or(false, |range| range.max < length as u128)
+}
+
+/// Returns `i` if the given index is `i * cast(condition)`, as created by flattening.
+fn guarded_index(dfg: &DataFlowGraph, index: ValueId, condition: ValueId) -> Option<ValueId> {
+    let (lhs, rhs) = match defining_instruction(dfg, index) {
+        Some(Instruction::Binary(Binary { lhs, rhs, operator: BinaryOp::Mul })) => {
+            (dfg.resolve(*lhs), dfg.resolve(*rhs))
+        }
+        _ => return None,
+    };
+
+    let is_condition = |value: ValueId| {
+        value == condition
+            || matches!(defining_instruction(dfg, value),
+                Some(Instruction::Cast(value, _)) if dfg.resolve(*value) == condition)
+    };
+
+    if is_condition(rhs) {
+        Some(lhs)
+    } else if is_condition(lhs) {
+        Some(rhs)
+    } else {
+        None
+    }
+}
+
+fn defining_instruction(dfg: &DataFlowGraph, value: ValueId) -> Option<&Instruction> {
+    match &dfg[dfg.resolve(value)] {
+        Value::Instruction { instruction, .. } => Some(&dfg[*instruction]),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::str::FromStr;
+
+    use crate::ssa_refactor::{ir::parser::assert_ssa_equals, ssa_gen::Ssa};
+
+    #[test]
+    fn removes_guards_of_indices_in_bounds() {
+        let src = "
+            acir fn main f0 {
+              b0(v0: u1, v1: [Field; 8], v2: u32, v3: u32):
+                v4 = lt v3, u32 8
+                constrain v4
+                enable_side_effects v0
+                v5 = and v2, u32 7
+                v6 = cast v0 as u32
+                v7 = mul v5, v6
+                v8 = array_get v1, index v7 -> Field
+                v9 = mul v2, v6
+                v10 = array_get v1, index v9 -> Field
+                v11 = mul v6, v3
+                v12 = array_set v1, index v11, value Field 1
+                enable_side_effects u1 1
+                return v8, v10, v12
+            }
+        ";
+        let expected = "
+            acir fn main f0 {
+              b0(v0: u1, v1: [Field; 8], v2: u32, v3: u32):
+                v4 = lt v3, u32 8
+                constrain v4
+                enable_side_effects v0
+                v5 = and v2, u32 7
+                v6 = cast v0 as u32
+                v7 = mul v5, v6
+                v8 = array_get v1, index v5 -> Field
+                v9 = mul v2, v6
+                v10 = array_get v1, index v9 -> Field
+                v11 = mul v6, v3
+                v12 = array_set v1, index v3, value Field 1
+                enable_side_effects u1 1
+                return v8, v10, v12
+            }
+        ";
+        let ssa = Ssa::from_str(src).unwrap().eliminate_bounds_checks();
+        assert_ssa_equals(&ssa, expected);
+    }
+
+    #[test]
+    fn keeps_guards_of_other_conditions() {
+        let src = "
+            acir fn main f0 {
+              b0(v0: u1, v1: u1, v2: [Field; 8], v3: u32):
+                v4 = and v3, u32 7
+                enable_side_effects v0
+                v5 = cast v1 as u32
+                v6 = mul v4, v5
+                v7 = array_get v2, index v6 -> Field
+                enable_side_effects u1 1
+                return v7
+            }
+        ";
+        let ssa = Ssa::from_str(src).unwrap().eliminate_bounds_checks();
+        assert_ssa_equals(&ssa, src);
+    }
+}
@@ -0,0 +1,185 @@
+//! Redundant cast elimination removes casts which are unnecessary given the cast before them.
+//!
+//! Code which converts values back and forth between fields and sized integers leaves pairs of
+//! casts like `v1 = cast v0 as Field; v2 = cast v1 as u8` behind. In ACIR, each cast into an
+//! unsigned integer which may not fit truncates its operand, which requires a range constraint.
+//! For a pair `cast(cast(x, T), U)`:
+//! - If the inner cast is lossless, i.e. `x` is known to fit within `T`, the pair becomes
+//!   `cast(x, U)`, or simply `x` if `x` already has type `U`.
+//! - If `T` and `U` are both unsigned integers and `U` is no larger than `T`, truncating to `T`
+//!   first makes no difference, so the pair also becomes `cast(x, U)`.
+//!
+//! Whether `x` fits within `T` is decided by its type where possible, and otherwise by
+//! [RangeAnalysis]. Casts of constants are folded into constants. Inner casts which are no longer
+//! used are left for dead instruction elimination. Signed integers are never simplified.
+use crate::ssa_refactor::{
+    ir::{
+        basic_block::BasicBlockId,
+        dfg::DataFlowGraph,
+        instruction::{Instruction, SimplifyResult},
+        range_analysis::RangeAnalysis,
+        types::{NumericType, Type},
+        value::{Value, ValueId},
+    },
+    ssa_gen::Ssa,
+};
+
+impl Ssa {
+    /// Removes casts within each function which are made redundant by the cast before them, and
+    /// folds casts of constants.
+    pub(crate) fn eliminate_redundant_casts(mut self) -> Ssa {
+        for function in self.functions.values_mut() {
+            let ranges = RangeAnalysis::with_function(function);
+            for block in function.reachable_blocks() {
+                eliminate_casts_in_block(&mut function.dfg, block, &ranges);
+            }
+        }
+        self
+    }
+}
+
+fn eliminate_casts_in_block(dfg: &mut DataFlowGraph, block: BasicBlockId, ranges: &RangeAnalysis) {
+    let instructions = std::mem::take(dfg[block].instructions_mut());
+    let mut new_instructions = Vec::with_capacity(instructions.len());
+
+    for id in instructions {
+        let (value, typ) = match &dfg[id] {
+            Instruction::Cast(value, typ) => (dfg.resolve(*value), typ.clone()),
+            _ => {
+                new_instructions.push(id);
+                continue;
+            }
+        };
+        let result = dfg.instruction_results(id)[0];
+
+        let value = match defining_instruction(dfg, value) {
+            Some(Instruction::Cast(inner_value, inner_type))
+                if is_redundant(dfg, ranges, block, *inner_value, inner_type, &typ) =>
+            {
+                dfg.resolve(*inner_value)
+            }
+            _ => value,
+        };
+
+        let cast = Instruction::Cast(value, typ);
+        match cast.simplify(dfg, block) {
+            SimplifyResult::SimplifiedTo(simplified) => {
+                dfg.set_value_from_id(result, simplified);
+                continue;
+            }
+            _ => dfg[id] = cast,
+        }
+        new_instructions.push(id);
+    }
+
+    *dfg[block].instructions_mut() = new_instructions;
+}
+
+/// Returns true if `cast(cast(value, inner_type), outer_type)` within the given block is
+/// equivalent to `cast(value, outer_type)`.
+fn is_redundant(
+    dfg: &DataFlowGraph,
+    ranges: &RangeAnalysis,
+    block: BasicBlockId,
+    value: ValueId,
+    inner_type: &Type,
+    outer_type: &Type,
+) -> bool {
+    let value_type = dfg.type_of_value(value);
+    if [&value_type, inner_type, outer_type].into_iter().any(is_signed) {
+        return false;
+    }
+
+    match (inner_type, outer_type) {
+        (Type::Numeric(NumericType::NativeField), _) => true,
+        (
+            Type::Numeric(NumericType::Unsigned { bit_size: inner_size }),
+            Type::Numeric(NumericType::Unsigned { bit_size: outer_size }),
+        ) if outer_size <= inner_size => true,
+        (Type::Numeric(NumericType::Unsigned { bit_size }), _) => match value_type {
+            Type::Numeric(NumericType::Unsigned { bit_size: value_size })
+                if value_size <= *bit_size =>
+            {
+                true
+            }
+            _ => ranges
+                .range_in_block(dfg, block, value)
+                .map_or(false, |range| range.fits_in(*bit_size)),
+        },
+        _ => false,
+    }
+}
+
+fn is_signed(typ: &Type) -> bool {
+    matches!(typ, Type::Numeric(NumericType::Signed { .. }))
+}
+
+fn defining_instruction(dfg: &DataFlowGraph, value: ValueId) -> Option<&Instruction> {
+    match &dfg[dfg.resolve(value)] {
+        Value::Instruction { instruction, .. } => Some(&dfg[*instruction]),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::str::FromStr;
+
+    use crate::ssa_refactor::{ir::parser::assert_ssa_equals, ssa_gen::Ssa};
+
+    #[test]
+    fn removes_redundant_casts() {
+        let src = "
+            acir fn main f0 {
+              b0(v0: u8, v1: Field, v2: u32):
+                v3 = cast v0 as Field
+                v4 = cast v3 as u8
+                v5 = cast v1 as u32
+                v6 = cast v5 as u8
+                v7 = cast v2 as u8
+                v8 = cast v7 as u32
+                v9 = cast u32 300 as u8
+                return v4, v6, v8, v9
+            }
+        ";
+        let expected = "
+            acir fn main f0 {
+              b0(v0: u8, v1: Field, v2: u32):
+                v3 = cast v0 as Field
+                v5 = cast v1 as u32
+                v6 = cast v1 as u8
+                v7 = cast v2 as u8
+                v8 = cast v7 as u32
+                return v0, v6, v8, u8 44
+            }
+        ";
+        let ssa = Ssa::from_str(src).unwrap().eliminate_redundant_casts();
+        assert_ssa_equals(&ssa, expected);
+    }
+
+    #[test]
+    fn removes_casts_of_values_known_to_fit() {
+        // v1 is constrained to be less than 256, so casting it to u8 is lossless
+        let src = "
+            acir fn main f0 {
+              b0(v0: u32):
+                v1 = lt v0, u32 256
+                constrain v1
+                v2 = cast v0 as u8
+                v3 = cast v2 as u32
+                return v3
+            }
+        ";
+        let expected = "
+            acir fn main f0 {
+              b0(v0: u32):
+                v1 = lt v0, u32 256
+                constrain v1
+                v2 = cast v0 as u8
+                return v0
+            }
+        ";
+        let ssa = Ssa::from_str(src).unwrap().eliminate_redundant_casts();
+        assert_ssa_equals(&ssa, expected);
+    }
+}
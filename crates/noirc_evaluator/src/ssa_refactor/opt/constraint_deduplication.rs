@@ -0,0 +1,175 @@
+//! Constraint deduplication removes each constraint which is implied by an earlier constraint
+//! on the same value within the same block.
+//!
+//! Flattening predicates each constraint within a branch on the branch's condition `c`, turning
+//! `constrain v` into `constrain (v * c) == c`. Once a program is flattened, the same value is
+//! often constrained in several branches, e.g. by an assertion inlined into each branch of an if
+//! expression. A constraint of `v` under the predicate `p` is redundant if `v` was already
+//! constrained under a predicate which holds whenever `p` holds. The predicates of nested
+//! branches are combined via `And` instructions, so a predicate implies another if it contains
+//! every condition combined within the other. A constraint without a predicate implies every
+//! other constraint of the same value.
+//!
+//! The instructions computing the removed constraints are left for dead instruction elimination.
+use std::collections::{HashMap, HashSet};
+
+use crate::ssa_refactor::{
+    ir::{
+        basic_block::BasicBlockId,
+        dfg::DataFlowGraph,
+        instruction::{BinaryOp, Instruction},
+        types::Type,
+        value::{Value, ValueId},
+    },
+    ssa_gen::Ssa,
+};
+
+impl Ssa {
+    /// Removes each constraint within each function which is implied by an earlier constraint.
+    pub(crate) fn deduplicate_constraints(mut self) -> Ssa {
+        for function in self.functions.values_mut() {
+            for block in function.reachable_blocks() {
+                deduplicate_constraints_in_block(&mut function.dfg, block);
+            }
+        }
+        self
+    }
+}
+
+fn deduplicate_constraints_in_block(dfg: &mut DataFlowGraph, block: BasicBlockId) {
+    // Maps each constrained value to the conditions of each predicate it was constrained under
+    let mut constrained_values: HashMap<ValueId, Vec<HashSet<ValueId>>> = HashMap::new();
+    let mut redundant_constraints = HashSet::new();
+
+    for instruction in dfg[block].instructions() {
+        if let Instruction::Constrain(value, _) = &dfg[*instruction] {
+            let (value, predicate) = split_predicate(dfg, *value);
+            let conditions = predicate_conditions(dfg, predicate);
+
+            let predicates = constrained_values.entry(value).or_default();
+            if predicates.iter().any(|earlier| earlier.is_subset(&conditions)) {
+                redundant_constraints.insert(*instruction);
+            } else {
+                predicates.push(conditions);
+            }
+        }
+    }
+
+    dfg[block]
+        .instructions_mut()
+        .retain(|instruction| !redundant_constraints.contains(instruction));
+}
+
+/// Splits a constrained value into the value asserted and the predicate it is asserted under,
+/// if it has the form `(value * predicate) == predicate` produced by flattening.
+fn split_predicate(dfg: &DataFlowGraph, constrained: ValueId) -> (ValueId, Option<ValueId>) {
+    let constrained = dfg.resolve(constrained);
+    if let Some((lhs, rhs)) = defining_binary(dfg, constrained, BinaryOp::Eq) {
+        for (product, predicate) in [(lhs, rhs), (rhs, lhs)] {
+            match defining_binary(dfg, product, BinaryOp::Mul) {
+                Some((value, factor)) | Some((factor, value)) if factor == predicate => {
+                    return (value, Some(predicate));
+                }
+                _ => (),
+            }
+        }
+    }
+    (constrained, None)
+}
+
+/// Returns each condition combined via `And` instructions into the given predicate. The
+/// predicate holds exactly when every condition holds, and no predicate is always true.
+fn predicate_conditions(dfg: &DataFlowGraph, predicate: Option<ValueId>) -> HashSet<ValueId> {
+    let mut conditions = HashSet::new();
+    let mut pending: Vec<_> = predicate.into_iter().collect();
+
+    while let Some(condition) = pending.pop() {
+        let condition = dfg.resolve(condition);
+        if dfg.get_numeric_constant(condition).map_or(false, |constant| constant.is_one()) {
+            continue;
+        }
+
+        match defining_binary(dfg, condition, BinaryOp::And) {
+            Some((lhs, rhs)) if dfg.type_of_value(condition) == Type::bool() => {
+                pending.push(lhs);
+                pending.push(rhs);
+            }
+            _ => {
+                conditions.insert(condition);
+            }
+        }
+    }
+    conditions
+}
+
+/// Returns the resolved operands of the binary instruction defining the given value, if any
+fn defining_binary(
+    dfg: &DataFlowGraph,
+    value: ValueId,
+    operator: BinaryOp,
+) -> Option<(ValueId, ValueId)> {
+    match &dfg[dfg.resolve(value)] {
+        Value::Instruction { instruction, .. } => match &dfg[*instruction] {
+            Instruction::Binary(binary) if binary.operator == operator => {
+                Some((dfg.resolve(binary.lhs), dfg.resolve(binary.rhs)))
+            }
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::str::FromStr;
+
+    use crate::ssa_refactor::{ir::parser::assert_ssa_equals, ssa_gen::Ssa};
+
+    #[test]
+    fn removes_constraints_implied_by_earlier_constraints() {
+        let src = "
+            acir fn main f0 {
+              b0(v0: u1, v1: u1, v2: u1):
+                v3 = and v0, v1
+                enable_side_effects v0
+                v4 = mul v2, v0
+                v5 = eq v4, v0
+                constrain v5
+                enable_side_effects v3
+                v6 = mul v2, v3
+                v7 = eq v6, v3
+                constrain v7
+                enable_side_effects v1
+                v8 = mul v1, v2
+                v9 = eq v8, v1
+                constrain v9
+                enable_side_effects u1 1
+                constrain v2
+                constrain v2
+                return
+            }
+        ";
+        let expected = "
+            acir fn main f0 {
+              b0(v0: u1, v1: u1, v2: u1):
+                v3 = and v0, v1
+                enable_side_effects v0
+                v4 = mul v2, v0
+                v5 = eq v4, v0
+                constrain v5
+                enable_side_effects v3
+                v6 = mul v2, v3
+                v7 = eq v6, v3
+                enable_side_effects v1
+                v8 = mul v1, v2
+                v9 = eq v8, v1
+                constrain v9
+                enable_side_effects u1 1
+                constrain v2
+                return
+            }
+        ";
+        let ssa = Ssa::from_str(src).unwrap().deduplicate_constraints();
+        assert_ssa_equals(&ssa, expected);
+    }
+}
@@ -0,0 +1,297 @@
+//! Copy propagation replaces each value which is only a copy of another value with that value,
+//! and removes the block parameter or instruction which created the copy.
+//!
+//! Values are recognized as copies in two cases:
+//! - A block parameter for which every predecessor passes the same argument, ignoring any
+//!   arguments which are the parameter itself from a back edge. These are commonly created for
+//!   the return values of inlined functions and for loop variables which are never modified.
+//! - The merge `c*x + (!c)*x` of the same value from both branches of an if, as created by
+//!   flattening when a variable is assigned to within a branch without being changed.
+//!
+//! Each parameter removed also removes its argument from every jmp to its block, which may in
+//! turn expose more copies, so parameters are checked repeatedly until none are removed.
+use crate::ssa_refactor::{
+    ir::{
+        basic_block::BasicBlockId,
+        cfg::ControlFlowGraph,
+        dfg::DataFlowGraph,
+        function::Function,
+        instruction::{Binary, BinaryOp, Instruction, InstructionId, TerminatorInstruction},
+        value::{Value, ValueId},
+    },
+    ssa_gen::Ssa,
+};
+
+impl Ssa {
+    /// Replaces each copied value within each function with its source, removing the block
+    /// parameters and instructions which created the copies.
+    pub(crate) fn propagate_copies(mut self) -> Ssa {
+        for function in self.functions.values_mut() {
+            while remove_copied_parameters(function) {}
+
+            for block in function.reachable_blocks() {
+                remove_copy_instructions(&mut function.dfg, block);
+            }
+        }
+        self
+    }
+}
+
+/// Removes each block parameter whose arguments are all the same value, replacing the parameter
+/// with that value. Returns true if any parameter was removed.
+fn remove_copied_parameters(function: &mut Function) -> bool {
+    let cfg = ControlFlowGraph::with_function(function);
+    let mut changed = false;
+
+    for block in function.reachable_blocks() {
+        if block == function.entry_block() {
+            continue;
+        }
+
+        let predecessors = cfg.predecessors(block).collect::<Vec<_>>();
+        let parameters = function.dfg.block_parameters(block).to_vec();
+        let mut is_kept = Vec::with_capacity(parameters.len());
+
+        for (index, parameter) in parameters.iter().enumerate() {
+            match copied_argument(&function.dfg, &predecessors, *parameter, index) {
+                Some(source) => {
+                    function.dfg.set_value_from_id(*parameter, source);
+                    is_kept.push(false);
+                }
+                None => is_kept.push(true),
+            }
+        }
+
+        if is_kept.contains(&false) {
+            let kept_parameters = parameters.iter().zip(&is_kept).filter(|(_, kept)| **kept);
+            let kept_parameters = kept_parameters.map(|(parameter, _)| *parameter).collect();
+            function.dfg[block].set_parameters(kept_parameters);
+
+            for predecessor in &predecessors {
+                if let TerminatorInstruction::Jmp { arguments, .. } =
+                    function.dfg[*predecessor].unwrap_terminator_mut()
+                {
+                    let mut is_kept = is_kept.iter();
+                    arguments.retain(|_| *is_kept.next().unwrap());
+                }
+            }
+            changed = true;
+        }
+    }
+    changed
+}
+
+/// Returns the single value passed to the given parameter by each of the block's predecessors,
+/// ignoring any predecessor passing the parameter itself.
+///
+/// Returns None if the predecessors pass different values, or if any predecessor does not end
+/// in a jmp to the block.
+fn copied_argument(
+    dfg: &DataFlowGraph,
+    predecessors: &[BasicBlockId],
+    parameter: ValueId,
+    index: usize,
+) -> Option<ValueId> {
+    let mut source = None;
+
+    for predecessor in predecessors {
+        let argument = match dfg[*predecessor].terminator() {
+            Some(TerminatorInstruction::Jmp { arguments, .. }) => dfg.resolve(arguments[index]),
+            _ => return None,
+        };
+
+        if argument == parameter {
+            continue;
+        }
+        match source {
+            Some(source) if source != argument => return None,
+            _ => source = Some(argument),
+        }
+    }
+    source
+}
+
+/// Removes each instruction within the block whose result is a copy of another value,
+/// replacing its result with that value.
+fn remove_copy_instructions(dfg: &mut DataFlowGraph, block: BasicBlockId) {
+    let instructions = std::mem::take(dfg[block].instructions_mut());
+    let mut new_instructions = Vec::with_capacity(instructions.len());
+
+    for instruction in instructions {
+        match copied_value(dfg, instruction) {
+            Some(source) => {
+                let result = dfg.instruction_results(instruction)[0];
+                dfg.set_value_from_id(result, source);
+            }
+            None => new_instructions.push(instruction),
+        }
+    }
+
+    *dfg[block].instructions_mut() = new_instructions;
+}
+
+/// Returns `x` if the given instruction is `c*x + (!c)*x`, in any operand order.
+fn copied_value(dfg: &DataFlowGraph, instruction: InstructionId) -> Option<ValueId> {
+    let (lhs, rhs) = binary_operands(dfg, &dfg[instruction], BinaryOp::Add)?;
+    let (lhs_lhs, lhs_rhs) = defining_binary(dfg, lhs, BinaryOp::Mul)?;
+    let (rhs_lhs, rhs_rhs) = defining_binary(dfg, rhs, BinaryOp::Mul)?;
+
+    let pairings = [
+        (lhs_lhs, lhs_rhs, rhs_lhs, rhs_rhs),
+        (lhs_lhs, lhs_rhs, rhs_rhs, rhs_lhs),
+        (lhs_rhs, lhs_lhs, rhs_lhs, rhs_rhs),
+        (lhs_rhs, lhs_lhs, rhs_rhs, rhs_lhs),
+    ];
+
+    pairings.into_iter().find_map(|(condition, value, other_condition, other_value)| {
+        let is_merge = value == other_value && are_complements(dfg, condition, other_condition);
+        is_merge.then_some(value)
+    })
+}
+
+/// Returns true if one of the given conditions is the `not` of the other, ignoring the casts
+/// inserted to convert each condition to the type of the merged values.
+fn are_complements(dfg: &DataFlowGraph, condition: ValueId, other_condition: ValueId) -> bool {
+    let condition = strip_cast(dfg, condition);
+    let other_condition = strip_cast(dfg, other_condition);
+
+    is_not(dfg, condition, other_condition) || is_not(dfg, other_condition, condition)
+}
+
+/// Returns true if the given value is `!inner`
+fn is_not(dfg: &DataFlowGraph, value: ValueId, inner: ValueId) -> bool {
+    match defining_instruction(dfg, value) {
+        Some(Instruction::Not(value)) => dfg.resolve(*value) == inner,
+        _ => false,
+    }
+}
+
+fn strip_cast(dfg: &DataFlowGraph, value: ValueId) -> ValueId {
+    match defining_instruction(dfg, value) {
+        Some(Instruction::Cast(inner, _)) => dfg.resolve(*inner),
+        _ => value,
+    }
+}
+
+/// Returns the resolved operands of the given instruction if it is a binary instruction with
+/// the given operator
+fn binary_operands(
+    dfg: &DataFlowGraph,
+    instruction: &Instruction,
+    operator: BinaryOp,
+) -> Option<(ValueId, ValueId)> {
+    match instruction {
+        Instruction::Binary(Binary { lhs, rhs, operator: binary_operator })
+            if *binary_operator == operator =>
+        {
+            Some((dfg.resolve(*lhs), dfg.resolve(*rhs)))
+        }
+        _ => None,
+    }
+}
+
+fn defining_binary(
+    dfg: &DataFlowGraph,
+    value: ValueId,
+    operator: BinaryOp,
+) -> Option<(ValueId, ValueId)> {
+    binary_operands(dfg, defining_instruction(dfg, value)?, operator)
+}
+
+fn defining_instruction(dfg: &DataFlowGraph, value: ValueId) -> Option<&Instruction> {
+    match &dfg[dfg.resolve(value)] {
+        Value::Instruction { instruction, .. } => Some(&dfg[*instruction]),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::str::FromStr;
+
+    use crate::ssa_refactor::{ir::parser::assert_ssa_equals, ssa_gen::Ssa};
+
+    #[test]
+    fn removes_parameters_with_a_single_argument() {
+        let src = "
+            brillig fn main f0 {
+              b0(v0: Field, v1: u1):
+                jmpif v1 then: b1, else: b2
+              b1():
+                jmp b3(v0, Field 1)
+              b2():
+                jmp b3(v0, Field 2)
+              b3(v2: Field, v3: Field):
+                jmp b4(v2)
+              b4(v4: Field):
+                v5 = lt v4, Field 10
+                jmpif v5 then: b5, else: b6
+              b5():
+                v6 = add v3, v4
+                jmp b4(v4)
+              b6():
+                return v4, v3
+            }
+        ";
+        let expected = "
+            brillig fn main f0 {
+              b0(v0: Field, v1: u1):
+                jmpif v1 then: b1, else: b2
+              b1():
+                jmp b3(Field 1)
+              b2():
+                jmp b3(Field 2)
+              b3(v3: Field):
+                jmp b4()
+              b4():
+                v5 = lt v0, Field 10
+                jmpif v5 then: b5, else: b6
+              b5():
+                v6 = add v3, v0
+                jmp b4()
+              b6():
+                return v0, v3
+            }
+        ";
+        let ssa = Ssa::from_str(src).unwrap().propagate_copies();
+        assert_ssa_equals(&ssa, expected);
+    }
+
+    #[test]
+    fn removes_merges_of_the_same_value() {
+        let src = "
+            acir fn main f0 {
+              b0(v0: u1, v1: Field, v2: Field):
+                v3 = not v0
+                v4 = cast v0 as Field
+                v5 = cast v3 as Field
+                v6 = mul v4, v1
+                v7 = mul v5, v1
+                v8 = add v6, v7
+                v9 = mul v4, v1
+                v10 = mul v5, v2
+                v11 = add v9, v10
+                v12 = mul v1, v5
+                v13 = add v12, v6
+                return v8, v11, v13
+            }
+        ";
+        let expected = "
+            acir fn main f0 {
+              b0(v0: u1, v1: Field, v2: Field):
+                v3 = not v0
+                v4 = cast v0 as Field
+                v5 = cast v3 as Field
+                v6 = mul v4, v1
+                v7 = mul v5, v1
+                v9 = mul v4, v1
+                v10 = mul v5, v2
+                v11 = add v9, v10
+                v12 = mul v1, v5
+                return v1, v11, v1
+            }
+        ";
+        let ssa = Ssa::from_str(src).unwrap().propagate_copies();
+        assert_ssa_equals(&ssa, expected);
+    }
+}
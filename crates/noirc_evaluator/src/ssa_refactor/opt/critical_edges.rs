@@ -0,0 +1,160 @@
+//! Critical edge splitting inserts an empty block on each critical edge of a function.
+//!
+//! An edge is critical if its source has several successors and its destination has several
+//! predecessors. Code placed at the end of the source would be executed along its other edges,
+//! and code placed at the start of the destination would be executed along the destination's
+//! other edges, so there is nowhere to insert code which only runs along a critical edge.
+//! Splitting the edge gives such code a block of its own.
+//!
+//! Since the destination of a jmpif never has block parameters, each inserted block is a plain
+//! `jmp` to the destination of its edge. The utilities here update only the function itself, so
+//! passes calling them are responsible for recomputing any analyses of the function's control
+//! flow, such as its `ControlFlowGraph`.
+use std::collections::BTreeMap;
+
+use crate::ssa_refactor::{
+    ir::{
+        basic_block::BasicBlockId, cfg::ControlFlowGraph, function::Function,
+        instruction::TerminatorInstruction,
+    },
+    ssa_gen::Ssa,
+};
+
+impl Ssa {
+    /// Splits each critical edge within each function.
+    pub(crate) fn split_critical_edges(mut self) -> Ssa {
+        for function in self.functions.values_mut() {
+            split_critical_edges(function);
+        }
+        self
+    }
+}
+
+/// Splits each critical edge between reachable blocks of the given function, returning the
+/// block inserted on each edge, keyed by the source and destination of the edge.
+pub(crate) fn split_critical_edges(
+    function: &mut Function,
+) -> BTreeMap<(BasicBlockId, BasicBlockId), BasicBlockId> {
+    let cfg = ControlFlowGraph::with_function(function);
+
+    let mut blocks: Vec<_> = function.reachable_blocks().into_iter().collect();
+    blocks.sort();
+
+    let mut critical_edges = Vec::new();
+    for block in blocks {
+        if cfg.successors(block).len() > 1 {
+            let mut successors: Vec<_> = cfg.successors(block).collect();
+            successors.sort();
+            for successor in successors {
+                if cfg.predecessors(successor).len() > 1 {
+                    critical_edges.push((block, successor));
+                }
+            }
+        }
+    }
+
+    critical_edges
+        .into_iter()
+        .map(|(source, destination)| {
+            ((source, destination), split_edge(function, source, destination))
+        })
+        .collect()
+}
+
+/// Inserts a new block on the edge from `source` to `destination`, returning the new block.
+///
+/// The source jumps to the new block in place of the destination, and the new block jumps to
+/// the destination with any arguments the source passed along the edge. If the source is a jmpif
+/// whose destinations are both `destination`, both are redirected to the new block.
+pub(crate) fn split_edge(
+    function: &mut Function,
+    source: BasicBlockId,
+    destination: BasicBlockId,
+) -> BasicBlockId {
+    assert!(
+        function.dfg[source].successors().any(|successor| successor == destination),
+        "ICE: {source} does not jump to {destination}"
+    );
+
+    let new_block = function.dfg.make_block();
+    let terminator = function.dfg[source].unwrap_terminator_mut();
+    let arguments = match &mut *terminator {
+        TerminatorInstruction::Jmp { arguments, .. } => std::mem::take(arguments),
+        TerminatorInstruction::JmpIf { .. } | TerminatorInstruction::Return { .. } => Vec::new(),
+    };
+    terminator.mutate_blocks(|block| if block == destination { new_block } else { block });
+
+    let jmp = TerminatorInstruction::Jmp { destination, arguments };
+    function.dfg.set_block_terminator(new_block, jmp);
+    new_block
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use crate::ssa_refactor::{ir::parser::assert_ssa_equals, ssa_gen::Ssa};
+
+    use super::split_edge;
+
+    #[test]
+    fn splits_critical_edges() {
+        // The edge from b0 to b2 is critical
+        let src = "
+            acir fn main f0 {
+              b0(v0: u1):
+                jmpif v0 then: b1, else: b2
+              b1():
+                jmp b2()
+              b2():
+                return v0
+            }
+        ";
+        let expected = "
+            acir fn main f0 {
+              b0(v0: u1):
+                jmpif v0 then: b1, else: b3
+              b1():
+                jmp b2()
+              b2():
+                return v0
+              b3():
+                jmp b2()
+            }
+        ";
+        let ssa = Ssa::from_str(src).unwrap().split_critical_edges();
+        assert_ssa_equals(&ssa, expected);
+
+        // Splitting is idempotent
+        let ssa = ssa.split_critical_edges();
+        assert_ssa_equals(&ssa, expected);
+    }
+
+    #[test]
+    fn splits_edges_with_arguments() {
+        let src = "
+            acir fn main f0 {
+              b0(v0: Field):
+                jmp b1(v0)
+              b1(v1: Field):
+                return v1
+            }
+        ";
+        let expected = "
+            acir fn main f0 {
+              b0(v0: Field):
+                jmp b2()
+              b2():
+                jmp b1(v0)
+              b1(v1: Field):
+                return v1
+            }
+        ";
+        let mut ssa = Ssa::from_str(src).unwrap();
+        let function = ssa.main_mut();
+        let entry = function.entry_block();
+        let destination = function.dfg[entry].successors().next().unwrap();
+        split_edge(function, entry, destination);
+        assert_ssa_equals(&ssa, expected);
+    }
+}
@@ -0,0 +1,252 @@
+//! Function deduplication merges functions with identical bodies, redirecting each reference to
+//! a duplicate function to the function it was merged into.
+//!
+//! Monomorphization creates a separate function for each instantiation of a generic function,
+//! even when several instantiations lower to the same SSA, e.g. for types with the same
+//! representation. Each duplicate is otherwise generated as separate brillig bytecode, or
+//! inlined separately.
+//!
+//! Functions are compared by their printed form after renaming each value and block in the order
+//! they are first printed, and replacing each reference a function makes to itself. The names of
+//! functions are ignored, but the runtime of each function must match. Since merging functions
+//! may make their callers identical in turn, functions are merged until no duplicates remain.
+//! The main function is never merged, as it is the entry point of the program.
+use std::collections::{BTreeMap, HashMap};
+
+use crate::ssa_refactor::{
+    ir::{
+        function::{Function, FunctionId},
+        value::Value,
+    },
+    ssa_gen::Ssa,
+};
+
+impl Ssa {
+    /// Merges functions with identical bodies, redirecting each reference to a removed duplicate
+    /// to the function it was merged into.
+    pub(crate) fn deduplicate_functions(mut self) -> Ssa {
+        loop {
+            let duplicates = find_duplicates(&self);
+            if duplicates.is_empty() {
+                return self;
+            }
+
+            for duplicate in duplicates.keys() {
+                self.functions.remove(duplicate);
+            }
+            for function in self.functions.values_mut() {
+                redirect_references(function, &duplicates);
+            }
+        }
+    }
+}
+
+/// Maps each function which is identical to a function with a lower id to that function.
+fn find_duplicates(ssa: &Ssa) -> BTreeMap<FunctionId, FunctionId> {
+    let mut originals: HashMap<String, FunctionId> = HashMap::new();
+    let mut duplicates = BTreeMap::new();
+
+    for (id, function) in &ssa.functions {
+        if *id == ssa.main_id {
+            continue;
+        }
+
+        let body = normalized_body(function);
+        match originals.get(&body) {
+            Some(original) => {
+                duplicates.insert(*id, *original);
+            }
+            None => {
+                originals.insert(body, *id);
+            }
+        }
+    }
+    duplicates
+}
+
+/// Prints the given function without its name or id, replacing the name of each value and block
+/// with the order in which it is first printed, and each reference to the function with `self`.
+fn normalized_body(function: &Function) -> String {
+    let printed = function.to_string();
+    // The first line contains the function's name and id, of which only the runtime is kept
+    let body = printed.split_once('\n').map_or("", |(_, body)| body);
+    let own_id = function.id().to_string();
+
+    let mut names: HashMap<&str, String> = HashMap::new();
+    let mut normalized = function.runtime().to_string();
+    let mut word_start = None;
+
+    // Appending a trailing separator ensures the final word is renamed
+    for (index, character) in body.char_indices().chain(std::iter::once((body.len(), '\n'))) {
+        if character.is_ascii_alphanumeric() || character == '_' {
+            word_start.get_or_insert(index);
+            continue;
+        }
+
+        if let Some(start) = word_start.take() {
+            let word = &body[start..index];
+            if word == own_id {
+                normalized.push_str("self");
+            } else if is_numbered_name(word, 'v') || is_numbered_name(word, 'b') {
+                let count = names.len();
+                let prefix = &word[..1];
+                normalized
+                    .push_str(names.entry(word).or_insert_with(|| format!("{prefix}{count}")));
+            } else {
+                normalized.push_str(word);
+            }
+        }
+        normalized.push(character);
+    }
+    normalized
+}
+
+/// Returns true if the given word is the printed name of a value or block, e.g. `v12` or `b3`.
+fn is_numbered_name(word: &str, prefix: char) -> bool {
+    let digits = word.strip_prefix(prefix).unwrap_or("");
+    !digits.is_empty() && digits.chars().all(|digit| digit.is_ascii_digit())
+}
+
+/// Replaces each reference to a removed duplicate within the given function with a reference to
+/// the function it was merged into.
+fn redirect_references(function: &mut Function, duplicates: &BTreeMap<FunctionId, FunctionId>) {
+    let references: Vec<_> = function
+        .dfg
+        .values_iter()
+        .filter_map(|(value, contents)| match contents {
+            Value::Function(id) => duplicates.get(id).map(|original| (value, *original)),
+            _ => None,
+        })
+        .collect();
+
+    for (value, original) in references {
+        let original = function.dfg.import_function(original);
+        function.dfg.set_value_from_id(value, original);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::str::FromStr;
+
+    use crate::ssa_refactor::{ir::parser::assert_ssa_equals, ssa_gen::Ssa};
+
+    #[test]
+    fn merges_identical_functions() {
+        let src = "
+            acir fn main f0 {
+              b0(v0: Field):
+                v1 = call f1(v0) -> Field
+                v2 = call f2(v1) -> Field
+                v3 = call f3(v2) -> Field
+                return v3
+            }
+            brillig fn double f1 {
+              b0(v0: Field):
+                v1 = add v0, v0
+                return v1
+            }
+            brillig fn double f2 {
+              b0(v3: Field):
+                v4 = add v3, v3
+                return v4
+            }
+            acir fn double f3 {
+              b0(v0: Field):
+                v1 = add v0, v0
+                return v1
+            }
+        ";
+        let expected = "
+            acir fn main f0 {
+              b0(v0: Field):
+                v1 = call f1(v0) -> Field
+                v2 = call f1(v1) -> Field
+                v3 = call f3(v2) -> Field
+                return v3
+            }
+            brillig fn double f1 {
+              b0(v0: Field):
+                v1 = add v0, v0
+                return v1
+            }
+            acir fn double f3 {
+              b0(v0: Field):
+                v1 = add v0, v0
+                return v1
+            }
+        ";
+        let ssa = Ssa::from_str(src).unwrap().deduplicate_functions();
+        assert_ssa_equals(&ssa, expected);
+    }
+
+    #[test]
+    fn merges_callers_of_merged_functions() {
+        let src = "
+            acir fn main f0 {
+              b0(v0: Field):
+                v1 = call f1(v0) -> Field
+                v2 = call f2(v1) -> Field
+                return v2
+            }
+            brillig fn outer f1 {
+              b0(v0: Field):
+                v1 = call f3(v0) -> Field
+                return v1
+            }
+            brillig fn outer f2 {
+              b0(v0: Field):
+                v1 = call f4(v0) -> Field
+                return v1
+            }
+            brillig fn countdown f3 {
+              b0(v0: Field):
+                v1 = eq v0, Field 0
+                jmpif v1 then: b1, else: b2
+              b1():
+                return v0
+              b2():
+                v2 = sub v0, Field 1
+                v3 = call f3(v2) -> Field
+                return v3
+            }
+            brillig fn countdown f4 {
+              b0(v0: Field):
+                v1 = eq v0, Field 0
+                jmpif v1 then: b1, else: b2
+              b1():
+                return v0
+              b2():
+                v2 = sub v0, Field 1
+                v3 = call f4(v2) -> Field
+                return v3
+            }
+        ";
+        let expected = "
+            acir fn main f0 {
+              b0(v0: Field):
+                v1 = call f1(v0) -> Field
+                v2 = call f1(v1) -> Field
+                return v2
+            }
+            brillig fn outer f1 {
+              b0(v0: Field):
+                v1 = call f3(v0) -> Field
+                return v1
+            }
+            brillig fn countdown f3 {
+              b0(v0: Field):
+                v1 = eq v0, Field 0
+                jmpif v1 then: b1, else: b2
+              b1():
+                return v0
+              b2():
+                v2 = sub v0, Field 1
+                v3 = call f3(v2) -> Field
+                return v3
+            }
+        ";
+        let ssa = Ssa::from_str(src).unwrap().deduplicate_functions();
+        assert_ssa_equals(&ssa, expected);
+    }
+}
@@ -4,6 +4,12 @@
 //! with a non-literal target can be replaced with a call to an apply function.
 //! The apply function is a dispatch function that takes the function id as a parameter
 //! and dispatches to the correct target.
+//!
+//! Separate apply functions are created for calls made from ACIR and unconstrained functions.
+//! Flattening evaluates every comparison within an ACIR apply function regardless of how they
+//! are arranged, so these compare the function id against each target in turn using the cheaper
+//! equality check. Unconstrained apply functions instead perform a binary search over the sorted
+//! ids of the targets, so each call only makes a logarithmic number of comparisons at runtime.
 use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
 
 use acvm::FieldElement;
@@ -47,15 +53,15 @@ struct ApplyFunction {
 /// And creating apply functions that dispatch to the correct target by runtime comparisons with constants
 #[derive(Debug, Clone)]
 struct DefunctionalizationContext {
-    apply_functions: HashMap<Signature, ApplyFunction>,
+    apply_functions: HashMap<(Signature, RuntimeType), ApplyFunction>,
 }
 
 impl Ssa {
     pub(crate) fn defunctionalize(mut self) -> Ssa {
         // Find all functions used as value that share the same signature
-        let variants = find_variants(&self);
+        let (variants, dispatches) = find_variants(&self);
 
-        let apply_functions = create_apply_functions(&mut self, variants);
+        let apply_functions = create_apply_functions(&mut self, variants, &dispatches);
 
         let context = DefunctionalizationContext { apply_functions };
 
@@ -101,7 +107,7 @@ impl DefunctionalizationContext {
                         };
 
                         // Find the correct apply function
-                        let apply_function = self.get_apply_function(&signature);
+                        let apply_function = self.get_apply_function(signature, func.runtime());
 
                         // Replace the instruction with a call to apply
                         let apply_function_value_id = func.dfg.import_function(apply_function.id);
@@ -147,20 +153,28 @@ impl DefunctionalizationContext {
         }
     }
 
-    /// Returns the apply function for the given signature
-    fn get_apply_function(&self, signature: &Signature) -> ApplyFunction {
-        *self.apply_functions.get(signature).expect("Could not find apply function")
+    /// Returns the apply function for the given signature, called from a function of the given
+    /// runtime
+    fn get_apply_function(&self, signature: Signature, runtime: RuntimeType) -> ApplyFunction {
+        *self.apply_functions.get(&(signature, runtime)).expect("Could not find apply function")
     }
 }
 
-/// Collects all functions used as values that can be called by their signatures
-fn find_variants(ssa: &Ssa) -> BTreeMap<Signature, Vec<FunctionId>> {
+/// Collects all functions used as values that can be called by their signatures, along with
+/// the signature of each dynamic dispatch and the runtime of the function making it
+fn find_variants(
+    ssa: &Ssa,
+) -> (BTreeMap<Signature, Vec<FunctionId>>, HashSet<(Signature, RuntimeType)>) {
     let mut dynamic_dispatches: BTreeSet<Signature> = BTreeSet::new();
+    let mut dispatch_runtimes: HashSet<(Signature, RuntimeType)> = HashSet::new();
     let mut functions_as_values: BTreeSet<FunctionId> = BTreeSet::new();
 
     for function in ssa.functions.values() {
         functions_as_values.extend(find_functions_as_values(function));
-        dynamic_dispatches.extend(find_dynamic_dispatches(function));
+        for signature in find_dynamic_dispatches(function) {
+            dispatch_runtimes.insert((signature.clone(), function.runtime()));
+            dynamic_dispatches.insert(signature);
+        }
     }
 
     let mut signature_to_functions_as_value: BTreeMap<Signature, Vec<FunctionId>> = BTreeMap::new();
@@ -182,7 +196,7 @@ fn find_variants(ssa: &Ssa) -> BTreeMap<Signature, Vec<FunctionId>> {
         variants.insert(dispatch_signature, target_fns);
     }
 
-    variants
+    (variants, dispatch_runtimes)
 }
 
 /// Finds all literal functions used as values in the given function
@@ -244,7 +258,8 @@ fn find_dynamic_dispatches(func: &Function) -> BTreeSet<Signature> {
 fn create_apply_functions(
     ssa: &mut Ssa,
     variants_map: BTreeMap<Signature, Vec<FunctionId>>,
-) -> HashMap<Signature, ApplyFunction> {
+    dispatches: &HashSet<(Signature, RuntimeType)>,
+) -> HashMap<(Signature, RuntimeType), ApplyFunction> {
     let mut apply_functions = HashMap::new();
     for (signature, variants) in variants_map.into_iter() {
         assert!(
@@ -254,12 +269,23 @@ fn create_apply_functions(
         );
         let dispatches_to_multiple_functions = variants.len() > 1;
 
-        let id = if dispatches_to_multiple_functions {
-            create_apply_function(ssa, signature.clone(), variants)
-        } else {
-            variants[0]
-        };
-        apply_functions.insert(signature, ApplyFunction { id, dispatches_to_multiple_functions });
+        for runtime in [RuntimeType::Acir, RuntimeType::Brillig] {
+            let key = (signature.clone(), runtime);
+            if !dispatches.contains(&key) {
+                continue;
+            }
+
+            let id = match (dispatches_to_multiple_functions, runtime) {
+                (false, _) => variants[0],
+                (true, RuntimeType::Acir) => {
+                    create_apply_function(ssa, signature.clone(), variants.clone())
+                }
+                (true, RuntimeType::Brillig) => {
+                    create_brillig_apply_function(ssa, signature.clone(), variants.clone())
+                }
+            };
+            apply_functions.insert(key, ApplyFunction { id, dispatches_to_multiple_functions });
+        }
     }
     apply_functions
 }
@@ -305,7 +331,7 @@ fn create_apply_function(
                 function_builder.switch_to_block(executor_block);
             } else {
                 // Else just constrain the condition
-                function_builder.insert_constrain(condition);
+                function_builder.insert_constrain(condition, None);
             }
             // Find the target block or build it if necessary
             let current_block = function_builder.current_block();
@@ -336,6 +362,79 @@ fn create_apply_function(
     })
 }
 
+/// Creates an unconstrained apply function for the given signature and variants, which finds
+/// the function to call with a binary search over the ids of the variants.
+fn create_brillig_apply_function(
+    ssa: &mut Ssa,
+    signature: Signature,
+    mut function_ids: Vec<FunctionId>,
+) -> FunctionId {
+    assert!(!function_ids.is_empty());
+    function_ids.sort();
+    ssa.add_fn(|id| {
+        let mut function_builder =
+            FunctionBuilder::new("apply".to_string(), id, RuntimeType::Brillig);
+        let target_id = function_builder.add_parameter(Type::field());
+        let params_ids = vecmap(signature.params, |typ| function_builder.add_parameter(typ));
+
+        let results = build_dispatch_tree(
+            &mut function_builder,
+            target_id,
+            &params_ids,
+            &function_ids,
+            &signature.returns,
+        );
+        function_builder.terminate_with_return(results);
+        function_builder.current_function
+    })
+}
+
+/// Builds the comparisons dispatching to the function with the given target id, which must be
+/// one of the given sorted function ids, starting from the current block. Each comparison splits
+/// the remaining function ids in half, and the call results of both halves are joined in a new
+/// block. Returns the results of the call, which are available from the current block once the
+/// tree has been built.
+fn build_dispatch_tree(
+    builder: &mut FunctionBuilder,
+    target_id: ValueId,
+    arguments: &[ValueId],
+    function_ids: &[FunctionId],
+    returns: &[Type],
+) -> Vec<ValueId> {
+    let field_constant = |builder: &mut FunctionBuilder, function_id| {
+        builder.numeric_constant(function_id_to_field(function_id), Type::field())
+    };
+
+    if let [function_id] = function_ids {
+        let function_id_constant = field_constant(builder, *function_id);
+        let condition = builder.insert_binary(target_id, BinaryOp::Eq, function_id_constant);
+        builder.insert_constrain(condition, None);
+
+        let target_function_value = builder.import_function(*function_id);
+        return builder
+            .insert_call(target_function_value, arguments.to_vec(), returns.to_vec())
+            .to_vec();
+    }
+
+    let (lower_ids, upper_ids) = function_ids.split_at(function_ids.len() / 2);
+    let pivot = field_constant(builder, upper_ids[0]);
+    let is_lower = builder.insert_binary(target_id, BinaryOp::Lt, pivot);
+
+    let lower_block = builder.insert_block();
+    let upper_block = builder.insert_block();
+    let join_block = builder.insert_block();
+    builder.terminate_with_jmpif(is_lower, lower_block, upper_block);
+
+    for (block, function_ids) in [(lower_block, lower_ids), (upper_block, upper_ids)] {
+        builder.switch_to_block(block);
+        let results = build_dispatch_tree(builder, target_id, arguments, function_ids, returns);
+        builder.terminate_with_jmp(join_block, results);
+    }
+
+    builder.switch_to_block(join_block);
+    vecmap(returns, |typ| builder.add_block_parameter(join_block, typ.clone()))
+}
+
 /// Crates a return block, if no previous return exists, it will create a final return
 /// Else, it will create a bypass return block that points to the previous return block
 fn build_return_block(
@@ -355,3 +454,91 @@ fn build_return_block(
     builder.switch_to_block(previous_block);
     return_block
 }
+
+#[cfg(test)]
+mod test {
+    use std::str::FromStr;
+
+    use crate::ssa_refactor::{ir::parser::assert_ssa_equals, ssa_gen::Ssa};
+
+    #[test]
+    fn dispatches_unconstrained_calls_with_a_binary_search() {
+        let src = "
+            brillig fn main f0 {
+              b0(v0: function, v1: Field):
+                v2 = call v0(v1) -> Field
+                v3 = call f4(f1, f2, f3) -> Field
+                return v2
+            }
+            brillig fn one f1 {
+              b0(v0: Field):
+                return v0
+            }
+            brillig fn two f2 {
+              b0(v0: Field):
+                return v0
+            }
+            brillig fn three f3 {
+              b0(v0: Field):
+                return v0
+            }
+            brillig fn take f4 {
+              b0(v0: function, v1: function, v2: function):
+                return Field 0
+            }
+        ";
+        let expected = "
+            brillig fn main f0 {
+              b0(v0: Field, v1: Field):
+                v2 = call f5(v0, v1) -> Field
+                v3 = call f4(Field 1, Field 2, Field 3) -> Field
+                return v2
+            }
+            brillig fn one f1 {
+              b0(v0: Field):
+                return v0
+            }
+            brillig fn two f2 {
+              b0(v0: Field):
+                return v0
+            }
+            brillig fn three f3 {
+              b0(v0: Field):
+                return v0
+            }
+            brillig fn take f4 {
+              b0(v0: Field, v1: Field, v2: Field):
+                return Field 0
+            }
+            brillig fn apply f5 {
+              b0(v0: Field, v1: Field):
+                v2 = lt v0, Field 2
+                jmpif v2 then: b1, else: b2
+              b1():
+                v3 = eq v0, Field 1
+                constrain v3
+                v4 = call f1(v1) -> Field
+                jmp b3(v4)
+              b3(v5: Field):
+                return v5
+              b2():
+                v6 = lt v0, Field 3
+                jmpif v6 then: b4, else: b5
+              b4():
+                v7 = eq v0, Field 2
+                constrain v7
+                v8 = call f2(v1) -> Field
+                jmp b6(v8)
+              b6(v9: Field):
+                jmp b3(v9)
+              b5():
+                v10 = eq v0, Field 3
+                constrain v10
+                v11 = call f3(v1) -> Field
+                jmp b6(v11)
+            }
+        ";
+        let ssa = Ssa::from_str(src).unwrap().defunctionalize();
+        assert_ssa_equals(&ssa, expected);
+    }
+}
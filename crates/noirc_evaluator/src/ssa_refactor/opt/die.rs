@@ -95,7 +95,7 @@ impl Context {
         // These instruction types cannot be removed
         if matches!(
             instruction,
-            Constrain(_) | Call { .. } | Store { .. } | EnableSideEffects { .. }
+            Constrain(..) | Call { .. } | Store { .. } | EnableSideEffects { .. }
         ) {
             return false;
         }
@@ -0,0 +1,155 @@
+//! Side effects cleanup removes `EnableSideEffects` instructions which have no effect.
+//!
+//! Flattening inserts an `EnableSideEffects` instruction at the start and end of each branch it
+//! merges, and constant folding then resolves many of their conditions. This leaves behind many
+//! markers which re-enable the condition which is already active, or which are followed by
+//! another marker before any instruction depending on the condition. Only instructions for which
+//! [Instruction::requires_side_effects_condition] holds depend on the active condition, so within
+//! each block this pass:
+//! - Removes each marker which is superseded by a later marker before any such instruction.
+//! - Removes each marker which enables the condition which is already active. A constant true
+//!   condition is always active at the start of the entry block.
+//! - Moves each remaining marker down to just before the first instruction depending on it.
+//!
+//! A marker which is still unused at the end of a block is kept, since the condition it enables
+//! remains active in the successors of the block, unless the block returns.
+use crate::ssa_refactor::{
+    ir::{
+        basic_block::BasicBlockId,
+        dfg::DataFlowGraph,
+        function::Function,
+        instruction::{Instruction, InstructionId, TerminatorInstruction},
+        value::ValueId,
+    },
+    ssa_gen::Ssa,
+};
+
+impl Ssa {
+    /// Removes each `EnableSideEffects` instruction within each function which is either
+    /// redundant or does not guard any instruction.
+    pub(crate) fn cleanup_side_effects(mut self) -> Ssa {
+        for function in self.functions.values_mut() {
+            cleanup_function(function);
+        }
+        self
+    }
+}
+
+/// A side effects condition, as enabled by an `EnableSideEffects` instruction.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum Condition {
+    Always,
+    Value(ValueId),
+}
+
+impl Condition {
+    fn new(dfg: &DataFlowGraph, condition: ValueId) -> Self {
+        let condition = dfg.resolve(condition);
+        match dfg.get_numeric_constant(condition) {
+            Some(constant) if constant.is_one() => Condition::Always,
+            _ => Condition::Value(condition),
+        }
+    }
+}
+
+fn cleanup_function(function: &mut Function) {
+    let entry_block = function.entry_block();
+    for block in function.reachable_blocks() {
+        // The condition active at the start of any other block depends on its predecessors
+        let active = (block == entry_block).then_some(Condition::Always);
+        cleanup_block(&mut function.dfg, block, active);
+    }
+}
+
+fn cleanup_block(dfg: &mut DataFlowGraph, block: BasicBlockId, mut active: Option<Condition>) {
+    let instructions = std::mem::take(dfg[block].instructions_mut());
+    let mut new_instructions = Vec::with_capacity(instructions.len());
+
+    // The most recent marker, which is not yet needed by any instruction
+    let mut pending: Option<(InstructionId, Condition)> = None;
+
+    for id in instructions {
+        match &dfg[id] {
+            Instruction::EnableSideEffects { condition } => {
+                let condition = Condition::new(dfg, *condition);
+                pending = (active != Some(condition)).then_some((id, condition));
+                continue;
+            }
+            instruction if instruction.requires_side_effects_condition(dfg) => {
+                if let Some((marker, condition)) = pending.take() {
+                    new_instructions.push(marker);
+                    active = Some(condition);
+                }
+            }
+            _ => (),
+        }
+        new_instructions.push(id);
+    }
+
+    if let Some((marker, _)) = pending {
+        if !matches!(dfg[block].terminator(), Some(TerminatorInstruction::Return { .. })) {
+            new_instructions.push(marker);
+        }
+    }
+
+    *dfg[block].instructions_mut() = new_instructions;
+}
+
+#[cfg(test)]
+mod test {
+    use std::str::FromStr;
+
+    use crate::ssa_refactor::{ir::parser::assert_ssa_equals, ssa_gen::Ssa};
+
+    #[test]
+    fn removes_redundant_side_effect_markers() {
+        // Only the divisions depend on the active condition
+        let src = "
+            acir fn main f0 {
+              b0(v0: u1, v1: Field, v2: Field):
+                enable_side_effects v0
+                v3 = add v1, v2
+                enable_side_effects u1 1
+                v4 = div v1, v2
+                enable_side_effects v0
+                v5 = mul v1, v2
+                v6 = div v1, v5
+                enable_side_effects v0
+                v7 = div v2, v1
+                enable_side_effects u1 1
+                return v3, v4, v6, v7
+            }
+        ";
+        let expected = "
+            acir fn main f0 {
+              b0(v0: u1, v1: Field, v2: Field):
+                v3 = add v1, v2
+                v4 = div v1, v2
+                v5 = mul v1, v2
+                enable_side_effects v0
+                v6 = div v1, v5
+                v7 = div v2, v1
+                return v3, v4, v6, v7
+            }
+        ";
+        let ssa = Ssa::from_str(src).unwrap().cleanup_side_effects();
+        assert_ssa_equals(&ssa, expected);
+    }
+
+    #[test]
+    fn keeps_markers_active_in_successors() {
+        let src = "
+            acir fn main f0 {
+              b0(v0: u1, v1: Field):
+                enable_side_effects v0
+                jmp b1()
+              b1():
+                enable_side_effects v0
+                v2 = div v1, Field 3
+                return v2
+            }
+        ";
+        let ssa = Ssa::from_str(src).unwrap().cleanup_side_effects();
+        assert_ssa_equals(&ssa, src);
+    }
+}
@@ -131,6 +131,25 @@
 //!   v11 = mul v4, Field 12
 //!   v12 = add v10, v11
 //!   store v12 at v5         (new store)
+//!
+//! 4. An array access with a non-constant index is guarded by multiplying the index by c, so that
+//!    an access within a branch which is not taken uses index 0 rather than an index which may be
+//!    out of bounds. Indices which range analysis proves to be in bounds are left unguarded.
+//!
+//! v2 = array_get v0, index v1
+//! ============
+//! v2 = cast c as Field
+//! v3 = mul v1, v2
+//! v4 = array_get v0, index v3
+//!
+//! Not every branch needs to be predicated in this way. If the condition of a jmpif is known when
+//! it is reached, e.g. because an enclosing branch was taken on the same condition, only the
+//! destination taken is inlined and the other is discarded. If the branches of a jmpif are short
+//! sequences of instructions without side effects, they may be executed regardless of the
+//! condition. These branches are inlined without any `EnableSideEffects` instructions, leaving
+//! only the merges of their values at the end block. Instructions which narrow integers are not
+//! executed this way, since the values they are given in a branch which is not taken may not fit
+//! within their results.
 use std::collections::{BTreeMap, HashMap, HashSet};
 
 use acvm::FieldElement;
@@ -141,18 +160,25 @@ use crate::ssa_refactor::{
     ir::{
         basic_block::BasicBlockId,
         cfg::ControlFlowGraph,
-        dfg::InsertInstructionResult,
+        dfg::{DataFlowGraph, InsertInstructionResult},
         function::Function,
         function_inserter::FunctionInserter,
         instruction::{BinaryOp, Instruction, InstructionId, TerminatorInstruction},
-        types::Type,
+        range_analysis::RangeAnalysis,
+        types::{NumericType, Type},
         value::ValueId,
     },
     ssa_gen::Ssa,
 };
 
+use super::bounds_check_elimination::index_in_bounds;
+
 mod branch_analysis;
 
+/// The maximum number of instructions both branches of a jmpif may contain in total for them to
+/// be inlined without predicating them on the jmpif's condition
+const MAX_SPECULATED_INSTRUCTIONS: usize = 16;
+
 impl Ssa {
     /// Flattens the control flow graph of main such that the function is left with a
     /// single block containing all instructions and no more control-flow.
@@ -172,6 +198,10 @@ struct Context<'f> {
     /// This ControlFlowGraph is the graph from before the function was modified by this flattening pass.
     cfg: ControlFlowGraph,
 
+    /// The ranges of the function's values from before it was modified by this flattening pass,
+    /// used to leave array indices which are known to be in bounds unguarded.
+    ranges: RangeAnalysis,
+
     /// Maps start of branch -> end of branch
     branch_ends: HashMap<BasicBlockId, BasicBlockId>,
 
@@ -218,10 +248,12 @@ fn flatten_function_cfg(function: &mut Function) {
     }
     let cfg = ControlFlowGraph::with_function(function);
     let branch_ends = branch_analysis::find_branch_ends(function, &cfg);
+    let ranges = RangeAnalysis::with_function(function);
 
     let mut context = Context {
         inserter: FunctionInserter::new(function),
         cfg,
+        ranges,
         store_values: HashMap::new(),
         local_allocations: HashSet::new(),
         branch_ends,
@@ -255,9 +287,33 @@ impl<'f> Context<'f> {
                 let else_block = *else_destination;
                 let then_condition = self.inserter.resolve(old_condition);
 
+                // Only the destination taken needs to be inlined if the condition is known. Since
+                // no condition is pushed, its jmp to the end block is followed as any other jmp.
+                if let Some(constant) =
+                    self.inserter.function.dfg.get_numeric_constant(then_condition)
+                {
+                    let destination = if constant.is_zero() { else_block } else { then_block };
+                    return self.inline_block(destination, &[]);
+                }
+
+                let end = self.branch_ends[&block];
+                let then_cost = self.speculation_cost(then_block, end);
+                let else_cost = self.speculation_cost(else_block, end);
+                // Branches without any instructions have nothing to execute unconditionally
+                let predicated = then_cost.zip(else_cost).map_or(true, |(then_cost, else_cost)| {
+                    let cost = then_cost + else_cost;
+                    cost == 0 || cost > MAX_SPECULATED_INSTRUCTIONS
+                });
+
                 let one = FieldElement::one();
-                let then_branch =
-                    self.inline_branch(block, then_block, old_condition, then_condition, one);
+                let then_branch = self.inline_branch(
+                    block,
+                    then_block,
+                    old_condition,
+                    then_condition,
+                    one,
+                    predicated,
+                );
 
                 let else_condition =
                     self.insert_instruction(Instruction::Not(then_condition), None);
@@ -267,19 +323,26 @@ impl<'f> Context<'f> {
                 // rather than any values created in the 'then' branch.
                 self.undo_stores_in_then_branch(&then_branch);
 
-                let else_branch =
-                    self.inline_branch(block, else_block, old_condition, else_condition, zero);
+                let else_branch = self.inline_branch(
+                    block,
+                    else_block,
+                    old_condition,
+                    else_condition,
+                    zero,
+                    predicated,
+                );
 
                 // We must remember to reset whether side effects are enabled when both branches
                 // end, in addition to resetting the value of old_condition since it is set to
                 // known to be true/false within the then/else branch respectively.
-                self.insert_current_side_effects_enabled();
+                if predicated {
+                    self.insert_current_side_effects_enabled();
+                }
                 self.inserter.map_value(old_condition, old_condition);
 
                 // While there is a condition on the stack we don't compile outside the condition
                 // until it is popped. This ensures we inline the full then and else branches
                 // before continuing from the end of the conditional here where they can be merged properly.
-                let end = self.branch_ends[&block];
                 self.inline_branch_end(end, then_branch, else_branch)
             }
             TerminatorInstruction::Jmp { destination, arguments } => {
@@ -303,6 +366,33 @@ impl<'f> Context<'f> {
         }
     }
 
+    /// Returns the number of instructions within the branch from `start` to the given end block,
+    /// if its blocks form a single sequence whose instructions may all be executed when the
+    /// branch is not taken. Such a branch does not need to be predicated on its condition.
+    fn speculation_cost(&self, start: BasicBlockId, end: BasicBlockId) -> Option<usize> {
+        let dfg = &self.inserter.function.dfg;
+        let mut block = start;
+        let mut cost = 0;
+
+        while block != end {
+            let instructions = dfg[block].instructions();
+            if !instructions.iter().all(|instruction| can_speculate(dfg, &dfg[*instruction])) {
+                return None;
+            }
+
+            cost += instructions.len();
+            if cost > MAX_SPECULATED_INSTRUCTIONS {
+                return None;
+            }
+
+            match dfg[block].unwrap_terminator() {
+                TerminatorInstruction::Jmp { destination, .. } => block = *destination,
+                _ => return None,
+            }
+        }
+        Some(cost)
+    }
+
     /// Push a condition to the stack of conditions.
     ///
     /// This condition should be present while we're inlining each block reachable from the 'then'
@@ -502,7 +592,8 @@ impl<'f> Context<'f> {
     /// of the jmpif instruction is joined back into a single block.
     ///
     /// Within a branch of a jmpif instruction, we can assume the condition of the jmpif to be
-    /// always true or false, depending on which branch we're in.
+    /// always true or false, depending on which branch we're in. Unless the branch is
+    /// `predicated`, side effects are left enabled within it.
     ///
     /// Returns the ending block / join block of this branch.
     fn inline_branch(
@@ -512,6 +603,7 @@ impl<'f> Context<'f> {
         old_condition: ValueId,
         new_condition: ValueId,
         condition_value: FieldElement,
+        predicated: bool,
     ) -> Branch {
         if destination == self.branch_ends[&jmpif_block] {
             // If the branch destination is the same as the end of the branch, this must be the
@@ -527,7 +619,9 @@ impl<'f> Context<'f> {
             }
         } else {
             self.push_condition(jmpif_block, new_condition);
-            self.insert_current_side_effects_enabled();
+            if predicated {
+                self.insert_current_side_effects_enabled();
+            }
             let old_stores = std::mem::take(&mut self.store_values);
             let old_allocations = std::mem::take(&mut self.local_allocations);
 
@@ -665,7 +759,7 @@ impl<'f> Context<'f> {
         let instructions = self.inserter.function.dfg[destination].instructions().to_vec();
 
         for instruction in instructions {
-            self.push_instruction(instruction);
+            self.push_instruction(instruction, destination);
         }
 
         self.handle_terminator(destination)
@@ -677,9 +771,11 @@ impl<'f> Context<'f> {
     /// As a result, the instruction that will be pushed will actually be a new instruction
     /// with a different InstructionId from the original. The results of the given instruction
     /// will also be mapped to the results of the new instruction.
-    fn push_instruction(&mut self, id: InstructionId) {
+    fn push_instruction(&mut self, id: InstructionId, block: BasicBlockId) {
+        let index_in_bounds = self.index_in_bounds(id, block);
         let (instruction, location) = self.inserter.map_instruction(id);
-        let instruction = self.handle_instruction_side_effects(instruction, location);
+        let instruction =
+            self.handle_instruction_side_effects(instruction, location, index_in_bounds);
         let is_allocate = matches!(instruction, Instruction::Allocate);
 
         let entry = self.inserter.function.entry_block();
@@ -693,15 +789,18 @@ impl<'f> Context<'f> {
     }
 
     /// If we are currently in a branch, we need to modify constrain instructions
-    /// to multiply them by the branch's condition (see optimization #1 in the module comment).
+    /// to multiply them by the branch's condition (see optimization #1 in the module comment),
+    /// and guard the indices of array accesses which are not known to be in bounds (see
+    /// optimization #4).
     fn handle_instruction_side_effects(
         &mut self,
         instruction: Instruction,
         location: Option<Location>,
+        index_in_bounds: bool,
     ) -> Instruction {
         if let Some((_, condition)) = self.conditions.last().copied() {
             match instruction {
-                Instruction::Constrain(value) => {
+                Instruction::Constrain(value, message) => {
                     let mul = self.insert_instruction(
                         Instruction::binary(BinaryOp::Mul, value, condition),
                         location,
@@ -710,12 +809,20 @@ impl<'f> Context<'f> {
                         Instruction::binary(BinaryOp::Eq, mul, condition),
                         location,
                     );
-                    Instruction::Constrain(eq)
+                    Instruction::Constrain(eq, message)
                 }
                 Instruction::Store { address, value } => {
                     self.remember_store(address, value);
                     Instruction::Store { address, value }
                 }
+                Instruction::ArrayGet { array, index } if !index_in_bounds => {
+                    let index = self.guard_index(index, condition, location);
+                    Instruction::ArrayGet { array, index }
+                }
+                Instruction::ArraySet { array, index, value } if !index_in_bounds => {
+                    let index = self.guard_index(index, condition, location);
+                    Instruction::ArraySet { array, index, value }
+                }
                 other => other,
             }
         } else {
@@ -723,6 +830,35 @@ impl<'f> Context<'f> {
         }
     }
 
+    /// Returns true if the given instruction of the given block, from before the function was
+    /// modified, is an array access whose index is known to be in bounds.
+    fn index_in_bounds(&self, id: InstructionId, block: BasicBlockId) -> bool {
+        let dfg = &self.inserter.function.dfg;
+        match &dfg[id] {
+            Instruction::ArrayGet { array, index } | Instruction::ArraySet { array, index, .. } => {
+                index_in_bounds(dfg, &self.ranges, block, *array, *index)
+            }
+            _ => false,
+        }
+    }
+
+    /// Multiplies a non-constant array index by the branch's condition, so that the index is 0
+    /// when the branch is not taken (see optimization #4 in the module comment).
+    fn guard_index(
+        &mut self,
+        index: ValueId,
+        condition: ValueId,
+        location: Option<Location>,
+    ) -> ValueId {
+        let dfg = &self.inserter.function.dfg;
+        if dfg.get_numeric_constant(index).is_some() {
+            return index;
+        }
+        let typ = dfg.type_of_value(index);
+        let condition = self.insert_instruction(Instruction::Cast(condition, typ), location);
+        self.insert_instruction(Instruction::binary(BinaryOp::Mul, index, condition), location)
+    }
+
     fn undo_stores_in_then_branch(&mut self, then_branch: &Branch) {
         for (address, store) in &then_branch.store_values {
             let address = *address;
@@ -732,9 +868,42 @@ impl<'f> Context<'f> {
     }
 }
 
+/// Returns true if the given instruction has no side effects and is converted to ACIR without
+/// reference to whether side effects are enabled, so that it may be executed unconditionally.
+///
+/// Truncations and casts to smaller integers are excluded: their inputs in a branch which is not
+/// taken need not fit within their results, which would fail any range constraint placed on them.
+fn can_speculate(dfg: &DataFlowGraph, instruction: &Instruction) -> bool {
+    match instruction {
+        Instruction::Binary(binary) => {
+            !matches!(binary.operator, BinaryOp::Div | BinaryOp::Mod | BinaryOp::Lt | BinaryOp::Shr)
+        }
+        Instruction::Cast(value, typ) => match integer_bit_size(typ) {
+            Some(bit_size) => integer_bit_size(&dfg.type_of_value(*value))
+                .map_or(false, |value_bit_size| value_bit_size <= bit_size),
+            None => true,
+        },
+        Instruction::Not(_) => true,
+        Instruction::ArrayGet { index, .. } | Instruction::ArraySet { index, .. } => {
+            dfg.get_numeric_constant(*index).is_some()
+        }
+        _ => false,
+    }
+}
+
+/// Returns the bit size of the given type if it is an integer type
+fn integer_bit_size(typ: &Type) -> Option<u32> {
+    match typ {
+        Type::Numeric(NumericType::Unsigned { bit_size } | NumericType::Signed { bit_size }) => {
+            Some(*bit_size)
+        }
+        _ => None,
+    }
+}
+
 #[cfg(test)]
 mod test {
-    use std::rc::Rc;
+    use std::{rc::Rc, str::FromStr};
 
     use crate::ssa_refactor::{
         ir::{
@@ -742,10 +911,12 @@ mod test {
             function::{Function, RuntimeType},
             instruction::{BinaryOp, Instruction, Intrinsic, TerminatorInstruction},
             map::Id,
+            parser::assert_ssa_equals,
             types::Type,
             value::{Value, ValueId},
         },
         ssa_builder::FunctionBuilder,
+        ssa_gen::Ssa,
     };
 
     #[test]
@@ -826,7 +997,7 @@ mod test {
         builder.terminate_with_jmpif(v0, b1, b2);
 
         builder.switch_to_block(b1);
-        builder.insert_constrain(v1);
+        builder.insert_constrain(v1, None);
         builder.terminate_with_jmp(b2, vec![]);
 
         builder.switch_to_block(b2);
@@ -851,6 +1022,225 @@ mod test {
         assert_eq!(ssa.main().reachable_blocks().len(), 1);
     }
 
+    #[test]
+    fn guard_array_indices() {
+        let src = "
+            acir fn main f0 {
+              b0(v0: u1, v1: [Field; 2], v2: Field):
+                jmpif v0 then: b1, else: b2
+              b1():
+                v3 = array_get v1, index v2 -> Field
+                v4 = array_get v1, index Field 1 -> Field
+                jmp b2()
+              b2():
+                return
+            }
+        ";
+        let expected = "
+            acir fn main f0 {
+              b0(v0: u1, v1: [Field; 2], v2: Field):
+                enable_side_effects v0
+                v3 = cast v0 as Field
+                v4 = mul v2, v3
+                v5 = array_get v1, index v4 -> Field
+                v6 = array_get v1, index Field 1 -> Field
+                v7 = not v0
+                enable_side_effects u1 1
+                return
+            }
+        ";
+        let ssa = Ssa::from_str(src).unwrap().flatten_cfg();
+        assert_ssa_equals(&ssa, expected);
+    }
+
+    #[test]
+    fn leaves_indices_known_to_be_in_bounds_unguarded() {
+        let src = "
+            acir fn main f0 {
+              b0(v0: u1, v1: [Field; 8], v2: u32, v3: u32):
+                v4 = lt v3, u32 8
+                constrain v4
+                jmpif v0 then: b1, else: b2
+              b1():
+                v5 = and v2, u32 7
+                v6 = array_get v1, index v5 -> Field
+                v7 = array_set v1, index v3, value Field 1
+                v8 = array_get v1, index v2 -> Field
+                jmp b2()
+              b2():
+                return
+            }
+        ";
+        // Only the index v2 may be out of bounds
+        let expected = "
+            acir fn main f0 {
+              b0(v0: u1, v1: [Field; 8], v2: u32, v3: u32):
+                v4 = lt v3, u32 8
+                constrain v4
+                enable_side_effects v0
+                v5 = and v2, u32 7
+                v6 = array_get v1, index v5 -> Field
+                v7 = array_set v1, index v3, value Field 1
+                v8 = cast v0 as u32
+                v9 = mul v2, v8
+                v10 = array_get v1, index v9 -> Field
+                v11 = not v0
+                enable_side_effects u1 1
+                return
+            }
+        ";
+        let ssa = Ssa::from_str(src).unwrap().flatten_cfg();
+        assert_ssa_equals(&ssa, expected);
+    }
+
+    #[test]
+    fn merges_side_effect_free_branches_without_predicates() {
+        let src = "
+            acir fn main f0 {
+              b0(v0: u1, v1: Field):
+                jmpif v0 then: b1, else: b2
+              b1():
+                v2 = add v1, Field 1
+                jmp b3(v2)
+              b2():
+                v3 = mul v1, Field 2
+                jmp b3(v3)
+              b3(v4: Field):
+                return v4
+            }
+        ";
+        let expected = "
+            acir fn main f0 {
+              b0(v0: u1, v1: Field):
+                v2 = add v1, Field 1
+                v3 = not v0
+                v4 = mul v1, Field 2
+                v5 = cast v0 as Field
+                v6 = cast v3 as Field
+                v7 = mul v5, v2
+                v8 = mul v6, v4
+                v9 = add v7, v8
+                return v9
+            }
+        ";
+        let ssa = Ssa::from_str(src).unwrap().flatten_cfg();
+        assert_ssa_equals(&ssa, expected);
+    }
+
+    #[test]
+    fn merges_widening_casts_without_predicates() {
+        let src = "
+            acir fn main f0 {
+              b0(v0: u1, v1: u8):
+                jmpif v0 then: b1, else: b2
+              b1():
+                v2 = cast v1 as u32
+                jmp b3(v2)
+              b2():
+                v3 = cast v1 as u16
+                v4 = cast v3 as u32
+                jmp b3(v4)
+              b3(v5: u32):
+                return v5
+            }
+        ";
+        let expected = "
+            acir fn main f0 {
+              b0(v0: u1, v1: u8):
+                v2 = cast v1 as u32
+                v3 = not v0
+                v4 = cast v1 as u16
+                v5 = cast v4 as u32
+                v6 = cast v0 as u32
+                v7 = cast v3 as u32
+                v8 = mul v6, v2
+                v9 = mul v7, v5
+                v10 = add v8, v9
+                return v10
+            }
+        ";
+        let ssa = Ssa::from_str(src).unwrap().flatten_cfg();
+        assert_ssa_equals(&ssa, expected);
+    }
+
+    #[test]
+    fn keeps_predicates_of_branches_which_narrow_integers() {
+        let src = "
+            acir fn main f0 {
+              b0(v0: u1, v1: u32):
+                jmpif v0 then: b1, else: b2
+              b1():
+                v2 = truncate v1 to 8 bits, max_bit_size: 32
+                jmp b3(v2)
+              b2():
+                v3 = cast v1 as u8
+                v4 = cast v3 as u32
+                jmp b3(v4)
+              b3(v5: u32):
+                return v5
+            }
+        ";
+        let expected = "
+            acir fn main f0 {
+              b0(v0: u1, v1: u32):
+                enable_side_effects v0
+                v2 = truncate v1 to 8 bits, max_bit_size: 32
+                v3 = not v0
+                enable_side_effects v3
+                v4 = cast v1 as u8
+                v5 = cast v4 as u32
+                enable_side_effects u1 1
+                v6 = cast v0 as u32
+                v7 = cast v3 as u32
+                v8 = mul v6, v2
+                v9 = mul v7, v5
+                v10 = add v8, v9
+                return v10
+            }
+        ";
+        let ssa = Ssa::from_str(src).unwrap().flatten_cfg();
+        assert_ssa_equals(&ssa, expected);
+    }
+
+    #[test]
+    fn prunes_branches_with_known_conditions() {
+        let src = "
+            acir fn main f0 {
+              b0(v0: u1, v1: Field):
+                jmpif v0 then: b1, else: b5
+              b1():
+                jmpif v0 then: b2, else: b3
+              b2():
+                v2 = eq v1, Field 1
+                constrain v2
+                jmp b4()
+              b3():
+                v3 = eq v1, Field 2
+                constrain v3
+                jmp b4()
+              b4():
+                jmp b5()
+              b5():
+                return
+            }
+        ";
+        let expected = "
+            acir fn main f0 {
+              b0(v0: u1, v1: Field):
+                enable_side_effects v0
+                v2 = eq v1, Field 1
+                v3 = mul v2, v0
+                v4 = eq v3, v0
+                constrain v4
+                v5 = not v0
+                enable_side_effects u1 1
+                return
+            }
+        ";
+        let ssa = Ssa::from_str(src).unwrap().flatten_cfg();
+        assert_ssa_equals(&ssa, expected);
+    }
+
     #[test]
     fn merge_stores() {
         // fn main f0 {
@@ -1282,7 +1672,7 @@ mod test {
         builder.terminate_with_jmp(b2, vec![]);
 
         builder.switch_to_block(b2);
-        builder.insert_constrain(v_false); // should not be removed
+        builder.insert_constrain(v_false, None); // should not be removed
         builder.terminate_with_return(vec![]);
 
         let ssa = builder.finish().flatten_cfg();
@@ -1290,7 +1680,7 @@ mod test {
 
         // Assert we have not incorrectly removed a constraint:
         use Instruction::Constrain;
-        let constrain_count = count_instruction(main, |ins| matches!(ins, Constrain(_)));
+        let constrain_count = count_instruction(main, |ins| matches!(ins, Constrain(..)));
         assert_eq!(constrain_count, 1);
     }
 
@@ -1365,7 +1755,7 @@ mod test {
         builder.switch_to_block(b3);
         let b_true = builder.numeric_constant(1_u128, Type::unsigned(1));
         let v12 = builder.insert_binary(v9, BinaryOp::Eq, b_true);
-        builder.insert_constrain(v12);
+        builder.insert_constrain(v12, None);
         builder.terminate_with_return(vec![]);
 
         let ssa = builder.finish().flatten_cfg();
@@ -1374,8 +1764,8 @@ mod test {
         // Now assert that there is not an always-false constraint after flattening:
         let mut constrain_count = 0;
         for instruction in main.dfg[main.entry_block()].instructions() {
-            if let Instruction::Constrain(value) = main.dfg[*instruction] {
-                if let Some(constant) = main.dfg.get_numeric_constant(value) {
+            if let Instruction::Constrain(value, _) = &main.dfg[*instruction] {
+                if let Some(constant) = main.dfg.get_numeric_constant(*value) {
                     assert!(constant.is_one());
                 }
                 constrain_count += 1;
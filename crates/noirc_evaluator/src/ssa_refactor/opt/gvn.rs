@@ -0,0 +1,381 @@
+//! Global Value Numbering (GVN) deduplicates instructions without side-effects which compute
+//! the same value as an identical instruction in a dominating block.
+//!
+//! Blocks are visited in reverse post-order, so each block is visited after every block which
+//! dominates it. Each instruction is keyed by its resolved operands. If an identical instruction
+//! has already been seen in a block that dominates the current one, the results of the current
+//! instruction are replaced with the results of the earlier instruction and the current
+//! instruction is removed.
+//!
+//! The operands of commutative binary instructions are ordered before keying them, so `a + b`
+//! and `b + a` are considered identical. After `flatten_cfg` merges both sides of each
+//! conditional into a single block, this catches the many expressions recomputed in each branch.
+//!
+//! Instructions whose ACIR lowering depends on the active `EnableSideEffects` condition
+//! (e.g. division or array accesses) are only deduplicated when found under the same condition,
+//! or when the earlier instruction was found while side-effects were always enabled. Loads are only deduplicated within a single block, and only while no store or impure call
+//! occurs between them.
+use std::collections::{HashMap, HashSet};
+
+use crate::ssa_refactor::{
+    ir::{
+        basic_block::BasicBlockId,
+        cfg::ControlFlowGraph,
+        dfg::DataFlowGraph,
+        dom::DominatorTree,
+        function::Function,
+        instruction::{Binary, Instruction, Intrinsic},
+        post_order::PostOrder,
+        value::{Value, ValueId},
+    },
+    ssa_gen::Ssa,
+};
+
+impl Ssa {
+    /// Performs global value numbering on each function, replacing each instruction without
+    /// side-effects with the results of an identical instruction in a dominating block.
+    pub(crate) fn global_value_numbering(mut self) -> Ssa {
+        for function in self.functions.values_mut() {
+            global_value_numbering(function);
+        }
+        self
+    }
+}
+
+/// An instruction along with the side-effects condition it was found under.
+/// The condition is only recorded for instructions whose behavior depends on it.
+type InstructionKey = (Instruction, Option<ValueId>);
+
+fn global_value_numbering(function: &mut Function) {
+    let cfg = ControlFlowGraph::with_function(function);
+    let post_order = PostOrder::with_function(function);
+    let mut dom_tree = DominatorTree::with_cfg_and_post_order(&cfg, &post_order);
+
+    let mut context = Context::default();
+    for block in post_order.as_slice().iter().rev() {
+        context.number_values_in_block(function, *block, &mut dom_tree);
+    }
+}
+
+#[derive(Default)]
+struct Context {
+    /// Maps each instruction seen so far to every block it was found in
+    /// along with its results within that block.
+    available_instructions: HashMap<InstructionKey, Vec<(BasicBlockId, Vec<ValueId>)>>,
+}
+
+impl Context {
+    fn number_values_in_block(
+        &mut self,
+        function: &mut Function,
+        block: BasicBlockId,
+        dom_tree: &mut DominatorTree,
+    ) {
+        let mut side_effects_condition = None;
+
+        // Maps each address loaded from within this block to the result of that load.
+        // This is cleared whenever memory may have been modified.
+        let mut available_loads: HashMap<ValueId, ValueId> = HashMap::new();
+        let mut instructions_to_remove = HashSet::new();
+
+        let instructions = function.dfg[block].instructions().to_vec();
+        for instruction_id in instructions {
+            let instruction =
+                function.dfg[instruction_id].map_values(|value| function.dfg.resolve(value));
+            let instruction = order_commutative_operands(instruction);
+            let results = function.dfg.instruction_results(instruction_id).to_vec();
+
+            match &instruction {
+                Instruction::EnableSideEffects { condition } => {
+                    // A constant true condition is the same as no condition at all
+                    let always_enabled = function
+                        .dfg
+                        .get_numeric_constant(*condition)
+                        .map_or(false, |condition| condition.is_one());
+                    side_effects_condition = (!always_enabled).then_some(*condition);
+                    continue;
+                }
+                Instruction::Load { address } => {
+                    if let Some(existing) = available_loads.get(address) {
+                        function.dfg.set_value_from_id(results[0], *existing);
+                        instructions_to_remove.insert(instruction_id);
+                    } else {
+                        available_loads.insert(*address, results[0]);
+                    }
+                    continue;
+                }
+                _ => (),
+            }
+
+            if !can_be_deduplicated(&instruction, &function.dfg) {
+                if matches!(instruction, Instruction::Store { .. } | Instruction::Call { .. }) {
+                    available_loads.clear();
+                }
+                continue;
+            }
+
+            let condition = instruction
+                .requires_side_effects_condition(&function.dfg)
+                .then_some(side_effects_condition)
+                .flatten();
+
+            // An instruction found while side-effects were always enabled may replace the same
+            // instruction under any condition.
+            let mut existing_results = None;
+            if condition.is_some() {
+                let key = (instruction.clone(), None);
+                existing_results = self.find_dominating_results(&key, block, dom_tree);
+            }
+
+            let key = (instruction, condition);
+            if existing_results.is_none() {
+                existing_results = self.find_dominating_results(&key, block, dom_tree);
+            }
+
+            match existing_results {
+                Some(existing_results) => {
+                    assert_eq!(results.len(), existing_results.len());
+                    for (result, existing) in results.into_iter().zip(existing_results) {
+                        function.dfg.set_value_from_id(result, existing);
+                    }
+                    instructions_to_remove.insert(instruction_id);
+                }
+                None => self.available_instructions.entry(key).or_default().push((block, results)),
+            }
+        }
+
+        function.dfg[block]
+            .instructions_mut()
+            .retain(|instruction| !instructions_to_remove.contains(instruction));
+    }
+
+    /// Returns the results of an instruction with the given key within a block dominating the
+    /// given block, if there is one.
+    fn find_dominating_results(
+        &self,
+        key: &InstructionKey,
+        block: BasicBlockId,
+        dom_tree: &mut DominatorTree,
+    ) -> Option<Vec<ValueId>> {
+        let candidates = self.available_instructions.get(key)?;
+        candidates
+            .iter()
+            .find(|(candidate_block, _)| dom_tree.dominates(*candidate_block, block))
+            .map(|(_, existing_results)| existing_results.clone())
+    }
+}
+
+/// Orders the operands of a commutative binary instruction so that identical instructions are
+/// found regardless of the order their operands were written in.
+fn order_commutative_operands(instruction: Instruction) -> Instruction {
+    match instruction {
+        Instruction::Binary(Binary { lhs, rhs, operator })
+            if operator.is_commutative() && rhs < lhs =>
+        {
+            Instruction::Binary(Binary { lhs: rhs, rhs: lhs, operator })
+        }
+        other => other,
+    }
+}
+
+/// Returns true if the given instruction has no side-effects and always produces
+/// the same results when given the same arguments.
+fn can_be_deduplicated(instruction: &Instruction, dfg: &DataFlowGraph) -> bool {
+    match instruction {
+        Instruction::Binary(_)
+        | Instruction::Cast(..)
+        | Instruction::Not(_)
+        | Instruction::Truncate { .. }
+        | Instruction::ArrayGet { .. }
+        | Instruction::ArraySet { .. } => true,
+        Instruction::Call { func, .. } => match &dfg[*func] {
+            Value::Intrinsic(intrinsic) => !matches!(intrinsic, Intrinsic::Println),
+            _ => false,
+        },
+        Instruction::Constrain(..)
+        | Instruction::Allocate
+        | Instruction::Load { .. }
+        | Instruction::Store { .. }
+        | Instruction::EnableSideEffects { .. } => false,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::str::FromStr;
+
+    use crate::ssa_refactor::{
+        ir::{
+            function::RuntimeType,
+            instruction::{BinaryOp, Instruction},
+            map::Id,
+            parser::assert_ssa_equals,
+            types::Type,
+        },
+        ssa_builder::FunctionBuilder,
+        ssa_gen::Ssa,
+    };
+
+    #[test]
+    fn deduplicates_across_dominated_blocks() {
+        // fn main f0 {
+        //   b0(v0: Field):
+        //     v1 = mul v0, v0
+        //     jmp b1()
+        //   b1():
+        //     v2 = mul v0, v0
+        //     v3 = add v1, v2
+        //     return v3
+        // }
+        let main_id = Id::test_new(0);
+        let mut builder = FunctionBuilder::new("main".into(), main_id, RuntimeType::Acir);
+        let v0 = builder.add_parameter(Type::field());
+        let b1 = builder.insert_block();
+
+        let v1 = builder.insert_binary(v0, BinaryOp::Mul, v0);
+        builder.terminate_with_jmp(b1, vec![]);
+
+        builder.switch_to_block(b1);
+        let v2 = builder.insert_binary(v0, BinaryOp::Mul, v0);
+        let v3 = builder.insert_binary(v1, BinaryOp::Add, v2);
+        builder.terminate_with_return(vec![v3]);
+
+        // Expected output:
+        //
+        // fn main f0 {
+        //   b0(v0: Field):
+        //     v1 = mul v0, v0
+        //     jmp b1()
+        //   b1():
+        //     v3 = add v1, v1
+        //     return v3
+        // }
+        let ssa = builder.finish().global_value_numbering();
+        let main = ssa.main();
+        assert_eq!(main.dfg[main.entry_block()].instructions().len(), 1);
+        assert_eq!(main.dfg[b1].instructions().len(), 1);
+        assert_eq!(main.dfg.resolve(v2), v1);
+    }
+
+    #[test]
+    fn does_not_deduplicate_across_sibling_blocks() {
+        // fn main f0 {
+        //   b0(v0: Field, v1: u1):
+        //     jmpif v1 then: b1, else: b2
+        //   b1():
+        //     v2 = mul v0, v0
+        //     jmp b3(v2)
+        //   b2():
+        //     v3 = mul v0, v0
+        //     jmp b3(v3)
+        //   b3(v4: Field):
+        //     return v4
+        // }
+        let main_id = Id::test_new(0);
+        let mut builder = FunctionBuilder::new("main".into(), main_id, RuntimeType::Acir);
+        let v0 = builder.add_parameter(Type::field());
+        let v1 = builder.add_parameter(Type::bool());
+
+        let b1 = builder.insert_block();
+        let b2 = builder.insert_block();
+        let b3 = builder.insert_block();
+
+        builder.terminate_with_jmpif(v1, b1, b2);
+
+        builder.switch_to_block(b1);
+        let v2 = builder.insert_binary(v0, BinaryOp::Mul, v0);
+        builder.terminate_with_jmp(b3, vec![v2]);
+
+        builder.switch_to_block(b2);
+        let v3 = builder.insert_binary(v0, BinaryOp::Mul, v0);
+        builder.terminate_with_jmp(b3, vec![v3]);
+
+        builder.switch_to_block(b3);
+        let v4 = builder.add_block_parameter(b3, Type::field());
+        builder.terminate_with_return(vec![v4]);
+
+        let ssa = builder.finish().global_value_numbering();
+        let main = ssa.main();
+        assert_eq!(main.dfg[b1].instructions().len(), 1);
+        assert_eq!(main.dfg[b2].instructions().len(), 1);
+        assert_ne!(main.dfg.resolve(v2), main.dfg.resolve(v3));
+    }
+
+    #[test]
+    fn respects_side_effects_condition() {
+        // fn main f0 {
+        //   b0(v0: u32, v1: u32, v2: u1):
+        //     enable_side_effects v2
+        //     v3 = div v0, v1
+        //     v4 = add v0, v1
+        //     enable_side_effects u1 1
+        //     v5 = div v0, v1
+        //     v6 = add v0, v1
+        //     return v3, v4, v5, v6
+        // }
+        let main_id = Id::test_new(0);
+        let mut builder = FunctionBuilder::new("main".into(), main_id, RuntimeType::Acir);
+        let v0 = builder.add_parameter(Type::unsigned(32));
+        let v1 = builder.add_parameter(Type::unsigned(32));
+        let v2 = builder.add_parameter(Type::bool());
+
+        builder.insert_instruction(Instruction::EnableSideEffects { condition: v2 }, None);
+        let v3 = builder.insert_binary(v0, BinaryOp::Div, v1);
+        let v4 = builder.insert_binary(v0, BinaryOp::Add, v1);
+
+        let one = builder.numeric_constant(1u128, Type::bool());
+        builder.insert_instruction(Instruction::EnableSideEffects { condition: one }, None);
+        let v5 = builder.insert_binary(v0, BinaryOp::Div, v1);
+        let v6 = builder.insert_binary(v0, BinaryOp::Add, v1);
+        builder.terminate_with_return(vec![v3, v4, v5, v6]);
+
+        // Expected output:
+        //
+        // fn main f0 {
+        //   b0(v0: u32, v1: u32, v2: u1):
+        //     enable_side_effects v2
+        //     v3 = div v0, v1
+        //     v4 = add v0, v1
+        //     enable_side_effects u1 1
+        //     v5 = div v0, v1
+        //     return v3, v4, v5, v4
+        // }
+        let ssa = builder.finish().global_value_numbering();
+        let main = ssa.main();
+        assert_eq!(main.dfg[main.entry_block()].instructions().len(), 5);
+        assert_ne!(main.dfg.resolve(v5), v3);
+        assert_eq!(main.dfg.resolve(v6), v4);
+    }
+
+    #[test]
+    fn deduplicates_commutative_and_always_enabled_instructions() {
+        let src = "
+            acir fn main f0 {
+              b0(v0: u32, v1: u32, v2: u1):
+                v3 = div v0, v1
+                v4 = add v0, v1
+                enable_side_effects v2
+                v5 = div v0, v1
+                v6 = add v1, v0
+                v7 = mod v0, v1
+                enable_side_effects u1 1
+                v8 = mod v0, v1
+                return v3, v4, v5, v6, v7, v8
+            }
+        ";
+        let expected = "
+            acir fn main f0 {
+              b0(v0: u32, v1: u32, v2: u1):
+                v3 = div v0, v1
+                v4 = add v0, v1
+                enable_side_effects v2
+                v5 = mod v0, v1
+                enable_side_effects u1 1
+                v6 = mod v0, v1
+                return v3, v4, v3, v4, v5, v6
+            }
+        ";
+        let ssa = Ssa::from_str(src).unwrap().global_value_numbering();
+        assert_ssa_equals(&ssa, expected);
+    }
+}
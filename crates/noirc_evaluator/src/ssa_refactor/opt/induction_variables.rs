@@ -0,0 +1,302 @@
+//! Induction variable simplification rewrites chains of field arithmetic on a single value into
+//! a direct computation from that value.
+//!
+//! Unrolling a loop such as `for i in 0..n { array[i + offset] = ... }` leaves chains like
+//! `v1 = add v0, Field 1; v2 = add v1, Field 1; v3 = add v2, Field 1` behind, where each step of
+//! the induction variable is computed from the step before it. This pass tracks each field value
+//! as a linear form `base * scale + constant` of a single other value, and rewrites each addition,
+//! subtraction, or multiplication whose form is simple enough:
+//! - A form without a base, e.g. `sub v1, v1`, is replaced by its constant.
+//! - A form equal to its base is replaced by the base itself.
+//! - A form with a scale of one becomes a single addition, e.g. `v3 = add v0, Field 3`.
+//! - A form without a constant becomes a single multiplication, e.g. `add v1, v1` becomes
+//!   `mul v0, Field 2` if `v1 = add v0, v0`.
+//!
+//! Any other form is left as it is, though it is still tracked so that later steps of the chain
+//! may be rewritten. Each rewritten instruction no longer depends on the previous step of its
+//! chain, which is left for dead instruction elimination to remove if it is otherwise unused.
+//!
+//! Integer arithmetic is followed by a truncation which breaks these chains, so only field
+//! arithmetic is simplified.
+use std::collections::HashMap;
+
+use acvm::FieldElement;
+
+use crate::ssa_refactor::{
+    ir::{
+        basic_block::BasicBlockId,
+        dfg::DataFlowGraph,
+        function::Function,
+        instruction::{Binary, BinaryOp, Instruction},
+        post_order::PostOrder,
+        types::Type,
+        value::ValueId,
+    },
+    ssa_gen::Ssa,
+};
+
+impl Ssa {
+    /// Rewrites chains of field arithmetic within each function into direct computations.
+    pub(crate) fn simplify_induction_variables(mut self) -> Ssa {
+        for function in self.functions.values_mut() {
+            simplify_function(function);
+        }
+        self
+    }
+}
+
+/// A field value equal to `base * scale + constant`, or simply `constant` if there is no base.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+struct LinearForm {
+    base: Option<ValueId>,
+    scale: FieldElement,
+    constant: FieldElement,
+}
+
+impl LinearForm {
+    fn constant(constant: FieldElement) -> Self {
+        Self { base: None, scale: FieldElement::zero(), constant }
+    }
+
+    fn value(base: ValueId) -> Self {
+        Self { base: Some(base), scale: FieldElement::one(), constant: FieldElement::zero() }
+    }
+
+    /// Returns the sum of two forms, if they do not have different bases.
+    fn add(self, other: LinearForm) -> Option<LinearForm> {
+        let base = match (self.base, other.base) {
+            (Some(base), Some(other_base)) if base != other_base => return None,
+            (base, other_base) => base.or(other_base),
+        };
+        let scale = self.scale + other.scale;
+        let constant = self.constant + other.constant;
+        Some(if scale.is_zero() {
+            Self::constant(constant)
+        } else {
+            Self { base, scale, constant }
+        })
+    }
+
+    fn sub(self, other: LinearForm) -> Option<LinearForm> {
+        self.add(other.mul_constant(-FieldElement::one()))
+    }
+
+    /// Returns the product of two forms, if at least one of them is constant.
+    fn mul(self, other: LinearForm) -> Option<LinearForm> {
+        match (self.base, other.base) {
+            (_, None) => Some(self.mul_constant(other.constant)),
+            (None, _) => Some(other.mul_constant(self.constant)),
+            (Some(_), Some(_)) => None,
+        }
+    }
+
+    fn mul_constant(self, factor: FieldElement) -> LinearForm {
+        if factor.is_zero() {
+            return Self::constant(FieldElement::zero());
+        }
+        Self { base: self.base, scale: self.scale * factor, constant: self.constant * factor }
+    }
+}
+
+/// The simplest computation of a linear form.
+enum Simplification {
+    Value(ValueId),
+    Instruction(Binary),
+}
+
+fn simplify_function(function: &mut Function) {
+    let mut forms = HashMap::new();
+
+    // Blocks are visited in reverse post-order so that the form of each value is known before
+    // any use of it outside of a loop. Block parameters are the bases of any chain using them.
+    let mut blocks = PostOrder::with_function(function).as_slice().to_vec();
+    blocks.reverse();
+
+    for block in blocks {
+        simplify_block(&mut function.dfg, block, &mut forms);
+    }
+}
+
+fn simplify_block(
+    dfg: &mut DataFlowGraph,
+    block: BasicBlockId,
+    forms: &mut HashMap<ValueId, LinearForm>,
+) {
+    let instructions = std::mem::take(dfg[block].instructions_mut());
+    let mut new_instructions = Vec::with_capacity(instructions.len());
+
+    for id in instructions {
+        let binary = match &dfg[id] {
+            Instruction::Binary(binary) if is_field(dfg, binary.lhs) => binary.clone(),
+            _ => {
+                new_instructions.push(id);
+                continue;
+            }
+        };
+        let result = dfg.instruction_results(id)[0];
+        let lhs = form_of(dfg, forms, binary.lhs);
+        let rhs = form_of(dfg, forms, binary.rhs);
+
+        let form = match binary.operator {
+            BinaryOp::Add => lhs.add(rhs),
+            BinaryOp::Sub => lhs.sub(rhs),
+            BinaryOp::Mul => lhs.mul(rhs),
+            _ => None,
+        };
+        let form = match form {
+            Some(form) => form,
+            None => {
+                new_instructions.push(id);
+                continue;
+            }
+        };
+        forms.insert(result, form);
+
+        match simplify_form(dfg, form) {
+            Some(Simplification::Value(value)) => {
+                dfg.set_value_from_id(result, value);
+                continue;
+            }
+            Some(Simplification::Instruction(simplified))
+                if !is_same(dfg, &binary, &simplified) =>
+            {
+                dfg[id] = Instruction::Binary(simplified);
+            }
+            Some(Simplification::Instruction(_)) | None => (),
+        }
+        new_instructions.push(id);
+    }
+
+    *dfg[block].instructions_mut() = new_instructions;
+}
+
+/// Returns the form of the given value, treating any value without a known form as the base of
+/// a new chain.
+fn form_of(
+    dfg: &DataFlowGraph,
+    forms: &HashMap<ValueId, LinearForm>,
+    value: ValueId,
+) -> LinearForm {
+    let value = dfg.resolve(value);
+    match dfg.get_numeric_constant(value) {
+        Some(constant) => LinearForm::constant(constant),
+        None => forms.get(&value).copied().unwrap_or_else(|| LinearForm::value(value)),
+    }
+}
+
+/// Returns the simplest computation of the given form, or None if it would take more than one
+/// instruction.
+fn simplify_form(dfg: &mut DataFlowGraph, form: LinearForm) -> Option<Simplification> {
+    let base = match form.base {
+        Some(base) => base,
+        None => {
+            let constant = dfg.make_constant(form.constant, Type::field());
+            return Some(Simplification::Value(constant));
+        }
+    };
+
+    if form.scale.is_one() {
+        if form.constant.is_zero() {
+            return Some(Simplification::Value(base));
+        }
+        let constant = dfg.make_constant(form.constant, Type::field());
+        Some(Simplification::Instruction(Binary {
+            lhs: base,
+            rhs: constant,
+            operator: BinaryOp::Add,
+        }))
+    } else if form.constant.is_zero() {
+        let scale = dfg.make_constant(form.scale, Type::field());
+        Some(Simplification::Instruction(Binary { lhs: base, rhs: scale, operator: BinaryOp::Mul }))
+    } else {
+        None
+    }
+}
+
+/// Returns true if both binary instructions compute the same operation on the same operands.
+fn is_same(dfg: &DataFlowGraph, binary: &Binary, other: &Binary) -> bool {
+    binary.operator == other.operator
+        && dfg.resolve(binary.lhs) == dfg.resolve(other.lhs)
+        && dfg.resolve(binary.rhs) == dfg.resolve(other.rhs)
+}
+
+fn is_field(dfg: &DataFlowGraph, value: ValueId) -> bool {
+    dfg.type_of_value(value) == Type::field()
+}
+
+#[cfg(test)]
+mod test {
+    use std::str::FromStr;
+
+    use crate::ssa_refactor::{ir::parser::assert_ssa_equals, ssa_gen::Ssa};
+
+    #[test]
+    fn rewrites_chains_of_additions() {
+        let src = "
+            acir fn main f0 {
+              b0(v0: Field, v1: Field):
+                v2 = add v0, Field 1
+                v3 = add v2, Field 1
+                v4 = add v3, Field 1
+                v5 = array_get v1, index v4
+                v6 = sub v4, Field 3
+                return v5, v6
+            }
+        ";
+        let expected = "
+            acir fn main f0 {
+              b0(v0: Field, v1: Field):
+                v2 = add v0, Field 1
+                v3 = add v0, Field 2
+                v4 = add v0, Field 3
+                v5 = array_get v1, index v4
+                return v5, v0
+            }
+        ";
+        let ssa = Ssa::from_str(src).unwrap().simplify_induction_variables();
+        assert_ssa_equals(&ssa, expected);
+    }
+
+    #[test]
+    fn rewrites_chains_into_multiplications() {
+        let src = "
+            acir fn main f0 {
+              b0(v0: Field):
+                v1 = add v0, v0
+                v2 = add v1, v0
+                v3 = mul v2, Field 4
+                v4 = sub v3, v1
+                v5 = sub v3, v3
+                v6 = add v4, Field 7
+                return v4, v5, v6
+            }
+        ";
+        let expected = "
+            acir fn main f0 {
+              b0(v0: Field):
+                v1 = mul v0, Field 2
+                v2 = mul v0, Field 3
+                v3 = mul v0, Field 12
+                v4 = mul v0, Field 10
+                v6 = add v4, Field 7
+                return v4, Field 0, v6
+            }
+        ";
+        let ssa = Ssa::from_str(src).unwrap().simplify_induction_variables();
+        assert_ssa_equals(&ssa, expected);
+    }
+
+    #[test]
+    fn leaves_integer_arithmetic() {
+        let src = "
+            acir fn main f0 {
+              b0(v0: u32):
+                v1 = add v0, u32 1
+                v2 = add v1, u32 1
+                return v2
+            }
+        ";
+        let ssa = Ssa::from_str(src).unwrap().simplify_induction_variables();
+        assert_ssa_equals(&ssa, src);
+    }
+}
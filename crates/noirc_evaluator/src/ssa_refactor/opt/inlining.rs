@@ -5,17 +5,22 @@
 use std::collections::{HashMap, HashSet};
 
 use iter_extended::vecmap;
+use noirc_errors::Location;
 
-use crate::ssa_refactor::{
-    ir::{
-        basic_block::BasicBlockId,
-        dfg::InsertInstructionResult,
-        function::{Function, FunctionId, RuntimeType},
-        instruction::{Instruction, InstructionId, TerminatorInstruction},
-        value::{Value, ValueId},
+use crate::{
+    errors::{RuntimeError, RuntimeErrorKind},
+    ssa_refactor::{
+        ir::{
+            basic_block::BasicBlockId,
+            call_graph::CallGraph,
+            dfg::InsertInstructionResult,
+            function::{Function, FunctionId, RuntimeType},
+            instruction::{Instruction, InstructionId, TerminatorInstruction},
+            value::{Value, ValueId},
+        },
+        ssa_builder::FunctionBuilder,
+        ssa_gen::Ssa,
     },
-    ssa_builder::FunctionBuilder,
-    ssa_gen::Ssa,
 };
 
 /// An arbitrary limit to the maximum number of recursive call
@@ -26,7 +31,8 @@ impl Ssa {
     /// Inline all functions within the IR.
     ///
     /// In the case of recursive functions, this will attempt
-    /// to recursively inline until the RECURSION_LIMIT is reached.
+    /// to recursively inline until the RECURSION_LIMIT is reached,
+    /// at which point the recursive call cycle being inlined is reported as an error.
     ///
     /// Functions are recursively inlined into main until either we finish
     /// inlining all functions or we encounter a function whose function id is not known.
@@ -35,7 +41,7 @@ impl Ssa {
     /// changes. This is because if the function's id later becomes known by a later
     /// pass, we would need to re-run all of inlining anyway to inline it, so we might
     /// as well save the work for later instead of performing it twice.
-    pub(crate) fn inline_functions(self) -> Ssa {
+    pub(crate) fn inline_functions(self) -> Result<Ssa, RuntimeError> {
         InlineContext::new(&self).inline_all(self)
     }
 }
@@ -53,6 +59,14 @@ struct InlineContext {
     /// inlining we can remove all other functions from the resulting Ssa struct and keep only
     /// the function that was inlined into.
     failed_to_inline_a_call: bool,
+
+    /// The calls between the functions of the program, used to describe the cycle of calls
+    /// being inlined if the RECURSION_LIMIT is reached.
+    call_graph: CallGraph,
+
+    /// The error reported when the RECURSION_LIMIT was reached, if it was. No further calls are
+    /// inlined once this is set.
+    recursion_error: Option<RuntimeError>,
 }
 
 /// The per-function inlining context contains information that is only valid for one function.
@@ -98,11 +112,39 @@ impl InlineContext {
     fn new(ssa: &Ssa) -> InlineContext {
         let main_name = ssa.main().name().to_owned();
         let builder = FunctionBuilder::new(main_name, ssa.next_id.next(), RuntimeType::Acir);
-        Self { builder, recursion_level: 0, failed_to_inline_a_call: false }
+        Self {
+            builder,
+            recursion_level: 0,
+            failed_to_inline_a_call: false,
+            call_graph: CallGraph::with_ssa(ssa),
+            recursion_error: None,
+        }
+    }
+
+    fn should_inline(&self) -> bool {
+        self.recursion_error.is_none()
+    }
+
+    /// Records the error for reaching the RECURSION_LIMIT when inlining a call to `function`,
+    /// describing the shortest cycle of calls from `function` back to itself.
+    fn report_recursion_limit(
+        &mut self,
+        ssa: &Ssa,
+        function: FunctionId,
+        location: Option<Location>,
+    ) {
+        let kind = match self.call_graph.find_cycle(function) {
+            Some(cycle) => {
+                let names = vecmap(cycle, |function| ssa.functions[&function].name().to_owned());
+                RuntimeErrorKind::RecursiveCallCycle { cycle: names.join(" → ") }
+            }
+            None => RuntimeErrorKind::RecursionLimitExceeded { limit: RECURSION_LIMIT },
+        };
+        self.recursion_error = Some(RuntimeError::new(kind, location));
     }
 
     /// Start inlining the main function and all functions reachable from it.
-    fn inline_all(mut self, ssa: Ssa) -> Ssa {
+    fn inline_all(mut self, ssa: Ssa) -> Result<Ssa, RuntimeError> {
         let main = ssa.main();
         let mut context = PerFunctionContext::new(&mut self, main);
         context.inlining_main = true;
@@ -121,7 +163,11 @@ impl InlineContext {
 
         context.blocks.insert(context.source_function.entry_block(), entry_block);
         context.inline_blocks(&ssa);
-        self.finish(ssa)
+
+        match self.recursion_error.take() {
+            Some(error) => Err(error),
+            None => Ok(self.finish(ssa)),
+        }
     }
 
     /// Inlines a function into the current function and returns the translated return values
@@ -134,12 +180,6 @@ impl InlineContext {
     ) -> Vec<ValueId> {
         self.recursion_level += 1;
 
-        if self.recursion_level > RECURSION_LIMIT {
-            panic!(
-                "Attempted to recur more than {RECURSION_LIMIT} times during function inlining."
-            );
-        }
-
         let source_function = &ssa.functions[&id];
         let mut context = PerFunctionContext::new(self, source_function);
 
@@ -347,8 +387,19 @@ impl<'function> PerFunctionContext<'function> {
             match &self.source_function.dfg[*id] {
                 Instruction::Call { func, arguments } => match self.get_function(*func) {
                     Some(function) => match ssa.functions[&function].runtime() {
-                        RuntimeType::Acir => self.inline_function(ssa, *id, function, arguments),
-                        RuntimeType::Brillig => {
+                        RuntimeType::Acir
+                            if self.context.should_inline()
+                                && self.context.recursion_level >= RECURSION_LIMIT =>
+                        {
+                            let location = self.source_function.dfg.get_location(id);
+                            self.context.report_recursion_limit(ssa, function, location);
+                            self.context.failed_to_inline_a_call = true;
+                            self.push_instruction(*id);
+                        }
+                        RuntimeType::Acir if self.context.should_inline() => {
+                            self.inline_function(ssa, *id, function, arguments);
+                        }
+                        RuntimeType::Acir | RuntimeType::Brillig => {
                             self.context.failed_to_inline_a_call = true;
                             self.push_instruction(*id);
                         }
@@ -480,6 +531,8 @@ impl<'function> PerFunctionContext<'function> {
 
 #[cfg(test)]
 mod test {
+    use std::str::FromStr;
+
     use acvm::FieldElement;
 
     use crate::ssa_refactor::{
@@ -491,6 +544,7 @@ mod test {
             types::Type,
         },
         ssa_builder::FunctionBuilder,
+        ssa_gen::Ssa,
     };
 
     #[test]
@@ -520,7 +574,7 @@ mod test {
         let ssa = builder.finish();
         assert_eq!(ssa.functions.len(), 2);
 
-        let inlined = ssa.inline_functions();
+        let inlined = ssa.inline_functions().unwrap();
         assert_eq!(inlined.functions.len(), 1);
     }
 
@@ -586,7 +640,7 @@ mod test {
         let ssa = builder.finish();
         assert_eq!(ssa.functions.len(), 4);
 
-        let inlined = ssa.inline_functions();
+        let inlined = ssa.inline_functions().unwrap();
         assert_eq!(inlined.functions.len(), 1);
     }
 
@@ -660,7 +714,7 @@ mod test {
         //   b6():
         //     return Field 120
         // }
-        let inlined = ssa.inline_functions();
+        let inlined = ssa.inline_functions().unwrap();
         assert_eq!(inlined.functions.len(), 1);
 
         let main = inlined.main();
@@ -743,7 +797,7 @@ mod test {
         builder.switch_to_block(join_block);
         builder.terminate_with_return(vec![join_param]);
 
-        let ssa = builder.finish().inline_functions();
+        let ssa = builder.finish().inline_functions().unwrap();
         // Expected result:
         // fn main f3 {
         //   b0(v0: u1):
@@ -759,4 +813,36 @@ mod test {
         let main = ssa.main();
         assert_eq!(main.reachable_blocks().len(), 4);
     }
+
+    #[test]
+    fn reports_recursive_call_cycles() {
+        let src = "
+            acir fn main f0 {
+              b0(v0: Field):
+                v1 = call f1(v0) -> Field
+                return v1
+            }
+            acir fn ping f1 {
+              b0(v0: Field):
+                v1 = call f2(v0) -> Field
+                return v1
+            }
+            acir fn pong f2 {
+              b0(v0: Field):
+                v1 = call f1(v0) -> Field
+                return v1
+            }
+        ";
+        // Inlining recurses up to RECURSION_LIMIT times before giving up, which needs more
+        // stack than the default test thread provides.
+        let error = std::thread::Builder::new()
+            .stack_size(256 * 1024 * 1024)
+            .spawn(move || Ssa::from_str(src).unwrap().inline_functions().err())
+            .unwrap()
+            .join()
+            .unwrap()
+            .expect("Expected inlining to fail");
+
+        assert_eq!(error.kind.to_string(), "recursive call cycle: ping → pong → ping");
+    }
 }
@@ -0,0 +1,187 @@
+//! Integer arithmetic lowering prepares the truncations which follow integer arithmetic for ACIR.
+//!
+//! Arithmetic on sized integers is performed on field elements and followed by a truncation back
+//! to the bit size of the integer type, which ACIR implements with a range constraint. This pass
+//! makes everything that truncation depends on explicit within the SSA:
+//! - A truncation of a value which is already known to fit within the truncated bit size has no
+//!   effect, so it is removed, using [RangeAnalysis] to bound the value.
+//! - A subtraction may underflow to a "negative" field element, which cannot be truncated
+//!   directly. The integer modulus `2^bit_size` is added to the difference before it is truncated,
+//!   so that the truncated value is always non-negative. Additions and multiplications of
+//!   integers never underflow, so the truncations following them are kept as they are.
+//!
+//! Every other truncation is kept directly after the arithmetic it follows, so that each
+//! operation which may overflow is paired with the range constraint of its own result.
+//!
+//! Since the result is ordinary SSA, later passes such as global value numbering can remove any
+//! truncation which is computed twice. ACIR generation expects every subtraction to have been
+//! lowered by this pass before it is truncated. Brillig handles integer arithmetic itself, so only
+//! ACIR functions are lowered.
+use acvm::FieldElement;
+
+use crate::ssa_refactor::{
+    ir::{
+        basic_block::BasicBlockId,
+        dfg::DataFlowGraph,
+        function::RuntimeType,
+        instruction::{Binary, BinaryOp, Instruction},
+        range_analysis::RangeAnalysis,
+        value::{Value, ValueId},
+    },
+    ssa_gen::Ssa,
+};
+
+impl Ssa {
+    /// Removes truncations which have no effect, and adds the integer modulus to each
+    /// subtraction before it is truncated, within each ACIR function.
+    pub(crate) fn lower_integer_arithmetic(mut self) -> Ssa {
+        for function in self.functions.values_mut() {
+            if function.runtime() == RuntimeType::Acir {
+                let ranges = RangeAnalysis::with_function(function);
+                for block in function.reachable_blocks() {
+                    lower_block(&mut function.dfg, block, &ranges);
+                }
+            }
+        }
+        self
+    }
+}
+
+fn lower_block(dfg: &mut DataFlowGraph, block: BasicBlockId, ranges: &RangeAnalysis) {
+    let instructions = std::mem::take(dfg[block].instructions_mut());
+    let mut new_instructions = Vec::with_capacity(instructions.len());
+
+    for id in instructions {
+        let (value, bit_size, max_bit_size) = match &dfg[id] {
+            Instruction::Truncate { value, bit_size, max_bit_size } => {
+                (dfg.resolve(*value), *bit_size, *max_bit_size)
+            }
+            _ => {
+                new_instructions.push(id);
+                continue;
+            }
+        };
+
+        let fits =
+            ranges.range_in_block(dfg, block, value).map_or(false, |range| range.fits_in(bit_size));
+        if fits {
+            let result = dfg.instruction_results(id)[0];
+            dfg.set_value_from_id(result, value);
+            continue;
+        }
+
+        if is_subtraction(dfg, value) {
+            let modulus = FieldElement::from(2_u128).pow(&FieldElement::from(bit_size as u128));
+            let modulus = dfg.make_constant(modulus, dfg.type_of_value(value));
+            let add = Binary { lhs: value, rhs: modulus, operator: BinaryOp::Add };
+            let add = dfg.make_instruction(Instruction::Binary(add), None);
+            if let Some(location) = dfg.get_location(&id) {
+                dfg.set_location(add, location);
+            }
+            new_instructions.push(add);
+
+            let value = dfg.instruction_results(add)[0];
+            dfg[id] = Instruction::Truncate { value, bit_size, max_bit_size };
+        }
+        new_instructions.push(id);
+    }
+
+    *dfg[block].instructions_mut() = new_instructions;
+}
+
+fn is_subtraction(dfg: &DataFlowGraph, value: ValueId) -> bool {
+    match &dfg[value] {
+        Value::Instruction { instruction, .. } => matches!(
+            &dfg[*instruction],
+            Instruction::Binary(Binary { operator: BinaryOp::Sub, .. })
+        ),
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::str::FromStr;
+
+    use crate::ssa_refactor::{ir::parser::assert_ssa_equals, ssa_gen::Ssa};
+
+    #[test]
+    fn lowers_integer_truncations() {
+        // v0 is constrained to be less than 100, so v6 cannot overflow
+        let src = "
+            acir fn main f0 {
+              b0(v0: u8, v1: u8):
+                v2 = sub v0, v1
+                v3 = truncate v2 to 8 bits, max_bit_size: 9
+                v4 = lt v0, u8 100
+                constrain v4
+                v5 = add v0, u8 100
+                v6 = truncate v5 to 8 bits, max_bit_size: 9
+                v7 = mul v0, v1
+                v8 = truncate v7 to 8 bits, max_bit_size: 16
+                return v3, v6, v8
+            }
+        ";
+        let expected = "
+            acir fn main f0 {
+              b0(v0: u8, v1: u8):
+                v2 = sub v0, v1
+                v3 = add v2, u8 256
+                v4 = truncate v3 to 8 bits, max_bit_size: 9
+                v5 = lt v0, u8 100
+                constrain v5
+                v6 = add v0, u8 100
+                v7 = mul v0, v1
+                v8 = truncate v7 to 8 bits, max_bit_size: 16
+                return v4, v6, v8
+            }
+        ";
+        let ssa = Ssa::from_str(src).unwrap().lower_integer_arithmetic();
+        assert_ssa_equals(&ssa, expected);
+    }
+
+    #[test]
+    fn keeps_truncations_within_chains_of_additions() {
+        let src = "
+            acir fn main f0 {
+              b0(v0: u64, v1: u64, v2: u64, v3: u64):
+                v4 = add v0, v1
+                v5 = truncate v4 to 64 bits, max_bit_size: 65
+                v6 = add v5, v2
+                v7 = truncate v6 to 64 bits, max_bit_size: 65
+                v8 = sub v7, v3
+                v9 = truncate v8 to 64 bits, max_bit_size: 65
+                return v9
+            }
+        ";
+        let expected = "
+            acir fn main f0 {
+              b0(v0: u64, v1: u64, v2: u64, v3: u64):
+                v4 = add v0, v1
+                v5 = truncate v4 to 64 bits, max_bit_size: 65
+                v6 = add v5, v2
+                v7 = truncate v6 to 64 bits, max_bit_size: 65
+                v8 = sub v7, v3
+                v9 = add v8, u64 18446744073709551616
+                v10 = truncate v9 to 64 bits, max_bit_size: 65
+                return v10
+            }
+        ";
+        let ssa = Ssa::from_str(src).unwrap().lower_integer_arithmetic();
+        assert_ssa_equals(&ssa, expected);
+    }
+
+    #[test]
+    fn leaves_brillig_functions() {
+        let src = "
+            brillig fn main f0 {
+              b0(v0: u8, v1: u8):
+                v2 = sub v0, v1
+                v3 = truncate v2 to 8 bits, max_bit_size: 9
+                return v3
+            }
+        ";
+        let ssa = Ssa::from_str(src).unwrap().lower_integer_arithmetic();
+        assert_ssa_equals(&ssa, src);
+    }
+}
@@ -0,0 +1,314 @@
+//! Jump threading bypasses a block ending in a jmpif when the jmpif's condition is known along
+//! an incoming edge, jumping from the predecessor directly to the destination taken.
+//!
+//! After inlining, a branch is often made on a value which is a constant along some of the edges
+//! into its block, e.g. a flag set to `true` within one branch of an earlier if expression.
+//! Flattening would otherwise predicate both destinations of the branch along every path.
+//!
+//! For each edge `p -> b` into a block `b` ending in a jmpif, the instructions of `b` are copied
+//! into a new block with the parameters of `b` replaced by the jmp arguments of `p`. If the
+//! condition of the jmpif simplifies to a constant within the copy, the copy jumps to the
+//! destination taken and `p` jumps to the copy instead of `b`. This only applies to blocks with
+//! few instructions whose values are not used by any other block, and which are not loop headers.
+//!
+//! Flattening requires each branch to rejoin at a single block, so within ACIR functions a block
+//! is only bypassed if its condition is known along every incoming edge. Unconstrained functions
+//! are not flattened, so any edge along which the condition is known is threaded within them.
+//! Each block may still have at most two predecessors afterward.
+use std::collections::{HashMap, HashSet};
+
+use crate::ssa_refactor::{
+    ir::{
+        basic_block::BasicBlockId,
+        cfg::ControlFlowGraph,
+        dfg::DataFlowGraph,
+        function::{Function, RuntimeType},
+        function_inserter::FunctionInserter,
+        instruction::TerminatorInstruction,
+        loops::LoopForest,
+        value::{Value, ValueId},
+    },
+    ssa_gen::Ssa,
+};
+
+/// The maximum number of instructions a block may contain to be copied along an edge
+const MAX_COPIED_INSTRUCTIONS: usize = 10;
+
+/// An edge into a block which can be threaded through a copy of the block
+struct ThreadedEdge {
+    predecessor: BasicBlockId,
+    copy: BasicBlockId,
+    destination: BasicBlockId,
+}
+
+impl Ssa {
+    /// Bypasses each branch whose condition is known along an edge into its block.
+    pub(crate) fn thread_jumps(mut self) -> Ssa {
+        for function in self.functions.values_mut() {
+            thread_function_jumps(function);
+        }
+        self
+    }
+}
+
+fn thread_function_jumps(function: &mut Function) {
+    let mut cfg = ControlFlowGraph::with_function(function);
+    let mut reachable_blocks = function.reachable_blocks();
+    let loops = LoopForest::with_function(function);
+    let blocks_with_escaping_values = find_blocks_with_escaping_values(function);
+
+    let mut blocks: Vec<_> = reachable_blocks.iter().copied().collect();
+    blocks.sort();
+
+    for block in blocks {
+        let can_copy = block != function.entry_block()
+            && reachable_blocks.contains(&block)
+            && !loops.is_loop_header(block)
+            && !blocks_with_escaping_values.contains(&block)
+            && function.dfg[block].instructions().len() <= MAX_COPIED_INSTRUCTIONS;
+
+        if can_copy && thread_block(function, &cfg, block) {
+            cfg = ControlFlowGraph::with_function(function);
+            reachable_blocks = function.reachable_blocks();
+        }
+    }
+}
+
+/// Threads each edge into the given block along which its jmpif condition is known.
+/// Returns true if any edge was threaded.
+fn thread_block(function: &mut Function, cfg: &ControlFlowGraph, block: BasicBlockId) -> bool {
+    let (then_destination, else_destination) = match function.dfg[block].terminator() {
+        Some(TerminatorInstruction::JmpIf { then_destination, else_destination, .. }) => {
+            (*then_destination, *else_destination)
+        }
+        _ => return false,
+    };
+
+    let mut predecessors: Vec<_> = cfg.predecessors(block).collect();
+    predecessors.sort();
+
+    let mut edges = Vec::new();
+    for predecessor in &predecessors {
+        let arguments = match function.dfg[*predecessor].terminator() {
+            Some(TerminatorInstruction::Jmp { destination, arguments })
+                if *destination == block =>
+            {
+                arguments.clone()
+            }
+            _ => continue,
+        };
+
+        if let Some((copy, destination)) = copy_block(function, block, &arguments) {
+            edges.push(ThreadedEdge { predecessor: *predecessor, copy, destination });
+        }
+    }
+
+    let bypasses_block = edges.len() == predecessors.len();
+    if edges.is_empty() || (!bypasses_block && function.runtime() == RuntimeType::Acir) {
+        return false;
+    }
+
+    // The block is no longer a predecessor of its destinations if every edge is threaded
+    let mut destinations = vec![then_destination, else_destination];
+    destinations.dedup();
+    for destination in destinations {
+        let kept = cfg
+            .predecessors(destination)
+            .filter(|predecessor| *predecessor != block || !bypasses_block);
+        let added = edges.iter().filter(|edge| edge.destination == destination);
+        if kept.count() + added.count() > 2 {
+            return false;
+        }
+    }
+
+    for ThreadedEdge { predecessor, copy, destination } in edges {
+        let jmp = TerminatorInstruction::Jmp { destination, arguments: Vec::new() };
+        function.dfg[copy].set_terminator(jmp);
+
+        let jmp = TerminatorInstruction::Jmp { destination: copy, arguments: Vec::new() };
+        function.dfg[predecessor].set_terminator(jmp);
+    }
+    true
+}
+
+/// Copies the instructions of the given block into a new block, replacing the block's
+/// parameters with the given arguments. Returns the copy along with the destination of the
+/// block's jmpif, if its condition is known within the copy. The copy is left without a
+/// terminator, and remains unreachable unless a jump to it is later added.
+fn copy_block(
+    function: &mut Function,
+    block: BasicBlockId,
+    arguments: &[ValueId],
+) -> Option<(BasicBlockId, BasicBlockId)> {
+    let (condition, then_destination, else_destination) = match function.dfg[block].terminator() {
+        Some(TerminatorInstruction::JmpIf { condition, then_destination, else_destination }) => {
+            (*condition, *then_destination, *else_destination)
+        }
+        _ => unreachable!("Only blocks ending in a jmpif are threaded"),
+    };
+
+    let copy = function.dfg.make_block();
+    let parameters = function.dfg.block_parameters(block).to_vec();
+    let instructions = function.dfg[block].instructions().to_vec();
+
+    let mut inserter = FunctionInserter::new(function);
+    for (parameter, argument) in parameters.into_iter().zip(arguments) {
+        inserter.map_value(parameter, *argument);
+    }
+    for instruction in instructions {
+        inserter.push_instruction(instruction, copy);
+    }
+
+    let condition = inserter.resolve(condition);
+    let constant = inserter.function.dfg.get_numeric_constant(condition)?;
+    let destination = if constant.is_zero() { else_destination } else { then_destination };
+    Some((copy, destination))
+}
+
+/// Finds each block defining a parameter or instruction result which is used by another block.
+/// Copying such a block would leave these uses without a single definition.
+fn find_blocks_with_escaping_values(function: &Function) -> HashSet<BasicBlockId> {
+    let dfg = &function.dfg;
+    let blocks = function.reachable_blocks();
+
+    let mut defining_blocks = HashMap::new();
+    for block in &blocks {
+        for parameter in dfg.block_parameters(*block) {
+            defining_blocks.insert(*parameter, *block);
+        }
+        for instruction in dfg[*block].instructions() {
+            for result in dfg.instruction_results(*instruction) {
+                defining_blocks.insert(*result, *block);
+            }
+        }
+    }
+
+    let mut escaping = HashSet::new();
+    for block in &blocks {
+        let mut check_use = |value| {
+            for_each_used_value(dfg, value, &mut |value| match defining_blocks.get(&value) {
+                Some(defining_block) if defining_block != block => {
+                    escaping.insert(*defining_block);
+                }
+                _ => (),
+            });
+        };
+
+        for instruction in dfg[*block].instructions() {
+            dfg[*instruction].for_each_value(&mut check_use);
+        }
+        if let Some(terminator) = dfg[*block].terminator() {
+            terminator.for_each_value(&mut check_use);
+        }
+    }
+    escaping
+}
+
+/// Applies the given function to the given value and, if it is a constant array, to each value
+/// used within it.
+fn for_each_used_value(dfg: &DataFlowGraph, value: ValueId, f: &mut impl FnMut(ValueId)) {
+    let value = dfg.resolve(value);
+    f(value);
+    if let Value::Array { array, .. } = &dfg[value] {
+        for element in array {
+            for_each_used_value(dfg, *element, f);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::str::FromStr;
+
+    use crate::ssa_refactor::{ir::parser::assert_ssa_equals, ssa_gen::Ssa};
+
+    #[test]
+    fn bypasses_branches_known_along_every_edge() {
+        let src = "
+            acir fn main f0 {
+              b0(v0: u1):
+                jmpif v0 then: b1, else: b2
+              b1():
+                jmp b3(u1 1)
+              b2():
+                jmp b3(u1 0)
+              b3(v1: u1):
+                jmpif v1 then: b4, else: b5
+              b4():
+                jmp b6(Field 1)
+              b5():
+                jmp b6(Field 2)
+              b6(v2: Field):
+                return v2
+            }
+        ";
+        let expected = "
+            acir fn main f0 {
+              b0(v0: u1):
+                jmpif v0 then: b1, else: b2
+              b1():
+                jmp b7()
+              b7():
+                jmp b4()
+              b4():
+                jmp b6(Field 1)
+              b6(v2: Field):
+                return v2
+              b2():
+                jmp b8()
+              b8():
+                jmp b5()
+              b5():
+                jmp b6(Field 2)
+            }
+        ";
+        let ssa = Ssa::from_str(src).unwrap().thread_jumps();
+        assert_ssa_equals(&ssa, expected);
+    }
+
+    #[test]
+    fn threads_single_edges_only_in_unconstrained_functions() {
+        let src = "
+            brillig fn main f0 {
+              b0(v0: u1, v1: u1):
+                jmpif v0 then: b1, else: b2
+              b1():
+                jmp b3(u1 1)
+              b2():
+                jmp b3(v1)
+              b3(v2: u1):
+                v3 = not v2
+                jmpif v3 then: b4, else: b5
+              b4():
+                return Field 1
+              b5():
+                return Field 2
+            }
+        ";
+        let expected = "
+            brillig fn main f0 {
+              b0(v0: u1, v1: u1):
+                jmpif v0 then: b1, else: b2
+              b1():
+                jmp b6()
+              b6():
+                jmp b5()
+              b5():
+                return Field 2
+              b2():
+                jmp b3(v1)
+              b3(v2: u1):
+                v3 = not v2
+                jmpif v3 then: b4, else: b5
+              b4():
+                return Field 1
+            }
+        ";
+        let ssa = Ssa::from_str(src).unwrap().thread_jumps();
+        assert_ssa_equals(&ssa, expected);
+
+        let acir_src = src.replace("brillig", "acir");
+        let ssa = Ssa::from_str(&acir_src).unwrap().thread_jumps();
+        assert_ssa_equals(&ssa, &acir_src);
+    }
+}
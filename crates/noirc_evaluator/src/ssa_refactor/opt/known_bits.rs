@@ -0,0 +1,470 @@
+//! The known bits pass tracks which bits of each unsigned integer value are known to be zero or
+//! one, similar to LLVM's `computeKnownBits`, and uses them to simplify bitwise operations:
+//! - An operation whose result has every bit known is replaced by a constant.
+//! - `x & y` is replaced by `x` if every bit which may be one in `x` is known to be one in `y`.
+//! - `x | y` is replaced by `y` if every bit which may be one in `x` is known to be one in `y`.
+//! - `x | y` and `x ^ y` are replaced by `x + y` if no bit may be one in both operands, since the
+//!   addition cannot carry.
+//!
+//! In ACIR, `and`, `xor`, and non-boolean `or` operations are black box functions which decompose
+//! their operands into bits, whereas an addition is a single linear term. These patterns are
+//! common in hash functions, where values are masked, shifted, and recombined.
+//!
+//! Known bits flow through constants, masks, shifts by constants, casts, and truncations. Block
+//! parameters keep the bits known in every argument passed to them, other than loop-carried
+//! parameters, which only keep the bits implied by their type.
+use std::collections::HashMap;
+
+use crate::ssa_refactor::{
+    ir::{
+        basic_block::BasicBlockId,
+        cfg::ControlFlowGraph,
+        dfg::DataFlowGraph,
+        function::Function,
+        instruction::{Binary, BinaryOp, Instruction, TerminatorInstruction},
+        post_order::PostOrder,
+        types::{NumericType, Type},
+        value::ValueId,
+    },
+    ssa_gen::Ssa,
+};
+
+impl Ssa {
+    /// Simplifies bitwise operations within each function using the bits known about their
+    /// operands.
+    pub(crate) fn simplify_known_bits(mut self) -> Ssa {
+        for function in self.functions.values_mut() {
+            simplify_function(function);
+        }
+        self
+    }
+}
+
+/// The bits of a value which are known to be zero or one. Any other bit is unknown.
+///
+/// A value only has known bits if it is known to be less than `2^128`, so that every bit
+/// beyond the 128 tracked here is zero.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+struct KnownBits {
+    zeros: u128,
+    ones: u128,
+}
+
+impl KnownBits {
+    fn constant(value: u128) -> Self {
+        Self { zeros: !value, ones: value }
+    }
+
+    /// The known bits of a value which fits within the given number of bits.
+    fn of_bit_size(bit_size: u32) -> Self {
+        Self { zeros: !mask(bit_size), ones: 0 }
+    }
+
+    /// Returns the bits which are not known to be zero
+    fn possible_ones(self) -> u128 {
+        !self.zeros
+    }
+
+    fn is_constant(self) -> bool {
+        self.zeros | self.ones == u128::MAX
+    }
+
+    /// Combines the facts known about the same value from two sources.
+    fn refine(self, other: KnownBits) -> KnownBits {
+        Self { zeros: self.zeros | other.zeros, ones: self.ones | other.ones }
+    }
+
+    /// Returns the bits known to be the same in both values.
+    fn merge(self, other: KnownBits) -> KnownBits {
+        Self { zeros: self.zeros & other.zeros, ones: self.ones & other.ones }
+    }
+
+    fn and(self, other: KnownBits) -> KnownBits {
+        Self { zeros: self.zeros | other.zeros, ones: self.ones & other.ones }
+    }
+
+    fn or(self, other: KnownBits) -> KnownBits {
+        Self { zeros: self.zeros & other.zeros, ones: self.ones | other.ones }
+    }
+
+    fn xor(self, other: KnownBits) -> KnownBits {
+        Self {
+            zeros: (self.zeros & other.zeros) | (self.ones & other.ones),
+            ones: (self.zeros & other.ones) | (self.ones & other.zeros),
+        }
+    }
+
+    /// Returns the known bits of this value shifted left, if no bit which may be one is
+    /// shifted beyond the tracked bits.
+    fn shl(self, shift: u32) -> Option<KnownBits> {
+        if shift >= 128 || self.possible_ones().leading_zeros() < shift {
+            return None;
+        }
+        Some(Self { zeros: (self.zeros << shift) | mask(shift), ones: self.ones << shift })
+    }
+
+    fn shr(self, shift: u32) -> KnownBits {
+        if shift >= 128 {
+            return Self::constant(0);
+        }
+        Self { zeros: (self.zeros >> shift) | !(u128::MAX >> shift), ones: self.ones >> shift }
+    }
+
+    fn truncate(self, bit_size: u32) -> KnownBits {
+        self.and(Self::of_bit_size(bit_size))
+    }
+
+    /// Returns the known bits of the bitwise negation of this value within the given number of
+    /// bits, if the value fits within them.
+    fn not(self, bit_size: u32) -> Option<KnownBits> {
+        let mask = mask(bit_size);
+        (self.possible_ones() & !mask == 0)
+            .then(|| Self { zeros: (self.ones & mask) | !mask, ones: self.zeros & mask })
+    }
+
+    fn add(self, other: KnownBits) -> Option<KnownBits> {
+        if self.possible_ones() & other.possible_ones() == 0 {
+            return Some(self.or(other));
+        }
+        let max = self.possible_ones().checked_add(other.possible_ones())?;
+        let trailing_zeros = self.trailing_zeros().min(other.trailing_zeros());
+        Some(Self { zeros: !mask(bit_length(max)) | mask(trailing_zeros), ones: 0 })
+    }
+
+    fn mul(self, other: KnownBits) -> Option<KnownBits> {
+        let max = self.possible_ones().checked_mul(other.possible_ones())?;
+        let trailing_zeros = self.trailing_zeros() + other.trailing_zeros();
+        Some(Self { zeros: !mask(bit_length(max)) | mask(trailing_zeros), ones: 0 })
+    }
+
+    /// Returns the number of low bits known to be zero
+    fn trailing_zeros(self) -> u32 {
+        self.zeros.trailing_ones()
+    }
+}
+
+/// Returns a mask of the lowest `bit_size` bits.
+fn mask(bit_size: u32) -> u128 {
+    if bit_size >= 128 {
+        u128::MAX
+    } else {
+        (1 << bit_size) - 1
+    }
+}
+
+/// Returns the number of bits needed to represent the given value.
+fn bit_length(value: u128) -> u32 {
+    128 - value.leading_zeros()
+}
+
+/// A simplified form of a bitwise instruction
+enum Simplification {
+    /// Replace each use of the instruction's result with the given value and remove the
+    /// instruction.
+    Value(ValueId),
+
+    /// Replace the instruction with another instruction producing the same result.
+    Instruction(Instruction),
+}
+
+fn simplify_function(function: &mut Function) {
+    let cfg = ControlFlowGraph::with_function(function);
+    let post_order = PostOrder::with_function(function);
+    let entry_block = function.entry_block();
+    let mut known_bits = HashMap::new();
+
+    // Visiting blocks in reverse post-order visits the definition of each value before its
+    // uses, other than the arguments passed along loop back-edges.
+    for block in post_order.as_slice().iter().rev() {
+        let dfg = &mut function.dfg;
+
+        for (index, parameter) in dfg.block_parameters(*block).iter().enumerate() {
+            let mut bits = type_known_bits(&dfg.type_of_value(*parameter));
+            if *block != entry_block {
+                if let Some(argument_bits) =
+                    argument_known_bits(dfg, &cfg, &known_bits, *block, index)
+                {
+                    bits = Some(bits.map_or(argument_bits, |bits| bits.refine(argument_bits)));
+                }
+            }
+            if let Some(bits) = bits {
+                known_bits.insert(*parameter, bits);
+            }
+        }
+
+        simplify_block(dfg, *block, &mut known_bits);
+    }
+}
+
+fn simplify_block(
+    dfg: &mut DataFlowGraph,
+    block: BasicBlockId,
+    known_bits: &mut HashMap<ValueId, KnownBits>,
+) {
+    let instructions = std::mem::take(dfg[block].instructions_mut());
+    let mut new_instructions = Vec::with_capacity(instructions.len());
+
+    for id in instructions {
+        let instruction = dfg[id].map_values(|value| dfg.resolve(value));
+        let results = dfg.instruction_results(id);
+        if results.len() != 1 {
+            new_instructions.push(id);
+            continue;
+        }
+        let result = results[0];
+
+        let result_bits = instruction_known_bits(dfg, known_bits, &instruction, result);
+        if let Some(bits) = result_bits {
+            known_bits.insert(result, bits);
+        }
+
+        match simplify_instruction(dfg, known_bits, &instruction, result_bits) {
+            Some(Simplification::Value(value)) => {
+                dfg.set_value_from_id(result, value);
+                continue;
+            }
+            Some(Simplification::Instruction(simplified)) => dfg[id] = simplified,
+            None => (),
+        }
+        new_instructions.push(id);
+    }
+
+    *dfg[block].instructions_mut() = new_instructions;
+}
+
+/// Returns the bits known in every argument passed to the given parameter of a block, or None if
+/// the bits of an argument are not known, such as an argument passed along a loop back-edge.
+fn argument_known_bits(
+    dfg: &DataFlowGraph,
+    cfg: &ControlFlowGraph,
+    known_bits: &HashMap<ValueId, KnownBits>,
+    block: BasicBlockId,
+    index: usize,
+) -> Option<KnownBits> {
+    let mut merged: Option<KnownBits> = None;
+    for predecessor in cfg.predecessors(block) {
+        let argument = match dfg[predecessor].terminator() {
+            Some(TerminatorInstruction::Jmp { arguments, .. }) => arguments[index],
+            _ => return None,
+        };
+        let bits = known_bits_of(dfg, known_bits, argument)?;
+        merged = Some(merged.map_or(bits, |merged| merged.merge(bits)));
+    }
+    merged
+}
+
+/// Returns the bits implied by the given type, which hold for the parameters of a block.
+fn type_known_bits(typ: &Type) -> Option<KnownBits> {
+    unsigned_bit_size(typ).map(KnownBits::of_bit_size)
+}
+
+fn unsigned_bit_size(typ: &Type) -> Option<u32> {
+    match typ {
+        Type::Numeric(NumericType::Unsigned { bit_size }) => Some(*bit_size),
+        _ => None,
+    }
+}
+
+fn known_bits_of(
+    dfg: &DataFlowGraph,
+    known_bits: &HashMap<ValueId, KnownBits>,
+    value: ValueId,
+) -> Option<KnownBits> {
+    let value = dfg.resolve(value);
+    match dfg.get_numeric_constant(value) {
+        Some(constant) if constant.num_bits() <= 128 => {
+            Some(KnownBits::constant(constant.to_u128()))
+        }
+        Some(_) => None,
+        None => known_bits.get(&value).copied(),
+    }
+}
+
+/// Returns the bits known in the result of the given instruction.
+fn instruction_known_bits(
+    dfg: &DataFlowGraph,
+    known_bits: &HashMap<ValueId, KnownBits>,
+    instruction: &Instruction,
+    result: ValueId,
+) -> Option<KnownBits> {
+    let bits = |value| known_bits_of(dfg, known_bits, value);
+    let shift = |value| {
+        dfg.get_numeric_constant(value)
+            .filter(|shift| shift.num_bits() <= 32)
+            .map(|shift| shift.to_u128() as u32)
+    };
+
+    match instruction {
+        Instruction::Binary(Binary { lhs, rhs, operator }) => match operator {
+            BinaryOp::Eq | BinaryOp::Lt => Some(KnownBits::of_bit_size(1)),
+            // The known zeros of either operand are zero in the result
+            BinaryOp::And => match (bits(*lhs), bits(*rhs)) {
+                (Some(lhs), Some(rhs)) => Some(lhs.and(rhs)),
+                (Some(known), None) | (None, Some(known)) => {
+                    Some(KnownBits { zeros: known.zeros, ones: 0 })
+                }
+                (None, None) => None,
+            },
+            BinaryOp::Or => Some(bits(*lhs)?.or(bits(*rhs)?)),
+            BinaryOp::Xor => Some(bits(*lhs)?.xor(bits(*rhs)?)),
+            BinaryOp::Add => bits(*lhs)?.add(bits(*rhs)?),
+            BinaryOp::Mul => bits(*lhs)?.mul(bits(*rhs)?),
+            BinaryOp::Shl => {
+                // Brillig discards bits shifted beyond the bit size of the type, so only the bits
+                // within it may be known to be one.
+                let shifted = bits(*lhs)?.shl(shift(*rhs)?)?;
+                let bit_size = unsigned_bit_size(&dfg.type_of_value(result))?;
+                Some(KnownBits { ones: shifted.ones & mask(bit_size), ..shifted })
+            }
+            BinaryOp::Shr => Some(bits(*lhs)?.shr(shift(*rhs)?)),
+            BinaryOp::Sub | BinaryOp::Div | BinaryOp::Mod => None,
+        },
+        Instruction::Cast(value, Type::Numeric(NumericType::Unsigned { bit_size }))
+        | Instruction::Truncate { value, bit_size, .. } => Some(
+            bits(*value).map_or(KnownBits::of_bit_size(*bit_size), |bits| bits.truncate(*bit_size)),
+        ),
+        Instruction::Cast(value, Type::Numeric(NumericType::NativeField)) => bits(*value),
+        Instruction::Not(value) => {
+            bits(*value)?.not(unsigned_bit_size(&dfg.type_of_value(result))?)
+        }
+        _ => None,
+    }
+}
+
+/// Returns a simplified form of the given bitwise instruction, if there is one.
+fn simplify_instruction(
+    dfg: &mut DataFlowGraph,
+    known_bits: &HashMap<ValueId, KnownBits>,
+    instruction: &Instruction,
+    result_bits: Option<KnownBits>,
+) -> Option<Simplification> {
+    let (lhs, rhs, operator) = match instruction {
+        Instruction::Binary(binary) => (binary.lhs, binary.rhs, binary.operator),
+        _ => return None,
+    };
+    let bitwise = [BinaryOp::And, BinaryOp::Or, BinaryOp::Xor, BinaryOp::Shl, BinaryOp::Shr];
+    let typ = dfg.type_of_value(lhs);
+    if !bitwise.contains(&operator) || unsigned_bit_size(&typ).is_none() {
+        return None;
+    }
+
+    if let Some(bits) = result_bits.filter(|bits| bits.is_constant()) {
+        return Some(Simplification::Value(dfg.make_constant(bits.ones.into(), typ)));
+    }
+
+    let lhs_bits = known_bits_of(dfg, known_bits, lhs)?;
+    let rhs_bits = known_bits_of(dfg, known_bits, rhs)?;
+    // Returns true if every bit which may be one in `a` is known to be one in `b`
+    let covers = |a: KnownBits, b: KnownBits| a.possible_ones() & !b.ones == 0;
+    let disjoint = lhs_bits.possible_ones() & rhs_bits.possible_ones() == 0;
+    let add = || Instruction::Binary(Binary { lhs, rhs, operator: BinaryOp::Add });
+
+    match operator {
+        BinaryOp::And if covers(lhs_bits, rhs_bits) => Some(Simplification::Value(lhs)),
+        BinaryOp::And if covers(rhs_bits, lhs_bits) => Some(Simplification::Value(rhs)),
+        BinaryOp::Or if covers(lhs_bits, rhs_bits) => Some(Simplification::Value(rhs)),
+        BinaryOp::Or if covers(rhs_bits, lhs_bits) => Some(Simplification::Value(lhs)),
+        BinaryOp::Or | BinaryOp::Xor if disjoint => Some(Simplification::Instruction(add())),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::str::FromStr;
+
+    use crate::ssa_refactor::{ir::parser::assert_ssa_equals, ssa_gen::Ssa};
+
+    #[test]
+    fn simplifies_bitwise_operations() {
+        let src = "
+            acir fn main f0 {
+              b0(v0: u8, v1: u8):
+                v2 = and v0, u8 15
+                v3 = shl v1, u8 4
+                v4 = or v2, v3
+                v5 = and v2, u8 31
+                v6 = xor v2, v3
+                v7 = and v2, v3
+                v8 = or v2, u8 255
+                return v4, v5, v6, v7, v8
+            }
+        ";
+        let expected = "
+            acir fn main f0 {
+              b0(v0: u8, v1: u8):
+                v2 = and v0, u8 15
+                v3 = shl v1, u8 4
+                v4 = add v2, v3
+                v5 = add v2, v3
+                return v4, v2, v5, u8 0, u8 255
+            }
+        ";
+        let ssa = Ssa::from_str(src).unwrap().simplify_known_bits();
+        assert_ssa_equals(&ssa, expected);
+    }
+
+    #[test]
+    fn keeps_operations_on_unknown_bits() {
+        let src = "
+            acir fn main f0 {
+              b0(v0: u32, v1: u32):
+                v2 = and v0, v1
+                v3 = xor v0, u32 1
+                v4 = shr v0, u32 8
+                v5 = and v4, u32 16777215
+                v6 = and v4, u32 65535
+                return v2, v3, v5, v6
+            }
+        ";
+        let expected = "
+            acir fn main f0 {
+              b0(v0: u32, v1: u32):
+                v2 = and v0, v1
+                v3 = xor v0, u32 1
+                v4 = shr v0, u32 8
+                v5 = and v4, u32 65535
+                return v2, v3, v4, v5
+            }
+        ";
+        let ssa = Ssa::from_str(src).unwrap().simplify_known_bits();
+        assert_ssa_equals(&ssa, expected);
+    }
+
+    #[test]
+    fn merges_known_bits_of_block_arguments() {
+        let src = "
+            brillig fn main f0 {
+              b0(v0: u1, v1: u32):
+                jmpif v0 then: b1, else: b2
+              b1():
+                v2 = and v1, u32 240
+                jmp b3(v2)
+              b2():
+                v3 = shl v1, u32 8
+                v4 = and v3, u32 3840
+                jmp b3(v4)
+              b3(v5: u32):
+                v6 = and v5, u32 15
+                return v6
+            }
+        ";
+        let expected = "
+            brillig fn main f0 {
+              b0(v0: u1, v1: u32):
+                jmpif v0 then: b1, else: b2
+              b1():
+                v2 = and v1, u32 240
+                jmp b3(v2)
+              b2():
+                v3 = shl v1, u32 8
+                v4 = and v3, u32 3840
+                jmp b3(v4)
+              b3(v5: u32):
+                return u32 0
+            }
+        ";
+        let ssa = Ssa::from_str(src).unwrap().simplify_known_bits();
+        assert_ssa_equals(&ssa, expected);
+    }
+}
@@ -0,0 +1,249 @@
+//! Loop-invariant code motion (LICM) moves instructions whose operands are all defined outside
+//! of a loop into the loop's pre-header, so that they are computed once rather than on every
+//! iteration of the loop.
+//!
+//! Since a hoisted instruction will be executed even if the loop body never is, only instructions
+//! which can never fail are hoisted. For example, division is never hoisted since it may fail
+//! when dividing by zero, and array accesses are only hoisted when the index is known to be
+//! within bounds.
+//!
+//! Loops are visited from innermost to outermost so that an instruction hoisted into the
+//! pre-header of an inner loop may be hoisted again out of the enclosing loop.
+use std::collections::HashSet;
+
+use crate::ssa_refactor::{
+    ir::{
+        basic_block::BasicBlockId,
+        cfg::ControlFlowGraph,
+        dfg::DataFlowGraph,
+        dom::DominatorTree,
+        function::Function,
+        instruction::{BinaryOp, Instruction, InstructionId, Intrinsic, TerminatorInstruction},
+        loops::{Loop, LoopForest},
+        post_order::PostOrder,
+        value::{Value, ValueId},
+    },
+    ssa_gen::Ssa,
+};
+
+impl Ssa {
+    /// Hoists loop-invariant instructions out of each loop and into the loop's pre-header.
+    pub(crate) fn loop_invariant_code_motion(mut self) -> Ssa {
+        for function in self.functions.values_mut() {
+            hoist_loop_invariants(function);
+        }
+        self
+    }
+}
+
+fn hoist_loop_invariants(function: &mut Function) {
+    let cfg = ControlFlowGraph::with_function(function);
+    let post_order = PostOrder::with_function(function);
+    let mut dom_tree = DominatorTree::with_cfg_and_post_order(&cfg, &post_order);
+
+    // The loops are ordered such that inner loops of nested loops are visited first.
+    let loops = LoopForest::with_cfg_and_dom_tree(&cfg, &post_order, &mut dom_tree);
+    for loop_ in loops.loops() {
+        if let Some(pre_header) = find_pre_header(function, &cfg, loop_) {
+            hoist_from_loop(function, &post_order, loop_, pre_header);
+        }
+    }
+}
+
+/// Returns the pre-header of the given loop: the only block outside of the loop which jumps
+/// to the loop header. The pre-header must end in an unconditional jump so that any instruction
+/// appended to it is only executed when the loop is entered.
+///
+/// Returns None if there is no such block.
+fn find_pre_header(
+    function: &Function,
+    cfg: &ControlFlowGraph,
+    loop_: &Loop,
+) -> Option<BasicBlockId> {
+    let mut predecessors =
+        cfg.predecessors(loop_.header).filter(|predecessor| !loop_.blocks.contains(predecessor));
+
+    let pre_header = predecessors.next()?;
+    if predecessors.next().is_some() {
+        return None;
+    }
+
+    match function.dfg[pre_header].terminator() {
+        Some(TerminatorInstruction::Jmp { .. }) => Some(pre_header),
+        _ => None,
+    }
+}
+
+/// Moves each loop-invariant instruction within the given loop to the end of the pre-header.
+///
+/// The loop's blocks are visited in reverse post-order so that the definition of a value is
+/// always visited before its uses. This lets chains of loop-invariant instructions be hoisted
+/// in a single pass.
+fn hoist_from_loop(
+    function: &mut Function,
+    post_order: &PostOrder,
+    loop_: &Loop,
+    pre_header: BasicBlockId,
+) {
+    let mut defined_in_loop: HashSet<InstructionId> = loop_
+        .blocks
+        .iter()
+        .flat_map(|block| function.dfg[*block].instructions().iter().copied())
+        .collect();
+
+    let blocks = post_order.as_slice().iter().rev().filter(|block| loop_.blocks.contains(*block));
+    for block in blocks {
+        let mut hoisted = HashSet::new();
+
+        for instruction_id in function.dfg[*block].instructions().to_vec() {
+            let instruction = &function.dfg[instruction_id];
+            if !can_be_hoisted(instruction, &function.dfg) {
+                continue;
+            }
+
+            let mut is_invariant = true;
+            instruction.for_each_value(|value| {
+                is_invariant &=
+                    is_defined_outside_loop(value, &function.dfg, loop_, &defined_in_loop);
+            });
+
+            if is_invariant {
+                defined_in_loop.remove(&instruction_id);
+                hoisted.insert(instruction_id);
+                function.dfg[pre_header].insert_instruction(instruction_id);
+            }
+        }
+
+        function.dfg[*block]
+            .instructions_mut()
+            .retain(|instruction| !hoisted.contains(instruction));
+    }
+}
+
+/// Returns true if the given value is defined outside of the given loop.
+fn is_defined_outside_loop(
+    value: ValueId,
+    dfg: &DataFlowGraph,
+    loop_: &Loop,
+    defined_in_loop: &HashSet<InstructionId>,
+) -> bool {
+    match &dfg[dfg.resolve(value)] {
+        Value::Instruction { instruction, .. } => !defined_in_loop.contains(instruction),
+        Value::Param { block, .. } => !loop_.blocks.contains(block),
+        _ => true,
+    }
+}
+
+/// Returns true if the given instruction has no side-effects and can never fail,
+/// so it is safe to execute it even when the loop body itself would not have been executed.
+fn can_be_hoisted(instruction: &Instruction, dfg: &DataFlowGraph) -> bool {
+    match instruction {
+        Instruction::Binary(binary) => !matches!(binary.operator, BinaryOp::Div | BinaryOp::Mod),
+        Instruction::Cast(..) | Instruction::Not(_) | Instruction::Truncate { .. } => true,
+        Instruction::ArrayGet { array, index } | Instruction::ArraySet { array, index, .. } => {
+            is_known_to_be_in_bounds(*array, *index, dfg)
+        }
+        Instruction::Call { func, .. } => matches!(
+            &dfg[*func],
+            Value::Intrinsic(Intrinsic::ArrayLen | Intrinsic::Sort | Intrinsic::BlackBox(_))
+        ),
+        Instruction::Constrain(..)
+        | Instruction::Allocate
+        | Instruction::Load { .. }
+        | Instruction::Store { .. }
+        | Instruction::EnableSideEffects { .. } => false,
+    }
+}
+
+fn is_known_to_be_in_bounds(array: ValueId, index: ValueId, dfg: &DataFlowGraph) -> bool {
+    match (dfg.try_get_array_length(array), dfg.get_numeric_constant(index)) {
+        (Some(length), Some(index)) => index.to_u128() < length as u128,
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::ssa_refactor::{
+        ir::{function::RuntimeType, instruction::BinaryOp, map::Id, types::Type},
+        ssa_builder::FunctionBuilder,
+    };
+
+    #[test]
+    fn hoists_loop_invariants() {
+        // fn main f0 {
+        //   b0(v0: u32):
+        //     jmp b1(u32 0)
+        //   b1(v1: u32):
+        //     v2 = lt v1, u32 4
+        //     jmpif v2 then: b2, else: b3
+        //   b2():
+        //     v3 = mul v0, v0
+        //     v4 = add v3, u32 2
+        //     v5 = div v0, v4
+        //     v6 = add v1, v5
+        //     v7 = add v1, u32 1
+        //     jmp b1(v7)
+        //   b3():
+        //     return v0
+        // }
+        let main_id = Id::test_new(0);
+        let mut builder = FunctionBuilder::new("main".into(), main_id, RuntimeType::Acir);
+
+        let b1 = builder.insert_block();
+        let b2 = builder.insert_block();
+        let b3 = builder.insert_block();
+
+        let v0 = builder.add_parameter(Type::unsigned(32));
+        let zero = builder.numeric_constant(0u128, Type::unsigned(32));
+        let one = builder.numeric_constant(1u128, Type::unsigned(32));
+        let two = builder.numeric_constant(2u128, Type::unsigned(32));
+        let four = builder.numeric_constant(4u128, Type::unsigned(32));
+        builder.terminate_with_jmp(b1, vec![zero]);
+
+        builder.switch_to_block(b1);
+        let v1 = builder.add_block_parameter(b1, Type::unsigned(32));
+        let v2 = builder.insert_binary(v1, BinaryOp::Lt, four);
+        builder.terminate_with_jmpif(v2, b2, b3);
+
+        builder.switch_to_block(b2);
+        let v3 = builder.insert_binary(v0, BinaryOp::Mul, v0);
+        let v4 = builder.insert_binary(v3, BinaryOp::Add, two);
+        let v5 = builder.insert_binary(v0, BinaryOp::Div, v4);
+        builder.insert_binary(v1, BinaryOp::Add, v5);
+        let v7 = builder.insert_binary(v1, BinaryOp::Add, one);
+        builder.terminate_with_jmp(b1, vec![v7]);
+
+        builder.switch_to_block(b3);
+        builder.terminate_with_return(vec![v0]);
+
+        // Expected output:
+        //
+        // fn main f0 {
+        //   b0(v0: u32):
+        //     v3 = mul v0, v0
+        //     v4 = add v3, u32 2
+        //     jmp b1(u32 0)
+        //   b1(v1: u32):
+        //     v2 = lt v1, u32 4
+        //     jmpif v2 then: b2, else: b3
+        //   b2():
+        //     v5 = div v0, v4
+        //     v6 = add v1, v5
+        //     v7 = add v1, u32 1
+        //     jmp b1(v7)
+        //   b3():
+        //     return v0
+        // }
+        let ssa = builder.finish().loop_invariant_code_motion();
+        let main = ssa.main();
+
+        let entry_instructions = main.dfg[main.entry_block()].instructions();
+        assert_eq!(entry_instructions.len(), 2);
+        assert_eq!(main.dfg.instruction_results(entry_instructions[0])[0], v3);
+        assert_eq!(main.dfg.instruction_results(entry_instructions[1])[0], v4);
+
+        assert_eq!(main.dfg[b1].instructions().len(), 1);
+        assert_eq!(main.dfg[b2].instructions().len(), 3);
+    }
+}
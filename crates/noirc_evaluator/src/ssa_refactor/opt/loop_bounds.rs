@@ -0,0 +1,310 @@
+//! Loop bound evaluation replaces the bounds of each loop with constants where they can be
+//! evaluated at compile time, so that more loops can be unrolled.
+//!
+//! Unrolling requires the start of a loop's induction variable, passed to its header by the
+//! pre-header, and the end it is compared against by the header, to be constants. Instructions are
+//! simplified as they are inserted, but a bound computed from constants is still not a constant
+//! itself if it passes through a block parameter, e.g. `let n = if c { 4 } else { 4 } * 2;`, since
+//! the parameter is only known once every predecessor of its block has been inserted.
+//!
+//! This pass evaluates each bound by recursively evaluating the operands of the arithmetic, cast,
+//! and truncation instructions computing it. A block parameter is evaluated to a constant if every
+//! predecessor of its block passes the same constant for it. A bound computed within the loop
+//! itself is evaluated the same way, provided it does not depend on any value which changes
+//! between iterations. Bounds which cannot be evaluated are left unchanged, and are reported by
+//! the unrolling pass if the loop must be unrolled.
+use std::collections::{HashMap, HashSet};
+
+use crate::ssa_refactor::{
+    ir::{
+        basic_block::BasicBlockId,
+        cfg::ControlFlowGraph,
+        dfg::DataFlowGraph,
+        function::Function,
+        instruction::{
+            Binary, BinaryOp, Instruction, InstructionId, SimplifyResult, TerminatorInstruction,
+        },
+        loops::{Loop, LoopForest},
+        value::{Value, ValueId},
+    },
+    ssa_gen::Ssa,
+};
+
+impl Ssa {
+    /// Replaces the start and end of each loop within each function with a constant, where they
+    /// can be evaluated at compile time.
+    pub(crate) fn evaluate_loop_bounds(mut self) -> Ssa {
+        for function in self.functions.values_mut() {
+            let loops = LoopForest::with_function(function).into_loops();
+            let mut evaluator = BoundEvaluator::new(function);
+            for loop_ in &loops {
+                evaluator.evaluate_loop_bounds(loop_);
+            }
+        }
+        self
+    }
+}
+
+/// The bounds of a loop of the form `for i in start..end`
+struct LoopBounds {
+    /// The only block outside of the loop which jumps to its header
+    pre_header: BasicBlockId,
+
+    /// The position of the induction variable within the header's parameters
+    induction_variable: usize,
+
+    /// The comparison of the induction variable against the end of the loop
+    end_comparison: InstructionId,
+}
+
+struct BoundEvaluator<'f> {
+    function: &'f mut Function,
+    cfg: ControlFlowGraph,
+
+    /// The constant each value was evaluated to, or None if it is not constant
+    evaluated: HashMap<ValueId, Option<ValueId>>,
+
+    /// The values currently being evaluated, to stop at cycles through loop parameters
+    visiting: HashSet<ValueId>,
+}
+
+impl<'f> BoundEvaluator<'f> {
+    fn new(function: &'f mut Function) -> Self {
+        let cfg = ControlFlowGraph::with_function(function);
+        Self { function, cfg, evaluated: HashMap::new(), visiting: HashSet::new() }
+    }
+
+    fn evaluate_loop_bounds(&mut self, loop_: &Loop) {
+        let bounds = match find_loop_bounds(self.function, &self.cfg, loop_) {
+            Some(bounds) => bounds,
+            None => return,
+        };
+
+        let mut terminator = self.function.dfg[bounds.pre_header].unwrap_terminator().clone();
+        if let TerminatorInstruction::Jmp { arguments, .. } = &mut terminator {
+            let start = arguments[bounds.induction_variable];
+            if let Some(start) = self.evaluate(start) {
+                arguments[bounds.induction_variable] = start;
+                self.function.dfg.set_block_terminator(bounds.pre_header, terminator);
+            }
+        }
+
+        if let Instruction::Binary(Binary { lhs, rhs, .. }) = self.dfg()[bounds.end_comparison] {
+            if let Some(end) = self.evaluate(rhs) {
+                let comparison = Binary { lhs, rhs: end, operator: BinaryOp::Lt };
+                self.function.dfg[bounds.end_comparison] = Instruction::Binary(comparison);
+            }
+        }
+    }
+
+    /// Returns the constant the given value evaluates to, or None if it is not constant.
+    fn evaluate(&mut self, value: ValueId) -> Option<ValueId> {
+        let value = self.dfg().resolve(value);
+        if self.dfg().get_numeric_constant(value).is_some() {
+            return Some(value);
+        }
+        if let Some(constant) = self.evaluated.get(&value) {
+            return *constant;
+        }
+        if !self.visiting.insert(value) {
+            return None;
+        }
+
+        let constant = match self.dfg()[value] {
+            Value::Instruction { instruction, .. } => self.evaluate_instruction(instruction),
+            Value::Param { block, position, .. } => self.evaluate_parameter(block, position),
+            _ => None,
+        };
+
+        self.visiting.remove(&value);
+        self.evaluated.insert(value, constant);
+        constant
+    }
+
+    fn evaluate_instruction(&mut self, id: InstructionId) -> Option<ValueId> {
+        let instruction = self.dfg()[id].clone();
+        if !matches!(
+            instruction,
+            Instruction::Binary(_)
+                | Instruction::Cast(..)
+                | Instruction::Not(_)
+                | Instruction::Truncate { .. }
+        ) {
+            return None;
+        }
+
+        let mut operands = Vec::new();
+        instruction.for_each_value(|operand| operands.push(operand));
+
+        let mut constants = HashMap::new();
+        for operand in operands {
+            let constant = self.evaluate(operand)?;
+            constants.insert(operand, constant);
+        }
+
+        let instruction = instruction.map_values(|operand| constants[&operand]);
+        let block = self.function.entry_block();
+        match instruction.simplify(&mut self.function.dfg, block) {
+            SimplifyResult::SimplifiedTo(constant)
+                if self.dfg().get_numeric_constant(constant).is_some() =>
+            {
+                Some(constant)
+            }
+            _ => None,
+        }
+    }
+
+    /// Evaluates a block parameter to the constant passed for it by every predecessor of its
+    /// block, if they all pass the same constant.
+    fn evaluate_parameter(&mut self, block: BasicBlockId, position: usize) -> Option<ValueId> {
+        let predecessors = self.cfg.predecessors(block).collect::<Vec<_>>();
+        let mut constant = None;
+
+        for predecessor in predecessors {
+            let argument = match self.dfg()[predecessor].terminator() {
+                Some(TerminatorInstruction::Jmp { destination, arguments })
+                    if *destination == block =>
+                {
+                    arguments[position]
+                }
+                _ => return None,
+            };
+            let argument = self.evaluate(argument)?;
+            if constant.map_or(false, |constant| constant != argument) {
+                return None;
+            }
+            constant = Some(argument);
+        }
+        constant
+    }
+
+    fn dfg(&self) -> &DataFlowGraph {
+        &self.function.dfg
+    }
+}
+
+/// Returns the bounds of the given loop if its header compares one of its parameters against
+/// some end before continuing the loop, and it has a single pre-header which jumps to it.
+fn find_loop_bounds(
+    function: &Function,
+    cfg: &ControlFlowGraph,
+    loop_: &Loop,
+) -> Option<LoopBounds> {
+    let dfg = &function.dfg;
+
+    let mut pre_headers =
+        cfg.predecessors(loop_.header).filter(|predecessor| !loop_.blocks.contains(predecessor));
+    let pre_header = pre_headers.next()?;
+    if pre_headers.next().is_some() {
+        return None;
+    }
+    if !matches!(
+        dfg[pre_header].terminator(),
+        Some(TerminatorInstruction::Jmp { destination, .. }) if *destination == loop_.header
+    ) {
+        return None;
+    }
+
+    let condition = match dfg[loop_.header].terminator() {
+        Some(TerminatorInstruction::JmpIf { condition, .. }) => dfg.resolve(*condition),
+        _ => return None,
+    };
+    let end_comparison = match &dfg[condition] {
+        Value::Instruction { instruction, .. } => *instruction,
+        _ => return None,
+    };
+    let induction_variable = match &dfg[end_comparison] {
+        Instruction::Binary(Binary { lhs, operator: BinaryOp::Lt, .. }) => {
+            let lhs = dfg.resolve(*lhs);
+            dfg.block_parameters(loop_.header).iter().position(|parameter| *parameter == lhs)?
+        }
+        _ => return None,
+    };
+
+    Some(LoopBounds { pre_header, induction_variable, end_comparison })
+}
+
+#[cfg(test)]
+mod test {
+    use std::str::FromStr;
+
+    use crate::ssa_refactor::{ir::parser::assert_ssa_equals, ssa_gen::Ssa};
+
+    #[test]
+    fn evaluates_loop_bounds() {
+        // Both branches pass 4 for v1, so the loop is `for i in 1..8`
+        let src = "
+            acir fn main f0 {
+              b0(v0: u1):
+                jmpif v0 then: b1, else: b2
+              b1():
+                jmp b3(u32 4)
+              b2():
+                jmp b3(u32 4)
+              b3(v1: u32):
+                v2 = mul v1, u32 2
+                v3 = sub v1, u32 3
+                jmp b4(v3)
+              b4(v4: u32):
+                v5 = lt v4, v2
+                jmpif v5 then: b5, else: b6
+              b5():
+                v6 = add v4, u32 1
+                jmp b4(v6)
+              b6():
+                return
+            }
+        ";
+        let expected = "
+            acir fn main f0 {
+              b0(v0: u1):
+                jmpif v0 then: b1, else: b2
+              b1():
+                jmp b3(u32 4)
+              b2():
+                jmp b3(u32 4)
+              b3(v1: u32):
+                v2 = mul v1, u32 2
+                v3 = sub v1, u32 3
+                jmp b4(u32 1)
+              b4(v4: u32):
+                v5 = lt v4, u32 8
+                jmpif v5 then: b5, else: b6
+              b5():
+                v6 = add v4, u32 1
+                jmp b4(v6)
+              b6():
+                return
+            }
+        ";
+        let ssa = Ssa::from_str(src).unwrap().evaluate_loop_bounds();
+        assert_ssa_equals(&ssa, expected);
+    }
+
+    #[test]
+    fn leaves_unknown_loop_bounds() {
+        // The branches pass different values for v1
+        let src = "
+            acir fn main f0 {
+              b0(v0: u1):
+                jmpif v0 then: b1, else: b2
+              b1():
+                jmp b3(u32 4)
+              b2():
+                jmp b3(u32 5)
+              b3(v1: u32):
+                jmp b4(u32 0)
+              b4(v2: u32):
+                v3 = lt v2, v1
+                jmpif v3 then: b5, else: b6
+              b5():
+                v4 = add v2, u32 1
+                jmp b4(v4)
+              b6():
+                return
+            }
+        ";
+        let ssa = Ssa::from_str(src).unwrap().evaluate_loop_bounds();
+        assert_ssa_equals(&ssa, src);
+    }
+}
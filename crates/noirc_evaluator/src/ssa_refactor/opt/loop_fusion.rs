@@ -0,0 +1,374 @@
+//! Loop fusion merges adjacent loops over the same range into a single loop.
+//!
+//! Programs often contain several consecutive loops such as `for i in 0..N`, each over the same
+//! arrays. Each loop has its own header comparing its induction variable against the end, and its
+//! own increment of the induction variable, both of which are repeated for each iteration once
+//! the loop is unrolled, or executed on each iteration of a brillig loop. Fusing the loops runs
+//! the bodies of both loops on each iteration of a single loop instead.
+//!
+//! Two loops are fused if:
+//! - Each loop has a single back-edge, and a header which only checks that its induction
+//!   variable is less than the end before continuing into its body. Each back-edge increments
+//!   the induction variable by one.
+//! - The first loop exits to an empty block, which then jumps straight to the second loop.
+//! - Both loops start and end at the same values.
+//! - No value defined within either loop is used after it.
+//! - The loops are independent: neither loop calls a function with side-effects, and neither
+//!   loop stores to a reference which the other loop loads from or stores to.
+//!
+//! Fusing the loops makes the back-edge of the first loop continue into the body of the second,
+//! whose back-edge then jumps back to the header of the first. The induction variable of the
+//! second loop is replaced with that of the first, and the first loop then exits to the block
+//! after the second loop, whose header is no longer reachable.
+use std::collections::HashSet;
+
+use crate::ssa_refactor::{
+    ir::{
+        basic_block::BasicBlockId,
+        cfg::ControlFlowGraph,
+        dfg::DataFlowGraph,
+        function::Function,
+        instruction::{Binary, BinaryOp, Instruction, Intrinsic, TerminatorInstruction},
+        loops::{Loop, LoopForest},
+        value::{Value, ValueId},
+    },
+    ssa_gen::Ssa,
+};
+
+use super::unrolling::is_used_outside_of_loop;
+
+impl Ssa {
+    /// Fuses each pair of adjacent, independent loops over the same range.
+    pub(crate) fn fuse_loops(mut self) -> Ssa {
+        for function in self.functions.values_mut() {
+            fuse_function_loops(function);
+        }
+        self
+    }
+}
+
+/// A loop of the form `for i in start..end`, where the header only checks the loop condition.
+struct SimpleLoop {
+    header: BasicBlockId,
+    pre_header: BasicBlockId,
+    latch: BasicBlockId,
+    body: BasicBlockId,
+    exit: BasicBlockId,
+    induction_variable: ValueId,
+    start: ValueId,
+    end: ValueId,
+}
+
+/// The references a loop loads from and stores to
+#[derive(Default)]
+struct MemoryAccesses {
+    loads: HashSet<ValueId>,
+    stores: HashSet<ValueId>,
+
+    /// True if the loop accesses a reference which is not known to be a distinct allocation
+    has_unknown_accesses: bool,
+
+    /// True if the loop calls a function which may have side-effects
+    has_impure_calls: bool,
+}
+
+fn fuse_function_loops(function: &mut Function) {
+    // Fusing two loops changes the loops of the function, so they are found again after each
+    // fusion. Each fusion removes a loop, so this terminates.
+    loop {
+        let cfg = ControlFlowGraph::with_function(function);
+        let loops = LoopForest::with_function(function).into_loops();
+        let simple_loops: Vec<_> = loops
+            .iter()
+            .filter_map(|loop_| {
+                find_simple_loop(function, &cfg, loop_).map(|simple| (loop_, simple))
+            })
+            .collect();
+
+        let fusable = simple_loops.iter().find_map(|(first_loop, first)| {
+            simple_loops
+                .iter()
+                .find(|(second_loop, second)| {
+                    can_fuse(function, &cfg, (*first_loop, first), (*second_loop, second))
+                })
+                .map(|(_, second)| (first, second))
+        });
+
+        match fusable {
+            Some((first, second)) => fuse(&mut function.dfg, first, second),
+            None => return,
+        }
+    }
+}
+
+/// Returns the given loop if it is a simple loop over a range.
+fn find_simple_loop(
+    function: &Function,
+    cfg: &ControlFlowGraph,
+    loop_: &Loop,
+) -> Option<SimpleLoop> {
+    let dfg = &function.dfg;
+    let header = loop_.header;
+    let latch = loop_.single_latch()?;
+
+    let induction_variable = match dfg.block_parameters(header) {
+        [induction_variable] => *induction_variable,
+        _ => return None,
+    };
+
+    let (condition, body, exit) = match dfg[header].terminator() {
+        Some(TerminatorInstruction::JmpIf { condition, then_destination, else_destination }) => {
+            (dfg.resolve(*condition), *then_destination, *else_destination)
+        }
+        _ => return None,
+    };
+    if !loop_.blocks.contains(&body) || loop_.blocks.contains(&exit) {
+        return None;
+    }
+
+    let end = match dfg[header].instructions() {
+        [comparison] if dfg.instruction_results(*comparison) == [condition] => {
+            match &dfg[*comparison] {
+                Instruction::Binary(Binary { lhs, rhs, operator: BinaryOp::Lt })
+                    if dfg.resolve(*lhs) == induction_variable =>
+                {
+                    dfg.resolve(*rhs)
+                }
+                _ => return None,
+            }
+        }
+        _ => return None,
+    };
+
+    let mut pre_headers =
+        cfg.predecessors(header).filter(|predecessor| !loop_.blocks.contains(predecessor));
+    let pre_header = pre_headers.next()?;
+    if pre_headers.next().is_some() {
+        return None;
+    }
+    let start = match dfg[pre_header].terminator() {
+        Some(TerminatorInstruction::Jmp { destination, arguments }) if *destination == header => {
+            dfg.resolve(arguments[0])
+        }
+        _ => return None,
+    };
+
+    let next_value = match dfg[latch].terminator() {
+        Some(TerminatorInstruction::Jmp { arguments, .. }) => dfg.resolve(arguments[0]),
+        _ => return None,
+    };
+    if !is_increment_of(dfg, next_value, induction_variable) {
+        return None;
+    }
+
+    Some(SimpleLoop { header, pre_header, latch, body, exit, induction_variable, start, end })
+}
+
+/// Returns true if the given value is the given induction variable plus one.
+fn is_increment_of(dfg: &DataFlowGraph, value: ValueId, induction_variable: ValueId) -> bool {
+    match &dfg[value] {
+        Value::Instruction { instruction, .. } => match &dfg[*instruction] {
+            Instruction::Binary(Binary { lhs, rhs, operator: BinaryOp::Add }) => {
+                [(lhs, rhs), (rhs, lhs)].into_iter().any(|(lhs, rhs)| {
+                    dfg.resolve(*lhs) == induction_variable
+                        && dfg.get_numeric_constant(*rhs).map_or(false, |step| step.is_one())
+                })
+            }
+            _ => false,
+        },
+        _ => false,
+    }
+}
+
+/// Returns true if the second loop directly follows the first, and both are over the same range
+/// and independent of each other.
+fn can_fuse(
+    function: &Function,
+    cfg: &ControlFlowGraph,
+    (first_loop, first): (&Loop, &SimpleLoop),
+    (second_loop, second): (&Loop, &SimpleLoop),
+) -> bool {
+    let dfg = &function.dfg;
+    let is_adjacent = first.exit == second.pre_header
+        && dfg[first.exit].instructions().is_empty()
+        && cfg.predecessors(first.exit).len() == 1;
+
+    is_adjacent
+        && first.start == second.start
+        && first.end == second.end
+        && dfg.type_of_value(first.induction_variable)
+            == dfg.type_of_value(second.induction_variable)
+        && !is_used_outside_of_loop(function, first_loop)
+        && !is_used_outside_of_loop(function, second_loop)
+        && are_independent(&memory_accesses(dfg, first_loop), &memory_accesses(dfg, second_loop))
+}
+
+fn memory_accesses(dfg: &DataFlowGraph, loop_: &Loop) -> MemoryAccesses {
+    let mut accesses = MemoryAccesses::default();
+
+    for block in &loop_.blocks {
+        for instruction in dfg[*block].instructions() {
+            let (address, accessed) = match &dfg[*instruction] {
+                Instruction::Load { address } => (*address, &mut accesses.loads),
+                Instruction::Store { address, .. } => (*address, &mut accesses.stores),
+                Instruction::Call { func, .. } => {
+                    accesses.has_impure_calls |= !matches!(
+                        &dfg[*func],
+                        Value::Intrinsic(intrinsic) if *intrinsic != Intrinsic::Println
+                    );
+                    continue;
+                }
+                _ => continue,
+            };
+
+            let address = dfg.resolve(address);
+            accessed.insert(address);
+            accesses.has_unknown_accesses |= !is_allocation(dfg, address);
+        }
+    }
+    accesses
+}
+
+fn is_allocation(dfg: &DataFlowGraph, address: ValueId) -> bool {
+    match &dfg[address] {
+        Value::Instruction { instruction, .. } => {
+            matches!(&dfg[*instruction], Instruction::Allocate)
+        }
+        _ => false,
+    }
+}
+
+/// Returns true if the iterations of two loops may be interleaved without changing their results.
+fn are_independent(first: &MemoryAccesses, second: &MemoryAccesses) -> bool {
+    if first.has_impure_calls || second.has_impure_calls {
+        return false;
+    }
+
+    let accesses_memory =
+        |accesses: &MemoryAccesses| !accesses.loads.is_empty() || !accesses.stores.is_empty();
+    if (first.has_unknown_accesses && accesses_memory(second))
+        || (second.has_unknown_accesses && accesses_memory(first))
+    {
+        return false;
+    }
+
+    first.stores.is_disjoint(&second.loads)
+        && first.stores.is_disjoint(&second.stores)
+        && second.stores.is_disjoint(&first.loads)
+}
+
+/// Fuses the second loop into the first.
+fn fuse(dfg: &mut DataFlowGraph, first: &SimpleLoop, second: &SimpleLoop) {
+    // Each iteration of the first loop continues into the body of the second
+    let jmp = TerminatorInstruction::Jmp { destination: second.body, arguments: Vec::new() };
+    dfg.set_block_terminator(first.latch, jmp);
+
+    // The second loop increments the shared induction variable before starting the next iteration
+    let next_value = match dfg[second.latch].unwrap_terminator() {
+        TerminatorInstruction::Jmp { arguments, .. } => arguments[0],
+        other => unreachable!("Expected the latch of a loop to end in a jmp, found {other:?}"),
+    };
+    let jmp = TerminatorInstruction::Jmp { destination: first.header, arguments: vec![next_value] };
+    dfg.set_block_terminator(second.latch, jmp);
+    dfg.set_value_from_id(second.induction_variable, first.induction_variable);
+
+    // Once the fused loop is done, it continues after the second loop
+    let jmp = TerminatorInstruction::Jmp { destination: second.exit, arguments: Vec::new() };
+    dfg.set_block_terminator(first.exit, jmp);
+}
+
+#[cfg(test)]
+mod test {
+    use std::str::FromStr;
+
+    use crate::ssa_refactor::{ir::parser::assert_ssa_equals, ssa_gen::Ssa};
+
+    #[test]
+    fn fuses_adjacent_loops() {
+        let src = "
+            acir fn main f0 {
+              b0(v0: [Field; 3], v1: [Field; 3]):
+                jmp b1(u32 0)
+              b1(v2: u32):
+                v3 = lt v2, u32 3
+                jmpif v3 then: b2, else: b3
+              b2():
+                v4 = array_get v0, index v2
+                constrain v4
+                v5 = add v2, u32 1
+                jmp b1(v5)
+              b3():
+                jmp b4(u32 0)
+              b4(v6: u32):
+                v7 = lt v6, u32 3
+                jmpif v7 then: b5, else: b6
+              b5():
+                v8 = array_get v1, index v6
+                constrain v8
+                v9 = add v6, u32 1
+                jmp b4(v9)
+              b6():
+                return
+            }
+        ";
+        let expected = "
+            acir fn main f0 {
+              b0(v0: [Field; 3], v1: [Field; 3]):
+                jmp b1(u32 0)
+              b1(v2: u32):
+                v3 = lt v2, u32 3
+                jmpif v3 then: b2, else: b3
+              b2():
+                v4 = array_get v0, index v2
+                constrain v4
+                v5 = add v2, u32 1
+                jmp b4()
+              b4():
+                v8 = array_get v1, index v2
+                constrain v8
+                v9 = add v2, u32 1
+                jmp b1(v9)
+              b3():
+                jmp b6()
+              b6():
+                return
+            }
+        ";
+        let ssa = Ssa::from_str(src).unwrap().fuse_loops();
+        assert_ssa_equals(&ssa, expected);
+    }
+
+    #[test]
+    fn does_not_fuse_dependent_loops() {
+        // The second loop loads from the reference the first loop stores to
+        let src = "
+            acir fn main f0 {
+              b0(v0: Field):
+                v1 = allocate
+                store Field 0 at v1
+                jmp b1(u32 0)
+              b1(v2: u32):
+                v3 = lt v2, u32 3
+                jmpif v3 then: b2, else: b3
+              b2():
+                store v0 at v1
+                v4 = add v2, u32 1
+                jmp b1(v4)
+              b3():
+                jmp b4(u32 0)
+              b4(v5: u32):
+                v6 = lt v5, u32 3
+                jmpif v6 then: b5, else: b6
+              b5():
+                v7 = load v1
+                constrain v7
+                v8 = add v5, u32 1
+                jmp b4(v8)
+              b6():
+                return
+            }
+        ";
+        let ssa = Ssa::from_str(src).unwrap().fuse_loops();
+        assert_ssa_equals(&ssa, src);
+    }
+}
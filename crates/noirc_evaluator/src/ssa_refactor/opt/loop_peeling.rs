@@ -0,0 +1,387 @@
+//! Loop peeling copies the first iteration of a loop in front of the loop, for loops which
+//! handle their first iteration specially.
+//!
+//! A loop over `i` often branches on `i == start`, e.g. to initialize a value during its first
+//! iteration. Otherwise each copy of the loop body made by unrolling, or each iteration of an
+//! unconstrained loop, keeps both sides of the branch.
+//!
+//! A loop is peeled if its header is entered with `i` equal to the constant `start`, and `i` is
+//! incremented by one each iteration for as long as it is less than some bound. The first
+//! iteration is copied with `i` replaced by `start`, so that `i == start` is known to be true
+//! within the copy, whose branches are replaced by jumps to the destinations taken. Afterward the
+//! loop is only entered from the copy, with `i` greater than `start`, so each `i == start`
+//! comparison within the loop is replaced with false. Later passes remove the branches which are
+//! no longer taken.
+//!
+//! Loops are only peeled if they have a single back-edge and no value defined within them is used
+//! after them. Each block the loop exits to must have no other predecessors, since the copy of the
+//! first iteration exits to these blocks as well.
+use std::collections::HashMap;
+
+use acvm::FieldElement;
+
+use crate::ssa_refactor::{
+    ir::{
+        basic_block::BasicBlockId,
+        cfg::ControlFlowGraph,
+        dfg::DataFlowGraph,
+        function::Function,
+        function_inserter::FunctionInserter,
+        instruction::{BinaryOp, Instruction, InstructionId, TerminatorInstruction},
+        loops::{Loop, LoopForest},
+        post_order::PostOrder,
+        types::Type,
+        value::{Value, ValueId},
+    },
+    ssa_gen::Ssa,
+};
+
+use super::unrolling::is_used_outside_of_loop;
+
+/// The maximum number of instructions a loop may contain to be peeled
+const MAX_PEELED_INSTRUCTIONS: usize = 100;
+
+/// A loop whose first iteration can be peeled
+struct PeelableLoop {
+    /// The only block outside of the loop which jumps to its header
+    pre_header: BasicBlockId,
+
+    /// Each comparison of the loop's induction variable to its start, along with its block
+    first_iteration_checks: Vec<(BasicBlockId, InstructionId)>,
+}
+
+impl Ssa {
+    /// Peels the first iteration of each loop which compares its induction variable to its start.
+    pub(crate) fn peel_loops(mut self) -> Ssa {
+        for function in self.functions.values_mut() {
+            peel_function_loops(function);
+        }
+        self
+    }
+}
+
+fn peel_function_loops(function: &mut Function) {
+    // Peeling a loop adds blocks around it, so the loops are found again after each loop is
+    // peeled. A peeled loop no longer compares its induction variable to its start, so each loop
+    // is peeled at most once.
+    loop {
+        let cfg = ControlFlowGraph::with_function(function);
+        let loops = LoopForest::with_function(function).into_loops();
+
+        let peelable = loops.into_iter().find_map(|loop_| {
+            find_peelable_loop(function, &cfg, &loop_).map(|peelable| (loop_, peelable))
+        });
+
+        match peelable {
+            Some((loop_, peelable)) => peel_first_iteration(function, &loop_, peelable),
+            None => return,
+        }
+    }
+}
+
+/// Returns the pre-header of the given loop along with each comparison of its induction variable
+/// to its start, if the loop can be peeled.
+fn find_peelable_loop(
+    function: &Function,
+    cfg: &ControlFlowGraph,
+    loop_: &Loop,
+) -> Option<PeelableLoop> {
+    let dfg = &function.dfg;
+    let latch = loop_.single_latch()?;
+
+    let mut pre_headers =
+        cfg.predecessors(loop_.header).filter(|predecessor| !loop_.blocks.contains(predecessor));
+    let pre_header = pre_headers.next()?;
+    if pre_headers.next().is_some() {
+        return None;
+    }
+
+    let start_arguments = match dfg[pre_header].terminator() {
+        Some(TerminatorInstruction::Jmp { destination, arguments })
+            if *destination == loop_.header =>
+        {
+            arguments
+        }
+        _ => return None,
+    };
+
+    let index = find_induction_variable(dfg, loop_, latch)?;
+    let induction_variable = dfg.block_parameters(loop_.header)[index];
+    // Constants are interned, so comparing against the start only requires comparing ids
+    let start = dfg.resolve(start_arguments[index]);
+    dfg.get_numeric_constant(start)?;
+
+    let mut blocks: Vec<_> = loop_.blocks.iter().copied().collect();
+    blocks.sort();
+
+    let mut first_iteration_checks = Vec::new();
+    for block in &blocks {
+        for instruction in dfg[*block].instructions() {
+            let compares_to_start = match binary_operands(dfg, &dfg[*instruction], BinaryOp::Eq) {
+                Some((lhs, rhs)) => [(lhs, rhs), (rhs, lhs)]
+                    .into_iter()
+                    .any(|(lhs, rhs)| lhs == induction_variable && dfg.resolve(rhs) == start),
+                None => false,
+            };
+            if compares_to_start {
+                first_iteration_checks.push((*block, *instruction));
+            }
+        }
+    }
+
+    let instructions: usize = blocks.iter().map(|block| dfg[*block].instructions().len()).sum();
+    let exits_have_other_predecessors = blocks.iter().any(|block| {
+        cfg.successors(*block)
+            .any(|exit| !loop_.blocks.contains(&exit) && cfg.predecessors(exit).len() > 1)
+    });
+
+    if first_iteration_checks.is_empty()
+        || instructions > MAX_PEELED_INSTRUCTIONS
+        || exits_have_other_predecessors
+        || is_used_outside_of_loop(function, loop_)
+    {
+        return None;
+    }
+    Some(PeelableLoop { pre_header, first_iteration_checks })
+}
+
+/// Returns the index of the loop header's parameter which is incremented by one on each
+/// iteration, and which the header checks to be less than some bound before continuing the loop.
+fn find_induction_variable(
+    dfg: &DataFlowGraph,
+    loop_: &Loop,
+    latch: BasicBlockId,
+) -> Option<usize> {
+    let (condition, then_destination) = match dfg[loop_.header].terminator() {
+        Some(TerminatorInstruction::JmpIf { condition, then_destination, .. }) => {
+            (*condition, *then_destination)
+        }
+        _ => return None,
+    };
+    if !loop_.blocks.contains(&then_destination) {
+        return None;
+    }
+
+    let (induction_variable, _) = match &dfg[dfg.resolve(condition)] {
+        Value::Instruction { instruction, .. } => {
+            binary_operands(dfg, &dfg[*instruction], BinaryOp::Lt)?
+        }
+        _ => return None,
+    };
+    let index = dfg
+        .block_parameters(loop_.header)
+        .iter()
+        .position(|parameter| *parameter == induction_variable)?;
+
+    let next_value = match dfg[latch].terminator() {
+        Some(TerminatorInstruction::Jmp { arguments, .. }) => arguments[index],
+        _ => return None,
+    };
+    let increments_by_one = match &dfg[dfg.resolve(next_value)] {
+        Value::Instruction { instruction, .. } => {
+            match binary_operands(dfg, &dfg[*instruction], BinaryOp::Add) {
+                Some((lhs, rhs)) => [(lhs, rhs), (rhs, lhs)].into_iter().any(|(lhs, rhs)| {
+                    lhs == induction_variable
+                        && dfg.get_numeric_constant(rhs).map_or(false, |step| step.is_one())
+                }),
+                None => false,
+            }
+        }
+        _ => false,
+    };
+    increments_by_one.then_some(index)
+}
+
+/// Returns the resolved operands of the given instruction if it is a binary instruction with the
+/// given operator
+fn binary_operands(
+    dfg: &DataFlowGraph,
+    instruction: &Instruction,
+    operator: BinaryOp,
+) -> Option<(ValueId, ValueId)> {
+    match instruction {
+        Instruction::Binary(binary) if binary.operator == operator => {
+            Some((dfg.resolve(binary.lhs), dfg.resolve(binary.rhs)))
+        }
+        _ => None,
+    }
+}
+
+/// Copies the first iteration of the loop between its pre-header and its header, then replaces
+/// each comparison of the induction variable to its start within the loop with false.
+fn peel_first_iteration(function: &mut Function, loop_: &Loop, peelable: PeelableLoop) {
+    let PeelableLoop { pre_header, first_iteration_checks } = peelable;
+
+    // Copy blocks in reverse post-order so that each value is copied before it is used
+    let post_order = PostOrder::with_function(function);
+    let blocks: Vec<_> = post_order
+        .as_slice()
+        .iter()
+        .rev()
+        .filter(|block| loop_.blocks.contains(*block))
+        .copied()
+        .collect();
+
+    let start_arguments = function.dfg[pre_header].terminator_arguments().to_vec();
+    let mut inserter = FunctionInserter::new(function);
+    let mut copies = HashMap::new();
+
+    for block in &blocks {
+        let copy = if *block == loop_.header {
+            // The copy of the header is only entered from the pre-header, so its parameters are
+            // replaced with the pre-header's arguments
+            let parameters = inserter.function.dfg.block_parameters(*block).to_vec();
+            for (parameter, argument) in parameters.into_iter().zip(&start_arguments) {
+                inserter.map_value(parameter, *argument);
+            }
+            inserter.function.dfg.make_block()
+        } else {
+            let copy = inserter.function.dfg.make_block_with_parameters_from_block(*block);
+            inserter.remember_block_params_from_block(*block, copy);
+            copy
+        };
+        copies.insert(*block, copy);
+    }
+
+    for block in &blocks {
+        let copy = copies[block];
+        for instruction in inserter.function.dfg[*block].instructions().to_vec() {
+            inserter.push_instruction(instruction, copy);
+        }
+
+        let terminator = inserter.function.dfg[*block]
+            .unwrap_terminator()
+            .clone()
+            .map_values(|value| inserter.resolve(value));
+
+        let mut terminator = match terminator {
+            TerminatorInstruction::JmpIf { condition, then_destination, else_destination } => {
+                match inserter.function.dfg.get_numeric_constant(condition) {
+                    Some(constant) => {
+                        let destination =
+                            if constant.is_zero() { else_destination } else { then_destination };
+                        TerminatorInstruction::Jmp { destination, arguments: Vec::new() }
+                    }
+                    None => TerminatorInstruction::JmpIf {
+                        condition,
+                        then_destination,
+                        else_destination,
+                    },
+                }
+            }
+            other => other,
+        };
+
+        // The back-edge of the copy enters the original loop
+        terminator.mutate_blocks(|destination| {
+            if destination == loop_.header {
+                destination
+            } else {
+                copies.get(&destination).copied().unwrap_or(destination)
+            }
+        });
+        inserter.function.dfg.set_block_terminator(copy, terminator);
+    }
+
+    let destination = copies[&loop_.header];
+    let jmp = TerminatorInstruction::Jmp { destination, arguments: Vec::new() };
+    function.dfg.set_block_terminator(pre_header, jmp);
+
+    // The induction variable is greater than its start whenever the loop is entered from the copy
+    let false_value = function.dfg.make_constant(FieldElement::zero(), Type::bool());
+    for (block, instruction) in first_iteration_checks {
+        let result = function.dfg.instruction_results(instruction)[0];
+        function.dfg.set_value_from_id(result, false_value);
+        function.dfg[block].remove_instruction(instruction);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::str::FromStr;
+
+    use crate::ssa_refactor::{ir::parser::assert_ssa_equals, ssa_gen::Ssa};
+
+    #[test]
+    fn peels_loops_with_first_iteration_checks() {
+        let src = "
+            brillig fn main f0 {
+              b0(v0: Field):
+                jmp b1(u32 0)
+              b1(v1: u32):
+                v2 = lt v1, u32 4
+                jmpif v2 then: b2, else: b6
+              b2():
+                v3 = eq v1, u32 0
+                jmpif v3 then: b3, else: b4
+              b3():
+                v4 = eq v0, Field 1
+                constrain v4
+                jmp b5()
+              b4():
+                jmp b5()
+              b5():
+                v5 = add v1, u32 1
+                jmp b1(v5)
+              b6():
+                return
+            }
+        ";
+        let expected = "
+            brillig fn main f0 {
+              b0(v0: Field):
+                jmp b7()
+              b7():
+                jmp b8()
+              b8():
+                jmp b9()
+              b9():
+                v6 = eq v0, Field 1
+                constrain v6
+                jmp b11()
+              b11():
+                jmp b1(u32 1)
+              b1(v1: u32):
+                v2 = lt v1, u32 4
+                jmpif v2 then: b2, else: b6
+              b2():
+                jmpif u1 0 then: b3, else: b4
+              b3():
+                v4 = eq v0, Field 1
+                constrain v4
+                jmp b5()
+              b4():
+                jmp b5()
+              b5():
+                v5 = add v1, u32 1
+                jmp b1(v5)
+              b6():
+                return
+            }
+        ";
+        let ssa = Ssa::from_str(src).unwrap().peel_loops();
+        assert_ssa_equals(&ssa, expected);
+    }
+
+    #[test]
+    fn leaves_loops_without_first_iteration_checks() {
+        let src = "
+            brillig fn main f0 {
+              b0(v0: Field):
+                jmp b1(u32 0)
+              b1(v1: u32):
+                v2 = lt v1, u32 4
+                jmpif v2 then: b2, else: b3
+              b2():
+                v3 = eq v1, u32 1
+                constrain v3
+                v4 = add v1, u32 1
+                jmp b1(v4)
+              b3():
+                return
+            }
+        ";
+        let ssa = Ssa::from_str(src).unwrap().peel_loops();
+        assert_ssa_equals(&ssa, src);
+    }
+}
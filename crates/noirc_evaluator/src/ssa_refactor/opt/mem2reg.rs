@@ -1,6 +1,21 @@
 //! mem2reg implements a pass for promoting values stored in memory to values in registers where
 //! possible. This is particularly important for converting our memory-based representation of
 //! mutable variables into values that are easier to manipulate.
+//!
+//! Each address is resolved to the reference it aliases where possible. A reference loaded from
+//! an allocation whose last stored value is known is the stored reference itself, so references
+//! stored into other references, such as those created when a function taking a mutable
+//! reference is inlined, are tracked through their loads.
+//!
+//! An allocation is protected, keeping its stores, if its value may be observed elsewhere: if it
+//! is loaded without a known value, passed to a call, returned, passed to another block, or
+//! stored through an unknown address. Any reference stored within a protected allocation is
+//! protected as well, since it may be loaded and used from there.
+//!
+//! Stores to protected allocations are also removed when they are dead: when a later store in
+//! the same block overwrites the allocation before anything may read it. An allocation may be
+//! read by a load whose value is unknown, by a load through an unknown address, or by a call
+//! given any reference.
 use std::collections::{BTreeMap, HashMap, HashSet};
 
 use iter_extended::vecmap;
@@ -9,7 +24,8 @@ use crate::ssa_refactor::{
     ir::{
         basic_block::BasicBlockId,
         dfg::DataFlowGraph,
-        instruction::{Instruction, InstructionId, TerminatorInstruction},
+        instruction::{Instruction, InstructionId},
+        types::Type,
         value::{Value, ValueId},
     },
     ssa_gen::Ssa,
@@ -18,17 +34,26 @@ use crate::ssa_refactor::{
 impl Ssa {
     /// Attempts to remove any load instructions that recover values that are already available in
     /// scope, and attempts to remove store that are subsequently redundant, as long as they are
-    /// not stores on memory that will be passed into a function call or returned.
+    /// not stores on memory that will be passed into a function call or returned. Stores which
+    /// are overwritten before they may be read are removed regardless.
     pub(crate) fn mem2reg(mut self) -> Ssa {
         for function in self.functions.values_mut() {
             let mut all_protected_allocations = HashSet::new();
+            let mut all_stored_references: HashMap<AllocId, HashSet<ValueId>> = HashMap::new();
             let contexts = vecmap(function.reachable_blocks(), |block| {
                 let mut context = PerBlockContext::new(block);
                 let allocations_protected_by_block =
                     context.analyze_allocations_and_eliminate_known_loads(&mut function.dfg);
                 all_protected_allocations.extend(allocations_protected_by_block.into_iter());
+                for (allocation, references) in &context.stored_references {
+                    all_stored_references.entry(*allocation).or_default().extend(references);
+                }
                 context
             });
+
+            let all_protected_allocations =
+                protect_stored_references(all_protected_allocations, &all_stored_references);
+
             // Now that we have a comprehensive list of used allocations across all the
             // function's blocks, it is safe to remove any stores that do not touch such
             // allocations.
@@ -41,10 +66,50 @@ impl Ssa {
     }
 }
 
+/// Returns true if a value of the given type is or contains a reference
+fn contains_reference(typ: &Type) -> bool {
+    match typ {
+        Type::Reference => true,
+        Type::Array(element_types, _) | Type::Slice(element_types) => {
+            element_types.iter().any(contains_reference)
+        }
+        Type::Numeric(_) | Type::Function => false,
+    }
+}
+
+/// Returns the given protected allocations along with every reference stored within them,
+/// and every reference stored within those references, and so on.
+fn protect_stored_references(
+    mut protected_allocations: HashSet<AllocId>,
+    stored_references: &HashMap<AllocId, HashSet<ValueId>>,
+) -> HashSet<AllocId> {
+    let mut queue: Vec<_> = protected_allocations.iter().copied().collect();
+    while let Some(allocation) = queue.pop() {
+        for reference in stored_references.get(&allocation).into_iter().flatten() {
+            if protected_allocations.insert(*reference) {
+                queue.push(*reference);
+            }
+        }
+    }
+    protected_allocations
+}
+
 struct PerBlockContext {
     block_id: BasicBlockId,
     last_stores: BTreeMap<AllocId, ValueId>,
     store_ids: Vec<InstructionId>,
+
+    /// Maps each load result whose value is known to that value.
+    aliases: HashMap<ValueId, ValueId>,
+
+    /// The references stored within each allocation in this block
+    stored_references: HashMap<AllocId, HashSet<ValueId>>,
+
+    /// The last store to each allocation in this block which nothing may have read yet
+    unread_stores: HashMap<AllocId, InstructionId>,
+
+    /// Stores which are overwritten by a later store before anything may read them
+    dead_stores: HashSet<InstructionId>,
 }
 
 /// An AllocId is the ValueId returned from an allocate instruction. E.g. v0 in v0 = allocate.
@@ -54,7 +119,15 @@ type AllocId = ValueId;
 
 impl PerBlockContext {
     fn new(block_id: BasicBlockId) -> Self {
-        PerBlockContext { block_id, last_stores: BTreeMap::new(), store_ids: Vec::new() }
+        PerBlockContext {
+            block_id,
+            last_stores: BTreeMap::new(),
+            store_ids: Vec::new(),
+            aliases: HashMap::new(),
+            stored_references: HashMap::new(),
+            unread_stores: HashMap::new(),
+            dead_stores: HashSet::new(),
+        }
     }
 
     // Attempts to remove load instructions for which the result is already known from previous
@@ -69,23 +142,33 @@ impl PerBlockContext {
         // Maps Load instruction id -> value to replace the result of the load with
         let mut loads_to_substitute = HashMap::new();
 
-        // Maps Load result id -> value to replace the result of the load with
-        let mut load_values_to_substitute = HashMap::new();
-
         for instruction_id in block.instructions() {
             match &dfg[*instruction_id] {
-                Instruction::Store { mut address, value } => {
-                    if let Some(value) = load_values_to_substitute.get(&address) {
-                        address = *value;
+                Instruction::Store { address, value } => {
+                    let address = self.resolve(*address, dfg);
+                    let value = self.resolve(*value, dfg);
+
+                    if !Self::value_is_from_allocation(address, dfg) {
+                        // The address may alias any other reference, and the stored value may be
+                        // loaded from anywhere.
+                        self.last_stores.clear();
+                        protected_allocations.extend(Self::references_in(value, dfg));
+                    } else {
+                        let references = Self::references_in(value, dfg);
+                        self.stored_references.entry(address).or_default().extend(references);
+
+                        if let Some(overwritten) =
+                            self.unread_stores.insert(address, *instruction_id)
+                        {
+                            self.dead_stores.insert(overwritten);
+                        }
                     }
 
-                    self.last_stores.insert(address, *value);
+                    self.last_stores.insert(address, value);
                     self.store_ids.push(*instruction_id);
                 }
-                Instruction::Load { mut address } => {
-                    if let Some(value) = load_values_to_substitute.get(&address) {
-                        address = *value;
-                    }
+                Instruction::Load { address } => {
+                    let address = self.resolve(*address, dfg);
 
                     if let Some(last_value) = self.last_stores.get(&address) {
                         let result_value = *dfg
@@ -94,37 +177,54 @@ impl PerBlockContext {
                             .expect("ICE: Load instructions should have single result");
 
                         loads_to_substitute.insert(*instruction_id, *last_value);
-                        load_values_to_substitute.insert(result_value, *last_value);
+                        self.aliases.insert(result_value, *last_value);
                     } else {
                         protected_allocations.insert(address);
+
+                        if Self::value_is_from_allocation(address, dfg) {
+                            self.unread_stores.remove(&address);
+                        } else {
+                            // The address may alias any other reference
+                            self.unread_stores.clear();
+                        }
                     }
                 }
                 Instruction::Call { arguments, .. } => {
+                    let mut passes_references = false;
                     for arg in arguments {
-                        if Self::value_is_from_allocation(*arg, dfg) {
-                            protected_allocations.insert(*arg);
-                        }
+                        let arg = self.resolve(*arg, dfg);
+                        protected_allocations.extend(Self::references_in(arg, dfg));
+                        passes_references |= contains_reference(&dfg.type_of_value(arg));
+                    }
+
+                    // A call given any reference may read or modify any allocation, as
+                    // references reachable from it may have been stored in other blocks.
+                    if passes_references {
+                        self.last_stores.clear();
+                        self.unread_stores.clear();
                     }
                 }
-                _ => {
-                    // Nothing to do
+                instruction => {
+                    // Any reference used by another instruction, such as one stored in an array,
+                    // may be loaded from elsewhere.
+                    instruction.for_each_value(|value| {
+                        let value = self.resolve(value, dfg);
+                        protected_allocations.extend(Self::references_in(value, dfg));
+                    });
                 }
             }
         }
 
-        // Identify any arrays that are returned from this function
-        if let TerminatorInstruction::Return { return_values } = block.unwrap_terminator() {
-            for value in return_values {
-                if Self::value_is_from_allocation(*value, dfg) {
-                    protected_allocations.insert(*value);
-                }
-            }
-        }
+        // Identify any references that are returned from this function or passed to another block
+        block.unwrap_terminator().for_each_value(|value| {
+            let value = self.resolve(value, dfg);
+            protected_allocations.extend(Self::references_in(value, dfg));
+        });
 
         // Substitute load result values
-        for (result_value, new_value) in load_values_to_substitute {
-            let result_value = dfg.resolve(result_value);
-            dfg.set_value_from_id(result_value, new_value);
+        for (result_value, new_value) in &self.aliases {
+            let result_value = dfg.resolve(*result_value);
+            dfg.set_value_from_id(result_value, *new_value);
         }
 
         // Delete load instructions
@@ -137,6 +237,13 @@ impl PerBlockContext {
         protected_allocations
     }
 
+    /// Resolves the given value, replacing the result of a load whose value is known with that
+    /// value.
+    fn resolve(&self, value: ValueId, dfg: &DataFlowGraph) -> ValueId {
+        let value = dfg.resolve(value);
+        self.aliases.get(&value).copied().unwrap_or(value)
+    }
+
     /// Checks whether the given value id refers to an allocation.
     fn value_is_from_allocation(value: ValueId, dfg: &DataFlowGraph) -> bool {
         match &dfg[value] {
@@ -147,6 +254,18 @@ impl PerBlockContext {
         }
     }
 
+    /// Returns the given value if it is a reference, or each reference within it if it is a
+    /// constant array.
+    fn references_in(value: ValueId, dfg: &DataFlowGraph) -> Vec<ValueId> {
+        match &dfg[value] {
+            Value::Array { array, .. } => {
+                array.iter().flat_map(|element| Self::references_in(*element, dfg)).collect()
+            }
+            _ if dfg.type_of_value(value) == Type::Reference => vec![value],
+            _ => Vec::new(),
+        }
+    }
+
     /// Removes all store instructions identified during analysis that aren't present in the
     /// provided `protected_allocations` `HashSet`.
     fn remove_unused_stores(
@@ -159,11 +278,15 @@ impl PerBlockContext {
 
         for instruction_id in &self.store_ids {
             let address = match &dfg[*instruction_id] {
-                Instruction::Store { address, .. } => *address,
+                Instruction::Store { address, .. } => dfg.resolve(*address),
                 _ => unreachable!("store_ids should contain only store instructions"),
             };
 
-            if !protected_allocations.contains(&address) {
+            // Stores to addresses which are not known allocations may modify any reference
+            let is_unused = Self::value_is_from_allocation(address, dfg)
+                && !protected_allocations.contains(&address);
+
+            if is_unused || self.dead_stores.contains(instruction_id) {
                 stores_to_remove.insert(*instruction_id);
             }
         }
@@ -177,7 +300,7 @@ impl PerBlockContext {
 
 #[cfg(test)]
 mod tests {
-    use std::rc::Rc;
+    use std::{rc::Rc, str::FromStr};
 
     use acvm::FieldElement;
     use im::vector;
@@ -189,9 +312,11 @@ mod tests {
             function::RuntimeType,
             instruction::{Instruction, Intrinsic, TerminatorInstruction},
             map::Id,
+            parser::assert_ssa_equals,
             types::Type,
         },
         ssa_builder::FunctionBuilder,
+        ssa_gen::Ssa,
     };
 
     #[test]
@@ -394,4 +519,114 @@ mod tests {
             _ => unreachable!(),
         };
     }
+
+    #[test]
+    fn tracks_references_stored_in_references() {
+        let src = "
+            acir fn main f0 {
+              b0(v0: Field):
+                v1 = allocate
+                store Field 0 at v1
+                v2 = allocate
+                store v1 at v2
+                v3 = load v2 -> reference
+                v4 = load v3 -> Field
+                v5 = add v4, v0
+                store v5 at v3
+                v6 = load v1 -> Field
+                return v6
+            }
+        ";
+        let expected = "
+            acir fn main f0 {
+              b0(v0: Field):
+                v1 = allocate
+                v2 = allocate
+                v5 = add Field 0, v0
+                return v5
+            }
+        ";
+        let ssa = Ssa::from_str(src).unwrap().mem2reg();
+        assert_ssa_equals(&ssa, expected);
+    }
+
+    #[test]
+    fn protects_references_reachable_from_call_arguments() {
+        // The call may store to v0 through v1, so the value of v0 is unknown afterward
+        let src = "
+            acir fn main f0 {
+              b0():
+                v0 = allocate
+                store Field 1 at v0
+                v1 = allocate
+                store v0 at v1
+                call f1(v1)
+                v2 = load v0 -> Field
+                return v2
+            }
+            brillig fn foo f1 {
+              b0(v0: reference):
+                v1 = load v0 -> reference
+                store Field 2 at v1
+                return
+            }
+        ";
+        let ssa = Ssa::from_str(src).unwrap().mem2reg();
+        assert_ssa_equals(&ssa, src);
+    }
+
+    #[test]
+    fn removes_overwritten_stores() {
+        let src = "
+            acir fn main f0 {
+              b0(v0: Field):
+                v1 = allocate
+                store Field 1 at v1
+                store v0 at v1
+                call f1(v1)
+                store Field 2 at v1
+                v2 = load v1 -> Field
+                store Field 3 at v1
+                return v2, v1
+            }
+            brillig fn foo f1 {
+              b0(v0: reference):
+                v1 = load v0 -> Field
+                return
+            }
+        ";
+        let expected = "
+            acir fn main f0 {
+              b0(v0: Field):
+                v1 = allocate
+                store v0 at v1
+                call f1(v1)
+                store Field 3 at v1
+                return Field 2, v1
+            }
+            brillig fn foo f1 {
+              b0(v0: reference):
+                v1 = load v0 -> Field
+                return
+            }
+        ";
+        let ssa = Ssa::from_str(src).unwrap().mem2reg();
+        assert_ssa_equals(&ssa, expected);
+    }
+
+    #[test]
+    fn keeps_stores_which_may_be_read_through_other_references() {
+        let src = "
+            acir fn main f0 {
+              b0(v0: reference):
+                v1 = allocate
+                store Field 1 at v1
+                v2 = load v0 -> Field
+                store Field 2 at v1
+                return v1, v2
+            }
+        ";
+        let ssa = Ssa::from_str(src).unwrap().mem2reg();
+        assert_ssa_equals(&ssa, src);
+    }
 }
@@ -3,11 +3,37 @@
 //! Each pass is generally expected to mutate the SSA IR into a gradually
 //! simpler form until the IR only has a single function remaining with 1 block within it.
 //! Generally, these passes are also expected to minimize the final amount of instructions.
+mod adce;
+mod bounds_check_elimination;
+mod cast_elimination;
 mod constant_folding;
+mod constraint_deduplication;
+mod copy_propagation;
+mod critical_edges;
+mod deduplication;
 mod defunctionalize;
 mod die;
+mod enable_side_effects;
 mod flatten_cfg;
+mod gvn;
+mod induction_variables;
 mod inlining;
+mod integer_arithmetic;
+mod jump_threading;
+mod known_bits;
+mod licm;
+mod loop_bounds;
+mod loop_fusion;
+mod loop_peeling;
 mod mem2reg;
+mod normalize_ids;
+mod peephole;
+mod rematerialization;
+mod sccp;
 mod simplify_cfg;
+mod specialization;
+mod sroa;
+mod strength_reduction;
+mod tail_recursion;
+mod unreachable_functions;
 mod unrolling;
@@ -0,0 +1,210 @@
+//! ID normalization renumbers the values, blocks, and functions of a program so that equivalent
+//! programs are printed identically, regardless of the ids left behind by earlier passes.
+//!
+//! Passes create and abandon values and blocks freely, so the ids of the same program drift from
+//! pass to pass, which makes printed diffs of the SSA hard to read. Within each function, blocks
+//! are renumbered in reverse post-order, and values in the order they are defined while visiting
+//! each block in that order. Constants and other non-instruction values are numbered where they
+//! are first used. Functions are renumbered consecutively in the order of their existing ids.
+//!
+//! Unreachable blocks, and any value which is no longer used, are dropped. Instructions are copied
+//! exactly as they are and are never simplified.
+use std::collections::HashMap;
+
+use iter_extended::vecmap;
+
+use crate::ssa_refactor::{
+    ir::{
+        basic_block::BasicBlockId,
+        function::{Function, FunctionId},
+        map::AtomicCounter,
+        post_order::PostOrder,
+        value::{Value, ValueId},
+    },
+    ssa_gen::Ssa,
+};
+
+impl Ssa {
+    /// Returns a copy of this program with its values, blocks, and functions renumbered.
+    ///
+    /// This is run on each program before it is printed, so it does not take ownership of the
+    /// program being normalized.
+    pub(crate) fn normalize_ids(&self) -> Ssa {
+        let counter = AtomicCounter::default();
+        let function_ids: HashMap<FunctionId, FunctionId> =
+            self.functions.keys().map(|id| (*id, counter.next())).collect();
+
+        let mut functions = vecmap(self.functions.values(), |function| {
+            IdNormalizer::new(function, &function_ids).normalize()
+        });
+
+        // Ssa::new expects the main function to be first
+        let main_id = function_ids[&self.main_id];
+        let main_index = functions.iter().position(|function| function.id() == main_id);
+        functions.swap(0, main_index.expect("ICE: Ssa should have a main function"));
+        Ssa::new(functions)
+    }
+}
+
+/// Copies a single function into a new function with normalized ids.
+struct IdNormalizer<'f> {
+    source: &'f Function,
+    function: Function,
+    function_ids: &'f HashMap<FunctionId, FunctionId>,
+    blocks: HashMap<BasicBlockId, BasicBlockId>,
+    values: HashMap<ValueId, ValueId>,
+}
+
+impl<'f> IdNormalizer<'f> {
+    fn new(source: &'f Function, function_ids: &'f HashMap<FunctionId, FunctionId>) -> Self {
+        let mut function = Function::new(source.name().to_owned(), function_ids[&source.id()]);
+        function.set_runtime(source.runtime());
+        Self { source, function, function_ids, blocks: HashMap::new(), values: HashMap::new() }
+    }
+
+    fn normalize(mut self) -> Function {
+        let mut order = PostOrder::with_function(self.source).as_slice().to_vec();
+        order.reverse();
+
+        // Every block is created up front so that jumps to later blocks can be mapped. The entry
+        // block is always first in reverse post-order.
+        for block in &order {
+            let new_block = if *block == self.source.entry_block() {
+                self.function.entry_block()
+            } else {
+                self.function.dfg.make_block()
+            };
+            self.blocks.insert(*block, new_block);
+        }
+
+        for block in order {
+            self.normalize_block(block);
+        }
+        self.function
+    }
+
+    fn normalize_block(&mut self, block: BasicBlockId) {
+        let source = self.source;
+        let dfg = &source.dfg;
+        let new_block = self.blocks[&block];
+
+        for parameter in dfg.block_parameters(block) {
+            let typ = dfg.type_of_value(*parameter);
+            let new_parameter = self.function.dfg.add_block_parameter(new_block, typ);
+            self.values.insert(*parameter, new_parameter);
+        }
+
+        for instruction_id in dfg[block].instructions() {
+            let instruction = dfg[*instruction_id].map_values(|value| self.map_value(value));
+            let results = dfg.instruction_results(*instruction_id);
+
+            let ctrl_typevars = instruction
+                .requires_ctrl_typevars()
+                .then(|| vecmap(results, |result| dfg.type_of_value(*result)));
+
+            let new_instruction = self.function.dfg.make_instruction(instruction, ctrl_typevars);
+            self.function.dfg[new_block].insert_instruction(new_instruction);
+            if let Some(location) = dfg.get_location(instruction_id) {
+                self.function.dfg.set_location(new_instruction, location);
+            }
+
+            let new_results = self.function.dfg.instruction_results(new_instruction);
+            for (result, new_result) in results.iter().zip(new_results) {
+                self.values.insert(*result, *new_result);
+            }
+        }
+
+        let mut terminator =
+            dfg[block].unwrap_terminator().map_values(|value| self.map_value(value));
+        terminator.mutate_blocks(|block| self.blocks[&block]);
+        self.function.dfg.set_block_terminator(new_block, terminator);
+    }
+
+    /// Maps a value of the source function to its value within the normalized function, creating
+    /// any constant or imported value on its first use.
+    fn map_value(&mut self, value: ValueId) -> ValueId {
+        let source = self.source;
+        let value = source.dfg.resolve(value);
+        if let Some(new_value) = self.values.get(&value) {
+            return *new_value;
+        }
+
+        let new_value = match &source.dfg[value] {
+            Value::NumericConstant { constant, typ } => {
+                self.function.dfg.make_constant(*constant, typ.clone())
+            }
+            Value::Array { array, typ } => {
+                let array = array.iter().map(|element| self.map_value(*element)).collect();
+                self.function.dfg.make_array(array, typ.clone())
+            }
+            Value::Function(id) => self.function.dfg.import_function(self.function_ids[id]),
+            Value::Intrinsic(intrinsic) => self.function.dfg.import_intrinsic(*intrinsic),
+            Value::ForeignFunction(name) => self.function.dfg.import_foreign_function(name),
+            Value::Instruction { .. } | Value::Param { .. } => {
+                unreachable!("ICE: {value} is used before it is defined")
+            }
+        };
+        self.values.insert(value, new_value);
+        new_value
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use acvm::FieldElement;
+
+    use crate::ssa_refactor::{ir::types::Type, ssa_gen::Ssa};
+
+    #[test]
+    fn renumbers_values_and_blocks_in_reverse_post_order() {
+        let src = "
+            acir fn main f0 {
+              b0(v0: u1):
+                v1 = add Field 1, Field 2
+                jmpif v0 then: b1, else: b2
+              b1():
+                v2 = call f2(v1) -> Field
+                jmp b3(v2)
+              b2():
+                jmp b3(v1)
+              b3(v3: Field):
+                return v3
+            }
+            brillig fn foo f2 {
+              b0(v0: Field):
+                return v0
+            }
+        ";
+        let mut ssa = Ssa::from_str(src).unwrap();
+
+        // Simulate a pass which leaves unused values and blocks behind
+        let main = ssa.main_mut();
+        main.dfg.make_block();
+        main.dfg.make_constant(FieldElement::from(7u128), Type::field());
+
+        let expected = "acir fn main f0 {
+  b0(v0: u1):
+    v3 = add Field 1, Field 2
+    jmpif v0 then: b2, else: b1
+  b2():
+    v5 = call f1(v3) -> Field
+    jmp b3(v5)
+  b3(v6: Field):
+    return v6
+  b1():
+    jmp b3(v3)
+}
+brillig fn foo f1 {
+  b0(v0: Field):
+    return v0
+}
+";
+        let normalized = ssa.normalize_ids();
+        assert_eq!(normalized.to_string(), expected);
+
+        // Normalizing is idempotent
+        assert_eq!(normalized.normalize_ids().to_string(), expected);
+    }
+}
@@ -0,0 +1,440 @@
+//! The peephole pass applies a table of local rewrite rules to each instruction, such as
+//! `x ^ x => 0` or `(x + c1) + c2 => x + (c1 + c2)`.
+//!
+//! Each rule looks only at a single instruction and the instructions defining its operands.
+//! Rules are tried in order and an instruction is rewritten repeatedly until no rule applies,
+//! so that a rewrite exposing another simplification is also handled. Instructions whose result
+//! is replaced by an existing value are removed, while rewritten instructions may leave the
+//! instructions defining their original operands unused for dead instruction elimination.
+use acvm::FieldElement;
+
+use crate::ssa_refactor::{
+    ir::{
+        basic_block::BasicBlockId,
+        dfg::DataFlowGraph,
+        instruction::{Binary, BinaryOp, Instruction, InstructionId},
+        types::{NumericType, Type},
+        value::{Value, ValueId},
+    },
+    ssa_gen::Ssa,
+};
+
+/// The maximum number of times a single instruction is rewritten, as a safeguard against
+/// rules which undo each other.
+const MAX_REWRITES_PER_INSTRUCTION: usize = 8;
+
+impl Ssa {
+    /// Applies each peephole rewrite rule to every instruction in each function.
+    pub(crate) fn peephole_optimize(mut self) -> Ssa {
+        for function in self.functions.values_mut() {
+            for block in function.reachable_blocks() {
+                peephole_optimize_block(&mut function.dfg, block);
+            }
+        }
+        self
+    }
+}
+
+/// A single local rewrite rule
+struct Rule {
+    /// The rewrite performed, e.g. `x ^ x => 0`
+    name: &'static str,
+
+    /// Returns the rewrite to perform if the rule applies to the given instruction
+    apply: fn(&Instruction, &mut DataFlowGraph) -> Option<Rewrite>,
+}
+
+enum Rewrite {
+    /// Replace each use of the instruction's result with the given value and remove the
+    /// instruction.
+    Value(ValueId),
+
+    /// Replace the instruction with another instruction producing the same result.
+    Instruction(Instruction),
+}
+
+/// Each rule, in the order they are tried.
+const RULES: &[Rule] = &[
+    Rule { name: "x + 0 => x", apply: add_zero },
+    Rule { name: "x - 0 => x", apply: sub_zero },
+    Rule { name: "x - x => 0", apply: sub_self },
+    Rule { name: "x * 1 => x", apply: mul_one },
+    Rule { name: "x * 0 => 0", apply: mul_zero },
+    Rule { name: "x / 1 => x", apply: div_one },
+    Rule { name: "x & 0 => 0", apply: and_zero },
+    Rule { name: "x & ones => x", apply: and_all_ones },
+    Rule { name: "x & x => x", apply: and_self },
+    Rule { name: "x | 0 => x", apply: or_zero },
+    Rule { name: "x | x => x", apply: or_self },
+    Rule { name: "x ^ 0 => x", apply: xor_zero },
+    Rule { name: "x ^ x => 0", apply: xor_self },
+    Rule { name: "!(!x) => x", apply: double_not },
+    Rule { name: "(x + c1) + c2 => x + (c1 + c2)", apply: reassociate_add },
+    Rule { name: "(x * c1) * c2 => x * (c1 * c2)", apply: reassociate_mul },
+    Rule { name: "x as typeof(x) => x", apply: redundant_cast },
+    Rule {
+        name: "truncate(truncate(x, n), m) => truncate(x, n) if n <= m",
+        apply: double_truncate,
+    },
+];
+
+fn peephole_optimize_block(dfg: &mut DataFlowGraph, block: BasicBlockId) {
+    let instructions = std::mem::take(dfg[block].instructions_mut());
+    let mut new_instructions = Vec::with_capacity(instructions.len());
+
+    for instruction in instructions {
+        if !rewrite_instruction(dfg, instruction) {
+            new_instructions.push(instruction);
+        }
+    }
+
+    *dfg[block].instructions_mut() = new_instructions;
+}
+
+/// Applies rules to the given instruction until none apply.
+/// Returns true if the instruction was removed.
+fn rewrite_instruction(dfg: &mut DataFlowGraph, id: InstructionId) -> bool {
+    for _ in 0..MAX_REWRITES_PER_INSTRUCTION {
+        let instruction = dfg[id].map_values(|value| dfg.resolve(value));
+
+        match RULES.iter().find_map(|rule| (rule.apply)(&instruction, dfg)) {
+            Some(Rewrite::Value(value)) => {
+                let result = dfg.instruction_results(id)[0];
+                dfg.set_value_from_id(result, value);
+                return true;
+            }
+            Some(Rewrite::Instruction(new_instruction)) => dfg[id] = new_instruction,
+            None => return false,
+        }
+    }
+    false
+}
+
+/// Returns the operands of a binary instruction with the given operator
+fn binary(instruction: &Instruction, operator: BinaryOp) -> Option<(ValueId, ValueId)> {
+    match instruction {
+        Instruction::Binary(binary) if binary.operator == operator => {
+            Some((binary.lhs, binary.rhs))
+        }
+        _ => None,
+    }
+}
+
+/// Returns the operands of a commutative binary instruction with the given operator
+/// if either operand is a constant, with the constant operand returned second.
+fn binary_with_constant(
+    instruction: &Instruction,
+    operator: BinaryOp,
+    dfg: &DataFlowGraph,
+) -> Option<(ValueId, FieldElement)> {
+    let (lhs, rhs) = binary(instruction, operator)?;
+    match (dfg.get_numeric_constant(lhs), dfg.get_numeric_constant(rhs)) {
+        (_, Some(rhs)) => Some((lhs, rhs)),
+        (Some(lhs), None) => Some((rhs, lhs)),
+        (None, None) => None,
+    }
+}
+
+/// Returns the operands of the binary instruction defining the given value, if any
+fn defining_binary(value: ValueId, operator: BinaryOp, dfg: &DataFlowGraph) -> Option<Binary> {
+    match &dfg[value] {
+        Value::Instruction { instruction, .. } => match &dfg[*instruction] {
+            Instruction::Binary(binary) if binary.operator == operator => Some(binary.clone()),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+fn zero_of_type(value: ValueId, dfg: &mut DataFlowGraph) -> ValueId {
+    let typ = dfg.type_of_value(value);
+    dfg.make_constant(FieldElement::zero(), typ)
+}
+
+fn add_zero(instruction: &Instruction, dfg: &mut DataFlowGraph) -> Option<Rewrite> {
+    let (value, constant) = binary_with_constant(instruction, BinaryOp::Add, dfg)?;
+    constant.is_zero().then_some(Rewrite::Value(value))
+}
+
+fn sub_zero(instruction: &Instruction, dfg: &mut DataFlowGraph) -> Option<Rewrite> {
+    let (lhs, rhs) = binary(instruction, BinaryOp::Sub)?;
+    let rhs = dfg.get_numeric_constant(rhs)?;
+    rhs.is_zero().then_some(Rewrite::Value(lhs))
+}
+
+fn sub_self(instruction: &Instruction, dfg: &mut DataFlowGraph) -> Option<Rewrite> {
+    let (lhs, rhs) = binary(instruction, BinaryOp::Sub)?;
+    (lhs == rhs).then(|| Rewrite::Value(zero_of_type(lhs, dfg)))
+}
+
+fn mul_one(instruction: &Instruction, dfg: &mut DataFlowGraph) -> Option<Rewrite> {
+    let (value, constant) = binary_with_constant(instruction, BinaryOp::Mul, dfg)?;
+    constant.is_one().then_some(Rewrite::Value(value))
+}
+
+fn mul_zero(instruction: &Instruction, dfg: &mut DataFlowGraph) -> Option<Rewrite> {
+    let (value, constant) = binary_with_constant(instruction, BinaryOp::Mul, dfg)?;
+    constant.is_zero().then(|| Rewrite::Value(zero_of_type(value, dfg)))
+}
+
+fn div_one(instruction: &Instruction, dfg: &mut DataFlowGraph) -> Option<Rewrite> {
+    let (lhs, rhs) = binary(instruction, BinaryOp::Div)?;
+    let rhs = dfg.get_numeric_constant(rhs)?;
+    rhs.is_one().then_some(Rewrite::Value(lhs))
+}
+
+fn and_zero(instruction: &Instruction, dfg: &mut DataFlowGraph) -> Option<Rewrite> {
+    let (value, constant) = binary_with_constant(instruction, BinaryOp::And, dfg)?;
+    constant.is_zero().then(|| Rewrite::Value(zero_of_type(value, dfg)))
+}
+
+fn and_all_ones(instruction: &Instruction, dfg: &mut DataFlowGraph) -> Option<Rewrite> {
+    let (value, constant) = binary_with_constant(instruction, BinaryOp::And, dfg)?;
+    match dfg.type_of_value(value) {
+        Type::Numeric(NumericType::Unsigned { bit_size }) => {
+            let all_ones = FieldElement::from(2_u128).pow(&FieldElement::from(bit_size as u128))
+                - FieldElement::one();
+            (constant == all_ones).then_some(Rewrite::Value(value))
+        }
+        _ => None,
+    }
+}
+
+fn and_self(instruction: &Instruction, _dfg: &mut DataFlowGraph) -> Option<Rewrite> {
+    let (lhs, rhs) = binary(instruction, BinaryOp::And)?;
+    (lhs == rhs).then_some(Rewrite::Value(lhs))
+}
+
+fn or_zero(instruction: &Instruction, dfg: &mut DataFlowGraph) -> Option<Rewrite> {
+    let (value, constant) = binary_with_constant(instruction, BinaryOp::Or, dfg)?;
+    constant.is_zero().then_some(Rewrite::Value(value))
+}
+
+fn or_self(instruction: &Instruction, _dfg: &mut DataFlowGraph) -> Option<Rewrite> {
+    let (lhs, rhs) = binary(instruction, BinaryOp::Or)?;
+    (lhs == rhs).then_some(Rewrite::Value(lhs))
+}
+
+fn xor_zero(instruction: &Instruction, dfg: &mut DataFlowGraph) -> Option<Rewrite> {
+    let (value, constant) = binary_with_constant(instruction, BinaryOp::Xor, dfg)?;
+    constant.is_zero().then_some(Rewrite::Value(value))
+}
+
+fn xor_self(instruction: &Instruction, dfg: &mut DataFlowGraph) -> Option<Rewrite> {
+    let (lhs, rhs) = binary(instruction, BinaryOp::Xor)?;
+    (lhs == rhs).then(|| Rewrite::Value(zero_of_type(lhs, dfg)))
+}
+
+fn double_not(instruction: &Instruction, dfg: &mut DataFlowGraph) -> Option<Rewrite> {
+    let value = match instruction {
+        Instruction::Not(value) => *value,
+        _ => return None,
+    };
+    match &dfg[value] {
+        Value::Instruction { instruction, .. } => match &dfg[*instruction] {
+            Instruction::Not(inner) => Some(Rewrite::Value(dfg.resolve(*inner))),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// Reassociates a field operation whose operands are a constant and the result of the same
+/// operation on another constant. This is restricted to fields since combining the constants of
+/// integer operations may overflow the integer type.
+fn reassociate(
+    instruction: &Instruction,
+    operator: BinaryOp,
+    dfg: &mut DataFlowGraph,
+    combine: fn(FieldElement, FieldElement) -> FieldElement,
+) -> Option<Rewrite> {
+    let (value, outer_constant) = binary_with_constant(instruction, operator, dfg)?;
+    if dfg.type_of_value(value) != Type::field() {
+        return None;
+    }
+
+    let inner = defining_binary(value, operator, dfg)?;
+    let inner = Instruction::Binary(inner).map_values(|value| dfg.resolve(value));
+    let (inner_value, inner_constant) = binary_with_constant(&inner, operator, dfg)?;
+
+    let constant = dfg.make_constant(combine(inner_constant, outer_constant), Type::field());
+    Some(Rewrite::Instruction(Instruction::Binary(Binary {
+        lhs: inner_value,
+        rhs: constant,
+        operator,
+    })))
+}
+
+fn reassociate_add(instruction: &Instruction, dfg: &mut DataFlowGraph) -> Option<Rewrite> {
+    reassociate(instruction, BinaryOp::Add, dfg, |lhs, rhs| lhs + rhs)
+}
+
+fn reassociate_mul(instruction: &Instruction, dfg: &mut DataFlowGraph) -> Option<Rewrite> {
+    reassociate(instruction, BinaryOp::Mul, dfg, |lhs, rhs| lhs * rhs)
+}
+
+fn redundant_cast(instruction: &Instruction, dfg: &mut DataFlowGraph) -> Option<Rewrite> {
+    match instruction {
+        Instruction::Cast(value, typ) if dfg.type_of_value(*value) == *typ => {
+            Some(Rewrite::Value(*value))
+        }
+        _ => None,
+    }
+}
+
+fn double_truncate(instruction: &Instruction, dfg: &mut DataFlowGraph) -> Option<Rewrite> {
+    let (value, bit_size) = match instruction {
+        Instruction::Truncate { value, bit_size, .. } => (*value, *bit_size),
+        _ => return None,
+    };
+    match &dfg[value] {
+        Value::Instruction { instruction, .. } => match &dfg[*instruction] {
+            Instruction::Truncate { bit_size: inner_bit_size, .. }
+                if *inner_bit_size <= bit_size =>
+            {
+                Some(Rewrite::Value(value))
+            }
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::str::FromStr;
+
+    use crate::ssa_refactor::{ir::parser::assert_ssa_equals, ssa_gen::Ssa};
+
+    use super::RULES;
+
+    /// Checks that the peephole pass rewrites the given body of a function with parameters
+    /// `v0: Field, v1: u32, v2: u1` into the expected body.
+    fn assert_rewrites(body: &str, expected_body: &str) {
+        let function =
+            |body| format!("acir fn main f0 {{\n b0(v0: Field, v1: u32, v2: u1):\n{body}\n}}");
+        let ssa = Ssa::from_str(&function(body)).unwrap().peephole_optimize();
+        assert_ssa_equals(&ssa, &function(expected_body));
+    }
+
+    #[test]
+    fn rule_names_are_unique() {
+        for (index, rule) in RULES.iter().enumerate() {
+            assert!(RULES[index + 1..].iter().all(|other| other.name != rule.name));
+        }
+    }
+
+    #[test]
+    fn add_zero() {
+        assert_rewrites("v3 = add Field 0, v0\n return v3", "return v0");
+    }
+
+    #[test]
+    fn sub_zero() {
+        assert_rewrites("v3 = sub v1, u32 0\n return v3", "return v1");
+    }
+
+    #[test]
+    fn sub_self() {
+        assert_rewrites("v3 = sub v1, v1\n return v3", "return u32 0");
+    }
+
+    #[test]
+    fn mul_one() {
+        assert_rewrites("v3 = mul v0, Field 1\n return v3", "return v0");
+    }
+
+    #[test]
+    fn mul_zero() {
+        assert_rewrites("v3 = mul u32 0, v1\n return v3", "return u32 0");
+    }
+
+    #[test]
+    fn div_one() {
+        assert_rewrites("v3 = div v1, u32 1\n return v3", "return v1");
+    }
+
+    #[test]
+    fn and_zero() {
+        assert_rewrites("v3 = and v1, u32 0\n return v3", "return u32 0");
+    }
+
+    #[test]
+    fn and_all_ones() {
+        assert_rewrites("v3 = and v1, u32 4294967295\n return v3", "return v1");
+        assert_rewrites("v3 = and v1, u32 65535\n return v3", "v3 = and v1, u32 65535\n return v3");
+    }
+
+    #[test]
+    fn and_self() {
+        assert_rewrites("v3 = and v2, v2\n return v3", "return v2");
+    }
+
+    #[test]
+    fn or_zero() {
+        assert_rewrites("v3 = or u32 0, v1\n return v3", "return v1");
+    }
+
+    #[test]
+    fn or_self() {
+        assert_rewrites("v3 = or v1, v1\n return v3", "return v1");
+    }
+
+    #[test]
+    fn xor_zero() {
+        assert_rewrites("v3 = xor v1, u32 0\n return v3", "return v1");
+    }
+
+    #[test]
+    fn xor_self() {
+        assert_rewrites("v3 = xor v1, v1\n return v3", "return u32 0");
+    }
+
+    #[test]
+    fn double_not() {
+        assert_rewrites("v3 = not v2\n v4 = not v3\n return v4", "v3 = not v2\n return v2");
+    }
+
+    #[test]
+    fn reassociate_add() {
+        assert_rewrites(
+            "v3 = add v0, Field 2\n v4 = add Field 3, v3\n return v4",
+            "v3 = add v0, Field 2\n v4 = add v0, Field 5\n return v4",
+        );
+        // Integer constants are not combined since the combined constant may overflow
+        assert_rewrites(
+            "v3 = add v1, u32 2\n v4 = add v3, u32 3\n return v4",
+            "v3 = add v1, u32 2\n v4 = add v3, u32 3\n return v4",
+        );
+    }
+
+    #[test]
+    fn reassociate_mul() {
+        assert_rewrites(
+            "v3 = mul v0, Field 2\n v4 = mul v3, Field 3\n return v4",
+            "v3 = mul v0, Field 2\n v4 = mul v0, Field 6\n return v4",
+        );
+    }
+
+    #[test]
+    fn redundant_cast() {
+        assert_rewrites("v3 = cast v1 as u32\n return v3", "return v1");
+    }
+
+    #[test]
+    fn double_truncate() {
+        assert_rewrites(
+            "v3 = truncate v1 to 8 bits, max_bit_size: 32\n v4 = truncate v3 to 16 bits, max_bit_size: 32\n return v4",
+            "v3 = truncate v1 to 8 bits, max_bit_size: 32\n return v3",
+        );
+    }
+
+    #[test]
+    fn rewrites_repeatedly() {
+        // The inner addition is first reassociated into `v0 + 0` which is then simplified to `v0`
+        assert_rewrites(
+            "v3 = add v0, Field 1\n v4 = add v3, Field -1\n return v4",
+            "v3 = add v0, Field 1\n return v0",
+        );
+    }
+}
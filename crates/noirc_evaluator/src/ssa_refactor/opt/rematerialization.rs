@@ -0,0 +1,238 @@
+//! Rematerialization recomputes cheap values within each block using them, rather than keeping
+//! them live from the block which defined them.
+//!
+//! Each value is given its own register in brillig, which stays live from the value's definition
+//! to its last use. A value such as `v1 = add v0, Field 1` defined before a loop and used within
+//! it, or in a block after several calls, occupies a register throughout, which must be saved
+//! and restored around each call. Recomputing the value near its use takes a single instruction,
+//! while shortening the range its register is live for. Constants need no rematerialization since
+//! brillig generation already initializes a constant at each of its uses.
+//!
+//! A value is cheap to recompute if it is computed by a cast, a `not`, a truncation, or an
+//! addition, subtraction, or multiplication with a constant. It is recomputed within a block
+//! other than the one which defines it if each of its operands is live into that block anyway,
+//! so that recomputing it does not extend the range any other value is live for. Each use within
+//! the block is replaced by a copy of the instruction inserted before the first use. Definitions
+//! which are no longer used afterward are left for dead instruction elimination to remove.
+//!
+//! Since this undoes global value numbering, it only runs on brillig functions.
+use std::collections::{HashMap, HashSet};
+
+use crate::ssa_refactor::{
+    ir::{
+        basic_block::BasicBlockId,
+        dfg::DataFlowGraph,
+        function::{Function, RuntimeType},
+        instruction::{BinaryOp, Instruction, InstructionId},
+        liveness::VariableLiveness,
+        value::ValueId,
+    },
+    ssa_gen::Ssa,
+};
+
+impl Ssa {
+    /// Recomputes cheap values within each block of each brillig function which uses them, rather
+    /// than keeping them live from the block which defined them.
+    pub(crate) fn rematerialize(mut self) -> Ssa {
+        for function in self.functions.values_mut() {
+            if function.runtime() == RuntimeType::Brillig {
+                rematerialize_function(function);
+            }
+        }
+        self
+    }
+}
+
+/// The instruction defining a value which is cheap to recompute, along with its block.
+type Definition = (BasicBlockId, InstructionId);
+
+fn rematerialize_function(function: &mut Function) {
+    let liveness = VariableLiveness::with_function(function);
+    let blocks = function.reachable_blocks();
+
+    let mut definitions = HashMap::new();
+    for block in &blocks {
+        for instruction in function.dfg[*block].instructions() {
+            if is_cheap(&function.dfg, &function.dfg[*instruction]) {
+                let result = function.dfg.instruction_results(*instruction)[0];
+                definitions.insert(result, (*block, *instruction));
+            }
+        }
+    }
+
+    for block in blocks {
+        let live_in = liveness.live_in(block);
+        rematerialize_block(&mut function.dfg, block, &definitions, live_in);
+    }
+}
+
+fn rematerialize_block(
+    dfg: &mut DataFlowGraph,
+    block: BasicBlockId,
+    definitions: &HashMap<ValueId, Definition>,
+    live_in: &HashSet<ValueId>,
+) {
+    let instructions = std::mem::take(dfg[block].instructions_mut());
+    let mut new_instructions = Vec::with_capacity(instructions.len());
+    let mut copies = HashMap::new();
+
+    for id in instructions {
+        let mut operands = Vec::new();
+        dfg[id].for_each_value(|value| operands.push(value));
+        for operand in operands {
+            if let Some(copy) =
+                copy_definition(dfg, block, operand, definitions, live_in, &mut copies)
+            {
+                new_instructions.push(copy);
+            }
+        }
+
+        if !copies.is_empty() {
+            dfg[id] = dfg[id].map_values(|value| replace(dfg, &copies, value));
+        }
+        new_instructions.push(id);
+    }
+
+    let mut operands = Vec::new();
+    if let Some(terminator) = dfg[block].terminator() {
+        terminator.for_each_value(|value| operands.push(value));
+    }
+    for operand in operands {
+        if let Some(copy) = copy_definition(dfg, block, operand, definitions, live_in, &mut copies)
+        {
+            new_instructions.push(copy);
+        }
+    }
+    if !copies.is_empty() {
+        let terminator =
+            dfg[block].unwrap_terminator().clone().map_values(|value| replace(dfg, &copies, value));
+        dfg.set_block_terminator(block, terminator);
+    }
+
+    *dfg[block].instructions_mut() = new_instructions;
+}
+
+/// Inserts a copy of the instruction defining the given value if it should be recomputed within
+/// the given block, returning the id of the copy.
+fn copy_definition(
+    dfg: &mut DataFlowGraph,
+    block: BasicBlockId,
+    value: ValueId,
+    definitions: &HashMap<ValueId, Definition>,
+    live_in: &HashSet<ValueId>,
+    copies: &mut HashMap<ValueId, ValueId>,
+) -> Option<InstructionId> {
+    let value = dfg.resolve(value);
+    let (definition_block, definition) = *definitions.get(&value)?;
+    if definition_block == block || copies.contains_key(&value) {
+        return None;
+    }
+
+    let instruction = dfg[definition].clone();
+    let mut operands_are_live = true;
+    instruction.for_each_value(|operand| {
+        let operand = dfg.resolve(operand);
+        operands_are_live &= dfg.get_numeric_constant(operand).is_some()
+            || live_in.contains(&operand)
+            || copies.contains_key(&operand);
+    });
+    if !operands_are_live {
+        return None;
+    }
+
+    let instruction = instruction.map_values(|operand| replace(dfg, copies, operand));
+    let copy = dfg.make_instruction(instruction, None);
+    if let Some(location) = dfg.get_location(&definition) {
+        dfg.set_location(copy, location);
+    }
+    copies.insert(value, dfg.instruction_results(copy)[0]);
+    Some(copy)
+}
+
+fn replace(dfg: &DataFlowGraph, copies: &HashMap<ValueId, ValueId>, value: ValueId) -> ValueId {
+    let value = dfg.resolve(value);
+    copies.get(&value).copied().unwrap_or(value)
+}
+
+/// Returns true if the given instruction computes a single value which is cheap to recompute.
+fn is_cheap(dfg: &DataFlowGraph, instruction: &Instruction) -> bool {
+    match instruction {
+        Instruction::Binary(binary) => {
+            matches!(binary.operator, BinaryOp::Add | BinaryOp::Sub | BinaryOp::Mul)
+                && (dfg.get_numeric_constant(binary.lhs).is_some()
+                    || dfg.get_numeric_constant(binary.rhs).is_some())
+        }
+        Instruction::Cast(..) | Instruction::Not(_) | Instruction::Truncate { .. } => true,
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::str::FromStr;
+
+    use crate::ssa_refactor::{ir::parser::assert_ssa_equals, ssa_gen::Ssa};
+
+    #[test]
+    fn rematerializes_cheap_values_in_loops() {
+        // v2 is recomputed within the loop since v0 is live there anyway. v3 is not, since v1
+        // would otherwise have to stay live until the loop, and neither is recomputed after it.
+        let src = "
+            brillig fn main f0 {
+              b0(v0: Field, v1: Field):
+                v2 = add v0, Field 1
+                v3 = mul v1, Field 2
+                jmp b1(Field 0)
+              b1(v4: Field):
+                v5 = lt v4, Field 3
+                jmpif v5 then: b2, else: b3
+              b2():
+                v6 = add v4, v2
+                v7 = add v6, v0
+                v8 = add v7, v3
+                v9 = add v4, Field 1
+                jmp b1(v9)
+              b3():
+                return v2
+            }
+        ";
+        let expected = "
+            brillig fn main f0 {
+              b0(v0: Field, v1: Field):
+                v2 = add v0, Field 1
+                v3 = mul v1, Field 2
+                jmp b1(Field 0)
+              b1(v4: Field):
+                v5 = lt v4, Field 3
+                jmpif v5 then: b2, else: b3
+              b2():
+                v6 = add v0, Field 1
+                v7 = add v4, v6
+                v8 = add v7, v0
+                v9 = add v8, v3
+                v10 = add v4, Field 1
+                jmp b1(v10)
+              b3():
+                return v2
+            }
+        ";
+        let ssa = Ssa::from_str(src).unwrap().rematerialize();
+        assert_ssa_equals(&ssa, expected);
+    }
+
+    #[test]
+    fn leaves_acir_functions() {
+        let src = "
+            acir fn main f0 {
+              b0(v0: Field):
+                v1 = add v0, Field 1
+                jmp b1()
+              b1():
+                v2 = add v1, v0
+                return v2
+            }
+        ";
+        let ssa = Ssa::from_str(src).unwrap().rematerialize();
+        assert_ssa_equals(&ssa, src);
+    }
+}
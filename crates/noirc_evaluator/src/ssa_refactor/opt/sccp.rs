@@ -0,0 +1,440 @@
+//! Sparse conditional constant propagation (SCCP) propagates constants through the control flow
+//! graph, and removes any branch whose condition is found to be constant.
+//!
+//! Unlike constant folding, this pass is able to find constant block parameters when every
+//! executable jump into a block passes the same constant. In turn, this may make the condition of
+//! a later `jmpif` constant, in which case the branch which is never taken is made unreachable.
+//!
+//! This pass is expected to run before `flatten_cfg` so that branches which can never be taken,
+//! along with any constraints within them, are removed instead of being flattened.
+//!
+//! The pass has two steps:
+//! 1. Each value is assigned a lattice value, starting from `Unknown`, and the blocks of the
+//!    function are evaluated repeatedly until no lattice value or executable edge changes.
+//!    Only blocks reachable through an executable edge from the entry block are evaluated.
+//! 2. Each value found to be constant is replaced with that constant, and each `jmpif` with
+//!    a constant condition is replaced with a `jmp` to the only destination which is taken.
+use std::collections::{HashMap, HashSet};
+
+use crate::ssa_refactor::{
+    ir::{
+        basic_block::BasicBlockId,
+        cfg::ControlFlowGraph,
+        dfg::DataFlowGraph,
+        function::Function,
+        instruction::{Instruction, InstructionId, SimplifyResult, TerminatorInstruction},
+        post_order::PostOrder,
+        value::{Value, ValueId},
+    },
+    ssa_gen::Ssa,
+};
+
+impl Ssa {
+    /// Propagates constants through the control flow graph of each function and removes any
+    /// branch which can never be taken.
+    pub(crate) fn sparse_conditional_constant_propagation(mut self) -> Ssa {
+        for function in self.functions.values_mut() {
+            let mut context = Context::default();
+            context.analyze(function);
+            context.apply(function);
+        }
+        self
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum LatticeValue {
+    /// No value has been found yet
+    Unknown,
+
+    /// The value is always the given value, which is either a numeric or array constant
+    Constant(ValueId),
+
+    /// The value may differ between executions
+    Overdefined,
+}
+
+impl LatticeValue {
+    fn meet(self, other: LatticeValue) -> LatticeValue {
+        use LatticeValue::*;
+        match (self, other) {
+            (Unknown, value) | (value, Unknown) => value,
+            (Constant(a), Constant(b)) if a == b => Constant(a),
+            _ => Overdefined,
+        }
+    }
+}
+
+#[derive(Default)]
+struct Context {
+    /// The lattice value of each block parameter and instruction result evaluated so far.
+    /// Any value missing from this map is `Unknown`.
+    values: HashMap<ValueId, LatticeValue>,
+
+    executable_blocks: HashSet<BasicBlockId>,
+    executable_edges: HashSet<(BasicBlockId, BasicBlockId)>,
+
+    /// Set whenever a lattice value or executable edge changes so
+    /// that we know to evaluate the function once more.
+    changed: bool,
+}
+
+impl Context {
+    fn analyze(&mut self, function: &mut Function) {
+        let cfg = ControlFlowGraph::with_function(function);
+        let post_order = PostOrder::with_function(function);
+        let blocks = post_order.as_slice().iter().rev().copied().collect::<Vec<_>>();
+
+        self.executable_blocks.insert(function.entry_block());
+        for parameter in function.parameters() {
+            self.values.insert(*parameter, LatticeValue::Overdefined);
+        }
+
+        self.changed = true;
+        while self.changed {
+            self.changed = false;
+
+            for block in &blocks {
+                if self.executable_blocks.contains(block) {
+                    self.evaluate_block(function, &cfg, *block);
+                }
+            }
+        }
+    }
+
+    fn evaluate_block(
+        &mut self,
+        function: &mut Function,
+        cfg: &ControlFlowGraph,
+        block: BasicBlockId,
+    ) {
+        if block != function.entry_block() {
+            self.evaluate_block_parameters(&function.dfg, cfg, block);
+        }
+
+        for instruction in function.dfg[block].instructions().to_vec() {
+            self.evaluate_instruction(function, block, instruction);
+        }
+
+        match function.dfg[block].terminator() {
+            Some(TerminatorInstruction::Jmp { destination, .. }) => {
+                self.mark_edge_executable(block, *destination);
+            }
+            Some(TerminatorInstruction::JmpIf {
+                condition,
+                then_destination,
+                else_destination,
+            }) => {
+                let (then_destination, else_destination) = (*then_destination, *else_destination);
+
+                match self.lattice_value(&function.dfg, *condition) {
+                    LatticeValue::Unknown => (),
+                    LatticeValue::Constant(condition) => {
+                        let condition = function.dfg.get_numeric_constant(condition);
+                        if condition.map_or(false, |condition| condition.is_zero()) {
+                            self.mark_edge_executable(block, else_destination);
+                        } else {
+                            self.mark_edge_executable(block, then_destination);
+                        }
+                    }
+                    LatticeValue::Overdefined => {
+                        self.mark_edge_executable(block, then_destination);
+                        self.mark_edge_executable(block, else_destination);
+                    }
+                }
+            }
+            Some(TerminatorInstruction::Return { .. }) | None => (),
+        }
+    }
+
+    /// The value of each block parameter is the meet of each argument passed to
+    /// it along each executable edge into the block.
+    fn evaluate_block_parameters(
+        &mut self,
+        dfg: &DataFlowGraph,
+        cfg: &ControlFlowGraph,
+        block: BasicBlockId,
+    ) {
+        for predecessor in cfg.predecessors(block) {
+            if !self.executable_edges.contains(&(predecessor, block)) {
+                continue;
+            }
+
+            if let Some(TerminatorInstruction::Jmp { arguments, .. }) =
+                dfg[predecessor].terminator()
+            {
+                let parameters = dfg.block_parameters(block);
+                assert_eq!(parameters.len(), arguments.len());
+
+                for (parameter, argument) in parameters.iter().zip(arguments) {
+                    let argument = self.lattice_value(dfg, *argument);
+                    self.update_value(*parameter, argument);
+                }
+            }
+        }
+    }
+
+    /// Evaluates the results of the given instruction by simplifying it with each operand
+    /// known to be constant replaced with that constant.
+    fn evaluate_instruction(
+        &mut self,
+        function: &mut Function,
+        block: BasicBlockId,
+        instruction_id: InstructionId,
+    ) {
+        let results = function.dfg.instruction_results(instruction_id).to_vec();
+        if results.is_empty() {
+            return;
+        }
+
+        let instruction = &function.dfg[instruction_id];
+        if matches!(instruction, Instruction::Allocate | Instruction::Load { .. }) {
+            for result in results {
+                self.update_value(result, LatticeValue::Overdefined);
+            }
+            return;
+        }
+
+        let mut has_unknown_operand = false;
+        let instruction =
+            instruction.map_values(|value| match self.lattice_value(&function.dfg, value) {
+                LatticeValue::Constant(constant) => constant,
+                LatticeValue::Unknown => {
+                    has_unknown_operand = true;
+                    value
+                }
+                LatticeValue::Overdefined => value,
+            });
+
+        // Wait until each operand is known before evaluating this instruction
+        if has_unknown_operand {
+            return;
+        }
+
+        let new_values = match instruction.simplify(&mut function.dfg, block) {
+            SimplifyResult::SimplifiedTo(value) => {
+                vec![self.lattice_value(&function.dfg, value)]
+            }
+            SimplifyResult::SimplifiedToMultiple(values) => {
+                values.into_iter().map(|value| self.lattice_value(&function.dfg, value)).collect()
+            }
+            SimplifyResult::Remove | SimplifyResult::None => {
+                vec![LatticeValue::Overdefined; results.len()]
+            }
+        };
+
+        assert_eq!(results.len(), new_values.len());
+        for (result, new_value) in results.into_iter().zip(new_values) {
+            self.update_value(result, new_value);
+        }
+    }
+
+    fn lattice_value(&self, dfg: &DataFlowGraph, value: ValueId) -> LatticeValue {
+        let value = dfg.resolve(value);
+        match &dfg[value] {
+            Value::NumericConstant { .. } | Value::Array { .. } => LatticeValue::Constant(value),
+            Value::Instruction { .. } | Value::Param { .. } => {
+                self.values.get(&value).copied().unwrap_or(LatticeValue::Unknown)
+            }
+            Value::Function(_) | Value::Intrinsic(_) | Value::ForeignFunction(_) => {
+                LatticeValue::Overdefined
+            }
+        }
+    }
+
+    /// Lowers the lattice value of the given value to its meet with the given new value.
+    fn update_value(&mut self, value: ValueId, new_value: LatticeValue) {
+        let old_value = self.values.get(&value).copied().unwrap_or(LatticeValue::Unknown);
+        let new_value = old_value.meet(new_value);
+
+        if new_value != old_value {
+            self.values.insert(value, new_value);
+            self.changed = true;
+        }
+    }
+
+    fn mark_edge_executable(&mut self, source: BasicBlockId, destination: BasicBlockId) {
+        if self.executable_edges.insert((source, destination)) {
+            self.executable_blocks.insert(destination);
+            self.changed = true;
+        }
+    }
+
+    /// Replaces each value found to be constant and each jmpif with a constant condition.
+    fn apply(self, function: &mut Function) {
+        for (value, lattice_value) in &self.values {
+            if let LatticeValue::Constant(constant) = lattice_value {
+                function.dfg.set_value_from_id(*value, *constant);
+            }
+        }
+
+        for block in &self.executable_blocks {
+            let destination = match function.dfg[*block].terminator() {
+                Some(TerminatorInstruction::JmpIf {
+                    condition,
+                    then_destination,
+                    else_destination,
+                }) => match function.dfg.get_numeric_constant(*condition) {
+                    Some(condition) if condition.is_zero() => *else_destination,
+                    Some(_) => *then_destination,
+                    None => continue,
+                },
+                _ => continue,
+            };
+
+            let jmp = TerminatorInstruction::Jmp { destination, arguments: Vec::new() };
+            function.dfg[*block].set_terminator(jmp);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::ssa_refactor::{
+        ir::{
+            function::RuntimeType, instruction::BinaryOp, instruction::TerminatorInstruction,
+            map::Id, types::Type,
+        },
+        ssa_builder::FunctionBuilder,
+    };
+
+    #[test]
+    fn prunes_branch_with_constant_condition() {
+        // fn main f0 {
+        //   b0(v0: u1):
+        //     jmpif v0 then: b1, else: b2
+        //   b1():
+        //     jmp b3(Field 2)
+        //   b2():
+        //     jmp b3(Field 2)
+        //   b3(v1: Field):
+        //     v2 = eq v1, Field 2
+        //     jmpif v2 then: b4, else: b5
+        //   b4():
+        //     return v1
+        //   b5():
+        //     constrain u1 0
+        //     return Field 0
+        // }
+        let main_id = Id::test_new(0);
+        let mut builder = FunctionBuilder::new("main".into(), main_id, RuntimeType::Acir);
+        let v0 = builder.add_parameter(Type::bool());
+
+        let b1 = builder.insert_block();
+        let b2 = builder.insert_block();
+        let b3 = builder.insert_block();
+        let b4 = builder.insert_block();
+        let b5 = builder.insert_block();
+
+        let zero = builder.field_constant(0u128);
+        let two = builder.field_constant(2u128);
+        let false_ = builder.numeric_constant(0u128, Type::bool());
+
+        builder.terminate_with_jmpif(v0, b1, b2);
+
+        builder.switch_to_block(b1);
+        builder.terminate_with_jmp(b3, vec![two]);
+
+        builder.switch_to_block(b2);
+        builder.terminate_with_jmp(b3, vec![two]);
+
+        builder.switch_to_block(b3);
+        let v1 = builder.add_block_parameter(b3, Type::field());
+        let v2 = builder.insert_binary(v1, BinaryOp::Eq, two);
+        builder.terminate_with_jmpif(v2, b4, b5);
+
+        builder.switch_to_block(b4);
+        builder.terminate_with_return(vec![v1]);
+
+        builder.switch_to_block(b5);
+        builder.insert_constrain(false_, None);
+        builder.terminate_with_return(vec![zero]);
+
+        // Expected output:
+        //
+        // fn main f0 {
+        //   b0(v0: u1):
+        //     jmpif v0 then: b1, else: b2
+        //   b1():
+        //     jmp b3(Field 2)
+        //   b2():
+        //     jmp b3(Field 2)
+        //   b3(Field 2: Field):
+        //     v2 = eq Field 2, Field 2
+        //     jmp b4()
+        //   b4():
+        //     return Field 2
+        // }
+        let ssa = builder.finish().sparse_conditional_constant_propagation();
+        let main = ssa.main();
+
+        assert_eq!(main.dfg.get_numeric_constant(v1).map(|value| value.to_u128()), Some(2));
+        assert_eq!(main.dfg.get_numeric_constant(v2).map(|value| value.to_u128()), Some(1));
+
+        match main.dfg[b3].terminator() {
+            Some(TerminatorInstruction::Jmp { destination, .. }) => assert_eq!(*destination, b4),
+            _ => unreachable!("b3 should end in a jmp to b4"),
+        }
+
+        let reachable_blocks = main.reachable_blocks();
+        assert!(reachable_blocks.contains(&b4));
+        assert!(!reachable_blocks.contains(&b5));
+    }
+
+    #[test]
+    fn keeps_branch_with_unknown_condition() {
+        // fn main f0 {
+        //   b0(v0: u1):
+        //     jmpif v0 then: b1, else: b2
+        //   b1():
+        //     jmp b3(Field 1)
+        //   b2():
+        //     jmp b3(Field 2)
+        //   b3(v1: Field):
+        //     v2 = eq v1, Field 2
+        //     jmpif v2 then: b4, else: b5
+        //   b4():
+        //     return Field 1
+        //   b5():
+        //     return Field 2
+        // }
+        let main_id = Id::test_new(0);
+        let mut builder = FunctionBuilder::new("main".into(), main_id, RuntimeType::Acir);
+        let v0 = builder.add_parameter(Type::bool());
+
+        let b1 = builder.insert_block();
+        let b2 = builder.insert_block();
+        let b3 = builder.insert_block();
+        let b4 = builder.insert_block();
+        let b5 = builder.insert_block();
+
+        let one = builder.field_constant(1u128);
+        let two = builder.field_constant(2u128);
+
+        builder.terminate_with_jmpif(v0, b1, b2);
+
+        builder.switch_to_block(b1);
+        builder.terminate_with_jmp(b3, vec![one]);
+
+        builder.switch_to_block(b2);
+        builder.terminate_with_jmp(b3, vec![two]);
+
+        builder.switch_to_block(b3);
+        let v1 = builder.add_block_parameter(b3, Type::field());
+        let v2 = builder.insert_binary(v1, BinaryOp::Eq, two);
+        builder.terminate_with_jmpif(v2, b4, b5);
+
+        builder.switch_to_block(b4);
+        builder.terminate_with_return(vec![one]);
+
+        builder.switch_to_block(b5);
+        builder.terminate_with_return(vec![two]);
+
+        let ssa = builder.finish().sparse_conditional_constant_propagation();
+        let main = ssa.main();
+
+        assert_eq!(main.dfg.get_numeric_constant(v1), None);
+        assert!(matches!(main.dfg[b3].terminator(), Some(TerminatorInstruction::JmpIf { .. })));
+        assert_eq!(main.reachable_blocks().len(), 6);
+    }
+}
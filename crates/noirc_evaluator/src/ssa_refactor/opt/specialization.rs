@@ -0,0 +1,242 @@
+//! Function specialization creates a copy of each unconstrained function called with constant
+//! arguments, with those constants substituted for the corresponding parameters.
+//!
+//! Unconstrained functions are not inlined, so a loop bounded by a length parameter, or a branch
+//! on a flag parameter, cannot be simplified within the callee even when every call passes a
+//! constant. Within a specialized copy these parameters are constants, so later passes may fold
+//! the branches and unroll the loops using them.
+//!
+//! Each copy is shared by every call passing the same constants to the same function. Since each
+//! copy adds to the size of the program, copies are only created while the total number of
+//! instructions they add remains within the given budget. Calls which would exceed it are left
+//! unchanged.
+use std::collections::{BTreeMap, HashMap};
+
+use acvm::FieldElement;
+
+use crate::ssa_refactor::{
+    ir::{
+        function::{Function, FunctionId, RuntimeType},
+        instruction::{Instruction, InstructionId},
+        types::Type,
+        value::{Value, ValueId},
+    },
+    ssa_gen::Ssa,
+};
+
+/// The constant passed to each parameter of a function, if any
+type ConstantArguments = Vec<Option<(FieldElement, Type)>>;
+
+/// A call to a function with at least one constant argument
+struct SpecializableCall {
+    caller: FunctionId,
+    instruction: InstructionId,
+    callee: FunctionId,
+    constants: ConstantArguments,
+}
+
+impl Ssa {
+    /// Replaces each call to an unconstrained function with constant arguments with a call to a
+    /// copy of the function specialized to those arguments, as long as the copies add at most
+    /// `budget` instructions to the program.
+    pub(crate) fn specialize_functions(mut self, budget: usize) -> Ssa {
+        let mut remaining_budget = budget;
+        let mut specializations: HashMap<(FunctionId, ConstantArguments), FunctionId> =
+            HashMap::new();
+        let mut new_functions = BTreeMap::new();
+
+        for call in find_specializable_calls(&self) {
+            let key = (call.callee, call.constants.clone());
+            let specialized = match specializations.get(&key) {
+                Some(specialized) => *specialized,
+                None => {
+                    let callee = &self.functions[&call.callee];
+                    let cost = instruction_count(callee);
+                    if cost > remaining_budget {
+                        continue;
+                    }
+                    remaining_budget -= cost;
+
+                    let id = self.next_id.next();
+                    new_functions.insert(id, specialize(callee, id, &call.constants));
+                    specializations.insert(key, id);
+                    id
+                }
+            };
+
+            let caller = self.functions.get_mut(&call.caller).unwrap();
+            specialize_call(caller, call.instruction, specialized, &call.constants);
+        }
+
+        self.functions.extend(new_functions);
+        self
+    }
+}
+
+/// Finds each call to a known unconstrained function passing at least one numeric constant.
+fn find_specializable_calls(ssa: &Ssa) -> Vec<SpecializableCall> {
+    let mut calls = Vec::new();
+
+    for (caller, function) in &ssa.functions {
+        let dfg = &function.dfg;
+        let mut blocks: Vec<_> = function.reachable_blocks().into_iter().collect();
+        blocks.sort();
+
+        for block in blocks {
+            for instruction in dfg[block].instructions() {
+                let (func, arguments) = match &dfg[*instruction] {
+                    Instruction::Call { func, arguments } => (*func, arguments),
+                    _ => continue,
+                };
+                let callee = match &dfg[dfg.resolve(func)] {
+                    Value::Function(callee) => *callee,
+                    _ => continue,
+                };
+                if ssa.functions[&callee].runtime() != RuntimeType::Brillig {
+                    continue;
+                }
+
+                let constants: ConstantArguments = arguments
+                    .iter()
+                    .map(|argument| dfg.get_numeric_constant_with_type(*argument))
+                    .collect();
+
+                if constants.iter().any(Option::is_some) {
+                    let caller = *caller;
+                    let instruction = *instruction;
+                    calls.push(SpecializableCall { caller, instruction, callee, constants });
+                }
+            }
+        }
+    }
+    calls
+}
+
+/// The number of instructions within the given function, including terminators
+fn instruction_count(function: &Function) -> usize {
+    let blocks = function.reachable_blocks();
+    blocks.iter().map(|block| function.dfg[*block].instructions().len() + 1).sum()
+}
+
+/// Creates a copy of the given function with the given id, replacing each parameter passed a
+/// constant with that constant and removing it from the copy's parameters.
+fn specialize(function: &Function, id: FunctionId, constants: &ConstantArguments) -> Function {
+    let mut specialized = function.clone_with_id(id);
+    let entry_block = specialized.entry_block();
+    let parameters = specialized.parameters().to_vec();
+    let mut kept_parameters = Vec::with_capacity(parameters.len());
+
+    for (parameter, constant) in parameters.into_iter().zip(constants) {
+        match constant {
+            Some((constant, typ)) => {
+                let constant = specialized.dfg.make_constant(*constant, typ.clone());
+                specialized.dfg.set_value_from_id(parameter, constant);
+            }
+            None => kept_parameters.push(parameter),
+        }
+    }
+
+    specialized.dfg[entry_block].set_parameters(kept_parameters);
+    specialized
+}
+
+/// Replaces the given call within the caller with a call to the specialized function, passing
+/// only the arguments which were not substituted by constants.
+fn specialize_call(
+    caller: &mut Function,
+    instruction: InstructionId,
+    specialized: FunctionId,
+    constants: &ConstantArguments,
+) {
+    let arguments = match &caller.dfg[instruction] {
+        Instruction::Call { arguments, .. } => arguments,
+        _ => unreachable!("Expected a call instruction"),
+    };
+
+    let arguments: Vec<ValueId> = arguments
+        .iter()
+        .zip(constants)
+        .filter(|(_, constant)| constant.is_none())
+        .map(|(argument, _)| *argument)
+        .collect();
+
+    let func = caller.dfg.import_function(specialized);
+    caller.dfg[instruction] = Instruction::Call { func, arguments };
+}
+
+#[cfg(test)]
+mod test {
+    use std::str::FromStr;
+
+    use crate::ssa_refactor::{ir::parser::assert_ssa_equals, ssa_gen::Ssa};
+
+    #[test]
+    fn specializes_calls_with_constant_arguments() {
+        let src = "
+            acir fn main f0 {
+              b0(v0: Field):
+                v1 = call f1(u32 3, v0) -> Field
+                v2 = call f1(u32 3, v1) -> Field
+                v3 = call f1(v1, v2) -> Field
+                return v3
+            }
+            brillig fn foo f1 {
+              b0(v0: u32, v1: Field):
+                v2 = lt v0, u32 4
+                jmpif v2 then: b1, else: b2
+              b1():
+                return v1
+              b2():
+                return Field 0
+            }
+        ";
+        let expected = "
+            acir fn main f0 {
+              b0(v0: Field):
+                v1 = call f2(v0) -> Field
+                v2 = call f2(v1) -> Field
+                v3 = call f1(v1, v2) -> Field
+                return v3
+            }
+            brillig fn foo f1 {
+              b0(v0: u32, v1: Field):
+                v2 = lt v0, u32 4
+                jmpif v2 then: b1, else: b2
+              b1():
+                return v1
+              b2():
+                return Field 0
+            }
+            brillig fn foo f2 {
+              b0(v1: Field):
+                v2 = lt u32 3, u32 4
+                jmpif v2 then: b1, else: b2
+              b1():
+                return v1
+              b2():
+                return Field 0
+            }
+        ";
+        let ssa = Ssa::from_str(src).unwrap().specialize_functions(100);
+        assert_ssa_equals(&ssa, expected);
+    }
+
+    #[test]
+    fn keeps_calls_exceeding_the_budget() {
+        let src = "
+            acir fn main f0 {
+              b0(v0: Field):
+                v1 = call f1(Field 1, v0) -> Field
+                return v1
+            }
+            brillig fn foo f1 {
+              b0(v0: Field, v1: Field):
+                v2 = add v0, v1
+                v3 = mul v2, v2
+                return v3
+            }
+        ";
+        let ssa = Ssa::from_str(src).unwrap().specialize_functions(2);
+        assert_ssa_equals(&ssa, src);
+    }
+}
@@ -0,0 +1,388 @@
+//! Scalar replacement of aggregates (SROA) splits arrays which are only accessed at constant
+//! indices into a separate value for each of their elements.
+//!
+//! Structs and tuples within arrays, and small arrays themselves, are often only accessed at
+//! constant indices. When such an array is passed between blocks, every access to it requires an
+//! `array_get` or `array_set`, and flattening must merge the whole array element by element at
+//! the end of each branch. This pass instead passes each element as a separate block parameter,
+//! so each access becomes a direct use of the element:
+//!
+//! ```text
+//! b1(v0: [Field; 2]):              b1(v3: Field, v4: Field):
+//!   v1 = array_get v0, index 1  =>   v2 = add v4, Field 1
+//!   v2 = add v1, Field 1             jmp b2(v3, v2)
+//!   v3 = array_set v0, index 1,
+//!        value v2
+//!   jmp b2(v3)
+//! ```
+//!
+//! An array is split along with every array it is passed to or from through block parameters
+//! and `array_set`s. These arrays are only split if each of their uses is an `array_get` or
+//! `array_set` at a constant index within bounds, or is passing the array to another split
+//! block parameter. Arrays passed to a split parameter which are not split themselves, such as
+//! constant arrays or function parameters, are split into their elements by `array_get`s
+//! inserted before the jmp passing them.
+use std::collections::{HashMap, HashSet};
+
+use acvm::FieldElement;
+
+use crate::ssa_refactor::{
+    ir::{
+        basic_block::BasicBlockId,
+        dfg::DataFlowGraph,
+        function::Function,
+        instruction::{Instruction, TerminatorInstruction},
+        post_order::PostOrder,
+        types::Type,
+        value::ValueId,
+    },
+    ssa_gen::Ssa,
+};
+
+/// Arrays with more elements than this are never split, to avoid creating too many parameters.
+const MAX_SPLIT_ELEMENTS: usize = 16;
+
+impl Ssa {
+    /// Splits each array within each function which is only accessed at constant indices into
+    /// its individual elements.
+    pub(crate) fn scalar_replacement_of_aggregates(mut self) -> Ssa {
+        for function in self.functions.values_mut() {
+            let split_arrays = find_split_arrays(function);
+            if !split_arrays.is_empty() {
+                split_function_arrays(function, &split_arrays);
+            }
+        }
+        self
+    }
+}
+
+/// Returns the number of elements of the given array type, if it may be split
+fn split_length(typ: &Type) -> Option<usize> {
+    match typ {
+        Type::Array(element_types, length) => {
+            let length = element_types.len() * length;
+            (length <= MAX_SPLIT_ELEMENTS).then_some(length)
+        }
+        _ => None,
+    }
+}
+
+/// Returns the constant index of an array access if it is within the bounds of the array
+fn constant_index(dfg: &DataFlowGraph, array: ValueId, index: ValueId) -> Option<usize> {
+    let length = split_length(&dfg.type_of_value(array))?;
+    let index = dfg.get_numeric_constant(index)?.try_to_u64()? as usize;
+    (index < length).then_some(index)
+}
+
+/// Finds the block parameters and `array_set` results which can be split into their elements.
+fn find_split_arrays(function: &Function) -> HashSet<ValueId> {
+    let dfg = &function.dfg;
+    let blocks = function.reachable_blocks();
+
+    let mut candidates = HashSet::new();
+    for block in &blocks {
+        if *block != function.entry_block() {
+            let parameters = dfg.block_parameters(*block).iter();
+            candidates
+                .extend(parameters.filter(|p| split_length(&dfg.type_of_value(**p)).is_some()));
+        }
+    }
+    if candidates.is_empty() {
+        return candidates;
+    }
+
+    // Each use of a candidate which requires another candidate to be split as well.
+    // A use which rules out splitting the value entirely is instead recorded as None.
+    let mut uses: HashMap<ValueId, Vec<Option<ValueId>>> = HashMap::new();
+    for block in &blocks {
+        for instruction_id in dfg[*block].instructions() {
+            match &dfg[*instruction_id] {
+                Instruction::ArrayGet { array, index } => {
+                    let array = dfg.resolve(*array);
+                    if constant_index(dfg, array, *index).is_none() {
+                        uses.entry(array).or_default().push(None);
+                    }
+                    uses.entry(dfg.resolve(*index)).or_default().push(None);
+                }
+                Instruction::ArraySet { array, index, value } => {
+                    let array = dfg.resolve(*array);
+                    let result = dfg.instruction_results(*instruction_id)[0];
+                    if constant_index(dfg, array, *index).is_some() {
+                        candidates.insert(result);
+                        uses.entry(array).or_default().push(Some(result));
+                    } else {
+                        uses.entry(array).or_default().push(None);
+                    }
+                    uses.entry(dfg.resolve(*index)).or_default().push(None);
+                    uses.entry(dfg.resolve(*value)).or_default().push(None);
+                }
+                instruction => instruction.for_each_value(|value| {
+                    uses.entry(dfg.resolve(value)).or_default().push(None);
+                }),
+            }
+        }
+
+        match dfg[*block].unwrap_terminator() {
+            TerminatorInstruction::Jmp { destination, arguments } => {
+                let parameters = dfg.block_parameters(*destination);
+                for (argument, parameter) in arguments.iter().zip(parameters) {
+                    uses.entry(dfg.resolve(*argument)).or_default().push(Some(*parameter));
+                }
+            }
+            terminator => terminator.for_each_value(|value| {
+                uses.entry(dfg.resolve(value)).or_default().push(None);
+            }),
+        }
+    }
+
+    // Remove each candidate with a use ruling out splitting it, until none remain
+    loop {
+        let is_splittable = |value: &ValueId| {
+            uses.get(value).into_iter().flatten().all(|array_use| match array_use {
+                Some(other) => candidates.contains(other),
+                None => false,
+            })
+        };
+        let unsplittable: Vec<_> =
+            candidates.iter().filter(|v| !is_splittable(v)).copied().collect();
+        if unsplittable.is_empty() {
+            break;
+        }
+        for value in unsplittable {
+            candidates.remove(&value);
+        }
+    }
+
+    // Only split arrays connected to a block parameter. Splitting an `array_set` chain without
+    // one would only replace each `array_set` with an `array_get` of every element.
+    let mut connected: HashSet<ValueId> = candidates
+        .iter()
+        .filter(|value| blocks.iter().any(|block| dfg.block_parameters(*block).contains(*value)))
+        .copied()
+        .collect();
+
+    let mut queue: Vec<_> = connected.iter().copied().collect();
+    while let Some(value) = queue.pop() {
+        // Arrays this value is passed to
+        let mut neighbours: Vec<ValueId> =
+            uses.get(&value).into_iter().flatten().flatten().copied().collect();
+
+        // Arrays passed to this value, which must be split along with it
+        neighbours.extend(candidates.iter().copied().filter(|other| {
+            uses.get(other).into_iter().flatten().any(|array_use| *array_use == Some(value))
+        }));
+
+        for neighbour in neighbours {
+            if candidates.contains(&neighbour) && connected.insert(neighbour) {
+                queue.push(neighbour);
+            }
+        }
+    }
+    connected
+}
+
+/// Replaces each of the given arrays with its elements.
+fn split_function_arrays(function: &mut Function, split_arrays: &HashSet<ValueId>) {
+    let mut context = Context { elements: HashMap::new(), original_parameters: HashMap::new() };
+
+    let mut blocks = PostOrder::with_function(function).as_slice().to_vec();
+    blocks.reverse();
+
+    // Parameters are split first, since their elements may be used by any block jumping to them
+    for block in &blocks {
+        let parameters = function.dfg.block_parameters(*block).to_vec();
+        if parameters.iter().any(|parameter| split_arrays.contains(parameter)) {
+            context.split_parameters(&mut function.dfg, *block, &parameters, split_arrays);
+            context.original_parameters.insert(*block, parameters);
+        }
+    }
+
+    for block in blocks {
+        context.split_block(&mut function.dfg, block, split_arrays);
+    }
+}
+
+struct Context {
+    /// The elements of each split array
+    elements: HashMap<ValueId, Vec<ValueId>>,
+
+    /// The parameters of each block with split parameters, from before they were split
+    original_parameters: HashMap<BasicBlockId, Vec<ValueId>>,
+}
+
+impl Context {
+    /// Replaces each split parameter of the given block with a new parameter for each of its
+    /// elements, added after the block's remaining parameters.
+    fn split_parameters(
+        &mut self,
+        dfg: &mut DataFlowGraph,
+        block: BasicBlockId,
+        parameters: &[ValueId],
+        split_arrays: &HashSet<ValueId>,
+    ) {
+        let kept = parameters.iter().filter(|parameter| !split_arrays.contains(parameter));
+        dfg[block].set_parameters(kept.copied().collect());
+
+        for parameter in parameters.iter().filter(|parameter| split_arrays.contains(parameter)) {
+            let element_types = element_types(&dfg.type_of_value(*parameter));
+            let elements =
+                element_types.into_iter().map(|typ| dfg.add_block_parameter(block, typ)).collect();
+            self.elements.insert(*parameter, elements);
+        }
+    }
+
+    fn split_block(
+        &mut self,
+        dfg: &mut DataFlowGraph,
+        block: BasicBlockId,
+        split_arrays: &HashSet<ValueId>,
+    ) {
+        let instructions = std::mem::take(dfg[block].instructions_mut());
+
+        for instruction_id in instructions {
+            match dfg[instruction_id].clone() {
+                Instruction::ArrayGet { array, index }
+                    if split_arrays.contains(&dfg.resolve(array)) =>
+                {
+                    let elements = &self.elements[&dfg.resolve(array)];
+                    let element = elements[constant_index(dfg, array, index).unwrap()];
+                    let result = dfg.instruction_results(instruction_id)[0];
+                    dfg.set_value_from_id(result, element);
+                }
+                Instruction::ArraySet { array, index, value }
+                    if split_arrays.contains(&dfg.instruction_results(instruction_id)[0]) =>
+                {
+                    let index = constant_index(dfg, array, index).unwrap();
+                    let mut elements = self.elements_of(dfg, block, array);
+                    elements[index] = dfg.resolve(value);
+                    let result = dfg.instruction_results(instruction_id)[0];
+                    self.elements.insert(result, elements);
+                }
+                _ => dfg[block].insert_instruction(instruction_id),
+            }
+        }
+
+        if let TerminatorInstruction::Jmp { destination, arguments } =
+            dfg[block].unwrap_terminator().clone()
+        {
+            if let Some(parameters) = self.original_parameters.get(&destination).cloned() {
+                let mut new_arguments = Vec::with_capacity(arguments.len());
+                let mut split_arguments = Vec::new();
+
+                for (argument, parameter) in arguments.into_iter().zip(parameters) {
+                    if split_arrays.contains(&parameter) {
+                        split_arguments.extend(self.elements_of(dfg, block, argument));
+                    } else {
+                        new_arguments.push(argument);
+                    }
+                }
+
+                new_arguments.extend(split_arguments);
+                let jmp = TerminatorInstruction::Jmp { destination, arguments: new_arguments };
+                dfg[block].set_terminator(jmp);
+            }
+        }
+    }
+
+    /// Returns the elements of the given array. If the array is not split, each element is
+    /// retrieved by an `array_get` inserted at the end of the given block.
+    fn elements_of(
+        &self,
+        dfg: &mut DataFlowGraph,
+        block: BasicBlockId,
+        array: ValueId,
+    ) -> Vec<ValueId> {
+        let array = dfg.resolve(array);
+        if let Some(elements) = self.elements.get(&array) {
+            return elements.clone();
+        }
+
+        let element_types = element_types(&dfg.type_of_value(array));
+        let location = dfg.get_value_location(&array);
+        let element = |(index, typ): (usize, Type)| {
+            let index = dfg.make_constant(FieldElement::from(index as u128), Type::field());
+            let get = Instruction::ArrayGet { array, index };
+            dfg.insert_instruction_and_results(get, block, Some(vec![typ]), location).first()
+        };
+        element_types.into_iter().enumerate().map(element).collect()
+    }
+}
+
+/// Returns the type of each element of the given array type, in the order they are indexed
+fn element_types(typ: &Type) -> Vec<Type> {
+    match typ {
+        Type::Array(element_types, length) => {
+            element_types.iter().cycle().take(element_types.len() * length).cloned().collect()
+        }
+        _ => unreachable!("Expected an array type, found {typ}"),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::str::FromStr;
+
+    use crate::ssa_refactor::{ir::parser::assert_ssa_equals, ssa_gen::Ssa};
+
+    #[test]
+    fn splits_arrays_accessed_at_constant_indices() {
+        let src = "
+            acir fn main f0 {
+              b0(v0: u1, v1: [Field; 2]):
+                jmpif v0 then: b1, else: b2
+              b1():
+                jmp b3([Field; 2] [Field 1, Field 2])
+              b2():
+                jmp b3(v1)
+              b3(v2: [Field; 2]):
+                v3 = array_get v2, index Field 1 -> Field
+                v4 = add v3, Field 1
+                v5 = array_set v2, index Field 0, value v4
+                jmp b4(v5)
+              b4(v6: [Field; 2]):
+                v7 = array_get v6, index Field 0 -> Field
+                return v7
+            }
+        ";
+        let expected = "
+            acir fn main f0 {
+              b0(v0: u1, v1: [Field; 2]):
+                jmpif v0 then: b1, else: b2
+              b1():
+                jmp b3(Field 1, Field 2)
+              b2():
+                v8 = array_get v1, index Field 0 -> Field
+                v9 = array_get v1, index Field 1 -> Field
+                jmp b3(v8, v9)
+              b3(v10: Field, v11: Field):
+                v4 = add v11, Field 1
+                jmp b4(v4, v11)
+              b4(v12: Field, v13: Field):
+                return v12
+            }
+        ";
+        let ssa = Ssa::from_str(src).unwrap().scalar_replacement_of_aggregates();
+        assert_ssa_equals(&ssa, expected);
+    }
+
+    #[test]
+    fn keeps_arrays_accessed_at_dynamic_indices() {
+        let src = "
+            acir fn main f0 {
+              b0(v0: u1, v1: [Field; 2], v2: u32):
+                jmpif v0 then: b1, else: b2
+              b1():
+                jmp b3([Field; 2] [Field 1, Field 2])
+              b2():
+                jmp b3(v1)
+              b3(v3: [Field; 2]):
+                v4 = array_get v3, index Field 0 -> Field
+                v5 = array_get v3, index v2 -> Field
+                v6 = add v4, v5
+                return v6
+            }
+        ";
+        let ssa = Ssa::from_str(src).unwrap().scalar_replacement_of_aggregates();
+        assert_ssa_equals(&ssa, src);
+    }
+}
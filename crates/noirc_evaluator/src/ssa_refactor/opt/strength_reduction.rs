@@ -0,0 +1,267 @@
+//! Strength reduction rewrites expensive operations into equivalent, cheaper ones:
+//! - In brillig functions, multiplying or dividing an unsigned integer by a constant `2^k`
+//!   becomes a left or right shift by `k`.
+//! - In ACIR functions, a left shift by a constant `k` smaller than the bit size of the integer
+//!   becomes a multiplication by `2^k`, which is a linear term in ACIR rather than a shift.
+//! - Dividing a field element by a constant `c` becomes a multiplication by the inverse of `c`.
+//! - A chain of field additions which repeatedly adds the same value, as left behind by unrolling
+//!   a loop such as `for _ in 0..n { sum += x; }`, becomes a single addition of `x * n`.
+//!
+//! The additions skipped over by rewriting a chain are left for dead instruction elimination
+//! to remove.
+use std::collections::HashMap;
+
+use acvm::FieldElement;
+
+use crate::ssa_refactor::{
+    ir::{
+        basic_block::BasicBlockId,
+        dfg::DataFlowGraph,
+        function::{Function, RuntimeType},
+        instruction::{Binary, BinaryOp, Instruction},
+        types::{NumericType, Type},
+        value::ValueId,
+    },
+    ssa_gen::Ssa,
+};
+
+impl Ssa {
+    /// Replaces expensive operations within each function with cheaper equivalents.
+    pub(crate) fn strength_reduction(mut self) -> Ssa {
+        for function in self.functions.values_mut() {
+            let use_counts = function.use_counts();
+            for block in function.reachable_blocks() {
+                reduce_block(function, block, &use_counts);
+            }
+        }
+        self
+    }
+}
+
+/// A field value equal to `base + term * count`, which was computed by a chain of `count`
+/// additions of `term` onto `base`.
+#[derive(Debug, Copy, Clone)]
+struct RepeatedAddition {
+    base: ValueId,
+    term: ValueId,
+    count: u128,
+}
+
+fn reduce_block(
+    function: &mut Function,
+    block: BasicBlockId,
+    use_counts: &HashMap<ValueId, usize>,
+) {
+    let runtime = function.runtime();
+    let dfg = &mut function.dfg;
+    let instructions = std::mem::take(dfg[block].instructions_mut());
+    let mut new_instructions = Vec::with_capacity(instructions.len());
+    let mut additions: HashMap<ValueId, RepeatedAddition> = HashMap::new();
+
+    for instruction in instructions {
+        if let Instruction::Binary(binary) = &dfg[instruction] {
+            let binary = binary.clone();
+            let result = dfg.instruction_results(instruction)[0];
+
+            if binary.operator == BinaryOp::Add && is_field(dfg, binary.lhs) {
+                let addition = extend_repeated_addition(dfg, &additions, &binary, use_counts);
+                if addition.count > 1 {
+                    let count =
+                        dfg.make_constant(FieldElement::from(addition.count), Type::field());
+                    let mul = Binary { lhs: addition.term, rhs: count, operator: BinaryOp::Mul };
+                    let mul = dfg.make_instruction(Instruction::Binary(mul), None);
+                    new_instructions.push(mul);
+
+                    let product = dfg.instruction_results(mul)[0];
+                    let add = Binary { lhs: addition.base, rhs: product, operator: BinaryOp::Add };
+                    dfg[instruction] = Instruction::Binary(add);
+                }
+                additions.insert(result, addition);
+            } else if let Some(reduced) = reduce_binary(dfg, &binary, runtime) {
+                dfg[instruction] = reduced;
+            }
+        }
+        new_instructions.push(instruction);
+    }
+
+    *dfg[block].instructions_mut() = new_instructions;
+}
+
+/// Returns the repeated addition computed by the given addition. If one operand is itself a
+/// repeated addition of the other operand which is used only by this addition, the chain is
+/// extended by one instead of starting a new chain.
+fn extend_repeated_addition(
+    dfg: &DataFlowGraph,
+    additions: &HashMap<ValueId, RepeatedAddition>,
+    binary: &Binary,
+    use_counts: &HashMap<ValueId, usize>,
+) -> RepeatedAddition {
+    let lhs = dfg.resolve(binary.lhs);
+    let rhs = dfg.resolve(binary.rhs);
+
+    for (previous, term) in [(lhs, rhs), (rhs, lhs)] {
+        if let Some(addition) = additions.get(&previous) {
+            if addition.term == term && use_counts.get(&previous) == Some(&1) {
+                return RepeatedAddition { count: addition.count + 1, ..*addition };
+            }
+        }
+    }
+    RepeatedAddition { base: lhs, term: rhs, count: 1 }
+}
+
+/// Returns a cheaper instruction equivalent to the given binary instruction, if there is one.
+fn reduce_binary(
+    dfg: &mut DataFlowGraph,
+    binary: &Binary,
+    runtime: RuntimeType,
+) -> Option<Instruction> {
+    let typ = dfg.type_of_value(binary.lhs);
+    let lhs_constant = dfg.get_numeric_constant(binary.lhs);
+    let rhs_constant = dfg.get_numeric_constant(binary.rhs);
+
+    let reduced = match (binary.operator, runtime) {
+        (BinaryOp::Mul, RuntimeType::Brillig) if is_unsigned(&typ) => {
+            let (value, power) = match (power_of_two(rhs_constant), power_of_two(lhs_constant)) {
+                (Some(power), _) => (binary.lhs, power),
+                (None, Some(power)) => (binary.rhs, power),
+                (None, None) => return None,
+            };
+            let power = dfg.make_constant(power.into(), typ);
+            Binary { lhs: value, rhs: power, operator: BinaryOp::Shl }
+        }
+        (BinaryOp::Div, RuntimeType::Brillig) if is_unsigned(&typ) => {
+            let power = power_of_two(rhs_constant)?;
+            let power = dfg.make_constant(power.into(), typ);
+            Binary { lhs: binary.lhs, rhs: power, operator: BinaryOp::Shr }
+        }
+        (BinaryOp::Shl, RuntimeType::Acir) if is_unsigned(&typ) => {
+            // Shifting by at least the bit size leaves no bits, whereas multiplying by a factor
+            // which does not fit within the integer would not wrap around to zero
+            let exponent = rhs_constant.filter(|exponent| exponent_fits(*exponent, &typ))?;
+            let factor = FieldElement::from(2_u128).pow(&exponent);
+            let factor = dfg.make_constant(factor, typ);
+            Binary { lhs: binary.lhs, rhs: factor, operator: BinaryOp::Mul }
+        }
+        (BinaryOp::Div, _) if typ == Type::field() => {
+            let divisor = rhs_constant.filter(|divisor| !divisor.is_zero())?;
+            let inverse = dfg.make_constant(divisor.inverse(), typ);
+            Binary { lhs: binary.lhs, rhs: inverse, operator: BinaryOp::Mul }
+        }
+        _ => return None,
+    };
+    Some(Instruction::Binary(reduced))
+}
+
+/// Returns `k` if the given constant is equal to `2^k`
+fn power_of_two(constant: Option<FieldElement>) -> Option<u128> {
+    let constant = constant?;
+    if constant.num_bits() > 128 {
+        return None;
+    }
+    let constant = constant.to_u128();
+    constant.is_power_of_two().then(|| constant.trailing_zeros().into())
+}
+
+fn is_field(dfg: &DataFlowGraph, value: ValueId) -> bool {
+    dfg.type_of_value(value) == Type::field()
+}
+
+fn is_unsigned(typ: &Type) -> bool {
+    matches!(typ, Type::Numeric(NumericType::Unsigned { .. }))
+}
+
+/// Returns true if `2^exponent` fits within an unsigned integer of the given type
+fn exponent_fits(exponent: FieldElement, typ: &Type) -> bool {
+    match typ {
+        Type::Numeric(NumericType::Unsigned { bit_size }) => {
+            exponent.num_bits() <= 32 && exponent.to_u128() < u128::from(*bit_size)
+        }
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::str::FromStr;
+
+    use crate::ssa_refactor::{ir::parser::assert_ssa_equals, ssa_gen::Ssa};
+
+    #[test]
+    fn reduces_brillig_operations() {
+        let src = "
+            brillig fn main f0 {
+              b0(v0: u32, v1: Field):
+                v2 = mul v0, u32 8
+                v3 = div v2, u32 4
+                v4 = mul v0, u32 3
+                v5 = div v1, Field 2
+                return v3, v4, v5
+            }
+        ";
+        let expected = "
+            brillig fn main f0 {
+              b0(v0: u32, v1: Field):
+                v2 = shl v0, u32 3
+                v3 = shr v2, u32 2
+                v4 = mul v0, u32 3
+                v5 = mul v1, Field 10944121435919637611123202872628637544274182200208017171849102093287904247809
+                return v3, v4, v5
+            }
+        ";
+        let ssa = Ssa::from_str(src).unwrap().strength_reduction();
+        assert_ssa_equals(&ssa, expected);
+    }
+
+    #[test]
+    fn reduces_acir_shifts() {
+        let src = "
+            acir fn main f0 {
+              b0(v0: u32):
+                v1 = shl v0, u32 4
+                v2 = mul v0, u32 8
+                v3 = shl v0, u32 32
+                v4 = shl v0, u32 200
+                return v1, v2, v3, v4
+            }
+        ";
+        // Shifts by at least the bit size overflow to zero, unlike multiplications by `2^k`
+        let expected = "
+            acir fn main f0 {
+              b0(v0: u32):
+                v1 = mul v0, u32 16
+                v2 = mul v0, u32 8
+                v3 = shl v0, u32 32
+                v4 = shl v0, u32 200
+                return v1, v2, v3, v4
+            }
+        ";
+        let ssa = Ssa::from_str(src).unwrap().strength_reduction();
+        assert_ssa_equals(&ssa, expected);
+    }
+
+    #[test]
+    fn reduces_repeated_additions() {
+        let src = "
+            acir fn main f0 {
+              b0(v0: Field, v1: Field):
+                v2 = add v0, v1
+                v3 = add v2, v1
+                v4 = add v1, v3
+                return v4
+            }
+        ";
+        let expected = "
+            acir fn main f0 {
+              b0(v0: Field, v1: Field):
+                v2 = add v0, v1
+                v3 = mul v1, Field 2
+                v4 = add v0, v3
+                v5 = mul v1, Field 3
+                v6 = add v0, v5
+                return v6
+            }
+        ";
+        let ssa = Ssa::from_str(src).unwrap().strength_reduction();
+        assert_ssa_equals(&ssa, expected);
+    }
+}
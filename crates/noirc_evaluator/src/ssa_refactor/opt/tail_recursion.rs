@@ -0,0 +1,191 @@
+//! Tail recursion elimination converts each call an unconstrained function makes to itself in
+//! tail position into a jump back to the start of the function.
+//!
+//! A call is in tail position if it is the last instruction of its block and the block returns
+//! exactly the results of the call. Brillig generates unconstrained functions as-is rather than
+//! inlining them, so each recursive call otherwise creates a new call frame at runtime.
+//!
+//! The contents of the entry block are moved into a new loop header block taking the same
+//! parameters as the function, and the entry block jumps to it with the function's parameters.
+//! Each tail call is then replaced with a jump to the loop header passing the call's arguments.
+use std::collections::HashMap;
+
+use crate::ssa_refactor::{
+    ir::{
+        basic_block::BasicBlockId,
+        dfg::DataFlowGraph,
+        function::{Function, RuntimeType},
+        instruction::{Instruction, TerminatorInstruction},
+        value::{Value, ValueId},
+    },
+    ssa_gen::Ssa,
+};
+
+impl Ssa {
+    /// Replaces each self tail call within each unconstrained function with a loop.
+    pub(crate) fn eliminate_tail_recursion(mut self) -> Ssa {
+        for function in self.functions.values_mut() {
+            if function.runtime() == RuntimeType::Brillig {
+                eliminate_tail_recursion(function);
+            }
+        }
+        self
+    }
+}
+
+fn eliminate_tail_recursion(function: &mut Function) {
+    let mut blocks: Vec<_> = function.reachable_blocks().into_iter().collect();
+    blocks.sort();
+    blocks.retain(|block| tail_call_arguments(function, *block).is_some());
+
+    if blocks.is_empty() {
+        return;
+    }
+
+    let entry_block = function.entry_block();
+    let loop_header = function.dfg.make_block_with_parameters_from_block(entry_block);
+    function.dfg.inline_block(entry_block, loop_header);
+
+    let parameters = function.parameters().to_vec();
+    let header_parameters = function.dfg.block_parameters(loop_header).to_vec();
+    let parameter_map: HashMap<_, _> = parameters.iter().copied().zip(header_parameters).collect();
+
+    let enter_loop = TerminatorInstruction::Jmp { destination: loop_header, arguments: parameters };
+    function.dfg[entry_block].set_terminator(enter_loop);
+
+    // Every other use of a parameter of the function now refers to the loop header's parameters
+    for block in function.reachable_blocks() {
+        if block != entry_block {
+            map_block_values(&mut function.dfg, block, &parameter_map);
+        }
+    }
+
+    for block in blocks {
+        // The entry block's contents, including any tail call, are now within the loop header
+        let block = if block == entry_block { loop_header } else { block };
+
+        let arguments = tail_call_arguments(function, block)
+            .expect("ICE: Tail calls should remain after moving the entry block");
+
+        function.dfg[block].instructions_mut().pop();
+        let jmp = TerminatorInstruction::Jmp { destination: loop_header, arguments };
+        function.dfg[block].set_terminator(jmp);
+    }
+}
+
+/// Returns the arguments of the call the given block ends with, if the call is a tail call from
+/// the function to itself.
+fn tail_call_arguments(function: &Function, block: BasicBlockId) -> Option<Vec<ValueId>> {
+    let dfg = &function.dfg;
+    let call = *dfg[block].instructions().last()?;
+
+    let arguments = match &dfg[call] {
+        Instruction::Call { func, arguments } => match &dfg[dfg.resolve(*func)] {
+            Value::Function(id) if *id == function.id() => arguments,
+            _ => return None,
+        },
+        _ => return None,
+    };
+
+    let return_values = match dfg[block].terminator() {
+        Some(TerminatorInstruction::Return { return_values }) => return_values,
+        _ => return None,
+    };
+
+    let results = dfg.instruction_results(call);
+    let returns_results = return_values.len() == results.len()
+        && return_values.iter().zip(results).all(|(value, result)| dfg.resolve(*value) == *result);
+
+    returns_results.then(|| arguments.clone())
+}
+
+/// Replaces each use of a value within the given map within the block's instructions and
+/// terminator.
+fn map_block_values(
+    dfg: &mut DataFlowGraph,
+    block: BasicBlockId,
+    value_map: &HashMap<ValueId, ValueId>,
+) {
+    let map_value = |value: ValueId| {
+        let value = dfg.resolve(value);
+        value_map.get(&value).copied().unwrap_or(value)
+    };
+
+    let instructions = dfg[block].instructions().to_vec();
+    let mapped_instructions = instructions
+        .iter()
+        .map(|instruction| dfg[*instruction].map_values(map_value))
+        .collect::<Vec<_>>();
+    let terminator = dfg[block].terminator().map(|terminator| terminator.map_values(map_value));
+
+    for (instruction, mapped) in instructions.into_iter().zip(mapped_instructions) {
+        dfg[instruction] = mapped;
+    }
+    if let Some(terminator) = terminator {
+        dfg[block].set_terminator(terminator);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::str::FromStr;
+
+    use crate::ssa_refactor::{ir::parser::assert_ssa_equals, ssa_gen::Ssa};
+
+    #[test]
+    fn converts_tail_calls_into_loops() {
+        let src = "
+            brillig fn sum f0 {
+              b0(v0: u32, v1: Field):
+                v2 = eq v0, u32 0
+                jmpif v2 then: b1, else: b2
+              b1():
+                return v1
+              b2():
+                v3 = sub v0, u32 1
+                v4 = cast v0 as Field
+                v5 = add v1, v4
+                v6 = call f0(v3, v5) -> Field
+                return v6
+            }
+        ";
+        let expected = "
+            brillig fn sum f0 {
+              b0(v0: u32, v1: Field):
+                jmp b3(v0, v1)
+              b3(v7: u32, v8: Field):
+                v2 = eq v7, u32 0
+                jmpif v2 then: b1, else: b2
+              b1():
+                return v8
+              b2():
+                v3 = sub v7, u32 1
+                v4 = cast v7 as Field
+                v5 = add v8, v4
+                jmp b3(v3, v5)
+            }
+        ";
+        let ssa = Ssa::from_str(src).unwrap().eliminate_tail_recursion();
+        assert_ssa_equals(&ssa, expected);
+    }
+
+    #[test]
+    fn keeps_calls_not_in_tail_position() {
+        let src = "
+            brillig fn factorial f0 {
+              b0(v0: Field):
+                v1 = eq v0, Field 0
+                jmpif v1 then: b1, else: b2
+              b1():
+                return Field 1
+              b2():
+                v2 = sub v0, Field 1
+                v3 = call f0(v2) -> Field
+                v4 = mul v0, v3
+                return v4
+            }
+        ";
+        let ssa = Ssa::from_str(src).unwrap().eliminate_tail_recursion();
+        assert_ssa_equals(&ssa, src);
+    }
+}
@@ -0,0 +1,133 @@
+//! Unreachable function removal drops each function which can no longer be reached from main.
+//!
+//! Simplifications such as constant propagation and jump threading may remove every call to a
+//! function. Such a function would otherwise still have brillig bytecode generated for it, or be
+//! kept alongside the inlined main function.
+//!
+//! A function is reachable if main refers to it from one of its reachable blocks, or if another
+//! reachable function does. Any reference counts, not only direct calls, since a function used as
+//! a value before defunctionalization may still be called through that value.
+use std::collections::BTreeSet;
+
+use crate::ssa_refactor::{
+    ir::{
+        dfg::DataFlowGraph,
+        function::{Function, FunctionId},
+        value::{Value, ValueId},
+    },
+    ssa_gen::Ssa,
+};
+
+impl Ssa {
+    /// Removes each function which is not reachable from the main function.
+    pub(crate) fn remove_unreachable_functions(mut self) -> Ssa {
+        let mut reachable = BTreeSet::from([self.main_id]);
+        let mut queue = vec![self.main_id];
+
+        while let Some(function) = queue.pop() {
+            for referenced in referenced_functions(&self.functions[&function]) {
+                if self.functions.contains_key(&referenced) && reachable.insert(referenced) {
+                    queue.push(referenced);
+                }
+            }
+        }
+
+        self.functions.retain(|id, _| reachable.contains(id));
+        self
+    }
+}
+
+/// Returns each function referred to by an instruction or terminator within the reachable blocks
+/// of the given function.
+fn referenced_functions(function: &Function) -> BTreeSet<FunctionId> {
+    let dfg = &function.dfg;
+    let mut functions = BTreeSet::new();
+    let mut collect = |value| collect_functions(dfg, value, &mut functions);
+
+    for block in function.reachable_blocks() {
+        for instruction in dfg[block].instructions() {
+            dfg[*instruction].for_each_value(&mut collect);
+        }
+        if let Some(terminator) = dfg[block].terminator() {
+            terminator.for_each_value(&mut collect);
+        }
+    }
+    functions
+}
+
+/// Collects the function the given value refers to, or each function referred to within it if it
+/// is a constant array.
+fn collect_functions(dfg: &DataFlowGraph, value: ValueId, functions: &mut BTreeSet<FunctionId>) {
+    match &dfg[dfg.resolve(value)] {
+        Value::Function(function) => {
+            functions.insert(*function);
+        }
+        Value::Array { array, .. } => {
+            for element in array {
+                collect_functions(dfg, *element, functions);
+            }
+        }
+        _ => (),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::str::FromStr;
+
+    use crate::ssa_refactor::{ir::parser::assert_ssa_equals, ssa_gen::Ssa};
+
+    #[test]
+    fn removes_unreachable_functions() {
+        // f2 is only called from an unreachable block of main, and f3 only from f2.
+        // f4 is never called, but main passes it as a value to f1.
+        let src = "
+            acir fn main f0 {
+              b0(v0: Field):
+                v1 = call f1(v0, f4) -> Field
+                return v1
+              b1():
+                v2 = call f2(Field 1) -> Field
+                return v2
+            }
+            brillig fn apply f1 {
+              b0(v0: Field, v1: function):
+                v2 = call v1(v0) -> Field
+                return v2
+            }
+            brillig fn unused f2 {
+              b0(v0: Field):
+                v1 = call f3(v0) -> Field
+                return v1
+            }
+            brillig fn also_unused f3 {
+              b0(v0: Field):
+                return v0
+            }
+            brillig fn double f4 {
+              b0(v0: Field):
+                v1 = add v0, v0
+                return v1
+            }
+        ";
+        let expected = "
+            acir fn main f0 {
+              b0(v0: Field):
+                v1 = call f1(v0, f4) -> Field
+                return v1
+            }
+            brillig fn apply f1 {
+              b0(v0: Field, v1: function):
+                v2 = call v1(v0) -> Field
+                return v2
+            }
+            brillig fn double f4 {
+              b0(v0: Field):
+                v1 = add v0, v0
+                return v1
+            }
+        ";
+        let ssa = Ssa::from_str(src).unwrap().remove_unreachable_functions();
+        assert_ssa_equals(&ssa, expected);
+    }
+}
@@ -6,21 +6,36 @@
 //!    a. If the loop is in our list of loops that previously failed to unroll, skip it.
 //!    b. If we have previously modified any of the blocks in the loop,
 //!       restart from step 1 to refresh the context.
-//!    c. If not, try to unroll the loop. If successful, remember the modified
+//!    c. If unrolling the loop would exceed the budget of unrolled instructions, remember
+//!       that the loop failed to unroll and leave it unmodified.
+//!    d. If not, try to unroll the loop. If successful, remember the modified
 //!       blocks. If not, remember that the loop failed to unroll and leave it
 //!       unmodified.
+//! 3. If any loop of an ACIR main function failed to unroll, report an error, since ACIR cannot
+//!    represent loops. Innermost loops of brillig functions which failed to unroll may instead be
+//!    partially unrolled, which does not require their bounds to be known.
 //!
 //! Note that this pass also often creates superfluous jmp instructions in the
 //! program that will need to be removed by a later simplify cfg pass.
 use std::collections::{HashMap, HashSet};
 
-use crate::ssa_refactor::{
-    ir::{
-        basic_block::BasicBlockId, cfg::ControlFlowGraph, dfg::DataFlowGraph, dom::DominatorTree,
-        function::Function, function_inserter::FunctionInserter,
-        instruction::TerminatorInstruction, post_order::PostOrder, value::ValueId,
+use crate::{
+    errors::{RuntimeError, RuntimeErrorKind},
+    ssa_refactor::{
+        ir::{
+            basic_block::BasicBlockId,
+            cfg::ControlFlowGraph,
+            dfg::DataFlowGraph,
+            dom::DominatorTree,
+            function::{Function, RuntimeType},
+            function_inserter::FunctionInserter,
+            instruction::{Binary, BinaryOp, Instruction, TerminatorInstruction},
+            loops::{Loop, LoopForest},
+            post_order::PostOrder,
+            value::{Value, ValueId},
+        },
+        ssa_gen::Ssa,
     },
-    ssa_gen::Ssa,
 };
 
 impl Ssa {
@@ -28,125 +43,352 @@ impl Ssa {
     /// If any loop cannot be unrolled, it is left as-is or in a partially unrolled state.
     pub(crate) fn unroll_loops(mut self) -> Ssa {
         for function in self.functions.values_mut() {
-            find_all_loops(function).unroll_each_loop(function);
+            find_all_loops(function).unroll_each_loop(function, None);
         }
         self
     }
+
+    /// Unroll all loops in each SSA function, unrolling at most `max_unrolled_instructions`
+    /// instructions within each function if a maximum is given.
+    ///
+    /// Innermost loops of brillig functions which cannot be fully unrolled are instead partially
+    /// unrolled into `unroll_factor` copies of their body. Loops of other functions which cannot
+    /// be unrolled are left as-is, except within an ACIR main function where this is an error.
+    pub(crate) fn try_unroll_loops(
+        mut self,
+        unroll_factor: usize,
+        max_unrolled_instructions: Option<usize>,
+    ) -> Result<Ssa, RuntimeError> {
+        let main_id = self.main_id;
+
+        for function in self.functions.values_mut() {
+            let failures =
+                find_all_loops(function).unroll_each_loop(function, max_unrolled_instructions);
+
+            match function.runtime() {
+                RuntimeType::Acir if function.id() == main_id => {
+                    if let Some((header, failure)) =
+                        failures.iter().min_by_key(|(header, _)| **header)
+                    {
+                        return Err(failure.to_error(function, *header));
+                    }
+                }
+                RuntimeType::Acir => (),
+                RuntimeType::Brillig if unroll_factor > 1 => {
+                    partially_unroll_loops(function, &failures, unroll_factor);
+                }
+                RuntimeType::Brillig => (),
+            }
+        }
+        Ok(self)
+    }
+}
+
+/// The reason a loop could not be fully unrolled
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum UnrollFailure {
+    /// The start of the loop's range could not be determined to be constant
+    UnknownStart,
+
+    /// The end of the loop's range could not be determined to be constant
+    UnknownEnd,
+
+    /// Unrolling the loop would exceed the maximum number of unrolled instructions
+    ExceedsBudget { max_instructions: usize },
+
+    /// The loop has no induction variable, as in a `while` loop, so its number of iterations
+    /// depends on values only known at runtime
+    NoInductionVariable,
 }
 
-struct Loop {
-    /// The header block of a loop is the block which dominates all the
-    /// other blocks in the loop.
-    header: BasicBlockId,
+impl UnrollFailure {
+    fn to_error(self, function: &Function, header: BasicBlockId) -> RuntimeError {
+        let kind = match self {
+            UnrollFailure::UnknownStart => RuntimeErrorKind::UnknownLoopBound { bound: "start" },
+            UnrollFailure::UnknownEnd => RuntimeErrorKind::UnknownLoopBound { bound: "end" },
+            UnrollFailure::ExceedsBudget { max_instructions } => {
+                RuntimeErrorKind::UnrollLimitExceeded { max_instructions }
+            }
+            UnrollFailure::NoInductionVariable => RuntimeErrorKind::UnboundedLoop,
+        };
 
-    /// The start of the back_edge n -> d is the block n at the end of
-    /// the loop that jumps back to the header block d which restarts the loop.
-    back_edge_start: BasicBlockId,
+        let location = function.dfg[header]
+            .instructions()
+            .iter()
+            .find_map(|instruction| function.dfg.get_location(instruction));
 
-    /// All the blocks contained within the loop, including `header` and `back_edge_start`.
-    blocks: HashSet<BasicBlockId>,
+        RuntimeError::new(kind, location)
+    }
 }
 
 struct Loops {
     /// The loops that failed to be unrolled so that we do not try to unroll them again.
     /// Each loop is identified by its header block id.
-    failed_to_unroll: HashSet<BasicBlockId>,
+    failed_to_unroll: HashMap<BasicBlockId, UnrollFailure>,
 
     yet_to_unroll: Vec<Loop>,
     modified_blocks: HashSet<BasicBlockId>,
     cfg: ControlFlowGraph,
     dom_tree: DominatorTree,
+
+    /// The estimated number of instructions created by unrolling loops so far
+    unrolled_instructions: usize,
 }
 
-/// Find a loop in the program by finding a node that dominates any predecessor node.
+/// Find each loop in the program by finding a node that dominates any predecessor node.
 /// The edge where this happens will be the back-edge of the loop.
 fn find_all_loops(function: &Function) -> Loops {
     let cfg = ControlFlowGraph::with_function(function);
     let post_order = PostOrder::with_function(function);
     let mut dom_tree = DominatorTree::with_cfg_and_post_order(&cfg, &post_order);
 
-    let mut loops = vec![];
-
-    for (block, _) in function.dfg.basic_blocks_iter() {
-        // These reachable checks wouldn't be needed if we only iterated over reachable blocks
-        if dom_tree.is_reachable(block) {
-            for predecessor in cfg.predecessors(block) {
-                if dom_tree.is_reachable(predecessor) && dom_tree.dominates(block, predecessor) {
-                    // predecessor -> block is the back-edge of a loop
-                    loops.push(find_blocks_in_loop(block, predecessor, &cfg));
-                }
-            }
-        }
-    }
-
-    // Sort loops by block size so that we unroll the larger, outer loops of nested loops first.
-    // This is needed because inner loops may use the induction variable from their outer loops in
-    // their loop range.
-    loops.sort_by_key(|loop_| loop_.blocks.len());
+    // Loops are sorted from innermost to outermost. Since loops are popped from the end of this
+    // list, we unroll the larger, outer loops of nested loops first. This is needed because inner
+    // loops may use the induction variable from their outer loops in their loop range.
+    let loops = LoopForest::with_cfg_and_dom_tree(&cfg, &post_order, &mut dom_tree).into_loops();
 
     Loops {
-        failed_to_unroll: HashSet::new(),
+        failed_to_unroll: HashMap::new(),
         yet_to_unroll: loops,
         modified_blocks: HashSet::new(),
         cfg,
         dom_tree,
+        unrolled_instructions: 0,
     }
 }
 
 impl Loops {
-    /// Unroll all loops within a given function.
-    /// Any loops which fail to be unrolled (due to using non-constant indices) will be unmodified.
-    fn unroll_each_loop(mut self, function: &mut Function) {
+    /// Unroll all loops within a given function, stopping once `max_unrolled_instructions`
+    /// instructions have been unrolled if a maximum is given.
+    /// Any loops which fail to be unrolled (due to using non-constant indices or exceeding the
+    /// maximum) will be unmodified. These are returned along with the reason each failed.
+    fn unroll_each_loop(
+        mut self,
+        function: &mut Function,
+        max_unrolled_instructions: Option<usize>,
+    ) -> HashMap<BasicBlockId, UnrollFailure> {
         while let Some(next_loop) = self.yet_to_unroll.pop() {
             // If we've previously modified a block in this loop we need to refresh the context.
             // This happens any time we have nested loops.
             if next_loop.blocks.iter().any(|block| self.modified_blocks.contains(block)) {
                 let mut new_context = find_all_loops(function);
                 new_context.failed_to_unroll = self.failed_to_unroll;
-                return new_context.unroll_each_loop(function);
+                new_context.unrolled_instructions = self.unrolled_instructions;
+                return new_context.unroll_each_loop(function, max_unrolled_instructions);
             }
 
             // Don't try to unroll the loop again if it is known to fail
-            if !self.failed_to_unroll.contains(&next_loop.header) {
-                if unroll_loop(function, &self.cfg, &next_loop).is_ok() {
-                    self.modified_blocks.extend(next_loop.blocks);
-                } else {
-                    self.failed_to_unroll.insert(next_loop.header);
+            if self.failed_to_unroll.contains_key(&next_loop.header) {
+                continue;
+            }
+
+            let instructions = estimate_unrolled_instructions(function, &self.cfg, &next_loop);
+            let unrolled_instructions = self.unrolled_instructions.saturating_add(instructions);
+
+            if let Some(max_instructions) = max_unrolled_instructions {
+                if unrolled_instructions > max_instructions {
+                    let failure = UnrollFailure::ExceedsBudget { max_instructions };
+                    self.failed_to_unroll.insert(next_loop.header, failure);
+                    continue;
                 }
             }
+
+            if unroll_loop(function, &self.cfg, &next_loop).is_ok() {
+                self.modified_blocks.extend(next_loop.blocks);
+                self.unrolled_instructions = unrolled_instructions;
+            } else {
+                let pre_header = get_pre_header(&self.cfg, &next_loop);
+                let failure = if function.dfg.block_parameters(next_loop.header).len() != 1 {
+                    UnrollFailure::NoInductionVariable
+                } else if get_induction_variable(function, pre_header).is_err() {
+                    UnrollFailure::UnknownStart
+                } else {
+                    UnrollFailure::UnknownEnd
+                };
+                self.failed_to_unroll.insert(next_loop.header, failure);
+            }
         }
+        self.failed_to_unroll
     }
 }
 
-/// Return each block that is in a loop starting in the given header block.
-/// Expects back_edge_start -> header to be the back edge of the loop.
-fn find_blocks_in_loop(
-    header: BasicBlockId,
-    back_edge_start: BasicBlockId,
+/// Estimates the number of instructions a loop will be unrolled into, assuming it is a loop of
+/// the form `for i in start..end` where `start` and `end` are constants.
+///
+/// Returns 0 if the number of iterations cannot be determined. Note that this only counts the
+/// instructions of nested loops once since their bounds may only be known after this loop is
+/// unrolled. They are counted separately when they are unrolled later.
+fn estimate_unrolled_instructions(
+    function: &Function,
     cfg: &ControlFlowGraph,
-) -> Loop {
-    let mut blocks = HashSet::new();
-    blocks.insert(header);
-
-    let mut insert = |block, stack: &mut Vec<BasicBlockId>| {
-        if !blocks.contains(&block) {
-            blocks.insert(block);
-            stack.push(block);
-        }
+    loop_: &Loop,
+) -> usize {
+    let iterations = constant_trip_count(function, cfg, loop_).unwrap_or_default();
+    let instructions: usize =
+        loop_.blocks.iter().map(|block| function.dfg[*block].instructions().len()).sum();
+    usize::try_from(iterations).unwrap_or(usize::MAX).saturating_mul(instructions)
+}
+
+/// Returns the number of iterations of a loop whose induction variable starts at a constant
+/// and whose header compares the induction variable against a constant bound.
+fn constant_trip_count(function: &Function, cfg: &ControlFlowGraph, loop_: &Loop) -> Option<u128> {
+    let dfg = &function.dfg;
+    let start = get_induction_variable(function, get_pre_header(cfg, loop_)).ok()?;
+    let start = dfg.get_numeric_constant(start)?;
+
+    let induction_variable = *dfg.block_parameters(loop_.header).first()?;
+    let condition = match dfg[loop_.header].terminator()? {
+        TerminatorInstruction::JmpIf { condition, .. } => *condition,
+        _ => return None,
+    };
+
+    let end = match &dfg[dfg.resolve(condition)] {
+        Value::Instruction { instruction, .. } => match &dfg[*instruction] {
+            Instruction::Binary(Binary { lhs, rhs, operator: BinaryOp::Lt })
+                if *lhs == induction_variable =>
+            {
+                dfg.get_numeric_constant(*rhs)?
+            }
+            _ => return None,
+        },
+        _ => return None,
     };
 
-    // Starting from the back edge of the loop, each predecessor of this block until
-    // the header is within the loop.
-    let mut stack = vec![];
-    insert(back_edge_start, &mut stack);
+    Some(end.to_u128().saturating_sub(start.to_u128()))
+}
 
-    while let Some(block) = stack.pop() {
-        for predecessor in cfg.predecessors(block) {
-            insert(predecessor, &mut stack);
+/// Partially unrolls each innermost loop of the function which is in the given list of failures.
+/// Loops which cannot be partially unrolled are left unmodified.
+fn partially_unroll_loops(
+    function: &mut Function,
+    failures: &HashMap<BasicBlockId, UnrollFailure>,
+    unroll_factor: usize,
+) {
+    let forest = LoopForest::with_function(function);
+    let loops = forest.loops();
+
+    for (id, loop_) in loops.iter().enumerate() {
+        let is_innermost = !loops.iter().any(|other| other.parent == Some(id));
+        if is_innermost && failures.contains_key(&loop_.header) {
+            // Innermost loops are disjoint so partially unrolling one never modifies another
+            let _ = partially_unroll_loop(function, loop_, unroll_factor);
         }
     }
+}
+
+/// Partially unroll a loop by placing `unroll_factor` copies of its body in sequence, where the
+/// back-edge of each copy jumps to the header of the next copy and the back-edge of the last copy
+/// jumps to the original header. Since each copy keeps its own copy of the header, this does not
+/// require the number of iterations to be known, but the loop itself also remains.
+///
+/// Returns Err(()) and leaves the loop unmodified if the loop has multiple back-edges or if any
+/// value defined within the loop is used after it.
+fn partially_unroll_loop(
+    function: &mut Function,
+    loop_: &Loop,
+    unroll_factor: usize,
+) -> Result<(), ()> {
+    let latch = loop_.single_latch().ok_or(())?;
+    if is_used_outside_of_loop(function, loop_) {
+        return Err(());
+    }
+
+    // Copy blocks in reverse post-order so that each value is copied before it is used
+    let post_order = PostOrder::with_function(function);
+    let blocks = post_order
+        .as_slice()
+        .iter()
+        .rev()
+        .filter(|block| loop_.blocks.contains(*block))
+        .copied()
+        .collect::<Vec<_>>();
 
-    Loop { header, back_edge_start, blocks }
+    // Every copy is made before any back-edge is redirected so that each copy is of the original
+    // loop body.
+    let copies =
+        (1..unroll_factor).map(|_| copy_loop_body(function, loop_, &blocks)).collect::<Vec<_>>();
+
+    let mut previous_latch = latch;
+    for new_blocks in copies {
+        // Redirect the back-edge of the previous copy into the header of this copy
+        let new_header = new_blocks[&loop_.header];
+        let mut terminator = function.dfg[previous_latch].unwrap_terminator().clone();
+        terminator.mutate_blocks(|block| if block == loop_.header { new_header } else { block });
+        function.dfg.set_block_terminator(previous_latch, terminator);
+
+        previous_latch = new_blocks[&latch];
+    }
+    Ok(())
+}
+
+/// Creates a copy of each of the given blocks of a loop, returning the mapping from each original
+/// block to its copy. Jumps to the loop header from within the copy still jump to the original
+/// header, and jumps to blocks outside of the loop are unchanged.
+fn copy_loop_body(
+    function: &mut Function,
+    loop_: &Loop,
+    blocks: &[BasicBlockId],
+) -> HashMap<BasicBlockId, BasicBlockId> {
+    let mut inserter = FunctionInserter::new(function);
+    let mut new_blocks = HashMap::new();
+
+    for block in blocks {
+        let new_block = inserter.function.dfg.make_block_with_parameters_from_block(*block);
+        inserter.remember_block_params_from_block(*block, new_block);
+        new_blocks.insert(*block, new_block);
+    }
+
+    for block in blocks {
+        let new_block = new_blocks[block];
+        for instruction in inserter.function.dfg[*block].instructions().to_vec() {
+            inserter.push_instruction(instruction, new_block);
+        }
+
+        let mut terminator = inserter.function.dfg[*block]
+            .unwrap_terminator()
+            .clone()
+            .map_values(|value| inserter.resolve(value));
+
+        terminator.mutate_blocks(|destination| {
+            if destination == loop_.header {
+                destination
+            } else {
+                new_blocks.get(&destination).copied().unwrap_or(destination)
+            }
+        });
+        inserter.function.dfg.set_block_terminator(new_block, terminator);
+    }
+
+    new_blocks
+}
+
+/// Returns true if any value defined within the loop is used by a block outside of it.
+pub(super) fn is_used_outside_of_loop(function: &Function, loop_: &Loop) -> bool {
+    let dfg = &function.dfg;
+    let mut defined_in_loop = HashSet::new();
+
+    for block in &loop_.blocks {
+        defined_in_loop.extend(dfg.block_parameters(*block));
+        for instruction in dfg[*block].instructions() {
+            defined_in_loop.extend(dfg.instruction_results(*instruction));
+        }
+    }
+
+    let mut is_used = false;
+    for block in function.reachable_blocks() {
+        if loop_.blocks.contains(&block) {
+            continue;
+        }
+
+        let mut check_value = |value| is_used |= defined_in_loop.contains(&dfg.resolve(value));
+        for instruction in dfg[block].instructions() {
+            dfg[*instruction].for_each_value(&mut check_value);
+        }
+        if let Some(terminator) = dfg[block].terminator() {
+            terminator.for_each_value(&mut check_value);
+        }
+    }
+    is_used
 }
 
 /// Unroll a single loop in the function.
@@ -170,7 +412,7 @@ fn unroll_loop(function: &mut Function, cfg: &ControlFlowGraph, loop_: &Loop) ->
 fn get_pre_header(cfg: &ControlFlowGraph, loop_: &Loop) -> BasicBlockId {
     let mut pre_header = cfg
         .predecessors(loop_.header)
-        .filter(|predecessor| *predecessor != loop_.back_edge_start)
+        .filter(|predecessor| !loop_.blocks.contains(predecessor))
         .collect::<Vec<_>>();
 
     assert_eq!(pre_header.len(), 1);
@@ -180,22 +422,18 @@ fn get_pre_header(cfg: &ControlFlowGraph, loop_: &Loop) -> BasicBlockId {
 /// Return the induction value of the current iteration of the loop, from the given block's jmp arguments.
 ///
 /// Expects the current block to terminate in `jmp h(N)` where h is the loop header and N is
-/// a Field value.
+/// a Field value. Loops without an induction variable, such as `while` loops, jump to their
+/// header without arguments and cannot be unrolled.
 fn get_induction_variable(function: &Function, block: BasicBlockId) -> Result<ValueId, ()> {
     match function.dfg[block].terminator() {
-        Some(TerminatorInstruction::Jmp { arguments, .. }) => {
-            // This assumption will no longer be valid if e.g. mutable variables are represented as
-            // block parameters. If that becomes the case we'll need to figure out which variable
-            // is generally constant and increasing to guess which parameter is the induction
-            // variable.
-            assert_eq!(arguments.len(), 1, "It is expected that a loop's induction variable is the only block parameter of the loop header");
-            let value = arguments[0];
-            if function.dfg.get_numeric_constant(value).is_some() {
-                Ok(value)
-            } else {
-                Err(())
-            }
-        }
+        // This assumption will no longer be valid if e.g. mutable variables are represented as
+        // block parameters. If that becomes the case we'll need to figure out which variable
+        // is generally constant and increasing to guess which parameter is the induction
+        // variable.
+        Some(TerminatorInstruction::Jmp { arguments, .. }) => match arguments.as_slice() {
+            [value] if function.dfg.get_numeric_constant(*value).is_some() => Ok(*value),
+            _ => Err(()),
+        },
         _ => Err(()),
     }
 }
@@ -424,9 +662,18 @@ impl<'f> LoopIteration<'f> {
 
 #[cfg(test)]
 mod tests {
-    use crate::ssa_refactor::{
-        ir::{function::RuntimeType, instruction::BinaryOp, map::Id, types::Type},
-        ssa_builder::FunctionBuilder,
+    use std::str::FromStr;
+
+    use crate::{
+        errors::RuntimeErrorKind,
+        ssa_refactor::{
+            ir::{
+                function::RuntimeType, instruction::BinaryOp, loops::LoopForest, map::Id,
+                types::Type,
+            },
+            ssa_builder::FunctionBuilder,
+            ssa_gen::Ssa,
+        },
     };
 
     #[test]
@@ -507,7 +754,7 @@ mod tests {
         builder.switch_to_block(b5);
         let v4 = builder.insert_binary(v0, BinaryOp::Add, v2);
         let v5 = builder.insert_binary(ten, BinaryOp::Lt, v4);
-        builder.insert_constrain(v5);
+        builder.insert_constrain(v5, None);
         let v6 = builder.insert_binary(v2, BinaryOp::Add, one);
         builder.terminate_with_jmp(b4, vec![v6]);
 
@@ -599,4 +846,119 @@ mod tests {
         let ssa = ssa.unroll_loops();
         assert_eq!(ssa.main().reachable_blocks().len(), 4);
     }
+
+    #[test]
+    fn reports_loops_which_cannot_be_unrolled() {
+        // The first loop has 2 instructions and 5 iterations, the second has an unknown bound
+        let src = "
+            acir fn main f0 {
+              b0(v0: u32):
+                jmp b1(u32 0)
+              b1(v1: u32):
+                v2 = lt v1, u32 5
+                jmpif v2 then: b2, else: b3
+              b2():
+                v3 = add v1, u32 1
+                jmp b1(v3)
+              b3():
+                jmp b4(u32 0)
+              b4(v4: u32):
+                v5 = lt v4, v0
+                jmpif v5 then: b5, else: b6
+              b5():
+                v6 = add v4, u32 1
+                jmp b4(v6)
+              b6():
+                return
+            }
+        ";
+
+        let error = Ssa::from_str(src).unwrap().try_unroll_loops(1, Some(9)).unwrap_err();
+        assert!(matches!(
+            error.kind,
+            RuntimeErrorKind::UnrollLimitExceeded { max_instructions: 9 }
+        ));
+
+        let error = Ssa::from_str(src).unwrap().try_unroll_loops(1, Some(10)).unwrap_err();
+        assert!(matches!(error.kind, RuntimeErrorKind::UnknownLoopBound { bound: "end" }));
+    }
+
+    #[test]
+    fn reports_loops_with_unknown_start() {
+        let src = "
+            acir fn main f0 {
+              b0(v0: u32):
+                jmp b1(v0)
+              b1(v1: u32):
+                v2 = lt v1, u32 5
+                jmpif v2 then: b2, else: b3
+              b2():
+                v3 = add v1, u32 1
+                jmp b1(v3)
+              b3():
+                return
+            }
+        ";
+
+        let error = Ssa::from_str(src).unwrap().try_unroll_loops(1, None).unwrap_err();
+        assert!(matches!(error.kind, RuntimeErrorKind::UnknownLoopBound { bound: "start" }));
+    }
+
+    #[test]
+    fn reports_while_loops_only_in_acir_functions() {
+        // while *v1 != 0 { *v1 = *v1 - 1; }
+        let src = "
+            acir fn main f0 {
+              b0(v0: Field):
+                v1 = allocate
+                store v0 at v1
+                jmp b1()
+              b1():
+                v2 = load v1 -> Field
+                v3 = eq v2, Field 0
+                jmpif v3 then: b3, else: b2
+              b2():
+                v4 = sub v2, Field 1
+                store v4 at v1
+                jmp b1()
+              b3():
+                return
+            }
+        ";
+
+        let error = Ssa::from_str(src).unwrap().try_unroll_loops(1, None).unwrap_err();
+        assert!(matches!(error.kind, RuntimeErrorKind::UnboundedLoop));
+
+        // Brillig functions can loop any number of times, so the loop is left as-is
+        let src = src.replace("acir fn", "brillig fn");
+        let ssa = Ssa::from_str(&src).unwrap().try_unroll_loops(1, None).unwrap();
+        assert_eq!(ssa.main().reachable_blocks().len(), 4);
+    }
+
+    #[test]
+    fn partially_unrolls_brillig_loops() {
+        let src = "
+            brillig fn main f0 {
+              b0(v0: u32):
+                jmp b1(u32 0)
+              b1(v1: u32):
+                v2 = lt v1, v0
+                jmpif v2 then: b2, else: b3
+              b2():
+                v3 = add v1, u32 1
+                jmp b1(v3)
+              b3():
+                return
+            }
+        ";
+
+        let ssa = Ssa::from_str(src).unwrap().try_unroll_loops(3, None).unwrap();
+        let main = ssa.main();
+        assert_eq!(main.reachable_blocks().len(), 8);
+
+        // The copies of the loop body are all part of the same loop
+        let forest = LoopForest::with_function(main);
+        assert_eq!(forest.loops().len(), 1);
+        assert_eq!(forest.loops()[0].blocks.len(), 6);
+    }
 }
@@ -0,0 +1,696 @@
+//! The PassManager determines which SSA optimization passes are run, and in which order.
+//!
+//! The SSA pipeline is split into two stages:
+//! - Passes run on every function before brillig is generated for unconstrained functions.
+//! - Passes run afterward to reduce an ACIR main function down to a single block,
+//!   which is required before it can be converted into ACIR.
+//!
+//! Each stage is a separate PassManager within [SsaOptions]. The default pipeline can be adjusted
+//! by inserting, skipping, or moving passes, or replaced entirely to compare pipelines.
+use std::{collections::BTreeMap, fmt::Display, path::PathBuf, str::FromStr, time::Instant};
+
+use thiserror::Error;
+
+use crate::errors::RuntimeError;
+
+use super::{
+    ir::{dot::write_dot_files, function::FunctionId, printer::display_function_diff},
+    pass_statistics::{PassStatistics, ProgramSize},
+    ssa_gen::Ssa,
+};
+
+/// A single optimization pass over the SSA IR.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Pass {
+    FunctionDeduplication,
+    Defunctionalize,
+    TailRecursionElimination,
+    FunctionSpecialization,
+    LoopInvariantCodeMotion,
+    AggressiveDeadCodeElimination,
+    Inlining,
+    LoopPeeling,
+    Unrolling,
+    SparseConditionalConstantPropagation,
+    JumpThreading,
+    SimplifyCfg,
+    ScalarReplacementOfAggregates,
+    Flattening,
+    Mem2Reg,
+    CopyPropagation,
+    ConstantFolding,
+    Peephole,
+    KnownBits,
+    BoundsCheckElimination,
+    StrengthReduction,
+    GlobalValueNumbering,
+    ConstraintDeduplication,
+    DeadInstructionElimination,
+    IdNormalization,
+    CriticalEdgeSplitting,
+    InductionVariableSimplification,
+    RedundantCastElimination,
+    UnreachableFunctionRemoval,
+    IntegerArithmeticLowering,
+    SideEffectsCleanup,
+    LoopBoundEvaluation,
+    Rematerialization,
+    LoopFusion,
+}
+
+impl Pass {
+    /// Every pass, in no particular order
+    pub const ALL: [Pass; 34] = [
+        Pass::FunctionDeduplication,
+        Pass::Defunctionalize,
+        Pass::TailRecursionElimination,
+        Pass::FunctionSpecialization,
+        Pass::LoopInvariantCodeMotion,
+        Pass::AggressiveDeadCodeElimination,
+        Pass::Inlining,
+        Pass::LoopPeeling,
+        Pass::Unrolling,
+        Pass::SparseConditionalConstantPropagation,
+        Pass::JumpThreading,
+        Pass::SimplifyCfg,
+        Pass::ScalarReplacementOfAggregates,
+        Pass::Flattening,
+        Pass::Mem2Reg,
+        Pass::CopyPropagation,
+        Pass::ConstantFolding,
+        Pass::Peephole,
+        Pass::KnownBits,
+        Pass::BoundsCheckElimination,
+        Pass::StrengthReduction,
+        Pass::GlobalValueNumbering,
+        Pass::ConstraintDeduplication,
+        Pass::DeadInstructionElimination,
+        Pass::IdNormalization,
+        Pass::CriticalEdgeSplitting,
+        Pass::InductionVariableSimplification,
+        Pass::RedundantCastElimination,
+        Pass::UnreachableFunctionRemoval,
+        Pass::IntegerArithmeticLowering,
+        Pass::SideEffectsCleanup,
+        Pass::LoopBoundEvaluation,
+        Pass::Rematerialization,
+        Pass::LoopFusion,
+    ];
+
+    /// The short name used to refer to this pass, e.g. from the command line
+    pub fn name(self) -> &'static str {
+        match self {
+            Pass::FunctionDeduplication => "deduplicate",
+            Pass::Defunctionalize => "defunctionalize",
+            Pass::TailRecursionElimination => "tail_recursion",
+            Pass::FunctionSpecialization => "specialize",
+            Pass::LoopInvariantCodeMotion => "licm",
+            Pass::AggressiveDeadCodeElimination => "adce",
+            Pass::Inlining => "inline",
+            Pass::LoopPeeling => "peel_loops",
+            Pass::Unrolling => "unroll",
+            Pass::SparseConditionalConstantPropagation => "sccp",
+            Pass::JumpThreading => "thread_jumps",
+            Pass::SimplifyCfg => "simplify_cfg",
+            Pass::ScalarReplacementOfAggregates => "sroa",
+            Pass::Flattening => "flatten",
+            Pass::Mem2Reg => "mem2reg",
+            Pass::CopyPropagation => "copy_propagation",
+            Pass::ConstantFolding => "fold_constants",
+            Pass::Peephole => "peephole",
+            Pass::KnownBits => "known_bits",
+            Pass::BoundsCheckElimination => "bounds_checks",
+            Pass::StrengthReduction => "strength_reduce",
+            Pass::GlobalValueNumbering => "gvn",
+            Pass::ConstraintDeduplication => "deduplicate_constraints",
+            Pass::DeadInstructionElimination => "die",
+            Pass::IdNormalization => "normalize_ids",
+            Pass::CriticalEdgeSplitting => "split_critical_edges",
+            Pass::InductionVariableSimplification => "induction_variables",
+            Pass::RedundantCastElimination => "redundant_casts",
+            Pass::UnreachableFunctionRemoval => "remove_unreachable_functions",
+            Pass::IntegerArithmeticLowering => "lower_integer_arithmetic",
+            Pass::SideEffectsCleanup => "cleanup_side_effects",
+            Pass::LoopBoundEvaluation => "evaluate_loop_bounds",
+            Pass::Rematerialization => "rematerialize",
+            Pass::LoopFusion => "fuse_loops",
+        }
+    }
+
+    /// A human-readable description used when printing the SSA after this pass
+    fn description(self) -> &'static str {
+        match self {
+            Pass::FunctionDeduplication => "Function Deduplication",
+            Pass::Defunctionalize => "Defunctionalization",
+            Pass::TailRecursionElimination => "Tail Recursion Elimination",
+            Pass::FunctionSpecialization => "Function Specialization",
+            Pass::LoopInvariantCodeMotion => "Loop Invariant Code Motion",
+            Pass::AggressiveDeadCodeElimination => "Aggressive Dead Code Elimination",
+            Pass::Inlining => "Inlining",
+            Pass::LoopPeeling => "Loop Peeling",
+            Pass::Unrolling => "Unrolling",
+            Pass::SparseConditionalConstantPropagation => "Sparse Conditional Constant Propagation",
+            Pass::JumpThreading => "Jump Threading",
+            Pass::SimplifyCfg => "Simplifying",
+            Pass::ScalarReplacementOfAggregates => "Scalar Replacement of Aggregates",
+            Pass::Flattening => "Flattening",
+            Pass::Mem2Reg => "Mem2Reg",
+            Pass::CopyPropagation => "Copy Propagation",
+            Pass::ConstantFolding => "Constant Folding",
+            Pass::Peephole => "Peephole Optimization",
+            Pass::KnownBits => "Known Bits Simplification",
+            Pass::BoundsCheckElimination => "Bounds Check Elimination",
+            Pass::StrengthReduction => "Strength Reduction",
+            Pass::GlobalValueNumbering => "Global Value Numbering",
+            Pass::ConstraintDeduplication => "Constraint Deduplication",
+            Pass::DeadInstructionElimination => "Dead Instruction Elimination",
+            Pass::IdNormalization => "ID Normalization",
+            Pass::CriticalEdgeSplitting => "Critical Edge Splitting",
+            Pass::InductionVariableSimplification => "Induction Variable Simplification",
+            Pass::RedundantCastElimination => "Redundant Cast Elimination",
+            Pass::UnreachableFunctionRemoval => "Unreachable Function Removal",
+            Pass::IntegerArithmeticLowering => "Integer Arithmetic Lowering",
+            Pass::SideEffectsCleanup => "Side Effects Cleanup",
+            Pass::LoopBoundEvaluation => "Loop Bound Evaluation",
+            Pass::Rematerialization => "Rematerialization",
+            Pass::LoopFusion => "Loop Fusion",
+        }
+    }
+
+    fn run(self, ssa: Ssa, options: &SsaOptions) -> Result<Ssa, RuntimeError> {
+        let ssa = match self {
+            Pass::FunctionDeduplication => ssa.deduplicate_functions(),
+            Pass::Defunctionalize => ssa.defunctionalize(),
+            Pass::TailRecursionElimination => ssa.eliminate_tail_recursion(),
+            Pass::FunctionSpecialization => ssa.specialize_functions(options.specialization_budget),
+            Pass::LoopInvariantCodeMotion => ssa.loop_invariant_code_motion(),
+            Pass::AggressiveDeadCodeElimination => ssa.aggressive_dead_code_elimination(),
+            Pass::Inlining => ssa.inline_functions()?,
+            Pass::LoopPeeling => ssa.peel_loops(),
+            Pass::Unrolling => {
+                ssa.try_unroll_loops(options.unroll_factor, options.max_unrolled_instructions)?
+            }
+            Pass::SparseConditionalConstantPropagation => {
+                ssa.sparse_conditional_constant_propagation()
+            }
+            Pass::JumpThreading => ssa.thread_jumps(),
+            Pass::SimplifyCfg => ssa.simplify_cfg(),
+            Pass::ScalarReplacementOfAggregates => ssa.scalar_replacement_of_aggregates(),
+            Pass::Flattening => ssa.flatten_cfg(),
+            Pass::Mem2Reg => ssa.mem2reg(),
+            Pass::CopyPropagation => ssa.propagate_copies(),
+            Pass::ConstantFolding => ssa.fold_constants(),
+            Pass::Peephole => ssa.peephole_optimize(),
+            Pass::KnownBits => ssa.simplify_known_bits(),
+            Pass::BoundsCheckElimination => ssa.eliminate_bounds_checks(),
+            Pass::StrengthReduction => ssa.strength_reduction(),
+            Pass::GlobalValueNumbering => ssa.global_value_numbering(),
+            Pass::ConstraintDeduplication => ssa.deduplicate_constraints(),
+            Pass::DeadInstructionElimination => ssa.dead_instruction_elimination(),
+            Pass::IdNormalization => ssa.normalize_ids(),
+            Pass::CriticalEdgeSplitting => ssa.split_critical_edges(),
+            Pass::InductionVariableSimplification => ssa.simplify_induction_variables(),
+            Pass::RedundantCastElimination => ssa.eliminate_redundant_casts(),
+            Pass::UnreachableFunctionRemoval => ssa.remove_unreachable_functions(),
+            Pass::IntegerArithmeticLowering => ssa.lower_integer_arithmetic(),
+            Pass::SideEffectsCleanup => ssa.cleanup_side_effects(),
+            Pass::LoopBoundEvaluation => ssa.evaluate_loop_bounds(),
+            Pass::Rematerialization => ssa.rematerialize(),
+            Pass::LoopFusion => ssa.fuse_loops(),
+        };
+        Ok(ssa)
+    }
+}
+
+impl Display for Pass {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+impl FromStr for Pass {
+    type Err = PassManagerError;
+
+    fn from_str(name: &str) -> Result<Pass, PassManagerError> {
+        Pass::ALL
+            .into_iter()
+            .find(|pass| pass.name() == name)
+            .ok_or_else(|| PassManagerError::UnknownPass(name.to_owned()))
+    }
+}
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum PassManagerError {
+    #[error("Unknown SSA pass '{0}'")]
+    UnknownPass(String),
+    #[error("SSA pass '{0}' is not in the pipeline")]
+    PassNotFound(Pass),
+    #[error("Unknown pass statistics format '{0}'")]
+    UnknownStatisticsFormat(String),
+}
+
+/// An ordered list of passes to run over the SSA IR.
+/// The same pass may occur more than once within the list.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PassManager {
+    passes: Vec<Pass>,
+}
+
+impl PassManager {
+    pub fn new(passes: Vec<Pass>) -> Self {
+        Self { passes }
+    }
+
+    /// The passes run before brillig generation by default
+    ///
+    /// Brillig functions are only optimized by this pipeline, so it includes the passes of the
+    /// ACIR pipeline which do not require the function to be inlined, unrolled or flattened.
+    /// Every other pass is opt-in.
+    pub fn default_brillig_pipeline() -> Self {
+        Self::new(vec![
+            Pass::Defunctionalize,
+            Pass::Mem2Reg,
+            Pass::ConstantFolding,
+            Pass::SimplifyCfg,
+            Pass::DeadInstructionElimination,
+        ])
+    }
+
+    /// The passes run on an ACIR program by default. Every other pass is opt-in.
+    pub fn default_acir_pipeline() -> Self {
+        Self::new(vec![
+            Pass::Inlining,
+            Pass::Unrolling,
+            Pass::SimplifyCfg,
+            Pass::Flattening,
+            Pass::Mem2Reg,
+            Pass::ConstantFolding,
+            Pass::DeadInstructionElimination,
+        ])
+    }
+
+    pub fn passes(&self) -> &[Pass] {
+        &self.passes
+    }
+
+    /// Adds a pass to the end of the pipeline
+    pub fn push(&mut self, pass: Pass) {
+        self.passes.push(pass);
+    }
+
+    /// Inserts a pass directly before the first occurrence of `anchor`
+    pub fn insert_before(&mut self, anchor: Pass, pass: Pass) -> Result<(), PassManagerError> {
+        let index = self.position(anchor)?;
+        self.passes.insert(index, pass);
+        Ok(())
+    }
+
+    /// Inserts a pass directly after the first occurrence of `anchor`
+    pub fn insert_after(&mut self, anchor: Pass, pass: Pass) -> Result<(), PassManagerError> {
+        let index = self.position(anchor)?;
+        self.passes.insert(index + 1, pass);
+        Ok(())
+    }
+
+    /// Removes every occurrence of the given pass from the pipeline
+    pub fn skip(&mut self, pass: Pass) {
+        self.passes.retain(|existing| *existing != pass);
+    }
+
+    /// Moves every occurrence of `pass` so that it runs once, directly before `anchor`
+    pub fn move_before(&mut self, pass: Pass, anchor: Pass) -> Result<(), PassManagerError> {
+        self.position(pass)?;
+        self.position(anchor)?;
+        if pass != anchor {
+            self.skip(pass);
+            self.insert_before(anchor, pass)?;
+        }
+        Ok(())
+    }
+
+    /// Moves every occurrence of `pass` so that it runs once, directly after `anchor`
+    pub fn move_after(&mut self, pass: Pass, anchor: Pass) -> Result<(), PassManagerError> {
+        self.position(pass)?;
+        self.position(anchor)?;
+        if pass != anchor {
+            self.skip(pass);
+            self.insert_after(anchor, pass)?;
+        }
+        Ok(())
+    }
+
+    fn position(&self, pass: Pass) -> Result<usize, PassManagerError> {
+        self.passes
+            .iter()
+            .position(|existing| *existing == pass)
+            .ok_or(PassManagerError::PassNotFound(pass))
+    }
+
+    /// Runs each pass in order, printing and verifying the SSA after each pass if enabled
+    /// by the given options. The name of the stage is used to name any DOT files written.
+    /// If pass statistics are enabled, the statistics of each pass are added to `statistics`.
+    pub(crate) fn run(
+        &self,
+        mut ssa: Ssa,
+        options: &SsaOptions,
+        stage: &'static str,
+        statistics: &mut Vec<PassStatistics>,
+    ) -> Result<Ssa, RuntimeError> {
+        for (index, pass) in self.passes.iter().enumerate() {
+            let message = format!("After {}:", pass.description());
+            let before = options.print_ssa_diffs.then(|| print_functions(&ssa));
+            let size_before = options.pass_statistics.map(|_| ProgramSize::of(&ssa));
+
+            let start = Instant::now();
+            ssa = pass.run(ssa, options)?;
+            let duration = start.elapsed();
+
+            if let Some(size_before) = size_before {
+                let size_after = ProgramSize::of(&ssa);
+                statistics.push(PassStatistics::new(
+                    stage,
+                    *pass,
+                    duration,
+                    size_before,
+                    size_after,
+                ));
+            }
+
+            match before {
+                Some(before) => print_diff(&message, &before, &print_functions(&ssa)),
+                None => ssa = ssa.print(options.print_ssa_passes, &message),
+            }
+
+            if let Some(directory) = &options.dot_directory {
+                if options.dot_passes.contains(pass) {
+                    let prefix = format!("{stage}_{index:02}_{pass}");
+                    if let Err(error) = write_dot_files(&ssa, directory, &prefix) {
+                        eprintln!("Failed to write DOT files to {}: {error}", directory.display());
+                    }
+                }
+            }
+
+            if options.verify_ssa {
+                ssa.verify()?;
+            }
+        }
+        Ok(ssa)
+    }
+}
+
+/// The printed form of each function in the program after normalizing its ids, keyed by the id
+/// of the function before normalization. Normalization preserves the order of function ids.
+fn print_functions(ssa: &Ssa) -> BTreeMap<FunctionId, String> {
+    let normalized = ssa.normalize_ids();
+    let functions = ssa.functions.keys().zip(normalized.functions.values());
+    functions.map(|(id, function)| (*id, function.to_string())).collect()
+}
+
+/// Prints the changes made to each function of the program by a pass
+fn print_diff(
+    message: &str,
+    before: &BTreeMap<FunctionId, String>,
+    after: &BTreeMap<FunctionId, String>,
+) {
+    let mut ids: Vec<_> = before.keys().chain(after.keys()).collect();
+    ids.sort();
+    ids.dedup();
+
+    let diffs: Vec<_> = ids
+        .into_iter()
+        .filter_map(|id| {
+            let before = before.get(id).map_or("", String::as_str);
+            let after = after.get(id).map_or("", String::as_str);
+            (before != after).then(|| display_function_diff(before, after))
+        })
+        .collect();
+
+    if diffs.is_empty() {
+        println!("{message} (no changes)\n");
+    } else {
+        println!("{message}\n{}\n", diffs.join("\n"));
+    }
+}
+
+impl FromStr for PassManager {
+    type Err = PassManagerError;
+
+    /// Parses a comma-separated list of pass names, e.g. `inline,unroll,flatten`
+    fn from_str(passes: &str) -> Result<PassManager, PassManagerError> {
+        let passes = passes
+            .split(',')
+            .map(str::trim)
+            .filter(|name| !name.is_empty())
+            .map(Pass::from_str)
+            .collect::<Result<_, _>>()?;
+        Ok(PassManager::new(passes))
+    }
+}
+
+/// How the statistics recorded for each pass are printed
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum StatisticsFormat {
+    Table,
+    Json,
+}
+
+impl FromStr for StatisticsFormat {
+    type Err = PassManagerError;
+
+    fn from_str(format: &str) -> Result<StatisticsFormat, PassManagerError> {
+        match format {
+            "table" => Ok(StatisticsFormat::Table),
+            "json" => Ok(StatisticsFormat::Json),
+            _ => Err(PassManagerError::UnknownStatisticsFormat(format.to_owned())),
+        }
+    }
+}
+
+/// Options controlling how a program is compiled from SSA into ACIR.
+#[derive(Debug, Clone)]
+pub struct SsaOptions {
+    /// Passes run over every function before brillig is generated for unconstrained functions
+    pub brillig_passes: PassManager,
+
+    /// Passes run afterward if the main function is an ACIR function
+    pub acir_passes: PassManager,
+
+    /// Print the SSA after each pass
+    pub print_ssa_passes: bool,
+
+    /// Print only the changes each pass makes to the SSA, rather than the SSA after each pass
+    pub print_ssa_diffs: bool,
+
+    /// The directory to write DOT files of the CFG of each function, and of the call graph,
+    /// into after each of the `dot_passes`
+    pub dot_directory: Option<PathBuf>,
+
+    /// The passes after which DOT files are written, if `dot_directory` is set
+    pub dot_passes: Vec<Pass>,
+
+    /// Print the time taken by each pass, with the number of instructions and blocks it added
+    /// or removed and the memory used afterward, once every pass has run
+    pub pass_statistics: Option<StatisticsFormat>,
+
+    /// Check that the SSA is well-formed after each pass
+    pub verify_ssa: bool,
+
+    /// Print the brillig bytecode generated for unconstrained functions
+    pub print_brillig_trace: bool,
+
+    /// The number of copies of the body to create for loops in unconstrained functions which
+    /// cannot be fully unrolled. Factors of 0 or 1 leave these loops unmodified.
+    pub unroll_factor: usize,
+
+    /// The maximum number of instructions the loops of each function may be unrolled into.
+    /// If None, loops are always fully unrolled when their bounds are known.
+    pub max_unrolled_instructions: Option<usize>,
+
+    /// The maximum number of instructions which copies of unconstrained functions specialized
+    /// to constant arguments may add to the program.
+    pub specialization_budget: usize,
+
+    /// The maximum number of opcodes the circuit is expected to have. If exceeded, the locations
+    /// generating the most opcodes are reported.
+    pub opcode_budget: Option<usize>,
+
+    /// Fail to compile circuits exceeding the opcode budget, rather than only reporting them
+    pub strict_opcode_budget: bool,
+
+    /// Record the SSA instruction each opcode is generated for, and its purpose, in the debug
+    /// information of the circuit
+    pub record_opcode_provenance: bool,
+
+    /// Record the source code location of each opcode of the Brillig code called by the circuit
+    /// in its debug information, so that debuggers can step through unconstrained code
+    pub record_brillig_locations: bool,
+
+    /// The directory to cache the initial SSA of each function in, so that it is only generated
+    /// again once the function changes
+    pub ssa_cache_directory: Option<PathBuf>,
+}
+
+impl SsaOptions {
+    /// Adds aggressive dead code elimination after simplifying the control flow graph of each
+    /// stage, if that pass is within the pipeline.
+    pub fn enable_aggressive_dce(&mut self) {
+        let adce = Pass::AggressiveDeadCodeElimination;
+        self.brillig_passes.insert_after(Pass::SimplifyCfg, adce).ok();
+        self.acir_passes.insert_after(Pass::SimplifyCfg, adce).ok();
+    }
+}
+
+impl Default for SsaOptions {
+    fn default() -> Self {
+        Self {
+            brillig_passes: PassManager::default_brillig_pipeline(),
+            acir_passes: PassManager::default_acir_pipeline(),
+            print_ssa_passes: false,
+            print_ssa_diffs: false,
+            dot_directory: None,
+            dot_passes: Vec::new(),
+            pass_statistics: None,
+            verify_ssa: false,
+            print_brillig_trace: false,
+            unroll_factor: 1,
+            max_unrolled_instructions: None,
+            specialization_budget: 1000,
+            opcode_budget: None,
+            strict_opcode_budget: false,
+            record_opcode_provenance: false,
+            record_brillig_locations: false,
+            ssa_cache_directory: None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use crate::ssa_refactor::{
+        ir::{instruction::Instruction, parser::assert_ssa_equals},
+        ssa_gen::Ssa,
+    };
+
+    use super::{Pass, PassManager, PassManagerError, SsaOptions, StatisticsFormat};
+
+    #[test]
+    fn modifies_pipelines() {
+        let mut passes = PassManager::from_str("inline, unroll,flatten").unwrap();
+        assert_eq!(passes.passes(), [Pass::Inlining, Pass::Unrolling, Pass::Flattening]);
+
+        passes.insert_after(Pass::Unrolling, Pass::Mem2Reg).unwrap();
+        passes.insert_before(Pass::Inlining, Pass::Defunctionalize).unwrap();
+        passes.move_after(Pass::Inlining, Pass::Flattening).unwrap();
+        passes.skip(Pass::Unrolling);
+
+        let expected = [Pass::Defunctionalize, Pass::Mem2Reg, Pass::Flattening, Pass::Inlining];
+        assert_eq!(passes.passes(), expected);
+
+        let error = passes.insert_after(Pass::Unrolling, Pass::Mem2Reg);
+        assert_eq!(error, Err(PassManagerError::PassNotFound(Pass::Unrolling)));
+
+        let error = PassManager::from_str("inline,foo");
+        assert_eq!(error, Err(PassManagerError::UnknownPass("foo".to_owned())));
+    }
+
+    #[test]
+    fn pass_names_round_trip() {
+        for pass in Pass::ALL {
+            assert_eq!(Pass::from_str(pass.name()), Ok(pass));
+        }
+    }
+
+    #[test]
+    fn enables_aggressive_dce() {
+        let mut options = SsaOptions::default();
+        options.enable_aggressive_dce();
+
+        let adce = Pass::AggressiveDeadCodeElimination;
+        let brillig_passes = options.brillig_passes.passes();
+        let simplify_cfg = brillig_passes.iter().position(|pass| *pass == Pass::SimplifyCfg);
+        assert_eq!(brillig_passes[simplify_cfg.unwrap() + 1], adce);
+
+        let acir_passes = options.acir_passes.passes();
+        let simplify_cfg = acir_passes.iter().position(|pass| *pass == Pass::SimplifyCfg).unwrap();
+        assert_eq!(acir_passes[simplify_cfg + 1], adce);
+    }
+
+    #[test]
+    fn runs_passes_in_order() {
+        let src = "
+            acir fn main f0 {
+              b0(v0: Field):
+                v1 = mul v0, v0
+                v2 = mul v0, v0
+                v3 = add v1, v2
+                v4 = mul v3, Field 2
+                return v1
+            }
+        ";
+        let expected = "
+            acir fn main f0 {
+              b0(v0: Field):
+                v1 = mul v0, v0
+                return v1
+            }
+        ";
+        let passes =
+            PassManager::new(vec![Pass::GlobalValueNumbering, Pass::DeadInstructionElimination]);
+        let options = SsaOptions {
+            verify_ssa: true,
+            pass_statistics: Some(StatisticsFormat::Table),
+            ..SsaOptions::default()
+        };
+        let mut statistics = Vec::new();
+        let ssa =
+            passes.run(Ssa::from_str(src).unwrap(), &options, "test", &mut statistics).unwrap();
+        assert_ssa_equals(&ssa, expected);
+
+        let instruction_deltas: Vec<_> = statistics
+            .iter()
+            .map(|statistic| (statistic.pass, statistic.instruction_delta))
+            .collect();
+        assert_eq!(
+            instruction_deltas,
+            [(Pass::GlobalValueNumbering, -1), (Pass::DeadInstructionElimination, -2)]
+        );
+    }
+
+    #[test]
+    fn optimizes_brillig_functions() {
+        let src = "
+            brillig fn main f0 {
+              b0(v0: Field):
+                v1 = allocate
+                store Field 3 at v1
+                v2 = load v1 -> Field
+                v3 = eq v2, Field 3
+                jmpif v3 then: b1, else: b2
+              b1():
+                v4 = mul v0, v2
+                return v4
+              b2():
+                return v0
+            }
+        ";
+        let options = SsaOptions { verify_ssa: true, ..SsaOptions::default() };
+        let ssa = options
+            .brillig_passes
+            .run(Ssa::from_str(src).unwrap(), &options, "brillig", &mut Vec::new())
+            .unwrap();
+
+        // The stored value is forwarded to the load, which lets the branch be folded
+        let main = ssa.main();
+        let blocks = main.reachable_blocks();
+        assert_eq!(blocks.len(), 1);
+        let instructions = blocks.iter().flat_map(|block| main.dfg[*block].instructions());
+        for instruction in instructions {
+            assert!(!matches!(
+                main.dfg[*instruction],
+                Instruction::Allocate | Instruction::Load { .. } | Instruction::Store { .. }
+            ));
+        }
+    }
+}
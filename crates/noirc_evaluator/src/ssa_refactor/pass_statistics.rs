@@ -0,0 +1,210 @@
+//! Records statistics about each pass run over the SSA IR, to find which passes dominate
+//! compilation time or grow the program the most.
+//!
+//! For each pass this records the wall time taken, the change in the number of instructions and
+//! blocks reachable within every function, and the memory resident after the pass. Collecting
+//! these requires counting every instruction of the program after each pass, so they are only
+//! recorded when requested.
+use std::time::Duration;
+
+use super::{pass_manager::Pass, ssa_gen::Ssa};
+
+/// The statistics recorded for a single run of a pass
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct PassStatistics {
+    /// The stage of the pipeline the pass was run within, e.g. `brillig` or `acir`
+    pub(crate) stage: &'static str,
+    pub(crate) pass: Pass,
+    pub(crate) duration: Duration,
+    pub(crate) instruction_delta: i64,
+    pub(crate) block_delta: i64,
+    /// The resident memory of the compiler after the pass, in kilobytes, if it is known
+    pub(crate) resident_memory: Option<u64>,
+}
+
+/// The number of instructions and blocks reachable within every function of a program
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub(crate) struct ProgramSize {
+    instructions: usize,
+    blocks: usize,
+}
+
+impl ProgramSize {
+    pub(crate) fn of(ssa: &Ssa) -> ProgramSize {
+        let mut size = ProgramSize { instructions: 0, blocks: 0 };
+        for function in ssa.functions.values() {
+            for block in function.reachable_blocks() {
+                size.instructions += function.dfg[block].instructions().len();
+                size.blocks += 1;
+            }
+        }
+        size
+    }
+}
+
+impl PassStatistics {
+    pub(crate) fn new(
+        stage: &'static str,
+        pass: Pass,
+        duration: Duration,
+        before: ProgramSize,
+        after: ProgramSize,
+    ) -> PassStatistics {
+        PassStatistics {
+            stage,
+            pass,
+            duration,
+            instruction_delta: after.instructions as i64 - before.instructions as i64,
+            block_delta: after.blocks as i64 - before.blocks as i64,
+            resident_memory: resident_memory(),
+        }
+    }
+}
+
+/// Reads the resident memory of the current process, in kilobytes. This is only available on
+/// platforms providing `/proc/self/status`.
+fn resident_memory() -> Option<u64> {
+    let status = std::fs::read_to_string("/proc/self/status").ok()?;
+    let line = status.lines().find(|line| line.starts_with("VmRSS:"))?;
+    line.trim_start_matches("VmRSS:").trim().trim_end_matches("kB").trim().parse().ok()
+}
+
+/// Formats the statistics as a table with a row for each pass in the order they were run,
+/// followed by a row with the totals of every pass.
+pub(crate) fn display_table(statistics: &[PassStatistics]) -> String {
+    let mut rows = vec![[
+        "stage".to_owned(),
+        "pass".to_owned(),
+        "time (ms)".to_owned(),
+        "instructions".to_owned(),
+        "blocks".to_owned(),
+        "memory (kB)".to_owned(),
+    ]];
+
+    for statistic in statistics {
+        rows.push([
+            statistic.stage.to_owned(),
+            statistic.pass.name().to_owned(),
+            format_milliseconds(statistic.duration),
+            format!("{:+}", statistic.instruction_delta),
+            format!("{:+}", statistic.block_delta),
+            statistic.resident_memory.map_or("-".to_owned(), |memory| memory.to_string()),
+        ]);
+    }
+
+    let total_duration = statistics.iter().map(|statistic| statistic.duration).sum();
+    let total_instructions: i64 =
+        statistics.iter().map(|statistic| statistic.instruction_delta).sum();
+    let total_blocks: i64 = statistics.iter().map(|statistic| statistic.block_delta).sum();
+    let peak_memory = statistics.iter().filter_map(|statistic| statistic.resident_memory).max();
+    rows.push([
+        "total".to_owned(),
+        String::new(),
+        format_milliseconds(total_duration),
+        format!("{total_instructions:+}"),
+        format!("{total_blocks:+}"),
+        peak_memory.map_or("-".to_owned(), |memory| memory.to_string()),
+    ]);
+
+    let mut widths = [0; 6];
+    for row in &rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.len());
+        }
+    }
+
+    let lines: Vec<_> = rows
+        .iter()
+        .map(|row| {
+            // The stage and pass names are left-aligned, and every number right-aligned
+            let [stage, pass, numbers @ ..] = row;
+            let [stage_width, pass_width, number_widths @ ..] = widths;
+            let mut line = format!("{stage:<stage_width$}  {pass:<pass_width$}");
+            for (number, width) in numbers.iter().zip(number_widths) {
+                line += &format!("  {number:>width$}");
+            }
+            line
+        })
+        .collect();
+    lines.join("\n")
+}
+
+/// Formats the statistics as a JSON array with an object for each pass in the order they were run
+pub(crate) fn to_json(statistics: &[PassStatistics]) -> String {
+    let objects: Vec<_> = statistics
+        .iter()
+        .map(|statistic| {
+            let memory =
+                statistic.resident_memory.map_or("null".to_owned(), |memory| memory.to_string());
+            format!(
+                "  {{\"stage\": \"{}\", \"pass\": \"{}\", \"time_ms\": {}, \"instruction_delta\": {}, \"block_delta\": {}, \"resident_memory_kb\": {}}}",
+                statistic.stage,
+                statistic.pass.name(),
+                format_milliseconds(statistic.duration),
+                statistic.instruction_delta,
+                statistic.block_delta,
+                memory,
+            )
+        })
+        .collect();
+
+    if objects.is_empty() {
+        "[]".to_owned()
+    } else {
+        format!("[\n{}\n]", objects.join(",\n"))
+    }
+}
+
+fn format_milliseconds(duration: Duration) -> String {
+    format!("{:.3}", duration.as_secs_f64() * 1000.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use crate::ssa_refactor::pass_manager::Pass;
+
+    use super::{display_table, to_json, PassStatistics};
+
+    fn statistics() -> Vec<PassStatistics> {
+        vec![
+            PassStatistics {
+                stage: "acir",
+                pass: Pass::Inlining,
+                duration: Duration::from_micros(1500),
+                instruction_delta: 12,
+                block_delta: -3,
+                resident_memory: Some(2048),
+            },
+            PassStatistics {
+                stage: "acir",
+                pass: Pass::DeadInstructionElimination,
+                duration: Duration::from_micros(250),
+                instruction_delta: -20,
+                block_delta: 0,
+                resident_memory: None,
+            },
+        ]
+    }
+
+    #[test]
+    fn displays_tables() {
+        let expected = "\
+stage  pass    time (ms)  instructions  blocks  memory (kB)
+acir   inline      1.500           +12      -3         2048
+acir   die         0.250           -20      +0            -
+total              1.750            -8      -3         2048";
+        assert_eq!(display_table(&statistics()), expected);
+    }
+
+    #[test]
+    fn converts_to_json() {
+        let expected = r#"[
+  {"stage": "acir", "pass": "inline", "time_ms": 1.500, "instruction_delta": 12, "block_delta": -3, "resident_memory_kb": 2048},
+  {"stage": "acir", "pass": "die", "time_ms": 0.250, "instruction_delta": -20, "block_delta": 0, "resident_memory_kb": null}
+]"#;
+        assert_eq!(to_json(&statistics()), expected);
+        assert_eq!(to_json(&[]), "[]");
+    }
+}
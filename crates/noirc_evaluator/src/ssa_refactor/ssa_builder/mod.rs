@@ -86,6 +86,18 @@ impl FunctionBuilder {
         self.new_function_with_type(name, function_id, RuntimeType::Brillig);
     }
 
+    /// Replace the function being built with an already built function of the same id, such as
+    /// one whose SSA was loaded from a cache rather than generated.
+    pub(crate) fn set_current_function(&mut self, function: Function) {
+        assert_eq!(
+            function.id(),
+            self.current_function.id(),
+            "ICE: Replaced function has another id"
+        );
+        self.current_block = function.entry_block();
+        self.current_function = function;
+    }
+
     /// Consume the FunctionBuilder returning all the functions it has generated.
     pub(crate) fn finish(mut self) -> Ssa {
         self.finished_functions.push(self.current_function);
@@ -232,9 +244,10 @@ impl FunctionBuilder {
             .first()
     }
 
-    /// Insert a constrain instruction at the end of the current block.
-    pub(crate) fn insert_constrain(&mut self, boolean: ValueId) {
-        self.insert_instruction(Instruction::Constrain(boolean), None);
+    /// Insert a constrain instruction at the end of the current block, along with the message
+    /// to report if the constraint fails.
+    pub(crate) fn insert_constrain(&mut self, boolean: ValueId, message: Option<String>) {
+        self.insert_instruction(Instruction::Constrain(boolean, message), None);
     }
 
     /// Insert a call instruction at the end of the current block and return
@@ -0,0 +1,95 @@
+//! Caches the SSA generated for each function of a program in a directory, so that the SSA of a
+//! function is only generated again once its monomorphized AST changes.
+use std::path::{Path, PathBuf};
+
+use noirc_frontend::monomorphization::ast;
+use sha2::{Digest, Sha256};
+
+use crate::ssa_refactor::ir::{function::Function, serialization::SsaSerializationError};
+
+/// A directory holding the SSA of functions, each in a file named after a hash of the
+/// monomorphized function it was generated from.
+pub(crate) struct FunctionCache<'a> {
+    directory: &'a Path,
+}
+
+impl<'a> FunctionCache<'a> {
+    pub(crate) fn new(directory: &'a Path) -> Self {
+        Self { directory }
+    }
+
+    /// Returns the SSA cached for the given function, if any. Files which cannot be read, or
+    /// which hold SSA of another format version, are treated as missing.
+    pub(crate) fn load(&self, function: &ast::Function) -> Option<Function> {
+        let bytes = std::fs::read(self.path(function).ok()?).ok()?;
+        Function::from_bytes(&bytes).ok()
+    }
+
+    /// Caches the SSA generated for the given function.
+    pub(crate) fn store(
+        &self,
+        function: &ast::Function,
+        ssa: &Function,
+    ) -> Result<(), SsaSerializationError> {
+        std::fs::create_dir_all(self.directory)?;
+        std::fs::write(self.path(function)?, ssa.to_bytes()?)?;
+        Ok(())
+    }
+
+    /// Returns the path of the file holding the SSA of the given function. The file is named
+    /// after a hash of the version of the compiler and of the function's monomorphized AST,
+    /// which includes the source locations within it and the ids of the functions it calls.
+    fn path(&self, function: &ast::Function) -> Result<PathBuf, SsaSerializationError> {
+        let mut hasher = Sha256::new();
+        hasher.update(env!("CARGO_PKG_VERSION"));
+        hasher.update(bincode::serialize(function)?);
+        let hash: String = hasher.finalize().iter().map(|byte| format!("{byte:02x}")).collect();
+        Ok(self.directory.join(format!("{hash}.ssa")))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use noirc_frontend::monomorphization::ast::{self, Expression, FuncId, Literal};
+
+    use crate::ssa_refactor::ssa_gen::Ssa;
+
+    use super::FunctionCache;
+
+    /// Returns a monomorphized main function returning the given boolean
+    fn main_returning(value: bool) -> ast::Function {
+        ast::Function {
+            id: FuncId(0),
+            name: "main".into(),
+            parameters: Vec::new(),
+            body: Expression::Literal(Literal::Bool(value)),
+            return_type: ast::Type::Bool,
+            unconstrained: false,
+        }
+    }
+
+    #[test]
+    fn caches_functions_by_their_monomorphized_ast() {
+        let directory =
+            std::env::temp_dir().join(format!("nssa_function_cache_{}", std::process::id()));
+        let cache = FunctionCache::new(&directory);
+        let src = "
+            acir fn main f0 {
+              b0():
+                return u1 1
+            }
+        ";
+        let ssa = Ssa::from_str(src).unwrap();
+
+        cache.store(&main_returning(true), ssa.main()).unwrap();
+        let cached = cache.load(&main_returning(true)).expect("The function is cached");
+        assert_eq!(cached.to_string(), ssa.main().to_string());
+
+        // Any change to the monomorphized function is a cache miss
+        assert!(cache.load(&main_returning(false)).is_none());
+
+        std::fs::remove_dir_all(directory).unwrap();
+    }
+}
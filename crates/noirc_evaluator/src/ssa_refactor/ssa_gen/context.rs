@@ -15,7 +15,7 @@ use crate::ssa_refactor::ir::function::{Function, RuntimeType};
 use crate::ssa_refactor::ir::instruction::BinaryOp;
 use crate::ssa_refactor::ir::map::AtomicCounter;
 use crate::ssa_refactor::ir::types::{NumericType, Type};
-use crate::ssa_refactor::ir::value::ValueId;
+use crate::ssa_refactor::ir::value::{Value as IrValue, ValueId};
 use crate::ssa_refactor::ssa_builder::FunctionBuilder;
 
 use super::value::{Tree, Value, Values};
@@ -58,12 +58,13 @@ pub(super) struct SharedContext {
     ///
     /// The queue is currently Last-in First-out (LIFO) but this is an
     /// implementation detail that can be trivially changed and should
-    /// not impact the resulting SSA besides changing which IDs are assigned
-    /// to which functions.
+    /// not impact the resulting SSA.
     function_queue: Mutex<FunctionQueue>,
 
-    /// Shared counter used to assign the ID of the next function
-    function_counter: AtomicCounter<Function>,
+    /// The ID of each function of the program, by the index of the function. IDs follow the
+    /// order of the monomorphized functions rather than the order in which functions are
+    /// reached, so that the SSA of each function depends only on its own monomorphized AST.
+    function_ids: Vec<IrFunctionId>,
 
     /// The entire monomorphized source program
     pub(super) program: Program,
@@ -660,9 +661,8 @@ fn operator_result_max_bit_size_to_truncate(
                 // increase: lhs_bit_size + rhs
                 return Some(lhs_bit_size + (rhs_constant.to_u128() as u32));
             }
-            // Unhappy case is that we don't yet know the rhs value, (even though it will
-            // eventually have to resolve to a constant). The best we can is assume the value of
-            // rhs to be the maximum value of it's numeric type. If that turns out to be larger
+            // Unhappy case is that we don't know the rhs value. The best we can is assume the
+            // value of rhs to be the maximum value of it's numeric type. If that turns out to be larger
             // than the native field's bit size, we full back to using that.
 
             // The formula for calculating the max bit size of a left shift is:
@@ -716,10 +716,12 @@ fn convert_operator(op: noirc_frontend::BinaryOpKind) -> BinaryOp {
 impl SharedContext {
     /// Create a new SharedContext for the given monomorphized program.
     pub(super) fn new(program: Program) -> Self {
+        let function_counter = AtomicCounter::<Function>::default();
+        let function_ids = vecmap(&program.functions, |_| function_counter.next());
         Self {
             functions: Default::default(),
             function_queue: Default::default(),
-            function_counter: Default::default(),
+            function_ids,
             program,
         }
     }
@@ -729,9 +731,8 @@ impl SharedContext {
         self.function_queue.lock().expect("Failed to lock function_queue").pop()
     }
 
-    /// Return the matching id for the given function if known. If it is not known this
-    /// will add the function to the queue of functions to compile, assign it a new id,
-    /// and return this new id.
+    /// Return the matching id for the given function. If the function is not known yet this
+    /// will also add it to the queue of functions to compile.
     pub(super) fn get_or_queue_function(&self, id: ast::FuncId) -> IrFunctionId {
         // Start a new block to guarantee the destructor for the map lock is released
         // before map needs to be aquired again in self.functions.write() below
@@ -742,7 +743,7 @@ impl SharedContext {
             }
         }
 
-        let next_id = self.function_counter.next();
+        let next_id = self.function_id(id);
 
         let mut queue = self.function_queue.lock().expect("Failed to lock function queue");
         queue.push((id, next_id));
@@ -751,6 +752,23 @@ impl SharedContext {
 
         next_id
     }
+
+    /// Return the id assigned to the given function, whether or not it has been queued.
+    pub(super) fn function_id(&self, id: ast::FuncId) -> IrFunctionId {
+        self.function_ids[id.0 as usize]
+    }
+
+    /// Queues the functions called by a function whose SSA was loaded from a cache rather than
+    /// generated, as they would otherwise be queued when generating its SSA.
+    pub(super) fn queue_called_functions(&self, function: &Function) {
+        for (_, value) in function.dfg.values_iter() {
+            if let IrValue::Function(id) = value {
+                let index = self.function_ids.iter().position(|function_id| function_id == id);
+                let index = index.expect("ICE: Cached function calls an unknown function");
+                self.get_or_queue_function(FuncId(index as u32));
+            }
+        }
+    }
 }
 
 /// Used to remember the results of each step of extracting a value from an ast::LValue
@@ -1,8 +1,10 @@
+mod cache;
 mod context;
 mod program;
 mod value;
 
-pub(crate) use program::Ssa;
+pub(crate) use cache::FunctionCache;
+pub use program::Ssa;
 
 use context::SharedContext;
 use iter_extended::vecmap;
@@ -14,12 +16,20 @@ use self::{
     value::{Tree, Values},
 };
 
-use super::ir::{function::RuntimeType, instruction::BinaryOp, types::Type, value::ValueId};
+use super::ir::{
+    function::RuntimeType, instruction::BinaryOp, serialization::SsaSerializationError,
+    types::Type, value::ValueId,
+};
 
 /// Generates SSA for the given monomorphized program.
 ///
-/// This function will generate the SSA but does not perform any optimizations on it.
-pub(crate) fn generate_ssa(program: Program) -> Ssa {
+/// This function will generate the SSA but does not perform any optimizations on it. Given a
+/// cache, the SSA of each function is loaded from the cache if it holds the SSA of an identical
+/// function, and is otherwise generated and stored in the cache.
+pub(crate) fn generate_ssa(
+    program: Program,
+    cache: Option<&FunctionCache>,
+) -> Result<Ssa, SsaSerializationError> {
     let context = SharedContext::new(program);
 
     let main_id = Program::main_id();
@@ -34,7 +44,11 @@ pub(crate) fn generate_ssa(program: Program) -> Ssa {
         if main.unconstrained { RuntimeType::Brillig } else { RuntimeType::Acir },
         &context,
     );
-    function_context.codegen_function_body(&main.body);
+    let mut generated = Vec::new();
+    if !function_context.load_function(main, cache) {
+        function_context.codegen_function_body(&main.body);
+        generated.push(main);
+    }
 
     // Main has now been compiled and any other functions referenced within have been added to the
     // function queue as they were found in codegen_ident. This queueing will happen each time a
@@ -43,13 +57,35 @@ pub(crate) fn generate_ssa(program: Program) -> Ssa {
     while let Some((src_function_id, dest_id)) = context.pop_next_function_in_queue() {
         let function = &context.program[src_function_id];
         function_context.new_function(dest_id, function);
-        function_context.codegen_function_body(&function.body);
+        if !function_context.load_function(function, cache) {
+            function_context.codegen_function_body(&function.body);
+            generated.push(function);
+        }
     }
 
-    function_context.builder.finish()
+    let ssa = function_context.builder.finish();
+    if let Some(cache) = cache {
+        for function in generated {
+            cache.store(function, &ssa.functions[&context.function_id(function.id)])?;
+        }
+    }
+    Ok(ssa)
 }
 
 impl<'a> FunctionContext<'a> {
+    /// Replaces the current function with the SSA cached for the given function, if any.
+    /// Returns false if the SSA of the function is not cached, and must be generated instead.
+    fn load_function(&mut self, function: &ast::Function, cache: Option<&FunctionCache>) -> bool {
+        match cache.and_then(|cache| cache.load(function)) {
+            Some(cached) => {
+                self.shared_context.queue_called_functions(&cached);
+                self.builder.set_current_function(cached);
+                true
+            }
+            None => false,
+        }
+    }
+
     /// Codegen a function's body and set its return value to that of its last parameter.
     /// For functions returning nothing, this will be an empty list.
     fn codegen_function_body(&mut self, body: &Expression) {
@@ -68,6 +104,7 @@ impl<'a> FunctionContext<'a> {
             Expression::Index(index) => self.codegen_index(index),
             Expression::Cast(cast) => self.codegen_cast(cast),
             Expression::For(for_expr) => self.codegen_for(for_expr),
+            Expression::While(while_expr) => self.codegen_while(while_expr),
             Expression::If(if_expr) => self.codegen_if(if_expr),
             Expression::Tuple(tuple) => self.codegen_tuple(tuple),
             Expression::ExtractTupleField(tuple, index) => {
@@ -75,8 +112,8 @@ impl<'a> FunctionContext<'a> {
             }
             Expression::Call(call) => self.codegen_call(call),
             Expression::Let(let_expr) => self.codegen_let(let_expr),
-            Expression::Constrain(constrain, location) => {
-                self.codegen_constrain(constrain, *location)
+            Expression::Constrain(constrain, location, message) => {
+                self.codegen_constrain(constrain, *location, message.clone())
             }
             Expression::Assign(assign) => self.codegen_assign(assign),
             Expression::Semi(semi) => self.codegen_semi(semi),
@@ -282,6 +319,45 @@ impl<'a> FunctionContext<'a> {
         Self::unit_value()
     }
 
+    /// Codegens a while loop, creating three new blocks in the process.
+    /// The return value of a while loop is always a unit literal.
+    ///
+    /// For example, the loop `while cond { body }` is codegen'd as:
+    ///
+    ///   br loop_entry()
+    /// loop_entry():
+    ///   v0 = ... codegen cond ...
+    ///   brif v0, then: loop_body, else: loop_end
+    /// loop_body():
+    ///   v1 = ... codegen body ...
+    ///   br loop_entry()
+    /// loop_end():
+    ///   ... This is the current insert point after codegen_while finishes ...
+    ///
+    /// Unlike a for loop, the loop header has no induction variable, so the loop cannot be
+    /// unrolled and is only supported in brillig functions.
+    fn codegen_while(&mut self, while_expr: &ast::While) -> Values {
+        let loop_entry = self.builder.insert_block();
+        let loop_body = self.builder.insert_block();
+        let loop_end = self.builder.insert_block();
+
+        self.builder.terminate_with_jmp(loop_entry, vec![]);
+
+        // Compile the loop entry block, which checks the condition on each iteration
+        self.builder.switch_to_block(loop_entry);
+        let jump_condition = self.codegen_non_tuple_expression(&while_expr.condition);
+        self.builder.terminate_with_jmpif(jump_condition, loop_body, loop_end);
+
+        // Compile the loop body
+        self.builder.switch_to_block(loop_body);
+        self.codegen_expression(&while_expr.block);
+        self.builder.terminate_with_jmp(loop_entry, vec![]);
+
+        // Finish by switching back to the end of the loop
+        self.builder.switch_to_block(loop_end);
+        Self::unit_value()
+    }
+
     /// Codegens an if expression, handling the case of what to do if there is no 'else'.
     ///
     /// For example, the expression `if cond { a } else { b }` is codegen'd as:
@@ -386,9 +462,14 @@ impl<'a> FunctionContext<'a> {
         Self::unit_value()
     }
 
-    fn codegen_constrain(&mut self, expr: &Expression, location: Location) -> Values {
+    fn codegen_constrain(
+        &mut self,
+        expr: &Expression,
+        location: Location,
+        message: Option<String>,
+    ) -> Values {
         let boolean = self.codegen_non_tuple_expression(expr);
-        self.builder.set_location(location).insert_constrain(boolean);
+        self.builder.set_location(location).insert_constrain(boolean, message);
         Self::unit_value()
     }
 
@@ -1,6 +1,7 @@
 use std::{collections::BTreeMap, fmt::Display};
 
 use iter_extended::btree_map;
+use serde::{Deserialize, Serialize};
 
 use crate::ssa_refactor::ir::{
     function::{Function, FunctionId},
@@ -8,7 +9,8 @@ use crate::ssa_refactor::ir::{
 };
 
 /// Contains the entire SSA representation of the program.
-pub(crate) struct Ssa {
+#[derive(Serialize, Deserialize)]
+pub struct Ssa {
     pub(crate) functions: BTreeMap<FunctionId, Function>,
     pub(crate) main_id: FunctionId,
     pub(crate) next_id: AtomicCounter<Function>,
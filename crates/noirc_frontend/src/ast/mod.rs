@@ -139,7 +139,7 @@ impl UnresolvedType {
     }
 }
 
-#[derive(Debug, PartialEq, Eq, Copy, Clone, Hash)]
+#[derive(Debug, PartialEq, Eq, Copy, Clone, Hash, serde::Serialize)]
 pub enum Signedness {
     Unsigned,
     Signed,
@@ -71,6 +71,7 @@ impl Statement {
                     // Semicolons are optional for these expressions
                     (ExpressionKind::Block(_), semi, _)
                     | (ExpressionKind::For(_), semi, _)
+                    | (ExpressionKind::While(_), semi, _)
                     | (ExpressionKind::If(_), semi, _) => {
                         if semi.is_some() {
                             Statement::Semi(expr)
@@ -406,8 +407,15 @@ pub enum LValue {
     Dereference(Box<LValue>),
 }
 
+/// An assertion of a boolean expression, along with the message to report if it fails
 #[derive(Debug, PartialEq, Eq, Clone)]
-pub struct ConstrainStatement(pub Expression);
+pub struct ConstrainStatement(pub Expression, pub Option<String>);
+
+impl Recoverable for ConstrainStatement {
+    fn error(span: Span) -> Self {
+        ConstrainStatement(Expression::error(span), None)
+    }
+}
 
 #[derive(Debug, PartialEq, Eq, Clone)]
 pub enum Pattern {
@@ -486,7 +494,11 @@ impl Display for LetStatement {
 
 impl Display for ConstrainStatement {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "constrain {}", self.0)
+        write!(f, "constrain {}", self.0)?;
+        if let Some(message) = &self.1 {
+            write!(f, ", {message:?}")?;
+        }
+        Ok(())
     }
 }
 
@@ -15,7 +15,7 @@ use crate::hir_def::expr::{
     HirArrayLiteral, HirBinaryOp, HirBlockExpression, HirCallExpression, HirCastExpression,
     HirConstructorExpression, HirExpression, HirForExpression, HirIdent, HirIfExpression,
     HirIndexExpression, HirInfixExpression, HirLambda, HirLiteral, HirMemberAccess,
-    HirMethodCallExpression, HirPrefixExpression,
+    HirMethodCallExpression, HirPrefixExpression, HirWhileExpression,
 };
 use crate::token::Attribute;
 use std::collections::{HashMap, HashSet};
@@ -840,7 +840,7 @@ impl<'a> Resolver<'a> {
             }
             Statement::Constrain(constrain_stmt) => {
                 let expr_id = self.resolve_expression(constrain_stmt.0);
-                HirStatement::Constrain(HirConstrainStatement(expr_id, self.file))
+                HirStatement::Constrain(HirConstrainStatement(expr_id, self.file, constrain_stmt.1))
             }
             Statement::Expression(expr) => HirStatement::Expression(self.resolve_expression(expr)),
             Statement::Semi(expr) => HirStatement::Semi(self.resolve_expression(expr)),
@@ -983,6 +983,10 @@ impl<'a> Resolver<'a> {
                     identifier,
                 })
             }
+            ExpressionKind::While(while_expr) => HirExpression::While(HirWhileExpression {
+                condition: self.resolve_expression(while_expr.condition),
+                block: self.resolve_expression(while_expr.block),
+            }),
             ExpressionKind::If(if_expr) => HirExpression::If(HirIfExpression {
                 condition: self.resolve_expression(if_expr.condition),
                 consequence: self.resolve_expression(if_expr.consequence),
@@ -212,6 +212,22 @@ impl<'interner> TypeChecker<'interner> {
                 self.check_expression(&for_expr.block);
                 Type::Unit
             }
+            HirExpression::While(while_expr) => {
+                let condition_type = self.check_expression(&while_expr.condition);
+                let expr_span = self.interner.expr_span(&while_expr.condition);
+
+                let bool_type = Type::Bool(CompTime::new(self.interner));
+                self.unify(&condition_type, &bool_type, expr_span, || {
+                    TypeCheckError::TypeMismatch {
+                        expected_typ: Type::Bool(CompTime::No(None)).to_string(),
+                        expr_typ: condition_type.to_string(),
+                        expr_span,
+                    }
+                });
+
+                self.check_expression(&while_expr.block);
+                Type::Unit
+            }
             HirExpression::Block(block_expr) => {
                 let mut block_type = Type::Unit;
 
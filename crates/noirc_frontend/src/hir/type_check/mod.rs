@@ -315,6 +315,21 @@ mod test {
         type_check_src_code(src, vec![String::from("main"), String::from("foo")]);
     }
 
+    #[test]
+    fn basic_while_expr() {
+        let src = r#"
+            fn main(x : u32) {
+                let mut i = 0;
+                while i < x {
+                    i = i + 1;
+                }
+            }
+
+        "#;
+
+        type_check_src_code(src, vec![String::from("main"), String::from("foo")]);
+    }
+
     // This is the same Stub that is in the resolver, maybe we can pull this out into a test module and re-use?
     struct TestPathResolver(HashMap<String, ModuleDefId>);
 
@@ -15,7 +15,7 @@ use crate::{BinaryOpKind, Signedness};
 ///   e.g. `let (a, b) = (1, 2)` have been split up: `let tmp = (1, 2); let a = tmp.0; let b = tmp.1;`.
 ///   This also affects function parameters: `fn foo((a, b): (i32, i32)` => `fn foo(a: i32, b: i32)`.
 /// - All structs are replaced with tuples
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, serde::Serialize)]
 pub enum Expression {
     Ident(Ident),
     Literal(Literal),
@@ -25,20 +25,22 @@ pub enum Expression {
     Index(Index),
     Cast(Cast),
     For(For),
+    While(While),
     If(If),
     Tuple(Vec<Expression>),
     ExtractTupleField(Box<Expression>, usize),
     Call(Call),
 
     Let(Let),
-    Constrain(Box<Expression>, Location),
+    /// A boolean expression asserted to be true, with the message to report if it is not
+    Constrain(Box<Expression>, Location, Option<String>),
     Assign(Assign),
     Semi(Box<Expression>),
 }
 
 /// A definition is either a local (variable), function, or is a built-in
 /// function that will be generated or referenced by the compiler later.
-#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, serde::Serialize)]
 pub enum Definition {
     Local(LocalId),
     Function(FuncId),
@@ -50,14 +52,14 @@ pub enum Definition {
 
 /// ID of a local definition, e.g. from a let binding or
 /// function parameter that should be compiled before it is referenced.
-#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, serde::Serialize)]
 pub struct LocalId(pub u32);
 
 /// A function ID corresponds directly to an index of `Program::functions`
-#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, serde::Serialize)]
 pub struct FuncId(pub u32);
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, serde::Serialize)]
 pub struct Ident {
     pub location: Option<Location>,
     pub definition: Definition,
@@ -66,7 +68,7 @@ pub struct Ident {
     pub typ: Type,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, serde::Serialize)]
 pub struct For {
     pub index_variable: LocalId,
     pub index_name: String,
@@ -77,7 +79,13 @@ pub struct For {
     pub block: Box<Expression>,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct While {
+    pub condition: Box<Expression>,
+    pub block: Box<Expression>,
+}
+
+#[derive(Debug, Clone, serde::Serialize)]
 pub enum Literal {
     Array(ArrayLiteral),
     Integer(FieldElement, Type),
@@ -85,7 +93,7 @@ pub enum Literal {
     Str(String),
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, serde::Serialize)]
 pub struct Unary {
     pub operator: crate::UnaryOp,
     pub rhs: Box<Expression>,
@@ -94,7 +102,7 @@ pub struct Unary {
 
 pub type BinaryOp = BinaryOpKind;
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, serde::Serialize)]
 pub struct Binary {
     pub lhs: Box<Expression>,
     pub operator: BinaryOp,
@@ -102,7 +110,7 @@ pub struct Binary {
     pub location: Location,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, serde::Serialize)]
 pub struct If {
     pub condition: Box<Expression>,
     pub consequence: Box<Expression>,
@@ -110,19 +118,19 @@ pub struct If {
     pub typ: Type,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, serde::Serialize)]
 pub struct Cast {
     pub lhs: Box<Expression>,
     pub r#type: Type,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, serde::Serialize)]
 pub struct ArrayLiteral {
     pub contents: Vec<Expression>,
     pub typ: Type,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, serde::Serialize)]
 pub struct Call {
     pub func: Box<Expression>,
     pub arguments: Vec<Expression>,
@@ -130,7 +138,7 @@ pub struct Call {
     pub location: Location,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, serde::Serialize)]
 pub struct Index {
     pub collection: Box<Expression>,
     pub index: Box<Expression>,
@@ -150,7 +158,7 @@ pub struct Index {
 /// let field1 = tmp.0; // the struct has been translated to a tuple as well
 /// let field2 = tmp.1;
 /// ```
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, serde::Serialize)]
 pub struct Let {
     pub id: LocalId,
     pub mutable: bool,
@@ -158,7 +166,7 @@ pub struct Let {
     pub expression: Box<Expression>,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, serde::Serialize)]
 pub struct Assign {
     pub lvalue: LValue,
     pub expression: Box<Expression>,
@@ -172,7 +180,7 @@ pub struct BinaryStatement {
 }
 
 /// Represents an Ast form that can be assigned to
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, serde::Serialize)]
 pub enum LValue {
     Ident(Ident),
     Index { array: Box<LValue>, index: Box<Expression>, element_type: Type, location: Location },
@@ -182,7 +190,7 @@ pub enum LValue {
 
 pub type Parameters = Vec<(LocalId, /*mutable:*/ bool, /*name:*/ String, Type)>;
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, serde::Serialize)]
 pub struct Function {
     pub id: FuncId,
     pub name: String,
@@ -200,7 +208,7 @@ pub struct Function {
 /// - Several other variants removed (such as Type::Constant)
 /// - No CompTime
 /// - All structs replaced with tuples
-#[derive(Debug, PartialEq, Eq, Clone)]
+#[derive(Debug, PartialEq, Eq, Clone, serde::Serialize)]
 pub enum Type {
     Field,
     Array(/*len:*/ u64, Box<Type>),     // Array(4, Field) = [Field; 4]
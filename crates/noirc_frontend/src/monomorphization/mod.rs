@@ -322,6 +322,11 @@ impl<'interner> Monomorphizer<'interner> {
                 })
             }
 
+            HirExpression::While(while_expr) => ast::Expression::While(ast::While {
+                condition: Box::new(self.expr(while_expr.condition)),
+                block: Box::new(self.expr(while_expr.block)),
+            }),
+
             HirExpression::If(if_expr) => {
                 let cond = self.expr(if_expr.condition);
                 let then = self.expr(if_expr.consequence);
@@ -391,7 +396,7 @@ impl<'interner> Monomorphizer<'interner> {
             HirStatement::Constrain(constrain) => {
                 let expr = self.expr(constrain.0);
                 let location = self.interner.expr_location(&constrain.0);
-                ast::Expression::Constrain(Box::new(expr), location)
+                ast::Expression::Constrain(Box::new(expr), location, constrain.2)
             }
             HirStatement::Assign(assign) => self.assign(assign),
             HirStatement::Expression(expr) => self.expr(expr),
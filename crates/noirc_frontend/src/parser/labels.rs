@@ -16,6 +16,7 @@ pub enum ParsingRuleLabel {
     Parameter,
     Pattern,
     Statement,
+    String,
     Term,
     TypeExpression,
     TokenKind(TokenKind),
@@ -34,6 +35,7 @@ impl fmt::Display for ParsingRuleLabel {
             ParsingRuleLabel::Parameter => write!(f, "parameter"),
             ParsingRuleLabel::Pattern => write!(f, "pattern"),
             ParsingRuleLabel::Statement => write!(f, "statement"),
+            ParsingRuleLabel::String => write!(f, "string"),
             ParsingRuleLabel::Term => write!(f, "term"),
             ParsingRuleLabel::TypeExpression => write!(f, "type expression"),
             ParsingRuleLabel::TokenKind(token_kind) => write!(f, "{:?}", token_kind),
@@ -37,7 +37,7 @@ use crate::{
     BinaryOp, BinaryOpKind, BlockExpression, CompTime, ConstrainStatement, FunctionDefinition,
     Ident, IfExpression, InfixExpression, LValue, Lambda, Literal, NoirFunction, NoirStruct,
     NoirTrait, Path, PathKind, Pattern, Recoverable, TraitConstraint, TraitImpl, TraitImplItem,
-    TraitItem, TypeImpl, UnaryOp, UnresolvedTypeExpression, UseTree, UseTreeKind,
+    TraitItem, TypeImpl, UnaryOp, UnresolvedTypeExpression, UseTree, UseTreeKind, WhileExpression,
 };
 
 use chumsky::prelude::*;
@@ -653,7 +653,7 @@ where
         keyword(Keyword::Constrain).labelled(ParsingRuleLabel::Statement),
         expr_parser,
     )
-    .map(|expr| Statement::Constrain(ConstrainStatement(expr)))
+    .map(|expr| Statement::Constrain(ConstrainStatement(expr, None)))
     .validate(|expr, span, emit| {
         emit(ParserError::with_reason(ParserErrorReason::ConstrainDeprecated, span));
         expr
@@ -664,9 +664,16 @@ fn assertion<'a, P>(expr_parser: P) -> impl NoirParser<Statement> + 'a
 where
     P: ExprParser + 'a,
 {
-    ignore_then_commit(keyword(Keyword::Assert), parenthesized(expr_parser))
+    let message = just(Token::Comma).ignore_then(filter_map(|span, token: Token| match token {
+        Token::Str(message) => Ok(message),
+        unexpected => Err(ParserError::expected_label(ParsingRuleLabel::String, unexpected, span)),
+    }));
+    let arguments =
+        expr_parser.then(message.or_not()).map(|(expr, message)| ConstrainStatement(expr, message));
+
+    ignore_then_commit(keyword(Keyword::Assert), parenthesized(arguments))
         .labelled(ParsingRuleLabel::Statement)
-        .map(|expr| Statement::Constrain(ConstrainStatement(expr)))
+        .map(Statement::Constrain)
 }
 
 fn declaration<'a, P>(expr_parser: P) -> impl NoirParser<Statement> + 'a
@@ -1186,6 +1193,19 @@ where
         .map_with_span(|((identifier, range), block), span| range.into_for(identifier, block, span))
 }
 
+fn while_expr<'a, P, P2>(
+    expr_parser: P,
+    expr_no_constructors: P2,
+) -> impl NoirParser<ExpressionKind> + 'a
+where
+    P: ExprParser + 'a,
+    P2: ExprParser + 'a,
+{
+    keyword(Keyword::While).ignore_then(expr_no_constructors).then(block_expr(expr_parser)).map(
+        |(condition, block)| ExpressionKind::While(Box::new(WhileExpression { condition, block })),
+    )
+}
+
 /// The 'range' of a for loop. Either an actual range `start .. end` or an array expression.
 fn for_range<P>(expr_no_constructors: P) -> impl NoirParser<ForRange>
 where
@@ -1284,7 +1304,8 @@ where
 {
     choice((
         if_expr(expr_parser.clone(), expr_no_constructors.clone()),
-        for_expr(expr_parser.clone(), expr_no_constructors),
+        for_expr(expr_parser.clone(), expr_no_constructors.clone()),
+        while_expr(expr_parser.clone(), expr_no_constructors),
         array_expr(expr_parser.clone()),
         if allow_constructors {
             constructor(expr_parser.clone()).boxed()
@@ -1639,6 +1660,8 @@ mod test {
     #[test]
     fn parse_assert() {
         parse_with(assertion(expression()), "assert(x == y)").unwrap();
+        parse_with(assertion(expression()), "assert(x == y, \"x and y differ\")").unwrap();
+        parse_with(assertion(expression()), "assert(x == y, z)").unwrap_err();
 
         // Currently we disallow constrain statements where the outer infix operator
         // produces a value. This would require an implicit `==` which
@@ -1710,6 +1733,22 @@ mod test {
         );
     }
 
+    #[test]
+    fn parse_while_loop() {
+        parse_all(
+            while_expr(expression(), expression_no_constructors()),
+            vec!["while i < n {}", "while x { foo; bar }", "while f(x) == y { x = x + 1; }"],
+        );
+
+        parse_all_failing(
+            while_expr(expression(), expression_no_constructors()),
+            vec![
+                "while {}",    // A condition is required
+                "while x < y", // A block is required
+            ],
+        );
+    }
+
     #[test]
     fn parse_function() {
         parse_all(